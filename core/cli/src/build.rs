@@ -0,0 +1,70 @@
+//! `argon build`: exports the targets declared in `Argon.toml`.
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use compiler::{config::Target, gds::LayoutFormat};
+use itertools::Itertools;
+
+use crate::{SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct BuildArgs {
+    /// Names of the targets to build. Defaults to every target.
+    targets: Vec<String>,
+    /// Directory to write GDS files to for targets without an `out` path.
+    #[arg(short, long, default_value = "gds")]
+    out: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    /// Number of cells to compile in parallel. Defaults to the number of CPUs.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+}
+
+/// Name of `target`, defaulting to the file stem of its invocation.
+pub fn target_name(target: &Target) -> String {
+    target
+        .name
+        .clone()
+        .unwrap_or_else(|| crate::file_stem(&target.cell))
+}
+
+pub fn run(args: BuildArgs) -> Result<()> {
+    let workspace = Workspace::load(&args.workspace, &args.solver)?;
+    let targets = &workspace.config.targets;
+    if targets.is_empty() {
+        bail!("no targets to build; declare them with `[[target]]` in Argon.toml");
+    }
+    if let Some(unknown) = args
+        .targets
+        .iter()
+        .find(|name| !targets.iter().any(|target| target_name(target) == **name))
+    {
+        bail!(
+            "no target named `{unknown}`; available targets: {}",
+            targets.iter().map(target_name).join(", ")
+        );
+    }
+
+    let cells = targets
+        .iter()
+        .filter(|target| args.targets.is_empty() || args.targets.contains(&target_name(target)))
+        .map(|target| {
+            let path = match &target.out {
+                Some(out) => workspace.root_dir.join(out),
+                None => args.out.join(format!("{}.gds", target_name(target))),
+            };
+            (target.cell.clone(), path)
+        })
+        .collect_vec();
+    crate::gds::export(
+        &workspace,
+        &cells,
+        &args.out,
+        LayoutFormat::Gds,
+        Default::default(),
+        args.jobs,
+    )
+}
@@ -0,0 +1,60 @@
+//! `argon check`: reports static errors and source lints and, for the given
+//! cells, compile errors.
+use anyhow::{Result, bail};
+use compiler::{
+    compile::{self, CompileOutput, ExecErrorCompileOutput},
+    diagnostics::Diagnostic,
+    lint::Linter,
+};
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs, emit};
+
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    /// Cell invocations to also compile, e.g. `inv(1200., 2000., 4)`.
+    cells: Vec<String>,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+pub fn run(args: CheckArgs) -> Result<()> {
+    let Workspace {
+        lib_path,
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let mut linter = Linter::new(&config, &ast);
+    linter.check_source();
+    for lint in linter.finish() {
+        emit(&Diagnostic::from(&lint));
+    }
+    let mut failures = 0;
+    for cell in &args.cells {
+        let cell = &args.params.apply(&ast, cell)?;
+        match compile::dynamic_compile_invocation(&static_ast, cell, &lyp, &config.solver)? {
+            CompileOutput::Valid(_) => eprintln!("{cell}: ok"),
+            CompileOutput::ExecErrors(ExecErrorCompileOutput { errors, .. }) => {
+                failures += 1;
+                for e in &errors {
+                    emit(&Diagnostic::from(e).with_cell(cell));
+                }
+            }
+            CompileOutput::StaticErrors(_) | CompileOutput::FatalParseErrors => {
+                failures += 1;
+                emit(&Diagnostic::error("static errors encountered").with_cell(cell));
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{failures} of {} cells failed to compile", args.cells.len());
+    }
+    eprintln!("{}: no errors", lib_path.display());
+    Ok(())
+}
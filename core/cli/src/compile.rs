@@ -0,0 +1,72 @@
+//! `argon compile`: compiles a cell and writes its output as JSON or bincode.
+use std::{io::Write, path::PathBuf, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use compiler::compile;
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct CompileArgs {
+    /// Cell invocation to compile, e.g. `inv(1200., 2000., 4)`.
+    cell: String,
+    /// File to write the output to. Defaults to standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Serialization of the output, either `json` or `bincode`. JSON is
+    /// readable for debugging, while bincode is much smaller for large cells.
+    #[arg(long, default_value = "json")]
+    format: OutputFormat,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    Bincode,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "bincode" => Ok(Self::Bincode),
+            _ => Err(format!(
+                "unknown output format `{s}`; expected `json` or `bincode`"
+            )),
+        }
+    }
+}
+
+pub fn run(args: CompileArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let cell = args.params.apply(&ast, &args.cell)?;
+    let output = compile::dynamic_compile_invocation(&static_ast, &cell, &lyp, &config.solver)?;
+    let bytes = match args.format {
+        OutputFormat::Json => {
+            let mut json = output.to_json()?.into_bytes();
+            json.push(b'\n');
+            json
+        }
+        OutputFormat::Bincode => output.to_bincode()?,
+    };
+    match args.output {
+        Some(path) => std::fs::write(&path, bytes)
+            .map_err(|e| anyhow!("failed to write output to {path:?}: {e}"))?,
+        None => std::io::stdout().write_all(&bytes)?,
+    }
+    Ok(())
+}
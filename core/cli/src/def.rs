@@ -0,0 +1,44 @@
+//! `argon def`: exports the placed instances of cells to DEF.
+use std::path::PathBuf;
+
+use anyhow::Result;
+use compiler::compile;
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct DefArgs {
+    /// Cell invocations to export, e.g. `top()`.
+    #[arg(required = true)]
+    cells: Vec<String>,
+    /// Directory to write DEF files to.
+    #[arg(short, long, default_value = "def")]
+    out: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+pub fn run(args: DefArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let cells = args
+        .cells
+        .iter()
+        .map(|cell| args.params.apply(&ast, cell))
+        .collect::<Result<Vec<_>>>()?;
+    for (cell, path) in crate::output_paths(cells, &args.out, "def")? {
+        let output = compile::dynamic_compile_invocation(&static_ast, &cell, &lyp, &config.solver)?;
+        output.to_def(&path)?;
+        println!("{cell}: wrote {}", path.display());
+    }
+    Ok(())
+}
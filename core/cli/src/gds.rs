@@ -0,0 +1,313 @@
+//! `argon gds`: exports cells to GDS or OASIS.
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ::gds::GdsUnits;
+use anyhow::{Result, anyhow, bail};
+use compiler::{
+    ast::{Decl, WorkspaceAst},
+    compile::{self, CompileOutput, ExecErrorCompileOutput, VarIdTyMetadata},
+    config::Config,
+    dbu::DEFAULT_DBU,
+    diagnostics::Diagnostic,
+    gds::{GdsMap, LayoutFormat, write_layout_library},
+    parse,
+};
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs, emit};
+
+/// File stem of the library containing every exported cell.
+pub const COMBINED_LIBRARY: &str = "library";
+
+#[derive(clap::Args, Debug)]
+pub struct GdsArgs {
+    /// Cell invocations to export, e.g. `inv(1200., 2000., 4)`.
+    cells: Vec<String>,
+    /// Also export the cells listed in `Argon.toml`, or every cell without
+    /// parameters in the root module if none are listed.
+    #[arg(long)]
+    all: bool,
+    /// Directory to write layout files to.
+    #[arg(short, long, default_value = "gds")]
+    out: PathBuf,
+    /// Layout file format, either `gds` or `oasis`.
+    #[arg(long, default_value = "gds")]
+    format: LayoutFormat,
+    #[command(flatten)]
+    options: ExportOptions,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+    /// Number of cells to compile in parallel. Defaults to the number of CPUs.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+}
+
+/// Transformations applied to the geometry of each exported cell.
+#[derive(clap::Args, Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Flatten each exported cell, expanding instances up to DEPTH levels deep,
+    /// or the whole hierarchy if no depth is given.
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1)]
+    flatten: Option<Option<usize>>,
+    /// Merge overlapping and abutting rects on the same layer into polygons.
+    #[arg(long)]
+    merge: bool,
+}
+
+struct Export {
+    cell: String,
+    path: PathBuf,
+    output: Option<CompileOutput>,
+    result: Result<(f64, f64)>,
+}
+
+/// Units of exported layouts, with the database unit from `Argon.toml` and a
+/// user unit of one micron.
+fn gds_units(config: &Config) -> GdsUnits {
+    let dbu = config.dbu.unwrap_or(DEFAULT_DBU);
+    GdsUnits::new(dbu * 1e-3, dbu * 1e-9)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_cell(
+    ast: &WorkspaceAst<VarIdTyMetadata>,
+    cell: &str,
+    lyp: &Path,
+    config: &Config,
+    extern_gds: &IndexMap<String, PathBuf>,
+    map: &GdsMap,
+    format: LayoutFormat,
+    options: ExportOptions,
+    path: PathBuf,
+) -> Export {
+    let mut output = match compile::dynamic_compile_invocation(ast, cell, lyp, &config.solver) {
+        Ok(output) => output,
+        Err(e) => {
+            return Export {
+                cell: cell.to_string(),
+                path,
+                output: None,
+                result: Err(e),
+            };
+        }
+    };
+    output.set_dbu(config.dbu);
+    output.set_extern_gds(extern_gds);
+    if let Some(depth) = options.flatten {
+        output.flatten(depth);
+    }
+    output.set_merge_shapes(options.merge);
+    output.check_density(&config.density);
+    output.extract_nets(&config.connect);
+    let result = match &output {
+        CompileOutput::Valid(data) => output
+            .to_layout(map.clone(), gds_units(config), format, &path)
+            .map(|_| {
+                data.cell_bbox(data.top)
+                    .map(|rect| {
+                        let bbox = rect.bbox();
+                        (bbox.width(), bbox.height())
+                    })
+                    .unwrap_or_default()
+            }),
+        CompileOutput::ExecErrors(ExecErrorCompileOutput { errors, .. }) => Err(anyhow!(
+            "{}",
+            errors.iter().map(|e| format!("{}", e.kind)).join("; ")
+        )),
+        CompileOutput::StaticErrors(_) | CompileOutput::FatalParseErrors => {
+            Err(anyhow!("static errors encountered"))
+        }
+    };
+    Export {
+        cell: cell.to_string(),
+        path,
+        output: result.is_ok().then_some(output),
+        result,
+    }
+}
+
+/// Cells exported by `--all`.
+pub(crate) fn all_cells(ast: &parse::WorkspaceParseAst, config: &Config) -> Vec<String> {
+    if !config.cells.is_empty() || !config.targets.is_empty() {
+        return config
+            .targets
+            .iter()
+            .map(|target| target.cell.clone())
+            .chain(config.cells.iter().cloned())
+            .collect();
+    }
+    ast.get(&vec![])
+        .into_iter()
+        .flat_map(|root| &root.ast.decls)
+        .filter_map(|decl| match decl {
+            Decl::Cell(cell) if cell.args.is_empty() && cell.bbox.is_none() => {
+                Some(format!("{}()", cell.name.name))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn print_summary(exports: &[Export]) {
+    let width = exports
+        .iter()
+        .map(|export| export.cell.len())
+        .max()
+        .unwrap_or_default()
+        .max("cell".len());
+    println!(
+        "{:<width$}  {:>12}  {:>12}  {:>10}  status",
+        "cell", "width", "height", "bytes"
+    );
+    for export in exports {
+        match &export.result {
+            Ok((w, h)) => {
+                let bytes = std::fs::metadata(&export.path)
+                    .map(|m| m.len().to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{:<width$}  {w:>12.3}  {h:>12.3}  {bytes:>10}  ok",
+                    export.cell
+                );
+            }
+            Err(e) => println!(
+                "{:<width$}  {:>12}  {:>12}  {:>10}  {e}",
+                export.cell, "-", "-", "-"
+            ),
+        }
+    }
+}
+
+pub fn run(args: GdsArgs) -> Result<()> {
+    let workspace = Workspace::load(&args.workspace, &args.solver)?;
+
+    let mut cells = args
+        .cells
+        .iter()
+        .map(|cell| args.params.apply(&workspace.ast, cell))
+        .collect::<Result<Vec<_>>>()?;
+    if args.all {
+        cells.extend(all_cells(&workspace.ast, &workspace.config));
+    }
+    if cells.is_empty() {
+        bail!("no cells to export; pass cell invocations or `--all`");
+    }
+    let cells = crate::output_paths(cells, &args.out, args.format.extension())?;
+    export(
+        &workspace,
+        &cells,
+        &args.out,
+        args.format,
+        args.options,
+        args.jobs,
+    )
+}
+
+/// Exports each cell invocation in `cells` to its paired path in `format`,
+/// along with a combined library in `out`, and prints a summary.
+///
+/// Cells are transformed as described by `options` before they are written.
+/// Compiles up to `jobs` cells in parallel, defaulting to the number of CPUs.
+pub(crate) fn export(
+    workspace: &Workspace,
+    cells: &[(String, PathBuf)],
+    out: &Path,
+    format: LayoutFormat,
+    options: ExportOptions,
+    jobs: Option<usize>,
+) -> Result<()> {
+    let Workspace {
+        root_dir,
+        config,
+        lyp,
+        static_ast,
+        ..
+    } = workspace;
+    crate::check_distinct_paths(cells)?;
+    let map = GdsMap::from_lyp(lyp)?;
+    let extern_gds = config.extern_gds_paths(root_dir);
+    let drc = config.drc_rules(root_dir)?;
+    let jobs = jobs
+        .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .clamp(1, cells.len());
+    let next = AtomicUsize::new(0);
+    let mut exports = std::thread::scope(|s| {
+        let workers = (0..jobs)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut exports = Vec::new();
+                    while let Some((cell, path)) = cells.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        exports.push(export_cell(
+                            static_ast,
+                            cell,
+                            lyp,
+                            config,
+                            &extern_gds,
+                            &map,
+                            format,
+                            options,
+                            path.clone(),
+                        ));
+                    }
+                    exports
+                })
+            })
+            .collect_vec();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("export worker panicked"))
+            .sorted_by_key(|export| cells.iter().position(|(cell, _)| *cell == export.cell))
+            .collect_vec()
+    });
+
+    write_layout_library(
+        "TOP",
+        exports.iter().filter_map(|export| export.output.as_ref()),
+        map,
+        gds_units(config),
+        format,
+        out.join(format!("{COMBINED_LIBRARY}.{}", format.extension())),
+    )?;
+
+    print_summary(&exports);
+    for export in &mut exports {
+        if let Some(output) = &mut export.output {
+            output.check_drc(&drc);
+        }
+        if let Some(CompileOutput::Valid(data)) = &export.output {
+            let warn = |message: String| {
+                emit(&Diagnostic::warning(message).with_cell(&export.cell));
+            };
+            for violation in &data.density_violations {
+                warn(violation.to_string());
+            }
+            for violation in &data.drc_violations {
+                warn(violation.to_string());
+            }
+            for warning in data.nets.iter().flat_map(|nets| nets.warnings()) {
+                warn(warning.to_string());
+            }
+            // Rotated shapes must land on the database grid of the exported layout.
+            for warning in data.off_grid_rotations(data.dbu) {
+                warn(warning.to_string());
+            }
+        }
+    }
+    let failures = exports
+        .iter()
+        .filter(|export| export.result.is_err())
+        .count();
+    if failures > 0 {
+        bail!("failed to export {failures} of {} cells", exports.len());
+    }
+    Ok(())
+}
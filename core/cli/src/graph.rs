@@ -0,0 +1,57 @@
+//! `argon graph`: prints the constraint graph of a scope in Graphviz DOT.
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use compiler::compile::{self, CompileOutput, ExecErrorCompileOutput};
+
+use crate::{SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct GraphArgs {
+    /// Cell invocation to graph, e.g. `top()`.
+    cell: String,
+    /// Name of the scope to graph, as shown in the GUI's scope hierarchy.
+    /// Defaults to the root scope of the cell.
+    #[arg(long)]
+    scope: Option<String>,
+    /// File to write the graph to. Defaults to standard output.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+pub fn run(args: GraphArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    // Cells with inconsistent constraints are worth graphing, so execution
+    // errors only abort if nothing was solved.
+    let data =
+        match compile::dynamic_compile_invocation(&static_ast, &args.cell, &lyp, &config.solver)? {
+            CompileOutput::Valid(data)
+            | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+                output: Some(data), ..
+            }) => data,
+            _ => bail!("failed to compile `{}`", args.cell),
+        };
+    let scope = match &args.scope {
+        Some(name) => data.scope_named(name)?,
+        None => data.cells[&data.top].root,
+    };
+    let dot = data.constraint_graph(scope).to_dot(&ast);
+    match &args.out {
+        Some(out) => {
+            std::fs::write(out, dot)?;
+            println!("{}: wrote {}", args.cell, out.display());
+        }
+        None => print!("{dot}"),
+    }
+    Ok(())
+}
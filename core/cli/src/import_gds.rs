@@ -0,0 +1,31 @@
+//! `argon import-gds`: reverse-compiles a GDS file into Argon source.
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use compiler::gds::{GdsMap, import_gds_source};
+
+use crate::WorkspaceArgs;
+
+#[derive(clap::Args, Debug)]
+pub struct ImportGdsArgs {
+    /// GDS file to import.
+    file: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    /// File to write the source to. Defaults to standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: ImportGdsArgs) -> Result<()> {
+    let root_dir = args.workspace.root_dir()?;
+    let config = args.workspace.config(&root_dir)?;
+    let map = GdsMap::from_lyp(config.lyp_path(&root_dir))?;
+    let src = import_gds_source(&args.file, &map)?;
+    match args.output {
+        Some(path) => std::fs::write(&path, src)
+            .map_err(|e| anyhow!("failed to write source to {path:?}: {e}"))?,
+        None => print!("{src}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! `argon journal`: inspects and replays the journal of GUI-originated edits.
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    path::PathBuf,
+};
+
+use anyhow::{Result, anyhow, bail};
+use lang_server::journal::{JournalEntry, journal_path, read_journal};
+
+#[derive(clap::Args, Debug)]
+pub struct JournalArgs {
+    #[command(subcommand)]
+    command: JournalCommand,
+    /// Journal file. Defaults to `journal.jsonl` in the Argon home directory.
+    #[arg(long)]
+    journal: Option<PathBuf>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum JournalCommand {
+    /// Lists the journaled edits, oldest first.
+    List,
+    /// Reverts the most recent journaled edits, newest first.
+    Undo {
+        /// Number of edits to revert.
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+    /// Re-applies the journaled edits starting at the given index.
+    Replay {
+        /// Index of the first edit to re-apply, as printed by `argon journal list`.
+        from: usize,
+    },
+}
+
+/// Applies `entries` in order, writing the affected files only if every entry
+/// applies cleanly.
+fn apply<'a>(entries: impl IntoIterator<Item = (usize, &'a JournalEntry)>) -> Result<()> {
+    let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+    for (i, entry) in entries {
+        let text = match files.entry(entry.path.clone()) {
+            Entry::Occupied(text) => text.into_mut(),
+            Entry::Vacant(slot) => {
+                let text = std::fs::read_to_string(&entry.path)
+                    .map_err(|e| anyhow!("failed to read {:?}: {e}", entry.path))?;
+                slot.insert(text)
+            }
+        };
+        let Some(new_text) = entry.apply(text) else {
+            bail!(
+                "edit {i} (`{}`) does not match the current contents of {:?}",
+                entry.action,
+                entry.path
+            );
+        };
+        *text = new_text;
+    }
+    for (path, text) in files {
+        std::fs::write(&path, text)?;
+        println!("updated {}", path.display());
+    }
+    Ok(())
+}
+
+pub fn run(args: JournalArgs) -> Result<()> {
+    let path = args
+        .journal
+        .or_else(journal_path)
+        .ok_or_else(|| anyhow!("unable to determine Argon home directory"))?;
+    let entries = read_journal(&path).map_err(|e| anyhow!("failed to read {path:?}: {e}"))?;
+    match args.command {
+        JournalCommand::List => {
+            for (i, entry) in entries.iter().enumerate() {
+                println!(
+                    "{i}\t{}\t{}\t{}\t{} edit(s)",
+                    entry.timestamp,
+                    entry.action,
+                    entry.path.display(),
+                    entry.edits.len()
+                );
+            }
+        }
+        JournalCommand::Undo { count } => {
+            let inverses = entries
+                .iter()
+                .enumerate()
+                .rev()
+                .take(count)
+                .map(|(i, entry)| (i, entry.inverse()))
+                .collect::<Vec<_>>();
+            apply(inverses.iter().map(|(i, entry)| (*i, entry)))?;
+        }
+        JournalCommand::Replay { from } => {
+            if from >= entries.len() {
+                bail!("journal has only {} edit(s)", entries.len());
+            }
+            apply(entries.iter().enumerate().skip(from))?;
+        }
+    }
+    Ok(())
+}
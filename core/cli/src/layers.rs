@@ -0,0 +1,33 @@
+//! `argon layers`: reports and renames the layers referenced in a workspace.
+use anyhow::Result;
+use compiler::layer::LayerUsage;
+
+use crate::{SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct LayersArgs {
+    /// Renames a layer in Argon source, `Argon.toml`, and the layer properties file.
+    #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+    rename: Option<Vec<String>>,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+}
+
+pub fn run(args: LayersArgs) -> Result<()> {
+    let Workspace {
+        root_dir,
+        config,
+        ast,
+        ..
+    } = Workspace::load(&args.workspace, &SolverArgs::default())?;
+    let usage = LayerUsage::from_workspace(&root_dir, &config, &ast)?;
+    match args.rename.as_deref() {
+        Some([from, to]) => {
+            for path in usage.rename(from, to)? {
+                println!("updated {}", path.display());
+            }
+        }
+        _ => print!("{usage}"),
+    }
+    Ok(())
+}
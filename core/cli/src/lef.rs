@@ -0,0 +1,44 @@
+//! `argon lef`: exports abstracts of cells to LEF.
+use std::path::PathBuf;
+
+use anyhow::Result;
+use compiler::compile;
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct LefArgs {
+    /// Cell invocations to export, e.g. `top()`.
+    #[arg(required = true)]
+    cells: Vec<String>,
+    /// Directory to write LEF files to.
+    #[arg(short, long, default_value = "lef")]
+    out: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+pub fn run(args: LefArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let cells = args
+        .cells
+        .iter()
+        .map(|cell| args.params.apply(&ast, cell))
+        .collect::<Result<Vec<_>>>()?;
+    for (cell, path) in crate::output_paths(cells, &args.out, "lef")? {
+        let output = compile::dynamic_compile_invocation(&static_ast, &cell, &lyp, &config.solver)?;
+        output.to_lef(&path)?;
+        println!("{cell}: wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,393 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::{Context, anyhow, bail};
+use clap::{Parser, Subcommand};
+use compiler::{
+    ast::WorkspaceAst,
+    compile::VarIdTyMetadata,
+    config::{Config, SolverBackend, parse_config},
+    diagnostics::Diagnostic,
+    overrides::invocation_with_params,
+    parse::{self, WorkspaceParseAst},
+};
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+pub mod build;
+pub mod check;
+pub mod compile;
+pub mod def;
+pub mod gds;
+pub mod graph;
+pub mod import_gds;
+pub mod journal;
+pub mod layers;
+pub mod lef;
+pub mod lint;
+pub mod lvs;
+pub mod netlist;
+pub mod svg;
+pub mod verify_gds;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Format of diagnostics, either `human` or `json`. JSON diagnostics are
+    /// printed to standard output, one object per line.
+    #[arg(long, global = true, default_value = "human")]
+    message_format: MessageFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown message format `{s}`; expected `human` or `json`"
+            )),
+        }
+    }
+}
+
+/// Message format chosen on the command line, set once in [`main`].
+static MESSAGE_FORMAT: OnceLock<MessageFormat> = OnceLock::new();
+
+/// Reports `diagnostic` in the message format chosen on the command line.
+fn emit(diagnostic: &Diagnostic) {
+    match MESSAGE_FORMAT.get().copied().unwrap_or_default() {
+        MessageFormat::Human => eprintln!("{diagnostic}"),
+        MessageFormat::Json => println!(
+            "{}",
+            serde_json::to_string(diagnostic).expect("diagnostics serialize to JSON")
+        ),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Exports the targets declared in `Argon.toml` to GDS.
+    Build(build::BuildArgs),
+    /// Reports static errors in the workspace and compile errors in cells.
+    Check(check::CheckArgs),
+    /// Compiles a cell and writes its output as JSON.
+    Compile(compile::CompileArgs),
+    /// Exports cells to GDS or OASIS.
+    Gds(gds::GdsArgs),
+    /// Exports the placed instances of cells to DEF.
+    Def(def::DefArgs),
+    /// Renders cells to SVG.
+    Svg(svg::SvgArgs),
+    /// Exports abstracts of cells and the cells they instantiate to LEF.
+    Lef(lef::LefArgs),
+    /// Prints the constraint graph of a scope in Graphviz DOT.
+    Graph(graph::GraphArgs),
+    /// Checks a cell against a reference SPICE netlist.
+    Lvs(lvs::LvsArgs),
+    /// Extracts SPICE netlists of cells from their layout.
+    Netlist(netlist::NetlistArgs),
+    /// Lists the layers referenced in the workspace, or renames one.
+    Layers(layers::LayersArgs),
+    /// Checks the workspace against the lint rules configured in `Argon.toml`.
+    Lint(lint::LintArgs),
+    /// Checks that an exported GDS file matches a fresh compile of its cell.
+    VerifyGds(verify_gds::VerifyGdsArgs),
+    /// Reverse-compiles a GDS file into Argon source.
+    ImportGds(import_gds::ImportGdsArgs),
+    /// Lists, reverts, or replays edits made to Argon source from the GUI.
+    Journal(journal::JournalArgs),
+}
+
+/// The workspace a command operates on.
+#[derive(clap::Args, Debug)]
+pub struct WorkspaceArgs {
+    /// Workspace directory containing `lib.ar`, or the root source file of the
+    /// workspace, whose directory becomes the workspace directory. Defaults to
+    /// the current directory.
+    #[arg(long)]
+    root: Option<PathBuf>,
+    /// Layer properties file, overriding `lyp` in `Argon.toml`.
+    #[arg(long)]
+    lyp: Option<PathBuf>,
+}
+
+impl WorkspaceArgs {
+    /// The workspace directory.
+    fn root_dir(&self) -> anyhow::Result<PathBuf> {
+        Ok(match &self.root {
+            Some(root) if root.is_file() => match root.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        })
+    }
+
+    /// The root source file of the workspace in `root_dir`.
+    fn lib_path(&self, root_dir: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) if root.is_file() => root.clone(),
+            _ => root_dir.join("lib.ar"),
+        }
+    }
+
+    /// Parses the `Argon.toml` in `root_dir`, with the layer properties file
+    /// overridden by `--lyp`. A missing `Argon.toml` is treated as an empty one.
+    fn config(&self, root_dir: &Path) -> anyhow::Result<Config> {
+        let path = root_dir.join("Argon.toml");
+        let mut config = if path.exists() {
+            parse_config(&path).with_context(|| format!("failed to read {path:?}"))?
+        } else {
+            Config::default()
+        };
+        if let Some(lyp) = &self.lyp {
+            // Relative to the current directory rather than the workspace.
+            config.lyp = Some(std::path::absolute(lyp)?);
+        }
+        Ok(config)
+    }
+}
+
+/// Command-line overrides of the `[solver]` options in `Argon.toml`.
+#[derive(clap::Args, Debug, Default)]
+pub struct SolverArgs {
+    /// Solver backend, either `eliminate` or `svd`.
+    #[arg(long)]
+    solver_backend: Option<SolverBackend>,
+    /// Magnitude below which the solver treats values as zero.
+    #[arg(long)]
+    solver_tolerance: Option<f64>,
+    /// Step that solved values are rounded to, in layout units.
+    #[arg(long)]
+    round_step: Option<f64>,
+    /// Maximum number of solver iterations per cell.
+    #[arg(long)]
+    max_solver_iterations: Option<u64>,
+    /// Maximum time to spend compiling each cell invocation, in seconds.
+    #[arg(long)]
+    solver_timeout: Option<f64>,
+    /// Priority of the fallback constraints set by a keyword argument, e.g.
+    /// `y0i=10`. May be repeated.
+    #[arg(long, value_parser = parse_fallback_priority)]
+    fallback_priority: Vec<(String, i32)>,
+}
+
+/// Parameters added to the cell invocations given on the command line.
+#[derive(clap::Args, Debug, Default)]
+pub struct ParamArgs {
+    /// Argument passed to each cell invocation by name, e.g. `nw=1200`. May be
+    /// repeated, and takes precedence over `--params-json`.
+    #[arg(long = "param", value_parser = parse_param)]
+    params: Vec<(String, String)>,
+    /// JSON file containing an object of arguments keyed by parameter name,
+    /// e.g. `{"nw": 1200, "flavor": "Lvt"}`.
+    #[arg(long)]
+    params_json: Option<PathBuf>,
+}
+
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    let (param, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `<param>=<value>`, found `{s}`"))?;
+    Ok((param.trim().to_string(), value.trim().to_string()))
+}
+
+impl ParamArgs {
+    /// Returns the arguments to add, keyed by parameter name.
+    fn params(&self) -> anyhow::Result<IndexMap<String, String>> {
+        let mut params = IndexMap::new();
+        if let Some(path) = &self.params_json {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("failed to read {path:?}: {e}"))?;
+            let serde_json::Value::Object(object) = serde_json::from_str(&json)
+                .map_err(|e| anyhow!("failed to parse {path:?}: {e}"))?
+            else {
+                bail!("expected a JSON object of parameters in {path:?}");
+            };
+            for (param, value) in object {
+                let value = match value {
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    // Strings name enum variants, e.g. `Lvt` or `Flavor::Lvt`.
+                    serde_json::Value::String(s) => s,
+                    value => bail!("unsupported value for parameter `{param}`: {value}"),
+                };
+                params.insert(param, value);
+            }
+        }
+        params.extend(self.params.iter().cloned());
+        Ok(params)
+    }
+
+    /// Adds the parameters to the invocation `cell`.
+    fn apply(&self, ast: &WorkspaceParseAst, cell: &str) -> anyhow::Result<String> {
+        invocation_with_params(ast, cell, &self.params()?)
+    }
+}
+
+fn parse_fallback_priority(s: &str) -> Result<(String, i32), String> {
+    let (kwarg, priority) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `<kwarg>=<priority>`, found `{s}`"))?;
+    let priority = priority
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid priority `{priority}`: {e}"))?;
+    Ok((kwarg.trim().to_string(), priority))
+}
+
+impl SolverArgs {
+    /// Applies the overrides to the solver options in `config`.
+    fn apply(&self, config: &mut Config) -> anyhow::Result<()> {
+        let solver = &mut config.solver;
+        if let Some(backend) = self.solver_backend {
+            solver.backend = backend;
+        }
+        if let Some(tolerance) = self.solver_tolerance {
+            solver.tolerance = tolerance;
+        }
+        if let Some(round_step) = self.round_step {
+            solver.round_step = round_step;
+        }
+        if let Some(max_iterations) = self.max_solver_iterations {
+            solver.max_iterations = Some(max_iterations);
+        }
+        if let Some(timeout) = self.solver_timeout {
+            solver.timeout = Some(timeout);
+        }
+        solver
+            .fallback_priority
+            .extend(self.fallback_priority.iter().cloned());
+        solver.validate()
+    }
+}
+
+/// A statically compiled workspace.
+struct Workspace {
+    root_dir: PathBuf,
+    lib_path: PathBuf,
+    config: Config,
+    lyp: PathBuf,
+    ast: WorkspaceParseAst,
+    static_ast: WorkspaceAst<VarIdTyMetadata>,
+}
+
+impl Workspace {
+    /// Parses and statically compiles `workspace`, with the solver options in
+    /// `Argon.toml` overridden by `solver`.
+    fn load(workspace: &WorkspaceArgs, solver: &SolverArgs) -> anyhow::Result<Self> {
+        let root_dir = workspace.root_dir()?;
+        let mut config = workspace.config(&root_dir)?;
+        solver.apply(&mut config)?;
+        let lyp = config.lyp_path(&root_dir);
+        let lib_path = workspace.lib_path(&root_dir);
+        let parse_output = parse::parse_workspace_with_std(&lib_path);
+        let parse_errs = parse_output.static_errors();
+        let ast = parse_output.ast();
+        let Some((static_ast, static_output)) = compiler::compile::static_compile(&ast) else {
+            bail!("fatal parse errors encountered, unable to compile");
+        };
+        if !parse_errs.is_empty() || !static_output.errors.is_empty() {
+            for e in parse_errs.iter().chain(&static_output.errors) {
+                emit(&Diagnostic::from(e));
+            }
+            bail!("static errors encountered");
+        }
+        Ok(Self {
+            root_dir,
+            lib_path,
+            config,
+            lyp,
+            ast,
+            static_ast,
+        })
+    }
+}
+
+/// Converts a cell invocation into a file name, e.g. `inv(1200., 4)` to `inv_1200_4`.
+fn file_stem(cell: &str) -> String {
+    cell.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+        .filter(|s| !s.is_empty())
+        .join("_")
+}
+
+/// Pairs each cell invocation in `cells` with the file in `out` named by its
+/// [`file_stem`] and `extension`.
+fn output_paths(
+    cells: Vec<String>,
+    out: &Path,
+    extension: &str,
+) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let cells = cells
+        .into_iter()
+        .map(|cell| {
+            let path = out.join(format!("{}.{extension}", file_stem(&cell)));
+            (cell, path)
+        })
+        .collect_vec();
+    check_distinct_paths(&cells)?;
+    Ok(cells)
+}
+
+/// Fails if different cell invocations in `cells` are paired with the same
+/// path, e.g. `inv(1.5)` and `inv(1,5)`, which share a [`file_stem`].
+fn check_distinct_paths(cells: &[(String, PathBuf)]) -> anyhow::Result<()> {
+    let mut cells_by_path = HashMap::new();
+    for (cell, path) in cells {
+        if let Some(other) = cells_by_path.insert(path, cell)
+            && other != cell
+        {
+            bail!("`{other}` and `{cell}` would both be written to {path:?}");
+        }
+    }
+    Ok(())
+}
+
+pub fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    MESSAGE_FORMAT
+        .set(args.message_format)
+        .expect("message format is only set once");
+    let result = match args.command {
+        Command::Build(args) => build::run(args),
+        Command::Check(args) => check::run(args),
+        Command::Compile(args) => compile::run(args),
+        Command::Gds(args) => gds::run(args),
+        Command::Def(args) => def::run(args),
+        Command::Svg(args) => svg::run(args),
+        Command::Lef(args) => lef::run(args),
+        Command::Graph(args) => graph::run(args),
+        Command::Lvs(args) => lvs::run(args),
+        Command::Netlist(args) => netlist::run(args),
+        Command::Layers(args) => layers::run(args),
+        Command::Lint(args) => lint::run(args),
+        Command::VerifyGds(args) => verify_gds::run(args),
+        Command::ImportGds(args) => import_gds::run(args),
+        Command::Journal(args) => journal::run(args),
+    };
+    // Failures are diagnostics too, so that JSON consumers see every error.
+    if let Err(e) = &result
+        && args.message_format == MessageFormat::Json
+    {
+        emit(&Diagnostic::error(format!("{e:#}")));
+        std::process::exit(1);
+    }
+    result
+}
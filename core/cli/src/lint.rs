@@ -0,0 +1,62 @@
+//! `argon lint`: checks a workspace against the lint rules configured in `Argon.toml`.
+use anyhow::{Result, bail};
+use compiler::{
+    compile::{self, CompileOutput, ExecErrorCompileOutput},
+    diagnostics::Diagnostic,
+    lint::{LintLevel, Linter},
+};
+
+use crate::{SolverArgs, Workspace, WorkspaceArgs, emit, gds::all_cells};
+
+#[derive(clap::Args, Debug)]
+pub struct LintArgs {
+    /// Cell invocations whose geometry to check, e.g. `inv(1200., 2000., 4)`.
+    /// Defaults to the cells exported by `argon gds --all`.
+    cells: Vec<String>,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+pub fn run(args: LintArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let cells = if args.cells.is_empty() {
+        all_cells(&ast, &config)
+    } else {
+        args.cells
+    };
+
+    let mut linter = Linter::new(&config, &ast);
+    linter.check_source();
+    for cell in &cells {
+        match compile::dynamic_compile_invocation(&static_ast, cell, &lyp, &config.solver)? {
+            CompileOutput::Valid(data)
+            | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+                output: Some(data), ..
+            }) => linter.check_output(&data),
+            _ => emit(
+                &Diagnostic::warning("failed to compile, skipping geometry lints").with_cell(cell),
+            ),
+        }
+    }
+
+    let lints = linter.finish();
+    for lint in &lints {
+        emit(&Diagnostic::from(lint));
+    }
+    let denied = lints
+        .iter()
+        .filter(|lint| lint.level == LintLevel::Deny)
+        .count();
+    if denied > 0 {
+        bail!("{denied} denied lint(s) found");
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! `argon lvs`: checks a cell against a reference SPICE netlist.
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, anyhow, bail};
+use compiler::{
+    compile::{self, CompileOutput},
+    lvs::parse_spice,
+};
+
+use crate::{SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct LvsArgs {
+    /// Cell invocation to check, e.g. `top()`.
+    cell: String,
+    /// Reference SPICE netlist containing a subcircuit with the cell's name.
+    #[arg(short, long)]
+    netlist: PathBuf,
+    /// Subcircuit to compare against. Defaults to the cell's name.
+    #[arg(long)]
+    subckt: Option<String>,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+pub fn run(args: LvsArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let text = std::fs::read_to_string(&args.netlist)
+        .with_context(|| format!("failed to read netlist at {:?}", args.netlist))?;
+    let subckts = parse_spice(&text)?;
+    let name = args
+        .subckt
+        .unwrap_or_else(|| {
+            args.cell
+                .split('(')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .to_lowercase();
+    let reference = subckts
+        .iter()
+        .find(|subckt| subckt.name == name)
+        .ok_or_else(|| anyhow!("no subcircuit named `{name}` in {:?}", args.netlist))?;
+
+    let output =
+        compile::dynamic_compile_invocation(&static_ast, &args.cell, &lyp, &config.solver)?;
+    let CompileOutput::Valid(data) = output else {
+        bail!("failed to compile `{}`", args.cell);
+    };
+    let report = data.check_netlist(reference, &config.connect);
+    print!("{report}");
+    if !report.is_clean() {
+        bail!("layout does not match netlist");
+    }
+    Ok(())
+}
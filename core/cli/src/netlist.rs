@@ -0,0 +1,47 @@
+//! `argon netlist`: extracts SPICE netlists of cells from their layout.
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use compiler::compile;
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct NetlistArgs {
+    /// Cell invocations to extract, e.g. `top()`.
+    #[arg(required = true)]
+    cells: Vec<String>,
+    /// Directory to write SPICE netlists to.
+    #[arg(short, long, default_value = "netlist")]
+    out: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+pub fn run(args: NetlistArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    if config.connect.is_empty() {
+        bail!("no layer connectivity in `Argon.toml`; add a `[[connect]]` section");
+    }
+    let cells = args
+        .cells
+        .iter()
+        .map(|cell| args.params.apply(&ast, cell))
+        .collect::<Result<Vec<_>>>()?;
+    for (cell, path) in crate::output_paths(cells, &args.out, "spice")? {
+        let output = compile::dynamic_compile_invocation(&static_ast, &cell, &lyp, &config.solver)?;
+        output.to_spice(&config.connect, &path)?;
+        println!("{cell}: wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! `argon svg`: renders cells to SVG.
+use std::path::PathBuf;
+
+use anyhow::Result;
+use compiler::compile;
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct SvgArgs {
+    /// Cell invocations to render, e.g. `inv(1200., 2000., 4)`.
+    #[arg(required = true)]
+    cells: Vec<String>,
+    /// Directory to write SVG files to.
+    #[arg(short, long, default_value = "svg")]
+    out: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+pub fn run(args: SvgArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let cells = args
+        .cells
+        .iter()
+        .map(|cell| args.params.apply(&ast, cell))
+        .collect::<Result<Vec<_>>>()?;
+    for (cell, path) in crate::output_paths(cells, &args.out, "svg")? {
+        let output = compile::dynamic_compile_invocation(&static_ast, &cell, &lyp, &config.solver)?;
+        output.to_svg(&path)?;
+        println!("{cell}: wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! `argon verify-gds`: checks an exported GDS file against a fresh compile.
+use std::path::PathBuf;
+
+use ::gds::GdsLibrary;
+use anyhow::{Result, anyhow, bail};
+use compiler::{
+    compile::{self, CompileOutput},
+    gds::GdsMap,
+};
+
+use crate::{ParamArgs, SolverArgs, Workspace, WorkspaceArgs};
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyGdsArgs {
+    /// Cell invocation that was exported, e.g. `inv(1200., 2000., 4)`.
+    cell: String,
+    /// GDS file written by `argon gds`.
+    file: PathBuf,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    params: ParamArgs,
+}
+
+pub fn run(args: VerifyGdsArgs) -> Result<()> {
+    let Workspace {
+        config,
+        lyp,
+        ast,
+        static_ast,
+        ..
+    } = Workspace::load(&args.workspace, &args.solver)?;
+    let cell = args.params.apply(&ast, &args.cell)?;
+    let lib = GdsLibrary::load(&args.file)
+        .map_err(|e| anyhow!("failed to read GDS at {:?}: {e}", args.file))?;
+    let map = GdsMap::from_lyp(&lyp)?;
+
+    let output = compile::dynamic_compile_invocation(&static_ast, &cell, &lyp, &config.solver)?;
+    let CompileOutput::Valid(mut data) = output else {
+        bail!("failed to compile `{cell}`");
+    };
+    // Export inserts fill, so the GDS is expected to contain it.
+    data.check_density(&config.density);
+    let mismatches = data.verify_gds(&lib, &map)?;
+    for mismatch in &mismatches {
+        println!("{mismatch}");
+    }
+    if !mismatches.is_empty() {
+        bail!(
+            "{} shape(s) in {:?} do not match `{cell}`",
+            mismatches.len(),
+            args.file
+        );
+    }
+    println!("{:?} matches `{cell}`", args.file);
+    Ok(())
+}
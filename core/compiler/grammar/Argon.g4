@@ -14,6 +14,7 @@ decl
     : enumDecl
     | structDecl
     | cellDecl
+    | externCellDecl
     | fnDecl
     | constantDecl
     | modDecl
@@ -35,8 +36,19 @@ seqNilLiteral
     : LBRACK RBRACK
     ;
 
+seqExpr
+    : LBRACK expr (COMMA expr)* COMMA? RBRACK
+    ;
+
 floatLiteral
-    : INTLIT DOT INTLIT?
+    : INTLIT DOT INTLIT? lengthUnit?
+    | INTLIT lengthUnit
+    ;
+
+// The unit of a length literal, `nm` or `um`, only recognized directly after
+// its number, e.g. `0.15um`.
+lengthUnit
+    : ident
     ;
 
 intLiteral
@@ -60,7 +72,7 @@ literal
     ;
 
 enumDecl
-    : ENUM ident LBRACE enumVariants RBRACE
+    : ENUM ident (LPAREN structFields RPAREN)? LBRACE enumVariants RBRACE
     ;
 
 structDecl
@@ -76,7 +88,11 @@ modDecl
     ;
 
 enumVariants
-    : (ident (COMMA ident)* COMMA?)?
+    : (enumVariant (COMMA enumVariant)* COMMA?)?
+    ;
+
+enumVariant
+    : ident (LPAREN (expr (COMMA expr)* COMMA?)? RPAREN)? (EQ expr)?
     ;
 
 structFields
@@ -91,6 +107,10 @@ cellDecl
     : CELL ident LPAREN argDecls RPAREN scope
     ;
 
+externCellDecl
+    : EXTERN CELL ident LPAREN args RPAREN SEMI
+    ;
+
 fnDecl
     : FN ident LPAREN argDecls RPAREN (ARROW tySpec)? scope
     ;
@@ -104,13 +124,18 @@ argDecl
     ;
 
 scope
-    : scopeAnnotation? unannotatedScope
+    : (scopeAnnotation constructionModifier?)? unannotatedScope
     ;
 
 scopeAnnotation
     : ANNOTATION
     ;
 
+// The contextual keyword `construction`, only recognized directly before `{`.
+constructionModifier
+    : ident
+    ;
+
 unannotatedScope
     : LBRACE statements RBRACE
     | LBRACE statements expr RBRACE
@@ -134,7 +159,7 @@ letBinding
     ;
 
 forLoop
-    : FOR ident IN expr scope
+    : FOR ident IN expr (DOTDOT expr)? scope
     ;
 
 ifExpr
@@ -149,6 +174,7 @@ matchArms
     : matchArm+
     ;
 
+// A pattern of `_` matches any variant.
 matchArm
     : identPath FAT_ARROW expr COMMA
     ;
@@ -165,6 +191,7 @@ expr
     | expr comparisonOp=(EQEQ | NEQ | GEQ | GT | LEQ | LT) expr
     | nilLiteral
     | seqNilLiteral
+    | seqExpr
     | tupleExpr
     | LPAREN expr RPAREN
     | ifExpr
@@ -222,6 +249,7 @@ STRUCT: 'struct';
 MATCH: 'match';
 CONST: 'const';
 CELL: 'cell';
+EXTERN: 'extern';
 MOD: 'mod';
 IF: 'if';
 FN: 'fn';
@@ -248,6 +276,7 @@ LEQ: '<=';
 LT: '<';
 EQ: '=';
 ARROW: '->';
+DOTDOT: '..';
 PLUS: '+';
 STAR: '*';
 PERCENT: '%';
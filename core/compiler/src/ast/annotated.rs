@@ -43,7 +43,12 @@ impl<T: AstMetadata> AnnotatedAst<T> {
                 Decl::Enum(e) => {
                     decls.push(Decl::Enum(pass.transform_enum_decl(e)));
                 }
-                _ => todo!(),
+                Decl::Struct(s) => {
+                    decls.push(Decl::Struct(pass.transform_struct_decl(s)));
+                }
+                Decl::Constant(c) => {
+                    decls.push(Decl::Constant(pass.transform_constant_decl(c)));
+                }
             }
         }
 
@@ -92,11 +97,30 @@ impl<S, T: AstMetadata> AstTransformer for AstAnnotationPass<S, T> {
         &mut self,
         input: &super::EnumDecl<Self::InputS, Self::InputMetadata>,
         _name: &super::Ident<Self::OutputS, Self::OutputMetadata>,
-        _variants: &[super::Ident<Self::OutputS, Self::OutputMetadata>],
+        _fields: &[super::StructField<Self::OutputS, Self::OutputMetadata>],
+        _variants: &[super::EnumVariant<Self::OutputS, Self::OutputMetadata>],
     ) -> <Self::OutputMetadata as AstMetadata>::EnumDecl {
         input.metadata.clone()
     }
 
+    fn dispatch_struct_decl(
+        &mut self,
+        input: &super::StructDecl<Self::InputS, Self::InputMetadata>,
+        _name: &super::Ident<Self::OutputS, Self::OutputMetadata>,
+        _fields: &[super::StructField<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::StructDecl {
+        input.metadata.clone()
+    }
+
+    fn dispatch_struct_field(
+        &mut self,
+        input: &super::StructField<Self::InputS, Self::InputMetadata>,
+        _name: &super::Ident<Self::OutputS, Self::OutputMetadata>,
+        _ty: &super::Ident<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::StructField {
+        input.metadata.clone()
+    }
+
     fn dispatch_cell_decl(
         &mut self,
         input: &super::CellDecl<Self::InputS, Self::InputMetadata>,
@@ -209,6 +233,23 @@ impl<S, T: AstMetadata> AstTransformer for AstAnnotationPass<S, T> {
         input.metadata.clone()
     }
 
+    fn dispatch_seq_expr(
+        &mut self,
+        input: &super::SeqExpr<Self::InputS, Self::InputMetadata>,
+        _items: &[super::Expr<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::SeqExpr {
+        input.metadata.clone()
+    }
+
+    fn dispatch_range_expr(
+        &mut self,
+        input: &super::RangeExpr<Self::InputS, Self::InputMetadata>,
+        _start: &super::Expr<Self::OutputS, Self::OutputMetadata>,
+        _end: &super::Expr<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::RangeExpr {
+        input.metadata.clone()
+    }
+
     fn dispatch_field_access_expr(
         &mut self,
         input: &super::FieldAccessExpr<Self::InputS, Self::InputMetadata>,
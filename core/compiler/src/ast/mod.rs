@@ -67,7 +67,35 @@ pub struct SeqNilLiteral {
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct FloatLiteral {
     pub span: cfgrammar::Span,
+    /// Value in layout units, after conversion from `unit`.
     pub value: f64,
+    /// Unit suffix of a length literal such as `0.15um`, if any.
+    pub unit: Option<LengthUnit>,
+}
+
+/// Unit suffix of a length literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LengthUnit {
+    Nm,
+    Um,
+}
+
+impl LengthUnit {
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "nm" => Some(LengthUnit::Nm),
+            "um" => Some(LengthUnit::Um),
+            _ => None,
+        }
+    }
+
+    /// Power of ten of the layout units, i.e. nanometers, per unit.
+    pub fn exponent(&self) -> i32 {
+        match self {
+            LengthUnit::Nm => 0,
+            LengthUnit::Um => 3,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -91,10 +119,22 @@ pub struct BoolLiteral {
 #[derive_where(Debug, Clone, Serialize, Deserialize; S)]
 pub struct EnumDecl<S, T: AstMetadata> {
     pub name: Ident<S, T>,
-    pub variants: Vec<Ident<S, T>>,
+    /// Constant data carried by every variant, e.g. `(gds: Int, min_width: Float)`.
+    pub fields: Vec<StructField<S, T>>,
+    pub variants: Vec<EnumVariant<S, T>>,
     pub metadata: T::EnumDecl,
 }
 
+#[derive_where(Debug, Clone, Serialize, Deserialize; S)]
+pub struct EnumVariant<S, T: AstMetadata> {
+    pub name: Ident<S, T>,
+    /// Values of the enum's fields, in declaration order.
+    pub values: Vec<Expr<S, T>>,
+    /// Explicit discriminant, e.g. `= 3`.
+    pub discriminant: Option<Expr<S, T>>,
+    pub span: cfgrammar::Span,
+}
+
 #[derive_where(Debug, Clone, Serialize, Deserialize; S)]
 pub struct StructDecl<S, T: AstMetadata> {
     pub name: Ident<S, T>,
@@ -116,6 +156,11 @@ pub struct CellDecl<S, T: AstMetadata> {
     pub name: Ident<S, T>,
     pub args: Vec<ArgDecl<S, T>>,
     pub scope: Scope<S, T>,
+    /// Bounding box of an `extern cell`, given as keyword arguments of `rect`
+    /// in place of a body, e.g. `extern cell sram(x0=0., y0=0., x1=1200., y1=800.);`.
+    ///
+    /// The layout of an extern cell comes from a GDS file listed in `Argon.toml`.
+    pub bbox: Option<Args<S, T>>,
     pub span: cfgrammar::Span,
     pub metadata: T::CellDecl,
 }
@@ -154,6 +199,9 @@ pub struct ConstantDecl<S, T: AstMetadata> {
 #[derive_where(Debug, Clone, Serialize, Deserialize; S)]
 pub struct Scope<S, T: AstMetadata> {
     pub scope_annotation: Option<Ident<S, T>>,
+    /// Whether this is a construction scope (`#name construction { ... }`),
+    /// whose geometry is kept for constraints but not emitted.
+    pub construction: bool,
     pub span: cfgrammar::Span,
     pub stmts: Vec<Statement<S, T>>,
     pub tail: Option<Expr<S, T>>,
@@ -231,6 +279,8 @@ pub enum Expr<S, T: AstMetadata> {
     Scope(Box<Scope<S, T>>),
     Cast(Box<CastExpr<S, T>>),
     Tuple(TupleExpr<S, T>),
+    Range(Box<RangeExpr<S, T>>),
+    Seq(SeqExpr<S, T>),
 }
 
 #[derive_where(Debug, Clone, Serialize, Deserialize; S)]
@@ -253,7 +303,8 @@ pub struct MatchExpr<S, T: AstMetadata> {
 
 #[derive_where(Debug, Clone, Serialize, Deserialize; S)]
 pub struct MatchArm<S, T: AstMetadata> {
-    pub pattern: IdentPath<S, T>,
+    /// Enum value matched by the arm, or `None` for the wildcard pattern `_`.
+    pub pattern: Option<IdentPath<S, T>>,
     pub expr: Expr<S, T>,
     pub span: cfgrammar::Span,
 }
@@ -347,6 +398,16 @@ pub struct ArgDecl<S, T: AstMetadata> {
     pub metadata: T::ArgDecl,
 }
 
+/// The half-open range of integers `start..end`, e.g. `0..n` in
+/// `for i in 0..n { ... }`.
+#[derive_where(Debug, Clone, Serialize, Deserialize; S)]
+pub struct RangeExpr<S, T: AstMetadata> {
+    pub start: Expr<S, T>,
+    pub end: Expr<S, T>,
+    pub span: cfgrammar::Span,
+    pub metadata: T::RangeExpr,
+}
+
 #[derive_where(Debug, Clone, Serialize, Deserialize; S)]
 pub struct CastExpr<S, T: AstMetadata> {
     pub value: Expr<S, T>,
@@ -362,6 +423,14 @@ pub struct TupleExpr<S, T: AstMetadata> {
     pub metadata: T::TupleExpr,
 }
 
+/// A non-empty sequence literal, e.g. `[r0, r1, r2]`.
+#[derive_where(Debug, Clone, Serialize, Deserialize; S)]
+pub struct SeqExpr<S, T: AstMetadata> {
+    pub items: Vec<Expr<S, T>>,
+    pub span: cfgrammar::Span,
+    pub metadata: T::SeqExpr,
+}
+
 impl<S, T: AstMetadata> Expr<S, T> {
     pub fn span(&self) -> cfgrammar::Span {
         match self {
@@ -384,6 +453,8 @@ impl<S, T: AstMetadata> Expr<S, T> {
             Self::BoolLiteral(x) => x.span,
             Self::Scope(x) => x.span,
             Self::Cast(x) => x.span,
+            Self::Range(x) => x.span,
+            Self::Seq(x) => x.span,
             Self::Tuple(x) => x.span,
         }
     }
@@ -417,6 +488,8 @@ pub trait AstMetadata {
     type Typ: Debug + Clone + Serialize + DeserializeOwned;
     type CastExpr: Debug + Clone + Serialize + DeserializeOwned;
     type TupleExpr: Debug + Clone + Serialize + DeserializeOwned;
+    type RangeExpr: Debug + Clone + Serialize + DeserializeOwned;
+    type SeqExpr: Debug + Clone + Serialize + DeserializeOwned;
 }
 
 pub trait AstTransformer {
@@ -437,8 +510,21 @@ pub trait AstTransformer {
         &mut self,
         input: &EnumDecl<Self::InputS, Self::InputMetadata>,
         name: &Ident<Self::OutputS, Self::OutputMetadata>,
-        variants: &[Ident<Self::OutputS, Self::OutputMetadata>],
+        fields: &[StructField<Self::OutputS, Self::OutputMetadata>],
+        variants: &[EnumVariant<Self::OutputS, Self::OutputMetadata>],
     ) -> <Self::OutputMetadata as AstMetadata>::EnumDecl;
+    fn dispatch_struct_decl(
+        &mut self,
+        input: &StructDecl<Self::InputS, Self::InputMetadata>,
+        name: &Ident<Self::OutputS, Self::OutputMetadata>,
+        fields: &[StructField<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::StructDecl;
+    fn dispatch_struct_field(
+        &mut self,
+        input: &StructField<Self::InputS, Self::InputMetadata>,
+        name: &Ident<Self::OutputS, Self::OutputMetadata>,
+        ty: &Ident<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::StructField;
     fn dispatch_cell_decl(
         &mut self,
         input: &CellDecl<Self::InputS, Self::InputMetadata>,
@@ -515,6 +601,17 @@ pub trait AstTransformer {
         input: &TupleExpr<Self::InputS, Self::InputMetadata>,
         items: &[Expr<Self::OutputS, Self::OutputMetadata>],
     ) -> <Self::OutputMetadata as AstMetadata>::TupleExpr;
+    fn dispatch_range_expr(
+        &mut self,
+        input: &RangeExpr<Self::InputS, Self::InputMetadata>,
+        start: &Expr<Self::OutputS, Self::OutputMetadata>,
+        end: &Expr<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::RangeExpr;
+    fn dispatch_seq_expr(
+        &mut self,
+        input: &SeqExpr<Self::InputS, Self::InputMetadata>,
+        items: &[Expr<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::SeqExpr;
     fn dispatch_field_access_expr(
         &mut self,
         input: &FieldAccessExpr<Self::InputS, Self::InputMetadata>,
@@ -629,18 +726,77 @@ pub trait AstTransformer {
         input: &EnumDecl<Self::InputS, Self::InputMetadata>,
     ) -> EnumDecl<Self::OutputS, Self::OutputMetadata> {
         let name = self.transform_ident(&input.name);
+        let fields = input
+            .fields
+            .iter()
+            .map(|field| self.transform_struct_field(field))
+            .collect_vec();
         let variants = input
             .variants
             .iter()
-            .map(|variant| self.transform_ident(variant))
+            .map(|variant| self.transform_enum_variant(variant))
             .collect_vec();
-        let metadata = self.dispatch_enum_decl(input, &name, &variants);
+        let metadata = self.dispatch_enum_decl(input, &name, &fields, &variants);
         EnumDecl {
             name,
+            fields,
             variants,
             metadata,
         }
     }
+    fn transform_struct_decl(
+        &mut self,
+        input: &StructDecl<Self::InputS, Self::InputMetadata>,
+    ) -> StructDecl<Self::OutputS, Self::OutputMetadata> {
+        let name = self.transform_ident(&input.name);
+        let fields = input
+            .fields
+            .iter()
+            .map(|field| self.transform_struct_field(field))
+            .collect_vec();
+        let metadata = self.dispatch_struct_decl(input, &name, &fields);
+        StructDecl {
+            name,
+            fields,
+            span: input.span,
+            metadata,
+        }
+    }
+    fn transform_struct_field(
+        &mut self,
+        input: &StructField<Self::InputS, Self::InputMetadata>,
+    ) -> StructField<Self::OutputS, Self::OutputMetadata> {
+        let name = self.transform_ident(&input.name);
+        let ty = self.transform_ident(&input.ty);
+        let metadata = self.dispatch_struct_field(input, &name, &ty);
+        StructField {
+            name,
+            ty,
+            span: input.span,
+            metadata,
+        }
+    }
+    fn transform_enum_variant(
+        &mut self,
+        input: &EnumVariant<Self::InputS, Self::InputMetadata>,
+    ) -> EnumVariant<Self::OutputS, Self::OutputMetadata> {
+        let name = self.transform_ident(&input.name);
+        let values = input
+            .values
+            .iter()
+            .map(|value| self.transform_expr(value))
+            .collect_vec();
+        let discriminant = input
+            .discriminant
+            .as_ref()
+            .map(|discriminant| self.transform_expr(discriminant));
+        EnumVariant {
+            name,
+            values,
+            discriminant,
+            span: input.span,
+        }
+    }
     fn transform_cell_decl(
         &mut self,
         input: &CellDecl<Self::InputS, Self::InputMetadata>,
@@ -652,11 +808,13 @@ pub trait AstTransformer {
             .map(|arg| self.transform_arg_decl(arg))
             .collect_vec();
         let scope = self.transform_scope(&input.scope);
+        let bbox = input.bbox.as_ref().map(|bbox| self.transform_args(bbox));
         let metadata = self.dispatch_cell_decl(input, &name, &args, &scope);
         CellDecl {
             name,
             args,
             scope,
+            bbox,
             span: input.span,
             metadata,
         }
@@ -784,7 +942,7 @@ pub trait AstTransformer {
             .arms
             .iter()
             .map(|arm| MatchArm {
-                pattern: self.transform_ident_path(&arm.pattern),
+                pattern: arm.pattern.as_ref().map(|p| self.transform_ident_path(p)),
                 expr: self.transform_expr(&arm.expr),
                 span: arm.span,
             })
@@ -979,6 +1137,7 @@ pub trait AstTransformer {
         let metadata = self.dispatch_scope(input, &stmts, &tail);
         let output = Scope {
             scope_annotation,
+            construction: input.construction,
             span: input.span,
             stmts,
             tail,
@@ -1020,6 +1179,38 @@ pub trait AstTransformer {
         }
     }
 
+    fn transform_seq_expr(
+        &mut self,
+        input: &SeqExpr<Self::InputS, Self::InputMetadata>,
+    ) -> SeqExpr<Self::OutputS, Self::OutputMetadata> {
+        let items = input
+            .items
+            .iter()
+            .map(|i| self.transform_expr(i))
+            .collect::<Vec<_>>();
+        let metadata = self.dispatch_seq_expr(input, &items);
+        SeqExpr {
+            items,
+            span: input.span,
+            metadata,
+        }
+    }
+
+    fn transform_range_expr(
+        &mut self,
+        input: &RangeExpr<Self::InputS, Self::InputMetadata>,
+    ) -> RangeExpr<Self::OutputS, Self::OutputMetadata> {
+        let start = self.transform_expr(&input.start);
+        let end = self.transform_expr(&input.end);
+        let metadata = self.dispatch_range_expr(input, &start, &end);
+        RangeExpr {
+            start,
+            end,
+            span: input.span,
+            metadata,
+        }
+    }
+
     fn transform_string_literal(
         &mut self,
         input: &StringLiteral<Self::InputS>,
@@ -1068,6 +1259,8 @@ pub trait AstTransformer {
             Expr::Scope(scope) => Expr::Scope(Box::new(self.transform_scope(scope))),
             Expr::Cast(cast) => Expr::Cast(Box::new(self.transform_cast(cast))),
             Expr::Tuple(tuple) => Expr::Tuple(self.transform_tuple_expr(tuple)),
+            Expr::Range(range) => Expr::Range(Box::new(self.transform_range_expr(range))),
+            Expr::Seq(seq) => Expr::Seq(self.transform_seq_expr(seq)),
         }
     }
 }
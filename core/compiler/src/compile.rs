@@ -4,13 +4,17 @@
 //! Pass 2: assign variable IDs/type checking
 //! Pass 3: solving
 use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+#[cfg(feature = "fs")]
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+#[cfg(feature = "std-time")]
+use std::time::{Duration, Instant};
 
 use arcstr::Substr;
+use argon_geometry::{Bbox, Flatten, Transform};
 use enumify::enumify;
-use geometry::transform::{Rotation, TransformationMatrix};
 use indexmap::{IndexMap, IndexSet};
 use itertools::{Either, Itertools};
 use serde::{Deserialize, Serialize};
@@ -18,13 +22,21 @@ use thiserror::Error;
 
 use crate::ast::annotated::AnnotatedAst;
 use crate::ast::{
-    BinOp, CastExpr, ComparisonOp, ConstantDecl, EnumDecl, FieldAccessExpr, FnDecl, ForLoop,
-    IdentPath, IndexExpr, IndexFieldAccessExpr, IntLiteral, KwArgValue, MatchExpr, ModPath, Scope,
-    Span, TySpec, TySpecKind, UnaryOp, UnaryOpExpr, WorkspaceAst,
+    BinOp, CastExpr, ComparisonOp, ConstantDecl, EnumDecl, EnumVariant, FieldAccessExpr, FnDecl,
+    ForLoop, IdentPath, IndexExpr, IndexFieldAccessExpr, IntLiteral, KwArgValue, MatchExpr,
+    ModPath, Scope, Span, StructDecl, StructField, TySpec, TySpecKind, UnaryOp, UnaryOpExpr,
+    WorkspaceAst,
 };
-use crate::layer::LayerProperties;
+use crate::config::SolverConfig;
+use crate::consts::{ConstValue, Constant, eval_const_expr, eval_consts};
+use crate::dbu::{DEFAULT_DBU, DbuShape, snap_objects};
+use crate::density::{DensityViolation, FillShape};
+use crate::drc::DrcViolation;
+use crate::invocation::Invocation;
+use crate::layer::{LayerProperties, line_col};
+use crate::nets::Nets;
 use crate::parse::WorkspaceParseAst;
-use crate::solver::{ConstraintId, Var};
+use crate::solver::{ConstraintId, Var, conflict_component, irreducible_infeasible_subset};
 use crate::{
     ast::{
         ArgDecl, Ast, AstMetadata, AstTransformer, BinOpExpr, CallExpr, CellDecl, ComparisonExpr,
@@ -34,22 +46,68 @@ use crate::{
     solver::{LinearExpr, Solver},
 };
 
-pub const BUILTINS: [&str; 13] = [
+pub const BUILTINS: [&str; 33] = [
     "list",
     "cons",
     "head",
     "tail",
+    "len",
     "range_full",
     "crect",
     "rect",
     "text",
+    "polygon",
+    "pin",
     "float",
     "eq",
     "dimension",
     "inst",
+    "inst_array",
     "bbox",
+    "merge",
+    "subtract",
+    "min",
+    "max",
+    "abs",
+    "floor",
+    "ceil",
+    "round",
+    "align_left",
+    "align_right",
+    "align_bottom",
+    "align_top",
+    "align_center_x",
+    "align_center_y",
+    "distribute_x",
+    "distribute_y",
 ];
 
+/// Names of the keyword arguments accepted by the builtin `name`.
+pub fn builtin_kwargs(name: &str) -> &'static [&'static str] {
+    match name {
+        "rect" => &["x0", "y0", "x1", "y1", "w", "h", "x0i", "y0i", "x1i", "y1i"],
+        "crect" => &[
+            "x0", "y0", "x1", "y1", "w", "h", "x0i", "y0i", "x1i", "y1i", "layer",
+        ],
+        "polygon" => &["points"],
+        "inst" => &["x", "y", "xi", "yi", "angle", "reflect", "construction"],
+        "inst_array" => &[
+            "x",
+            "y",
+            "xi",
+            "yi",
+            "angle",
+            "reflect",
+            "construction",
+            "nx",
+            "ny",
+            "dx",
+            "dy",
+        ],
+        _ => &[],
+    }
+}
+
 pub fn static_compile(
     ast: &WorkspaceParseAst,
 ) -> Option<(WorkspaceAst<VarIdTyMetadata>, StaticErrorCompileOutput)> {
@@ -66,7 +124,25 @@ pub fn dynamic_compile(
     ast: &WorkspaceAst<VarIdTyMetadata>,
     input: CompileInput<'_>,
 ) -> CompileOutput {
-    let res = ExecPass::new(ast).execute(input);
+    check_compile_output(ExecPass::new(ast).execute(input))
+}
+
+/// Like [`dynamic_compile`], but with the layer properties given directly
+/// rather than read from [`CompileInput::lyp_file`], which is ignored.
+///
+/// Useful where there is no filesystem to read a layer properties file from.
+pub fn dynamic_compile_with_layers(
+    ast: &WorkspaceAst<VarIdTyMetadata>,
+    input: CompileInput<'_>,
+    layers: LayerProperties,
+) -> CompileOutput {
+    let mut pass = ExecPass::new(ast);
+    pass.layers = Some(layers);
+    check_compile_output(pass.execute(input))
+}
+
+/// Reports uses of undefined layers in the output of an [`ExecPass`].
+fn check_compile_output(res: CompileOutput) -> CompileOutput {
     let (data, mut errors) = match res {
         CompileOutput::ExecErrors(ExecErrorCompileOutput { errors, output }) => {
             if let Some(output) = output {
@@ -102,6 +178,50 @@ pub fn compile(ast: &WorkspaceParseAst, input: CompileInput<'_>) -> CompileOutpu
     dynamic_compile(&ast, input)
 }
 
+/// Compiles a cell invocation such as `inv(1200., 2000., nf=4)`.
+///
+/// The invocation is resolved against the cell's declaration first; see
+/// [`Invocation::resolve`].
+pub fn dynamic_compile_invocation(
+    ast: &WorkspaceAst<VarIdTyMetadata>,
+    cell: &str,
+    lyp_file: &Path,
+    solver: &SolverConfig,
+) -> anyhow::Result<CompileOutput> {
+    let invocation = Invocation::resolve(ast, cell)?;
+    let cell_path = invocation.path.iter().map(String::as_str).collect_vec();
+    Ok(dynamic_compile(
+        ast,
+        CompileInput {
+            cell: &cell_path,
+            args: invocation.cell_args(ast),
+            lyp_file,
+            solver: solver.clone(),
+        },
+    ))
+}
+
+/// Returns the path of the module declaring the cell at `cell`, e.g. `["a"]`
+/// for `crate::a::top`.
+pub(crate) fn cell_mod_path(cell: &[&str]) -> ModPath {
+    match cell[0] {
+        "std" => {
+            vec!["std".to_string()]
+        }
+        "crate" => cell
+            .iter()
+            .skip(1)
+            .dropping_back(1)
+            .map(|ident| ident.to_string())
+            .collect_vec(),
+        _ => cell
+            .iter()
+            .dropping_back(1)
+            .map(|ident| ident.to_string())
+            .collect_vec(),
+    }
+}
+
 type ModDag<'a> = IndexMap<&'a ModPath, IndexSet<&'a ModPath>>;
 
 pub(crate) struct ImportPass<'a> {
@@ -152,9 +272,7 @@ impl<'a> ImportPass<'a> {
                 Decl::Cell(c) => {
                     self.transform_cell_decl(c);
                 }
-                Decl::Mod(_) => {}
-                Decl::Enum(_) => {}
-                _ => todo!(),
+                Decl::Mod(_) | Decl::Enum(_) | Decl::Struct(_) | Decl::Constant(_) => {}
             }
         }
 
@@ -184,10 +302,27 @@ impl<'a> AstTransformer for ImportPass<'a> {
         &mut self,
         _input: &crate::ast::EnumDecl<Self::InputS, Self::InputMetadata>,
         _name: &Ident<Self::OutputS, Self::OutputMetadata>,
-        _variants: &[Ident<Self::OutputS, Self::OutputMetadata>],
+        _fields: &[StructField<Self::OutputS, Self::OutputMetadata>],
+        _variants: &[EnumVariant<Self::OutputS, Self::OutputMetadata>],
     ) -> <Self::OutputMetadata as AstMetadata>::EnumDecl {
     }
 
+    fn dispatch_struct_decl(
+        &mut self,
+        _input: &StructDecl<Self::InputS, Self::InputMetadata>,
+        _name: &Ident<Self::OutputS, Self::OutputMetadata>,
+        _fields: &[StructField<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::StructDecl {
+    }
+
+    fn dispatch_struct_field(
+        &mut self,
+        _input: &StructField<Self::InputS, Self::InputMetadata>,
+        _name: &Ident<Self::OutputS, Self::OutputMetadata>,
+        _ty: &Ident<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::StructField {
+    }
+
     fn dispatch_cell_decl(
         &mut self,
         _input: &CellDecl<Self::InputS, Self::InputMetadata>,
@@ -288,6 +423,21 @@ impl<'a> AstTransformer for ImportPass<'a> {
     ) -> <Self::OutputMetadata as AstMetadata>::TupleExpr {
     }
 
+    fn dispatch_range_expr(
+        &mut self,
+        _input: &crate::ast::RangeExpr<Self::InputS, Self::InputMetadata>,
+        _start: &Expr<Self::OutputS, Self::OutputMetadata>,
+        _end: &Expr<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::RangeExpr {
+    }
+
+    fn dispatch_seq_expr(
+        &mut self,
+        _input: &crate::ast::SeqExpr<Self::InputS, Self::InputMetadata>,
+        _items: &[Expr<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::SeqExpr {
+    }
+
     fn dispatch_field_access_expr(
         &mut self,
         _input: &FieldAccessExpr<Self::InputS, Self::InputMetadata>,
@@ -319,7 +469,7 @@ impl<'a> AstTransformer for ImportPass<'a> {
         _args: &crate::ast::Args<Self::OutputS, Self::OutputMetadata>,
     ) -> <Self::OutputMetadata as AstMetadata>::CallExpr {
         if func.path[0].name != "std" {
-            let path = if func.path[0].name == "crate" {
+            let mut path = if func.path[0].name == "crate" {
                 func.path
                     .iter()
                     .skip(1)
@@ -338,6 +488,16 @@ impl<'a> AstTransformer for ImportPass<'a> {
                     )
                     .collect_vec()
             };
+            // `MyEnum::variants()` is qualified by an enum rather than a module.
+            if func.path.len() >= 2
+                && func.path.last().unwrap().name == "variants"
+                && !self.ast.contains_key(&path)
+            {
+                path.pop();
+                if &path == self.current_path {
+                    return;
+                }
+            }
             if let Some((path_ref, _)) = self.ast.get_key_value(&path) {
                 self.deps.insert(path_ref);
             } else {
@@ -400,14 +560,19 @@ fn check_layers(data: &CompiledData, errs: &mut Vec<ExecError>) {
     }
     for (cell_id, cell) in data.cells.iter() {
         for (_, obj) in cell.objects.iter() {
-            if let SolvedValue::Rect(r) = obj
-                && let Some(layer) = &r.layer
-                && !layers.contains(layer)
+            let (layer, span) = match obj {
+                SolvedValue::Rect(r) => (r.layer.clone(), &r.span),
+                SolvedValue::Polygon(p) => (Some(p.layer.clone()), &p.span),
+                SolvedValue::Pin(p) => (Some(p.pin_layer(|l| layers.contains(l))), &p.span),
+                _ => continue,
+            };
+            if let Some(layer) = layer
+                && !layers.contains(&layer)
             {
                 errs.push(ExecError {
-                    span: r.span.clone(),
+                    span: span.clone(),
                     cell: *cell_id,
-                    kind: ExecErrorKind::IllegalLayer(layer.clone()),
+                    kind: ExecErrorKind::IllegalLayer(layer),
                 })
             }
         }
@@ -426,6 +591,14 @@ pub(crate) struct VarIdTyPass<'a> {
     current_path: &'a ModPath,
     next_id: VarId,
     bindings: Vec<VarIdTyFrame>,
+    /// Values of the constants declared in the current module.
+    consts: IndexMap<String, Constant>,
+    /// Data of the enums declared in the current module.
+    enums: IndexMap<EnumId, EnumData>,
+    /// Spans of `if` expressions whose values are discarded.
+    ///
+    /// The branches of these expressions may evaluate to different types.
+    discarded_ifs: IndexSet<cfgrammar::Span>,
     errors: Vec<StaticError>,
 }
 
@@ -493,6 +666,9 @@ pub(crate) fn execute_var_id_ty_pass_inner<'a>(
         current_path,
         next_id: *next_id,
         bindings: vec![VarIdTyFrame::default()],
+        consts: IndexMap::new(),
+        enums: IndexMap::new(),
+        discarded_ifs: IndexSet::new(),
         errors: vec![],
     };
     let ast = pass.execute();
@@ -529,6 +705,8 @@ pub enum Ty {
     Fn(Box<FnTy>),
     /// An enum variant type, e.g. the type of `MyEnum::MyVariant`.
     Enum(EnumTy),
+    /// A user-defined struct, e.g. the type of `Point(x=1., y=2.)`.
+    Struct(StructTy),
     CellFn(Box<CellFnTy>),
     Seq(Box<Ty>),
     Tuple(Vec<Ty>),
@@ -541,6 +719,9 @@ impl Ty {
             "Int" => Some(Ty::Int),
             "Float" => Some(Ty::Float),
             "Rect" => Some(Ty::Rect),
+            // Instances and cells of any cell share these names in annotations.
+            "Inst" => Some(Ty::Inst(Default::default())),
+            "Cell" => Some(Ty::Cell(Default::default())),
             "Any" => Some(Ty::Any),
             "String" => Some(Ty::String),
             "()" => Some(Ty::Nil),
@@ -590,7 +771,7 @@ pub struct CellFnTy {
     cell: Arc<CellTy>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CellTy {
     data: IndexMap<String, Ty>,
 }
@@ -599,16 +780,39 @@ pub struct CellTy {
 pub struct EnumTy {
     id: EnumId,
     variants: IndexSet<String>,
+    /// Types of the data carried by each variant.
+    fields: IndexMap<String, Ty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructTy {
+    id: StructId,
+    name: String,
+    /// Types of the struct's fields, in declaration order.
+    fields: IndexMap<String, Ty>,
+}
+
+/// The discriminants and associated data of an enum's variants.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EnumData {
+    pub id: EnumId,
+    pub variants: IndexMap<String, EnumVariantData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumVariantData {
+    pub discriminant: i64,
+    pub fields: IndexMap<String, ConstValue>,
 }
 
 impl AstMetadata for VarIdTyMetadata {
     type Ident = ();
     type IdentPath = (Option<VarId>, Ty);
-    type EnumDecl = ();
+    type EnumDecl = EnumData;
     type StructDecl = ();
     type StructField = ();
     type CellDecl = (PathBuf, VarId);
-    type ConstantDecl = ();
+    type ConstantDecl = (VarId, ConstValue);
     type LetBinding = VarId;
     type ForLoop = VarId; // the var ID of the var Ident
     type FnDecl = (PathBuf, VarId);
@@ -629,6 +833,28 @@ impl AstMetadata for VarIdTyMetadata {
     type Typ = ();
     type CastExpr = Ty;
     type TupleExpr = Ty;
+    type RangeExpr = Ty;
+    type SeqExpr = Ty;
+}
+
+/// Returns the power of length of an expression built only from numeric
+/// literals, e.g. 1 for `0.15um + 20nm` and 0 for `2.`, or `None` if it
+/// involves anything else or is itself inconsistent.
+fn length_dim<S, T: AstMetadata>(expr: &Expr<S, T>) -> Option<i32> {
+    match expr {
+        Expr::FloatLiteral(f) => Some(f.unit.is_some() as i32),
+        Expr::IntLiteral(_) => Some(0),
+        Expr::UnaryOp(op) if op.op == UnaryOp::Neg => length_dim(&op.operand),
+        Expr::BinOp(op) => {
+            let (left, right) = (length_dim(&op.left)?, length_dim(&op.right)?);
+            match op.op {
+                BinOp::Add | BinOp::Sub | BinOp::Rem => (left == right).then_some(left),
+                BinOp::Mul => Some(left + right),
+                BinOp::Div => Some(left - right),
+            }
+        }
+        _ => None,
+    }
 }
 
 impl<'a> VarIdTyPass<'a> {
@@ -666,13 +892,20 @@ impl<'a> VarIdTyPass<'a> {
 
     fn execute(&mut self) -> AnnotatedAst<VarIdTyMetadata> {
         let mut decls = Vec::new();
+        self.declare_consts();
+        // Types are declared first so that functions can take them as arguments.
         for decl in &self.ast.ast.decls {
             match decl {
-                Decl::Fn(f) => self.declare_fn_decl(f),
                 Decl::Enum(e) => self.declare_enum_decl(e),
+                Decl::Struct(s) => self.declare_struct_decl(s),
                 _ => (),
             }
         }
+        for decl in &self.ast.ast.decls {
+            if let Decl::Fn(f) = decl {
+                self.declare_fn_decl(f);
+            }
+        }
 
         for decl in &self.ast.ast.decls {
             match decl {
@@ -688,7 +921,19 @@ impl<'a> VarIdTyPass<'a> {
                 Decl::Enum(e) => {
                     decls.push(Decl::Enum(self.transform_enum_decl(e)));
                 }
-                _ => todo!(),
+                Decl::Struct(s) => {
+                    decls.push(Decl::Struct(self.transform_struct_decl(s)));
+                }
+                Decl::Constant(c) => {
+                    // Invalid and duplicate constants have already been reported.
+                    if self
+                        .consts
+                        .get(c.name.name.as_str())
+                        .is_some_and(|constant| constant.span.span == c.name.span)
+                    {
+                        decls.push(Decl::Constant(self.transform_constant_decl(c)));
+                    }
+                }
             }
         }
 
@@ -728,6 +973,31 @@ impl<'a> VarIdTyPass<'a> {
         self.alloc(&input.name.name, ty);
     }
 
+    /// Evaluates the module's constants and declares those with valid values.
+    fn declare_consts(&mut self) {
+        let (consts, errors) = eval_consts(self.ast);
+        self.errors.extend(errors);
+        for decl in &self.ast.ast.decls {
+            let Decl::Constant(c) = decl else {
+                continue;
+            };
+            if BUILTINS.contains(&c.name.name.as_str()) {
+                self.errors.push(StaticError {
+                    span: self.span(c.name.span),
+                    kind: StaticErrorKind::RedeclarationOfBuiltin,
+                });
+                continue;
+            }
+            if let Some(constant) = consts.get(c.name.name.as_str())
+                && constant.span.span == c.name.span
+            {
+                self.alloc(&c.name.name, constant.value.ty());
+                self.consts
+                    .insert(c.name.name.to_string(), constant.clone());
+            }
+        }
+    }
+
     fn declare_enum_decl(&mut self, input: &'a EnumDecl<Substr, ParseMetadata>) {
         if BUILTINS.contains(&input.name.name.as_str()) {
             self.errors.push(StaticError {
@@ -736,23 +1006,196 @@ impl<'a> VarIdTyPass<'a> {
             });
             return;
         }
+        let mut fields = IndexMap::with_capacity(input.fields.len());
+        for field in input.fields.iter() {
+            if fields.contains_key(field.name.as_str()) {
+                self.errors.push(StaticError {
+                    span: self.span(field.name.span),
+                    kind: StaticErrorKind::DuplicateNameDeclaration,
+                });
+            }
+            // Associated data must be a compile-time constant.
+            let ty = match Ty::from_name(field.ty.name.as_str()) {
+                Some(ty @ (Ty::Bool | Ty::Int | Ty::Float | Ty::String)) => ty,
+                _ => {
+                    self.errors.push(StaticError {
+                        span: self.span(field.ty.span),
+                        kind: StaticErrorKind::UnknownType,
+                    });
+                    Ty::Unknown
+                }
+            };
+            fields.insert(field.name.to_string(), ty);
+        }
         let mut variants = IndexSet::with_capacity(input.variants.len());
+        let mut data = IndexMap::with_capacity(input.variants.len());
+        let mut discriminants = IndexSet::with_capacity(input.variants.len());
+        let mut next_discriminant = 0;
         for variant in input.variants.iter() {
-            if variants.contains(variant.name.as_str()) {
+            if variants.contains(variant.name.name.as_str()) {
                 self.errors.push(StaticError {
-                    span: self.span(variant.span),
+                    span: self.span(variant.name.span),
                     kind: StaticErrorKind::DuplicateNameDeclaration,
                 });
             }
             variants.insert(variant.name.to_string());
+            // Variants without an explicit discriminant follow the previous variant.
+            let discriminant = match variant
+                .discriminant
+                .as_ref()
+                .and_then(|expr| self.eval_const(expr, &Ty::Int))
+            {
+                Some(ConstValue::Int(discriminant)) => discriminant,
+                _ => next_discriminant,
+            };
+            if !discriminants.insert(discriminant) {
+                self.errors.push(StaticError {
+                    span: self.span(variant.name.span),
+                    kind: StaticErrorKind::DuplicateDiscriminant(discriminant),
+                });
+            }
+            next_discriminant = discriminant + 1;
+            if variant.values.len() != fields.len() {
+                self.errors.push(StaticError {
+                    span: self.span(variant.span),
+                    kind: StaticErrorKind::CallIncorrectPositionalArity {
+                        expected: fields.len(),
+                        found: variant.values.len(),
+                    },
+                });
+            }
+            let values = fields
+                .iter()
+                .zip(&variant.values)
+                .filter_map(|((name, ty), expr)| Some((name.clone(), self.eval_const(expr, ty)?)))
+                .collect();
+            data.insert(
+                variant.name.to_string(),
+                EnumVariantData {
+                    discriminant,
+                    fields: values,
+                },
+            );
         }
+        let id = self.alloc_id();
+        self.enums.insert(id, EnumData { id, variants: data });
         let ty = Ty::Enum(EnumTy {
-            id: self.alloc_id(),
+            id,
             variants,
+            fields,
         });
         self.alloc(&input.name.name, ty);
     }
 
+    fn declare_struct_decl(&mut self, input: &'a StructDecl<Substr, ParseMetadata>) {
+        if BUILTINS.contains(&input.name.name.as_str()) {
+            self.errors.push(StaticError {
+                span: self.span(input.name.span),
+                kind: StaticErrorKind::RedeclarationOfBuiltin,
+            });
+            return;
+        }
+        let mut fields = IndexMap::with_capacity(input.fields.len());
+        for field in input.fields.iter() {
+            if fields.contains_key(field.name.as_str()) {
+                self.errors.push(StaticError {
+                    span: self.span(field.name.span),
+                    kind: StaticErrorKind::DuplicateNameDeclaration,
+                });
+            }
+            // Fields may be builtin types or previously declared enums and structs.
+            let ty = Ty::from_name(field.ty.name.as_str())
+                .or_else(|| {
+                    self.lookup(field.ty.name.as_str())
+                        .map(|(_, ty)| ty)
+                        .filter(|ty| matches!(ty, Ty::Enum(_) | Ty::Struct(_)))
+                })
+                .unwrap_or_else(|| {
+                    self.errors.push(StaticError {
+                        span: self.span(field.ty.span),
+                        kind: StaticErrorKind::UnknownType,
+                    });
+                    Ty::Unknown
+                });
+            fields.insert(field.name.to_string(), ty);
+        }
+        // The struct's ID doubles as the variable ID of its name, so that calls
+        // to the name can be told apart from calls to values of the struct type.
+        let id = self.alloc_id();
+        let ty = Ty::Struct(StructTy {
+            id,
+            name: input.name.to_string(),
+            fields,
+        });
+        self.bindings
+            .last_mut()
+            .unwrap()
+            .var_bindings
+            .insert(input.name.name.clone(), (id, ty));
+    }
+
+    /// Evaluates `expr` as a compile-time constant of type `ty`, reporting an
+    /// error if it is not one.
+    fn eval_const(&mut self, expr: &Expr<Substr, ParseMetadata>, ty: &Ty) -> Option<ConstValue> {
+        match eval_const_expr(expr, &self.consts) {
+            Ok(value) if value.ty() == *ty => Some(value),
+            Ok(value) => {
+                if *ty != Ty::Unknown {
+                    self.errors.push(StaticError {
+                        span: self.span(expr.span()),
+                        kind: StaticErrorKind::IncorrectTy {
+                            expected: ty.clone(),
+                            found: value.ty(),
+                        },
+                    });
+                }
+                None
+            }
+            Err((span, kind)) => {
+                self.errors.push(StaticError {
+                    span: self.span(span),
+                    kind,
+                });
+                None
+            }
+        }
+    }
+
+    /// Looks up the enum named by all but the last segment of `path`, e.g.
+    /// `MyEnum` in `MyEnum::Variant` or `crate::a::MyEnum::variants`.
+    fn lookup_enum<M: AstMetadata>(&self, path: &[Ident<Substr, M>]) -> Option<(VarId, Ty)> {
+        let mod_path = match path[0].name.as_str() {
+            "std" => {
+                vec!["std".to_string()]
+            }
+            "crate" => path
+                .iter()
+                .skip(1)
+                .dropping_back(2)
+                .map(|ident| ident.name.to_string())
+                .collect_vec(),
+            _ => self
+                .current_path
+                .iter()
+                .cloned()
+                .chain(
+                    path.iter()
+                        .dropping_back(2)
+                        .map(|ident| ident.name.to_string()),
+                )
+                .collect_vec(),
+        };
+        let enum_ = &path[path.len() - 2];
+        if mod_path.is_empty() || &mod_path == self.current_path {
+            self.lookup(&enum_.name)
+        } else {
+            self.mod_bindings
+                .get(&mod_path)
+                .as_ref()
+                .and_then(|mod_binding| mod_binding.var_bindings.get(enum_.name.as_str()).cloned())
+        }
+    }
+
     fn ty_from_spec<M: AstMetadata>(&mut self, spec: &TySpec<Substr, M>) -> Ty {
         match &spec.kind {
             TySpecKind::Ident(ident) => Ty::from_name(ident.name.as_str()).unwrap_or_else(|| {
@@ -806,6 +1249,15 @@ impl<'a> VarIdTyPass<'a> {
             return VarIdTyPass::is_eq_ty(a, b);
         }
 
+        // Instances (and cells) of different cells differ only in the fields
+        // they expose, so an `Inst` annotation accepts any of them.
+        if matches!(
+            (a, b),
+            (Ty::Inst(_), Ty::Inst(_)) | (Ty::Cell(_), Ty::Cell(_))
+        ) {
+            return true;
+        }
+
         if let Ty::Tuple(a) = a
             && let Ty::Tuple(b) = b
         {
@@ -833,6 +1285,43 @@ impl<'a> VarIdTyPass<'a> {
         }
     }
 
+    /// Reports operands built from literals whose powers of length differ,
+    /// such as a length literal added to a unitless one.
+    fn check_units<M: AstMetadata>(
+        &mut self,
+        span: cfgrammar::Span,
+        left: &Expr<Substr, M>,
+        right: &Expr<Substr, M>,
+    ) {
+        if let (Some(left), Some(right)) = (length_dim(left), length_dim(right))
+            && left != right
+        {
+            self.errors.push(StaticError {
+                span: self.span(span),
+                kind: StaticErrorKind::MixedUnits { left, right },
+            });
+        }
+    }
+
+    /// Returns the type of an argument of a numeric builtin, reporting an error
+    /// unless it is an `Int` or a `Float`.
+    fn numeric_ty(&mut self, arg: &Expr<Substr, VarIdTyMetadata>) -> Ty {
+        let ty = arg.ty();
+        match ty {
+            Ty::Int | Ty::Float | Ty::Any | Ty::Unknown => ty,
+            _ => {
+                self.errors.push(StaticError {
+                    span: self.span(arg.span()),
+                    kind: StaticErrorKind::IncorrectTyCategory {
+                        found: ty,
+                        expected: "Int/Float".to_string(),
+                    },
+                });
+                Ty::Unknown
+            }
+        }
+    }
+
     fn assert_ty_is_cell(&mut self, span: cfgrammar::Span, ty: &Ty) {
         if !matches!(ty, Ty::Cell(_) | Ty::Any) {
             self.errors.push(StaticError {
@@ -922,6 +1411,41 @@ impl<'a> VarIdTyPass<'a> {
         self.typecheck_kwargs(&args.kwargs, kwarg_defs);
     }
 
+    /// Type checks the fields of a struct literal, which may be given
+    /// positionally in declaration order, by name, or both.
+    fn typecheck_struct_args(
+        &mut self,
+        call_span: cfgrammar::Span,
+        args: &crate::ast::Args<Substr, VarIdTyMetadata>,
+        ty: &StructTy,
+    ) {
+        let field_tys = ty.fields.values().cloned().collect_vec();
+        if args.posargs.len() > field_tys.len() {
+            self.assert_eq_arity(call_span, args.posargs.len(), field_tys.len());
+        }
+        for (found, expected) in args.posargs.iter().zip(&field_tys) {
+            self.assert_eq_ty(found.span(), &found.ty(), expected);
+        }
+        let kwarg_defs = ty
+            .fields
+            .iter()
+            .skip(args.posargs.len())
+            .map(|(name, ty)| (name.as_str(), ty.clone()))
+            .collect::<IndexMap<_, _>>();
+        let missing = kwarg_defs
+            .keys()
+            .filter(|name| !args.kwargs.iter().any(|kwarg| kwarg.name.name == **name))
+            .map(|name| name.to_string())
+            .collect_vec();
+        self.typecheck_kwargs(&args.kwargs, kwarg_defs);
+        if !missing.is_empty() {
+            self.errors.push(StaticError {
+                span: self.span(call_span),
+                kind: StaticErrorKind::MissingFields(missing),
+            });
+        }
+    }
+
     fn typecheck_call(
         &mut self,
         lookup: Option<(VarId, Ty)>,
@@ -938,6 +1462,11 @@ impl<'a> VarIdTyPass<'a> {
                     self.typecheck_args(call_span, args, &ty.args, IndexMap::new());
                     (Some(varid), Ty::Cell(ty.cell.clone()))
                 }
+                // A struct literal, e.g. `Point(1., y=2.)`.
+                Ty::Struct(ty) if ty.id == varid => {
+                    self.typecheck_struct_args(call_span, args, &ty);
+                    (None, Ty::Struct(ty))
+                }
                 ty => {
                     self.errors.push(StaticError {
                         span: self.span(call_span),
@@ -981,6 +1510,8 @@ impl<S> Expr<S, VarIdTyMetadata> {
             Expr::Cast(cast) => cast.metadata.clone(),
             Expr::UnaryOp(unary_op_expr) => unary_op_expr.metadata.clone(),
             Expr::Tuple(t) => t.metadata.clone(),
+            Expr::Range(r) => r.metadata.clone(),
+            Expr::Seq(s) => s.metadata.clone(),
         }
     }
 }
@@ -1015,43 +1546,8 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                 (None, Ty::Unknown)
             }
         } else {
-            // look up enum
-            let path = match input.path[0].name.as_str() {
-                "std" => {
-                    vec!["std".to_string()]
-                }
-                "crate" => input
-                    .path
-                    .iter()
-                    .skip(1)
-                    .dropping_back(2)
-                    .map(|ident| ident.name.to_string())
-                    .collect_vec(),
-                _ => self
-                    .current_path
-                    .iter()
-                    .cloned()
-                    .chain(
-                        input
-                            .path
-                            .iter()
-                            .dropping_back(2)
-                            .map(|ident| ident.name.to_string()),
-                    )
-                    .collect_vec(),
-            };
             let enum_ = &input.path[input.path.len() - 2];
-            let lookup = if path.is_empty() || &path == self.current_path {
-                self.lookup(&enum_.name)
-            } else {
-                self.mod_bindings
-                    .get(&path)
-                    .as_ref()
-                    .and_then(|mod_binding| {
-                        mod_binding.var_bindings.get(enum_.name.as_str()).cloned()
-                    })
-            };
-            if let Some((_, ty)) = lookup {
+            if let Some((_, ty)) = self.lookup_enum(&input.path) {
                 if let Ty::Enum(ref e) = ty {
                     let variant = &input.path.last().unwrap().name;
                     if !e.variants.contains(variant.as_str()) {
@@ -1080,10 +1576,31 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
 
     fn dispatch_enum_decl(
         &mut self,
-        _input: &crate::ast::EnumDecl<Substr, Self::InputMetadata>,
+        input: &crate::ast::EnumDecl<Substr, Self::InputMetadata>,
         _name: &Ident<Substr, Self::OutputMetadata>,
-        _variants: &[Ident<Substr, Self::OutputMetadata>],
+        _fields: &[StructField<Substr, Self::OutputMetadata>],
+        _variants: &[EnumVariant<Substr, Self::OutputMetadata>],
     ) -> <Self::OutputMetadata as AstMetadata>::EnumDecl {
+        match self.lookup(&input.name.name) {
+            Some((_, Ty::Enum(e))) => self.enums.get(&e.id).cloned().unwrap_or_default(),
+            _ => EnumData::default(),
+        }
+    }
+
+    fn dispatch_struct_decl(
+        &mut self,
+        _input: &StructDecl<Substr, Self::InputMetadata>,
+        _name: &Ident<Substr, Self::OutputMetadata>,
+        _fields: &[StructField<Substr, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::StructDecl {
+    }
+
+    fn dispatch_struct_field(
+        &mut self,
+        _input: &StructField<Substr, Self::InputMetadata>,
+        _name: &Ident<Substr, Self::OutputMetadata>,
+        _ty: &Ident<Substr, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::StructField {
     }
 
     fn dispatch_cell_decl(
@@ -1143,6 +1660,7 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         let metadata = self.dispatch_scope(&input.scope, &stmts, &tail);
         let scope = Scope {
             scope_annotation,
+            construction: input.scope.construction,
             span: input.span,
             stmts,
             tail,
@@ -1182,12 +1700,22 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
             .scope_annotation
             .as_ref()
             .map(|ident| self.transform_ident(ident));
-        let stmts = input
-            .scope
-            .stmts
-            .iter()
-            .map(|stmt| self.transform_statement(stmt))
-            .collect_vec();
+        let stmts = match &input.bbox {
+            Some(bbox) => vec![self.transform_statement(&extern_cell_stmt(bbox))],
+            None => input
+                .scope
+                .stmts
+                .iter()
+                .map(|stmt| self.transform_statement(stmt))
+                .collect_vec(),
+        };
+        let bbox = input.bbox.as_ref().map(|_| match &stmts[0] {
+            Statement::Expr {
+                value: Expr::Call(call),
+                ..
+            } => call.args.clone(),
+            _ => unreachable!("extern cell body is a single call"),
+        });
         let tail = input
             .scope
             .tail
@@ -1196,6 +1724,7 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         let metadata = self.dispatch_scope(&input.scope, &stmts, &tail);
         let scope = Scope {
             scope_annotation,
+            construction: input.scope.construction,
             span: input.scope.span,
             stmts,
             tail,
@@ -1236,6 +1765,7 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
             name,
             scope,
             args,
+            bbox,
             span: input.span,
             metadata,
         }
@@ -1272,11 +1802,14 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
 
     fn dispatch_constant_decl(
         &mut self,
-        _input: &ConstantDecl<Substr, Self::InputMetadata>,
+        input: &ConstantDecl<Substr, Self::InputMetadata>,
         _name: &Ident<Substr, Self::OutputMetadata>,
         _ty: &Ident<Substr, Self::OutputMetadata>,
         _value: &Expr<Substr, Self::OutputMetadata>,
     ) -> <Self::OutputMetadata as AstMetadata>::ConstantDecl {
+        let name = input.name.name.as_str();
+        let (id, _) = self.lookup(name).unwrap();
+        (id, self.consts[name].value.clone())
     }
 
     fn dispatch_if_expr(
@@ -1308,7 +1841,18 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                 kind: StaticErrorKind::IfCondNotBool,
             });
         }
-        then_ty.lub(&else_ty)
+        let ty = then_ty.lub(&else_ty);
+        if !self.discarded_ifs.swap_remove(&input.span)
+            && ty == Ty::Any
+            && ![&then_ty, &else_ty].contains(&&Ty::Any)
+            && !VarIdTyPass::is_eq_ty(&then_ty, &else_ty)
+        {
+            self.errors.push(StaticError {
+                span: self.span(input.span),
+                kind: StaticErrorKind::BranchesDifferentTypes(then_ty, else_ty),
+            });
+        }
+        ty
     }
 
     fn dispatch_match_expr(
@@ -1324,16 +1868,36 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         if let Ty::Enum(ref e) = scrutinee_ty {
             let mut covered = IndexSet::new();
             let mut remaining = e.variants.clone();
+            let mut wildcard = false;
             for arm in arms.iter() {
-                let arm_ty = &arm.pattern.metadata.1;
-                self.assert_eq_ty(arm.pattern.span, arm_ty, &scrutinee_ty);
-
-                let variant = arm.pattern.path.last().unwrap().name.clone();
-                remaining.swap_remove(variant.as_str());
-                if !covered.insert(variant) {
+                // Arms after a wildcard can never be taken.
+                let unreachable = wildcard;
+                let (span, duplicate) = match &arm.pattern {
+                    Some(pattern) => {
+                        let arm_ty = &pattern.metadata.1;
+                        self.assert_eq_ty(pattern.span, arm_ty, &scrutinee_ty);
+
+                        let variant = pattern.path.last().unwrap().name.clone();
+                        remaining.swap_remove(variant.as_str());
+                        (pattern.span, !covered.insert(variant))
+                    }
+                    None => {
+                        remaining.clear();
+                        wildcard = true;
+                        (arm.span, false)
+                    }
+                };
+                let kind = if unreachable {
+                    Some(StaticErrorKind::UnreachableMatchArm)
+                } else if duplicate {
+                    Some(StaticErrorKind::DuplicateMatchArm)
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
                     self.errors.push(StaticError {
-                        span: self.span(arm.pattern.span),
-                        kind: StaticErrorKind::DuplicateMatchArm,
+                        span: self.span(span),
+                        kind,
                     });
                 }
 
@@ -1361,6 +1925,9 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         left: &Expr<Substr, Self::OutputMetadata>,
         right: &Expr<Substr, Self::OutputMetadata>,
     ) -> <Self::OutputMetadata as AstMetadata>::BinOpExpr {
+        if matches!(input.op, BinOp::Add | BinOp::Sub | BinOp::Rem) {
+            self.check_units(input.span, left, right);
+        }
         let left_ty = left.ty();
         let right_ty = right.ty();
         if !VarIdTyPass::is_eq_ty(&left_ty, &right_ty) {
@@ -1416,6 +1983,7 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         left: &Expr<Substr, Self::OutputMetadata>,
         right: &Expr<Substr, Self::OutputMetadata>,
     ) -> <Self::OutputMetadata as AstMetadata>::ComparisonExpr {
+        self.check_units(input.span, left, right);
         let left_ty = left.ty();
         let right_ty = right.ty();
         let lub_ty = left_ty.lub(&right_ty);
@@ -1488,12 +2056,28 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
             },
             Ty::Inst(ref c) => match field.name.as_str() {
                 "x" | "y" => Ty::Float,
+                "bbox" => Ty::Rect,
                 name => c
                     .data
                     .get(name)
                     .cloned()
                     .unwrap_or_else(|| self.no_field_on_ty(field, base_ty.clone())),
             },
+            Ty::Cell(_) => match field.name.as_str() {
+                "width" | "height" => Ty::Float,
+                "bbox" => Ty::Rect,
+                _ => self.no_field_on_ty(field, base_ty.clone()),
+            },
+            Ty::Enum(ref e) => e
+                .fields
+                .get(field.name.as_str())
+                .cloned()
+                .unwrap_or_else(|| self.no_field_on_ty(field, base_ty.clone())),
+            Ty::Struct(ref s) => s
+                .fields
+                .get(field.name.as_str())
+                .cloned()
+                .unwrap_or_else(|| self.no_field_on_ty(field, base_ty.clone())),
             // Propagate any and unknown types without throwing an error.
             Ty::Any => Ty::Any,
             Ty::Unknown => Ty::Unknown,
@@ -1575,8 +2159,16 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                 .scope_bindings
                 .insert(scope_annotation.name.clone());
         }
-        if func.path.len() == 1 {
-            match func.path[0].name.as_str() {
+        // Builtins may also be called through `std`, e.g. `std::max`.
+        let builtin = match func.path.as_slice() {
+            [name] => Some(name),
+            [std, name] if std.name == "std" && BUILTINS.contains(&name.name.as_str()) => {
+                Some(name)
+            }
+            _ => None,
+        };
+        if let Some(builtin) = builtin {
+            match builtin.name.as_str() {
                 name @ "crect" | name @ "rect" => {
                     let kwarg_defs = if name == "crect" {
                         self.typecheck_posargs(input.span, &args.posargs, &[]);
@@ -1621,8 +2213,37 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                     self.typecheck_kwargs(&args.kwargs, IndexMap::default());
                     (None, Ty::Nil)
                 }
-                "cons" => {
-                    self.assert_eq_arity(input.span, args.posargs.len(), 2);
+                "pin" => {
+                    // layer, name, rect
+                    self.typecheck_posargs(
+                        input.span,
+                        &args.posargs,
+                        &[Ty::String, Ty::String, Ty::Rect],
+                    );
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
+                    (None, Ty::Nil)
+                }
+                "polygon" => {
+                    // layer, points=[(x, y), ...]
+                    self.typecheck_posargs(input.span, &args.posargs, &[Ty::String]);
+                    self.typecheck_kwargs(
+                        &args.kwargs,
+                        IndexMap::from_iter([(
+                            "points",
+                            Ty::Seq(Box::new(Ty::Tuple(vec![Ty::Float, Ty::Float]))),
+                        )]),
+                    );
+                    if !args.kwargs.iter().any(|kwarg| kwarg.name.name == "points") {
+                        self.errors.push(StaticError {
+                            span: self.span(input.span),
+                            kind: StaticErrorKind::MissingKwArg("points".to_string()),
+                        });
+                    }
+                    (None, Ty::Nil)
+                }
+                "cons" => {
+                    self.assert_eq_arity(input.span, args.posargs.len(), 2);
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
                     if args.posargs.len() == 2 {
                         let seqty = Ty::Seq(Box::new(args.posargs[0].ty()));
                         let tailty = args.posargs[1].ty();
@@ -1665,8 +2286,26 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                     self.typecheck_kwargs(&args.kwargs, IndexMap::default());
                     (None, Ty::Seq(Box::new(Ty::Int)))
                 }
+                "len" => {
+                    self.assert_eq_arity(input.span, args.posargs.len(), 1);
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
+                    if let Some(arg) = args.posargs.first() {
+                        let argty = arg.ty();
+                        if !matches!(argty, Ty::Seq(_) | Ty::SeqNil | Ty::Any | Ty::Unknown) {
+                            self.errors.push(StaticError {
+                                span: self.span(input.span),
+                                kind: StaticErrorKind::IncorrectTyCategory {
+                                    found: argty,
+                                    expected: "Seq".to_string(),
+                                },
+                            });
+                        }
+                    }
+                    (None, Ty::Int)
+                }
                 "head" => {
                     self.assert_eq_arity(input.span, args.posargs.len(), 1);
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
                     if args.posargs.len() == 1 {
                         let argty = args.posargs[0].ty();
                         let vty = match argty {
@@ -1691,6 +2330,7 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                 }
                 "tail" => {
                     self.assert_eq_arity(input.span, args.posargs.len(), 1);
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
                     if args.posargs.len() == 1 {
                         let argty = args.posargs[0].ty();
                         let vty = match argty {
@@ -1715,22 +2355,85 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                 }
                 "bbox" => {
                     self.assert_eq_arity(input.span, args.posargs.len(), 1);
-                    let argty = args.posargs[0].ty();
-                    if !matches!(argty, Ty::Cell(_) | Ty::Inst(_)) {
-                        self.errors.push(StaticError {
-                            span: self.span(input.span),
-                            kind: StaticErrorKind::IncorrectTyCategory {
-                                found: argty,
-                                expected: "Cell/Inst".to_string(),
-                            },
-                        });
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
+                    if let Some(arg) = args.posargs.first() {
+                        let argty = arg.ty();
+                        if !matches!(argty, Ty::Cell(_) | Ty::Inst(_) | Ty::Any | Ty::Unknown) {
+                            self.errors.push(StaticError {
+                                span: self.span(input.span),
+                                kind: StaticErrorKind::IncorrectTyCategory {
+                                    found: argty,
+                                    expected: "Cell/Inst".to_string(),
+                                },
+                            });
+                        }
                     }
                     (None, Ty::Rect)
                 }
+                "merge" => {
+                    self.typecheck_args(
+                        input.span,
+                        args,
+                        &[Ty::Seq(Box::new(Ty::Rect))],
+                        IndexMap::new(),
+                    );
+                    (None, Ty::Nil)
+                }
+                "subtract" => {
+                    self.typecheck_args(input.span, args, &[Ty::Rect, Ty::Rect], IndexMap::new());
+                    (None, Ty::Nil)
+                }
+                "align_left" | "align_right" | "align_bottom" | "align_top" | "align_center_x"
+                | "align_center_y" => {
+                    // Any number of rects, but at least two.
+                    if args.posargs.len() < 2 {
+                        self.assert_eq_arity(input.span, args.posargs.len(), 2);
+                    }
+                    for arg in &args.posargs {
+                        self.assert_eq_ty(arg.span(), &arg.ty(), &Ty::Rect);
+                    }
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
+                    (None, Ty::Nil)
+                }
+                "distribute_x" | "distribute_y" => {
+                    self.typecheck_args(
+                        input.span,
+                        args,
+                        &[Ty::Seq(Box::new(Ty::Rect)), Ty::Float],
+                        IndexMap::new(),
+                    );
+                    (None, Ty::Nil)
+                }
                 "float" => {
                     self.typecheck_args(input.span, args, &[], IndexMap::new());
                     (None, Ty::Float)
                 }
+                "min" | "max" => {
+                    self.assert_eq_arity(input.span, args.posargs.len(), 2);
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
+                    if let [a, b] = args.posargs.as_slice() {
+                        let ty = self.numeric_ty(a);
+                        if ty != Ty::Unknown {
+                            self.assert_eq_ty(b.span(), &b.ty(), &ty);
+                        }
+                        (None, ty)
+                    } else {
+                        (None, Ty::Unknown)
+                    }
+                }
+                "abs" => {
+                    self.assert_eq_arity(input.span, args.posargs.len(), 1);
+                    self.typecheck_kwargs(&args.kwargs, IndexMap::default());
+                    if let Some(arg) = args.posargs.first() {
+                        (None, self.numeric_ty(arg))
+                    } else {
+                        (None, Ty::Unknown)
+                    }
+                }
+                "floor" | "ceil" | "round" => {
+                    self.typecheck_args(input.span, args, &[Ty::Float], IndexMap::new());
+                    (None, Ty::Float)
+                }
                 "eq" => {
                     self.typecheck_args(input.span, args, &[Ty::Float, Ty::Float], IndexMap::new());
                     (None, Ty::Nil)
@@ -1752,20 +2455,34 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                     );
                     (None, Ty::Nil)
                 }
-                "inst" => {
+                f @ ("inst" | "inst_array") => {
                     self.assert_eq_arity(input.span, args.posargs.len(), 1);
-                    self.typecheck_kwargs(
-                        &args.kwargs,
-                        IndexMap::from_iter([
-                            ("reflect", Ty::Bool),
-                            ("angle", Ty::Int),
-                            ("x", Ty::Float),
-                            ("y", Ty::Float),
-                            ("xi", Ty::Float),
-                            ("yi", Ty::Float),
-                            ("construction", Ty::Bool),
-                        ]),
-                    );
+                    // Angles may be given in whole degrees or as floats.
+                    let angle_ty = args
+                        .kwargs
+                        .iter()
+                        .find(|kwarg| kwarg.name.name == "angle")
+                        .map(|kwarg| kwarg.value.ty())
+                        .filter(|ty| *ty == Ty::Float)
+                        .unwrap_or(Ty::Int);
+                    let mut kwargs = IndexMap::from_iter([
+                        ("reflect", Ty::Bool),
+                        ("angle", angle_ty),
+                        ("x", Ty::Float),
+                        ("y", Ty::Float),
+                        ("xi", Ty::Float),
+                        ("yi", Ty::Float),
+                        ("construction", Ty::Bool),
+                    ]);
+                    if f == "inst_array" {
+                        kwargs.extend([
+                            ("nx", Ty::Int),
+                            ("ny", Ty::Int),
+                            ("dx", Ty::Float),
+                            ("dy", Ty::Float),
+                        ]);
+                    }
+                    self.typecheck_kwargs(&args.kwargs, kwargs);
                     if let Some(ty) = args.posargs.first() {
                         self.assert_ty_is_cell(ty.span(), &ty.ty());
                         match ty.ty() {
@@ -1779,6 +2496,11 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
                 }
                 name => self.typecheck_call(self.lookup(name), input.span, args),
             }
+        } else if func.path.last().unwrap().name == "variants"
+            && let Some((_, ty @ Ty::Enum(_))) = self.lookup_enum(&func.path)
+        {
+            self.typecheck_args(input.span, args, &[], IndexMap::new());
+            (None, Ty::Seq(Box::new(ty)))
         } else {
             let path = match func.path[0].name.as_str() {
                 "std" => {
@@ -1840,7 +2562,8 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
             (Ty::Int, Ty::Float)
             | (Ty::Int, Ty::Int)
             | (Ty::Float, Ty::Int)
-            | (Ty::Float, Ty::Float) => (),
+            | (Ty::Float, Ty::Float)
+            | (Ty::Enum(_), Ty::Int) => (),
             (_, Ty::Unknown) => (),
             (Ty::Any, _) | (_, Ty::Any) => (),
             _ => {
@@ -1861,6 +2584,31 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         Ty::Tuple(items.iter().map(|i| i.ty()).collect())
     }
 
+    fn dispatch_range_expr(
+        &mut self,
+        _input: &crate::ast::RangeExpr<Self::InputS, Self::InputMetadata>,
+        start: &Expr<Self::OutputS, Self::OutputMetadata>,
+        end: &Expr<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::RangeExpr {
+        self.assert_eq_ty(start.span(), &start.ty(), &Ty::Int);
+        self.assert_eq_ty(end.span(), &end.ty(), &Ty::Int);
+        Ty::Seq(Box::new(Ty::Int))
+    }
+
+    fn dispatch_seq_expr(
+        &mut self,
+        _input: &crate::ast::SeqExpr<Self::InputS, Self::InputMetadata>,
+        items: &[Expr<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::SeqExpr {
+        // Like `list(...)`, the element type is the least upper bound of the items.
+        let elem_ty = items
+            .iter()
+            .map(Expr::ty)
+            .reduce(|acc, e| acc.lub(&e))
+            .unwrap_or(Ty::Unknown);
+        Ty::Seq(Box::new(elem_ty))
+    }
+
     fn dispatch_kw_arg_value(
         &mut self,
         _input: &crate::ast::KwArgValue<Substr, Self::InputMetadata>,
@@ -1957,6 +2705,9 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
             .iter()
             .map(|stmt| self.transform_statement(stmt))
             .collect_vec();
+        if let Some(Expr::If(if_)) = &input.body.tail {
+            self.discarded_ifs.insert(if_.span);
+        }
         let tail = input
             .body
             .tail
@@ -1965,6 +2716,7 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         let metadata = self.dispatch_scope(&input.body, &stmts, &tail);
         let body = Scope {
             scope_annotation,
+            construction: input.body.construction,
             span: input.span,
             stmts,
             tail,
@@ -1990,17 +2742,74 @@ impl<'a> AstTransformer for VarIdTyPass<'a> {
         unreachable!()
     }
 
+    fn transform_statement(
+        &mut self,
+        input: &Statement<Self::InputS, Self::InputMetadata>,
+    ) -> Statement<Self::OutputS, Self::OutputMetadata> {
+        match input {
+            Statement::Expr { value, semicolon } => {
+                if let Expr::If(if_) = value {
+                    self.discarded_ifs.insert(if_.span);
+                }
+                Statement::Expr {
+                    value: self.transform_expr(value),
+                    semicolon: *semicolon,
+                }
+            }
+            Statement::LetBinding(l) => Statement::LetBinding(self.transform_let_binding(l)),
+            Statement::ForLoop(l) => Statement::ForLoop(self.transform_for_loop(l)),
+        }
+    }
+
     fn transform_s(&mut self, s: &Self::InputS) -> Self::OutputS {
         s.clone()
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CellArg {
     Float(f64),
     Int(i64),
     Bool(bool),
+    /// A variant of an enum, e.g. `Flavor::Lvt`.
+    Enum {
+        id: EnumId,
+        name: String,
+        variant: String,
+    },
     Seq(Vec<CellArg>),
+    /// A value of a user-defined struct, e.g. `Point(x=1., y=2.)`.
+    Struct {
+        id: StructId,
+        name: String,
+        fields: IndexMap<String, CellArg>,
+    },
+}
+
+impl std::fmt::Display for CellArg {
+    /// Formats the argument as Argon source.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellArg::Float(x) => write!(f, "{x:?}"),
+            CellArg::Int(i) => write!(f, "{i}"),
+            CellArg::Bool(b) => write!(f, "{b}"),
+            CellArg::Enum { name, variant, .. } => write!(f, "{name}::{variant}"),
+            CellArg::Seq(v) => {
+                for arg in v {
+                    write!(f, "cons({arg}, ")?;
+                }
+                write!(f, "[]{}", ")".repeat(v.len()))
+            }
+            CellArg::Struct { name, fields, .. } => write!(
+                f,
+                "{name}({})",
+                fields
+                    .iter()
+                    .map(|(field, arg)| format!("{field}={arg}"))
+                    .join(", ")
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -2015,7 +2824,9 @@ enum CellArgKey {
     Float(u64),
     Int(i64),
     Bool(bool),
+    Enum(EnumId, String),
     Seq(Vec<CellArgKey>),
+    Struct(StructId, Vec<CellArgKey>),
 }
 
 impl From<&CellArg> for CellArgKey {
@@ -2024,7 +2835,11 @@ impl From<&CellArg> for CellArgKey {
             CellArg::Float(f) => Self::Float(f.to_bits()),
             CellArg::Int(i) => Self::Int(*i),
             CellArg::Bool(b) => Self::Bool(*b),
+            CellArg::Enum { id, variant, .. } => Self::Enum(*id, variant.clone()),
             CellArg::Seq(v) => Self::Seq(v.iter().map(Self::from).collect()),
+            CellArg::Struct { id, fields, .. } => {
+                Self::Struct(*id, fields.values().map(Self::from).collect())
+            }
         }
     }
 }
@@ -2035,6 +2850,8 @@ pub struct CompileInput<'a> {
     pub cell: &'a [&'a str],
     pub args: Vec<CellArg>,
     pub lyp_file: &'a Path,
+    /// Options of the constraint solver, usually from `[solver]` in `Argon.toml`.
+    pub solver: SolverConfig,
 }
 
 pub type VarId = u64;
@@ -2081,6 +2898,66 @@ pub struct Dimension<T> {
     pub span: Option<Span>,
 }
 
+/// A polygon whose edges connect consecutive vertices, and the last vertex
+/// back to the first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Polygon<T> {
+    pub id: ObjectId,
+    pub layer: String,
+    pub points: Vec<(T, T)>,
+    pub construction: bool,
+    pub span: Option<Span>,
+}
+
+/// A named pin, drawn on the pin purpose of a layer and labeled with its name.
+///
+/// For a pin on `met1`, the shape is exported on `met1.pin` and the label on
+/// `met1.label`, falling back to the pin layer and to `met1` itself when those
+/// layers are not defined.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pin<T> {
+    pub id: ObjectId,
+    pub name: String,
+    pub layer: String,
+    pub x0: T,
+    pub y0: T,
+    pub x1: T,
+    pub y1: T,
+    pub span: Option<Span>,
+}
+
+impl<T> Pin<T> {
+    /// Returns the layer of the pin shape, given which layers `exist`.
+    pub fn pin_layer(&self, exists: impl Fn(&str) -> bool) -> String {
+        let layer = format!("{}.pin", self.layer);
+        if exists(&layer) {
+            layer
+        } else {
+            self.layer.clone()
+        }
+    }
+
+    /// Returns the layer of the pin label, given which layers `exist`.
+    pub fn label_layer(&self, exists: impl Fn(&str) -> bool) -> String {
+        let layer = format!("{}.label", self.layer);
+        if exists(&layer) {
+            layer
+        } else {
+            self.pin_layer(exists)
+        }
+    }
+}
+
+impl Pin<f64> {
+    pub fn bbox(&self) -> Bbox {
+        Bbox::new(self.x0, self.y0, self.x1, self.y1)
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        ((self.x0 + self.x1) / 2., (self.y0 + self.y1) / 2.)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Text<T> {
     pub id: ObjectId,
@@ -2095,6 +2972,7 @@ type FrameId = u64;
 type ValueId = u64;
 pub type CellId = u64;
 pub type EnumId = u64;
+pub type StructId = u64;
 
 /// Sequence number.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize, Ord, PartialOrd)]
@@ -2106,6 +2984,20 @@ pub struct ObjectId(u64);
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 pub struct ScopeId(u64);
 
+/// An identifier for an object or scope that, unlike [`ObjectId`] and
+/// [`ScopeId`], is derived from its scope path and name and therefore survives
+/// recompilation of edited source.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub struct StableId(u64);
+
+impl StableId {
+    fn new(key: impl Hash) -> Self {
+        let mut s = DefaultHasher::new();
+        key.hash(&mut s);
+        Self(s.finish())
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 pub(crate) struct DynLoc {
     pub(crate) cell: CellId,
@@ -2134,13 +3026,30 @@ struct ObjectEmit {
     span: Span,
 }
 
+/// A `merge` or `subtract` whose result is computed once the cell is solved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BooleanOp {
+    layer: Option<String>,
+    rects: Vec<Rect<LinearExpr>>,
+    cut: Vec<Rect<LinearExpr>>,
+    construction: bool,
+    scope: ScopeId,
+    span: Span,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct ExecScope {
     parent: Option<ScopeId>,
     static_parent: Option<(ScopeId, SeqNum)>,
     name: String,
+    /// The scope name without any generated unique identifier.
+    key: String,
     span: Span,
     bindings: IndexMap<SeqNum, (String, ValueId)>,
+    /// Whether objects created in this scope are construction geometry.
+    ///
+    /// Set for `construction` scopes and inherited by all of their children.
+    construction: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -2176,6 +3085,7 @@ struct CellState {
     fields: IndexMap<String, ValueId>,
     emit: Vec<Emit>,
     object_emit: Vec<ObjectEmit>,
+    booleans: Vec<BooleanOp>,
     objects: IndexMap<ObjectId, Object>,
     deferred: IndexSet<ValueId>,
     root_scope: ScopeId,
@@ -2184,8 +3094,33 @@ struct CellState {
     fallback_constraints_used: Vec<UsedFallback>,
     rowspace_vecs: Vec<Vec<(f64, Var)>>,
     unsolved_vars: Option<IndexSet<Var>>,
-    constraint_span_map: IndexMap<ConstraintId, Span>,
+    constraints: IndexMap<ConstraintId, CellConstraint>,
     var_dependents: IndexMap<Var, IndexSet<ValueId>>,
+    /// Name of the cell generator.
+    name: String,
+    /// Arguments the cell was compiled with, keyed by parameter name.
+    params: IndexMap<String, CellArg>,
+    /// Whether the cell is an `extern cell`.
+    external: bool,
+}
+
+impl CellState {
+    /// Constrains the value of `expr` to 0, recording the constraint and the
+    /// `span` it came from in [`CompiledCell::constraints`].
+    fn constrain(&mut self, expr: LinearExpr, span: Span, fallback: bool) -> ConstraintId {
+        let vars = expr.coeffs.iter().map(|(_, var)| *var).unique().collect();
+        let id = self.solver.constrain_eq0(expr.clone());
+        self.constraints.insert(
+            id,
+            CellConstraint {
+                vars,
+                expr,
+                span,
+                fallback,
+            },
+        );
+        id
+    }
 }
 
 struct ExecPass<'a> {
@@ -2207,7 +3142,15 @@ struct ExecPass<'a> {
     partial_cells: VecDeque<CellId>,
     compiled_cells: IndexMap<CellId, CompiledCell>,
     compiled_cell_cache: HashMap<CellExecKey, CellId>,
+    /// Data of the enums declared in the workspace.
+    enums: IndexMap<EnumId, EnumData>,
     errors: Vec<ExecError>,
+    solver: SolverConfig,
+    /// Time after which compilation is abandoned, from [`SolverConfig::timeout`].
+    #[cfg(feature = "std-time")]
+    deadline: Option<Instant>,
+    /// Layer properties to use instead of reading [`CompileInput::lyp_file`].
+    layers: Option<LayerProperties>,
 }
 
 enum ExecScopeName {
@@ -2228,6 +3171,21 @@ fn add_scope(cell: &mut CompiledCell, state: &CellState, id: ScopeId, scope: &Ex
     if let Some((p, _)) = scope.static_parent {
         add_scope(cell, state, p, &state.scopes[&p]);
     }
+    // Disambiguate same-named siblings (e.g. the iterations of a loop) by
+    // their order of execution rather than by their generated names.
+    let stable_id = match scope.parent {
+        Some(p) => {
+            let parent = &cell.scopes[&p];
+            let ordinal = parent
+                .children
+                .iter()
+                .take_while(|child| **child != id)
+                .filter(|child| state.scopes[*child].key == scope.key)
+                .count();
+            StableId::new((parent.stable_id, &scope.key, ordinal))
+        }
+        None => StableId::new(&scope.key),
+    };
     cell.scopes.insert(
         id,
         CompiledScope {
@@ -2235,12 +3193,58 @@ fn add_scope(cell: &mut CompiledCell, state: &CellState, id: ScopeId, scope: &Ex
             bindings: Default::default(),
             children: Default::default(),
             name: scope.name.clone(),
+            stable_id,
             span: scope.span.clone(),
             emit: Vec::new(),
+            bbox: None,
         },
     );
 }
 
+/// Assigns a [`StableId`] to every object that is bound to a name or emitted in `cell`.
+///
+/// Named objects are identified by their scope and binding name; the rest are
+/// identified by their order among the unnamed objects emitted in their scope.
+fn stable_object_ids(cell: &CompiledCell) -> IndexMap<ObjectId, StableId> {
+    let mut ids = IndexMap::new();
+    for scope in cell.scopes.values() {
+        for (name, objs) in scope.bindings.values() {
+            let mut index = 0;
+            objs.for_each(&mut |obj| {
+                ids.entry(*obj)
+                    .or_insert_with(|| StableId::new((scope.stable_id, name, index)));
+                index += 1;
+            });
+        }
+    }
+    for scope in cell.scopes.values() {
+        let mut index = 0;
+        for (obj, _) in &scope.emit {
+            if !ids.contains_key(obj) {
+                ids.insert(*obj, StableId::new((scope.stable_id, index)));
+                index += 1;
+            }
+        }
+    }
+    ids
+}
+
+/// Reads the layer properties file at `path`, if it exists and is valid.
+#[cfg(feature = "fs")]
+fn read_lyp(path: &Path) -> Option<LayerProperties> {
+    let file = std::fs::File::open(path).ok()?;
+    klayout_lyp::from_reader(BufReader::new(file))
+        .ok()
+        .map(Into::into)
+}
+
+/// Without filesystem access, layer properties must be given directly; see
+/// [`dynamic_compile_with_layers`].
+#[cfg(not(feature = "fs"))]
+fn read_lyp(_path: &Path) -> Option<LayerProperties> {
+    None
+}
+
 impl<'a> ExecPass<'a> {
     pub(crate) fn new(ast: &'a WorkspaceAst<VarIdTyMetadata>) -> Self {
         Self {
@@ -2269,10 +3273,29 @@ impl<'a> ExecPass<'a> {
             partial_cells: VecDeque::new(),
             compiled_cells: IndexMap::new(),
             compiled_cell_cache: HashMap::new(),
+            enums: IndexMap::new(),
             errors: Vec::new(),
+            solver: SolverConfig::default(),
+            #[cfg(feature = "std-time")]
+            deadline: None,
+            layers: None,
         }
     }
 
+    /// Whether the [`SolverConfig::timeout`] has passed.
+    fn timed_out(&self) -> bool {
+        #[cfg(feature = "std-time")]
+        return self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        #[cfg(not(feature = "std-time"))]
+        false
+    }
+
+    fn enum_variant(&self, value: &EnumValue) -> Option<&EnumVariantData> {
+        self.enums.get(&value.id)?.variants.get(&value.variant)
+    }
+
     fn span(&self, loc: &DynLoc, span: cfgrammar::Span) -> Span {
         Span {
             path: self.cell_state(loc.cell).scopes[&loc.scope]
@@ -2296,25 +3319,17 @@ impl<'a> ExecPass<'a> {
     }
 
     pub(crate) fn execute(mut self, input: CompileInput<'a>) -> CompileOutput {
+        #[cfg(feature = "std-time")]
+        {
+            self.deadline = input
+                .solver
+                .timeout
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                .map(|timeout| Instant::now() + timeout);
+        }
+        self.solver = input.solver;
         self.declare_globals();
-        let path = match input.cell[0] {
-            "std" => {
-                vec!["std".to_string()]
-            }
-            "crate" => input
-                .cell
-                .iter()
-                .skip(1)
-                .dropping_back(1)
-                .map(|ident| ident.to_string())
-                .collect_vec(),
-            _ => input
-                .cell
-                .iter()
-                .dropping_back(1)
-                .map(|ident| ident.to_string())
-                .collect_vec(),
-        };
+        let path = cell_mod_path(input.cell);
         if let Some((_, vid)) = self.ast[&path].ast.decls.iter().find_map(|d| match d {
             Decl::Cell(
                 v @ CellDecl {
@@ -2333,27 +3348,34 @@ impl<'a> ExecPass<'a> {
                     });
                 }
             };
-            let layers = if let Ok(layers) = std::fs::File::open(input.lyp_file)
-                .map_err(|_| ())
-                .and_then(|f| klayout_lyp::from_reader(BufReader::new(f)).map_err(|_| ()))
-            {
-                layers.into()
-            } else {
-                return CompileOutput::StaticErrors(StaticErrorCompileOutput {
-                    errors: vec![StaticError {
-                        span: Span {
-                            path: self.ast[&vec![]].path.clone(),
-                            span: cfgrammar::Span::new(0, 0),
-                        },
-                        kind: StaticErrorKind::InvalidLyp,
-                    }],
-                });
-            };
+            let layers =
+                if let Some(layers) = self.layers.take().or_else(|| read_lyp(input.lyp_file)) {
+                    layers
+                } else {
+                    return CompileOutput::StaticErrors(StaticErrorCompileOutput {
+                        errors: vec![StaticError {
+                            span: Span {
+                                path: self.ast[&vec![]].path.clone(),
+                                span: cfgrammar::Span::new(0, 0),
+                            },
+                            kind: StaticErrorKind::InvalidLyp,
+                        }],
+                    });
+                };
+            compute_scope_bboxes(&mut self.compiled_cells);
             if self.errors.is_empty() {
                 CompileOutput::Valid(CompiledData {
                     cells: self.compiled_cells,
                     top: cell_id,
                     layers,
+                    density_violations: Vec::new(),
+                    drc_violations: Vec::new(),
+                    fill: Vec::new(),
+                    nets: None,
+                    dbu: DEFAULT_DBU,
+                    grid: DEFAULT_DBU,
+                    extern_gds: IndexMap::new(),
+                    merge_shapes: false,
                 })
             } else {
                 CompileOutput::ExecErrors(ExecErrorCompileOutput {
@@ -2362,6 +3384,14 @@ impl<'a> ExecPass<'a> {
                         cells: self.compiled_cells,
                         top: cell_id,
                         layers,
+                        density_violations: Vec::new(),
+                        drc_violations: Vec::new(),
+                        fill: Vec::new(),
+                        nets: None,
+                        dbu: DEFAULT_DBU,
+                        grid: DEFAULT_DBU,
+                        extern_gds: IndexMap::new(),
+                        merge_shapes: false,
                     }),
                 })
             }
@@ -2414,7 +3444,13 @@ impl<'a> ExecPass<'a> {
             } else {
                 format!("cell {} {}", &cell_decl.name.name, root_scope_id.0)
             },
+            key: if let Some(anno) = scope_annotation {
+                format!("{} cell {}", anno, &cell_decl.name.name)
+            } else {
+                format!("cell {}", &cell_decl.name.name)
+            },
             bindings: Default::default(),
+            construction: false,
         };
 
         let cell_id = self.alloc_id();
@@ -2425,10 +3461,11 @@ impl<'a> ExecPass<'a> {
                     cell_id,
                     CellState {
                         solve_iters: 0,
-                        solver: Solver::new(),
+                        solver: Solver::with_config(self.solver.clone()),
                         fields: Default::default(),
                         emit: Vec::new(),
                         object_emit: Vec::new(),
+                        booleans: Vec::new(),
                         deferred: Default::default(),
                         scopes: IndexMap::from_iter([(root_scope_id, root_scope)]),
                         fallback_constraints: Default::default(),
@@ -2437,8 +3474,16 @@ impl<'a> ExecPass<'a> {
                         root_scope: root_scope_id,
                         unsolved_vars: Default::default(),
                         objects: Default::default(),
-                        constraint_span_map: IndexMap::new(),
+                        constraints: IndexMap::new(),
                         var_dependents: IndexMap::new(),
+                        name: cell_decl.name.name.to_string(),
+                        params: cell_decl
+                            .args
+                            .iter()
+                            .map(|decl| decl.name.name.to_string())
+                            .zip(args.iter().cloned())
+                            .collect(),
+                        external: cell_decl.bbox.is_some(),
                     }
                 )
                 .is_none()
@@ -2502,6 +3547,15 @@ impl<'a> ExecPass<'a> {
             let state = self.cell_state(cell_id);
             !state.deferred.is_empty() || !state.solver.fully_solved()
         } {
+            let iters = self.cell_state(cell_id).solve_iters;
+            if self.solver.max_iterations.is_some_and(|max| iters >= max) || self.timed_out() {
+                self.errors.push(ExecError {
+                    span: None,
+                    cell: cell_id,
+                    kind: ExecErrorKind::SolverBudgetExceeded,
+                });
+                return Err(());
+            }
             let mut progress = false;
             while let Some(vid) = {
                 let state = self.cell_state_mut(cell_id);
@@ -2552,8 +3606,7 @@ impl<'a> ExecPass<'a> {
                             constraint: constraint.clone(),
                             span: span.clone(),
                         });
-                        let constraint_id = state.solver.constrain_eq0(constraint);
-                        state.constraint_span_map.insert(constraint_id, span);
+                        state.constrain(constraint, span, true);
                         constraint_added = true;
                         break;
                     }
@@ -2565,17 +3618,62 @@ impl<'a> ExecPass<'a> {
             }
         }
 
-        let state = self.cell_state_mut(cell_id);
-        for constraint in state.solver.inconsistent_constraints().clone() {
-            let span = self
-                .cell_state(cell_id)
-                .constraint_span_map
+        let state = self.cell_state(cell_id);
+        let inconsistent = state.solver.inconsistent_constraints().clone();
+        let exprs = if inconsistent.is_empty() {
+            IndexMap::new()
+        } else {
+            state
+                .constraints
+                .iter()
+                .map(|(id, constraint)| (*id, constraint.expr.clone()))
+                .collect()
+        };
+        // Inconsistent constraints that share a component are explained by one
+        // subset, found for the last of them so that it sees every constraint
+        // the others do. The search counts against the solver budget.
+        let mut iters = self.cell_state(cell_id).solve_iters;
+        let mut subsets: Vec<(IndexSet<ConstraintId>, Option<Vec<ConstraintId>>)> = Vec::new();
+        for constraint in inconsistent.iter().rev() {
+            if subsets
+                .iter()
+                .any(|(component, _)| component.contains(constraint))
+            {
+                continue;
+            }
+            let component = conflict_component(&exprs, *constraint);
+            let subset = irreducible_infeasible_subset(
+                &self.solver,
+                &exprs,
+                component.clone(),
+                *constraint,
+                || {
+                    iters += 1;
+                    self.solver.max_iterations.is_some_and(|max| iters > max) || self.timed_out()
+                },
+            );
+            subsets.push((component, subset));
+        }
+        for constraint in inconsistent {
+            let state = self.cell_state(cell_id);
+            let span = state
+                .constraints
                 .get(&constraint)
-                .cloned();
+                .map(|constraint| constraint.span.clone());
+            // Without a subset, the constraint is reported on its own.
+            let conflicts = subsets
+                .iter()
+                .find(|(component, _)| component.contains(&constraint))
+                .and_then(|(_, subset)| subset.as_ref())
+                .into_iter()
+                .flatten()
+                .filter(|id| **id != constraint)
+                .filter_map(|id| self.conflicting_constraint(cell_id, *id))
+                .collect();
             self.errors.push(ExecError {
                 span,
                 cell: cell_id,
-                kind: ExecErrorKind::InconsistentConstraint(constraint),
+                kind: ExecErrorKind::InconsistentConstraint(constraint, conflicts),
             });
         }
         for var in self
@@ -2603,101 +3701,6 @@ impl<'a> ExecPass<'a> {
 
     fn emit(&mut self, cell: CellId) -> CompiledCell {
         let state = self.cell_states.get(&cell).expect("cell not found");
-        let mut emit_obj = |obj: &Object| -> SolvedValue {
-            match obj {
-                Object::Rect(rect) => {
-                    let x0 = state
-                        .solver
-                        .eval_expr(&rect.x0)
-                        .expect("rect x0 not solved");
-                    let y0 = state
-                        .solver
-                        .eval_expr(&rect.y0)
-                        .expect("rect y0 not solved");
-                    let x1 = state
-                        .solver
-                        .eval_expr(&rect.x1)
-                        .expect("rect x1 not solved");
-                    let y1 = state
-                        .solver
-                        .eval_expr(&rect.y1)
-                        .expect("rect y1 not solved");
-                    if x0 > x1 {
-                        self.errors.push(ExecError {
-                            span: rect.span.clone(),
-                            cell,
-                            kind: ExecErrorKind::FlippedRect("x0 > x1".to_string()),
-                        });
-                    }
-                    if y0 > y1 {
-                        self.errors.push(ExecError {
-                            span: rect.span.clone(),
-                            cell,
-                            kind: ExecErrorKind::FlippedRect("y0 > y1".to_string()),
-                        });
-                    }
-                    SolvedValue::Rect(Rect {
-                        id: rect.id,
-                        layer: rect.layer.clone(),
-                        x0: (x0, rect.x0.clone()),
-                        y0: (y0, rect.y0.clone()),
-                        x1: (x1, rect.x1.clone()),
-                        y1: (y1, rect.y1.clone()),
-                        construction: rect.construction,
-                        span: rect.span.clone(),
-                    })
-                }
-                Object::Text(text) => SolvedValue::Text(Text {
-                    id: text.id,
-                    text: text.text.clone(),
-                    layer: text.layer.clone(),
-                    x: state.solver.eval_expr(&text.x).expect("text x not solved"),
-                    y: state.solver.eval_expr(&text.y).expect("text x not solved"),
-                    span: text.span.clone(),
-                }),
-                Object::Dimension(dim) => SolvedValue::Dimension(Dimension {
-                    id: dim.id,
-                    p: state.solver.eval_expr(&dim.p).expect("dim p not solved"),
-                    n: state.solver.eval_expr(&dim.n).expect("dim n not solved"),
-                    value: state
-                        .solver
-                        .eval_expr(&dim.value)
-                        .expect("dim value not solved"),
-                    coord: state
-                        .solver
-                        .eval_expr(&dim.coord)
-                        .expect("dim coord not solved"),
-                    pstop: state
-                        .solver
-                        .eval_expr(&dim.pstop)
-                        .expect("dim pstop not solved"),
-                    nstop: state
-                        .solver
-                        .eval_expr(&dim.nstop)
-                        .expect("dim nstop not solved"),
-                    horiz: dim.horiz,
-                    constraint: dim.constraint,
-                    span: dim.span.clone(),
-                }),
-                Object::Inst(inst) => SolvedValue::Instance(SolvedInstance {
-                    id: inst.id,
-                    x: state.solver.eval_expr(&inst.x).expect("inst x not solved"),
-                    y: state.solver.eval_expr(&inst.y).expect("inst y not solved"),
-                    angle: inst.angle,
-                    reflect: inst.reflect,
-                    construction: inst.construction,
-                    cell: *self.values[&inst.cell]
-                        .as_ref()
-                        .into_ready()
-                        .expect("inst parent cell not ready")
-                        .as_ref()
-                        .into_cell()
-                        .expect("inst parent not a cell"),
-                    span: inst.span.clone(),
-                    cell_vid: inst.cell,
-                }),
-            }
-        };
         let emit_value = |vid: ValueId| -> Option<Arrayed<ObjectId>> {
             let value = &self.values[&vid];
             value
@@ -2708,6 +3711,9 @@ impl<'a> ExecPass<'a> {
         };
 
         let mut ccell = CompiledCell {
+            name: state.name.clone(),
+            params: state.params.clone(),
+            external: state.external,
             scopes: IndexMap::new(),
             root: state.root_scope,
             fields: IndexMap::new(),
@@ -2715,21 +3721,108 @@ impl<'a> ExecPass<'a> {
             fallback_constraints_used: state.fallback_constraints_used.clone(),
             unsolved_vars: state.unsolved_vars.clone().unwrap_or_default(),
             inconsistent_constraints: state.solver.inconsistent_constraints().clone(),
+            constraints: state.constraints.clone(),
             objects: IndexMap::new(),
+            stable_ids: IndexMap::new(),
+            ports: Vec::new(),
+            dbu: IndexMap::new(),
         };
         for (id, scope) in state.scopes.iter() {
             add_scope(&mut ccell, state, *id, scope);
         }
 
         for (id, obj) in state.objects.iter() {
-            ccell.objects.insert(*id, emit_obj(obj));
-        }
-
-        for emit in state.emit.iter() {
-            let obj_id = emit_value(emit.value)
-                .expect("failed to emit")
-                .into_elem()
-                .expect("emitted non-element object");
+            // Objects whose coordinates the solver left undetermined are
+            // reported and left out rather than aborting the compile.
+            let Some(value) = self.solve_object(state, obj) else {
+                let (kind, span) = match obj {
+                    Object::Rect(rect) => ("rect", rect.span.clone()),
+                    Object::Polygon(polygon) => ("polygon", polygon.span.clone()),
+                    Object::Pin(pin) => ("pin", pin.span.clone()),
+                    Object::Text(text) => ("text", text.span.clone()),
+                    Object::Dimension(dim) => ("dimension", dim.span.clone()),
+                    Object::Inst(inst) => ("instance", Some(inst.span.clone())),
+                };
+                self.errors.push(ExecError {
+                    span,
+                    cell,
+                    kind: ExecErrorKind::UnsolvedObject(kind.to_string()),
+                });
+                continue;
+            };
+            if let SolvedValue::Rect(rect) = &value {
+                if rect.x0.0 > rect.x1.0 {
+                    self.errors.push(ExecError {
+                        span: rect.span.clone(),
+                        cell,
+                        kind: ExecErrorKind::FlippedRect("x0 > x1".to_string()),
+                    });
+                }
+                if rect.y0.0 > rect.y1.0 {
+                    self.errors.push(ExecError {
+                        span: rect.span.clone(),
+                        cell,
+                        kind: ExecErrorKind::FlippedRect("y0 > y1".to_string()),
+                    });
+                }
+            }
+            ccell.objects.insert(*id, value);
+        }
+
+        for op in state.booleans.iter() {
+            let bbox = |rect: &Rect<LinearExpr>| {
+                let [x0, y0, x1, y1] = [&rect.x0, &rect.y0, &rect.x1, &rect.y1]
+                    .map(|coord| state.solver.eval_expr(coord));
+                Some(Bbox::new(x0?, y0?, x1?, y1?))
+            };
+            let (Some(rects), Some(cut)) = (
+                op.rects.iter().map(bbox).collect::<Option<Vec<_>>>(),
+                op.cut.iter().map(bbox).collect::<Option<Vec<_>>>(),
+            ) else {
+                self.errors.push(ExecError {
+                    span: Some(op.span.clone()),
+                    cell,
+                    kind: ExecErrorKind::UnsolvedObject("boolean operation".to_string()),
+                });
+                continue;
+            };
+            for r in argon_geometry::boolean::subtract(&rects, &cut) {
+                let id = object_id(&mut self.next_id);
+                ccell.objects.insert(
+                    id,
+                    SolvedValue::Rect(Rect {
+                        id,
+                        layer: op.layer.clone(),
+                        x0: (r.x0, r.x0.into()),
+                        y0: (r.y0, r.y0.into()),
+                        x1: (r.x1, r.x1.into()),
+                        y1: (r.y1, r.y1.into()),
+                        construction: op.construction,
+                        span: Some(op.span.clone()),
+                    }),
+                );
+                ccell
+                    .scopes
+                    .get_mut(&op.scope)
+                    .expect("cell scope not found for boolean operation")
+                    .emit
+                    .push((
+                        id,
+                        CompiledEmit {
+                            span: op.span.clone(),
+                        },
+                    ));
+            }
+        }
+
+        for emit in state.emit.iter() {
+            let obj_id = emit_value(emit.value)
+                .expect("failed to emit")
+                .into_elem()
+                .expect("emitted non-element object");
+            if !ccell.objects.contains_key(&obj_id) {
+                continue;
+            }
             ccell
                 .scopes
                 .get_mut(&emit.scope)
@@ -2744,6 +3837,9 @@ impl<'a> ExecPass<'a> {
         }
 
         for emit in state.object_emit.iter() {
+            if !ccell.objects.contains_key(&emit.object) {
+                continue;
+            }
             ccell
                 .scopes
                 .get_mut(&emit.scope)
@@ -2760,6 +3856,11 @@ impl<'a> ExecPass<'a> {
         for (id, scope) in state.scopes.iter() {
             for (seq_num, (name, value)) in scope.bindings.iter() {
                 if let Some(obj_id) = emit_value(*value) {
+                    if let Arrayed::Elem(obj) = &obj_id
+                        && !ccell.objects.contains_key(obj)
+                    {
+                        continue;
+                    }
                     let scope = ccell.scopes.get_mut(id).expect("scope not found");
                     scope
                         .bindings
@@ -2770,10 +3871,128 @@ impl<'a> ExecPass<'a> {
                 }
             }
         }
+        ccell.stable_ids = stable_object_ids(&ccell);
+        ccell.ports = ccell
+            .objects
+            .values()
+            .filter_map(|obj| match obj {
+                SolvedValue::Text(text) => Some(Port {
+                    name: text.text.clone(),
+                    layer: text.layer.clone(),
+                    x: text.x,
+                    y: text.y,
+                }),
+                SolvedValue::Pin(pin) => {
+                    let (x, y) = pin.center();
+                    Some(Port {
+                        name: pin.name.clone(),
+                        layer: pin.layer.clone(),
+                        x,
+                        y,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        ccell.dbu = snap_objects(&ccell.objects, DEFAULT_DBU);
 
         ccell
     }
 
+    /// Evaluates the coordinates of `obj` in the solution of `state`, or
+    /// returns `None` if any of them is unsolved.
+    fn solve_object(&self, state: &CellState, obj: &Object) -> Option<SolvedValue> {
+        let eval = |expr: &LinearExpr| state.solver.eval_expr(expr);
+        Some(match obj {
+            Object::Rect(rect) => SolvedValue::Rect(Rect {
+                id: rect.id,
+                layer: rect.layer.clone(),
+                x0: (eval(&rect.x0)?, rect.x0.clone()),
+                y0: (eval(&rect.y0)?, rect.y0.clone()),
+                x1: (eval(&rect.x1)?, rect.x1.clone()),
+                y1: (eval(&rect.y1)?, rect.y1.clone()),
+                construction: rect.construction,
+                span: rect.span.clone(),
+            }),
+            Object::Polygon(polygon) => SolvedValue::Polygon(Polygon {
+                id: polygon.id,
+                layer: polygon.layer.clone(),
+                points: polygon
+                    .points
+                    .iter()
+                    .map(|(x, y)| Some((eval(x)?, eval(y)?)))
+                    .collect::<Option<_>>()?,
+                construction: polygon.construction,
+                span: polygon.span.clone(),
+            }),
+            Object::Pin(pin) => SolvedValue::Pin(Pin {
+                id: pin.id,
+                name: pin.name.clone(),
+                layer: pin.layer.clone(),
+                x0: eval(&pin.x0)?,
+                y0: eval(&pin.y0)?,
+                x1: eval(&pin.x1)?,
+                y1: eval(&pin.y1)?,
+                span: pin.span.clone(),
+            }),
+            Object::Text(text) => SolvedValue::Text(Text {
+                id: text.id,
+                text: text.text.clone(),
+                layer: text.layer.clone(),
+                x: eval(&text.x)?,
+                y: eval(&text.y)?,
+                span: text.span.clone(),
+            }),
+            Object::Dimension(dim) => SolvedValue::Dimension(Dimension {
+                id: dim.id,
+                p: eval(&dim.p)?,
+                n: eval(&dim.n)?,
+                value: eval(&dim.value)?,
+                coord: eval(&dim.coord)?,
+                pstop: eval(&dim.pstop)?,
+                nstop: eval(&dim.nstop)?,
+                horiz: dim.horiz,
+                constraint: dim.constraint,
+                span: dim.span.clone(),
+            }),
+            Object::Inst(inst) => {
+                let cell = *self.values[&inst.cell]
+                    .as_ref()
+                    .into_ready()
+                    .expect("inst parent cell not ready")
+                    .as_ref()
+                    .into_cell()
+                    .expect("inst parent not a cell");
+                let cell_state = &self.cell_states[&cell];
+                let solved = SolvedInstance {
+                    id: inst.id,
+                    x: eval(&inst.x)?,
+                    y: eval(&inst.y)?,
+                    angle: inst.angle,
+                    reflect: inst.reflect,
+                    construction: inst.construction,
+                    cell,
+                    cell_name: cell_state.name.clone(),
+                    params: cell_state.params.clone(),
+                    span: inst.span.clone(),
+                    cell_vid: inst.cell,
+                };
+                match &inst.array {
+                    Some(array) => SolvedValue::InstanceArray(SolvedInstanceArray {
+                        inst: solved,
+                        array: ArrayDims {
+                            nx: array.nx,
+                            ny: array.ny,
+                            dx: eval(&array.dx)?,
+                            dy: eval(&array.dy)?,
+                        },
+                    }),
+                    None => SolvedValue::Instance(solved),
+                }
+            }
+        })
+    }
+
     fn value_id(&mut self) -> ValueId {
         let id = self.next_id;
         self.next_id += 1;
@@ -2848,6 +4067,25 @@ impl<'a> ExecPass<'a> {
                                 .is_none()
                         );
                     }
+                    Decl::Constant(c) => {
+                        let vid = self.value_id();
+                        assert!(
+                            self.values
+                                .insert(vid, DeferValue::Ready(Value::from_const(&c.metadata.1)))
+                                .is_none()
+                        );
+                        assert!(
+                            self.frames
+                                .get_mut(&self.global_frame)
+                                .unwrap()
+                                .bindings
+                                .insert(c.metadata.0, vid)
+                                .is_none()
+                        );
+                    }
+                    Decl::Enum(e) => {
+                        self.enums.insert(e.metadata.id, e.metadata.clone());
+                    }
                     _ => (),
                 }
             }
@@ -2901,18 +4139,22 @@ impl<'a> ExecPass<'a> {
         span: Span,
     ) -> ScopeId {
         let id = self.scope_id();
-        let name = match name {
-            ExecScopeName::Specified(name) => name,
-            ExecScopeName::Prefix(prefix) => format!("{} {}", prefix, id.0),
+        let (name, key) = match name {
+            ExecScopeName::Specified(name) => (name.clone(), name),
+            ExecScopeName::Prefix(prefix) => (format!("{} {}", prefix, id.0), prefix),
         };
-        self.cell_state_mut(cell_id).scopes.insert(
+        let state = self.cell_state_mut(cell_id);
+        let construction = state.scopes[&parent].construction;
+        state.scopes.insert(
             id,
             ExecScope {
                 parent: Some(parent),
                 static_parent,
                 name,
+                key,
                 span,
                 bindings: Default::default(),
+                construction,
             },
         );
         id
@@ -2944,6 +4186,13 @@ impl<'a> ExecPass<'a> {
         scope: ScopeId,
         s: &Scope<Substr, VarIdTyMetadata>,
     ) -> ValueId {
+        if s.construction {
+            self.cell_state_mut(cell_id)
+                .scopes
+                .get_mut(&scope)
+                .unwrap()
+                .construction = true;
+        }
         let mut seq_num = SeqNum::new();
         for stmt in &s.stmts {
             let loc = DynLoc {
@@ -3010,9 +4259,13 @@ impl<'a> ExecPass<'a> {
                 } else {
                     // must be an enum value
                     assert!(path.path.len() >= 2);
-                    self.new_ready_value(Value::EnumValue(
-                        path.path.last().unwrap().name.to_string(),
-                    ))
+                    let Ty::Enum(e) = &path.metadata.1 else {
+                        unreachable!("enum value without an enum type");
+                    };
+                    self.new_ready_value(Value::EnumValue(EnumValue {
+                        id: e.id,
+                        variant: path.path.last().unwrap().name.to_string(),
+                    }))
                 }
             }
             Expr::Emit(e) => {
@@ -3046,6 +4299,43 @@ impl<'a> ExecPass<'a> {
                             },
                         }))
                     })
+                } else if let (None, Ty::Seq(elem_ty)) = &c.metadata
+                    && let Ty::Enum(e) = elem_ty.as_ref()
+                {
+                    // `MyEnum::variants()`
+                    self.new_ready_value(Value::Seq(
+                        e.variants
+                            .iter()
+                            .map(|variant| {
+                                Value::EnumValue(EnumValue {
+                                    id: e.id,
+                                    variant: variant.clone(),
+                                })
+                            })
+                            .collect(),
+                    ))
+                } else if let (None, Ty::Struct(ty)) = &c.metadata {
+                    // A struct literal, e.g. `Point(1., y=2.)`.
+                    self.new_deferred_value(loc, |this| {
+                        let mut fields = c
+                            .args
+                            .posargs
+                            .iter()
+                            .zip(ty.fields.keys())
+                            .map(|(arg, name)| (name.clone(), this.visit_expr(loc, arg)))
+                            .collect::<IndexMap<_, _>>();
+                        for kwarg in &c.args.kwargs {
+                            let value = this.visit_expr(loc, &kwarg.value);
+                            fields.insert(kwarg.name.name.to_string(), value);
+                        }
+                        // Static checks guarantee that every field is given exactly once.
+                        fields.sort_by_cached_key(|name, _| ty.fields.get_index_of(name));
+                        PartialEvalState::Struct(PartialStructExpr {
+                            id: ty.id,
+                            name: ty.name.clone(),
+                            fields,
+                        })
+                    })
                 } else {
                     let arg_vals = c
                         .args
@@ -3208,6 +4498,20 @@ impl<'a> ExecPass<'a> {
                         .collect(),
                 })
             }),
+            Expr::Seq(seq) => self.new_deferred_value(loc, |this| {
+                PartialEvalState::Seq(PartialSeqExpr {
+                    items: seq.items.iter().map(|i| this.visit_expr(loc, i)).collect(),
+                })
+            }),
+            Expr::Range(r) => self.new_deferred_value(loc, |this| {
+                let start = this.visit_expr(loc, &r.start);
+                let end = this.visit_expr(loc, &r.end);
+                PartialEvalState::Range(PartialRangeExpr {
+                    start,
+                    end,
+                    span: r.span,
+                })
+            }),
         }
     }
 
@@ -3226,6 +4530,41 @@ impl<'a> ExecPass<'a> {
             .insert(dependent);
     }
 
+    /// Describes constraint `id` of cell `cell_id` by its source and location.
+    fn conflicting_constraint(
+        &self,
+        cell_id: CellId,
+        id: ConstraintId,
+    ) -> Option<ConflictingConstraint> {
+        let span = &self.cell_state(cell_id).constraints.get(&id)?.span;
+        let file = self.ast.values().find(|file| file.path == span.path)?;
+        let (line, col) = line_col(&file.text, span.span.start());
+        Some(ConflictingConstraint {
+            id,
+            source: file.text[span.span.start()..span.span.end()]
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            span: span.clone(),
+            line,
+            col,
+        })
+    }
+
+    /// Returns the name of the enum with ID `id`.
+    fn enum_name(&self, id: EnumId) -> String {
+        self.ast
+            .values()
+            .flat_map(|file| &file.ast.decls)
+            .find_map(|decl| match decl {
+                Decl::Enum(e) if e.metadata.id == id => Some(e.name.name.to_string()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
     pub fn cell_arg_from_value(
         &mut self,
         cell_id: CellId,
@@ -3245,11 +4584,31 @@ impl<'a> ExecPass<'a> {
             }
             Value::Int(i) => Some(CellArg::Int(*i)),
             Value::Bool(b) => Some(CellArg::Bool(*b)),
+            Value::EnumValue(e) => Some(CellArg::Enum {
+                id: e.id,
+                name: self.enum_name(e.id),
+                variant: e.variant.clone(),
+            }),
             Value::Seq(s) => s
                 .iter()
                 .map(|v| self.cell_arg_from_value(cell_id, dependent_vid, v))
                 .collect::<Option<Vec<_>>>()
                 .map(CellArg::Seq),
+            Value::Struct(st) => st
+                .fields
+                .iter()
+                .map(|(field, v)| {
+                    Some((
+                        field.clone(),
+                        self.cell_arg_from_value(cell_id, dependent_vid, v)?,
+                    ))
+                })
+                .collect::<Option<IndexMap<_, _>>>()
+                .map(|fields| CellArg::Struct {
+                    id: st.id,
+                    name: st.name.clone(),
+                    fields,
+                }),
             v => unreachable!("invalid cell arg {v:?}"),
         }
     }
@@ -3310,6 +4669,8 @@ impl<'a> ExecPass<'a> {
                         let id = self.object_id();
                         let span = self.span(&vref.loc, c.expr.span);
                         let state = self.cell_state_mut(cell_id);
+                        let construction =
+                            f == "crect" || state.scopes[&vref.loc.scope].construction;
                         let rect = Rect {
                             id,
                             layer,
@@ -3317,7 +4678,7 @@ impl<'a> ExecPass<'a> {
                             y0: state.solver.new_var().into(),
                             x1: state.solver.new_var().into(),
                             y1: state.solver.new_var().into(),
-                            construction: f == "crect",
+                            construction,
                             span: Some(span.clone()),
                         };
                         state.objects.insert(rect.id, rect.clone().into());
@@ -3378,6 +4739,12 @@ impl<'a> ExecPass<'a> {
                             // `x1i=100.`) rather than the whole kwarg, so the GUI
                             // can rewrite just the value when persisting a
                             // solution-space-exploration drag.
+                            let priority = self
+                                .solver
+                                .fallback_priority
+                                .get(kwarg.name.name.as_str())
+                                .copied()
+                                .unwrap_or(priority);
                             let span = self.span(&vref.loc, kwarg.value.span());
                             self.new_deferred_value(vref.loc, |_| {
                                 PartialEvalState::Constraint(PartialConstraint {
@@ -3435,18 +4802,289 @@ impl<'a> ExecPass<'a> {
                         false
                     }
                 }
+                "pin" => {
+                    let unready = c
+                        .state
+                        .posargs
+                        .iter()
+                        .filter(|v| !matches!(self.values[*v], Defer::Ready(_)))
+                        .copied()
+                        .collect_vec();
+                    if unready.is_empty() {
+                        let arg =
+                            |i: usize| self.values[&c.state.posargs[i]].as_ref().unwrap_ready();
+                        let layer = arg(0).as_ref().unwrap_string().clone();
+                        let name = arg(1).as_ref().unwrap_string().clone();
+                        let rect = arg(2).as_ref().unwrap_rect().clone();
+                        let id = object_id(&mut self.next_id);
+                        let span = self.span(&vref.loc, c.expr.span);
+                        let state = self.cell_state_mut(cell_id);
+                        let pin = Pin {
+                            id,
+                            name,
+                            layer,
+                            x0: rect.x0,
+                            y0: rect.y0,
+                            x1: rect.x1,
+                            y1: rect.y1,
+                            span: Some(span.clone()),
+                        };
+                        state.object_emit.push(ObjectEmit {
+                            scope: vref.loc.scope,
+                            object: pin.id,
+                            span,
+                        });
+                        state.objects.insert(pin.id, pin.into());
+                        self.values.insert(vid, Defer::Ready(Value::Nil));
+                        true
+                    } else {
+                        for arg_vid in unready {
+                            self.add_value_dependent(arg_vid, vid);
+                        }
+                        false
+                    }
+                }
+                "polygon" => {
+                    let unready = c
+                        .state
+                        .posargs
+                        .iter()
+                        .chain(&c.state.kwargs)
+                        .filter(|v| !matches!(self.values[*v], Defer::Ready(_)))
+                        .copied()
+                        .collect_vec();
+                    if unready.is_empty() {
+                        let layer = self.values[&c.state.posargs[0]]
+                            .as_ref()
+                            .unwrap_ready()
+                            .as_ref()
+                            .unwrap_string()
+                            .clone();
+                        let points = match self.values[&c.state.kwargs[0]].as_ref().unwrap_ready() {
+                            Value::Seq(seq) => seq
+                                .iter()
+                                .map(|point| {
+                                    let coords = point.as_ref().unwrap_tuple();
+                                    (
+                                        coords[0].as_ref().unwrap_linear().clone(),
+                                        coords[1].as_ref().unwrap_linear().clone(),
+                                    )
+                                })
+                                .collect_vec(),
+                            _ => Vec::new(),
+                        };
+                        let span = self.span(&vref.loc, c.expr.span);
+                        if points.len() < 3 {
+                            self.errors.push(ExecError {
+                                span: Some(span),
+                                cell: cell_id,
+                                kind: ExecErrorKind::DegeneratePolygon(points.len()),
+                            });
+                            return Err(());
+                        }
+                        let id = object_id(&mut self.next_id);
+                        let state = self.cell_state_mut(cell_id);
+                        // Each vertex gets its own variables, constrained to the
+                        // given coordinates.
+                        let points = points
+                            .into_iter()
+                            .map(|(x, y)| {
+                                let vx = LinearExpr::from(state.solver.new_var());
+                                let vy = LinearExpr::from(state.solver.new_var());
+                                state.constrain(vx.clone() - x, span.clone(), false);
+                                state.constrain(vy.clone() - y, span.clone(), false);
+                                (vx, vy)
+                            })
+                            .collect_vec();
+                        let polygon = Polygon {
+                            id,
+                            layer,
+                            points,
+                            construction: state.scopes[&vref.loc.scope].construction,
+                            span: Some(span.clone()),
+                        };
+                        state.object_emit.push(ObjectEmit {
+                            scope: vref.loc.scope,
+                            object: polygon.id,
+                            span,
+                        });
+                        state.objects.insert(polygon.id, polygon.into());
+                        self.values.insert(vid, Defer::Ready(Value::Nil));
+                        true
+                    } else {
+                        for arg_vid in unready {
+                            self.add_value_dependent(arg_vid, vid);
+                        }
+                        false
+                    }
+                }
+                f @ "merge" | f @ "subtract" => {
+                    let unready = c
+                        .state
+                        .posargs
+                        .iter()
+                        .filter(|v| !matches!(self.values[*v], Defer::Ready(_)))
+                        .copied()
+                        .collect_vec();
+                    if unready.is_empty() {
+                        let arg =
+                            |i: usize| self.values[&c.state.posargs[i]].as_ref().unwrap_ready();
+                        let (rects, cut) = if f == "merge" {
+                            let rects = match arg(0) {
+                                Value::Seq(seq) => seq
+                                    .iter()
+                                    .map(|v| v.as_ref().unwrap_rect().clone())
+                                    .collect_vec(),
+                                _ => Vec::new(),
+                            };
+                            (rects, Vec::new())
+                        } else {
+                            (
+                                vec![arg(0).as_ref().unwrap_rect().clone()],
+                                vec![arg(1).as_ref().unwrap_rect().clone()],
+                            )
+                        };
+                        let span = self.span(&vref.loc, c.expr.span);
+                        let layer = rects.first().and_then(|r| r.layer.clone());
+                        if rects.iter().any(|r| r.layer != layer) {
+                            self.errors.push(ExecError {
+                                span: Some(span),
+                                cell: cell_id,
+                                kind: ExecErrorKind::MismatchedLayers,
+                            });
+                            return Err(());
+                        }
+                        let state = self.cell_state_mut(cell_id);
+                        // The operands are replaced by the result of the operation.
+                        for rect in rects.iter().chain(&cut) {
+                            if let Some(Object::Rect(obj)) = state.objects.get_mut(&rect.id) {
+                                obj.construction = true;
+                            }
+                        }
+                        state.booleans.push(BooleanOp {
+                            layer,
+                            rects,
+                            cut,
+                            construction: state.scopes[&vref.loc.scope].construction,
+                            scope: vref.loc.scope,
+                            span,
+                        });
+                        self.values.insert(vid, Defer::Ready(Value::Nil));
+                        true
+                    } else {
+                        for arg_vid in unready {
+                            self.add_value_dependent(arg_vid, vid);
+                        }
+                        false
+                    }
+                }
+                f @ ("align_left" | "align_right" | "align_bottom" | "align_top"
+                | "align_center_x" | "align_center_y" | "distribute_x" | "distribute_y") => {
+                    let unready = c
+                        .state
+                        .posargs
+                        .iter()
+                        .filter(|v| !matches!(self.values[*v], Defer::Ready(_)))
+                        .copied()
+                        .collect_vec();
+                    if unready.is_empty() {
+                        let arg =
+                            |i: usize| self.values[&c.state.posargs[i]].as_ref().unwrap_ready();
+                        let (rects, spacing) = if f.starts_with("distribute") {
+                            // `Any` sequences pass static checking, so their
+                            // elements may turn out not to be rects.
+                            let rects = match arg(0) {
+                                Value::Seq(seq) => seq
+                                    .iter()
+                                    .map(|v| match v {
+                                        Value::Rect(rect) => Some(rect.clone()),
+                                        _ => None,
+                                    })
+                                    .collect::<Option<Vec<_>>>(),
+                                _ => None,
+                            };
+                            let (Some(rects), Value::Linear(spacing)) = (rects, arg(1)) else {
+                                let span = self.span(&vref.loc, c.expr.span);
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            };
+                            (rects, spacing.clone())
+                        } else {
+                            let rects = (0..c.state.posargs.len())
+                                .map(|i| arg(i).as_ref().unwrap_rect().clone())
+                                .collect_vec();
+                            (rects, LinearExpr::from(0.))
+                        };
+                        let span = self.span(&vref.loc, c.expr.span);
+                        let state = self.cell_state_mut(cell_id);
+                        // Each rect is constrained against the one before it.
+                        for pair in rects.windows(2) {
+                            let (a, b) = (&pair[0], &pair[1]);
+                            let expr = match f {
+                                "align_left" => a.x0.clone() - &b.x0,
+                                "align_right" => a.x1.clone() - &b.x1,
+                                "align_bottom" => a.y0.clone() - &b.y0,
+                                "align_top" => a.y1.clone() - &b.y1,
+                                "align_center_x" => a.x0.clone() + a.x1.clone() - &b.x0 - &b.x1,
+                                "align_center_y" => a.y0.clone() + a.y1.clone() - &b.y0 - &b.y1,
+                                "distribute_x" => b.x0.clone() - &a.x1 - &spacing,
+                                _ => b.y0.clone() - &a.y1 - &spacing,
+                            };
+                            state.constrain(expr, span.clone(), false);
+                        }
+                        self.values.insert(vid, Defer::Ready(Value::Nil));
+                        true
+                    } else {
+                        for arg_vid in unready {
+                            self.add_value_dependent(arg_vid, vid);
+                        }
+                        false
+                    }
+                }
                 "bbox" => {
                     let arg = &self.values[&c.state.posargs[0]];
                     if let Some(val) = arg.get_ready() {
                         let span = self.span(&vref.loc, c.expr.span);
                         let r = match val {
                             Value::Inst(i) => {
-                                if let Defer::Ready(cell) = &self.values[&i.cell] {
+                                let array = i.array.as_ref().map(|array| {
+                                    let solver = &self.cell_states[&cell_id].solver;
+                                    Some(ArrayDims {
+                                        nx: array.nx,
+                                        ny: array.ny,
+                                        dx: solver.eval_expr(&array.dx)?,
+                                        dy: solver.eval_expr(&array.dy)?,
+                                    })
+                                });
+                                if let Some(None) = array {
+                                    // Wait for the pitch of the array to be solved.
+                                    let array = i.array.as_ref().unwrap();
+                                    let vars = array
+                                        .dx
+                                        .coeffs
+                                        .iter()
+                                        .chain(&array.dy.coeffs)
+                                        .map(|(_, var)| *var)
+                                        .collect_vec();
+                                    for var in vars {
+                                        self.add_var_dependent(cell_id, var, vid);
+                                    }
+                                    None
+                                } else if let Defer::Ready(cell) = &self.values[&i.cell] {
                                     let cell_id = cell.as_ref().unwrap_cell();
-                                    Some(
-                                        self.bbox(*cell_id)
-                                            .map(|r| r.transform(i.reflect, i.angle)),
-                                    )
+                                    Some(self.bbox(*cell_id).map(|r| {
+                                        let r = r.transform(&Transform::rotate(
+                                            i.angle, i.reflect, 0., 0.,
+                                        ));
+                                        match &array {
+                                            Some(Some(array)) => array_bbox(r, array),
+                                            _ => r,
+                                        }
+                                    }))
                                 } else {
                                     self.add_value_dependent(i.cell, vid);
                                     None
@@ -3522,31 +5160,116 @@ impl<'a> ExecPass<'a> {
                     );
                     true
                 }
-                "eq" => {
+                f @ ("min" | "max") => {
                     if let (Defer::Ready(vl), Defer::Ready(vr)) = (
                         &self.values[&c.state.posargs[0]],
                         &self.values[&c.state.posargs[1]],
                     ) {
-                        let expr = vl.as_ref().unwrap_linear().clone()
-                            - vr.as_ref().unwrap_linear().clone();
-                        let constraint = state.solver.constrain_eq0(expr);
-
-                        state.constraint_span_map.insert(
-                            constraint,
-                            Span {
-                                path: state.scopes[&vref.loc.scope].span.path.clone(),
-                                span: c.expr.span,
-                            },
-                        );
-                        self.values.insert(vid, Defer::Ready(Value::Nil));
-                        true
-                    } else {
-                        self.add_value_dependent(c.state.posargs[0], vid);
-                        self.add_value_dependent(c.state.posargs[1], vid);
-                        false
-                    }
-                }
-                "cons" => {
+                        match (vl, vr) {
+                            (Value::Int(vl), Value::Int(vr)) => {
+                                let res = if f == "min" { *vl.min(vr) } else { *vl.max(vr) };
+                                self.values.insert(vid, Defer::Ready(Value::Int(res)));
+                                true
+                            }
+                            (Value::Linear(vl), Value::Linear(vr)) => {
+                                // Selecting an operand requires both to be solved.
+                                if let (Some(l), Some(r)) =
+                                    (state.solver.eval_expr(vl), state.solver.eval_expr(vr))
+                                {
+                                    let res = if (l <= r) == (f == "min") {
+                                        vl.clone()
+                                    } else {
+                                        vr.clone()
+                                    };
+                                    self.values.insert(vid, Defer::Ready(Value::Linear(res)));
+                                    true
+                                } else {
+                                    for (_, var) in
+                                        vl.coeffs.clone().into_iter().chain(vr.coeffs.clone())
+                                    {
+                                        self.add_var_dependent(cell_id, var, vid);
+                                    }
+                                    false
+                                }
+                            }
+                            _ => {
+                                let span = self.span(&vref.loc, c.expr.span);
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            }
+                        }
+                    } else {
+                        self.add_value_dependent(c.state.posargs[0], vid);
+                        self.add_value_dependent(c.state.posargs[1], vid);
+                        false
+                    }
+                }
+                f @ ("abs" | "floor" | "ceil" | "round") => {
+                    if let Defer::Ready(val) = &self.values[&c.state.posargs[0]] {
+                        match val {
+                            Value::Int(val) if f == "abs" => {
+                                self.values.insert(vid, Defer::Ready(Value::Int(val.abs())));
+                                true
+                            }
+                            Value::Linear(expr) => {
+                                if let Some(val) = state.solver.eval_expr(expr) {
+                                    let res = match f {
+                                        // Keep the operand's expression, as `if` would.
+                                        "abs" if val < 0. => expr.clone() * -1.,
+                                        "abs" => expr.clone(),
+                                        "floor" => LinearExpr::from(val.floor()),
+                                        "ceil" => LinearExpr::from(val.ceil()),
+                                        _ => LinearExpr::from(val.round()),
+                                    };
+                                    self.values.insert(vid, Defer::Ready(Value::Linear(res)));
+                                    true
+                                } else {
+                                    for (_, var) in expr.coeffs.clone() {
+                                        self.add_var_dependent(cell_id, var, vid);
+                                    }
+                                    false
+                                }
+                            }
+                            _ => {
+                                let span = self.span(&vref.loc, c.expr.span);
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            }
+                        }
+                    } else {
+                        self.add_value_dependent(c.state.posargs[0], vid);
+                        false
+                    }
+                }
+                "eq" => {
+                    if let (Defer::Ready(vl), Defer::Ready(vr)) = (
+                        &self.values[&c.state.posargs[0]],
+                        &self.values[&c.state.posargs[1]],
+                    ) {
+                        let expr = vl.as_ref().unwrap_linear().clone()
+                            - vr.as_ref().unwrap_linear().clone();
+                        let span = Span {
+                            path: state.scopes[&vref.loc.scope].span.path.clone(),
+                            span: c.expr.span,
+                        };
+                        state.constrain(expr, span, false);
+                        self.values.insert(vid, Defer::Ready(Value::Nil));
+                        true
+                    } else {
+                        self.add_value_dependent(c.state.posargs[0], vid);
+                        self.add_value_dependent(c.state.posargs[1], vid);
+                        false
+                    }
+                }
+                "cons" => {
                     if let (Defer::Ready(head), Defer::Ready(tail)) = (
                         &self.values[&c.state.posargs[0]],
                         &self.values[&c.state.posargs[1]],
@@ -3642,6 +5365,28 @@ impl<'a> ExecPass<'a> {
                         false
                     }
                 }
+                "len" => {
+                    if let Defer::Ready(seq) = &self.values[&c.state.posargs[0]] {
+                        let len = match seq {
+                            Value::SeqNil => 0,
+                            Value::Seq(s) => s.len() as i64,
+                            _ => {
+                                let span = self.span(&vref.loc, c.expr.span);
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            }
+                        };
+                        self.values.insert(vid, Defer::Ready(Value::Int(len)));
+                        true
+                    } else {
+                        self.add_value_dependent(c.state.posargs[0], vid);
+                        false
+                    }
+                }
                 "head" => {
                     if let Defer::Ready(head) = &self.values[&c.state.posargs[0]] {
                         let val = match head {
@@ -3750,7 +5495,7 @@ impl<'a> ExecPass<'a> {
                         let (nstop, pstop, coord, value, n, p) =
                             (arg(), arg(), arg(), arg(), arg(), arg());
                         let expr = p.clone() - n.clone() - value.clone();
-                        let constraint = state.solver.constrain_eq0(expr);
+                        let constraint = state.constrain(expr, span.clone(), false);
                         let dim = Dimension {
                             id,
                             horiz,
@@ -3763,7 +5508,6 @@ impl<'a> ExecPass<'a> {
                             constraint,
                             span: Some(span.clone()),
                         };
-                        state.constraint_span_map.insert(constraint, span.clone());
                         state.object_emit.push(ObjectEmit {
                             scope: vref.loc.scope,
                             object: dim.id,
@@ -3779,7 +5523,7 @@ impl<'a> ExecPass<'a> {
                         false
                     }
                 }
-                "inst" => {
+                f @ ("inst" | "inst_array") => {
                     let refl = c
                         .expr
                         .args
@@ -3812,19 +5556,19 @@ impl<'a> ExecPass<'a> {
                         .find_map(|(kwarg, arg_vid)| {
                             if kwarg.name.name == "angle" {
                                 let span = self.span(&vref.loc, kwarg.value.span());
-                                Some(if let Defer::Ready(refl) = &self.values[arg_vid] {
-                                    Some(match ((*refl.as_ref().unwrap_int() % 360) + 360) % 360 {
-                                        0 => Rotation::R0,
-                                        90 => Rotation::R90,
-                                        180 => Rotation::R180,
-                                        270 => Rotation::R270,
+                                Some(if let Defer::Ready(angle) = &self.values[arg_vid] {
+                                    Some(match angle {
+                                        Value::Int(angle) => *angle as f64,
+                                        Value::Linear(angle) if angle.coeffs.is_empty() => {
+                                            angle.constant
+                                        }
                                         _ => {
                                             self.errors.push(ExecError {
                                                 span: Some(span),
                                                 cell: cell_id,
                                                 kind: ExecErrorKind::InvalidRotation,
                                             });
-                                            Rotation::R0
+                                            0.
                                         }
                                     })
                                 } else {
@@ -3863,12 +5607,70 @@ impl<'a> ExecPass<'a> {
                         Some(None) => None,
                         Some(Some(v)) => Some(Some(v)),
                     };
-                    if let (Some(refl), Some(angle), Some(construction)) =
-                        (refl, angle, construction)
+                    let array = if f == "inst_array" {
+                        let mut array = Some(ArrayDims {
+                            nx: 1,
+                            ny: 1,
+                            dx: LinearExpr::from(0.),
+                            dy: LinearExpr::from(0.),
+                        });
+                        for (kwarg, arg_vid) in c.expr.args.kwargs.iter().zip(c.state.kwargs.iter())
+                        {
+                            let name = kwarg.name.name.as_str();
+                            if !matches!(name, "nx" | "ny" | "dx" | "dy") {
+                                continue;
+                            }
+                            let Defer::Ready(v) = &self.values[arg_vid] else {
+                                self.add_value_dependent(*arg_vid, vid);
+                                array = None;
+                                continue;
+                            };
+                            let Some(array) = &mut array else {
+                                continue;
+                            };
+                            match (name, v) {
+                                ("nx" | "ny", Value::Int(n)) if *n >= 1 => {
+                                    let n = *n as usize;
+                                    if name == "nx" {
+                                        array.nx = n;
+                                    } else {
+                                        array.ny = n;
+                                    }
+                                }
+                                ("nx" | "ny", Value::Int(n)) => {
+                                    let span = self.span(&vref.loc, kwarg.value.span());
+                                    self.errors.push(ExecError {
+                                        span: Some(span),
+                                        cell: cell_id,
+                                        kind: ExecErrorKind::EmptyArray(*n),
+                                    });
+                                    return Err(());
+                                }
+                                ("dx", Value::Linear(dx)) => array.dx = dx.clone(),
+                                ("dy", Value::Linear(dy)) => array.dy = dy.clone(),
+                                _ => {
+                                    let span = self.span(&vref.loc, kwarg.value.span());
+                                    self.errors.push(ExecError {
+                                        span: Some(span),
+                                        cell: cell_id,
+                                        kind: ExecErrorKind::InvalidType,
+                                    });
+                                    return Err(());
+                                }
+                            }
+                        }
+                        array.map(Some)
+                    } else {
+                        Some(None)
+                    };
+                    if let (Some(refl), Some(angle), Some(construction), Some(array)) =
+                        (refl, angle, construction, array)
                     {
                         let id = object_id(&mut self.next_id);
                         let span = self.span(&vref.loc, c.expr.span);
                         let state = self.cell_states.get_mut(&cell_id).unwrap();
+                        let construction = construction.unwrap_or_default()
+                            || state.scopes[&vref.loc.scope].construction;
                         let inst = Instance {
                             id,
                             x: state.solver.new_var().into(),
@@ -3876,7 +5678,8 @@ impl<'a> ExecPass<'a> {
                             cell: *c.state.posargs.first().unwrap(),
                             reflect: refl.unwrap_or_default(),
                             angle: angle.unwrap_or_default(),
-                            construction: construction.unwrap_or_default(),
+                            construction,
+                            array,
                             span: span.clone(),
                         };
                         state.emit.push(Emit {
@@ -3904,6 +5707,12 @@ impl<'a> ExecPass<'a> {
                             // `x1i=100.`) rather than the whole kwarg, so the GUI
                             // can rewrite just the value when persisting a
                             // solution-space-exploration drag.
+                            let priority = self
+                                .solver
+                                .fallback_priority
+                                .get(kwarg.name.name.as_str())
+                                .copied()
+                                .unwrap_or(priority);
                             let span = self.span(&vref.loc, kwarg.value.span());
                             self.new_deferred_value(vref.loc, |_| {
                                 PartialEvalState::Constraint(PartialConstraint {
@@ -4099,7 +5908,16 @@ impl<'a> ExecPass<'a> {
             PartialEvalState::If(if_) => match if_.state {
                 IfExprState::Cond(cond) => {
                     if let Defer::Ready(val) = &self.values[&cond] {
-                        if *val.as_ref().unwrap_bool() {
+                        let cond_val = *val.as_ref().unwrap_bool();
+                        if self.cell_state(cell_id).unsolved_vars.is_some() {
+                            let span = self.span(&vref.loc, if_.expr.cond.span());
+                            self.errors.push(ExecError {
+                                span: Some(span),
+                                cell: cell_id,
+                                kind: ExecErrorKind::UnderconstrainedCondition,
+                            });
+                        }
+                        if cond_val {
                             let scope = self.create_exec_scope_at_loc(
                                 vref.loc,
                                 if let Some(scope_annotation) = &if_.expr.scope_annotation {
@@ -4170,12 +5988,16 @@ impl<'a> ExecPass<'a> {
             PartialEvalState::Match(match_) => match match_.state {
                 MatchExprState::Scrutinee(scrutinee) => {
                     if let Defer::Ready(val) = &self.values[&scrutinee] {
-                        let variant = val.as_ref().unwrap_enum_value();
+                        let variant = &val.as_ref().unwrap_enum_value().variant;
                         let arm = match_
                             .expr
                             .arms
                             .iter()
-                            .find(|arm| *variant == arm.pattern.path.last().unwrap().name)
+                            .find(|arm| {
+                                arm.pattern.as_ref().is_none_or(|pattern| {
+                                    *variant == pattern.path.last().unwrap().name
+                                })
+                            })
                             .unwrap();
                         let value = self.visit_expr(vref.loc, &arm.expr);
                         match_.state = MatchExprState::Value(value);
@@ -4289,6 +6111,22 @@ impl<'a> ExecPass<'a> {
             PartialEvalState::FieldAccess(field_access_expr) => {
                 if let Defer::Ready(base) = &self.values[&field_access_expr.state.base] {
                     match base.as_ref() {
+                        ValueRef::Struct(st) => {
+                            if let Some(val) =
+                                st.fields.get(field_access_expr.expr.field.name.as_str())
+                            {
+                                self.values.insert(vid, DeferValue::Ready(val.clone()));
+                                true
+                            } else {
+                                let span = self.span(&vref.loc, field_access_expr.expr.span);
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            }
+                        }
                         ValueRef::Rect(rect) => {
                             let val = match field_access_expr.expr.field.name.as_str() {
                                 "x0" => Value::Linear(rect.x0.clone()),
@@ -4324,6 +6162,62 @@ impl<'a> ExecPass<'a> {
                             self.values.insert(vid, DeferValue::Ready(val));
                             true
                         }
+                        ValueRef::Inst(inst)
+                            if field_access_expr.expr.field.name.as_str() == "bbox" =>
+                        {
+                            let inst = inst.clone();
+                            let span = self.span(&vref.loc, field_access_expr.expr.span);
+                            if let Some(rect) = self.inst_bbox(cell_id, vid, &inst, span)? {
+                                self.values
+                                    .insert(vid, DeferValue::Ready(Value::Rect(rect)));
+                                true
+                            } else {
+                                false
+                            }
+                        }
+                        ValueRef::Cell(child) => {
+                            let child = *child;
+                            let span = self.span(&vref.loc, field_access_expr.expr.span);
+                            let field = field_access_expr.expr.field.name.as_str();
+                            if !matches!(field, "width" | "height" | "bbox") {
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            }
+                            let Some(r) = self.bbox(child) else {
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::EmptyBbox,
+                                });
+                                return Err(());
+                            };
+                            let val = match field {
+                                "width" => Value::Linear(LinearExpr::from(r.x1 - r.x0)),
+                                "height" => Value::Linear(LinearExpr::from(r.y1 - r.y0)),
+                                _ => {
+                                    let rect = Rect {
+                                        id: object_id(&mut self.next_id),
+                                        layer: None,
+                                        x0: r.x0.into(),
+                                        y0: r.y0.into(),
+                                        x1: r.x1.into(),
+                                        y1: r.y1.into(),
+                                        construction: true,
+                                        span: Some(span),
+                                    };
+                                    self.cell_state_mut(cell_id)
+                                        .objects
+                                        .insert(rect.id, rect.clone().into());
+                                    Value::Rect(rect)
+                                }
+                            };
+                            self.values.insert(vid, DeferValue::Ready(val));
+                            true
+                        }
                         ValueRef::Inst(inst) => {
                             let val = match field_access_expr.expr.field.name.as_str() {
                                 "x" => Some(Value::Linear(inst.x.clone())),
@@ -4360,9 +6254,14 @@ impl<'a> ExecPass<'a> {
                                             &mut move |v| match v {
                                                 SolvedValue::Rect(rect) => {
                                                     let id = object_id(obj_id);
-                                                    let rect = rect
-                                                        .to_float()
-                                                        .transform(inst.reflect, inst.angle);
+                                                    let rect = rect.to_float().transform(
+                                                        &Transform::rotate(
+                                                            inst.angle,
+                                                            inst.reflect,
+                                                            0.,
+                                                            0.,
+                                                        ),
+                                                    );
                                                     let xrect = Rect {
                                                         id,
                                                         layer: rect.layer.clone(),
@@ -4388,15 +6287,30 @@ impl<'a> ExecPass<'a> {
                                                     objects.insert(xrect.id, xrect.clone().into());
                                                     Value::Rect(xrect)
                                                 }
-                                                SolvedValue::Instance(cinst) => {
-                                                    let (angle, reflect, cx, cy) = cascade(
+                                                SolvedValue::Instance(_)
+                                                | SolvedValue::InstanceArray(_) => {
+                                                    let cinst = v.instance().unwrap();
+                                                    let rotate = Transform::rotate(
                                                         inst.angle,
                                                         inst.reflect,
-                                                        cinst.angle,
-                                                        cinst.reflect,
-                                                        cinst.x,
-                                                        cinst.y,
+                                                        0.,
+                                                        0.,
                                                     );
+                                                    let xform = rotate.compose(&cinst.transform());
+                                                    let (angle, reflect) = xform.orientation();
+                                                    let (cx, cy) = xform.offset;
+                                                    // Arrays whose rows and columns are no
+                                                    // longer axis-aligned keep only their
+                                                    // first element.
+                                                    let array = v
+                                                        .get_instance_array()
+                                                        .and_then(|a| a.array.transform(&rotate))
+                                                        .map(|a| ArrayDims {
+                                                            nx: a.nx,
+                                                            ny: a.ny,
+                                                            dx: LinearExpr::from(a.dx),
+                                                            dy: LinearExpr::from(a.dy),
+                                                        });
                                                     let id = object_id(obj_id);
                                                     let oinst = Instance {
                                                         id,
@@ -4406,6 +6320,7 @@ impl<'a> ExecPass<'a> {
                                                         angle,
                                                         reflect,
                                                         construction: cinst.construction,
+                                                        array,
                                                         span: cinst.span.clone(),
                                                     };
                                                     objects.insert(oinst.id, oinst.clone().into());
@@ -4428,6 +6343,25 @@ impl<'a> ExecPass<'a> {
                                 false
                             }
                         }
+                        ValueRef::EnumValue(value) => {
+                            let Some(val) = self
+                                .enum_variant(value)
+                                .and_then(|data| {
+                                    data.fields.get(field_access_expr.expr.field.name.as_str())
+                                })
+                                .map(Value::from_const)
+                            else {
+                                let span = self.span(&vref.loc, field_access_expr.expr.span);
+                                self.errors.push(ExecError {
+                                    span: Some(span),
+                                    cell: cell_id,
+                                    kind: ExecErrorKind::InvalidType,
+                                });
+                                return Err(());
+                            };
+                            self.values.insert(vid, DeferValue::Ready(val));
+                            true
+                        }
                         _ => {
                             let span = self.span(&vref.loc, field_access_expr.expr.span);
                             self.errors.push(ExecError {
@@ -4534,8 +6468,7 @@ impl<'a> ExecPass<'a> {
                             span: c.span.clone(),
                         });
                     } else {
-                        let constraint = state.solver.constrain_eq0(expr);
-                        state.constraint_span_map.insert(constraint, c.span.clone());
+                        state.constrain(expr, c.span.clone(), false);
                     }
                     self.values.insert(vid, DeferValue::Ready(Value::Nil));
                     true
@@ -4565,6 +6498,9 @@ impl<'a> ExecPass<'a> {
                             res
                         }
                         (expr @ Value::Linear(_), Ty::Float) => Some(expr.clone()),
+                        (Value::EnumValue(value), Ty::Int) => self
+                            .enum_variant(value)
+                            .map(|data| Value::Int(data.discriminant)),
                         _ => {
                             let span = self.span(&vref.loc, c.expr.span);
                             self.errors.push(ExecError {
@@ -4606,6 +6542,72 @@ impl<'a> ExecPass<'a> {
                     false
                 }
             }
+            PartialEvalState::Struct(st) => {
+                let fields = st
+                    .fields
+                    .iter()
+                    .map(|(name, i)| Some((name.clone(), self.values[i].get_ready().cloned()?)))
+                    .collect::<Option<IndexMap<_, _>>>();
+                if let Some(fields) = fields {
+                    self.values.insert(
+                        vid,
+                        DeferValue::Ready(Value::Struct(StructValue {
+                            id: st.id,
+                            name: st.name.clone(),
+                            fields,
+                        })),
+                    );
+                    true
+                } else {
+                    for item in st.fields.values().filter(|&i| !self.values[i].is_ready()) {
+                        self.add_value_dependent(*item, vid);
+                    }
+                    false
+                }
+            }
+            PartialEvalState::Seq(seq) => {
+                let items = seq
+                    .items
+                    .iter()
+                    .map(|i| self.values[i].get_ready().cloned())
+                    .collect::<Option<Seq>>();
+                if let Some(items) = items {
+                    self.values
+                        .insert(vid, DeferValue::Ready(Value::Seq(items)));
+                    true
+                } else {
+                    for item in seq.items.iter().filter(|&i| !self.values[i].is_ready()) {
+                        self.add_value_dependent(*item, vid);
+                    }
+                    false
+                }
+            }
+            PartialEvalState::Range(r) => {
+                match (&self.values[&r.start], &self.values[&r.end]) {
+                    (Defer::Ready(Value::Int(start)), Defer::Ready(Value::Int(end))) => {
+                        // A bound that depends on the solver (e.g. `(x.w / 10.) as Int`)
+                        // only becomes ready once its variables are solved, so the loop
+                        // waits on the cast rather than on the solver directly.
+                        let seq = (*start..*end).map(Value::Int).collect();
+                        self.values.insert(vid, Defer::Ready(Value::Seq(seq)));
+                        true
+                    }
+                    (Defer::Ready(_), Defer::Ready(_)) => {
+                        let span = self.span(&vref.loc, r.span);
+                        self.errors.push(ExecError {
+                            span: Some(span),
+                            cell: cell_id,
+                            kind: ExecErrorKind::InvalidType,
+                        });
+                        return Err(());
+                    }
+                    _ => {
+                        self.add_value_dependent(r.start, vid);
+                        self.add_value_dependent(r.end, vid);
+                        false
+                    }
+                }
+            }
             PartialEvalState::ForLoop(f) => {
                 if let Defer::Ready(val) = &self.values[&f.seq] {
                     let seq = match val.as_ref() {
@@ -4670,8 +6672,17 @@ impl<'a> ExecPass<'a> {
         for (_, o) in cell.objects.iter() {
             match o {
                 SolvedValue::Rect(r) => bbox = bbox_union(bbox, Some(r.to_float())),
+                SolvedValue::Polygon(p) => bbox = bbox_polygon_union(bbox, p),
+                SolvedValue::Pin(p) => bbox = bbox_pin_union(bbox, p),
                 SolvedValue::Instance(i) => {
-                    let cell_bbox = self.bbox(i.cell).map(|r| r.transform(i.reflect, i.angle));
+                    let cell_bbox = self.bbox(i.cell).map(|r| r.transform(&i.transform()));
+                    bbox = bbox_union(bbox, cell_bbox);
+                }
+                SolvedValue::InstanceArray(a) => {
+                    let i = &a.inst;
+                    let cell_bbox = self
+                        .bbox(i.cell)
+                        .map(|r| array_bbox(r.transform(&i.transform()), &a.array));
                     bbox = bbox_union(bbox, cell_bbox);
                 }
                 _ => (),
@@ -4679,6 +6690,73 @@ impl<'a> ExecPass<'a> {
         }
         bbox
     }
+
+    /// Returns the bounding box of `inst` in the coordinates of `cell_id`.
+    ///
+    /// The edges of the box are offset from the instance's position, so
+    /// constraining them places the instance. Returns `Ok(None)` and defers
+    /// `vid` if the instantiated cell or the pitch of an instance array is not
+    /// yet known.
+    fn inst_bbox(
+        &mut self,
+        cell_id: CellId,
+        vid: ValueId,
+        inst: &Instance,
+        span: Span,
+    ) -> Result<Option<Rect<LinearExpr>>, ()> {
+        let Defer::Ready(cell) = &self.values[&inst.cell] else {
+            self.add_value_dependent(inst.cell, vid);
+            return Ok(None);
+        };
+        let child = *cell.as_ref().unwrap_cell();
+        let array = match &inst.array {
+            Some(array) => {
+                let solver = &self.cell_states[&cell_id].solver;
+                let (Some(dx), Some(dy)) =
+                    (solver.eval_expr(&array.dx), solver.eval_expr(&array.dy))
+                else {
+                    for (_, var) in array.dx.coeffs.iter().chain(&array.dy.coeffs) {
+                        self.add_var_dependent(cell_id, *var, vid);
+                    }
+                    return Ok(None);
+                };
+                Some(ArrayDims {
+                    nx: array.nx,
+                    ny: array.ny,
+                    dx,
+                    dy,
+                })
+            }
+            None => None,
+        };
+        let Some(r) = self.bbox(child) else {
+            self.errors.push(ExecError {
+                span: Some(span),
+                cell: cell_id,
+                kind: ExecErrorKind::EmptyBbox,
+            });
+            return Err(());
+        };
+        let r = r.transform(&Transform::rotate(inst.angle, inst.reflect, 0., 0.));
+        let r = match &array {
+            Some(array) => array_bbox(r, array),
+            None => r,
+        };
+        let rect = Rect {
+            id: object_id(&mut self.next_id),
+            layer: None,
+            x0: LinearExpr::add(r.x0, inst.x.clone()),
+            y0: LinearExpr::add(r.y0, inst.y.clone()),
+            x1: LinearExpr::add(r.x1, inst.x.clone()),
+            y1: LinearExpr::add(r.y1, inst.y.clone()),
+            construction: true,
+            span: Some(span),
+        };
+        self.cell_state_mut(cell_id)
+            .objects
+            .insert(rect.id, rect.clone().into());
+        Ok(Some(rect))
+    }
 }
 
 /// Persistent immutable sequence backing `Value::Seq`.
@@ -4691,10 +6769,26 @@ impl<'a> ExecPass<'a> {
 /// exactly when `Value` is — no regression for the (tokio) language server.
 type Seq = im::Vector<Value>;
 
+/// A variant of an enum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumValue {
+    pub id: EnumId,
+    pub variant: String,
+}
+
+/// A value of a user-defined struct, e.g. `Point(x=1., y=2.)`.
+#[derive(Debug, Clone)]
+pub struct StructValue {
+    pub id: StructId,
+    pub name: String,
+    /// Values of the struct's fields, in declaration order.
+    pub fields: IndexMap<String, Value>,
+}
+
 #[enumify]
 #[derive(Debug, Clone)]
 pub enum Value {
-    EnumValue(String),
+    EnumValue(EnumValue),
     String(String),
     Linear(LinearExpr),
     Int(i64),
@@ -4740,6 +6834,7 @@ pub enum Value {
     Inst(Instance),
     Seq(Seq),
     Tuple(Vec<Value>),
+    Struct(StructValue),
     SeqNil,
     Nil,
 }
@@ -4758,7 +6853,28 @@ impl Value {
             CellArg::Int(i) => Value::Int(*i),
             CellArg::Bool(b) => Value::Bool(*b),
             CellArg::Float(f) => Value::Linear(LinearExpr::from(*f)),
+            CellArg::Enum { id, variant, .. } => Value::EnumValue(EnumValue {
+                id: *id,
+                variant: variant.clone(),
+            }),
             CellArg::Seq(v) => Value::Seq(v.iter().map(Self::from_arg).collect()),
+            CellArg::Struct { id, name, fields } => Value::Struct(StructValue {
+                id: *id,
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(field, arg)| (field.clone(), Self::from_arg(arg)))
+                    .collect(),
+            }),
+        }
+    }
+
+    pub fn from_const(value: &ConstValue) -> Self {
+        match value {
+            ConstValue::Bool(b) => Value::Bool(*b),
+            ConstValue::Int(i) => Value::Int(*i),
+            ConstValue::Float(f) => Value::Linear(LinearExpr::from(*f)),
+            ConstValue::String(s) => Value::String(s.clone()),
         }
     }
 
@@ -4788,20 +6904,78 @@ pub struct Instance {
     pub y: LinearExpr,
     pub cell: ValueId,
     pub reflect: bool,
-    pub angle: Rotation,
+    /// Counterclockwise rotation in degrees.
+    pub angle: f64,
     pub construction: bool,
+    /// Rows and columns of an `inst_array`; `None` for a single instance.
+    pub array: Option<ArrayDims<LinearExpr>>,
     pub span: Span,
 }
 
+/// Layout of an instance array: `nx` columns spaced `dx` apart and `ny` rows
+/// spaced `dy` apart, in the coordinates of the array's parent cell.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ArrayDims<T> {
+    pub nx: usize,
+    pub ny: usize,
+    pub dx: T,
+    pub dy: T,
+}
+
+impl ArrayDims<f64> {
+    /// Offsets of the elements of the array from the first, row by row.
+    pub fn offsets(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        (0..self.ny)
+            .flat_map(move |j| (0..self.nx).map(move |i| (i as f64 * self.dx, j as f64 * self.dy)))
+    }
+
+    /// Offset of the last element of the array from the first.
+    pub fn extent(&self) -> (f64, f64) {
+        (
+            self.nx.saturating_sub(1) as f64 * self.dx,
+            self.ny.saturating_sub(1) as f64 * self.dy,
+        )
+    }
+
+    /// Returns the layout of the array after the rotation and reflection of
+    /// `xform`, or `None` if its rows and columns are no longer axis-aligned.
+    pub fn transform(&self, xform: &Transform) -> Option<Self> {
+        let (cx, cy) = xform.apply_vec((self.dx, 0.));
+        let (rx, ry) = xform.apply_vec((0., self.dy));
+        let zero = |v: f64| v.abs() < 1e-9;
+        if zero(cy) && zero(rx) {
+            Some(Self {
+                dx: cx,
+                dy: ry,
+                ..*self
+            })
+        } else if zero(cx) && zero(ry) {
+            Some(Self {
+                nx: self.ny,
+                ny: self.nx,
+                dx: rx,
+                dy: cy,
+            })
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SolvedInstance {
     pub id: ObjectId,
     pub x: f64,
     pub y: f64,
-    pub angle: Rotation,
+    /// Counterclockwise rotation in degrees.
+    pub angle: f64,
     pub reflect: bool,
     pub construction: bool,
     pub cell: CellId,
+    /// Name of the instantiated cell generator.
+    pub cell_name: String,
+    /// Arguments the instantiated cell was compiled with, keyed by parameter name.
+    pub params: IndexMap<String, CellArg>,
     pub span: Span,
     /// The value ID of the cell being instantiated.
     ///
@@ -4809,19 +6983,94 @@ pub struct SolvedInstance {
     cell_vid: ValueId,
 }
 
+impl SolvedInstance {
+    /// The transform from the instantiated cell's coordinates to the parent's.
+    pub fn transform(&self) -> Transform {
+        Transform::rotate(self.angle, self.reflect, self.x, self.y)
+    }
+}
+
+/// An instance repeated in a grid by `inst_array`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolvedInstanceArray {
+    /// The element in the first row and column.
+    pub inst: SolvedInstance,
+    pub array: ArrayDims<f64>,
+}
+
+impl SolvedInstanceArray {
+    /// The transforms of the elements of the array, row by row.
+    pub fn transforms(&self) -> impl Iterator<Item = Transform> + '_ {
+        let xform = self.inst.transform();
+        self.array
+            .offsets()
+            .map(move |(dx, dy)| Transform::translate(dx, dy).compose(&xform))
+    }
+}
+
 #[enumify]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SolvedValue {
     Rect(Rect<(f64, LinearExpr)>),
+    Polygon(Polygon<f64>),
+    Pin(Pin<f64>),
     Text(Text<f64>),
     Dimension(Dimension<f64>),
     Instance(SolvedInstance),
+    InstanceArray(SolvedInstanceArray),
+}
+
+impl SolvedValue {
+    /// Returns the instance, or the first element of an instance array.
+    pub fn instance(&self) -> Option<&SolvedInstance> {
+        match self {
+            SolvedValue::Instance(inst) => Some(inst),
+            SolvedValue::InstanceArray(array) => Some(&array.inst),
+            _ => None,
+        }
+    }
+
+    /// Returns the transforms of the cells placed by an instance or by each
+    /// element of an instance array.
+    pub fn instance_transforms(&self) -> Vec<Transform> {
+        match self {
+            SolvedValue::Instance(inst) => vec![inst.transform()],
+            SolvedValue::InstanceArray(array) => array.transforms().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            SolvedValue::Rect(rect) => rect.span.as_ref(),
+            SolvedValue::Polygon(polygon) => polygon.span.as_ref(),
+            SolvedValue::Pin(pin) => pin.span.as_ref(),
+            SolvedValue::Text(text) => text.span.as_ref(),
+            SolvedValue::Dimension(dim) => dim.span.as_ref(),
+            SolvedValue::Instance(inst) => Some(&inst.span),
+            SolvedValue::InstanceArray(array) => Some(&array.inst.span),
+        }
+    }
+
+    pub fn id(&self) -> ObjectId {
+        match self {
+            SolvedValue::Rect(rect) => rect.id,
+            SolvedValue::Polygon(polygon) => polygon.id,
+            SolvedValue::Pin(pin) => pin.id,
+            SolvedValue::Text(text) => text.id,
+            SolvedValue::Dimension(dim) => dim.id,
+            SolvedValue::Instance(inst) => inst.id,
+            SolvedValue::InstanceArray(array) => array.inst.id,
+        }
+    }
 }
 
 #[enumify]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Object {
     Rect(Rect<LinearExpr>),
+    Polygon(Polygon<LinearExpr>),
+    Pin(Pin<LinearExpr>),
     Text(Text<LinearExpr>),
     Dimension(Dimension<LinearExpr>),
     Inst(Instance),
@@ -4833,6 +7082,18 @@ impl From<Rect<LinearExpr>> for Object {
     }
 }
 
+impl From<Polygon<LinearExpr>> for Object {
+    fn from(value: Polygon<LinearExpr>) -> Self {
+        Self::Polygon(value)
+    }
+}
+
+impl From<Pin<LinearExpr>> for Object {
+    fn from(value: Pin<LinearExpr>) -> Self {
+        Self::Pin(value)
+    }
+}
+
 impl From<Text<LinearExpr>> for Object {
     fn from(value: Text<LinearExpr>) -> Self {
         Self::Text(value)
@@ -4858,9 +7119,13 @@ pub struct CompiledScope {
     /// Dynamic children.
     pub children: IndexSet<ScopeId>,
     pub name: String,
+    pub stable_id: StableId,
     pub span: Span,
     /// Objects emitted in this scope.
     pub emit: Vec<(ObjectId, CompiledEmit)>,
+    /// Bounding box of the objects emitted in this scope and its dynamic
+    /// children, in the coordinates of the enclosing cell.
+    pub bbox: Option<Rect<f64>>,
 }
 
 /// A fallback (initial-condition) constraint that was actually applied while
@@ -4879,14 +7144,53 @@ pub struct UsedFallback {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledCell {
+    /// Name of the cell generator.
+    pub name: String,
+    /// Arguments the cell was compiled with, keyed by parameter name.
+    pub params: IndexMap<String, CellArg>,
+    /// Whether the cell is an `extern cell`, whose layout is merged from the GDS
+    /// file listed for it in [`CompiledData::extern_gds`] on export.
+    pub external: bool,
     pub scopes: IndexMap<ScopeId, CompiledScope>,
     pub root: ScopeId,
     pub fields: IndexMap<String, Arrayed<ObjectId>>,
     pub rowspace_vecs: Vec<Vec<(f64, Var)>>,
     pub objects: IndexMap<ObjectId, SolvedValue>,
+    /// Stable IDs of the named and emitted objects in [`CompiledCell::objects`].
+    pub stable_ids: IndexMap<ObjectId, StableId>,
     pub fallback_constraints_used: Vec<UsedFallback>,
     pub unsolved_vars: IndexSet<Var>,
     pub inconsistent_constraints: IndexSet<ConstraintId>,
+    /// Constraints applied while solving the cell.
+    pub constraints: IndexMap<ConstraintId, CellConstraint>,
+    /// Text labels placed in the cell, which serve as its connection points
+    /// when it is instantiated.
+    pub ports: Vec<Port>,
+    /// Geometry of [`CompiledCell::objects`] snapped to integer database units.
+    pub dbu: IndexMap<ObjectId, DbuShape>,
+}
+
+/// A constraint applied while solving a [`CompiledCell`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CellConstraint {
+    /// Variables related by the constraint when it was applied.
+    pub vars: Vec<Var>,
+    /// Expression the constraint sets to zero.
+    pub expr: LinearExpr,
+    pub span: Span,
+    /// Whether the constraint is a fallback that pins an otherwise
+    /// underconstrained variable.
+    pub fallback: bool,
+}
+
+/// A labeled connection point of a [`CompiledCell`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Port {
+    pub name: String,
+    pub layer: String,
+    /// Position of the label in the cell's coordinates.
+    pub x: f64,
+    pub y: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -4926,80 +7230,127 @@ impl CompiledCell {
     }
 }
 
-pub fn bbox_union(b1: Option<Rect<f64>>, b2: Option<Rect<f64>>) -> Option<Rect<f64>> {
-    match (b1, b2) {
-        (Some(r1), Some(r2)) => Some(Rect {
-            layer: None,
-            x0: r1.x0.min(r2.x0),
-            y0: r1.y0.min(r2.y0),
-            x1: r1.x1.max(r2.x1),
-            y1: r1.y1.max(r2.y1),
-            id: r1.id,
-            construction: true,
-            span: None,
+/// Returns the statement that the body of an `extern cell` compiles to: a
+/// construction rect spanning the cell's bounding box, so that instances of
+/// the cell have a fixed size.
+fn extern_cell_stmt(
+    bbox: &crate::ast::Args<Substr, ParseMetadata>,
+) -> Statement<Substr, ParseMetadata> {
+    let func = Ident {
+        span: bbox.span,
+        name: Substr::from("crect"),
+        metadata: (),
+    };
+    Statement::Expr {
+        value: Expr::Call(CallExpr {
+            scope_annotation: None,
+            func: IdentPath {
+                path: vec![func],
+                metadata: (),
+                span: bbox.span,
+            },
+            args: bbox.clone(),
+            span: bbox.span,
+            metadata: (),
         }),
-        (Some(r), None) | (None, Some(r)) => Some(r),
-        (None, None) => None,
+        semicolon: true,
     }
 }
 
-pub fn bbox_text_union(b: Option<Rect<f64>>, t: &Text<f64>) -> Option<Rect<f64>> {
-    match b {
-        Some(r) => Some(Rect {
-            layer: None,
-            x0: r.x0.min(t.x),
-            y0: r.y0.min(t.y),
-            x1: r.x1.max(t.x),
-            y1: r.y1.max(t.y),
-            id: r.id,
-            construction: true,
-            span: None,
-        }),
-        None => Some(Rect {
-            layer: None,
-            x0: t.x,
-            y0: t.y,
-            x1: t.x,
-            y1: t.y,
-            id: t.id,
-            construction: true,
-            span: None,
-        }),
+/// Fills in [`CompiledScope::bbox`] for every scope of every cell.
+fn compute_scope_bboxes(cells: &mut IndexMap<CellId, CompiledCell>) {
+    let mut bboxes = HashMap::new();
+    for (cell_id, cell) in cells.iter() {
+        for scope_id in cell.scopes.keys() {
+            scope_bbox(cells, *cell_id, *scope_id, &mut bboxes);
+        }
+    }
+    for ((cell_id, scope_id), bbox) in bboxes {
+        cells[&cell_id].scopes[&scope_id].bbox = bbox;
     }
 }
 
+fn scope_bbox(
+    cells: &IndexMap<CellId, CompiledCell>,
+    cell_id: CellId,
+    scope_id: ScopeId,
+    bboxes: &mut HashMap<(CellId, ScopeId), Option<Rect<f64>>>,
+) -> Option<Rect<f64>> {
+    if let Some(bbox) = bboxes.get(&(cell_id, scope_id)) {
+        return bbox.clone();
+    }
+    let cell = &cells[&cell_id];
+    let scope = &cell.scopes[&scope_id];
+    let mut bbox = None;
+    for (obj, _) in &scope.emit {
+        bbox = match &cell.objects[obj] {
+            SolvedValue::Rect(rect) => bbox_union(bbox, Some(rect.to_float())),
+            SolvedValue::Polygon(polygon) => bbox_polygon_union(bbox, polygon),
+            SolvedValue::Pin(pin) => bbox_pin_union(bbox, pin),
+            SolvedValue::Instance(inst) => {
+                let inst_bbox = scope_bbox(cells, inst.cell, cells[&inst.cell].root, bboxes)
+                    .map(|r| r.transform(&inst.transform()));
+                bbox_union(bbox, inst_bbox)
+            }
+            SolvedValue::InstanceArray(array) => {
+                let inst = &array.inst;
+                let inst_bbox = scope_bbox(cells, inst.cell, cells[&inst.cell].root, bboxes)
+                    .map(|r| array_bbox(r.transform(&inst.transform()), &array.array));
+                bbox_union(bbox, inst_bbox)
+            }
+            SolvedValue::Dimension(dim) => bbox_dim_union(bbox, dim),
+            SolvedValue::Text(text) => bbox_text_union(bbox, text),
+        };
+    }
+    for child in &scope.children {
+        bbox = bbox_union(bbox, scope_bbox(cells, cell_id, *child, bboxes));
+    }
+    bboxes.insert((cell_id, scope_id), bbox.clone());
+    bbox
+}
+
+/// Extends `bbox`, the bounding box of the first element of an instance array,
+/// to cover every element.
+pub fn array_bbox(bbox: Rect<f64>, array: &ArrayDims<f64>) -> Rect<f64> {
+    let (ex, ey) = array.extent();
+    let last = bbox.transform(&Transform::translate(ex, ey));
+    bbox_union(Some(bbox), Some(last)).unwrap()
+}
+
+pub fn bbox_union(b1: Option<Rect<f64>>, b2: Option<Rect<f64>>) -> Option<Rect<f64>> {
+    let id = b1.as_ref().or(b2.as_ref())?.id;
+    Bbox::union(b1.map(|r| r.bbox()), b2.map(|r| r.bbox())).map(|bbox| Rect::from_bbox(id, bbox))
+}
+
+pub fn bbox_polygon_union(b: Option<Rect<f64>>, p: &Polygon<f64>) -> Option<Rect<f64>> {
+    let id = b.as_ref().map_or(p.id, |r| r.id);
+    Bbox::union(b.map(|r| r.bbox()), p.bbox()).map(|bbox| Rect::from_bbox(id, bbox))
+}
+
+pub fn bbox_pin_union(b: Option<Rect<f64>>, p: &Pin<f64>) -> Option<Rect<f64>> {
+    let id = b.as_ref().map_or(p.id, |r| r.id);
+    Bbox::union(b.map(|r| r.bbox()), Some(p.bbox())).map(|bbox| Rect::from_bbox(id, bbox))
+}
+
+pub fn bbox_text_union(b: Option<Rect<f64>>, t: &Text<f64>) -> Option<Rect<f64>> {
+    let id = b.as_ref().map_or(t.id, |r| r.id);
+    Bbox::union(b.map(|r| r.bbox()), Some(Bbox::point((t.x, t.y))))
+        .map(|bbox| Rect::from_bbox(id, bbox))
+}
+
 pub fn bbox_dim_union(bbox: Option<Rect<f64>>, dim: &Dimension<f64>) -> Option<Rect<f64>> {
     let perp_max = dim.coord.max(dim.pstop).max(dim.nstop);
     let perp_min = dim.coord.min(dim.pstop).min(dim.nstop);
     let par_max = dim.n.max(dim.p);
     let par_min = dim.n.min(dim.p);
-    let (xmin, xmax, ymin, ymax) = if dim.horiz {
-        (par_min, par_max, perp_min, perp_max)
+    let dim_bbox = if dim.horiz {
+        Bbox::new(par_min, perp_min, par_max, perp_max)
     } else {
-        (perp_min, perp_max, par_min, par_max)
+        Bbox::new(perp_min, par_min, perp_max, par_max)
     };
-    match bbox {
-        Some(r) => Some(Rect {
-            layer: None,
-            x0: r.x0.min(xmin),
-            y0: r.y0.min(ymin),
-            x1: r.x1.max(xmax),
-            y1: r.y1.max(ymax),
-            id: r.id,
-            construction: true,
-            span: None,
-        }),
-        None => Some(Rect {
-            layer: None,
-            x0: xmin,
-            y0: ymin,
-            x1: xmax,
-            y1: ymax,
-            id: ObjectId(0), // FIXME: should not need to allocate an object ID
-            construction: true,
-            span: None,
-        }),
-    }
+    // FIXME: should not need to allocate an object ID
+    let id = bbox.as_ref().map_or(ObjectId(0), |r| r.id);
+    Bbox::union(bbox.map(|r| r.bbox()), Some(dim_bbox)).map(|bbox| Rect::from_bbox(id, bbox))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5033,12 +7384,15 @@ pub enum StaticErrorKind {
     /// If conditions must have type bool.
     #[error("if conditions must have type bool")]
     IfCondNotBool,
-    /// Branches in expresssions must evaluate to the same type.
-    #[error("branches must evaluate to same type")]
-    BranchesDifferentTypes,
+    /// Branches of an `if` whose value is used must evaluate to the same type.
+    #[error("branches must evaluate to same type (found {0:?} and {1:?})")]
+    BranchesDifferentTypes(Ty, Ty),
     /// Multiple match arms with matching patterns.
     #[error("match arms must be distinct")]
     DuplicateMatchArm,
+    /// A match arm after a wildcard arm, which can never be taken.
+    #[error("unreachable match arm")]
+    UnreachableMatchArm,
     /// Match arms must be comprehensive.
     #[error("match arms must be comprehensive")]
     MatchArmsNotComprehensive,
@@ -5099,9 +7453,15 @@ pub enum StaticErrorKind {
     /// Called a function or cell with the wrong number of positional arguments.
     #[error("expected {expected} position arguments, found {found}")]
     CallIncorrectPositionalArity { expected: usize, found: usize },
+    /// A struct literal does not give a value for every field.
+    #[error("missing value(s) for field(s) {}", .0.join(", "))]
+    MissingFields(Vec<String>),
     /// Invalid keyword argument.
     #[error("invalid keyword argument")]
     InvalidKwArg,
+    /// A required keyword argument was not given.
+    #[error("missing keyword argument `{0}`")]
+    MissingKwArg(String),
     /// Duplicate keyword argument.
     #[error("duplicate keyword argument")]
     DuplicateKwArg,
@@ -5126,9 +7486,91 @@ pub enum StaticErrorKind {
     /// Invalid LYP file.
     #[error("invalid LYP file")]
     InvalidLyp,
+    /// Two variants of an enum have the same discriminant.
+    #[error("duplicate discriminant {0}")]
+    DuplicateDiscriminant(i64),
+    /// A constant's value is not a compile-time constant expression.
+    #[error("expression is not a compile-time constant")]
+    NotConstant,
+    /// Division by zero while evaluating a constant.
+    #[error("division by zero")]
+    DivisionByZero,
+    /// Integer arithmetic overflowed while evaluating a constant.
+    #[error("integer overflow")]
+    IntegerOverflow,
     /// Unimplemented.
     #[error("unimplemented")]
     Unimplemented,
+    /// Added, subtracted, or compared values with different powers of length,
+    /// e.g. `0.15um + 20.`.
+    #[error("cannot combine a {} with a {}", length_name(.left), length_name(.right))]
+    MixedUnits { left: i32, right: i32 },
+}
+
+/// Describes a power of length, e.g. `length` for 1.
+fn length_name(dim: &i32) -> String {
+    match *dim {
+        0 => "unitless value".to_string(),
+        1 => "length".to_string(),
+        2 => "area".to_string(),
+        dim => format!("length^{dim}"),
+    }
+}
+
+impl StaticErrorKind {
+    /// Returns the stable code of the error kind, e.g. `E0033`.
+    ///
+    /// Codes are never reused; new kinds take the next unused code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            StaticErrorKind::DuplicateNameDeclaration { .. } => "E0001",
+            StaticErrorKind::RedeclarationOfBuiltin { .. } => "E0002",
+            StaticErrorKind::NotAnEnum { .. } => "E0003",
+            StaticErrorKind::InvalidVariant { .. } => "E0004",
+            StaticErrorKind::CellWithTailExpr { .. } => "E0005",
+            StaticErrorKind::IfCondNotBool { .. } => "E0006",
+            StaticErrorKind::BranchesDifferentTypes { .. } => "E0007",
+            StaticErrorKind::DuplicateMatchArm { .. } => "E0008",
+            StaticErrorKind::MatchArmsNotComprehensive { .. } => "E0009",
+            StaticErrorKind::BinOpMismatchedTypes { .. } => "E0010",
+            StaticErrorKind::FloatEquality { .. } => "E0011",
+            StaticErrorKind::EnumsNotOrd { .. } => "E0012",
+            StaticErrorKind::NilNotOrd { .. } => "E0013",
+            StaticErrorKind::SeqNilNotOrd { .. } => "E0014",
+            StaticErrorKind::SeqMustCompareEqSeqNil { .. } => "E0015",
+            StaticErrorKind::BinOpInvalidType { .. } => "E0016",
+            StaticErrorKind::UnaryOpInvalidType { .. } => "E0017",
+            StaticErrorKind::ComparisonInvalidType { .. } => "E0018",
+            StaticErrorKind::UnknownType { .. } => "E0019",
+            StaticErrorKind::NoFieldOnTy { .. } => "E0020",
+            StaticErrorKind::TupleIndexOutOfRange { .. } => "E0021",
+            StaticErrorKind::CannotIndexFieldAccess { .. } => "E0022",
+            StaticErrorKind::CannotIndex { .. } => "E0023",
+            StaticErrorKind::CannotIterate { .. } => "E0024",
+            StaticErrorKind::IncorrectTy { .. } => "E0025",
+            StaticErrorKind::IncorrectTyCategory { .. } => "E0026",
+            StaticErrorKind::EmptyListConstructor { .. } => "E0027",
+            StaticErrorKind::CallIncorrectPositionalArity { .. } => "E0028",
+            StaticErrorKind::MissingFields { .. } => "E0029",
+            StaticErrorKind::InvalidKwArg { .. } => "E0030",
+            StaticErrorKind::MissingKwArg { .. } => "E0031",
+            StaticErrorKind::DuplicateKwArg { .. } => "E0032",
+            StaticErrorKind::UndeclaredVar { .. } => "E0033",
+            StaticErrorKind::CannotCall { .. } => "E0034",
+            StaticErrorKind::InvalidCast { .. } => "E0035",
+            StaticErrorKind::InvalidMod { .. } => "E0036",
+            StaticErrorKind::LexError { .. } => "E0037",
+            StaticErrorKind::ParseError { .. } => "E0038",
+            StaticErrorKind::InvalidLyp { .. } => "E0039",
+            StaticErrorKind::DuplicateDiscriminant { .. } => "E0040",
+            StaticErrorKind::NotConstant { .. } => "E0041",
+            StaticErrorKind::DivisionByZero { .. } => "E0042",
+            StaticErrorKind::Unimplemented { .. } => "E0043",
+            StaticErrorKind::MixedUnits { .. } => "E0044",
+            StaticErrorKind::IntegerOverflow { .. } => "E0045",
+            StaticErrorKind::UnreachableMatchArm { .. } => "E0046",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5138,10 +7580,35 @@ pub struct ExecError {
     pub kind: ExecErrorKind,
 }
 
+/// A constraint that conflicts with an inconsistent constraint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConflictingConstraint {
+    pub id: ConstraintId,
+    /// First line of the constraint's source, e.g. `eq(r.x0, 0.)`.
+    pub source: String,
+    pub span: Span,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl std::fmt::Display for ConflictingConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` at {}:{}", self.source, self.line, self.col)
+    }
+}
+
+fn describe_conflicts(conflicts: &[ConflictingConstraint]) -> String {
+    if conflicts.is_empty() {
+        String::new()
+    } else {
+        format!("; conflicts with {}", conflicts.iter().join(", "))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Error)]
 pub enum ExecErrorKind {
-    /// A non-Manhattan rotation.
-    #[error("non-Manhattan rotation")]
+    /// A rotation angle that does not evaluate to a constant.
+    #[error("invalid rotation")]
     InvalidRotation,
     /// An invalid cell was specified for execution.
     #[error("invalid cell")]
@@ -5152,9 +7619,10 @@ pub enum ExecErrorKind {
     /// Illegal layer (not defined in layer properties).
     #[error("layer {0} is not defined in layer properties")]
     IllegalLayer(String),
-    /// Inconsistent constraint.
-    #[error("inconsistent constraint")]
-    InconsistentConstraint(ConstraintId),
+    /// Inconsistent constraint, along with the other constraints of the smallest
+    /// set containing it that cannot all hold.
+    #[error("inconsistent constraint{}", describe_conflicts(.1))]
+    InconsistentConstraint(ConstraintId, Vec<ConflictingConstraint>),
     /// Invalid rounding (e.g. solved value is not sufficiently close to a rounding step).
     #[error("invalid rounding")]
     InvalidRounding(Var),
@@ -5167,6 +7635,9 @@ pub enum ExecErrorKind {
     /// Edges of a rect are in the wrong order (e.g. x0 > x1 or y0 > y1).
     #[error("rect edges are in the wrong order: {0}")]
     FlippedRect(String),
+    /// A polygon has fewer than 3 vertices.
+    #[error("polygons must have at least 3 vertices (found {0})")]
+    DegeneratePolygon(usize),
     /// Operation on an incompatible type, usually due to erroneous use of `Any`.
     #[error("operation on an incompatible type (check usage of `Any`)")]
     InvalidType,
@@ -5182,6 +7653,55 @@ pub enum ExecErrorKind {
     /// Attempt to access the tail of an empty list.
     #[error("attempted to access the tail of an empty list")]
     TailEmptyList,
+    /// The operands of `merge` or `subtract` were on different layers.
+    #[error("operands of a boolean operation must be on the same layer")]
+    MismatchedLayers,
+    /// An `if` condition depended on an underconstrained value.
+    ///
+    /// The branch taken, and any geometry it emits, depends on fallback constraints
+    /// or an arbitrary solution.
+    #[error("if condition depends on an underconstrained value, so the branch taken is arbitrary")]
+    UnderconstrainedCondition,
+    /// The solver exceeded the iteration or time budget set under `[solver]`.
+    #[error("solver exceeded its iteration or time budget")]
+    SolverBudgetExceeded,
+    /// An instance array was given fewer than one row or column.
+    #[error("instance arrays must have at least one row and column (found {0})")]
+    EmptyArray(i64),
+    /// The solver left a coordinate of an object undetermined, so the object
+    /// was left out of the layout.
+    #[error("{0} has unsolved coordinates and was left out of the layout")]
+    UnsolvedObject(String),
+}
+
+impl ExecErrorKind {
+    /// Returns the stable code of the error kind, e.g. `E1003`.
+    ///
+    /// Codes are never reused; new kinds take the next unused code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExecErrorKind::InvalidRotation { .. } => "E1001",
+            ExecErrorKind::InvalidCell { .. } => "E1002",
+            ExecErrorKind::Underconstrained { .. } => "E1003",
+            ExecErrorKind::IllegalLayer { .. } => "E1004",
+            ExecErrorKind::InconsistentConstraint { .. } => "E1005",
+            ExecErrorKind::InvalidRounding { .. } => "E1006",
+            ExecErrorKind::EmptyBbox { .. } => "E1007",
+            ExecErrorKind::EmptyField { .. } => "E1008",
+            ExecErrorKind::FlippedRect { .. } => "E1009",
+            ExecErrorKind::DegeneratePolygon { .. } => "E1010",
+            ExecErrorKind::InvalidType { .. } => "E1011",
+            ExecErrorKind::InvalidCast { .. } => "E1012",
+            ExecErrorKind::IndexOutOfBounds { .. } => "E1013",
+            ExecErrorKind::HeadEmptyList { .. } => "E1014",
+            ExecErrorKind::TailEmptyList { .. } => "E1015",
+            ExecErrorKind::MismatchedLayers { .. } => "E1016",
+            ExecErrorKind::UnderconstrainedCondition { .. } => "E1017",
+            ExecErrorKind::SolverBudgetExceeded { .. } => "E1018",
+            ExecErrorKind::EmptyArray { .. } => "E1019",
+            ExecErrorKind::UnsolvedObject { .. } => "E1020",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5204,11 +7724,55 @@ pub struct ExecErrorCompileOutput {
     pub output: Option<CompiledData>,
 }
 
+/// A scope yielded by [`CompiledData::iter_flat_scopes`].
+#[derive(Debug, Clone)]
+pub struct FlatScope<T> {
+    pub cell: CellId,
+    pub scope: ScopeId,
+    /// IDs of the instances leading to the scope.
+    pub path: Vec<ObjectId>,
+    /// Data passed down from the scope's parent.
+    pub data: T,
+}
+
+/// A rect yielded by [`CompiledData::iter_flat`].
+#[derive(Debug, Clone)]
+pub struct FlatRect<'a> {
+    pub layer: Option<&'a str>,
+    /// The rect in the coordinates of the cell being flattened.
+    pub rect: Rect<f64>,
+    /// IDs of the instances leading to the rect, followed by the rect's own ID.
+    pub path: Vec<ObjectId>,
+    /// Transform from the rect's cell to the cell being flattened.
+    pub transform: Transform,
+    /// Number of instances between the flattened cell and the rect.
+    pub depth: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledData {
     pub cells: IndexMap<CellId, CompiledCell>,
     pub top: CellId,
     pub layers: LayerProperties,
+    /// Populated by [`CompiledData::check_density`].
+    pub density_violations: Vec<DensityViolation>,
+    /// Populated by [`CompiledData::check_drc`].
+    pub drc_violations: Vec<DrcViolation>,
+    /// Fill inserted into the top cell by [`CompiledData::check_density`].
+    pub fill: Vec<FillShape>,
+    /// Populated by [`CompiledData::extract_nets`].
+    pub nets: Option<Nets>,
+    /// Layout units per database unit of the snapped geometry in each
+    /// [`CompiledCell::dbu`].
+    pub dbu: f64,
+    /// Manufacturing grid in layout units, set by [`CompiledData::set_grid`].
+    pub grid: f64,
+    /// GDS files of `extern cell`s, keyed by cell name, set by
+    /// [`CompiledData::set_extern_gds`].
+    pub extern_gds: IndexMap<String, PathBuf>,
+    /// Whether overlapping and abutting rects are merged on export, set by
+    /// [`CompiledData::set_merge_shapes`].
+    pub merge_shapes: bool,
 }
 
 #[enumify(generics_only)]
@@ -5240,6 +7804,9 @@ enum PartialEvalState<T: AstMetadata> {
     Constraint(PartialConstraint),
     Cast(Box<PartialCastExpr<T>>),
     Tuple(PartialTupleExpr),
+    Struct(PartialStructExpr),
+    Range(PartialRangeExpr),
+    Seq(PartialSeqExpr),
     ForLoop(Box<PartialForLoop<T>>),
 }
 
@@ -5351,6 +7918,25 @@ struct PartialTupleExpr {
     items: Vec<ValueId>,
 }
 
+#[derive(Debug, Clone)]
+struct PartialStructExpr {
+    id: StructId,
+    name: String,
+    fields: IndexMap<String, ValueId>,
+}
+
+#[derive(Debug, Clone)]
+struct PartialSeqExpr {
+    items: Vec<ValueId>,
+}
+
+#[derive(Debug, Clone)]
+struct PartialRangeExpr {
+    start: ValueId,
+    end: ValueId,
+    span: cfgrammar::Span,
+}
+
 #[derive(Debug, Clone)]
 struct PartialForLoop<T: AstMetadata> {
     for_loop: ForLoop<Substr, T>,
@@ -5373,34 +7959,6 @@ pub struct IndexExprState {
     index: ValueId,
 }
 
-pub fn ifmatvec(mat: TransformationMatrix, pt: (f64, f64)) -> (f64, f64) {
-    (
-        mat[0][0] as f64 * pt.0 + mat[0][1] as f64 * pt.1,
-        mat[1][0] as f64 * pt.0 + mat[1][1] as f64 * pt.1,
-    )
-}
-
-fn tmat(rot: Rotation, refv: bool) -> TransformationMatrix {
-    let mut mat = TransformationMatrix::identity();
-    if refv {
-        mat = mat.reflect_vert()
-    }
-    mat = mat.rotate(rot);
-    mat
-}
-
-fn imat(mat: TransformationMatrix) -> (Rotation, bool) {
-    let refv = mat[1][0] == mat[0][1] && mat[0][0] == -mat[1][1];
-    let rot = match (mat[0][0], mat[1][0]) {
-        (1, 0) => Rotation::R0,
-        (0, 1) => Rotation::R90,
-        (-1, 0) => Rotation::R180,
-        (0, -1) => Rotation::R270,
-        _ => panic!("invalid rotation matrix"),
-    };
-    (rot, refv)
-}
-
 impl<T> Rect<(f64, T)> {
     pub fn to_float(&self) -> Rect<f64> {
         Rect {
@@ -5416,39 +7974,49 @@ impl<T> Rect<(f64, T)> {
     }
 }
 
+impl Polygon<f64> {
+    /// Returns the bounding box of the vertices, if there are any.
+    pub fn bbox(&self) -> Option<Bbox> {
+        self.points
+            .iter()
+            .fold(None, |bbox, pt| Bbox::union(bbox, Some(Bbox::point(*pt))))
+    }
+}
+
 impl Rect<f64> {
-    fn transform(&self, reflect_vert: bool, angle: Rotation) -> Self {
-        let mat = tmat(angle, reflect_vert);
-        let p0p = ifmatvec(mat, (self.x0, self.y0));
-        let p1p = ifmatvec(mat, (self.x1, self.y1));
+    /// Creates a construction rect covering `bbox`.
+    pub fn from_bbox(id: ObjectId, bbox: Bbox) -> Self {
+        Self {
+            layer: None,
+            id,
+            x0: bbox.x0,
+            y0: bbox.y0,
+            x1: bbox.x1,
+            y1: bbox.y1,
+            construction: true,
+            span: None,
+        }
+    }
+
+    pub fn bbox(&self) -> Bbox {
+        Bbox::new(self.x0, self.y0, self.x1, self.y1)
+    }
+
+    pub fn transform(&self, t: &Transform) -> Self {
+        let bbox = self.bbox().transform(t);
         Self {
             id: self.id,
             layer: self.layer.clone(),
-            x0: p0p.0.min(p1p.0),
-            y0: p0p.1.min(p1p.1),
-            x1: p0p.0.max(p1p.0),
-            y1: p0p.1.max(p1p.1),
+            x0: bbox.x0,
+            y0: bbox.y0,
+            x1: bbox.x1,
+            y1: bbox.y1,
             construction: self.construction,
             span: None,
         }
     }
 }
 
-fn cascade(
-    rot: Rotation,
-    refv: bool,
-    crot: Rotation,
-    crefv: bool,
-    cx: f64,
-    cy: f64,
-) -> (Rotation, bool, f64, f64) {
-    let mat = tmat(rot, refv);
-    let cmat = tmat(crot, crefv);
-    let (x, y) = ifmatvec(mat, (cx, cy));
-    let (rot, refv) = imat(mat * cmat);
-    (rot, refv, x, y)
-}
-
 impl SeqNum {
     #[inline]
     fn new() -> Self {
@@ -5476,6 +8044,400 @@ fn object_id(id: &mut u64) -> ObjectId {
 }
 
 impl CompiledData {
+    /// Bounding box of `cell`, i.e. of its root scope.
+    pub fn cell_bbox(&self, cell: CellId) -> Option<&Rect<f64>> {
+        let cell = &self.cells[&cell];
+        cell.scopes[&cell.root].bbox.as_ref()
+    }
+
+    /// Returns the stable ID of the object or scope whose source is at `span`.
+    pub fn stable_id(&self, span: &Span) -> Option<StableId> {
+        self.cells.values().find_map(|cell| {
+            cell.scopes
+                .values()
+                .find(|scope| &scope.span == span)
+                .map(|scope| scope.stable_id)
+                .or_else(|| {
+                    cell.stable_ids
+                        .iter()
+                        .find(|(obj, _)| cell.objects[*obj].span() == Some(span))
+                        .map(|(_, id)| *id)
+                })
+        })
+    }
+
+    /// Returns the source span of the object or scope with stable ID `id`.
+    pub fn span_of(&self, id: StableId) -> Option<&Span> {
+        self.cells.values().find_map(|cell| {
+            cell.scopes
+                .values()
+                .find(|scope| scope.stable_id == id)
+                .map(|scope| &scope.span)
+                .or_else(|| {
+                    cell.stable_ids
+                        .iter()
+                        .find(|(_, sid)| **sid == id)
+                        .and_then(|(obj, _)| cell.objects[obj].span())
+                })
+        })
+    }
+
+    /// Iterates over the non-construction rects of `cell` and the cells it
+    /// instantiates, in the coordinates of `cell`.
+    ///
+    /// Instances nested more than `max_depth` levels deep are not expanded.
+    /// Construction instances are skipped along with their contents.
+    pub fn iter_flat(
+        &self,
+        cell: CellId,
+        max_depth: Option<usize>,
+    ) -> impl Iterator<Item = FlatRect<'_>> + '_ {
+        self.flat_cells(cell, max_depth)
+            .flat_map(move |((cell, path), transform, depth)| {
+                self.emitted(cell)
+                    .filter_map(|obj| obj.get_rect())
+                    .filter(|rect| !rect.construction)
+                    .map(move |rect| {
+                        let mut path = path.clone();
+                        path.push(rect.id);
+                        FlatRect {
+                            layer: rect.layer.as_deref(),
+                            rect: rect.to_float().transform(&transform),
+                            path,
+                            transform,
+                            depth,
+                        }
+                    })
+            })
+    }
+
+    /// Iterates over the non-construction polygons of `cell` and the cells it
+    /// instantiates, yielding their layers and vertices in the coordinates of
+    /// `cell`.
+    ///
+    /// Instances are expanded as in [`CompiledData::iter_flat`].
+    pub fn iter_flat_polygons(
+        &self,
+        cell: CellId,
+        max_depth: Option<usize>,
+    ) -> impl Iterator<Item = (&str, Vec<(f64, f64)>)> + '_ {
+        self.flat_cells(cell, max_depth)
+            .flat_map(move |((cell, _), transform, _)| {
+                self.emitted(cell)
+                    .filter_map(|obj| obj.get_polygon())
+                    .filter(|polygon| !polygon.construction)
+                    .map(move |polygon| {
+                        (
+                            polygon.layer.as_str(),
+                            polygon
+                                .points
+                                .iter()
+                                .map(|pt| transform.apply(*pt))
+                                .collect(),
+                        )
+                    })
+            })
+    }
+
+    /// Iterates over `scope` of `cell`, its dynamic children, and the scopes of
+    /// the cells it instantiates, yielding each with its transform to `cell` and
+    /// its depth.
+    ///
+    /// Both a dynamic child scope and the root scope of an instantiated cell
+    /// are one level deeper than their parent. Each element of an instance
+    /// array is visited separately, placed at its snapped transform if
+    /// `snapped` is set. Construction instances are skipped.
+    ///
+    /// `child` is called with each visited scope, its depth, and the cell and
+    /// scope of one of its children, and returns the data to pass down to that
+    /// child, or `None` to skip it along with its descendants. The starting
+    /// scope gets `data`.
+    pub fn iter_flat_scopes<T: Clone>(
+        &self,
+        cell: CellId,
+        scope: ScopeId,
+        snapped: bool,
+        data: T,
+        mut child: impl FnMut(&FlatScope<T>, usize, CellId, ScopeId) -> Option<T>,
+    ) -> impl Iterator<Item = (FlatScope<T>, Transform, usize)> + '_ {
+        let root = FlatScope {
+            cell,
+            scope,
+            path: Vec::new(),
+            data,
+        };
+        Flatten::new(root, None, move |parent: &FlatScope<T>, _, depth| {
+            let cell_info = &self.cells[&parent.cell];
+            let scope_info = &cell_info.scopes[&parent.scope];
+            let mut children = Vec::new();
+            for (obj, _) in &scope_info.emit {
+                let value = &cell_info.objects[obj];
+                let Some(inst) = value.instance().filter(|inst| !inst.construction) else {
+                    continue;
+                };
+                let inst_root = self.cells[&inst.cell].root;
+                let Some(data) = child(parent, depth, inst.cell, inst_root) else {
+                    continue;
+                };
+                let xforms = if snapped {
+                    self.snapped_transforms(cell_info, *obj)
+                        .expect("instance was not snapped")
+                } else {
+                    value.instance_transforms()
+                };
+                let mut path = parent.path.clone();
+                path.push(*obj);
+                for xform in xforms {
+                    let inst_scope = FlatScope {
+                        cell: inst.cell,
+                        scope: inst_root,
+                        path: path.clone(),
+                        data: data.clone(),
+                    };
+                    children.push((inst_scope, xform));
+                }
+            }
+            for scope in &scope_info.children {
+                if let Some(data) = child(parent, depth, parent.cell, *scope) {
+                    let child_scope = FlatScope {
+                        cell: parent.cell,
+                        scope: *scope,
+                        path: parent.path.clone(),
+                        data,
+                    };
+                    children.push((child_scope, Transform::identity()));
+                }
+            }
+            children
+        })
+    }
+
+    /// Iterates over `cell` and the cells it instantiates, along with the IDs of
+    /// the instances leading to each, its transform to `cell`, and its depth.
+    pub(crate) fn flat_cells(
+        &self,
+        cell: CellId,
+        max_depth: Option<usize>,
+    ) -> impl Iterator<Item = ((CellId, Vec<ObjectId>), Transform, usize)> + '_ {
+        let root = self.cells[&cell].root;
+        self.iter_flat_scopes(cell, root, false, (), move |parent, _, cell, scope| {
+            let instance = scope == self.cells[&cell].root;
+            (!instance || max_depth.is_none_or(|max| parent.path.len() < max)).then_some(())
+        })
+        .filter(move |(scope, _, _)| scope.scope == self.cells[&scope.cell].root)
+        .map(|(scope, transform, _)| {
+            let depth = scope.path.len();
+            ((scope.cell, scope.path), transform, depth)
+        })
+    }
+
+    /// Objects emitted by the root scope of `cell` and its dynamic children.
+    pub(crate) fn emitted(&self, cell: CellId) -> impl Iterator<Item = &SolvedValue> + '_ {
+        let cell = &self.cells[&cell];
+        let mut stack = vec![cell.root];
+        std::iter::from_fn(move || {
+            let scope = &cell.scopes[&stack.pop()?];
+            stack.extend(scope.children.iter().rev());
+            Some(scope.emit.iter().map(move |(obj, _)| &cell.objects[obj]))
+        })
+        .flatten()
+    }
+
+    /// Flattens the top cell, replacing its instances with the transformed
+    /// geometry of the cells they instantiate.
+    ///
+    /// Only instances nested at most `depth` levels deep are expanded; the
+    /// instances below them are placed directly in the top cell instead. `None`
+    /// flattens the whole hierarchy. Construction instances and instances of
+    /// extern cells, whose layout only exists in GDS, are never expanded.
+    /// Bindings and fields of the top cell that refer to expanded instances are
+    /// removed.
+    pub fn flatten(&mut self, depth: Option<usize>) {
+        if depth == Some(0) {
+            return;
+        }
+        let top = &self.cells[&self.top];
+        let mut next_id = top.objects.keys().map(|id| id.0 + 1).max().unwrap_or(0);
+        let mut expanded = IndexSet::new();
+        let mut flat = Vec::new();
+        for scope in top.scopes.values() {
+            for (id, emit) in &scope.emit {
+                let obj = &top.objects[id];
+                let Some(inst) = obj.instance() else {
+                    continue;
+                };
+                if inst.construction || self.cells[&inst.cell].external {
+                    continue;
+                }
+                expanded.insert(*id);
+                let mut objs = Vec::new();
+                for xform in obj.instance_transforms() {
+                    self.flatten_cell(
+                        inst.cell,
+                        xform,
+                        depth.map(|depth| depth - 1),
+                        &mut next_id,
+                        &mut objs,
+                    );
+                }
+                flat.extend(objs.into_iter().map(|obj| (obj, emit.clone())));
+            }
+        }
+
+        let top = self.cells.get_mut(&self.top).unwrap();
+        let refers_to_expanded = |objs: &Arrayed<ObjectId>| {
+            let mut found = false;
+            objs.for_each(&mut |obj| found |= expanded.contains(obj));
+            found
+        };
+        for scope in top.scopes.values_mut() {
+            scope.emit.retain(|(obj, _)| !expanded.contains(obj));
+            scope
+                .bindings
+                .retain(|_, (_, objs)| !refers_to_expanded(objs));
+        }
+        top.fields.retain(|_, objs| !refers_to_expanded(objs));
+        top.objects.retain(|obj, _| !expanded.contains(obj));
+        top.stable_ids.retain(|obj, _| !expanded.contains(obj));
+        let root = top.scopes.get_mut(&top.root).unwrap();
+        for (obj, emit) in flat {
+            let id = obj.id();
+            root.emit.push((id, emit));
+            top.objects.insert(id, obj);
+        }
+        top.dbu = snap_objects(&top.objects, self.dbu);
+    }
+
+    /// Appends the geometry emitted by `cell` to `out`, transformed by `xform`
+    /// and with fresh object IDs, expanding instances up to `depth` levels deep.
+    fn flatten_cell(
+        &self,
+        cell: CellId,
+        xform: Transform,
+        depth: Option<usize>,
+        next_id: &mut u64,
+        out: &mut Vec<SolvedValue>,
+    ) {
+        for obj in self.emitted(cell) {
+            match obj {
+                SolvedValue::Rect(rect) if !rect.construction => {
+                    let id = object_id(next_id);
+                    let bbox = rect.to_float().bbox();
+                    match (&rect.layer, xform.is_manhattan()) {
+                        (Some(layer), false) => out.push(SolvedValue::Polygon(Polygon {
+                            id,
+                            layer: layer.clone(),
+                            points: bbox.corners().map(|pt| xform.apply(pt)).to_vec(),
+                            construction: false,
+                            span: rect.span.clone(),
+                        })),
+                        _ => {
+                            let bbox = bbox.transform(&xform);
+                            let coord = |v: f64| (v, LinearExpr::from(v));
+                            out.push(SolvedValue::Rect(Rect {
+                                layer: rect.layer.clone(),
+                                id,
+                                x0: coord(bbox.x0),
+                                y0: coord(bbox.y0),
+                                x1: coord(bbox.x1),
+                                y1: coord(bbox.y1),
+                                construction: false,
+                                span: rect.span.clone(),
+                            }));
+                        }
+                    }
+                }
+                SolvedValue::Polygon(polygon) if !polygon.construction => {
+                    out.push(SolvedValue::Polygon(Polygon {
+                        id: object_id(next_id),
+                        points: polygon.points.iter().map(|pt| xform.apply(*pt)).collect(),
+                        ..polygon.clone()
+                    }));
+                }
+                SolvedValue::Pin(pin) => {
+                    let bbox = pin.bbox().transform(&xform);
+                    out.push(SolvedValue::Pin(Pin {
+                        id: object_id(next_id),
+                        x0: bbox.x0,
+                        y0: bbox.y0,
+                        x1: bbox.x1,
+                        y1: bbox.y1,
+                        ..pin.clone()
+                    }));
+                }
+                SolvedValue::Text(text) => {
+                    let (x, y) = xform.apply((text.x, text.y));
+                    out.push(SolvedValue::Text(Text {
+                        id: object_id(next_id),
+                        x,
+                        y,
+                        ..text.clone()
+                    }));
+                }
+                SolvedValue::Instance(_) | SolvedValue::InstanceArray(_) => {
+                    let inst = obj.instance().unwrap();
+                    if inst.construction {
+                        continue;
+                    }
+                    if depth != Some(0) && !self.cells[&inst.cell].external {
+                        for child in obj.instance_transforms() {
+                            self.flatten_cell(
+                                inst.cell,
+                                xform.compose(&child),
+                                depth.map(|depth| depth - 1),
+                                next_id,
+                                out,
+                            );
+                        }
+                        continue;
+                    }
+                    let place = |xform: Transform, id| {
+                        let (angle, reflect) = xform.orientation();
+                        let (x, y) = xform.offset;
+                        SolvedInstance {
+                            id,
+                            x,
+                            y,
+                            angle,
+                            reflect,
+                            ..inst.clone()
+                        }
+                    };
+                    match obj {
+                        SolvedValue::InstanceArray(array) => {
+                            match array.array.transform(&xform) {
+                                Some(dims) => {
+                                    out.push(SolvedValue::InstanceArray(SolvedInstanceArray {
+                                        inst: place(
+                                            xform.compose(&inst.transform()),
+                                            object_id(next_id),
+                                        ),
+                                        array: dims,
+                                    }))
+                                }
+                                // Arrays that are no longer axis-aligned are placed
+                                // element by element.
+                                None => {
+                                    for child in array.transforms() {
+                                        out.push(SolvedValue::Instance(place(
+                                            xform.compose(&child),
+                                            object_id(next_id),
+                                        )));
+                                    }
+                                }
+                            }
+                        }
+                        _ => out.push(SolvedValue::Instance(place(
+                            xform.compose(&inst.transform()),
+                            object_id(next_id),
+                        ))),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub fn reachable_objs(&self, cell: CellId, scope: ScopeId) -> IndexMap<ObjectId, String> {
         let mut set = Default::default();
         self.reachable_objs_inner(cell, scope, SeqNum::end(), "", &mut set);
@@ -5501,7 +8463,8 @@ impl CompiledData {
                     SolvedValue::Rect(r) => {
                         set.insert(r.id, format!("{}{}", name_prefix, name));
                     }
-                    SolvedValue::Instance(inst) => {
+                    SolvedValue::Instance(inst)
+                    | SolvedValue::InstanceArray(SolvedInstanceArray { inst, .. }) => {
                         set.insert(inst.id, format!("{}{}", name_prefix, name));
                     }
                     _ => (),
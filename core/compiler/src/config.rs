@@ -1,8 +1,13 @@
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use anyhow::bail;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
+use crate::drc::DrcRules;
+use crate::lint::{LintLevel, LintRule};
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Config {
     #[serde(default)]
@@ -10,8 +15,277 @@ pub struct Config {
     /// Additional modules to add to the current crate.
     #[serde(default)]
     pub mods: IndexMap<String, PathBuf>,
+    /// Cell invocations exported by `argon gds --all`, e.g. `inv(1200., 2000., 4)`.
+    ///
+    /// Defaults to every cell without parameters in the root module.
+    #[serde(default)]
+    pub cells: Vec<String>,
+    /// Cells built by `argon build`, declared as `[[target]]` tables.
+    #[serde(default, rename = "target")]
+    pub targets: Vec<Target>,
+    /// Layer connectivity used to extract nets for netlist checks.
+    #[serde(default)]
+    pub connect: Vec<Connect>,
+    /// Pattern density rules checked after compilation.
+    #[serde(default)]
+    pub density: Vec<DensityRule>,
+    /// Rule deck of minimum widths, spacings, and enclosures checked after
+    /// compilation, e.g. `drc = "rules.toml"`. See [`crate::drc`].
+    #[serde(default)]
+    pub drc: Option<PathBuf>,
+    /// Minimum width of the shapes on each layer, in layout units, e.g.
+    /// `met1 = 140.` under `[min_width]`. Checked by the `min-width` lint.
+    #[serde(default)]
+    pub min_width: IndexMap<String, f64>,
+    /// Levels of the lints checked by `argon lint`, keyed by rule name, e.g.
+    /// `magic-number = "allow"`.
+    #[serde(default)]
+    pub lint: IndexMap<LintRule, LintLevel>,
+    /// Database unit of exported layouts in layout units, i.e. nanometers,
+    /// e.g. `dbu = 0.5`. Geometry is snapped to multiples of it.
+    ///
+    /// Defaults to 1.
+    #[serde(default)]
+    pub dbu: Option<f64>,
+    /// Manufacturing grid in layout units, e.g. `grid = 5.`. Values generated by
+    /// the GUI are rounded to multiples of it.
+    ///
+    /// Defaults to the database unit.
+    #[serde(default)]
+    pub grid: Option<f64>,
+    /// GDS files containing the layout of `extern cell`s, keyed by cell name,
+    /// e.g. `sram = "macros/sram.gds"` under `[extern]`. Each file must contain
+    /// a struct named after its cell.
+    #[serde(default, rename = "extern")]
+    pub extern_gds: IndexMap<String, PathBuf>,
+    /// How the language server starts the GUI.
+    #[serde(default)]
+    pub gui: GuiConfig,
+    /// Options of the constraint solver.
+    #[serde(default)]
+    pub solver: SolverConfig,
+}
+
+/// Options of the constraint solver, declared under `[solver]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SolverConfig {
+    /// Algorithm used to solve systems of linear constraints.
+    pub backend: SolverBackend,
+    /// Magnitude below which values are treated as zero, e.g. when checking
+    /// constraints for consistency or computing the rank of a system.
+    pub tolerance: f64,
+    /// Step that solved values are rounded to, in layout units. Values further
+    /// than `tolerance` from a multiple of it are reported as invalid rounding.
+    pub round_step: f64,
+    /// Maximum number of solver iterations per cell. Unlimited by default.
+    pub max_iterations: Option<u64>,
+    /// Maximum time to spend compiling a cell invocation, in seconds.
+    /// Unlimited by default.
+    pub timeout: Option<f64>,
+    /// Priorities of fallback constraints, keyed by keyword argument, e.g.
+    /// `y0i = 10`. When a cell is underconstrained, the unused fallback
+    /// constraint with the highest priority is applied first.
+    ///
+    /// Keywords not listed keep their default priorities, from highest to
+    /// lowest: `x0i`, `x1i`, `y0i`, `y1i`, `xi`, `yi`.
+    pub fallback_priority: IndexMap<String, i32>,
 }
 
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            backend: SolverBackend::default(),
+            tolerance: 1e-8,
+            round_step: 0.1,
+            max_iterations: None,
+            timeout: None,
+            fallback_priority: IndexMap::new(),
+        }
+    }
+}
+
+/// Algorithm used to solve systems of linear constraints.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SolverBackend {
+    /// Eliminates variables defined by constraints on at most two variables,
+    /// then solves what remains with an SVD.
+    #[default]
+    Eliminate,
+    /// Solves every connected component of the system with an SVD.
+    Svd,
+}
+
+impl SolverConfig {
+    /// Checks that the options are in range.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.tolerance.is_nan() || self.tolerance < 0. {
+            bail!(
+                "solver tolerance must be non-negative, found {}",
+                self.tolerance
+            );
+        }
+        if self.round_step.is_nan() || self.round_step <= 0. {
+            bail!(
+                "solver round step must be positive, found {}",
+                self.round_step
+            );
+        }
+        if let Some(timeout) = self.timeout
+            && (timeout.is_nan() || timeout < 0.)
+        {
+            bail!("solver timeout must be non-negative, found {timeout}");
+        }
+        #[cfg(not(feature = "std-time"))]
+        if self.timeout.is_some() {
+            bail!("solver timeout requires the compiler's `std-time` feature");
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SolverBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eliminate" => Ok(Self::Eliminate),
+            "svd" => Ok(Self::Svd),
+            _ => Err(format!(
+                "unknown solver backend `{s}`; expected `eliminate` or `svd`"
+            )),
+        }
+    }
+}
+
+/// How the language server starts the GUI, declared under `[gui]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GuiConfig {
+    /// GUI binary to run, relative to the workspace root. Defaults to the
+    /// first `gui` binary found next to the language server or on `PATH`.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Index of the display to open the GUI window on. Defaults to the
+    /// primary display.
+    #[serde(default)]
+    pub display: Option<usize>,
+    /// Position of the top left corner of the GUI window relative to its
+    /// display, in pixels, e.g. `[100, 50]`.
+    #[serde(default)]
+    pub position: Option<[f32; 2]>,
+}
+
+/// A cell invocation built by `argon build`, similar to a Cargo `[[bin]]` target.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Target {
+    /// Cell invocation with the target's default parameters, e.g. `inv(1200., 2000., 4)`.
+    pub cell: String,
+    /// Name used to select the target on the command line. Defaults to the
+    /// invocation with its punctuation replaced, e.g. `inv_1200_2000_4`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// GDS file to write, relative to the workspace root. Defaults to a file
+    /// named after the target in the output directory.
+    #[serde(default)]
+    pub out: Option<PathBuf>,
+}
+
+impl Target {
+    /// Path of the invoked cell, e.g. `inv` for `inv(1200., 2000., 4)`.
+    pub fn cell_path(&self) -> &str {
+        self.cell.split('(').next().unwrap_or_default().trim()
+    }
+}
+
+/// Two conducting layers that connect where they overlap, or where both
+/// overlap a shape on `via` if one is given.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Connect {
+    pub top: String,
+    pub bot: String,
+    #[serde(default)]
+    pub via: Option<String>,
+}
+
+/// Bounds on the fraction of each window covered by shapes on `layer`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DensityRule {
+    pub layer: String,
+    /// Side length of the square density window.
+    pub window: f64,
+    /// Distance between adjacent windows. Defaults to half the window size.
+    #[serde(default)]
+    pub step: Option<f64>,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    /// Fill to insert in windows below the minimum density.
+    #[serde(default)]
+    pub fill: Option<FillRule>,
+}
+
+/// Square fill shapes placed on a regular grid away from existing geometry.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FillRule {
+    /// Layer to draw fill on. Fill counts towards the density of the rule's layer.
+    pub layer: String,
+    /// Side length of each fill square.
+    pub size: f64,
+    /// Minimum spacing between fill and other shapes, including other fill.
+    pub spacing: f64,
+}
+
+impl Config {
+    /// The layer properties file, resolving relative paths against `root_dir`.
+    ///
+    /// Falls back to the bundled SKY130 layer properties.
+    pub fn lyp_path(&self, root_dir: &Path) -> PathBuf {
+        match &self.lyp {
+            Some(lyp) if lyp.is_relative() => root_dir.join(lyp),
+            Some(lyp) => lyp.clone(),
+            None => PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../../pdks/sky130/sky130.lyp"
+            )),
+        }
+    }
+
+    /// Reads the rule deck, resolving a relative path against `root_dir`.
+    ///
+    /// Returns an empty deck if none is configured.
+    pub fn drc_rules(&self, root_dir: &Path) -> anyhow::Result<DrcRules> {
+        match &self.drc {
+            Some(path) => DrcRules::load(root_dir.join(path)),
+            None => Ok(DrcRules::default()),
+        }
+    }
+
+    /// The GDS files of `extern cell`s, resolving relative paths against `root_dir`.
+    pub fn extern_gds_paths(&self, root_dir: &Path) -> IndexMap<String, PathBuf> {
+        self.extern_gds
+            .iter()
+            .map(|(cell, path)| (cell.clone(), root_dir.join(path)))
+            .collect()
+    }
+}
+
+#[cfg(feature = "fs")]
 pub fn parse_config(manifest_path: impl AsRef<Path>) -> anyhow::Result<Config> {
-    Ok(toml::from_str(&std::fs::read_to_string(manifest_path)?)?)
+    parse_config_str(&std::fs::read_to_string(manifest_path)?)
+}
+
+/// Parses and validates the contents of an `Argon.toml`.
+pub fn parse_config_str(text: &str) -> anyhow::Result<Config> {
+    let config: Config = toml::from_str(text)?;
+    config.solver.validate()?;
+    for (key, value) in [("dbu", config.dbu), ("grid", config.grid)] {
+        if let Some(value) = value
+            && (value.is_nan() || value <= 0.)
+        {
+            bail!("{key} must be positive, found {value}");
+        }
+    }
+    Ok(config)
 }
@@ -0,0 +1,228 @@
+//! Compile-time evaluation of `const` declarations.
+//!
+//! Constants are declared at module scope, e.g. `const POLY_PITCH: Int = 430;`,
+//! and may refer to constants declared before them in the same module.
+use std::fmt::{self, Display};
+
+use arcstr::Substr;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{
+        AstMetadata, BinOp, ComparisonOp, Decl, Expr, Span, TySpecKind, UnaryOp,
+        annotated::AnnotatedAst,
+    },
+    compile::{StaticError, StaticErrorKind, Ty},
+};
+
+/// The value of a constant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConstValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl ConstValue {
+    pub fn ty(&self) -> Ty {
+        match self {
+            ConstValue::Bool(_) => Ty::Bool,
+            ConstValue::Int(_) => Ty::Int,
+            ConstValue::Float(_) => Ty::Float,
+            ConstValue::String(_) => Ty::String,
+        }
+    }
+}
+
+impl Display for ConstValue {
+    /// Formats the value as an Argon literal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Bool(b) => write!(f, "{b}"),
+            ConstValue::Int(x) => write!(f, "{x}"),
+            ConstValue::Float(x) => write!(f, "{x:?}"),
+            ConstValue::String(s) => write!(f, "\"{s}\""),
+        }
+    }
+}
+
+/// A constant declared in a module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Constant {
+    pub value: ConstValue,
+    /// Span of the constant's name in its declaration.
+    pub span: Span,
+}
+
+/// Evaluates the constants declared in `ast`, in declaration order.
+///
+/// Constants whose values are not compile-time constants, or do not have the
+/// declared type, are omitted from the result and reported as errors.
+pub fn eval_consts<T: AstMetadata>(
+    ast: &AnnotatedAst<T>,
+) -> (IndexMap<String, Constant>, Vec<StaticError>) {
+    let mut consts = IndexMap::new();
+    let mut errors = Vec::new();
+    let span = |span| Span {
+        path: ast.path.clone(),
+        span,
+    };
+    for decl in &ast.ast.decls {
+        let Decl::Constant(c) = decl else {
+            continue;
+        };
+        let name = c.name.name.to_string();
+        if consts.contains_key(&name) {
+            errors.push(StaticError {
+                span: span(c.name.span),
+                kind: StaticErrorKind::DuplicateNameDeclaration,
+            });
+            continue;
+        }
+        let Some(expected) = Ty::from_name(c.ty.name.as_str()) else {
+            errors.push(StaticError {
+                span: span(c.ty.span),
+                kind: StaticErrorKind::UnknownType,
+            });
+            continue;
+        };
+        match eval_const_expr(&c.value, &consts) {
+            Ok(value) if value.ty() == expected => {
+                consts.insert(
+                    name,
+                    Constant {
+                        value,
+                        span: span(c.name.span),
+                    },
+                );
+            }
+            Ok(value) => errors.push(StaticError {
+                span: span(c.value.span()),
+                kind: StaticErrorKind::IncorrectTy {
+                    expected,
+                    found: value.ty(),
+                },
+            }),
+            Err((err_span, kind)) => errors.push(StaticError {
+                span: span(err_span),
+                kind,
+            }),
+        }
+    }
+    (consts, errors)
+}
+
+type EvalResult = Result<ConstValue, (cfgrammar::Span, StaticErrorKind)>;
+
+/// Evaluates `expr`, which may only contain literals, arithmetic, comparisons,
+/// casts, and references to the constants in `consts`.
+pub fn eval_const_expr<T: AstMetadata>(
+    expr: &Expr<Substr, T>,
+    consts: &IndexMap<String, Constant>,
+) -> EvalResult {
+    let span = expr.span();
+    let err = |kind| Err((span, kind));
+    match expr {
+        Expr::IntLiteral(lit) => Ok(ConstValue::Int(lit.value)),
+        Expr::FloatLiteral(lit) => Ok(ConstValue::Float(lit.value)),
+        Expr::BoolLiteral(lit) => Ok(ConstValue::Bool(lit.value)),
+        Expr::StringLiteral(lit) => Ok(ConstValue::String(lit.value.to_string())),
+        Expr::IdentPath(path) => match path.path.as_slice() {
+            [ident] => match consts.get(ident.name.as_str()) {
+                Some(c) => Ok(c.value.clone()),
+                None => err(StaticErrorKind::NotConstant),
+            },
+            _ => err(StaticErrorKind::NotConstant),
+        },
+        Expr::UnaryOp(e) => match (e.op, eval_const_expr(&e.operand, consts)?) {
+            (UnaryOp::Neg, ConstValue::Int(x)) => match x.checked_neg() {
+                Some(x) => Ok(ConstValue::Int(x)),
+                None => err(StaticErrorKind::IntegerOverflow),
+            },
+            (UnaryOp::Neg, ConstValue::Float(x)) => Ok(ConstValue::Float(-x)),
+            (UnaryOp::Not, ConstValue::Bool(b)) => Ok(ConstValue::Bool(!b)),
+            _ => err(StaticErrorKind::UnaryOpInvalidType),
+        },
+        Expr::BinOp(e) => {
+            let (left, right) = (
+                eval_const_expr(&e.left, consts)?,
+                eval_const_expr(&e.right, consts)?,
+            );
+            match (left, right) {
+                (ConstValue::Int(l), ConstValue::Int(r)) => {
+                    if matches!(e.op, BinOp::Div | BinOp::Rem) && r == 0 {
+                        return err(StaticErrorKind::DivisionByZero);
+                    }
+                    let value = match e.op {
+                        BinOp::Add => l.checked_add(r),
+                        BinOp::Sub => l.checked_sub(r),
+                        BinOp::Mul => l.checked_mul(r),
+                        BinOp::Div => l.checked_div(r),
+                        BinOp::Rem => l.checked_rem(r),
+                    };
+                    match value {
+                        Some(value) => Ok(ConstValue::Int(value)),
+                        None => err(StaticErrorKind::IntegerOverflow),
+                    }
+                }
+                (ConstValue::Float(l), ConstValue::Float(r)) => match e.op {
+                    BinOp::Add => Ok(ConstValue::Float(l + r)),
+                    BinOp::Sub => Ok(ConstValue::Float(l - r)),
+                    BinOp::Mul => Ok(ConstValue::Float(l * r)),
+                    BinOp::Div => Ok(ConstValue::Float(l / r)),
+                    BinOp::Rem => err(StaticErrorKind::BinOpInvalidType(Ty::Float)),
+                },
+                (ConstValue::Int(_), ConstValue::Float(_))
+                | (ConstValue::Float(_), ConstValue::Int(_)) => {
+                    err(StaticErrorKind::BinOpMismatchedTypes)
+                }
+                (l, _) => err(StaticErrorKind::BinOpInvalidType(l.ty())),
+            }
+        }
+        Expr::Comparison(e) => {
+            let (left, right) = (
+                eval_const_expr(&e.left, consts)?,
+                eval_const_expr(&e.right, consts)?,
+            );
+            let ord = match (&left, &right) {
+                (ConstValue::Int(l), ConstValue::Int(r)) => l.cmp(r),
+                (ConstValue::Float(l), ConstValue::Float(r)) => {
+                    if matches!(e.op, ComparisonOp::Eq | ComparisonOp::Ne) {
+                        return err(StaticErrorKind::FloatEquality);
+                    }
+                    l.total_cmp(r)
+                }
+                (ConstValue::Bool(l), ConstValue::Bool(r))
+                    if matches!(e.op, ComparisonOp::Eq | ComparisonOp::Ne) =>
+                {
+                    l.cmp(r)
+                }
+                _ => return err(StaticErrorKind::ComparisonInvalidType),
+            };
+            Ok(ConstValue::Bool(match e.op {
+                ComparisonOp::Eq => ord.is_eq(),
+                ComparisonOp::Ne => ord.is_ne(),
+                ComparisonOp::Geq => ord.is_ge(),
+                ComparisonOp::Gt => ord.is_gt(),
+                ComparisonOp::Leq => ord.is_le(),
+                ComparisonOp::Lt => ord.is_lt(),
+            }))
+        }
+        Expr::Cast(e) => {
+            let ty = match &e.ty.kind {
+                TySpecKind::Ident(ident) => Ty::from_name(ident.name.as_str()),
+                _ => None,
+            };
+            match (eval_const_expr(&e.value, consts)?, ty) {
+                (ConstValue::Int(x), Some(Ty::Float)) => Ok(ConstValue::Float(x as f64)),
+                (ConstValue::Float(x), Some(Ty::Int)) => Ok(ConstValue::Int(x as i64)),
+                (x @ ConstValue::Int(_), Some(Ty::Int))
+                | (x @ ConstValue::Float(_), Some(Ty::Float)) => Ok(x),
+                _ => err(StaticErrorKind::InvalidCast),
+            }
+        }
+        _ => err(StaticErrorKind::NotConstant),
+    }
+}
@@ -0,0 +1,256 @@
+//! Integer coordinates for compiled geometry.
+//!
+//! The solver works in floating-point layout units. Once a cell is solved, its
+//! geometry is snapped to an integer grid of database units (DBU) in a single
+//! step, so that exporters and viewers consume exact integers instead of
+//! rounding floats like `0.999999` themselves.
+use argon_geometry::{Bbox, Transform};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::compile::{
+    ArrayDims, CompileOutput, CompiledCell, CompiledData, ExecErrorCompileOutput, ObjectId,
+    SolvedInstance, SolvedInstanceArray, SolvedValue,
+};
+
+/// A coordinate in database units.
+pub type Dbu = i64;
+
+/// Layout units per database unit used by the compiler.
+///
+/// One layout unit is one nanometer, which matches the database unit of
+/// exported GDS files.
+pub const DEFAULT_DBU: f64 = 1.;
+
+/// Snaps a coordinate in layout units to the nearest multiple of `dbu`.
+pub fn snap(coord: f64, dbu: f64) -> Dbu {
+    (coord / dbu).round() as Dbu
+}
+
+/// Maximum number of decimal places in a formatted grid value.
+const MAX_GRID_DECIMALS: usize = 9;
+
+/// Formats `value` as a float literal rounded to the nearest multiple of
+/// `grid`, e.g. `1.2999999999` becomes `1.3` on a grid of `0.005` and
+/// `1302.4` becomes `1300.` on a grid of `5.`.
+pub fn format_on_grid(value: f64, grid: f64) -> String {
+    if !(grid > 0. && grid.is_finite()) {
+        return format!("{value:?}");
+    }
+    // Rounding to as many decimal places as the grid has removes the error
+    // introduced by multiplying by a grid that is not exactly representable.
+    let decimals = (0..MAX_GRID_DECIMALS)
+        .find(|&d| {
+            let scaled = grid * 10f64.powi(d as i32);
+            (scaled - scaled.round()).abs() < 1e-6
+        })
+        .unwrap_or(MAX_GRID_DECIMALS);
+    let rounded = format!("{:.decimals$}", (value / grid).round() * grid);
+    // `+ 0.` collapses a possible `-0.` to `0.`.
+    let s = format!("{}", rounded.parse::<f64>().unwrap() + 0.);
+    if s.contains('.') { s } else { format!("{s}.") }
+}
+
+/// An axis-aligned rectangle in database units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DbuRect {
+    pub x0: Dbu,
+    pub y0: Dbu,
+    pub x1: Dbu,
+    pub y1: Dbu,
+}
+
+impl DbuRect {
+    /// Snaps the corners of `bbox` to the nearest multiple of `dbu`.
+    pub fn snap(bbox: Bbox, dbu: f64) -> Self {
+        Self {
+            x0: snap(bbox.x0, dbu),
+            y0: snap(bbox.y0, dbu),
+            x1: snap(bbox.x1, dbu),
+            y1: snap(bbox.y1, dbu),
+        }
+    }
+
+    /// Returns the rectangle in layout units.
+    pub fn to_bbox(&self, dbu: f64) -> Bbox {
+        Bbox::new(
+            self.x0 as f64 * dbu,
+            self.y0 as f64 * dbu,
+            self.x1 as f64 * dbu,
+            self.y1 as f64 * dbu,
+        )
+    }
+}
+
+/// The snapped geometry of a single [`SolvedValue`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DbuShape {
+    Rect(DbuRect),
+    /// The vertices of a polygon.
+    Polygon(Vec<(Dbu, Dbu)>),
+    /// The position of a text label or the origin of an instance.
+    Point(Dbu, Dbu),
+}
+
+impl DbuShape {
+    pub fn rect(&self) -> Option<DbuRect> {
+        match self {
+            DbuShape::Rect(rect) => Some(*rect),
+            DbuShape::Polygon(_) | DbuShape::Point(..) => None,
+        }
+    }
+
+    pub fn polygon(&self) -> Option<&[(Dbu, Dbu)]> {
+        match self {
+            DbuShape::Polygon(points) => Some(points),
+            DbuShape::Rect(_) | DbuShape::Point(..) => None,
+        }
+    }
+
+    pub fn point(&self) -> Option<(Dbu, Dbu)> {
+        match self {
+            DbuShape::Point(x, y) => Some((*x, *y)),
+            DbuShape::Rect(_) | DbuShape::Polygon(_) => None,
+        }
+    }
+}
+
+/// Snaps the geometry of each object in `objects` to a grid of `dbu` layout
+/// units. Dimensions have no geometry of their own and are skipped.
+pub(crate) fn snap_objects(
+    objects: &IndexMap<ObjectId, SolvedValue>,
+    dbu: f64,
+) -> IndexMap<ObjectId, DbuShape> {
+    objects
+        .iter()
+        .filter_map(|(id, obj)| {
+            let shape = match obj {
+                SolvedValue::Rect(rect) => {
+                    DbuShape::Rect(DbuRect::snap(rect.to_float().bbox(), dbu))
+                }
+                SolvedValue::Polygon(polygon) => DbuShape::Polygon(
+                    polygon
+                        .points
+                        .iter()
+                        .map(|(x, y)| (snap(*x, dbu), snap(*y, dbu)))
+                        .collect(),
+                ),
+                SolvedValue::Pin(pin) => DbuShape::Rect(DbuRect::snap(pin.bbox(), dbu)),
+                SolvedValue::Text(text) => DbuShape::Point(snap(text.x, dbu), snap(text.y, dbu)),
+                SolvedValue::Instance(SolvedInstance { x, y, .. })
+                | SolvedValue::InstanceArray(SolvedInstanceArray {
+                    inst: SolvedInstance { x, y, .. },
+                    ..
+                }) => DbuShape::Point(snap(*x, dbu), snap(*y, dbu)),
+                SolvedValue::Dimension(_) => return None,
+            };
+            Some((*id, shape))
+        })
+        .collect()
+}
+
+impl CompiledCell {
+    /// Returns the snapped bounds of the rect or pin `id`, in database units.
+    pub fn dbu_rect(&self, id: ObjectId) -> Option<DbuRect> {
+        self.dbu.get(&id)?.rect()
+    }
+
+    /// Returns the snapped vertices of the polygon `id`, in database units.
+    pub fn dbu_polygon(&self, id: ObjectId) -> Option<&[(Dbu, Dbu)]> {
+        self.dbu.get(&id)?.polygon()
+    }
+
+    /// Returns the snapped position of the text or instance `id`, in database
+    /// units.
+    pub fn dbu_point(&self, id: ObjectId) -> Option<(Dbu, Dbu)> {
+        self.dbu.get(&id)?.point()
+    }
+}
+
+impl CompileOutput {
+    /// Runs [`CompiledData::set_dbu`] on the compiled cell, if any.
+    pub fn set_dbu(&mut self, dbu: Option<f64>) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.set_dbu(dbu);
+        }
+    }
+
+    /// Runs [`CompiledData::set_grid`] on the compiled cell, if any.
+    pub fn set_grid(&mut self, grid: Option<f64>) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.set_grid(grid);
+        }
+    }
+}
+
+impl CompiledData {
+    /// Re-snaps the geometry of every cell to a database unit of `dbu` layout
+    /// units, defaulting to [`DEFAULT_DBU`].
+    ///
+    /// Call this before [`CompiledData::set_grid`], whose default is the
+    /// database unit.
+    pub fn set_dbu(&mut self, dbu: Option<f64>) {
+        let dbu = dbu.unwrap_or(DEFAULT_DBU);
+        for cell in self.cells.values_mut() {
+            cell.dbu = snap_objects(&cell.objects, dbu);
+        }
+        self.dbu = dbu;
+    }
+
+    /// Sets the manufacturing grid, defaulting to the database unit.
+    pub fn set_grid(&mut self, grid: Option<f64>) {
+        self.grid = grid.unwrap_or(self.dbu);
+    }
+
+    /// Returns the snapped bounds of the rect `id` in `cell`, in layout units.
+    pub fn snapped_bbox(&self, cell: &CompiledCell, id: ObjectId) -> Option<Bbox> {
+        Some(cell.dbu_rect(id)?.to_bbox(self.dbu))
+    }
+
+    /// Returns the transform of the instance `id` in `cell`, or of the first
+    /// element of the instance array `id`, with its origin snapped to the
+    /// database grid.
+    pub fn snapped_transform(&self, cell: &CompiledCell, id: ObjectId) -> Option<Transform> {
+        let inst = cell.objects.get(&id)?.instance()?;
+        let (x, y) = cell.dbu_point(id)?;
+        Some(Transform::rotate(
+            inst.angle,
+            inst.reflect,
+            x as f64 * self.dbu,
+            y as f64 * self.dbu,
+        ))
+    }
+
+    /// Returns the transforms of the instance `id` in `cell`, or of each
+    /// element of the instance array `id`, snapped to the database grid.
+    ///
+    /// The pitch of an array is snapped separately from its origin, as in GDS
+    /// export.
+    pub fn snapped_transforms(&self, cell: &CompiledCell, id: ObjectId) -> Option<Vec<Transform>> {
+        let first = self.snapped_transform(cell, id)?;
+        Some(match cell.objects.get(&id)? {
+            SolvedValue::InstanceArray(array) => {
+                let array = ArrayDims {
+                    dx: snap(array.array.dx, self.dbu) as f64 * self.dbu,
+                    dy: snap(array.array.dy, self.dbu) as f64 * self.dbu,
+                    ..array.array
+                };
+                array
+                    .offsets()
+                    .map(|(dx, dy)| Transform::translate(dx, dy).compose(&first))
+                    .collect()
+            }
+            _ => vec![first],
+        })
+    }
+}
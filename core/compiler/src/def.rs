@@ -0,0 +1,232 @@
+//! DEF export of a cell's placed instances.
+use std::{fmt::Write, path::Path};
+
+use anyhow::{Result, anyhow};
+use argon_geometry::{Bbox, Rotation, Transform};
+use indexmap::IndexMap;
+use tracing::trace;
+use uniquify::Names;
+
+use crate::compile::{
+    CellId, CompileOutput, CompiledData, ExecErrorCompileOutput, ObjectId, SolvedInstance,
+    SolvedValue,
+};
+
+/// DEF database units per micron; one unit per layout unit (nm).
+pub const DEF_UNITS_PER_MICRON: u32 = 1000;
+
+fn def_coord(coord: f64) -> i64 {
+    coord.round() as i64
+}
+
+/// Returns the DEF orientation of a GDS-style transform, which reflects about
+/// the x-axis (if `reflect` is set) and then rotates counterclockwise.
+fn def_orientation(rot: Rotation, reflect: bool) -> &'static str {
+    match (rot, reflect) {
+        (Rotation::R0, false) => "N",
+        (Rotation::R90, false) => "W",
+        (Rotation::R180, false) => "S",
+        (Rotation::R270, false) => "E",
+        (Rotation::R0, true) => "FS",
+        (Rotation::R90, true) => "FW",
+        (Rotation::R180, true) => "FN",
+        (Rotation::R270, true) => "FE",
+    }
+}
+
+/// Makes `name` a valid DEF identifier by replacing whitespace and reserved characters.
+fn def_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_whitespace() || matches!(c, ';' | '(' | ')' | '#' | '-' | '+' | '"') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+impl CompileOutput {
+    /// Writes the top cell's instances, labels, and bounding box to a DEF file.
+    pub fn to_def(&self, out_path: impl AsRef<Path>) -> Result<()> {
+        let out_path = out_path.as_ref();
+        trace!("Exporting to def at {out_path:?}");
+        let (CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        })) = self
+        else {
+            return Err(anyhow!("no compiled cell to export"));
+        };
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(out_path, output.to_def_string()?)?;
+        Ok(())
+    }
+}
+
+impl CompiledData {
+    /// Names of the objects bound to variables in `cell`, with array elements
+    /// suffixed by their flat index.
+    pub(crate) fn object_names(&self, cell: CellId) -> IndexMap<ObjectId, String> {
+        let mut names = IndexMap::new();
+        for scope in self.cells[&cell].scopes.values() {
+            for (name, objs) in scope.bindings.values() {
+                let mut index = 0;
+                let is_array = objs.is_array();
+                objs.for_each(&mut |obj| {
+                    names.entry(*obj).or_insert_with(|| {
+                        if is_array {
+                            format!("{name}[{index}]")
+                        } else {
+                            name.clone()
+                        }
+                    });
+                    index += 1;
+                });
+            }
+        }
+        names
+    }
+
+    /// Renders the top cell as DEF.
+    ///
+    /// Top-level instances become components whose masters are named as in GDS
+    /// export, text labels become pins, and the cell's bounding box becomes the
+    /// die area. Rects in the top cell are not exported.
+    pub fn to_def_string(&self) -> Result<String> {
+        let top = &self.cells[&self.top];
+        let masters = self.gds_cell_names(self.top);
+        let design = &masters[&self.top];
+        let object_names = self.object_names(self.top);
+
+        let mut out = String::new();
+        writeln!(out, "VERSION 5.8 ;")?;
+        writeln!(out, "DIVIDERCHAR \"/\" ;")?;
+        writeln!(out, "BUSBITCHARS \"[]\" ;")?;
+        writeln!(out, "DESIGN {design} ;")?;
+        writeln!(out, "UNITS DISTANCE MICRONS {DEF_UNITS_PER_MICRON} ;")?;
+        if let Some(bbox) = self.cell_bbox(self.top) {
+            writeln!(
+                out,
+                "DIEAREA ( {} {} ) ( {} {} ) ;",
+                def_coord(bbox.x0),
+                def_coord(bbox.y0),
+                def_coord(bbox.x1),
+                def_coord(bbox.y1)
+            )?;
+        }
+
+        // Each element of an instance array becomes its own component.
+        let instances = top
+            .objects
+            .iter()
+            .filter_map(|(id, obj)| Some((*id, obj, obj.instance()?)))
+            .filter(|(_, _, inst)| !inst.construction)
+            .flat_map(|(id, obj, inst)| {
+                let array = obj.is_instance_array();
+                obj.instance_transforms()
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, xform)| (id, array.then_some(i), inst, xform))
+            })
+            .collect::<Vec<_>>();
+        let mut component_names = Names::new();
+        writeln!(out, "COMPONENTS {} ;", instances.len())?;
+        for (id, index, inst, xform) in instances {
+            let name = def_name(object_names.get(&id).map_or("inst", String::as_str));
+            let name = match index {
+                Some(i) => component_names.assign_name((id, index), &format!("{name}_{i}")),
+                None => component_names.assign_name((id, index), &name),
+            };
+            let (x, y, orient) = self.def_placement(inst, &xform)?;
+            writeln!(
+                out,
+                "- {name} {} + PLACED ( {} {} ) {orient} ;",
+                masters[&inst.cell],
+                def_coord(x),
+                def_coord(y)
+            )?;
+        }
+        writeln!(out, "END COMPONENTS")?;
+
+        let labels = top
+            .objects
+            .values()
+            .filter_map(|obj| match obj {
+                SolvedValue::Text(text) => Some(text),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut pin_names = Names::new();
+        writeln!(out, "PINS {} ;", labels.len())?;
+        for text in labels {
+            let name = pin_names.assign_name(text.id, &def_name(&text.text));
+            // Use the rect the label sits on as the pin shape, if any.
+            let shape = top
+                .objects
+                .values()
+                .find_map(|obj| match obj {
+                    SolvedValue::Rect(rect)
+                        if !rect.construction
+                            && rect.layer.as_deref() == Some(text.layer.as_str())
+                            && (rect.x0.0..=rect.x1.0).contains(&text.x)
+                            && (rect.y0.0..=rect.y1.0).contains(&text.y) =>
+                    {
+                        Some(Bbox::new(rect.x0.0, rect.y0.0, rect.x1.0, rect.y1.0))
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| Bbox::point((text.x, text.y)));
+            writeln!(out, "- {name} + NET {name} + DIRECTION INOUT + USE SIGNAL")?;
+            writeln!(
+                out,
+                "  + LAYER {} ( {} {} ) ( {} {} )",
+                text.layer,
+                def_coord(shape.x0 - text.x),
+                def_coord(shape.y0 - text.y),
+                def_coord(shape.x1 - text.x),
+                def_coord(shape.y1 - text.y)
+            )?;
+            writeln!(
+                out,
+                "  + PLACED ( {} {} ) N ;",
+                def_coord(text.x),
+                def_coord(text.y)
+            )?;
+        }
+        writeln!(out, "END PINS")?;
+        writeln!(out, "END DESIGN")?;
+        Ok(out)
+    }
+
+    /// Returns the DEF location and orientation of `inst` placed by `xform`.
+    ///
+    /// DEF places the lower-left corner of the master's oriented bounding box,
+    /// which is assumed to match the abstract's size and origin. DEF has no
+    /// orientation for rotations that are not multiples of 90 degrees.
+    fn def_placement(
+        &self,
+        inst: &SolvedInstance,
+        xform: &Transform,
+    ) -> Result<(f64, f64, &'static str)> {
+        let rot = xform.rotation().ok_or_else(|| {
+            anyhow!(
+                "instance of `{}` is rotated by {} degrees, which DEF cannot represent",
+                inst.cell_name,
+                xform.angle
+            )
+        })?;
+        let (x, y) = self
+            .cell_bbox(inst.cell)
+            .map(|bbox| {
+                let bbox = bbox.bbox().transform(xform);
+                (bbox.x0, bbox.y0)
+            })
+            .unwrap_or(xform.offset);
+        Ok((x, y, def_orientation(rot, xform.reflect)))
+    }
+}
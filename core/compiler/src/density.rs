@@ -0,0 +1,244 @@
+//! Pattern density checks and fill insertion.
+use std::fmt::{self, Display};
+
+use argon_geometry::Bbox;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compile::{CompileOutput, CompiledData, ExecErrorCompileOutput},
+    config::{DensityRule, FillRule},
+};
+
+/// A density window outside the bounds of a [`DensityRule`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DensityViolation {
+    pub layer: String,
+    /// The window, clipped to the top cell's bounding box.
+    pub window: Bbox,
+    pub density: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Display for DensityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = match (self.min, self.max) {
+            (Some(min), _) if self.density < min => format!("below minimum {min}"),
+            (_, Some(max)) => format!("above maximum {max}"),
+            _ => "out of bounds".to_string(),
+        };
+        write!(
+            f,
+            "{} density {:.3} {bound} in window ({}, {}) to ({}, {})",
+            self.layer,
+            self.density,
+            self.window.x0,
+            self.window.y0,
+            self.window.x1,
+            self.window.y1
+        )
+    }
+}
+
+/// A fill shape in the top cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FillShape {
+    pub layer: String,
+    pub bbox: Bbox,
+}
+
+impl CompileOutput {
+    /// Runs [`CompiledData::check_density`] on the compiled cell, if any.
+    pub fn check_density(&mut self, rules: &[DensityRule]) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.check_density(rules);
+        }
+    }
+}
+
+impl CompiledData {
+    /// Checks the density of the flattened top cell against `rules`.
+    ///
+    /// Windows step across the top cell's bounding box, with the last window in
+    /// each row and column aligned to the bounding box's upper edge. Rules with
+    /// fill insert fill into windows below the minimum density before checking.
+    /// Replaces any previous [`CompiledData::density_violations`] and [`CompiledData::fill`].
+    pub fn check_density(&mut self, rules: &[DensityRule]) {
+        self.density_violations.clear();
+        self.fill.clear();
+        let Some(extent) = self.cell_bbox(self.top).map(|rect| rect.bbox()) else {
+            return;
+        };
+        for rule in rules {
+            let step = rule.step.unwrap_or(rule.window / 2.);
+            if rule.window <= 0. || step <= 0. {
+                continue;
+            }
+            let fill_layer = rule.fill.as_ref().map(|fill| fill.layer.as_str());
+            let mut shapes = self
+                .iter_flat(self.top, None)
+                .filter(|rect| {
+                    rect.layer
+                        .is_some_and(|layer| layer == rule.layer || Some(layer) == fill_layer)
+                })
+                .map(|rect| rect.rect.bbox())
+                .collect::<Vec<_>>();
+            let windows = windows(extent, rule.window, step);
+            if let (Some(fill), Some(min)) = (&rule.fill, rule.min) {
+                for window in &windows {
+                    for bbox in insert_fill(&mut shapes, extent, *window, fill, min) {
+                        self.fill.push(FillShape {
+                            layer: fill.layer.clone(),
+                            bbox,
+                        });
+                    }
+                }
+            }
+            for window in windows {
+                let density = density(&shapes, window);
+                if rule.min.is_some_and(|min| density < min)
+                    || rule.max.is_some_and(|max| density > max)
+                {
+                    self.density_violations.push(DensityViolation {
+                        layer: rule.layer.clone(),
+                        window,
+                        density,
+                        min: rule.min,
+                        max: rule.max,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Start coordinates of windows of `size` stepping by `step` across `lo..hi`.
+fn starts(lo: f64, hi: f64, size: f64, step: f64) -> Vec<f64> {
+    let mut starts = vec![lo];
+    while let Some(&last) = starts.last()
+        && last + size < hi
+    {
+        starts.push((last + step).min(hi - size));
+    }
+    starts
+}
+
+fn windows(extent: Bbox, size: f64, step: f64) -> Vec<Bbox> {
+    let xs = starts(extent.x0, extent.x1, size, step);
+    let ys = starts(extent.y0, extent.y1, size, step);
+    ys.iter()
+        .flat_map(|&y| {
+            xs.iter().map(move |&x| {
+                Bbox::new(x, y, (x + size).min(extent.x1), (y + size).min(extent.y1))
+            })
+        })
+        .collect()
+}
+
+/// The intersection of `a` and `b`, if it has positive area.
+fn intersection(a: &Bbox, b: &Bbox) -> Option<Bbox> {
+    let bbox = Bbox {
+        x0: a.x0.max(b.x0),
+        y0: a.y0.max(b.y0),
+        x1: a.x1.min(b.x1),
+        y1: a.y1.min(b.y1),
+    };
+    (bbox.x0 < bbox.x1 && bbox.y0 < bbox.y1).then_some(bbox)
+}
+
+/// Area covered by the union of `rects`.
+fn union_area(rects: &[Bbox]) -> f64 {
+    let mut xs = rects.iter().flat_map(|r| [r.x0, r.x1]).collect::<Vec<_>>();
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+    let mut area = 0.;
+    for slab in xs.windows(2) {
+        let (xa, xb) = (slab[0], slab[1]);
+        let mut spans = rects
+            .iter()
+            .filter(|r| r.x0 <= xa && r.x1 >= xb)
+            .map(|r| (r.y0, r.y1))
+            .collect::<Vec<_>>();
+        spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut covered = 0.;
+        let mut current: Option<(f64, f64)> = None;
+        for (y0, y1) in spans {
+            match current {
+                Some((c0, c1)) if y0 <= c1 => current = Some((c0, c1.max(y1))),
+                _ => {
+                    if let Some((c0, c1)) = current {
+                        covered += c1 - c0;
+                    }
+                    current = Some((y0, y1));
+                }
+            }
+        }
+        if let Some((c0, c1)) = current {
+            covered += c1 - c0;
+        }
+        area += covered * (xb - xa);
+    }
+    area
+}
+
+fn density(shapes: &[Bbox], window: Bbox) -> f64 {
+    let clipped = shapes
+        .iter()
+        .filter_map(|shape| intersection(shape, &window))
+        .collect::<Vec<_>>();
+    union_area(&clipped) / (window.width() * window.height())
+}
+
+/// Adds fill squares in `window` to `shapes` until the window reaches `min`
+/// density or runs out of room, returning the new squares.
+///
+/// Squares lie on a grid anchored at the lower-left corner of `extent`, so
+/// fill from overlapping windows lines up.
+fn insert_fill(
+    shapes: &mut Vec<Bbox>,
+    extent: Bbox,
+    window: Bbox,
+    fill: &FillRule,
+    min: f64,
+) -> Vec<Bbox> {
+    let area = window.width() * window.height();
+    let current = density(shapes, window);
+    if current >= min || fill.size <= 0. {
+        return Vec::new();
+    }
+    let mut needed = ((min - current) * area / (fill.size * fill.size)).ceil() as usize;
+    let pitch = fill.size + fill.spacing;
+    let first = |lo: f64, origin: f64| origin + ((lo - origin) / pitch).ceil().max(0.) * pitch;
+    let mut added = Vec::new();
+    let mut y = first(window.y0, extent.y0 + fill.spacing);
+    while needed > 0 && y + fill.size <= window.y1 {
+        let mut x = first(window.x0, extent.x0 + fill.spacing);
+        while needed > 0 && x + fill.size <= window.x1 {
+            let square = Bbox::new(x, y, x + fill.size, y + fill.size);
+            let keepout = Bbox::new(
+                x - fill.spacing,
+                y - fill.spacing,
+                x + fill.size + fill.spacing,
+                y + fill.size + fill.spacing,
+            );
+            if square.x1 <= extent.x1
+                && square.y1 <= extent.y1
+                && shapes
+                    .iter()
+                    .all(|shape| intersection(shape, &keepout).is_none())
+            {
+                shapes.push(square);
+                added.push(square);
+                needed -= 1;
+            }
+            x += pitch;
+        }
+        y += pitch;
+    }
+    added
+}
@@ -0,0 +1,180 @@
+//! Machine-readable diagnostics for tools other than the language server.
+//!
+//! Diagnostics flatten the compiler's errors and lints into a file, a span with
+//! 1-indexed lines and columns, a severity, and a message, and serialize to
+//! JSON.
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::Span,
+    compile::{ExecError, StaticError},
+    layer::line_col,
+    lint::{Lint, LintLevel},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Location of a [`Diagnostic`] within its file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticSpan {
+    /// Byte offset of the start of the span.
+    pub start: usize,
+    /// Byte offset just past the end of the span.
+    pub end: usize,
+    /// 1-indexed line of the start of the span.
+    pub line: usize,
+    /// 1-indexed column, in characters, of the start of the span.
+    pub col: usize,
+    /// 1-indexed line of the end of the span.
+    pub end_line: usize,
+    /// 1-indexed column, in characters, of the end of the span.
+    pub end_col: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable code of the kind of error, e.g. `E0033`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub message: String,
+    /// File the diagnostic refers to, if any.
+    pub file: Option<PathBuf>,
+    /// Span of the diagnostic within `file`, if known.
+    pub span: Option<DiagnosticSpan>,
+    /// Cell invocation whose compilation produced the diagnostic, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates an error without a location.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            file: None,
+            span: None,
+            cell: None,
+        }
+    }
+
+    /// Creates a warning without a location.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(message)
+        }
+    }
+
+    /// Sets the location of the diagnostic to `span`, reading its file to
+    /// compute lines and columns.
+    ///
+    /// Only the file is recorded if it cannot be read.
+    pub fn with_span(mut self, span: &Span) -> Self {
+        self.span = std::fs::read_to_string(&span.path)
+            .ok()
+            .filter(|text| span.span.end() <= text.len())
+            .map(|text| {
+                let (line, col) = line_col(&text, span.span.start());
+                let (end_line, end_col) = line_col(&text, span.span.end());
+                DiagnosticSpan {
+                    start: span.span.start(),
+                    end: span.span.end(),
+                    line,
+                    col,
+                    end_line,
+                    end_col,
+                }
+            });
+        self.file = Some(span.path.clone());
+        self
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Attributes the diagnostic to the compilation of `cell`.
+    pub fn with_cell(mut self, cell: impl Into<String>) -> Self {
+        self.cell = Some(cell.into());
+        self
+    }
+}
+
+impl From<&StaticError> for Diagnostic {
+    fn from(err: &StaticError) -> Self {
+        Diagnostic::error(err.kind.to_string())
+            .with_code(err.kind.code())
+            .with_span(&err.span)
+    }
+}
+
+impl From<&ExecError> for Diagnostic {
+    fn from(err: &ExecError) -> Self {
+        let diagnostic = Diagnostic::error(err.kind.to_string()).with_code(err.kind.code());
+        match &err.span {
+            Some(span) => diagnostic.with_span(span),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<&Lint> for Diagnostic {
+    /// Reports the lint as a warning, or as an error if its rule is denied, with
+    /// the rule's name as its code.
+    fn from(lint: &Lint) -> Self {
+        let diagnostic = Diagnostic::error(lint.message.clone())
+            .with_code(lint.rule.name())
+            .with_span(&lint.span);
+        Diagnostic {
+            severity: match lint.level {
+                LintLevel::Deny => Severity::Error,
+                _ => Severity::Warning,
+            },
+            ..diagnostic
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    /// Formats the diagnostic for humans, e.g. `lib.ar:3:5: error[E0033]: ...`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(cell) = &self.cell {
+            write!(f, "{cell}: ")?;
+        }
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+            if let Some(span) = &self.span {
+                write!(f, ":{}:{}", span.line, span.col)?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = &self.code {
+            write!(f, "[{code}]")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
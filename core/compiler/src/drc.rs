@@ -0,0 +1,294 @@
+//! Design rule checks of solved geometry against a rule deck.
+//!
+//! A rule deck is a TOML file of minimum widths, spacings, and enclosures in
+//! layout units, e.g.
+//!
+//! ```toml
+//! [[width]]
+//! layer = "met1"
+//! min = 140.
+//!
+//! [[spacing]]
+//! layer = "met1"
+//! min = 140.
+//!
+//! [[enclosure]]
+//! inner = "via1"
+//! outer = "met1"
+//! min = 30.
+//! ```
+//!
+//! Rules are checked against the flattened rects of the top cell. Widths are
+//! checked per rect, and spacings between rects that neither overlap nor abut.
+//! A rect is enclosed if growing it by the minimum enclosure keeps it within
+//! the union of the rects on the outer layer.
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use argon_geometry::{Bbox, boolean};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::Span,
+    compile::{CompileOutput, CompiledData, ExecErrorCompileOutput},
+};
+
+/// Slack allowed when comparing solved dimensions against rule minimums.
+const TOLERANCE: f64 = 1e-6;
+
+/// Rules read from a rule deck.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DrcRules {
+    #[serde(default)]
+    pub width: Vec<WidthRule>,
+    #[serde(default)]
+    pub spacing: Vec<SpacingRule>,
+    #[serde(default)]
+    pub enclosure: Vec<EnclosureRule>,
+}
+
+/// Minimum width of the shapes on `layer`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WidthRule {
+    pub layer: String,
+    pub min: f64,
+}
+
+/// Minimum distance between separate shapes on `layer`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpacingRule {
+    pub layer: String,
+    pub min: f64,
+}
+
+/// Minimum distance from the edges of shapes on `inner` to the edges of the
+/// shapes on `outer` enclosing them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EnclosureRule {
+    pub inner: String,
+    pub outer: String,
+    pub min: f64,
+}
+
+impl DrcRules {
+    /// Reads a rule deck from the TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read DRC rules at {path:?}"))?;
+        toml::from_str(&text).with_context(|| format!("invalid DRC rules at {path:?}"))
+    }
+}
+
+/// The rule broken by a [`DrcViolation`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DrcViolationKind {
+    Width { layer: String },
+    Spacing { layer: String },
+    Enclosure { inner: String, outer: String },
+}
+
+impl DrcViolationKind {
+    /// Name of the kind of rule, e.g. `min-width`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrcViolationKind::Width { .. } => "min-width",
+            DrcViolationKind::Spacing { .. } => "min-spacing",
+            DrcViolationKind::Enclosure { .. } => "min-enclosure",
+        }
+    }
+}
+
+/// Geometry of the top cell that breaks a rule of a [`DrcRules`] deck.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrcViolation {
+    pub kind: DrcViolationKind,
+    /// Minimum required by the rule.
+    pub min: f64,
+    /// Measured width or spacing. Enclosures are not measured.
+    pub actual: Option<f64>,
+    /// Extent of the violation in the top cell's coordinates: the offending
+    /// rect, or the gap between two rects that are too close.
+    pub bbox: Bbox,
+    /// Source of the offending rect, or of the first of two rects that are
+    /// too close.
+    pub span: Option<Span>,
+}
+
+impl Display for DrcViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, self.actual) {
+            (DrcViolationKind::Width { layer }, Some(actual)) => {
+                write!(f, "{layer} width {actual} below minimum {}", self.min)?
+            }
+            (DrcViolationKind::Spacing { layer }, Some(actual)) => {
+                write!(f, "{layer} spacing {actual} below minimum {}", self.min)?
+            }
+            (DrcViolationKind::Width { layer } | DrcViolationKind::Spacing { layer }, None) => {
+                write!(f, "{layer} {} below minimum {}", self.kind.name(), self.min)?
+            }
+            (DrcViolationKind::Enclosure { inner, outer }, _) => write!(
+                f,
+                "{inner} not enclosed by {outer} by at least {}",
+                self.min
+            )?,
+        }
+        write!(
+            f,
+            " at ({}, {}) to ({}, {})",
+            self.bbox.x0, self.bbox.y0, self.bbox.x1, self.bbox.y1
+        )
+    }
+}
+
+/// A flattened rect and the source of the rect it was instantiated from.
+struct Shape<'a> {
+    bbox: Bbox,
+    span: Option<&'a Span>,
+}
+
+impl CompileOutput {
+    /// Runs [`CompiledData::check_drc`] on the compiled cell, if any.
+    pub fn check_drc(&mut self, rules: &DrcRules) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.check_drc(rules);
+        }
+    }
+}
+
+impl CompiledData {
+    /// Checks the flattened top cell against `rules`.
+    ///
+    /// Replaces any previous [`CompiledData::drc_violations`].
+    pub fn check_drc(&mut self, rules: &DrcRules) {
+        let shapes = self.drc_shapes();
+        let layer = |layer: &str| shapes.get(layer).map(Vec::as_slice).unwrap_or_default();
+        let mut violations = Vec::new();
+        for rule in &rules.width {
+            for shape in layer(&rule.layer) {
+                let width = shape.bbox.width().min(shape.bbox.height());
+                if width < rule.min - TOLERANCE {
+                    violations.push(DrcViolation {
+                        kind: DrcViolationKind::Width {
+                            layer: rule.layer.clone(),
+                        },
+                        min: rule.min,
+                        actual: Some(width),
+                        bbox: shape.bbox,
+                        span: shape.span.cloned(),
+                    });
+                }
+            }
+        }
+        for rule in &rules.spacing {
+            let mut shapes = layer(&rule.layer).iter().collect::<Vec<_>>();
+            shapes.sort_by(|a, b| a.bbox.x0.total_cmp(&b.bbox.x0));
+            for (i, a) in shapes.iter().enumerate() {
+                for b in shapes[i + 1..]
+                    .iter()
+                    .take_while(|b| b.bbox.x0 < a.bbox.x1 + rule.min)
+                {
+                    let Some((spacing, gap)) = spacing(&a.bbox, &b.bbox) else {
+                        continue;
+                    };
+                    if spacing < rule.min - TOLERANCE {
+                        violations.push(DrcViolation {
+                            kind: DrcViolationKind::Spacing {
+                                layer: rule.layer.clone(),
+                            },
+                            min: rule.min,
+                            actual: Some(spacing),
+                            bbox: gap,
+                            span: a.span.cloned(),
+                        });
+                    }
+                }
+            }
+        }
+        for rule in &rules.enclosure {
+            let outer = layer(&rule.outer)
+                .iter()
+                .map(|shape| shape.bbox)
+                .collect::<Vec<_>>();
+            for shape in layer(&rule.inner) {
+                let margin = rule.min - TOLERANCE;
+                let grown = Bbox::new(
+                    shape.bbox.x0 - margin,
+                    shape.bbox.y0 - margin,
+                    shape.bbox.x1 + margin,
+                    shape.bbox.y1 + margin,
+                );
+                let candidates = outer
+                    .iter()
+                    .filter(|bbox| bbox.intersection(&grown).is_some())
+                    .copied()
+                    .collect::<Vec<_>>();
+                if !boolean::subtract(&[grown], &candidates).is_empty() {
+                    violations.push(DrcViolation {
+                        kind: DrcViolationKind::Enclosure {
+                            inner: rule.inner.clone(),
+                            outer: rule.outer.clone(),
+                        },
+                        min: rule.min,
+                        actual: None,
+                        bbox: shape.bbox,
+                        span: shape.span.cloned(),
+                    });
+                }
+            }
+        }
+        self.drc_violations = violations;
+    }
+
+    /// The non-construction rects of the flattened top cell, keyed by layer.
+    fn drc_shapes(&self) -> IndexMap<&str, Vec<Shape<'_>>> {
+        let mut shapes: IndexMap<&str, Vec<Shape<'_>>> = IndexMap::new();
+        for ((cell, _), transform, _) in self.flat_cells(self.top, None) {
+            for rect in self.emitted(cell).filter_map(|obj| obj.get_rect()) {
+                let Some(layer) = rect.layer.as_deref() else {
+                    continue;
+                };
+                if rect.construction {
+                    continue;
+                }
+                shapes.entry(layer).or_default().push(Shape {
+                    bbox: rect.to_float().bbox().transform(&transform),
+                    span: rect.span.as_ref(),
+                });
+            }
+        }
+        shapes
+    }
+}
+
+/// Returns the distance between `a` and `b` and the gap between them, or `None`
+/// if they overlap or touch.
+fn spacing(a: &Bbox, b: &Bbox) -> Option<(f64, Bbox)> {
+    let dx = (b.x0 - a.x1).max(a.x0 - b.x1);
+    let dy = (b.y0 - a.y1).max(a.y0 - b.y1);
+    if dx <= 0. && dy <= 0. {
+        return None;
+    }
+    // Along each axis, the gap spans the separation between the rects or,
+    // where they overlap, the overlap.
+    let (x0, x1) = if dx > 0. {
+        (a.x1.min(b.x1), a.x0.max(b.x0))
+    } else {
+        (a.x0.max(b.x0), a.x1.min(b.x1))
+    };
+    let (y0, y1) = if dy > 0. {
+        (a.y1.min(b.y1), a.y0.max(b.y0))
+    } else {
+        (a.y0.max(b.y0), a.y1.min(b.y1))
+    };
+    Some((dx.max(0.).hypot(dy.max(0.)), Bbox::new(x0, y0, x1, y1)))
+}
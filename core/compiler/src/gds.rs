@@ -1,17 +1,64 @@
-use std::{io::BufReader, ops::Deref, path::Path};
+use std::{
+    fmt::{self, Display, Write},
+    io::BufReader,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use ::gds::{
-    GdsBoundary, GdsElement, GdsLayerSpec, GdsLibrary, GdsPoint, GdsStrans, GdsStruct,
+    GdsArrayRef, GdsBoundary, GdsElement, GdsLayerSpec, GdsLibrary, GdsPoint, GdsStrans, GdsStruct,
     GdsStructRef, GdsTextElem, GdsUnits,
 };
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use arcstr::ArcStr;
-use indexmap::IndexMap;
+use argon_geometry::{Bbox, Flatten, Transform, boolean};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
 use tracing::trace;
 use uniquify::Names;
 
-use crate::compile::{CellId, CompileOutput, CompiledData, ExecErrorCompileOutput, SolvedValue};
+use crate::compile::{
+    ArrayDims, BUILTINS, CellArg, CellId, CompileOutput, CompiledCell, CompiledData,
+    ExecErrorCompileOutput, SolvedValue,
+};
+use crate::dbu::{Dbu, DbuRect, format_on_grid, snap};
+use crate::oasis::{rectangle, save_oasis};
+
+/// File format of exported layouts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutFormat {
+    #[default]
+    Gds,
+    Oasis,
+}
+
+impl LayoutFormat {
+    /// Extension of files in this format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gds => "gds",
+            Self::Oasis => "oas",
+        }
+    }
+}
 
+impl FromStr for LayoutFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gds" => Ok(Self::Gds),
+            "oasis" | "oas" => Ok(Self::Oasis),
+            _ => Err(format!(
+                "unknown layout format `{s}`; expected `gds` or `oasis`"
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct GdsMap {
     layers: IndexMap<String, GdsLayerSpec>,
 }
@@ -19,7 +66,10 @@ pub struct GdsMap {
 struct GdsExporter {
     lib: GdsLibrary,
     map: GdsMap,
-    names: Names<CellId>,
+    /// Cells are keyed by the index of their compile output, since cell IDs are
+    /// only unique within a single output.
+    names: Names<(usize, CellId)>,
+    output: usize,
 }
 
 impl GdsExporter {
@@ -30,11 +80,58 @@ impl GdsExporter {
             lib,
             map,
             names: Names::new(),
+            output: 0,
         }
     }
 
-    fn coord_to_gds(&self, coord: f64) -> i32 {
-        (coord * 1e-9 / self.lib.units.db_unit()).round() as i32
+    /// Converts a snapped coordinate of `dbu` layout units per step, avoiding
+    /// any rounding when the database units of the library and the compile
+    /// output agree.
+    fn dbu_to_gds(&self, coord: Dbu, dbu: f64) -> i32 {
+        let scale = dbu * 1e-9 / self.lib.units.db_unit();
+        if (scale - 1.).abs() < f64::EPSILON {
+            coord as i32
+        } else {
+            (coord as f64 * scale).round() as i32
+        }
+    }
+
+    fn boundary(&self, layer: &str, rect: DbuRect, dbu: f64) -> GdsElement {
+        let GdsLayerSpec {
+            layer,
+            xtype: datatype,
+        } = self.map[layer];
+        let x0 = self.dbu_to_gds(rect.x0, dbu);
+        let x1 = self.dbu_to_gds(rect.x1, dbu);
+        let y0 = self.dbu_to_gds(rect.y0, dbu);
+        let y1 = self.dbu_to_gds(rect.y1, dbu);
+        GdsElement::GdsBoundary(GdsBoundary {
+            layer,
+            datatype,
+            xy: vec![
+                GdsPoint::new(x0, y0),
+                GdsPoint::new(x0, y1),
+                GdsPoint::new(x1, y1),
+                GdsPoint::new(x1, y0),
+            ],
+            ..Default::default()
+        })
+    }
+
+    fn polygon_boundary(&self, layer: &str, points: &[(Dbu, Dbu)], dbu: f64) -> GdsElement {
+        let GdsLayerSpec {
+            layer,
+            xtype: datatype,
+        } = self.map[layer];
+        GdsElement::GdsBoundary(GdsBoundary {
+            layer,
+            datatype,
+            xy: points
+                .iter()
+                .map(|(x, y)| GdsPoint::new(self.dbu_to_gds(*x, dbu), self.dbu_to_gds(*y, dbu)))
+                .collect(),
+            ..Default::default()
+        })
     }
 }
 
@@ -77,66 +174,166 @@ impl GdsMap {
 
 impl CompileOutput {
     pub fn to_gds(&self, map: GdsMap, units: GdsUnits, out_path: impl AsRef<Path>) -> Result<()> {
-        let out_path = out_path.as_ref();
-        trace!("Exporting to gds at {out_path:?}");
-        let mut exporter = GdsExporter::new("TOP", map, units);
+        self.to_layout(map, units, LayoutFormat::Gds, out_path)
+    }
+
+    /// Writes the top cell and its subcells to a layout file in `format`.
+    pub fn to_layout(
+        &self,
+        map: GdsMap,
+        units: GdsUnits,
+        format: LayoutFormat,
+        out_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        write_layout_library("TOP", [self], map, units, format, out_path)
+    }
+}
+
+/// Writes the top cells of `outputs`, along with their subcells, to a single GDS library.
+///
+/// Outputs without compiled data are skipped.
+pub fn write_gds_library<'a>(
+    name: impl Into<ArcStr>,
+    outputs: impl IntoIterator<Item = &'a CompileOutput>,
+    map: GdsMap,
+    units: GdsUnits,
+    out_path: impl AsRef<Path>,
+) -> Result<()> {
+    write_layout_library(name, outputs, map, units, LayoutFormat::Gds, out_path)
+}
+
+/// Writes the top cells of `outputs`, along with their subcells, to a single
+/// library in `format`.
+///
+/// The library is built as GDS regardless of `format`, so cell and layer
+/// names are the same in both formats.
+pub fn write_layout_library<'a>(
+    name: impl Into<ArcStr>,
+    outputs: impl IntoIterator<Item = &'a CompileOutput>,
+    map: GdsMap,
+    units: GdsUnits,
+    format: LayoutFormat,
+    out_path: impl AsRef<Path>,
+) -> Result<()> {
+    let out_path = out_path.as_ref();
+    trace!("Exporting to {} at {out_path:?}", format.extension());
+    let mut exporter = GdsExporter::new(name, map, units);
+    for (i, output) in outputs.into_iter().enumerate() {
         if let CompileOutput::Valid(output)
         | CompileOutput::ExecErrors(ExecErrorCompileOutput {
             errors: _,
             output: Some(output),
-        }) = self
+        }) = output
         {
+            exporter.output = i;
             output.cell_to_gds(&mut exporter, output.top)?;
         }
-        if let Some(parent) = out_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        exporter.lib.save(out_path).map_err(|e| anyhow!("{e}"))?;
-
-        Ok(())
     }
+    if let Some(parent) = out_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match format {
+        LayoutFormat::Gds => exporter.lib.save(out_path).map_err(|e| anyhow!("{e}"))?,
+        LayoutFormat::Oasis => save_oasis(&exporter.lib, out_path)?,
+    }
+
+    Ok(())
 }
 
 impl CompiledData {
+    /// Returns the GDS struct names that [`CompileOutput::to_gds`] assigns to
+    /// `top` and the cells it instantiates.
+    pub fn gds_cell_names(&self, top: CellId) -> IndexMap<CellId, ArcStr> {
+        fn assign(
+            data: &CompiledData,
+            names: &mut Names<CellId>,
+            id: CellId,
+            order: &mut Vec<CellId>,
+        ) {
+            let cell = &data.cells[&id];
+            names.assign_name(id, &cell.gds_name());
+            order.push(id);
+            for obj in cell.objects.values() {
+                if let Some(i) = obj.instance()
+                    && !i.construction
+                    && names.name(&i.cell).is_none()
+                {
+                    assign(data, names, i.cell, order);
+                }
+            }
+        }
+        let mut names = Names::new();
+        let mut order = Vec::new();
+        assign(self, &mut names, top, &mut order);
+        order
+            .into_iter()
+            .map(|id| (id, names.name(&id).unwrap().clone()))
+            .collect()
+    }
+
+    /// Sets the GDS files of `extern cell`s, keyed by cell name.
+    pub fn set_extern_gds(&mut self, extern_gds: &IndexMap<String, PathBuf>) {
+        self.extern_gds = extern_gds.clone();
+    }
+
+    /// Sets whether overlapping and abutting rects on the same layer are merged
+    /// into polygons on export.
+    pub fn set_merge_shapes(&mut self, merge_shapes: bool) {
+        self.merge_shapes = merge_shapes;
+    }
+
     fn cell_to_gds(&self, exporter: &mut GdsExporter, id: CellId) -> Result<()> {
         trace!("Exporting cell {id}");
         let cell = &self.cells[&id];
-        let name = &cell.scopes[&cell.root].name;
-        let name = parse_cell_name(name)?;
-        let name = exporter.names.assign_name(id, name);
+        if cell.external {
+            return self.extern_cell_to_gds(exporter, id);
+        }
+        let name = exporter
+            .names
+            .assign_name((exporter.output, id), &cell.gds_name());
         let mut ocell = GdsStruct::new(name.to_string());
-        for (_, obj) in &cell.objects {
+        for (id, obj) in &cell.objects {
             match obj {
                 SolvedValue::Rect(rect) if !rect.construction => {
                     if let Some(layer) = &rect.layer {
-                        let GdsLayerSpec {
-                            layer,
-                            xtype: datatype,
-                        } = exporter.map[layer];
-                        let x0 = exporter.coord_to_gds(rect.x0.0);
-                        let x1 = exporter.coord_to_gds(rect.x1.0);
-                        let y0 = exporter.coord_to_gds(rect.y0.0);
-                        let y1 = exporter.coord_to_gds(rect.y1.0);
-                        ocell.elems.push(GdsElement::GdsBoundary(GdsBoundary {
-                            layer,
-                            datatype,
-                            xy: vec![
-                                GdsPoint::new(x0, y0),
-                                GdsPoint::new(x0, y1),
-                                GdsPoint::new(x1, y1),
-                                GdsPoint::new(x1, y0),
-                            ],
-                            ..Default::default()
-                        }));
+                        let rect = cell.dbu_rect(*id).expect("rect was not snapped");
+                        ocell.elems.push(exporter.boundary(layer, rect, self.dbu));
                     }
                 }
+                SolvedValue::Polygon(polygon) if !polygon.construction => {
+                    let points = cell.dbu_polygon(*id).expect("polygon was not snapped");
+                    ocell
+                        .elems
+                        .push(exporter.polygon_boundary(&polygon.layer, points, self.dbu));
+                }
+                SolvedValue::Pin(pin) => {
+                    let exists = |layer: &str| exporter.map.contains_key(layer);
+                    let rect = cell.dbu_rect(*id).expect("pin was not snapped");
+                    ocell
+                        .elems
+                        .push(exporter.boundary(&pin.pin_layer(exists), rect, self.dbu));
+                    let GdsLayerSpec {
+                        layer,
+                        xtype: texttype,
+                    } = exporter.map[&pin.label_layer(exists)];
+                    let x = exporter.dbu_to_gds((rect.x0 + rect.x1) / 2, self.dbu);
+                    let y = exporter.dbu_to_gds((rect.y0 + rect.y1) / 2, self.dbu);
+                    ocell.elems.push(GdsElement::GdsTextElem(GdsTextElem {
+                        string: ArcStr::from(&pin.name),
+                        layer,
+                        texttype,
+                        xy: GdsPoint::new(x, y),
+                        ..Default::default()
+                    }));
+                }
                 SolvedValue::Text(text) => {
                     let GdsLayerSpec {
                         layer,
                         xtype: texttype,
                     } = exporter.map[&text.layer];
-                    let x = exporter.coord_to_gds(text.x);
-                    let y = exporter.coord_to_gds(text.y);
+                    let (x, y) = cell.dbu_point(*id).expect("text was not snapped");
+                    let x = exporter.dbu_to_gds(x, self.dbu);
+                    let y = exporter.dbu_to_gds(y, self.dbu);
                     ocell.elems.push(GdsElement::GdsTextElem(GdsTextElem {
                         string: ArcStr::from(&text.text),
                         layer,
@@ -145,31 +342,351 @@ impl CompiledData {
                         ..Default::default()
                     }));
                 }
-                SolvedValue::Instance(i) if !i.construction => {
-                    if exporter.names.name(&i.cell).is_none() {
+                SolvedValue::Instance(_) | SolvedValue::InstanceArray(_) => {
+                    let i = obj.instance().unwrap();
+                    if i.construction {
+                        continue;
+                    }
+                    let key = (exporter.output, i.cell);
+                    if exporter.names.name(&key).is_none() {
                         self.cell_to_gds(exporter, i.cell)?;
                     }
-                    ocell.elems.push(GdsElement::GdsStructRef(GdsStructRef {
-                        name: exporter.names.name(&i.cell).unwrap().clone(),
-                        xy: GdsPoint::new(exporter.coord_to_gds(i.x), exporter.coord_to_gds(i.y)),
-                        strans: Some(GdsStrans {
-                            reflected: i.reflect,
-                            abs_mag: false,
-                            abs_angle: false,
-                            mag: None,
-                            angle: Some(i.angle.degrees()),
-                        }),
+                    let name = exporter.names.name(&key).unwrap().clone();
+                    let (angle, reflected) = i.transform().orientation();
+                    let strans = Some(GdsStrans {
+                        reflected,
+                        abs_mag: false,
+                        abs_angle: false,
+                        mag: None,
+                        angle: Some(angle),
+                    });
+                    let (x, y) = cell.dbu_point(*id).expect("instance was not snapped");
+                    let point = |x: Dbu, y: Dbu| {
+                        GdsPoint::new(
+                            exporter.dbu_to_gds(x, self.dbu),
+                            exporter.dbu_to_gds(y, self.dbu),
+                        )
+                    };
+                    let Some(array) = obj.get_instance_array() else {
+                        ocell.elems.push(GdsElement::GdsStructRef(GdsStructRef {
+                            name,
+                            xy: point(x, y),
+                            strans,
+                            ..Default::default()
+                        }));
+                        continue;
+                    };
+                    let (Ok(cols), Ok(rows)) =
+                        (i16::try_from(array.array.nx), i16::try_from(array.array.ny))
+                    else {
+                        bail!(
+                            "array of {} x {} instances of `{name}` is too large for GDS",
+                            array.array.nx,
+                            array.array.ny
+                        );
+                    };
+                    // The lattice points are one pitch past the last column and
+                    // row, in the parent's coordinates.
+                    let dx = snap(array.array.dx, self.dbu);
+                    let dy = snap(array.array.dy, self.dbu);
+                    ocell.elems.push(GdsElement::GdsArrayRef(GdsArrayRef {
+                        name,
+                        xy: [
+                            point(x, y),
+                            point(x + cols as Dbu * dx, y),
+                            point(x, y + rows as Dbu * dy),
+                        ],
+                        cols,
+                        rows,
+                        strans,
                         ..Default::default()
                     }));
                 }
                 _ => {}
             }
         }
+        if id == self.top {
+            for fill in &self.fill {
+                let rect = DbuRect::snap(fill.bbox, self.dbu);
+                ocell
+                    .elems
+                    .push(exporter.boundary(&fill.layer, rect, self.dbu));
+            }
+        }
+        if self.merge_shapes {
+            merge_boundaries(&mut ocell.elems);
+        }
         exporter.lib.structs.push(ocell);
         Ok(())
     }
 }
 
+impl CompiledData {
+    /// Copies the struct named after the extern cell `id`, along with the
+    /// structs it references, from the cell's GDS file into the exported
+    /// library.
+    ///
+    /// Referenced structs keep their names and are only copied once, so that
+    /// several extern cells may share library cells.
+    fn extern_cell_to_gds(&self, exporter: &mut GdsExporter, id: CellId) -> Result<()> {
+        let cell = &self.cells[&id];
+        let path = self.extern_gds.get(&cell.name).ok_or_else(|| {
+            anyhow!(
+                "no GDS file is listed for extern cell `{}` under `[extern]` in `Argon.toml`",
+                cell.name
+            )
+        })?;
+        trace!("Merging extern cell {} from {path:?}", cell.name);
+        let lib =
+            GdsLibrary::load(path).map_err(|e| anyhow!("failed to read GDS at {path:?}: {e}"))?;
+        let (db_unit, out_db_unit) = (lib.units.db_unit(), exporter.lib.units.db_unit());
+        if ((db_unit - out_db_unit) / out_db_unit).abs() > 1e-9 {
+            bail!(
+                "GDS at {path:?} has a database unit of {db_unit} m, but the exported library \
+                 has a database unit of {out_db_unit} m"
+            );
+        }
+        let find = |name: &str| {
+            lib.structs
+                .iter()
+                .find(|s| s.name.as_str() == name)
+                .ok_or_else(|| anyhow!("GDS at {path:?} has no struct named `{name}`"))
+        };
+
+        let top = find(&cell.name)?;
+        let name = exporter
+            .names
+            .assign_name((exporter.output, id), &cell.name);
+        let mut ocell = GdsStruct::new(name.to_string());
+        ocell.elems = top.elems.clone();
+        exporter.lib.structs.push(ocell);
+
+        let mut visited = IndexSet::new();
+        let mut stack = vec![top];
+        while let Some(s) = stack.pop() {
+            for elem in &s.elems {
+                let child = match elem {
+                    GdsElement::GdsStructRef(sref) => sref.name.as_str(),
+                    GdsElement::GdsArrayRef(aref) => aref.name.as_str(),
+                    _ => continue,
+                };
+                if !visited.insert(child.to_string()) {
+                    continue;
+                }
+                let child = find(child)?;
+                if !exporter.lib.structs.iter().any(|s| s.name == child.name) {
+                    exporter.lib.structs.push(child.clone());
+                }
+                stack.push(child);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CompileOutput {
+    /// Runs [`CompiledData::set_extern_gds`] on the compiled cell, if any.
+    pub fn set_extern_gds(&mut self, extern_gds: &IndexMap<String, PathBuf>) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.set_extern_gds(extern_gds);
+        }
+    }
+
+    /// Runs [`CompiledData::flatten`] on the compiled cell, if any.
+    pub fn flatten(&mut self, depth: Option<usize>) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.flatten(depth);
+        }
+    }
+
+    /// Runs [`CompiledData::set_merge_shapes`] on the compiled cell, if any.
+    pub fn set_merge_shapes(&mut self, merge_shapes: bool) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.set_merge_shapes(merge_shapes);
+        }
+    }
+}
+
+/// Merges the rectangular boundaries in `elems` that overlap or abut on the
+/// same layer and datatype into polygons.
+///
+/// Each group of connected rects becomes a single boundary tracing its outline.
+/// Groups whose outline has a hole or touches itself at a corner cannot be
+/// written as one boundary, so they are written as disjoint rects instead.
+/// Other elements, including non-rectangular boundaries, are kept as is.
+fn merge_boundaries(elems: &mut Vec<GdsElement>) {
+    let mut layers: IndexMap<(i16, i16), Vec<Bbox>> = IndexMap::new();
+    elems.retain(|elem| {
+        let GdsElement::GdsBoundary(boundary) = elem else {
+            return true;
+        };
+        let Some((p0, p1)) = rectangle(&boundary.xy) else {
+            return true;
+        };
+        layers
+            .entry((boundary.layer, boundary.datatype))
+            .or_default()
+            .push(Bbox::new(
+                p0.x as f64,
+                p0.y as f64,
+                p1.x as f64,
+                p1.y as f64,
+            ));
+        false
+    });
+    for ((layer, datatype), rects) in layers {
+        for group in connected_rects(&rects) {
+            let outlines = match outline(&group) {
+                Some(points) => vec![points],
+                None => boolean::merge(&group)
+                    .iter()
+                    .map(|bbox| bbox.corners().to_vec())
+                    .collect(),
+            };
+            for points in outlines {
+                elems.push(GdsElement::GdsBoundary(GdsBoundary {
+                    layer,
+                    datatype,
+                    xy: points
+                        .iter()
+                        .map(|(x, y)| GdsPoint::new(*x as i32, *y as i32))
+                        .collect(),
+                    ..Default::default()
+                }));
+            }
+        }
+    }
+}
+
+/// Partitions `rects` into groups connected by overlaps or shared edges.
+///
+/// Rects touching only at a corner are not connected.
+fn connected_rects(rects: &[Bbox]) -> Vec<Vec<Bbox>> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut order = (0..rects.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| rects[a].x0.total_cmp(&rects[b].x0));
+    let mut parent = (0..rects.len()).collect::<Vec<_>>();
+    for (k, &a) in order.iter().enumerate() {
+        for &b in order[k + 1..]
+            .iter()
+            .take_while(|&&b| rects[b].x0 <= rects[a].x1)
+        {
+            let (a_rect, b_rect) = (&rects[a], &rects[b]);
+            let dx = a_rect.x1.min(b_rect.x1) - a_rect.x0.max(b_rect.x0);
+            let dy = a_rect.y1.min(b_rect.y1) - a_rect.y0.max(b_rect.y0);
+            if dx >= 0. && dy >= 0. && (dx > 0. || dy > 0.) {
+                let (a, b) = (root(&mut parent, a), root(&mut parent, b));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: IndexMap<usize, Vec<Bbox>> = IndexMap::new();
+    for (i, rect) in rects.iter().enumerate() {
+        let group = root(&mut parent, i);
+        groups.entry(group).or_default().push(*rect);
+    }
+    groups.into_values().collect()
+}
+
+/// Returns the vertices of the outline of the region covered by `rects`,
+/// counterclockwise, or `None` if the outline is not a single simple polygon.
+fn outline(rects: &[Bbox]) -> Option<Vec<(f64, f64)>> {
+    let coords = |f: fn(&Bbox) -> [f64; 2]| {
+        let mut coords = rects.iter().flat_map(f).collect::<Vec<_>>();
+        coords.sort_by(f64::total_cmp);
+        coords.dedup();
+        coords
+    };
+    let xs = coords(|b| [b.x0, b.x1]);
+    let ys = coords(|b| [b.y0, b.y1]);
+    let index = |coords: &[f64], v: f64| coords.binary_search_by(|c| c.total_cmp(&v)).unwrap();
+
+    // Whether each cell of the grid formed by the rects' edges is covered.
+    let (nx, ny) = (xs.len() - 1, ys.len() - 1);
+    let mut covered = vec![false; nx * ny];
+    for rect in rects {
+        for i in index(&xs, rect.x0)..index(&xs, rect.x1) {
+            for j in index(&ys, rect.y0)..index(&ys, rect.y1) {
+                covered[i * ny + j] = true;
+            }
+        }
+    }
+    let is_covered = |i: usize, j: usize| i < nx && j < ny && covered[i * ny + j];
+
+    // Edges between grid vertices on the region's boundary, keyed by their
+    // start and directed with the region on their left.
+    let mut next = IndexMap::new();
+    for i in 0..nx {
+        for j in 0..ny {
+            if !is_covered(i, j) {
+                continue;
+            }
+            let edges = [
+                (j == 0 || !is_covered(i, j - 1), (i, j), (i + 1, j)),
+                (!is_covered(i + 1, j), (i + 1, j), (i + 1, j + 1)),
+                (!is_covered(i, j + 1), (i + 1, j + 1), (i, j + 1)),
+                (i == 0 || !is_covered(i - 1, j), (i, j + 1), (i, j)),
+            ];
+            for (boundary, from, to) in edges {
+                // Two edges leave a vertex where the region touches itself.
+                if boundary && next.insert(from, to).is_some() {
+                    return None;
+                }
+            }
+        }
+    }
+
+    let (&start, _) = next.first()?;
+    let mut vertices = vec![start];
+    let mut vertex = next[&start];
+    while vertex != start {
+        vertices.push(vertex);
+        vertex = next[&vertex];
+    }
+    // Any edges left over belong to the outlines of holes.
+    if vertices.len() != next.len() {
+        return None;
+    }
+    let n = vertices.len();
+    Some(
+        (0..n)
+            .filter(|&k| {
+                // Drop vertices between collinear edges.
+                let (prev, cur, after) = (
+                    vertices[(k + n - 1) % n],
+                    vertices[k],
+                    vertices[(k + 1) % n],
+                );
+                !((prev.0 == cur.0 && cur.0 == after.0) || (prev.1 == cur.1 && cur.1 == after.1))
+            })
+            .map(|k| (xs[vertices[k].0], ys[vertices[k].1]))
+            .collect(),
+    )
+}
+
 fn parse_layer_source(source: &str) -> Result<(i16, i16)> {
     let (layer, datatype) = source
         .split_once('/')
@@ -180,9 +697,498 @@ fn parse_layer_source(source: &str) -> Result<(i16, i16)> {
     Ok((layer.parse()?, datatype.parse()?))
 }
 
-fn parse_cell_name(name: &str) -> Result<&str> {
-    name.rsplit("cell ")
-        .next()
-        .and_then(|suffix| suffix.split_whitespace().next())
-        .ok_or_else(|| anyhow!("parse error"))
+/// A shape that differs between a GDS library and the compile output it was
+/// exported from, in layout units.
+#[derive(Debug, Clone)]
+pub struct GdsMismatch {
+    /// Whether the shape is in the compile output but not in the GDS.
+    pub missing: bool,
+    /// Layer name, or `layer/datatype` if the layer is not in the layer map.
+    pub layer: String,
+    pub bbox: Bbox,
+}
+
+impl Display for GdsMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} shape ({}, {}) to ({}, {})",
+            if self.missing { "missing" } else { "extra" },
+            self.layer,
+            self.bbox.x0,
+            self.bbox.y0,
+            self.bbox.x1,
+            self.bbox.y1
+        )
+    }
+}
+
+/// Returns the transform of a struct reference, with its offset in layout units.
+fn ref_transform(sref: &GdsStructRef, scale: f64) -> Result<Transform> {
+    let (reflected, angle) = ref_orientation(&sref.name, &sref.strans)?;
+    Ok(Transform::rotate(
+        angle,
+        reflected,
+        sref.xy.x as f64 * scale,
+        sref.xy.y as f64 * scale,
+    ))
+}
+
+/// Returns whether a reference to `name` is reflected and its rotation in
+/// degrees.
+fn ref_orientation(name: &str, strans: &Option<GdsStrans>) -> Result<(bool, f64)> {
+    Ok(match strans {
+        Some(strans) => {
+            if strans.mag.is_some_and(|mag| mag != 1.) {
+                bail!("magnified reference to `{name}` is not supported");
+            }
+            (strans.reflected, strans.angle.unwrap_or(0.))
+        }
+        None => (false, 0.),
+    })
+}
+
+/// Returns the transform of the first element of `aref` along with its
+/// layout, in layout units.
+///
+/// Only arrays whose columns step along x and rows along y are supported.
+fn aref_transform(aref: &GdsArrayRef, scale: f64) -> Result<(Transform, ArrayDims<f64>)> {
+    let (reflected, angle) = ref_orientation(&aref.name, &aref.strans)?;
+    let [origin, col, row] = &aref.xy;
+    if aref.cols < 1 || aref.rows < 1 {
+        bail!("array reference to `{}` has no elements", aref.name);
+    }
+    if col.y != origin.y || row.x != origin.x {
+        bail!(
+            "array reference to `{}` is not aligned to the axes",
+            aref.name
+        );
+    }
+    let pitch = |end: i32, start: i32, n: i16| (end as f64 - start as f64) * scale / n as f64;
+    Ok((
+        Transform::rotate(
+            angle,
+            reflected,
+            origin.x as f64 * scale,
+            origin.y as f64 * scale,
+        ),
+        ArrayDims {
+            nx: aref.cols as usize,
+            ny: aref.rows as usize,
+            dx: pitch(col.x, origin.x, aref.cols),
+            dy: pitch(row.y, origin.y, aref.rows),
+        },
+    ))
+}
+
+impl CompiledData {
+    /// Compares the flattened top cell, including fill, against the struct it
+    /// was exported to in `lib`.
+    ///
+    /// Boundaries are compared by their bounding boxes. Shapes match if they
+    /// are on the same GDS layer and their edges are within one database unit
+    /// of each other.
+    pub fn verify_gds(&self, lib: &GdsLibrary, map: &GdsMap) -> Result<Vec<GdsMismatch>> {
+        let scale = lib.units.db_unit() / 1e-9;
+        let spec = |layer: &str| {
+            map.get(layer)
+                .map(|spec| (spec.layer, spec.xtype))
+                .ok_or_else(|| anyhow!("layer `{layer}` is not in the layer map"))
+        };
+        let mut expected = Vec::new();
+        for rect in self.iter_flat(self.top, None) {
+            if let Some(layer) = rect.layer {
+                expected.push((spec(layer)?, rect.rect.bbox()));
+            }
+        }
+        for (layer, points) in self.iter_flat_polygons(self.top, None) {
+            let bbox = points
+                .into_iter()
+                .fold(None, |bbox, pt| Bbox::union(bbox, Some(Bbox::point(pt))));
+            if let Some(bbox) = bbox {
+                expected.push((spec(layer)?, bbox));
+            }
+        }
+        for ((cell, _), transform, _) in self.flat_cells(self.top, None) {
+            for pin in self.emitted(cell).filter_map(|obj| obj.get_pin()) {
+                let layer = pin.pin_layer(|layer| map.contains_key(layer));
+                expected.push((spec(&layer)?, pin.bbox().transform(&transform)));
+            }
+        }
+        for fill in &self.fill {
+            expected.push((spec(&fill.layer)?, fill.bbox));
+        }
+
+        let top = &self.gds_cell_names(self.top)[&self.top];
+        let actual = flatten_boundaries(lib, top)?;
+
+        // Match shapes that agree exactly on the database grid first, then
+        // fall back to a pairwise comparison of the remainder.
+        let grid = |(spec, bbox): &((i16, i16), Bbox)| {
+            let snap = |coord: f64| (coord / scale).round() as i64;
+            (
+                *spec,
+                [snap(bbox.x0), snap(bbox.y0), snap(bbox.x1), snap(bbox.y1)],
+            )
+        };
+        let mut counts: IndexMap<_, usize> = IndexMap::new();
+        for shape in &actual {
+            *counts.entry(grid(shape)).or_default() += 1;
+        }
+        let mut missing = Vec::new();
+        for shape in expected {
+            match counts.get_mut(&grid(&shape)) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => missing.push(shape),
+            }
+        }
+        let mut extra = actual
+            .into_iter()
+            .filter(|shape| {
+                let count = counts.get_mut(&grid(shape)).unwrap();
+                let unmatched = *count > 0;
+                if unmatched {
+                    *count -= 1;
+                }
+                unmatched
+            })
+            .collect::<Vec<_>>();
+        let tol = scale * (1. + 1e-6);
+        let close = |a: &Bbox, b: &Bbox| {
+            (a.x0 - b.x0).abs() <= tol
+                && (a.y0 - b.y0).abs() <= tol
+                && (a.x1 - b.x1).abs() <= tol
+                && (a.y1 - b.y1).abs() <= tol
+        };
+        missing.retain(|(spec, bbox)| {
+            match extra
+                .iter()
+                .position(|(other, actual)| other == spec && close(bbox, actual))
+            {
+                Some(i) => {
+                    extra.swap_remove(i);
+                    false
+                }
+                None => true,
+            }
+        });
+
+        Ok(missing
+            .into_iter()
+            .map(|shape| (true, shape))
+            .chain(extra.into_iter().map(|shape| (false, shape)))
+            .map(|(missing, (spec, bbox))| GdsMismatch {
+                missing,
+                layer: layer_name(map, spec),
+                bbox,
+            })
+            .collect())
+    }
+}
+
+/// Returns the name of the layer with GDS layer/datatype `spec` in `map`, or
+/// `layer/datatype` if it is not in the map.
+fn layer_name(map: &GdsMap, (layer, datatype): (i16, i16)) -> String {
+    map.iter()
+        .find(|(_, spec)| spec.layer == layer && spec.xtype == datatype)
+        .map_or_else(|| format!("{layer}/{datatype}"), |(name, _)| name.clone())
+}
+
+/// Flattens the boundaries of struct `top` in `lib` into `top`'s coordinate
+/// system, in layout units.
+///
+/// Each boundary is reduced to its bounding box after transformation, which
+/// is larger than the boundary itself under non-Manhattan rotations, and keyed
+/// by its GDS layer/datatype.
+fn flatten_boundaries(lib: &GdsLibrary, top: &str) -> Result<Vec<((i16, i16), Bbox)>> {
+    let scale = lib.units.db_unit() / 1e-9;
+    let structs = lib
+        .structs
+        .iter()
+        .map(|s| (s.name.as_str(), s))
+        .collect::<IndexMap<_, _>>();
+    let mut refs: IndexMap<&str, Vec<(&str, Transform)>> = IndexMap::new();
+    for s in &lib.structs {
+        let children = refs.entry(s.name.as_str()).or_default();
+        for elem in &s.elems {
+            match elem {
+                GdsElement::GdsStructRef(sref) => {
+                    if !structs.contains_key(sref.name.as_str()) {
+                        bail!("reference to undefined struct `{}`", sref.name);
+                    }
+                    children.push((sref.name.as_str(), ref_transform(sref, scale)?));
+                }
+                GdsElement::GdsArrayRef(aref) => {
+                    if !structs.contains_key(aref.name.as_str()) {
+                        bail!("reference to undefined struct `{}`", aref.name);
+                    }
+                    let (xform, array) = aref_transform(aref, scale)?;
+                    for (dx, dy) in array.offsets() {
+                        children.push((
+                            aref.name.as_str(),
+                            Transform::translate(dx, dy).compose(&xform),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if !structs.contains_key(top) {
+        bail!("no struct named `{top}` in GDS library");
+    }
+    let mut shapes = Vec::new();
+    for (name, xform, _) in Flatten::new(top, None, |name: &&str, _, _| refs[*name].clone()) {
+        for elem in &structs[name].elems {
+            if let GdsElement::GdsBoundary(boundary) = elem
+                && let Some(bbox) = boundary
+                    .xy
+                    .iter()
+                    .map(|pt| Bbox::point(xform.apply((pt.x as f64 * scale, pt.y as f64 * scale))))
+                    .reduce(|a, b| Bbox::union(Some(a), Some(b)).unwrap())
+            {
+                shapes.push(((boundary.layer, boundary.datatype), bbox));
+            }
+        }
+    }
+    Ok(shapes)
+}
+
+/// A shape read from a GDS library by [`import_gds`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedShape {
+    /// Layer name, or `layer/datatype` if the layer is not in the layer map.
+    pub layer: String,
+    /// Bounding box of the shape, in layout units.
+    pub bbox: Bbox,
+}
+
+/// Reads the GDS library at `path` and flattens its top struct.
+///
+/// The top struct is the first struct that is not referenced by any other
+/// struct. Boundaries are imported as their bounding boxes.
+pub fn import_gds(path: impl AsRef<Path>, map: &GdsMap) -> Result<Vec<ImportedShape>> {
+    let path = path.as_ref();
+    let lib = GdsLibrary::load(path).map_err(|e| anyhow!("failed to read GDS at {path:?}: {e}"))?;
+    let top = lib
+        .structs
+        .iter()
+        .find(|s| {
+            !lib.structs
+                .iter()
+                .flat_map(|other| &other.elems)
+                .any(|elem| {
+                    matches!(elem, GdsElement::GdsStructRef(sref) if sref.name == s.name)
+                        || matches!(elem, GdsElement::GdsArrayRef(aref) if aref.name == s.name)
+                })
+        })
+        .ok_or_else(|| anyhow!("no top struct in GDS at {path:?}"))?;
+    Ok(flatten_boundaries(&lib, top.name.as_str())?
+        .into_iter()
+        .map(|(spec, bbox)| ImportedShape {
+            layer: layer_name(map, spec),
+            bbox,
+        })
+        .collect())
+}
+
+/// Reads the GDS library at `path` and reverse-compiles it into Argon source.
+///
+/// Each struct becomes a cell without parameters whose geometry has literal
+/// coordinates: rectangular boundaries become `rect`s, other boundaries
+/// `polygon`s, text elements `text`s, struct references `inst`s, and array
+/// references `inst_array`s. Paths are skipped.
+pub fn import_gds_source(path: impl AsRef<Path>, map: &GdsMap) -> Result<String> {
+    let path = path.as_ref();
+    let lib = GdsLibrary::load(path).map_err(|e| anyhow!("failed to read GDS at {path:?}: {e}"))?;
+    let scale = lib.units.db_unit() / 1e-9;
+    let coord = |c: f64| format_on_grid(c, scale);
+    let mut names = Names::new();
+    for s in &lib.structs {
+        names.assign_name(s.name.clone(), &argon_ident(&s.name));
+    }
+
+    let mut out = String::new();
+    for (i, s) in lib.structs.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        writeln!(out, "cell {}() {{", names.name(&s.name).unwrap())?;
+        for elem in &s.elems {
+            match elem {
+                GdsElement::GdsBoundary(boundary) => {
+                    let layer = layer_name(map, (boundary.layer, boundary.datatype));
+                    let mut points = boundary
+                        .xy
+                        .iter()
+                        .map(|pt| (pt.x as f64 * scale, pt.y as f64 * scale))
+                        .collect::<Vec<_>>();
+                    if points.len() > 1 && points.first() == points.last() {
+                        points.pop();
+                    }
+                    if let Some(bbox) = as_rect(&points) {
+                        writeln!(
+                            out,
+                            "    rect(\"{layer}\", x0={}, y0={}, x1={}, y1={});",
+                            coord(bbox.x0),
+                            coord(bbox.y0),
+                            coord(bbox.x1),
+                            coord(bbox.y1)
+                        )?;
+                    } else {
+                        let points = points
+                            .iter()
+                            .map(|(x, y)| format!("({}, {})", coord(*x), coord(*y)))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        writeln!(out, "    polygon(\"{layer}\", points=[{points}]);")?;
+                    }
+                }
+                GdsElement::GdsTextElem(text) => {
+                    // Argon string literals have no escapes.
+                    let string = text.string.replace(['"', '\r', '\n'], "_");
+                    writeln!(
+                        out,
+                        "    text(\"{string}\", \"{}\", {}, {});",
+                        layer_name(map, (text.layer, text.texttype)),
+                        coord(text.xy.x as f64 * scale),
+                        coord(text.xy.y as f64 * scale)
+                    )?;
+                }
+                GdsElement::GdsStructRef(_) | GdsElement::GdsArrayRef(_) => {
+                    let (struct_name, transform, array) = match elem {
+                        GdsElement::GdsStructRef(sref) => {
+                            (&sref.name, ref_transform(sref, scale)?, None)
+                        }
+                        GdsElement::GdsArrayRef(aref) => {
+                            let (transform, array) = aref_transform(aref, scale)?;
+                            (&aref.name, transform, Some(array))
+                        }
+                        _ => unreachable!(),
+                    };
+                    let Some(name) = names.name(struct_name) else {
+                        bail!("reference to undefined struct `{struct_name}`");
+                    };
+                    let Transform { angle, reflect, .. } = transform;
+                    match array {
+                        Some(array) => write!(
+                            out,
+                            "    inst_array({name}(), nx={}, ny={}, dx={}, dy={}, x={}, y={}",
+                            array.nx,
+                            array.ny,
+                            coord(array.dx),
+                            coord(array.dy),
+                            coord(transform.offset.0),
+                            coord(transform.offset.1)
+                        )?,
+                        None => write!(
+                            out,
+                            "    inst({name}(), x={}, y={}",
+                            coord(transform.offset.0),
+                            coord(transform.offset.1)
+                        )?,
+                    }
+                    if angle.fract() != 0. {
+                        write!(out, ", angle={angle:?}")?;
+                    } else if angle != 0. {
+                        write!(out, ", angle={}", angle as i64)?;
+                    }
+                    if reflect {
+                        write!(out, ", reflect=true")?;
+                    }
+                    writeln!(out, ");")?;
+                }
+                _ => {}
+            }
+        }
+        writeln!(out, "}}")?;
+    }
+    Ok(out)
+}
+
+/// Returns the bounds of `points` if they are the corners of an axis-aligned
+/// rectangle, in order.
+fn as_rect(points: &[(f64, f64)]) -> Option<Bbox> {
+    if points.len() != 4 {
+        return None;
+    }
+    let manhattan = (0..4).all(|i| {
+        let (a, b) = (points[i], points[(i + 1) % 4]);
+        (a.0 == b.0) != (a.1 == b.1)
+    });
+    manhattan.then(|| Bbox::from_corners(points[0], points[2]))
+}
+
+/// Converts a GDS struct name into an Argon identifier that is neither a
+/// keyword nor the name of a built-in.
+fn argon_ident(name: &str) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    const KEYWORDS: [&str; 16] = [
+        "fn", "if", "as", "in", "let", "for", "mod", "enum", "cell", "true", "else", "match",
+        "const", "false", "struct", "extern",
+    ];
+    if KEYWORDS.contains(&ident.as_str()) || BUILTINS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+impl CompiledCell {
+    /// Returns the GDS struct name of the cell before uniquification.
+    ///
+    /// The name is the cell's name followed by its parameters, e.g.
+    /// `inv_w1200_l150` for `inv(1200., 150.)` with parameters `w` and `l`,
+    /// with characters that are not legal in GDS struct names replaced by `_`.
+    pub fn gds_name(&self) -> String {
+        let mut name = self.name.clone();
+        for (param, value) in &self.params {
+            name.push('_');
+            name.push_str(param);
+            name.push_str(&gds_param(value));
+        }
+        sanitize_gds_name(&name)
+    }
+}
+
+/// Formats a parameter value for use in a GDS struct name, e.g. `1200` for
+/// `1200.`, `0p5` for `0.5`, and `m3` for `-3`.
+fn gds_param(arg: &CellArg) -> String {
+    let value = match arg {
+        CellArg::Float(x) if x.fract() == 0. && x.abs() < 1e15 => format!("{}", *x as i64),
+        CellArg::Float(x) => format!("{x}"),
+        CellArg::Int(i) => format!("{i}"),
+        CellArg::Bool(b) => format!("{b}"),
+        CellArg::Enum { variant, .. } => variant.clone(),
+        CellArg::Seq(v) => v.iter().map(gds_param).collect::<Vec<_>>().join("_"),
+        CellArg::Struct { fields, .. } => {
+            fields.values().map(gds_param).collect::<Vec<_>>().join("_")
+        }
+    };
+    value.replace('-', "m").replace('.', "p")
+}
+
+/// Replaces characters other than ASCII letters, digits, `_`, `?`, and `$`,
+/// which are the only characters allowed in GDS struct names.
+fn sanitize_gds_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '?' | '$') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    }
 }
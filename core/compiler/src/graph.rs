@@ -0,0 +1,631 @@
+//! Constraint graphs of compiled scopes.
+//!
+//! A constraint graph relates the variables of the objects in a scope to the
+//! constraints that pin them, which helps explain why a variable has the value
+//! it does. Graphs are rendered in the Graphviz DOT language.
+//!
+//! The [provenance](CompiledData::provenance) of a single rect coordinate is
+//! the chain of constraints leading from it to a fixed value, for display in
+//! the GUI. The language server shows the [solved value](CoordNames::rect) of
+//! each coordinate instead.
+use std::fmt::{self, Display, Write};
+
+use anyhow::{Result, anyhow, bail};
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::Span,
+    compile::{
+        CellConstraint, CellId, CompiledCell, CompiledData, ObjectId, Rect, ScopeId, SolvedValue,
+    },
+    layer::line_col,
+    parse::WorkspaceParseAst,
+    solver::{ConstraintId, LinearExpr, Var},
+};
+
+/// Solve status of a node in a [`ConstraintGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeStatus {
+    Solved,
+    Unsolved,
+    /// A constraint that conflicts with the constraints applied before it.
+    Inconsistent,
+    /// A fallback constraint applied to pin an underconstrained variable.
+    Fallback,
+}
+
+impl NodeStatus {
+    fn color(self) -> &'static str {
+        match self {
+            NodeStatus::Solved => "black",
+            NodeStatus::Unsolved => "orange",
+            NodeStatus::Inconsistent => "red",
+            NodeStatus::Fallback => "gray50",
+        }
+    }
+}
+
+/// A variable in a [`ConstraintGraph`].
+#[derive(Debug, Clone)]
+pub struct VarNode {
+    /// Object coordinate the variable is, e.g. `r.x0`, if any.
+    pub label: Option<String>,
+    pub status: NodeStatus,
+}
+
+/// A constraint in a [`ConstraintGraph`].
+#[derive(Debug, Clone)]
+pub struct ConstraintNode {
+    pub span: Span,
+    pub status: NodeStatus,
+    pub vars: Vec<Var>,
+}
+
+/// The constraints on the variables of the objects in a scope.
+#[derive(Debug, Clone, Default)]
+pub struct ConstraintGraph {
+    pub vars: IndexMap<Var, VarNode>,
+    pub constraints: IndexMap<ConstraintId, ConstraintNode>,
+}
+
+impl CompiledData {
+    /// Returns the ID of the first scope named `name` in the top cell.
+    pub fn scope_named(&self, name: &str) -> Result<ScopeId> {
+        let cell = &self.cells[&self.top];
+        cell.scopes
+            .iter()
+            .find(|(_, scope)| scope.name == name)
+            .map(|(id, _)| *id)
+            .ok_or_else(|| {
+                anyhow!(
+                    "no scope named `{name}`; available scopes: {}",
+                    cell.scopes
+                        .values()
+                        .map(|scope| &scope.name)
+                        .unique()
+                        .join(", ")
+                )
+            })
+    }
+
+    /// Builds the constraint graph of `scope` in the top cell, covering the
+    /// objects bound or emitted in the scope and its dynamic children.
+    pub fn constraint_graph(&self, scope: ScopeId) -> ConstraintGraph {
+        let cell = &self.cells[&self.top];
+        let mut objects = IndexSet::new();
+        let mut stack = vec![scope];
+        while let Some(scope) = stack.pop() {
+            let scope = &cell.scopes[&scope];
+            for (_, obj) in scope.bindings.values() {
+                obj.for_each(&mut |obj| {
+                    objects.insert(*obj);
+                });
+            }
+            objects.extend(scope.emit.iter().map(|(obj, _)| *obj));
+            stack.extend(scope.children.iter().copied());
+        }
+
+        let var_status = |var: &Var| {
+            if cell.unsolved_vars.contains(var) {
+                NodeStatus::Unsolved
+            } else {
+                NodeStatus::Solved
+            }
+        };
+        let names = self.reachable_objs(self.top, scope);
+        let mut graph = ConstraintGraph::default();
+        for obj in objects {
+            let Some(SolvedValue::Rect(rect)) = cell.objects.get(&obj) else {
+                continue;
+            };
+            let name = names.get(&obj);
+            for (coord, (_, expr)) in [
+                ("x0", &rect.x0),
+                ("y0", &rect.y0),
+                ("x1", &rect.x1),
+                ("y1", &rect.y1),
+            ] {
+                // Only coordinates that are a variable of their own are labeled.
+                let label = match (name, expr.coeffs.as_slice()) {
+                    (Some(name), [(coeff, _)]) if *coeff == 1. && expr.constant == 0. => {
+                        Some(format!("{name}.{coord}"))
+                    }
+                    _ => None,
+                };
+                for (_, var) in &expr.coeffs {
+                    let node = graph.vars.entry(*var).or_insert(VarNode {
+                        label: None,
+                        status: var_status(var),
+                    });
+                    if node.label.is_none() {
+                        node.label.clone_from(&label);
+                    }
+                }
+            }
+        }
+
+        for (id, constraint) in &cell.constraints {
+            if !constraint
+                .vars
+                .iter()
+                .any(|var| graph.vars.contains_key(var))
+            {
+                continue;
+            }
+            let status = if cell.inconsistent_constraints.contains(id) {
+                NodeStatus::Inconsistent
+            } else if constraint.fallback {
+                NodeStatus::Fallback
+            } else if constraint
+                .vars
+                .iter()
+                .any(|var| var_status(var) == NodeStatus::Unsolved)
+            {
+                NodeStatus::Unsolved
+            } else {
+                NodeStatus::Solved
+            };
+            graph.constraints.insert(
+                *id,
+                ConstraintNode {
+                    span: constraint.span.clone(),
+                    status,
+                    vars: constraint.vars.clone(),
+                },
+            );
+        }
+        // Variables of the scope's constraints that belong to other objects.
+        for constraint in graph.constraints.values() {
+            for var in &constraint.vars {
+                graph.vars.entry(*var).or_insert(VarNode {
+                    label: None,
+                    status: var_status(var),
+                });
+            }
+        }
+        graph
+    }
+}
+
+impl ConstraintGraph {
+    /// Renders the graph in the Graphviz DOT language.
+    ///
+    /// Variables are ellipses and constraints are boxes labeled with their
+    /// source, both colored by [`NodeStatus`]. Constraint nodes link to the
+    /// file they are defined in, so they can be clicked through to the source
+    /// in SVG renderings.
+    pub fn to_dot(&self, ast: &WorkspaceParseAst) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "graph constraints {{").unwrap();
+        writeln!(dot, "  node [fontname=\"monospace\"];").unwrap();
+        for (i, (_, var)) in self.vars.iter().enumerate() {
+            let label = var.label.clone().unwrap_or_else(|| format!("v{i}"));
+            writeln!(
+                dot,
+                "  v{i} [shape=ellipse, label=\"{}\", color={}];",
+                escape(&label),
+                var.status.color()
+            )
+            .unwrap();
+        }
+        for (i, constraint) in self.constraints.values().enumerate() {
+            let path = constraint.span.path.display();
+            let (label, location) =
+                match ast.values().find(|file| file.path == constraint.span.path) {
+                    Some(file) => {
+                        let (line, col) = line_col(&file.text, constraint.span.span.start());
+                        let source =
+                            &file.text[constraint.span.span.start()..constraint.span.span.end()];
+                        (
+                            source.split_whitespace().join(" "),
+                            format!("{path}:{line}:{col}"),
+                        )
+                    }
+                    None => (path.to_string(), path.to_string()),
+                };
+            let style = if constraint.status == NodeStatus::Fallback {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "  c{i} [shape=box, label=\"{}\", tooltip=\"{}\", URL=\"file://{}\", color={}{style}];",
+                escape(&label),
+                escape(&location),
+                escape(&path.to_string()),
+                constraint.status.color()
+            )
+            .unwrap();
+            for var in &constraint.vars {
+                writeln!(dot, "  c{i} -- v{};", self.vars.get_index_of(var).unwrap()).unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+/// A constraint in a [`Provenance`] chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceStep {
+    /// What the previous coordinate in the chain equals, e.g. `r1.x1 + 50`.
+    pub expr: String,
+    /// Why, e.g. `` `eq(r2.x0, r1.x1 + 50.)` `` or ``param `pitch` ``.
+    pub reason: String,
+    /// Location of the constraint, e.g. `lib.ar:12`.
+    pub location: String,
+    pub span: Span,
+}
+
+/// The chain of constraints that fixed a rect coordinate, e.g.
+/// ``r2.x0 = r1.x1 (`eq(r2.x0, r1.x1)` at lib.ar:12) = 430 (param `pitch` at lib.ar:3)``.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// The coordinate, e.g. `r2.x0`.
+    pub coord: String,
+    pub value: f64,
+    pub steps: Vec<ProvenanceStep>,
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.coord)?;
+        for step in &self.steps {
+            write!(f, " = {} ({} at {})", step.expr, step.reason, step.location)?;
+        }
+        if self.steps.is_empty() {
+            write!(f, " = {} (unconstrained)", self.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of a rect coordinate, for display in the editor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SolvedCoord {
+    Solved(f64),
+    /// A coordinate that depends on unsolved variables, along with what it
+    /// equals in terms of other coordinates, e.g. `r1.x1 + 50`, unless it is a
+    /// variable of its own.
+    Unsolved(Option<String>),
+}
+
+impl Display for SolvedCoord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolvedCoord::Solved(value) => write!(f, "{value}"),
+            SolvedCoord::Unsolved(Some(expr)) => write!(f, "{expr} (unsolved)"),
+            SolvedCoord::Unsolved(None) => write!(f, "unsolved"),
+        }
+    }
+}
+
+/// Names and values of the rect coordinates of a compiled cell.
+pub struct CoordNames<'a> {
+    cell: &'a CompiledCell,
+    names: IndexMap<ObjectId, String>,
+    labels: IndexMap<Var, String>,
+    values: IndexMap<Var, f64>,
+}
+
+impl CoordNames<'_> {
+    fn is_solved(&self, expr: &LinearExpr) -> bool {
+        !expr
+            .coeffs
+            .iter()
+            .any(|(_, var)| self.cell.unsolved_vars.contains(var))
+    }
+
+    /// Name of `rect` and the value of each of its coordinates, e.g.
+    /// `("r", [("x0", Solved(0.)), ...])`.
+    pub fn rect(
+        &self,
+        rect: &Rect<(f64, LinearExpr)>,
+    ) -> (String, [(&'static str, SolvedCoord); 4]) {
+        let name = rect_name(rect, &self.names);
+        let coords = [
+            ("x0", &rect.x0),
+            ("y0", &rect.y0),
+            ("x1", &rect.x1),
+            ("y1", &rect.y1),
+        ]
+        .map(|(coord, (value, expr))| {
+            if self.is_solved(expr) {
+                return (coord, SolvedCoord::Solved(*value));
+            }
+            let expr = format_expr(&expr.coeffs, expr.constant, &self.labels);
+            let own = expr == format!("{name}.{coord}");
+            (coord, SolvedCoord::Unsolved((!own).then_some(expr)))
+        });
+        (name.to_string(), coords)
+    }
+
+    /// The named rect coordinates related by the constraint `id` and their
+    /// values, e.g. `[("r2.x0", Solved(580.)), ("r1.x1", Solved(530.))]`.
+    pub fn constraint(&self, id: ConstraintId) -> Vec<(String, SolvedCoord)> {
+        let Some(constraint) = self.cell.constraints.get(&id) else {
+            return Vec::new();
+        };
+        constraint
+            .vars
+            .iter()
+            .unique()
+            .filter_map(|var| {
+                let label = self.labels.get(var)?.clone();
+                let value = if self.cell.unsolved_vars.contains(var) {
+                    SolvedCoord::Unsolved(None)
+                } else {
+                    SolvedCoord::Solved(*self.values.get(var)?)
+                };
+                Some((label, value))
+            })
+            .collect()
+    }
+}
+
+impl CompiledData {
+    /// Names the rect coordinates of `cell`, e.g. `r.x0`, by the rects'
+    /// bindings in its root scope, or by their layers if they are unnamed.
+    pub fn coord_names(&self, cell: CellId) -> CoordNames<'_> {
+        let compiled = &self.cells[&cell];
+        let names = self.reachable_objs(cell, compiled.root);
+        let labels = var_labels(compiled, &names);
+        let mut values = IndexMap::new();
+        for rect in compiled.objects.values().filter_map(|obj| obj.get_rect()) {
+            for (value, expr) in [&rect.x0, &rect.y0, &rect.x1, &rect.y1] {
+                if let [(coeff, var)] = expr.coeffs.as_slice()
+                    && *coeff == 1.
+                    && expr.constant == 0.
+                {
+                    values.entry(*var).or_insert(*value);
+                }
+            }
+        }
+        CoordNames {
+            cell: compiled,
+            names,
+            labels,
+            values,
+        }
+    }
+
+    /// Explains the value of coordinate `coord` (`x0`, `y0`, `x1`, or `y1`) of
+    /// the rect created at `span`, preferring rects in the top cell.
+    ///
+    /// Starting from the coordinate, each step follows the nearest constraint
+    /// that relates the current variable to exactly one other, ending at a
+    /// constraint that pins a variable to a value. If no such chain exists,
+    /// the first constraint on the current variable ends the chain.
+    pub fn provenance(
+        &self,
+        ast: &WorkspaceParseAst,
+        span: &Span,
+        coord: &str,
+    ) -> Result<Provenance> {
+        let Some((cell_id, cell, rect)) = [self.top]
+            .into_iter()
+            .chain(self.cells.keys().copied().filter(|id| *id != self.top))
+            .find_map(|id| {
+                let cell = &self.cells[&id];
+                cell.objects
+                    .values()
+                    .filter_map(|obj| obj.get_rect())
+                    .find(|rect| rect.span.as_ref() == Some(span))
+                    .map(|rect| (id, cell, rect))
+            })
+        else {
+            bail!("no rect is created at the given location");
+        };
+        let (value, expr) = match coord {
+            "x0" => &rect.x0,
+            "y0" => &rect.y0,
+            "x1" => &rect.x1,
+            "y1" => &rect.y1,
+            _ => bail!("unknown coordinate `{coord}`; expected `x0`, `y0`, `x1`, or `y1`"),
+        };
+        let names = self.reachable_objs(cell_id, cell.root);
+        let labels = var_labels(cell, &names);
+        let mut provenance = Provenance {
+            coord: format!("{}.{coord}", rect_name(rect, &names)),
+            value: *value,
+            steps: Vec::new(),
+        };
+        let [(_, var)] = expr.coeffs.as_slice() else {
+            return Ok(provenance);
+        };
+        let mut current = *var;
+        for id in provenance_chain(cell, *var) {
+            let constraint = &cell.constraints[&id];
+            let (expr, next) = solve_for(&constraint.expr, current, &labels);
+            let Some((reason, location)) = describe(ast, cell, constraint) else {
+                break;
+            };
+            provenance.steps.push(ProvenanceStep {
+                expr,
+                reason,
+                location,
+                span: constraint.span.clone(),
+            });
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        Ok(provenance)
+    }
+}
+
+/// Name of `rect` in `names`, or its layer if it is unnamed.
+fn rect_name<'a>(
+    rect: &'a Rect<(f64, LinearExpr)>,
+    names: &'a IndexMap<ObjectId, String>,
+) -> &'a str {
+    names
+        .get(&rect.id)
+        .map(String::as_str)
+        .or(rect.layer.as_deref())
+        .unwrap_or("rect")
+}
+
+/// Labels each rect coordinate variable of `cell` with its rect's
+/// [name](rect_name), e.g. `r1.x1`.
+fn var_labels(cell: &CompiledCell, names: &IndexMap<ObjectId, String>) -> IndexMap<Var, String> {
+    let mut labels = IndexMap::new();
+    for rect in cell.objects.values().filter_map(|obj| obj.get_rect()) {
+        let name = rect_name(rect, names);
+        for (coord, (_, expr)) in [
+            ("x0", &rect.x0),
+            ("y0", &rect.y0),
+            ("x1", &rect.x1),
+            ("y1", &rect.y1),
+        ] {
+            if let [(coeff, var)] = expr.coeffs.as_slice()
+                && *coeff == 1.
+                && expr.constant == 0.
+            {
+                labels
+                    .entry(*var)
+                    .or_insert_with(|| format!("{name}.{coord}"));
+            }
+        }
+    }
+    labels
+}
+
+/// Returns the constraints leading from `var` to a constraint that pins a
+/// single variable, following constraints between two variables, or the first
+/// constraint on `var` if there is no such chain.
+fn provenance_chain(cell: &CompiledCell, var: Var) -> Vec<ConstraintId> {
+    let consistent = || {
+        cell.constraints
+            .iter()
+            .filter(|(id, _)| !cell.inconsistent_constraints.contains(*id))
+    };
+    // Breadth-first search over variables, remembering the constraint each
+    // variable was reached through.
+    let mut reached: IndexMap<Var, Option<(ConstraintId, Var)>> = IndexMap::from([(var, None)]);
+    let mut i = 0;
+    let pin = loop {
+        let Some(current) = reached.get_index(i).map(|(var, _)| *var) else {
+            break None;
+        };
+        if let Some((id, _)) = consistent().find(|(_, c)| c.vars.len() == 1 && c.vars[0] == current)
+        {
+            break Some((current, *id));
+        }
+        for (id, constraint) in consistent() {
+            if let [a, b] = constraint.vars.as_slice() {
+                let other = match (*a == current, *b == current) {
+                    (true, false) => *b,
+                    (false, true) => *a,
+                    _ => continue,
+                };
+                reached.entry(other).or_insert(Some((*id, current)));
+            }
+        }
+        i += 1;
+    };
+    let Some((mut current, pin)) = pin else {
+        return consistent()
+            .find(|(_, c)| c.vars.contains(&var))
+            .map(|(id, _)| vec![*id])
+            .unwrap_or_default();
+    };
+    let mut chain = vec![pin];
+    while let Some(Some((id, prev))) = reached.get(&current) {
+        chain.push(*id);
+        current = *prev;
+    }
+    chain.reverse();
+    chain
+}
+
+/// Solves `expr = 0` for `var`, formatting the other side with the variables
+/// labeled by `labels`.
+///
+/// Also returns the other variable if there is exactly one.
+fn solve_for(expr: &LinearExpr, var: Var, labels: &IndexMap<Var, String>) -> (String, Option<Var>) {
+    let coeff: f64 = expr
+        .coeffs
+        .iter()
+        .filter(|(_, v)| *v == var)
+        .map(|(c, _)| c)
+        .sum();
+    let terms = expr
+        .coeffs
+        .iter()
+        .filter(|(_, v)| *v != var)
+        .map(|(c, v)| (-c / coeff, *v))
+        .collect_vec();
+    let next = match terms.as_slice() {
+        [(_, v)] => Some(*v),
+        _ => None,
+    };
+    (format_expr(&terms, -expr.constant / coeff, labels), next)
+}
+
+/// Formats the sum of `terms` and `constant` with the variables labeled by
+/// `labels`, e.g. `r1.x1 + 50`.
+fn format_expr(terms: &[(f64, Var)], constant: f64, labels: &IndexMap<Var, String>) -> String {
+    // Adding zero turns `-0` into `0`.
+    let constant = constant + 0.;
+    let mut out = String::new();
+    for (i, (c, v)) in terms.iter().enumerate() {
+        let label = labels.get(v).map_or("?", String::as_str);
+        if i > 0 {
+            out.push_str(if *c < 0. { " - " } else { " + " });
+        } else if *c < 0. {
+            out.push('-');
+        }
+        if c.abs() == 1. {
+            out.push_str(label);
+        } else {
+            write!(out, "{} * {label}", c.abs()).unwrap();
+        }
+    }
+    if out.is_empty() {
+        write!(out, "{constant}").unwrap();
+    } else if constant != 0. {
+        let sign = if constant < 0. { "-" } else { "+" };
+        write!(out, " {sign} {}", constant.abs()).unwrap();
+    }
+    out
+}
+
+/// Describes `constraint` by its source and location, e.g. ``("`eq(a, b)`", "lib.ar:12")``.
+///
+/// Constraints that pin a variable to a parameter of the cell are described
+/// as ``param `name` ``.
+fn describe(
+    ast: &WorkspaceParseAst,
+    cell: &CompiledCell,
+    constraint: &CellConstraint,
+) -> Option<(String, String)> {
+    let span = &constraint.span;
+    let file = ast.values().find(|file| file.path == span.path)?;
+    let (line, _) = line_col(&file.text, span.span.start());
+    let source = file.text[span.span.start()..span.span.end()]
+        .split_whitespace()
+        .join(" ");
+    let name = span
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let reason = if constraint.fallback {
+        format!("fallback `{source}`")
+    } else if cell.params.contains_key(&source) {
+        format!("param `{source}`")
+    } else {
+        format!("`{source}`")
+    };
+    Some((reason, format!("{name}:{line}")))
+}
+
+/// Escapes `s` for use in a quoted DOT string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,312 @@
+//! Cell invocations such as `inv(1200., 2000., nf=4)`.
+//!
+//! An invocation names a cell generator and passes it literal arguments,
+//! either positionally or by parameter name. Invocations are resolved against
+//! the declaration of the cell before it is compiled, so that a mistyped cell
+//! name or argument is reported along with the cell's signature.
+use std::fmt::Display;
+
+use anyhow::{Context, bail};
+use arcstr::Substr;
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+use crate::{
+    ast::{
+        ArgDecl, AstMetadata, CellDecl, Decl, EnumDecl, Expr, ModPath, TySpec, TySpecKind, UnaryOp,
+        WorkspaceAst,
+    },
+    compile::{CellArg, VarIdTyMetadata, cell_mod_path},
+    parse::{ParseMetadata, parse_cell},
+};
+
+/// A literal argument of a cell invocation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvocationArg {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// A variant of an enum, e.g. `Flavor::Lvt`.
+    Enum {
+        name: String,
+        variant: String,
+    },
+}
+
+impl Display for InvocationArg {
+    /// Formats the argument as Argon source.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvocationArg::Int(i) => write!(f, "{i}"),
+            InvocationArg::Float(x) => write!(f, "{x:?}"),
+            InvocationArg::Bool(b) => write!(f, "{b}"),
+            InvocationArg::Enum { name, variant } => write!(f, "{name}::{variant}"),
+        }
+    }
+}
+
+/// A cell invocation resolved against the declaration of its cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    /// Path of the cell generator, e.g. `["inv"]`.
+    pub path: Vec<String>,
+    /// Arguments keyed by parameter name, in declaration order.
+    pub args: IndexMap<String, InvocationArg>,
+}
+
+impl Display for Invocation {
+    /// Formats the invocation with positional arguments, e.g. `inv(1200.0, 4)`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({})", self.name(), self.args.values().join(", "))
+    }
+}
+
+impl Invocation {
+    /// Parses `cell` and binds its arguments to the parameters of the cell
+    /// declared in `ast`.
+    ///
+    /// Arguments may be passed positionally, by name (e.g. `nf=4`), or both,
+    /// with named arguments after positional ones. Each must be an int, float,
+    /// bool, or enum literal of its parameter's type; int literals are also
+    /// accepted for float parameters.
+    pub fn resolve<T: AstMetadata>(ast: &WorkspaceAst<T>, cell: &str) -> anyhow::Result<Self> {
+        let call = parse_cell(cell)?;
+        let path = call.func.path.iter().map(|ident| ident.name).collect_vec();
+        let name = path.join("::");
+        let Some(decl) = find_cell(ast, &path) else {
+            let cells = cell_names(ast);
+            if cells.is_empty() {
+                bail!("no cell named `{name}`; the workspace declares no cells");
+            }
+            bail!(
+                "no cell named `{name}`; available cells: {}",
+                cells.join(", ")
+            );
+        };
+        let usage = || format!("usage: {}", signature(&name, decl));
+
+        let posargs = &call.args.posargs;
+        if posargs.len() > decl.args.len() {
+            bail!(
+                "`{name}` takes {} argument(s) but {} were given\n{}",
+                decl.args.len(),
+                posargs.len(),
+                usage()
+            );
+        }
+        let mut exprs = IndexMap::new();
+        for (param, arg) in decl.args.iter().zip(posargs) {
+            exprs.insert(param.name.name.as_str(), arg);
+        }
+        for kwarg in &call.args.kwargs {
+            if !decl
+                .args
+                .iter()
+                .any(|param| param.name.name.as_str() == kwarg.name.name)
+            {
+                bail!(
+                    "`{name}` has no parameter `{}`\n{}",
+                    kwarg.name.name,
+                    usage()
+                );
+            }
+            if exprs.insert(kwarg.name.name, &kwarg.value).is_some() {
+                bail!(
+                    "parameter `{}` of `{name}` is passed more than once",
+                    kwarg.name.name
+                );
+            }
+        }
+        let missing = decl
+            .args
+            .iter()
+            .filter(|param| !exprs.contains_key(param.name.name.as_str()))
+            .map(|param| format!("`{}`", param.name.name))
+            .collect_vec();
+        if !missing.is_empty() {
+            bail!(
+                "`{name}` is missing argument(s) for {}\n{}",
+                missing.join(", "),
+                usage()
+            );
+        }
+
+        let mod_path = cell_mod_path(&path);
+        let args = decl
+            .args
+            .iter()
+            .map(|param| {
+                let expr = exprs[param.name.name.as_str()];
+                let arg = literal(ast, &mod_path, &param.ty, cell, expr).with_context(|| {
+                    format!("invalid argument for parameter `{}`", param.name.name)
+                })?;
+                Ok((param.name.name.to_string(), arg))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            path: path.into_iter().map(String::from).collect(),
+            args,
+        })
+    }
+
+    /// Name of the cell generator, e.g. `inv`.
+    pub fn name(&self) -> String {
+        self.path.join("::")
+    }
+
+    /// Converts the arguments into [`CellArg`]s for compiling the invocation
+    /// against the statically compiled `ast` it was resolved against.
+    pub fn cell_args(&self, ast: &WorkspaceAst<VarIdTyMetadata>) -> Vec<CellArg> {
+        let mod_path = cell_mod_path(&self.path.iter().map(String::as_str).collect_vec());
+        self.args
+            .values()
+            .map(|arg| match arg {
+                InvocationArg::Int(i) => CellArg::Int(*i),
+                InvocationArg::Float(x) => CellArg::Float(*x),
+                InvocationArg::Bool(b) => CellArg::Bool(*b),
+                InvocationArg::Enum { name, variant } => CellArg::Enum {
+                    id: find_enum(ast, &mod_path, name)
+                        .expect("enum arguments are resolved against the same AST")
+                        .metadata
+                        .id,
+                    name: name.clone(),
+                    variant: variant.clone(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Returns the declaration of the cell at `path`.
+fn find_cell<'a, T: AstMetadata>(
+    ast: &'a WorkspaceAst<T>,
+    path: &[&str],
+) -> Option<&'a CellDecl<Substr, T>> {
+    let cell_name = path.last()?;
+    ast.get(&cell_mod_path(path))?
+        .ast
+        .decls
+        .iter()
+        .find_map(|decl| match decl {
+            Decl::Cell(cell) if &cell.name.name == cell_name => Some(cell),
+            _ => None,
+        })
+}
+
+/// Returns the paths of the cells declared in `ast` outside the standard
+/// library, e.g. `inv` or `a::top`.
+fn cell_names<T: AstMetadata>(ast: &WorkspaceAst<T>) -> Vec<String> {
+    ast.iter()
+        .filter(|(mod_path, _)| mod_path.first().is_none_or(|m| m != "std"))
+        .flat_map(|(mod_path, file)| {
+            file.ast.decls.iter().filter_map(move |decl| match decl {
+                Decl::Cell(cell) => Some(
+                    mod_path
+                        .iter()
+                        .map(String::as_str)
+                        .chain([cell.name.name.as_str()])
+                        .join("::"),
+                ),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Formats the signature of `decl`, e.g. `inv(w: Float, nf: Int)`.
+fn signature<T: AstMetadata>(name: &str, decl: &CellDecl<Substr, T>) -> String {
+    format!(
+        "{name}({})",
+        decl.args
+            .iter()
+            .map(|ArgDecl { name, ty, .. }| format!("{}: {}", name.name, ty_name(ty)))
+            .join(", ")
+    )
+}
+
+/// Formats `ty` as written in source, e.g. `[Float]`.
+pub fn ty_name<T: AstMetadata>(ty: &TySpec<Substr, T>) -> String {
+    match &ty.kind {
+        TySpecKind::Ident(ident) => ident.name.to_string(),
+        TySpecKind::Seq(elem) => format!("[{}]", ty_name(elem)),
+        TySpecKind::Tuple(tys) => format!("({})", tys.iter().map(ty_name).join(", ")),
+    }
+}
+
+/// Returns the declaration of the enum named `name`, looking in the module at
+/// `mod_path` before the rest of the workspace.
+fn find_enum<'a, T: AstMetadata>(
+    ast: &'a WorkspaceAst<T>,
+    mod_path: &ModPath,
+    name: &str,
+) -> Option<&'a EnumDecl<Substr, T>> {
+    let find = |decls: &'a [Decl<Substr, T>]| {
+        decls.iter().find_map(|decl| match decl {
+            Decl::Enum(e) if e.name.name == name => Some(e),
+            _ => None,
+        })
+    };
+    ast.get(mod_path)
+        .and_then(|file| find(&file.ast.decls))
+        .or_else(|| ast.values().find_map(|file| find(&file.ast.decls)))
+}
+
+/// Converts `expr`, an argument in `cell` for a parameter of type `ty`, into
+/// an [`InvocationArg`].
+fn literal<T: AstMetadata>(
+    ast: &WorkspaceAst<T>,
+    mod_path: &ModPath,
+    ty: &TySpec<Substr, T>,
+    cell: &str,
+    expr: &Expr<&str, ParseMetadata>,
+) -> anyhow::Result<InvocationArg> {
+    let source = &cell[expr.span().start()..expr.span().end()];
+    let TySpecKind::Ident(ty_ident) = &ty.kind else {
+        bail!(
+            "parameters of type `{}` cannot be passed in a cell invocation",
+            ty_name(ty)
+        );
+    };
+    // Negative numbers parse as negated literals.
+    let (sign, number) = match expr {
+        Expr::UnaryOp(unary) if unary.op == UnaryOp::Neg => (-1, &unary.operand),
+        _ => (1, expr),
+    };
+    match (ty_ident.name.as_str(), number, expr) {
+        ("Int", Expr::IntLiteral(i), _) => Ok(InvocationArg::Int(sign * i.value)),
+        ("Int", ..) => bail!("expected an int literal, found `{source}`"),
+        ("Float", Expr::FloatLiteral(x), _) => Ok(InvocationArg::Float(sign as f64 * x.value)),
+        ("Float", Expr::IntLiteral(i), _) => Ok(InvocationArg::Float((sign * i.value) as f64)),
+        ("Float", ..) => bail!("expected a float literal, found `{source}`"),
+        ("Bool", _, Expr::BoolLiteral(b)) => Ok(InvocationArg::Bool(b.value)),
+        ("Bool", ..) => bail!("expected `true` or `false`, found `{source}`"),
+        (name, ..) => {
+            let Some(decl) = find_enum(ast, mod_path, name) else {
+                bail!("parameters of type `{name}` cannot be passed in a cell invocation");
+            };
+            // Variants may be written with or without the enum name.
+            let variant = match expr {
+                Expr::IdentPath(path) => match path.path.as_slice() {
+                    [variant] => Some(variant.name),
+                    [.., enum_name, variant] if enum_name.name == name => Some(variant.name),
+                    _ => None,
+                },
+                _ => None,
+            }
+            .filter(|variant| decl.variants.iter().any(|v| v.name.name == *variant));
+            let Some(variant) = variant else {
+                bail!(
+                    "expected a variant of `{name}`, found `{source}`; variants: {}",
+                    decl.variants
+                        .iter()
+                        .map(|variant| format!("{name}::{}", variant.name.name))
+                        .join(", ")
+                );
+            };
+            Ok(InvocationArg::Enum {
+                name: name.to_string(),
+                variant: variant.to_string(),
+            })
+        }
+    }
+}
@@ -1,7 +1,22 @@
+use std::{
+    cmp::Reverse,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use arcstr::Substr;
+use indexmap::{IndexMap, IndexSet};
 use klayout_lyp::KlayoutLayerProperties;
 use rgb::Rgb;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    ast::{Decl, Expr, Scope, Span, Statement},
+    config::Config,
+    parse::{ParseMetadata, WorkspaceParseAst},
+};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LayerProperties {
     pub layers: Vec<Layer>,
@@ -29,3 +44,360 @@ impl From<KlayoutLayerProperties> for LayerProperties {
         }
     }
 }
+
+impl LayerProperties {
+    /// Parses the contents of a KLayout layer properties (`.lyp`) file.
+    pub fn from_lyp_str(text: &str) -> Result<Self> {
+        klayout_lyp::from_reader(text.as_bytes())
+            .map(Into::into)
+            .map_err(|_| anyhow!("invalid layer properties"))
+    }
+}
+
+/// Where a [`LayerRef`] appears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LayerRefKind {
+    /// A string literal in Argon source.
+    Source,
+    /// A layer in `Argon.toml`.
+    Config,
+    /// The layer's definition in the layer properties file.
+    Definition,
+}
+
+/// A reference to a layer by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerRef {
+    pub layer: String,
+    pub kind: LayerRefKind,
+    /// Span of the layer name, excluding any surrounding quotes.
+    pub span: Span,
+    /// 1-indexed line of the reference.
+    pub line: usize,
+    /// 1-indexed column of the reference.
+    pub col: usize,
+}
+
+/// Every reference to a layer across a workspace.
+///
+/// Displays as a report of each referenced layer and where it is used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayerUsage {
+    pub refs: Vec<LayerRef>,
+}
+
+pub(crate) fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+fn layer_ref(layer: &str, kind: LayerRefKind, path: &Path, text: &str, start: usize) -> LayerRef {
+    let (line, col) = line_col(text, start);
+    LayerRef {
+        layer: layer.to_string(),
+        kind,
+        span: Span {
+            path: path.to_path_buf(),
+            span: cfgrammar::Span::new(start, start + layer.len()),
+        },
+        line,
+        col,
+    }
+}
+
+/// Collects layer references from the string literals in an Argon source file.
+///
+/// A string literal is a layer reference if it names a layer in the layer
+/// properties or is passed as the layer of `rect`, `crect`, or `text`.
+struct SourceRefs<'a> {
+    layers: &'a IndexSet<String>,
+    path: &'a Path,
+    text: &'a str,
+    refs: Vec<LayerRef>,
+}
+
+impl SourceRefs<'_> {
+    fn scope(&mut self, scope: &Scope<Substr, ParseMetadata>) {
+        for stmt in &scope.stmts {
+            match stmt {
+                Statement::Expr { value, .. } => self.expr(value, false),
+                Statement::LetBinding(binding) => self.expr(&binding.value, false),
+                Statement::ForLoop(for_loop) => {
+                    self.expr(&for_loop.seq, false);
+                    self.scope(&for_loop.body);
+                }
+            }
+        }
+        if let Some(tail) = &scope.tail {
+            self.expr(tail, false);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<Substr, ParseMetadata>, layer_arg: bool) {
+        match expr {
+            Expr::If(e) => {
+                self.expr(&e.cond, false);
+                self.scope(&e.then);
+                self.scope(&e.else_);
+            }
+            Expr::Match(e) => {
+                self.expr(&e.scrutinee, false);
+                for arm in &e.arms {
+                    self.expr(&arm.expr, false);
+                }
+            }
+            Expr::Comparison(e) => {
+                self.expr(&e.left, false);
+                self.expr(&e.right, false);
+            }
+            Expr::BinOp(e) => {
+                self.expr(&e.left, false);
+                self.expr(&e.right, false);
+            }
+            Expr::UnaryOp(e) => self.expr(&e.operand, false),
+            Expr::Call(call) => {
+                let func = match call.func.path.as_slice() {
+                    [ident] => ident.name.as_str(),
+                    _ => "",
+                };
+                let layer_pos = match func {
+                    "rect" | "polygon" => Some(0),
+                    "text" => Some(1),
+                    _ => None,
+                };
+                for (i, arg) in call.args.posargs.iter().enumerate() {
+                    self.expr(arg, layer_pos == Some(i));
+                }
+                for kwarg in &call.args.kwargs {
+                    self.expr(
+                        &kwarg.value,
+                        func == "crect" && kwarg.name.name.as_str() == "layer",
+                    );
+                }
+            }
+            Expr::Emit(e) => self.expr(&e.value, false),
+            Expr::FieldAccess(e) => self.expr(&e.base, false),
+            Expr::IndexFieldAccess(e) => self.expr(&e.base, false),
+            Expr::Index(e) => {
+                self.expr(&e.base, false);
+                self.expr(&e.index, false);
+            }
+            Expr::StringLiteral(lit) => {
+                if layer_arg || self.layers.contains(lit.value.as_str()) {
+                    // Skip the opening quote.
+                    let start = lit.span.start() + 1;
+                    self.refs.push(layer_ref(
+                        &lit.value,
+                        LayerRefKind::Source,
+                        self.path,
+                        self.text,
+                        start,
+                    ));
+                }
+            }
+            Expr::Scope(scope) => self.scope(scope),
+            Expr::Cast(e) => self.expr(&e.value, false),
+            Expr::Tuple(e) => {
+                for item in &e.items {
+                    self.expr(item, false);
+                }
+            }
+            Expr::Range(e) => {
+                self.expr(&e.start, false);
+                self.expr(&e.end, false);
+            }
+            Expr::Seq(e) => {
+                for item in &e.items {
+                    self.expr(item, false);
+                }
+            }
+            Expr::IdentPath(_)
+            | Expr::Nil(_)
+            | Expr::SeqNil(_)
+            | Expr::FloatLiteral(_)
+            | Expr::IntLiteral(_)
+            | Expr::BoolLiteral(_) => {}
+        }
+    }
+}
+
+impl LayerUsage {
+    /// Finds the layer references in the Argon source of `ast`, the layers in
+    /// `config`, and the layer definitions in the layer properties file.
+    ///
+    /// The standard library is skipped.
+    pub fn from_workspace(
+        root_dir: &Path,
+        config: &Config,
+        ast: &WorkspaceParseAst,
+    ) -> Result<Self> {
+        let mut refs = Vec::new();
+
+        // Layer properties are XML with each layer's name in a `<name>` element.
+        let lyp_path = config.lyp_path(root_dir);
+        let lyp = std::fs::read_to_string(&lyp_path)
+            .with_context(|| format!("failed to read layer properties at {lyp_path:?}"))?;
+        let mut rest = 0;
+        while let Some(i) = lyp[rest..].find("<name>") {
+            let start = rest + i + "<name>".len();
+            let Some(len) = lyp[start..].find("</name>") else {
+                break;
+            };
+            let name = &lyp[start..start + len];
+            let trimmed = name.trim_start();
+            refs.push(layer_ref(
+                trimmed.trim_end(),
+                LayerRefKind::Definition,
+                &lyp_path,
+                &lyp,
+                start + name.len() - trimmed.len(),
+            ));
+            rest = start + len;
+        }
+        let layers = refs
+            .iter()
+            .map(|r| r.layer.clone())
+            .collect::<IndexSet<_>>();
+
+        for (mod_path, file) in ast {
+            if mod_path.first().map(String::as_str) == Some("std") {
+                continue;
+            }
+            let mut source = SourceRefs {
+                layers: &layers,
+                path: &file.path,
+                text: &file.text,
+                refs: Vec::new(),
+            };
+            for decl in &file.ast.decls {
+                match decl {
+                    Decl::Cell(cell) => source.scope(&cell.scope),
+                    Decl::Fn(f) => source.scope(&f.scope),
+                    Decl::Constant(c) => source.expr(&c.value, false),
+                    Decl::Enum(e) => {
+                        for value in e.variants.iter().flat_map(|v| &v.values) {
+                            source.expr(value, false);
+                        }
+                    }
+                    Decl::Struct(_) | Decl::Mod(_) => {}
+                }
+            }
+            refs.extend(source.refs);
+        }
+
+        let config_path = root_dir.join("Argon.toml");
+        if let Ok(text) = std::fs::read_to_string(&config_path) {
+            let config_layers = config
+                .connect
+                .iter()
+                .flat_map(|c| [&c.top, &c.bot].into_iter().chain(&c.via))
+                .chain(config.density.iter().flat_map(|rule| {
+                    std::iter::once(&rule.layer).chain(rule.fill.as_ref().map(|fill| &fill.layer))
+                }))
+                .collect::<IndexSet<_>>();
+            for layer in config_layers {
+                for quote in ['"', '\''] {
+                    let quoted = format!("{quote}{layer}{quote}");
+                    for (i, _) in text.match_indices(&quoted) {
+                        refs.push(layer_ref(
+                            layer,
+                            LayerRefKind::Config,
+                            &config_path,
+                            &text,
+                            i + 1,
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(Self { refs })
+    }
+
+    /// References grouped by layer, in order of first appearance.
+    pub fn by_layer(&self) -> IndexMap<&str, Vec<&LayerRef>> {
+        let mut layers: IndexMap<&str, Vec<&LayerRef>> = IndexMap::new();
+        for r in &self.refs {
+            layers.entry(r.layer.as_str()).or_default().push(r);
+        }
+        layers
+    }
+
+    /// Returns the reference at byte `offset` of the file at `path`, if any.
+    pub fn find(&self, path: &Path, offset: usize) -> Option<&LayerRef> {
+        self.refs.iter().find(|r| {
+            r.span.path == path && (r.span.span.start()..=r.span.span.end()).contains(&offset)
+        })
+    }
+
+    /// Checks that `to` is a valid new name for the layer `from`.
+    pub fn check_rename(&self, from: &str, to: &str) -> Result<()> {
+        if !self.refs.iter().any(|r| r.layer == from) {
+            bail!("layer `{from}` is not referenced in the workspace");
+        }
+        if to.is_empty() || to.contains(['"', '\'', '<', '>', '&']) || to.trim() != to {
+            bail!("`{to}` is not a valid layer name");
+        }
+        if from != to && self.refs.iter().any(|r| r.layer == to) {
+            bail!("layer `{to}` already exists");
+        }
+        Ok(())
+    }
+
+    /// Renames the layer `from` to `to` in every file that references it,
+    /// returning the paths of the modified files.
+    pub fn rename(&self, from: &str, to: &str) -> Result<Vec<PathBuf>> {
+        self.check_rename(from, to)?;
+        let mut files: IndexMap<&Path, Vec<&LayerRef>> = IndexMap::new();
+        for r in self.refs.iter().filter(|r| r.layer == from) {
+            files.entry(r.span.path.as_path()).or_default().push(r);
+        }
+        for (path, refs) in &mut files {
+            let mut text = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {path:?}"))?;
+            // Replace back to front so earlier offsets stay valid.
+            refs.sort_by_key(|r| Reverse(r.span.span.start()));
+            for r in refs.iter() {
+                let range = r.span.span.start()..r.span.span.end();
+                if text.get(range.clone()) != Some(from) {
+                    bail!("{path:?} changed since layer references were collected");
+                }
+                text.replace_range(range, to);
+            }
+            std::fs::write(path, text).with_context(|| format!("failed to write {path:?}"))?;
+        }
+        Ok(files.into_keys().map(Path::to_path_buf).collect())
+    }
+}
+
+impl Display for LayerUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (layer, refs) in self.by_layer() {
+            let uses = refs
+                .iter()
+                .filter(|r| r.kind != LayerRefKind::Definition)
+                .count();
+            if uses == 0 {
+                continue;
+            }
+            write!(f, "{layer}: {uses} reference(s)")?;
+            if refs.iter().all(|r| r.kind != LayerRefKind::Definition) {
+                write!(f, ", not in layer properties")?;
+            }
+            writeln!(f)?;
+            for r in refs {
+                write!(f, "  {}:{}:{}", r.span.path.display(), r.line, r.col)?;
+                match r.kind {
+                    LayerRefKind::Source => writeln!(f)?,
+                    LayerRefKind::Config => writeln!(f, " (config)")?,
+                    LayerRefKind::Definition => writeln!(f, " (definition)")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
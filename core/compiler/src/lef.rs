@@ -0,0 +1,218 @@
+//! LEF export of cell abstracts.
+use std::{fmt::Write, path::Path};
+
+use anyhow::{Result, anyhow};
+use argon_geometry::Bbox;
+use indexmap::IndexMap;
+use tracing::trace;
+
+use crate::compile::{CellId, CompileOutput, CompiledData, ExecErrorCompileOutput, SolvedValue};
+
+/// LEF database units per micron; one unit per layout unit (nm).
+pub const LEF_UNITS_PER_MICRON: u32 = 1000;
+
+/// Formats a coordinate in layout units as microns.
+fn lef_coord(coord: f64) -> String {
+    let s = format!("{:.3}", coord / LEF_UNITS_PER_MICRON as f64);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Returns the routing layer of an Argon layer, dropping any purpose suffix,
+/// e.g. `met1.drawing` becomes `met1`.
+fn lef_layer(layer: &str) -> &str {
+    layer.split_once('.').map_or(layer, |(layer, _)| layer)
+}
+
+/// Makes `name` a valid LEF identifier by replacing whitespace and reserved characters.
+fn lef_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_whitespace() || matches!(c, ';' | '#' | '"') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn write_rect(out: &mut String, indent: &str, bbox: &Bbox) -> Result<()> {
+    writeln!(
+        out,
+        "{indent}RECT {} {} {} {} ;",
+        lef_coord(bbox.x0),
+        lef_coord(bbox.y0),
+        lef_coord(bbox.x1),
+        lef_coord(bbox.y1)
+    )?;
+    Ok(())
+}
+
+/// A shape of a LEF pin or obstruction.
+enum LefShape {
+    Rect(Bbox),
+    Polygon(Vec<(f64, f64)>),
+}
+
+impl CompileOutput {
+    /// Writes abstracts of the top cell and the cells it instantiates to a LEF file.
+    pub fn to_lef(&self, out_path: impl AsRef<Path>) -> Result<()> {
+        let out_path = out_path.as_ref();
+        trace!("Exporting to lef at {out_path:?}");
+        let (CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        })) = self
+        else {
+            return Err(anyhow!("no compiled cell to export"));
+        };
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(out_path, output.to_lef_string()?)?;
+        Ok(())
+    }
+}
+
+impl CompiledData {
+    /// Renders the top cell and the cells it instantiates as LEF macros.
+    ///
+    /// Macros are named as in GDS export, so they line up with the components
+    /// written by [`CompiledData::to_def_string`]. Each macro's size is the
+    /// cell's bounding box and its origin is moved to the box's lower-left
+    /// corner. Pins come from the cell's `pin`s and from text labels on a rect
+    /// of the same layer. All other flattened geometry becomes obstructions on
+    /// its routing layer, i.e. its layer without any purpose suffix.
+    pub fn to_lef_string(&self) -> Result<String> {
+        let names = self.gds_cell_names(self.top);
+
+        let mut out = String::new();
+        writeln!(out, "VERSION 5.8 ;")?;
+        writeln!(out, "BUSBITCHARS \"[]\" ;")?;
+        writeln!(out, "DIVIDERCHAR \"/\" ;")?;
+        writeln!(out, "UNITS")?;
+        writeln!(out, "  DATABASE MICRONS {LEF_UNITS_PER_MICRON} ;")?;
+        writeln!(out, "END UNITS")?;
+        for (&cell, name) in &names {
+            writeln!(out)?;
+            self.write_lef_macro(&mut out, cell, name)?;
+        }
+        writeln!(out)?;
+        writeln!(out, "END LIBRARY")?;
+        Ok(out)
+    }
+
+    /// Writes the abstract of `cell` as a macro named `name`.
+    fn write_lef_macro(&self, out: &mut String, cell: CellId, name: &str) -> Result<()> {
+        let bbox = self
+            .cell_bbox(cell)
+            .map_or(Bbox::point((0., 0.)), |rect| rect.bbox());
+        writeln!(out, "MACRO {name}")?;
+        writeln!(out, "  CLASS BLOCK ;")?;
+        writeln!(
+            out,
+            "  ORIGIN {} {} ;",
+            lef_coord(-bbox.x0),
+            lef_coord(-bbox.y0)
+        )?;
+        writeln!(out, "  FOREIGN {name} 0 0 ;")?;
+        writeln!(
+            out,
+            "  SIZE {} BY {} ;",
+            lef_coord(bbox.width()),
+            lef_coord(bbox.height())
+        )?;
+
+        let mut pins: IndexMap<String, Vec<(&str, Bbox)>> = IndexMap::new();
+        for obj in self.emitted(cell) {
+            match obj {
+                SolvedValue::Pin(pin) => {
+                    pins.entry(lef_name(&pin.name))
+                        .or_default()
+                        .push((lef_layer(&pin.layer), pin.bbox()));
+                }
+                SolvedValue::Text(text) => {
+                    // Labels without a rect underneath have no shape to export.
+                    if let Some(rect) = self.emitted(cell).find_map(|obj| match obj {
+                        SolvedValue::Rect(rect)
+                            if !rect.construction
+                                && rect.layer.as_deref().map(lef_layer)
+                                    == Some(lef_layer(&text.layer))
+                                && (rect.x0.0..=rect.x1.0).contains(&text.x)
+                                && (rect.y0.0..=rect.y1.0).contains(&text.y) =>
+                        {
+                            Some(rect.to_float().bbox())
+                        }
+                        _ => None,
+                    }) {
+                        pins.entry(lef_name(&text.text))
+                            .or_default()
+                            .push((lef_layer(&text.layer), rect));
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (pin, shapes) in &pins {
+            writeln!(out, "  PIN {pin}")?;
+            writeln!(out, "    DIRECTION INOUT ;")?;
+            writeln!(out, "    USE SIGNAL ;")?;
+            writeln!(out, "    PORT")?;
+            for (layer, bbox) in shapes {
+                writeln!(out, "      LAYER {layer} ;")?;
+                write_rect(out, "        ", bbox)?;
+            }
+            writeln!(out, "    END")?;
+            writeln!(out, "  END {pin}")?;
+        }
+
+        let is_pin = |layer: &str, bbox: &Bbox| {
+            pins.values()
+                .flatten()
+                .any(|(pin_layer, pin)| *pin_layer == layer && pin == bbox)
+        };
+        let mut obs: IndexMap<&str, Vec<LefShape>> = IndexMap::new();
+        for flat in self.iter_flat(cell, None) {
+            let Some(layer) = flat.layer.map(lef_layer) else {
+                continue;
+            };
+            let bbox = flat.rect.bbox();
+            if !is_pin(layer, &bbox) {
+                obs.entry(layer).or_default().push(LefShape::Rect(bbox));
+            }
+        }
+        for (layer, points) in self.iter_flat_polygons(cell, None) {
+            obs.entry(lef_layer(layer))
+                .or_default()
+                .push(LefShape::Polygon(points));
+        }
+        if !obs.is_empty() {
+            writeln!(out, "  OBS")?;
+            for (layer, shapes) in &obs {
+                writeln!(out, "    LAYER {layer} ;")?;
+                for shape in shapes {
+                    match shape {
+                        LefShape::Rect(bbox) => write_rect(out, "      ", bbox)?,
+                        LefShape::Polygon(points) => {
+                            let points = points
+                                .iter()
+                                .map(|(x, y)| format!("{} {}", lef_coord(*x), lef_coord(*y)))
+                                .collect::<Vec<_>>()
+                                .join(" ");
+                            writeln!(out, "      POLYGON {points} ;")?;
+                        }
+                    }
+                }
+            }
+            writeln!(out, "  END")?;
+        }
+        writeln!(out, "END {name}")?;
+        Ok(())
+    }
+}
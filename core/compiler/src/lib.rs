@@ -1,11 +1,30 @@
 pub mod ast;
 pub mod compile;
 pub mod config;
+pub mod consts;
+pub mod dbu;
+pub mod def;
+pub mod density;
+pub mod diagnostics;
+pub mod drc;
 pub mod gds;
+pub mod graph;
+pub mod invocation;
 pub mod layer;
+pub mod lef;
+pub mod lint;
+pub mod lvs;
+pub mod nets;
+pub mod oasis;
+pub mod overrides;
 pub mod parse;
 mod parser;
+pub mod rotation;
+pub mod schema;
 pub mod solver;
+pub mod svg;
+pub mod sweep;
+pub mod symbols;
 
 /// A global allocator that tracks live and peak heap usage so that the scaling
 /// benchmarks in the test module can report memory consumption alongside
@@ -89,17 +108,33 @@ static BENCH_ALLOC: bench_alloc::Tracking = bench_alloc::Tracking;
 #[cfg(test)]
 mod tests {
 
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use crate::{
-        compile::{ExecErrorKind, SolvedValue, StaticErrorKind},
-        gds::GdsMap,
-        parse::parse_workspace_with_std,
+        compile::{
+            ExecErrorKind, SolvedValue, StaticErrorKind, Ty, dynamic_compile_invocation,
+            dynamic_compile_with_layers, static_compile,
+        },
+        config::{Config, SolverBackend, SolverConfig, parse_config},
+        consts::{ConstValue, eval_consts},
+        dbu::{DbuRect, format_on_grid},
+        diagnostics::{Diagnostic, Severity},
+        gds::{GdsMap, LayoutFormat, import_gds, import_gds_source},
+        graph::{NodeStatus, SolvedCoord},
+        invocation::Invocation,
+        layer::{LayerProperties, LayerRefKind, LayerUsage},
+        lint::{LintLevel, LintRule, Linter},
+        overrides::{invocation_name, invocation_with_params, override_invocation},
+        parse::{MemoryFiles, parse_workspace_with_std, parse_workspace_with_std_in},
+        schema::{SCHEMA_VERSION, SchemaError},
+        sweep::{SweepCache, sweep_invocations, sweep_values},
+        symbols::{SymbolKind, SymbolTable},
     };
-    use ::gds::GdsUnits;
+    use ::gds::{GdsElement, GdsLibrary, GdsUnits};
     use approx::assert_relative_eq;
     use approx::relative_eq;
     use const_format::concatcp;
+    use indexmap::IndexMap;
     use pegasus::drc::{DrcParams, run_drc};
 
     use crate::compile::{CellArg, CompileInput, compile};
@@ -114,6 +149,10 @@ mod tests {
     const ARGON_IMMEDIATE: &str = concatcp!(EXAMPLES_DIR, "/immediate/lib.ar");
     const ARGON_IF: &str = concatcp!(EXAMPLES_DIR, "/if/lib.ar");
     const ARGON_IF_INCONSISTENT: &str = concatcp!(EXAMPLES_DIR, "/if_inconsistent/lib.ar");
+    const ARGON_INCONSISTENT_CONFLICTS: &str =
+        concatcp!(EXAMPLES_DIR, "/inconsistent_conflicts/lib.ar");
+    const ARGON_IF_MISMATCHED: &str = concatcp!(EXAMPLES_DIR, "/if_mismatched/lib.ar");
+    const ARGON_IF_UNDERCONSTRAINED: &str = concatcp!(EXAMPLES_DIR, "/if_underconstrained/lib.ar");
     const ARGON_VIA: &str = concatcp!(EXAMPLES_DIR, "/via/lib.ar");
     const ARGON_VIA_ARRAY: &str = concatcp!(EXAMPLES_DIR, "/via_array/lib.ar");
     const ARGON_FUNC_OUT_OF_ORDER: &str = concatcp!(EXAMPLES_DIR, "/func_out_of_order/lib.ar");
@@ -121,13 +160,31 @@ mod tests {
     const ARGON_NESTED_INST: &str = concatcp!(EXAMPLES_DIR, "/nested_inst/lib.ar");
     const ARGON_CELL_OUT_OF_ORDER: &str = concatcp!(EXAMPLES_DIR, "/cell_out_of_order/lib.ar");
     const ARGON_FALLBACK_BASIC: &str = concatcp!(EXAMPLES_DIR, "/fallback_basic/lib.ar");
+    const ARGON_PROVENANCE: &str = concatcp!(EXAMPLES_DIR, "/provenance/lib.ar");
     const ARGON_FALLBACK_INST: &str = concatcp!(EXAMPLES_DIR, "/fallback_inst/lib.ar");
     const ARGON_BOOL_LITERAL: &str = concatcp!(EXAMPLES_DIR, "/bool_literal/lib.ar");
     const ARGON_DIMENSIONS: &str = concatcp!(EXAMPLES_DIR, "/dimensions/lib.ar");
     const ARGON_PARAM_FLOAT: &str = concatcp!(EXAMPLES_DIR, "/param_float/lib.ar");
     const ARGON_PARAM_INT: &str = concatcp!(EXAMPLES_DIR, "/param_int/lib.ar");
+    const ARGON_CELL_ARGS: &str = concatcp!(EXAMPLES_DIR, "/cell_args/lib.ar");
     const ARGON_ENUMERATIONS: &str = concatcp!(EXAMPLES_DIR, "/enumerations/lib.ar");
+    const ARGON_MATH: &str = concatcp!(EXAMPLES_DIR, "/math/lib.ar");
+    const ARGON_ALIGNMENT: &str = concatcp!(EXAMPLES_DIR, "/alignment/lib.ar");
+    const ARGON_DISTRIBUTE_ANY: &str = concatcp!(EXAMPLES_DIR, "/distribute_any/lib.ar");
+    const ARGON_INST_ARRAY: &str = concatcp!(EXAMPLES_DIR, "/inst_array/lib.ar");
+    const ARGON_ENUM_DATA: &str = concatcp!(EXAMPLES_DIR, "/enum_data/lib.ar");
+    const ARGON_CONSTRUCTION_SCOPE: &str = concatcp!(EXAMPLES_DIR, "/construction_scope/lib.ar");
+    const ARGON_BOOLEAN_OPS: &str = concatcp!(EXAMPLES_DIR, "/boolean_ops/lib.ar");
     const ARGON_BBOX: &str = concatcp!(EXAMPLES_DIR, "/bbox/lib.ar");
+    const ARGON_BBOX_FIELDS: &str = concatcp!(EXAMPLES_DIR, "/bbox_fields/lib.ar");
+    const ARGON_FN_GEOMETRY: &str = concatcp!(EXAMPLES_DIR, "/fn_geometry/lib.ar");
+    const ARGON_EXTERN_CELL: &str = concatcp!(EXAMPLES_DIR, "/extern_cell/lib.ar");
+    const ARGON_MERGE_SHAPES: &str = concatcp!(EXAMPLES_DIR, "/merge_shapes/lib.ar");
+    const ARGON_UNITS: &str = concatcp!(EXAMPLES_DIR, "/units/lib.ar");
+    const ARGON_MIXED_UNITS: &str = concatcp!(EXAMPLES_DIR, "/mixed_units/lib.ar");
+    const ARGON_ROTATION: &str = concatcp!(EXAMPLES_DIR, "/rotation/lib.ar");
+    const ARGON_DBU: &str = concatcp!(EXAMPLES_DIR, "/dbu/lib.ar");
+    const ARGON_LINT: &str = concatcp!(EXAMPLES_DIR, "/lint/lib.ar");
     const ARGON_ROUNDING: &str = concatcp!(EXAMPLES_DIR, "/rounding/lib.ar");
     const ARGON_FLIPPED_RECT: &str = concatcp!(EXAMPLES_DIR, "/flipped_rect/lib.ar");
     const ARGON_SEQ_BASIC: &str = concatcp!(EXAMPLES_DIR, "/seq_basic/lib.ar");
@@ -135,10 +192,20 @@ mod tests {
     const ARGON_SEQ_FN: &str = concatcp!(EXAMPLES_DIR, "/seq_fn/lib.ar");
     const ARGON_SEQ_RECUR: &str = concatcp!(EXAMPLES_DIR, "/seq_recur/lib.ar");
     const ARGON_LUB_MATCH: &str = concatcp!(EXAMPLES_DIR, "/lub_match/lib.ar");
+    const ARGON_MATCH_WILDCARD: &str = concatcp!(EXAMPLES_DIR, "/match_wildcard/lib.ar");
+    const ARGON_MATCH_UNREACHABLE: &str = concatcp!(EXAMPLES_DIR, "/match_unreachable/lib.ar");
     const ARGON_SEQ_CELL: &str = concatcp!(EXAMPLES_DIR, "/seq_cell/lib.ar");
     const ARGON_WORKSPACE: &str = concatcp!(EXAMPLES_DIR, "/argon_workspace/lib.ar");
     const ARGON_EXTERNAL_MODS: &str = concatcp!(EXAMPLES_DIR, "/external_mods/main_crate/lib.ar");
     const ARGON_TEXT: &str = concatcp!(EXAMPLES_DIR, "/text/lib.ar");
+    const ARGON_POLYGON: &str = concatcp!(EXAMPLES_DIR, "/polygon/lib.ar");
+    const ARGON_PIN: &str = concatcp!(EXAMPLES_DIR, "/pin/lib.ar");
+    const ARGON_LVS: &str = concatcp!(EXAMPLES_DIR, "/lvs/lib.ar");
+    const ARGON_NETLIST: &str = concatcp!(EXAMPLES_DIR, "/netlist/lib.ar");
+    const ARGON_DENSITY: &str = concatcp!(EXAMPLES_DIR, "/density/lib.ar");
+    const ARGON_DRC: &str = concatcp!(EXAMPLES_DIR, "/drc/lib.ar");
+    const ARGON_GUARD_RING: &str = concatcp!(EXAMPLES_DIR, "/guard_ring/lib.ar");
+    const ARGON_SKY130_INVERTER: &str = concatcp!(EXAMPLES_DIR, "/sky130_inverter/lib.ar");
     const ARGON_ANY_TYPE: &str = concatcp!(EXAMPLES_DIR, "/any_type/lib.ar");
     const ARGON_SEQ_INDEX: &str = concatcp!(EXAMPLES_DIR, "/seq_index/lib.ar");
     const ARGON_SEQ_CONSTRUCTOR: &str = concatcp!(EXAMPLES_DIR, "/seq_constructor/lib.ar");
@@ -150,7 +217,16 @@ mod tests {
     const ARGON_TUPLE_BASIC: &str = concatcp!(EXAMPLES_DIR, "/tuple_basic/lib.ar");
     const ARGON_TUPLE_ANY: &str = concatcp!(EXAMPLES_DIR, "/tuple_any/lib.ar");
     const ARGON_FOR_LOOP_BASIC: &str = concatcp!(EXAMPLES_DIR, "/for_loop_basic/lib.ar");
+    const ARGON_FOR_RANGE: &str = concatcp!(EXAMPLES_DIR, "/for_range/lib.ar");
+    const ARGON_SEQ_LITERAL: &str = concatcp!(EXAMPLES_DIR, "/seq_literal/lib.ar");
+    const ARGON_STRUCTS: &str = concatcp!(EXAMPLES_DIR, "/structs/lib.ar");
+    const ARGON_STRUCTS_INVALID: &str = concatcp!(EXAMPLES_DIR, "/structs_invalid/lib.ar");
+    const ARGON_BUILTIN_ARGS_INVALID: &str =
+        concatcp!(EXAMPLES_DIR, "/builtin_args_invalid/lib.ar");
     const ARGON_RANGE_PERF: &str = concatcp!(EXAMPLES_DIR, "/range_perf/lib.ar");
+    const ARGON_CONSTANTS: &str = concatcp!(EXAMPLES_DIR, "/constants/lib.ar");
+    const ARGON_CONSTANTS_INVALID: &str = concatcp!(EXAMPLES_DIR, "/constants_invalid/lib.ar");
+    const ARGON_CONSTANTS_OVERFLOW: &str = concatcp!(EXAMPLES_DIR, "/constants_overflow/lib.ar");
     const ARGON_SSE_BASIC: &str = concatcp!(EXAMPLES_DIR, "/sse_basic/lib.ar");
     const ARGON_PRECEDENCE: &str = concatcp!(EXAMPLES_DIR, "/precedence/lib.ar");
 
@@ -313,6 +389,7 @@ mod tests {
                         cell: &["shapes"],
                         args: vec![CellArg::Int(n)],
                         lyp_file: &PathBuf::from(BASIC_LYP),
+                        solver: Default::default(),
                     },
                 )
             });
@@ -357,6 +434,7 @@ mod tests {
                         cell: &["shapes_loop"],
                         args: vec![CellArg::Int(n)],
                         lyp_file: &PathBuf::from(BASIC_LYP),
+                        solver: Default::default(),
                     },
                 )
             });
@@ -393,6 +471,7 @@ mod tests {
                         cell: &["constraints"],
                         args: vec![CellArg::Int(n)],
                         lyp_file: &PathBuf::from(BASIC_LYP),
+                        solver: Default::default(),
                     },
                 )
             });
@@ -427,6 +506,7 @@ mod tests {
                         cell: &["instances"],
                         args: vec![CellArg::Int(n)],
                         lyp_file: &PathBuf::from(BASIC_LYP),
+                        solver: Default::default(),
                     },
                 )
             });
@@ -471,6 +551,7 @@ mod tests {
                         cell: &[&cellname],
                         args: vec![],
                         lyp_file: &PathBuf::from(BASIC_LYP),
+                        solver: Default::default(),
                     },
                 )
             });
@@ -507,6 +588,7 @@ mod tests {
                         cell: &[&cellname],
                         args: vec![],
                         lyp_file: &PathBuf::from(BASIC_LYP),
+                        solver: Default::default(),
                     },
                 )
             });
@@ -535,6 +617,7 @@ mod tests {
                     cell: &[cell],
                     args: vec![CellArg::Int(64)],
                     lyp_file: &PathBuf::from(BASIC_LYP),
+                    solver: Default::default(),
                 },
             );
             let d = out.unwrap_valid();
@@ -559,6 +642,7 @@ mod tests {
                 cell: &["constraints"],
                 args: vec![CellArg::Int(32)],
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         assert!(
@@ -578,6 +662,7 @@ mod tests {
                 cell: &["instances"],
                 args: vec![CellArg::Int(64)],
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         let d = out.unwrap_valid();
@@ -601,6 +686,7 @@ mod tests {
                 cell: &["h8"],
                 args: vec![],
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         let d = out.unwrap_valid();
@@ -619,6 +705,7 @@ mod tests {
                 cell: &["scopes"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
@@ -635,6 +722,7 @@ mod tests {
                 cell: &["immediate"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
@@ -651,6 +739,7 @@ mod tests {
                 cell: &["if_test"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
@@ -667,12 +756,137 @@ mod tests {
                 cell: &["if_test"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
         cell.unwrap_exec_errors();
     }
 
+    #[test]
+    fn argon_inconsistent_conflicts() {
+        let o = parse_workspace_with_std(ARGON_INCONSISTENT_CONFLICTS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_exec_errors();
+        let conflicts = errors
+            .errors
+            .iter()
+            .find_map(|e| match &e.kind {
+                ExecErrorKind::InconsistentConstraint(_, conflicts) => Some(conflicts),
+                _ => None,
+            })
+            .unwrap();
+        let mut conflicts = conflicts
+            .iter()
+            .map(|c| (c.line, c.col, c.source.as_str()))
+            .collect::<Vec<_>>();
+        conflicts.sort();
+        // Neither `r3` nor the `y0` and `h` of `r1` and `r2` are part of the conflict.
+        assert_eq!(
+            conflicts,
+            [
+                (2, 28, "0."),
+                (2, 41, "100."),
+                (3, 34, "100."),
+                (5, 3, "eq(r2.x0, r1.x1 + 50.)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn argon_if_mismatched() {
+        let o = parse_workspace_with_std(ARGON_IF_MISMATCHED);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Bool(true)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 1);
+        assert!(matches!(
+            errors.errors[0].kind,
+            StaticErrorKind::BranchesDifferentTypes(Ty::Rect, Ty::Float)
+        ));
+    }
+
+    #[test]
+    fn static_errors_as_diagnostics() {
+        let o = parse_workspace_with_std(ARGON_IF_MISMATCHED);
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Bool(true)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let errors = cells.unwrap_static_errors();
+        let diagnostic = Diagnostic::from(&errors.errors[0]);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert!(
+            diagnostic
+                .file
+                .as_ref()
+                .unwrap()
+                .ends_with("if_mismatched/lib.ar")
+        );
+        assert_eq!(diagnostic.span.as_ref().unwrap().line, 2);
+        assert!(diagnostic.to_string().contains("lib.ar:2:"));
+
+        let json = serde_json::to_value(&diagnostic).unwrap();
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["span"]["line"], 2);
+        assert_eq!(json["message"], errors.errors[0].kind.to_string());
+        assert_eq!(json["code"], "E0007");
+    }
+
+    #[test]
+    fn argon_if_underconstrained() {
+        let o = parse_workspace_with_std(ARGON_IF_UNDERCONSTRAINED);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_exec_errors();
+        assert!(
+            errors
+                .errors
+                .iter()
+                .any(|e| matches!(e.kind, ExecErrorKind::UnderconstrainedCondition))
+        );
+    }
+
     #[test]
     fn argon_via() {
         let o = parse_workspace_with_std(ARGON_VIA);
@@ -684,6 +898,7 @@ mod tests {
                 cell: &["via"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
@@ -700,6 +915,7 @@ mod tests {
                 cell: &["vias"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
@@ -730,6 +946,7 @@ mod tests {
                 cell: &["test"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cell:?}");
@@ -746,14 +963,15 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
     }
 
     #[test]
-    fn argon_nested_inst() {
-        let o = parse_workspace_with_std(ARGON_NESTED_INST);
+    fn argon_def_export() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
@@ -762,15 +980,21 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
+        let def = cells.unwrap_valid().to_def_string().unwrap();
+        assert!(def.contains("DESIGN top ;"));
+        assert!(def.contains("COMPONENTS 2 ;"));
+        assert!(def.contains("- left bot + PLACED ( 0 0 ) N ;"));
+        // `right` is rotated by 90 degrees about (300, 0), so its lower-left corner is (200, 0).
+        assert!(def.contains("- right bot + PLACED ( 200 0 ) W ;"));
+        assert!(def.contains("PINS 0 ;"));
     }
 
     #[test]
-    #[ignore = "not supported"]
-    fn argon_cell_out_of_order() {
-        let o = parse_workspace_with_std(ARGON_CELL_OUT_OF_ORDER);
+    fn argon_lvs_check() {
+        let o = parse_workspace_with_std(ARGON_LVS);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
@@ -778,35 +1002,84 @@ mod tests {
             CompileInput {
                 cell: &["top"],
                 args: Vec::new(),
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
+        let netlist = crate::lvs::parse_spice(
+            "* reference
+.SUBCKT top in out vdd gnd
+XR0 in out res
+XR1 out vdd
++ res
+XR2 vdd gnd res
+.ENDS
+",
+        )
+        .unwrap();
+        assert_eq!(netlist.len(), 1);
+        assert_eq!(netlist[0].ports, ["in", "out", "vdd", "gnd"]);
+        let connect = [crate::config::Connect {
+            top: "met2.drawing".to_string(),
+            bot: "met1.drawing".to_string(),
+            via: Some("via.drawing".to_string()),
+        }];
+        let report = cells.unwrap_valid().check_netlist(&netlist[0], &connect);
+        println!("{report}");
+        assert_eq!(report.missing_devices, [("res".to_string(), 1)]);
+        assert!(report.extra_devices.is_empty());
+        assert_eq!(report.shorts.len(), 1);
+        assert_eq!(report.shorts[0].labels, ["in", "out"]);
+        assert_eq!(report.opens.len(), 1);
+        assert_eq!(report.opens[0].label, "vdd");
+        assert_eq!(report.opens[0].locations, [(10., 110.), (290., 110.)]);
+        assert_eq!(report.missing_ports, ["gnd"]);
     }
 
     #[test]
-    fn argon_fallback_basic() {
-        let o = parse_workspace_with_std(ARGON_FALLBACK_BASIC);
+    fn argon_net_extraction() {
+        let o = parse_workspace_with_std(ARGON_LVS);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
-        let cells = compile(
+        let mut cells = compile(
             &ast,
             CompileInput {
                 cell: &["top"],
                 args: Vec::new(),
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
-        )
-        .unwrap_exec_errors()
-        .output
-        .unwrap();
-        println!("{cells:#?}");
-        assert!(!cells.cells[&cells.top].fallback_constraints_used.is_empty());
+        );
+        cells.extract_nets(&[crate::config::Connect {
+            top: "met2.drawing".to_string(),
+            bot: "met1.drawing".to_string(),
+            via: Some("via.drawing".to_string()),
+        }]);
+        let data = cells.unwrap_valid();
+        let nets = data.nets.as_ref().unwrap();
+        assert_eq!(nets.nets.len(), 4);
+        assert_eq!(nets.nets[0].name.as_deref(), Some("in"));
+        assert_eq!(nets.nets[0].shapes.len(), 5);
+        assert_eq!(nets.nets[1].name.as_deref(), Some("vdd"));
+        assert_eq!(nets.nets[2].name.as_deref(), Some("vdd"));
+        assert_eq!(nets.nets[3].name, None);
+        let shape = &nets.shapes[nets.nets[0].shapes[2]];
+        assert_eq!(nets.net_of(&shape.path), Some(0));
+
+        let warnings = nets.warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(
+            matches!(&warnings[0], crate::nets::NetWarning::Merged { net: 0, names, .. } if names == &["in", "out"])
+        );
+        assert!(matches!(
+            &warnings[1],
+            crate::nets::NetWarning::Floating { net: 3, .. }
+        ));
     }
 
     #[test]
-    fn argon_fallback_inst() {
-        let o = parse_workspace_with_std(ARGON_FALLBACK_INST);
+    fn argon_spice_netlist() {
+        let o = parse_workspace_with_std(ARGON_NETLIST);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
@@ -814,22 +1087,42 @@ mod tests {
             CompileInput {
                 cell: &["top"],
                 args: Vec::new(),
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
-        )
-        .unwrap_exec_errors()
-        .output
-        .unwrap();
-        assert!(!cells.cells[&cells.top].fallback_constraints_used.is_empty());
-        println!("{cells:#?}");
+        );
+        let root_dir = Path::new(ARGON_NETLIST).parent().unwrap();
+        let config = parse_config(root_dir.join("Argon.toml")).unwrap();
+        let data = cells.unwrap_valid();
+        let nets = data.connectivity(&config.connect);
+        assert_eq!(nets.nets.len(), 3);
+        assert_eq!(nets.pins.len(), 6);
+        let pins = |net: usize| {
+            nets.nets[net]
+                .pins
+                .iter()
+                .map(|&pin| nets.pins[pin].name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pins(0), ["y", "a"]);
+        assert_eq!(pins(1), ["in", "a"]);
+        assert_eq!(pins(2), ["out", "y"]);
+
+        let spice = data.to_spice_string(&config.connect).unwrap();
+        assert_eq!(
+            spice,
+            "* Extracted from the layout of `top`.
+.subckt top in out
+xi0 in net0 inv
+xi1 net0 out inv
+.ends top
+"
+        );
     }
 
-    /// Verifies the metadata the GUI relies on to persist solution-space-
-    /// exploration drags: the used fallback's constraint (`x1 - 100`) and the
-    /// source span pointing at the value text `100.` in `x1i=100.`.
     #[test]
-    fn argon_sse_basic_fallback_metadata() {
-        let o = parse_workspace_with_std(ARGON_SSE_BASIC);
+    fn argon_cell_ports() {
+        let o = parse_workspace_with_std(ARGON_LVS);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
@@ -837,30 +1130,28 @@ mod tests {
             CompileInput {
                 cell: &["top"],
                 args: Vec::new(),
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
-        )
-        .unwrap_exec_errors()
-        .output
-        .unwrap();
-        let used = &cells.cells[&cells.top].fallback_constraints_used;
-        // `eq(x1, y1)` leaves a single degree of freedom, pinned by exactly one
-        // fallback (the higher-priority `x1i`).
-        assert_eq!(used.len(), 1);
-        let fb = &used[0];
-        // Constraint is `x1 - 100`: a single variable with coefficient 1 and a
-        // pinned value of 100 (= -constant).
-        assert_eq!(fb.constraint.coeffs.len(), 1);
-        assert!((fb.constraint.coeffs[0].0 - 1.0).abs() < 1e-9);
-        assert!((-fb.constraint.constant - 100.0).abs() < 1e-9);
-        // The span addresses exactly the value text in the source.
-        let src = std::fs::read_to_string(&fb.span.path).unwrap();
-        assert_eq!(&src[fb.span.span.start()..fb.span.span.end()], "100.");
+        );
+        let data = cells.unwrap_valid();
+        let top = &data.cells[&data.top];
+        assert_eq!(
+            top.ports
+                .iter()
+                .map(|port| port.name.as_str())
+                .collect::<Vec<_>>(),
+            ["in", "out", "vdd", "vdd"]
+        );
+        assert_eq!(top.ports[1].layer, "met1.label");
+        assert_eq!((top.ports[1].x, top.ports[1].y), (290., 10.));
+        let res = data.cells.values().find(|cell| cell.name == "res").unwrap();
+        assert!(res.ports.is_empty());
     }
 
     #[test]
-    fn argon_bool_literal() {
-        let o = parse_workspace_with_std(ARGON_BOOL_LITERAL);
+    fn argon_guard_ring() {
+        let o = parse_workspace_with_std(ARGON_GUARD_RING);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
@@ -869,172 +1160,2082 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
         let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        let emit = cell.scopes[&cell.root]
-            .children
-            .iter()
-            .flat_map(|s| cell.scopes[s].emit.iter())
-            .collect::<Vec<_>>();
-        assert_eq!(emit.len(), 1);
-        let (obj, _) = emit.first().unwrap();
-        assert_eq!(
-            cell.objects[obj]
-                .as_ref()
-                .unwrap_rect()
-                .layer
-                .as_ref()
-                .unwrap(),
-            "met1"
-        );
+        let bbox = cells.cell_bbox(cells.top).unwrap();
+        assert_relative_eq!(bbox.x0, -150., epsilon = EPSILON);
+        assert_relative_eq!(bbox.y0, -150., epsilon = EPSILON);
+        assert_relative_eq!(bbox.x1, 1150., epsilon = EPSILON);
+        assert_relative_eq!(bbox.y1, 650., epsilon = EPSILON);
+        let count = |layer: &str| {
+            cells
+                .iter_flat(cells.top, None)
+                .filter(|rect| rect.layer == Some(layer))
+                .count()
+        };
+        assert_eq!(count("met1"), 5);
+        assert_eq!(count("met2"), 4);
+        // 16 vias along the top and bottom, and 8 along each side.
+        assert_eq!(count("via1"), 48);
     }
 
     #[test]
-    fn argon_dimensions() {
-        let o = parse_workspace_with_std(ARGON_DIMENSIONS);
+    fn argon_sky130_fet_inverter() {
+        let o = parse_workspace_with_std(ARGON_SKY130_INVERTER);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["top"],
-                args: Vec::new(),
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                cell: &["inverter"],
+                args: vec![
+                    CellArg::Float(1_200.),
+                    CellArg::Float(2_000.),
+                    CellArg::Int(4),
+                ],
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
         let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        assert_eq!(cell.objects.len(), 3);
-        let r = cell.objects.iter().find_map(|(_, v)| v.get_rect()).unwrap();
-        assert_eq!(r.layer.as_ref().unwrap(), "met1");
-        assert_relative_eq!(r.x0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.x1.0, 200., epsilon = EPSILON);
-        assert_relative_eq!(r.y1.0, 100., epsilon = EPSILON);
+        let count = |layer: &str| {
+            cells
+                .iter_flat(cells.top, None)
+                .filter(|rect| rect.layer == Some(layer))
+                .count()
+        };
+        // Four poly fingers and a gate strap per transistor.
+        assert_eq!(count("poly.drawing"), 10);
+        assert_eq!(count("nwell.drawing"), 1);
+        assert_eq!(count("mcon.drawing"), 4);
     }
 
     #[test]
-    fn argon_param_float() {
-        let o = parse_workspace_with_std(ARGON_PARAM_FLOAT);
+    fn argon_instance_params() {
+        let o = parse_workspace_with_std(ARGON_SKY130_INVERTER);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["top"],
-                args: vec![CellArg::Float(50.), CellArg::Float(20.)],
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                cell: &["inverter"],
+                args: vec![
+                    CellArg::Float(1_200.),
+                    CellArg::Float(2_000.),
+                    CellArg::Int(4),
+                ],
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
-        cells.unwrap_valid();
+        let cells = cells.unwrap_valid();
+        let insts = cells.cells[&cells.top]
+            .objects
+            .values()
+            .filter_map(|obj| obj.instance())
+            .collect::<Vec<_>>();
+        assert_eq!(insts.len(), 2);
+        assert_eq!(insts[0].cell_name, "nmos");
+        assert_eq!(insts[1].cell_name, "pmos");
+        assert_eq!(insts[1].params["w"], CellArg::Float(2_000.));
+        assert_eq!(insts[1].params["l"], CellArg::Float(150.));
+        assert_eq!(insts[1].params["fingers"], CellArg::Int(4));
+        assert_eq!(format!("{}", insts[1].params["w"]), "2000.0");
     }
 
     #[test]
-    fn argon_param_int() {
-        let o = parse_workspace_with_std(ARGON_PARAM_INT);
+    fn argon_gds_cell_names() {
+        let o = parse_workspace_with_std(ARGON_SKY130_INVERTER);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["top"],
-                args: vec![CellArg::Int(50), CellArg::Int(20)],
-                lyp_file: &PathBuf::from(BASIC_LYP),
+                cell: &["inverter"],
+                args: vec![
+                    CellArg::Float(1_200.),
+                    CellArg::Float(2_000.),
+                    CellArg::Int(4),
+                ],
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
-        cells.unwrap_valid();
+        let cells = cells.unwrap_valid();
+        let names = cells
+            .gds_cell_names(cells.top)
+            .into_values()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "inverter_nw1200_pw2000_fingers4",
+                "nmos_w1200_l150_fingers4",
+                "pmos_w2000_l150_fingers4",
+            ]
+        );
     }
 
     #[test]
-    fn argon_workspace() {
-        let o = parse_workspace_with_std(ARGON_WORKSPACE);
+    fn argon_config_targets() {
+        let root_dir = Path::new(ARGON_SKY130_INVERTER).parent().unwrap();
+        let config = parse_config(root_dir.join("Argon.toml")).unwrap();
+        assert_eq!(config.targets.len(), 1);
+        let target = &config.targets[0];
+        assert_eq!(target.name.as_deref(), Some("inverter"));
+        assert_eq!(target.cell_path(), "inverter");
+        assert_eq!(target.out.as_deref(), Some(Path::new("gds/inverter.gds")));
+
+        let o = parse_workspace_with_std(ARGON_SKY130_INVERTER);
+        assert!(o.static_errors().is_empty());
+        let (ast, _) = static_compile(&o.ast()).unwrap();
+        let output = dynamic_compile_invocation(
+            &ast,
+            &target.cell,
+            &config.lyp_path(root_dir),
+            &config.solver,
+        )
+        .unwrap();
+        output.unwrap_valid();
+    }
+
+    #[test]
+    fn argon_lint() {
+        let o = parse_workspace_with_std(ARGON_LINT);
         assert!(o.static_errors().is_empty());
         let ast = o.ast();
+        let root_dir = Path::new(ARGON_LINT).parent().unwrap();
+        let mut config = parse_config(root_dir.join("Argon.toml")).unwrap();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["test"],
+                cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:?}");
-        let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        assert_eq!(cell.objects.len(), 1);
-        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
-        assert_relative_eq!(r.x0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.x1.0, 10., epsilon = EPSILON);
-        assert_relative_eq!(r.y1.0, 15., epsilon = EPSILON);
+        let data = cells.unwrap_valid();
+
+        let mut linter = Linter::new(&config, &ast);
+        linter.check_source();
+        linter.check_output(&data);
+        let lints = linter.finish();
+        let rules = lints.iter().map(|lint| lint.rule).collect::<Vec<_>>();
+        for rule in LintRule::ALL {
+            assert_eq!(
+                rules.iter().filter(|r| **r == rule).count(),
+                1,
+                "expected one {rule} lint in {lints:#?}"
+            );
+        }
+        let magic = lints
+            .iter()
+            .find(|lint| lint.rule == LintRule::MagicNumber)
+            .unwrap();
+        assert_eq!(magic.level, LintLevel::Deny);
+        assert_eq!(magic.line, 14);
+        assert!(magic.message.contains("`WIDTH`"));
+        let unused = lints
+            .iter()
+            .find(|lint| lint.rule == LintRule::UnusedCell)
+            .unwrap();
+        assert!(unused.message.contains("`unused`"));
+        let unused_var = lints
+            .iter()
+            .find(|lint| lint.rule == LintRule::UnusedVariable)
+            .unwrap();
+        assert_eq!(unused_var.line, 15);
+        assert!(unused_var.message.contains("`flat`"));
+        let unused_variant = lints
+            .iter()
+            .find(|lint| lint.rule == LintRule::UnusedVariant)
+            .unwrap();
+        assert_eq!(unused_variant.line, 23);
+        assert!(unused_variant.message.contains("`Side::Right`"));
+        let off_grid = lints
+            .iter()
+            .find(|lint| lint.rule == LintRule::OffGrid)
+            .unwrap();
+        assert_eq!(off_grid.line, 18);
+        assert!(off_grid.message.contains("x1 = 52"));
+        let diagnostic = Diagnostic::from(unused_var);
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.code.as_deref(), Some("unused-variable"));
+        assert_eq!(diagnostic.span.as_ref().unwrap().line, 15);
+        let min_width = lints
+            .iter()
+            .find(|lint| lint.rule == LintRule::MinWidth)
+            .unwrap();
+        assert_eq!(min_width.line, 14);
+        assert!(
+            min_width
+                .message
+                .contains("rect `wide` on `met1` is 20 tall")
+        );
+        assert!(min_width.message.contains("`300.` at 14:37"));
+        assert!(
+            min_width
+                .message
+                .contains("`eq(wide.y1, wide.y0 + 20.)` at 17:3")
+        );
+
+        config.lint.insert(LintRule::MagicNumber, LintLevel::Allow);
+        let mut linter = Linter::new(&config, &ast);
+        linter.check_source();
+        assert!(
+            linter
+                .finish()
+                .iter()
+                .all(|lint| lint.rule != LintRule::MagicNumber)
+        );
+    }
+
+    #[test]
+    fn argon_layer_usage() {
+        let o = parse_workspace_with_std(ARGON_SKY130_INVERTER);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let root_dir = Path::new(ARGON_SKY130_INVERTER).parent().unwrap();
+        let config = parse_config(root_dir.join("Argon.toml")).unwrap();
+        let usage = LayerUsage::from_workspace(root_dir, &config, &ast).unwrap();
+        let layers = usage.by_layer();
+        let in_inverter = |layer: &str| {
+            layers[layer]
+                .iter()
+                .filter(|r| {
+                    r.kind == LayerRefKind::Source
+                        && r.span.path.ends_with("sky130_inverter/lib.ar")
+                })
+                .count()
+        };
+        assert_eq!(in_inverter("met1.drawing"), 2);
+        assert_eq!(in_inverter("mcon.drawing"), 4);
+        assert_eq!(in_inverter("li1.label"), 2);
+        assert!(
+            layers["poly.drawing"]
+                .iter()
+                .any(|r| r.kind == LayerRefKind::Definition)
+        );
+
+        let r = layers["met1.drawing"]
+            .iter()
+            .find(|r| r.kind == LayerRefKind::Source)
+            .unwrap();
+        let text = std::fs::read_to_string(&r.span.path).unwrap();
+        assert_eq!(
+            &text[r.span.span.start()..r.span.span.end()],
+            "met1.drawing"
+        );
+        assert!(usage.check_rename("met1.drawing", "metal1").is_ok());
+        assert!(usage.check_rename("met1.drawing", "li1.drawing").is_err());
+        assert!(usage.check_rename("met1.drawing", "met\"1").is_err());
+    }
+
+    #[test]
+    fn argon_density_fill() {
+        let o = parse_workspace_with_std(ARGON_DENSITY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let mut cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let mut rule = crate::config::DensityRule {
+            layer: "met1".to_string(),
+            window: 500.,
+            step: Some(500.),
+            min: Some(0.2),
+            max: Some(0.5),
+            fill: None,
+        };
+        cells.check_density(std::slice::from_ref(&rule));
+        let data = cells.get_valid().unwrap();
+        assert_eq!(data.density_violations.len(), 4);
+        assert_relative_eq!(data.density_violations[0].density, 0.04, epsilon = EPSILON);
+        assert!(data.fill.is_empty());
+
+        rule.fill = Some(crate::config::FillRule {
+            layer: "met1".to_string(),
+            size: 50.,
+            spacing: 50.,
+        });
+        cells.check_density(&[rule]);
+        let data = cells.unwrap_valid();
+        assert!(data.density_violations.is_empty());
+        assert!(!data.fill.is_empty());
+        // Fill keeps its spacing from the drawn shapes.
+        for fill in &data.fill {
+            assert!(fill.bbox.x0 >= 150. || fill.bbox.y0 >= 150.);
+            assert!(fill.bbox.x1 <= 850. || fill.bbox.y1 <= 850.);
+        }
+    }
+
+    #[test]
+    fn argon_drc() {
+        let o = parse_workspace_with_std(ARGON_DRC);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let mut cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let root_dir = Path::new(ARGON_DRC).parent().unwrap();
+        let config = parse_config(root_dir.join("Argon.toml")).unwrap();
+        cells.check_drc(&config.drc_rules(root_dir).unwrap());
+        let data = cells.unwrap_valid();
+        assert_eq!(data.drc_violations.len(), 3);
+        assert!(data.drc_violations.iter().all(|v| v.span.is_some()));
+
+        let width = &data.drc_violations[0];
+        assert!(matches!(
+            width.kind,
+            crate::drc::DrcViolationKind::Width { .. }
+        ));
+        assert_relative_eq!(width.actual.unwrap(), 50., epsilon = EPSILON);
+
+        let spacing = &data.drc_violations[1];
+        assert!(matches!(
+            spacing.kind,
+            crate::drc::DrcViolationKind::Spacing { .. }
+        ));
+        assert_relative_eq!(spacing.actual.unwrap(), 60., epsilon = EPSILON);
+        assert_relative_eq!(spacing.bbox.x0, 50., epsilon = EPSILON);
+        assert_relative_eq!(spacing.bbox.x1, 110., epsilon = EPSILON);
+        assert_relative_eq!(spacing.bbox.y1, 400., epsilon = EPSILON);
+
+        let enclosure = &data.drc_violations[2];
+        assert!(matches!(
+            enclosure.kind,
+            crate::drc::DrcViolationKind::Enclosure { .. }
+        ));
+        assert_relative_eq!(enclosure.bbox.x0, 130., epsilon = EPSILON);
+        assert_relative_eq!(enclosure.bbox.y0, 100., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_memory_workspace() {
+        let mut files = MemoryFiles::new();
+        files.insert(
+            "/play/lib.ar",
+            "mod utils;\n\ncell top() {\n    rect(\"met1\", x0=0., y0=0., x1=utils::width(), y1=20.);\n}\n",
+        );
+        files.insert("/play/utils.ar", "fn width() -> Float {\n    15.\n}\n");
+        let o = parse_workspace_with_std_in("/play/lib.ar", &files);
+        assert!(o.static_errors().is_empty());
+        // The standard library is served from memory too.
+        assert!(o.asts[&vec!["std".to_string()]].1.is_none());
+        let ast = o.ast();
+        let (ast, static_output) = static_compile(&ast).unwrap();
+        assert!(static_output.errors.is_empty());
+        let layers =
+            LayerProperties::from_lyp_str(&std::fs::read_to_string(BASIC_LYP).unwrap()).unwrap();
+        let cells = dynamic_compile_with_layers(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: Path::new("/nonexistent.lyp"),
+                solver: Default::default(),
+            },
+            layers,
+        );
+        let cells = cells.unwrap_valid();
+        let rect = cells.cells[&cells.top]
+            .objects
+            .values()
+            .find_map(|obj| obj.get_rect())
+            .unwrap();
+        assert_relative_eq!(rect.x1.0, 15., epsilon = EPSILON);
+        assert_relative_eq!(rect.y1.0, 20., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_scope_bboxes() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let cells = cells.unwrap_valid();
+        let top = cells.cell_bbox(cells.top).unwrap();
+        // `left` spans (0, 0) to (200, 100); `right` is rotated by 90 degrees
+        // about (300, 0), spanning (200, 0) to (300, 200).
+        assert_relative_eq!(top.x0, 0., epsilon = EPSILON);
+        assert_relative_eq!(top.y0, 0., epsilon = EPSILON);
+        assert_relative_eq!(top.x1, 300., epsilon = EPSILON);
+        assert_relative_eq!(top.y1, 200., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_iter_flat() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let cells = cells.unwrap_valid();
+
+        let top_only = cells.iter_flat(cells.top, Some(0)).collect::<Vec<_>>();
+        assert_eq!(top_only.len(), 1);
+        assert_eq!(top_only[0].layer, Some("met3"));
+        assert_eq!(top_only[0].path.len(), 1);
+
+        let flat = cells.iter_flat(cells.top, None).collect::<Vec<_>>();
+        assert_eq!(flat.len(), 5);
+        assert_eq!(flat.iter().filter(|r| r.depth == 1).count(), 4);
+        // `right.met2` is rotated by 90 degrees about (300, 0).
+        let right_met2 = flat
+            .iter()
+            .filter(|r| r.depth == 1)
+            .max_by(|a, b| a.rect.y1.total_cmp(&b.rect.y1))
+            .unwrap();
+        assert_eq!(right_met2.path.len(), 2);
+        assert_relative_eq!(right_met2.rect.x0, 200., epsilon = EPSILON);
+        assert_relative_eq!(right_met2.rect.y0, 100., epsilon = EPSILON);
+        assert_relative_eq!(right_met2.rect.x1, 300., epsilon = EPSILON);
+        assert_relative_eq!(right_met2.rect.y1, 200., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_flatten() {
+        let o = parse_workspace_with_std(ARGON_NESTED_INST);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let mut output = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let cells = output.clone().unwrap_valid();
+        let flat_rects = |cells: &crate::compile::CompiledData| {
+            let mut rects = cells
+                .iter_flat(cells.top, None)
+                .map(|r| (r.layer.map(str::to_string), r.rect.bbox()))
+                .collect::<Vec<_>>();
+            rects.sort_by(|(la, a), (lb, b)| {
+                (a.x0.total_cmp(&b.x0))
+                    .then(a.y0.total_cmp(&b.y0))
+                    .then(la.cmp(lb))
+            });
+            rects
+        };
+        let expected = flat_rects(&cells);
+
+        // Expanding `left` and `right` places their `bot` instances in the top
+        // cell, with `right`'s rotation applied.
+        let mut partial = cells.clone();
+        partial.flatten(Some(1));
+        let top = &partial.cells[&partial.top];
+        let insts = top
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_instance())
+            .collect::<Vec<_>>();
+        assert_eq!(insts.len(), 2);
+        assert!(
+            insts
+                .iter()
+                .all(|inst| partial.cells[&inst.cell].name == "bot")
+        );
+        assert!(insts.iter().any(|inst| inst.angle == 90.));
+        assert!(!top.fields.contains_key("left"));
+        assert_eq!(flat_rects(&partial).len(), expected.len());
+
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_flatten/layout.gds");
+        output.flatten(None);
+        output
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+        let flat = output.unwrap_valid();
+        let top = &flat.cells[&flat.top];
+        assert!(top.objects.values().all(|obj| !obj.is_instance()));
+        let actual = flat_rects(&flat);
+        assert_eq!(actual.len(), expected.len());
+        for ((layer, a), (expected_layer, b)) in actual.iter().zip(&expected) {
+            assert_eq!(layer, expected_layer);
+            assert_relative_eq!(a.x0, b.x0, epsilon = EPSILON);
+            assert_relative_eq!(a.y0, b.y0, epsilon = EPSILON);
+            assert_relative_eq!(a.x1, b.x1, epsilon = EPSILON);
+            assert_relative_eq!(a.y1, b.y1, epsilon = EPSILON);
+        }
+
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        assert_eq!(lib.structs.len(), 1);
+        let mismatches = flat.verify_gds(&lib, &map).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+    }
+
+    #[test]
+    fn argon_stable_ids_survive_edits() {
+        let work_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_stable_ids");
+        std::fs::create_dir_all(&work_dir).unwrap();
+        let lib = work_dir.join("lib.ar");
+        let compile_src = |src: &str| {
+            std::fs::write(&lib, src).unwrap();
+            let o = parse_workspace_with_std(&lib);
+            assert!(o.static_errors().is_empty());
+            let ast = o.ast();
+            compile(
+                &ast,
+                CompileInput {
+                    cell: &["top"],
+                    args: Vec::new(),
+                    lyp_file: &PathBuf::from(BASIC_LYP),
+                    solver: Default::default(),
+                },
+            )
+            .unwrap_valid()
+        };
+        let find = |cells: &crate::compile::CompiledData, x0: f64| {
+            let cell = &cells.cells[&cells.top];
+            cell.objects
+                .iter()
+                .find_map(|(id, obj)| {
+                    let rect = obj.get_rect()?;
+                    (rect.x0.0 == x0).then(|| (cell.stable_ids[id], rect.span.clone().unwrap()))
+                })
+                .unwrap()
+        };
+
+        let before = compile_src(
+            "cell top() {\n    let a = rect(\"met1\", x0=0., y0=0., x1=10., y1=10.);\n    rect(\"met2\", x0=20., y0=0., x1=30., y1=10.);\n}\n",
+        );
+        let after = compile_src(
+            "// comment\ncell top() {\n    let b = rect(\"met1\", x0=-10., y0=0., x1=0., y1=10.);\n    let a = rect(\"met1\", x0=0., y0=0., x1=10., y1=10.);\n    rect(\"met2\", x0=20., y0=0., x1=30., y1=10.);\n}\n",
+        );
+
+        for x0 in [0., 20.] {
+            let (id_before, span_before) = find(&before, x0);
+            let (id_after, span_after) = find(&after, x0);
+            assert_eq!(id_before, id_after);
+            assert_ne!(span_before, span_after);
+            assert_eq!(before.stable_id(&span_before), Some(id_before));
+            assert_eq!(after.span_of(id_before), Some(&span_after));
+        }
+        assert_ne!(find(&after, -10.).0, find(&after, 0.).0);
+    }
+
+    #[test]
+    fn argon_nested_inst() {
+        let o = parse_workspace_with_std(ARGON_NESTED_INST);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+    }
+
+    #[test]
+    #[ignore = "not supported"]
+    fn argon_cell_out_of_order() {
+        let o = parse_workspace_with_std(ARGON_CELL_OUT_OF_ORDER);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+    }
+
+    #[test]
+    fn argon_fallback_basic() {
+        let o = parse_workspace_with_std(ARGON_FALLBACK_BASIC);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        )
+        .unwrap_exec_errors()
+        .output
+        .unwrap();
+        println!("{cells:#?}");
+        assert!(!cells.cells[&cells.top].fallback_constraints_used.is_empty());
+    }
+
+    #[test]
+    fn constraint_graph_marks_fallbacks() {
+        let o = parse_workspace_with_std(ARGON_FALLBACK_BASIC);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        )
+        .unwrap_exec_errors()
+        .output
+        .unwrap();
+        let graph = cells.constraint_graph(cells.cells[&cells.top].root);
+        assert_eq!(
+            graph
+                .vars
+                .values()
+                .filter_map(|var| var.label.as_deref())
+                .collect::<Vec<_>>(),
+            ["met1.x0", "met1.y0", "met1.x1", "met1.y1"]
+        );
+        assert!(
+            graph
+                .constraints
+                .values()
+                .any(|constraint| constraint.status == NodeStatus::Fallback)
+        );
+        let dot = graph.to_dot(&ast);
+        assert!(dot.starts_with("graph constraints {"));
+        assert!(dot.contains("label=\"met1.y1\""));
+        assert!(dot.contains("style=dashed"));
+        assert!(cells.scope_named("missing").is_err());
+    }
+
+    #[test]
+    fn argon_provenance() {
+        let o = parse_workspace_with_std(ARGON_PROVENANCE);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Float(430.)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        )
+        .unwrap_valid();
+        let r2 = cells.cells[&cells.top]
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_rect())
+            .find(|rect| rect.x0.0 > 430.)
+            .unwrap();
+        let provenance = cells
+            .provenance(&ast, r2.span.as_ref().unwrap(), "x0")
+            .unwrap();
+        assert_relative_eq!(provenance.value, 580., epsilon = EPSILON);
+        assert_eq!(
+            provenance.to_string(),
+            "r2.x0 = r1.x1 + 50 (`eq(r2.x0, r1.x1 + 50.)` at lib.ar:4) \
+             = r1.x0 + 100 (`100.` at lib.ar:2) = 430 (param `pitch` at lib.ar:2)"
+        );
+        assert_eq!(provenance.steps[0].span.span.start(), 133);
+        assert!(
+            cells
+                .provenance(&ast, r2.span.as_ref().unwrap(), "z0")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn argon_rect_coords() {
+        let o = parse_workspace_with_std(ARGON_PROVENANCE);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Float(430.)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        )
+        .unwrap_valid();
+        let r2 = cells.cells[&cells.top]
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_rect())
+            .find(|rect| rect.x0.0 > 430.)
+            .unwrap();
+        let names = cells.coord_names(cells.top);
+        let (name, coords) = names.rect(r2);
+        assert_eq!(name, "r2");
+        for ((coord, value), (expected_coord, expected)) in
+            coords
+                .iter()
+                .zip([("x0", 580.), ("y0", 0.), ("x1", 680.), ("y1", 100.)])
+        {
+            assert_eq!(*coord, expected_coord);
+            let SolvedCoord::Solved(value) = value else {
+                panic!("{coord} is unsolved");
+            };
+            assert_relative_eq!(*value, expected, epsilon = EPSILON);
+        }
+        // `eq(r2.x0, r1.x1 + 50.)`
+        let eq = cells.cells[&cells.top]
+            .constraints
+            .keys()
+            .map(|id| names.constraint(*id))
+            .find(|coords| {
+                ["r2.x0", "r1.x1"]
+                    .iter()
+                    .all(|name| coords.iter().any(|(coord, _)| coord == name))
+            })
+            .unwrap();
+        assert_eq!(eq.len(), 2);
+        for (coord, value) in &eq {
+            let expected = match coord.as_str() {
+                "r2.x0" => 580.,
+                "r1.x1" => 530.,
+                _ => panic!("unexpected coordinate {coord}"),
+            };
+            let SolvedCoord::Solved(value) = value else {
+                panic!("{coord} is unsolved");
+            };
+            assert_relative_eq!(*value, expected, epsilon = EPSILON);
+        }
+        assert_eq!(
+            SolvedCoord::Unsolved(Some("r1.x1 + 50".to_string())).to_string(),
+            "r1.x1 + 50 (unsolved)"
+        );
+        assert_eq!(SolvedCoord::Unsolved(None).to_string(), "unsolved");
+    }
+
+    #[test]
+    fn argon_fallback_inst() {
+        let o = parse_workspace_with_std(ARGON_FALLBACK_INST);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        )
+        .unwrap_exec_errors()
+        .output
+        .unwrap();
+        assert!(!cells.cells[&cells.top].fallback_constraints_used.is_empty());
+        println!("{cells:#?}");
+    }
+
+    /// Verifies the metadata the GUI relies on to persist solution-space-
+    /// exploration drags: the used fallback's constraint (`x1 - 100`) and the
+    /// source span pointing at the value text `100.` in `x1i=100.`.
+    #[test]
+    fn argon_sse_basic_fallback_metadata() {
+        let o = parse_workspace_with_std(ARGON_SSE_BASIC);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        )
+        .unwrap_exec_errors()
+        .output
+        .unwrap();
+        let used = &cells.cells[&cells.top].fallback_constraints_used;
+        // `eq(x1, y1)` leaves a single degree of freedom, pinned by exactly one
+        // fallback (the higher-priority `x1i`).
+        assert_eq!(used.len(), 1);
+        let fb = &used[0];
+        // Constraint is `x1 - 100`: a single variable with coefficient 1 and a
+        // pinned value of 100 (= -constant).
+        assert_eq!(fb.constraint.coeffs.len(), 1);
+        assert!((fb.constraint.coeffs[0].0 - 1.0).abs() < 1e-9);
+        assert!((-fb.constraint.constant - 100.0).abs() < 1e-9);
+        // The span addresses exactly the value text in the source.
+        let src = std::fs::read_to_string(&fb.span.path).unwrap();
+        assert_eq!(&src[fb.span.span.start()..fb.span.span.end()], "100.");
+    }
+
+    #[test]
+    fn argon_bool_literal() {
+        let o = parse_workspace_with_std(ARGON_BOOL_LITERAL);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let emit = cell.scopes[&cell.root]
+            .children
+            .iter()
+            .flat_map(|s| cell.scopes[s].emit.iter())
+            .collect::<Vec<_>>();
+        assert_eq!(emit.len(), 1);
+        let (obj, _) = emit.first().unwrap();
+        assert_eq!(
+            cell.objects[obj]
+                .as_ref()
+                .unwrap_rect()
+                .layer
+                .as_ref()
+                .unwrap(),
+            "met1"
+        );
+    }
+
+    #[test]
+    fn argon_dimensions() {
+        let o = parse_workspace_with_std(ARGON_DIMENSIONS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 3);
+        let r = cell.objects.iter().find_map(|(_, v)| v.get_rect()).unwrap();
+        assert_eq!(r.layer.as_ref().unwrap(), "met1");
+        assert_relative_eq!(r.x0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.x1.0, 200., epsilon = EPSILON);
+        assert_relative_eq!(r.y1.0, 100., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_param_float() {
+        let o = parse_workspace_with_std(ARGON_PARAM_FLOAT);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Float(50.), CellArg::Float(20.)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        cells.unwrap_valid();
+    }
+
+    #[test]
+    fn argon_param_int() {
+        let o = parse_workspace_with_std(ARGON_PARAM_INT);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Int(50), CellArg::Int(20)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        cells.unwrap_valid();
+    }
+
+    #[test]
+    fn param_overrides_replace_named_args() {
+        let o = parse_workspace_with_std(ARGON_PARAM_INT);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let overrides = |kv: &[(&str, &str)]| {
+            kv.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<IndexMap<_, _>>()
+        };
+
+        assert_eq!(invocation_name("top(50, 20)").unwrap(), "top");
+        assert_eq!(
+            override_invocation(&ast, "top(50, 20)", &overrides(&[])).unwrap(),
+            "top(50, 20)"
+        );
+        assert_eq!(
+            override_invocation(&ast, "top(50, 20)", &overrides(&[("y", "35")])).unwrap(),
+            "top(50, 35)"
+        );
+        assert!(override_invocation(&ast, "top(50, 20)", &overrides(&[("z", "1")])).is_err());
+        assert!(override_invocation(&ast, "top(50, 20)", &overrides(&[("x", "1.")])).is_err());
+        assert!(override_invocation(&ast, "top(50, 20)", &overrides(&[("x", "a + 1")])).is_err());
+    }
+
+    #[test]
+    fn params_complete_invocations() {
+        let o = parse_workspace_with_std(ARGON_PARAM_INT);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let params = |kv: &[(&str, &str)]| {
+            kv.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<IndexMap<_, _>>()
+        };
+
+        assert_eq!(
+            invocation_with_params(&ast, "top", &params(&[("x", "50"), ("y", "20")])).unwrap(),
+            "top(50, 20)"
+        );
+        assert_eq!(
+            invocation_with_params(&ast, "top(50)", &params(&[("y", "35")])).unwrap(),
+            "top(50, 35)"
+        );
+        assert!(invocation_with_params(&ast, "top(50, 20)", &params(&[("y", "35")])).is_err());
+        assert!(invocation_with_params(&ast, "top", &params(&[("x", "50")])).is_err());
+    }
+
+    #[test]
+    fn invocations_bind_named_and_enum_args() {
+        let o = parse_workspace_with_std(ARGON_CELL_ARGS);
+        assert!(o.static_errors().is_empty());
+        let parse_ast = o.ast();
+        let (ast, _) = static_compile(&parse_ast).unwrap();
+
+        let invocation =
+            Invocation::resolve(&ast, "top(100, flavor=Flavor::Hvt, wide=true, nf=-2)").unwrap();
+        assert_eq!(invocation.to_string(), "top(100.0, -2, Flavor::Hvt, true)");
+
+        let err = |cell| format!("{:#}", Invocation::resolve(&ast, cell).unwrap_err());
+        assert!(err("bot()").contains("available cells: top"));
+        assert!(
+            err("top(1., 2)").contains("usage: top(w: Float, nf: Int, flavor: Flavor, wide: Bool)")
+        );
+        assert!(err("top(1., 2, 3, true, false)").contains("takes 4 argument(s) but 5 were given"));
+        assert!(err("top(1., 2., Lvt, true)").contains("expected an int literal, found `2.`"));
+        assert!(err("top(1., 2, Svt, true)").contains("variants: Flavor::Lvt, Flavor::Hvt"));
+        assert!(err("top(1., 2, Lvt, 1)").contains("expected `true` or `false`"));
+        assert!(err("top(1., 2, Lvt, true, nf=3)").contains("passed more than once"));
+        assert!(err("top(1., 2, Lvt, yes=true)").contains("has no parameter `yes`"));
+
+        let cells = dynamic_compile_invocation(
+            &ast,
+            "top(100., 2, Lvt, wide=true)",
+            &PathBuf::from(BASIC_LYP),
+            &Default::default(),
+        )
+        .unwrap()
+        .unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.params["flavor"].to_string(), "Flavor::Lvt");
+        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
+        assert_relative_eq!(r.x1.0, 200., epsilon = EPSILON);
+        assert_relative_eq!(r.y1.0, 200., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_param_sweep() {
+        let o = parse_workspace_with_std(ARGON_PARAM_INT);
+        assert!(o.static_errors().is_empty());
+        let parse_ast = o.ast();
+        let (ast, _) = static_compile(&parse_ast).unwrap();
+
+        let values = sweep_values(100., 101., 5);
+        assert_eq!(values, vec![100., 100.25, 100.5, 100.75, 101.]);
+        let invocations = sweep_invocations("top(50, 20)", 0, &values).unwrap();
+        assert_eq!(
+            invocations,
+            vec![
+                "top(100, 20)",
+                "top(100, 20)",
+                "top(101, 20)",
+                "top(101, 20)",
+                "top(101, 20)"
+            ]
+        );
+        assert!(sweep_invocations("top(50, 20)", 2, &values).is_err());
+
+        let mut cache = SweepCache::default();
+        cache.invalidate(&parse_ast);
+        let frames = cache
+            .compile(
+                &ast,
+                &invocations,
+                &values,
+                &PathBuf::from(BASIC_LYP),
+                &Default::default(),
+                |_| {},
+            )
+            .unwrap();
+        // Steps that round to the same invocation are only compiled once.
+        assert_eq!(cache.len(), 2);
+        for frame in &frames {
+            assert!(frame.is_valid());
+            let data = frame.output.clone().unwrap_valid();
+            let top = &data.cells[&data.top];
+            let bot = top.fields["bot"].clone().into_elem().unwrap();
+            let inst = top.objects[&bot].get_instance().unwrap();
+            assert_relative_eq!(inst.x, frame.value.round(), epsilon = EPSILON);
+        }
+
+        // Unchanged sources keep the cache; changed sources clear it.
+        cache.invalidate(&parse_ast);
+        assert_eq!(cache.len(), 2);
+        let mut edited = parse_ast.clone();
+        for ast in edited.values_mut() {
+            ast.text = arcstr::format!("{}\n", ast.text);
+        }
+        cache.invalidate(&edited);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn argon_workspace() {
+        let o = parse_workspace_with_std(ARGON_WORKSPACE);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["test"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 1);
+        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
+        assert_relative_eq!(r.x0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.x1.0, 10., epsilon = EPSILON);
+        assert_relative_eq!(r.y1.0, 15., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_workspace_symbols() {
+        let ast = parse_workspace_with_std(ARGON_WORKSPACE).ast();
+        let symbols = SymbolTable::from_workspace(&ast);
+        let root = &ast[&vec![]];
+        let at = |needle: &str, skip: usize| {
+            let offset = root.text.find(needle).unwrap() + skip;
+            symbols.symbol(symbols.find(&root.path, offset).unwrap())
+        };
+
+        let nested = at("nested::test()", "nested::".len());
+        assert_eq!(nested.kind, SymbolKind::Fn);
+        assert!(nested.span.path.ends_with("nested/nested.ar"));
+
+        let id = symbols
+            .find(
+                &root.path,
+                root.text.find("utils::test()").unwrap() + "utils::".len(),
+            )
+            .unwrap();
+        let utils = symbols.symbol(id);
+        assert_eq!(utils.kind, SymbolKind::Fn);
+        assert!(utils.span.path.ends_with("utils.ar"));
+        assert_eq!(symbols.references(id).count(), 1);
+
+        // Modules are defined by their files.
+        let module = at("mod utils", "mod ".len());
+        assert_eq!(module.kind, SymbolKind::Mod);
+        assert!(module.span.path.ends_with("utils.ar"));
+        assert_eq!(module.span.span.start(), 0);
+    }
+
+    #[test]
+    fn argon_enumerations_symbols() {
+        let ast = parse_workspace_with_std(ARGON_ENUMERATIONS).ast();
+        let symbols = SymbolTable::from_workspace(&ast);
+        let root = &ast[&vec![]];
+        let at = |needle: &str| {
+            symbols
+                .find(&root.path, root.text.find(needle).unwrap())
+                .unwrap()
+        };
+
+        let variant = at("Variant1 =>");
+        let symbol = symbols.symbol(variant);
+        assert_eq!(symbol.kind, SymbolKind::Variant);
+        assert_eq!(
+            &root.text[symbol.span.span.start()..symbol.span.span.end()],
+            "Variant1"
+        );
+        assert_eq!(symbols.references(variant).count(), 2);
+
+        let binding = at("variant ==");
+        assert_eq!(symbols.symbol(binding).kind, SymbolKind::Let);
+        assert_eq!(symbols.references(binding).count(), 2);
+
+        // `x0=` names a keyword argument rather than the binding.
+        let x0 = at("x0, x0_value");
+        assert_eq!(symbols.symbol(x0).kind, SymbolKind::Let);
+        assert_eq!(symbols.references(x0).count(), 3);
+    }
+
+    #[test]
+    fn argon_external_mods() {
+        let o = parse_workspace_with_std(ARGON_EXTERNAL_MODS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["test"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 1);
+        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
+        assert_relative_eq!(r.x0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.x1.0, 10., epsilon = EPSILON);
+        assert_relative_eq!(r.y1.0, 20., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_sky130_inverter() {
+        let o = parse_workspace_with_std(ARGON_SKY130_LIB);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["inv"],
+                args: vec![
+                    CellArg::Float(1_200.),
+                    CellArg::Float(2_000.),
+                    CellArg::Int(4),
+                ],
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("cells: {cells:?}");
+
+        assert!(cells.is_valid());
+
+        let work_dir =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_sky130_inverter");
+        cells
+            .to_gds(
+                GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map"),
+                GdsUnits::new(1e-3, 1e-9),
+                work_dir.join("layout.gds"),
+            )
+            .expect("Failed to write to GDS");
+    }
+
+    #[test]
+    fn argon_verify_gds() {
+        let o = parse_workspace_with_std(ARGON_SKY130_LIB);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["inv"],
+                args: vec![
+                    CellArg::Float(1_200.),
+                    CellArg::Float(2_000.),
+                    CellArg::Int(4),
+                ],
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map");
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_verify_gds/layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+        let cells = cells.unwrap_valid();
+
+        let mut lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let mismatches = cells.verify_gds(&lib, &map).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        let top = &cells.gds_cell_names(cells.top)[&cells.top];
+        let elems = &mut lib
+            .structs
+            .iter_mut()
+            .find(|s| s.name == *top)
+            .unwrap()
+            .elems;
+        let i = elems
+            .iter()
+            .position(|elem| matches!(elem, GdsElement::GdsBoundary(_)))
+            .unwrap();
+        elems.remove(i);
+        let mismatches = cells.verify_gds(&lib, &map).unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].missing);
+    }
+
+    #[test]
+    fn argon_math() {
+        let o = parse_workspace_with_std(ARGON_MATH);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let rect = |layer: &str| {
+            cell.objects
+                .values()
+                .filter_map(|obj| obj.get_rect())
+                .find(|r| r.layer.as_deref() == Some(layer))
+                .unwrap()
+        };
+        let met2 = rect("met2");
+        assert_relative_eq!(met2.x1.0, 140., epsilon = EPSILON);
+        assert_relative_eq!(met2.y1.0, 250., epsilon = EPSILON);
+        let met3 = rect("met3");
+        assert_relative_eq!(met3.x0.0, 16., epsilon = EPSILON);
+        assert_relative_eq!(met3.y0.0, 17., epsilon = EPSILON);
+        assert_relative_eq!(met3.x1.0, 27., epsilon = EPSILON);
+        let met4 = rect("met4");
+        assert_relative_eq!(met4.x1.0, 30., epsilon = EPSILON);
+        assert_relative_eq!(met4.y1.0, 10., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_alignment() {
+        let o = parse_workspace_with_std(ARGON_ALIGNMENT);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let rect = |layer: &str| {
+            cell.objects
+                .values()
+                .filter_map(|obj| obj.get_rect())
+                .find(|r| r.layer.as_deref() == Some(layer))
+                .unwrap()
+        };
+        let (b, c) = (rect("met2"), rect("met3"));
+        assert_relative_eq!(b.x0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(b.y0.0, 50., epsilon = EPSILON);
+        assert_relative_eq!(c.x0.0, 40., epsilon = EPSILON);
+        assert_relative_eq!(c.y0.0, 80., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_distribute_any() {
+        let o = parse_workspace_with_std(ARGON_DISTRIBUTE_ANY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_exec_errors();
+        assert_eq!(errors.errors.len(), 1);
+        assert!(matches!(errors.errors[0].kind, ExecErrorKind::InvalidType));
+    }
+
+    #[test]
+    fn argon_inst_array() {
+        let o = parse_workspace_with_std(ARGON_INST_ARRAY);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_inst_array/layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let array = cell
+            .objects
+            .values()
+            .find_map(|obj| obj.get_instance_array())
+            .unwrap();
+        assert_eq!((array.array.nx, array.array.ny), (4, 3));
+        assert_relative_eq!(array.array.dx, 20., epsilon = EPSILON);
+        assert_relative_eq!(array.array.dy, 20., epsilon = EPSILON);
+        assert!(cell.objects.values().all(|obj| !obj.is_instance()));
+        let met1 = cell
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_rect())
+            .find(|r| r.layer.as_deref() == Some("met1"))
+            .unwrap();
+        assert_relative_eq!(met1.x1.0, 80., epsilon = EPSILON);
+        assert_relative_eq!(met1.y1.0, 60., epsilon = EPSILON);
+        let vias = cells
+            .iter_flat(cells.top, None)
+            .filter(|r| r.layer == Some("via1"))
+            .count();
+        assert_eq!(vias, 12);
+
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let aref = lib
+            .structs
+            .iter()
+            .flat_map(|s| &s.elems)
+            .find_map(|elem| match elem {
+                GdsElement::GdsArrayRef(aref) => Some(aref),
+                _ => None,
+            })
+            .expect("array not exported");
+        assert_eq!((aref.cols, aref.rows), (4, 3));
+        assert_eq!((aref.xy[1].x, aref.xy[1].y), (85, 5));
+        assert_eq!((aref.xy[2].x, aref.xy[2].y), (5, 65));
+        let mismatches = cells.verify_gds(&lib, &map).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+    }
+
+    #[test]
+    fn argon_enumerations() {
+        let o = parse_workspace_with_std(ARGON_ENUMERATIONS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 1);
+        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
+        assert_eq!(r.layer.as_deref(), Some("met2"));
+        assert_relative_eq!(r.x0.0, 100., epsilon = EPSILON);
+        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
+        assert_relative_eq!(r.x1.0, 300., epsilon = EPSILON);
+        assert_relative_eq!(r.y1.0, 400., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_enum_data() {
+        let o = parse_workspace_with_std(ARGON_ENUM_DATA);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 3);
+        for (layer, x0, w) in [
+            ("met1", 1000., 140.),
+            ("met2", 2000., 200.),
+            ("met3", 5000., 300.),
+        ] {
+            let r = cell
+                .objects
+                .values()
+                .find_map(|v| v.get_rect().filter(|r| r.layer.as_deref() == Some(layer)))
+                .unwrap();
+            assert_relative_eq!(r.x0.0, x0, epsilon = EPSILON);
+            assert_relative_eq!(r.x1.0, x0 + w, epsilon = EPSILON);
+            assert_relative_eq!(r.y1.0, w, epsilon = EPSILON);
+        }
+    }
+
+    #[test]
+    fn argon_match_wildcard() {
+        let o = parse_workspace_with_std(ARGON_MATCH_WILDCARD);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 3);
+        for (layer, x0, w) in [
+            ("met1", 0., 150.),
+            ("met2", 1000., 200.),
+            ("met3", 2000., 200.),
+        ] {
+            let r = cell
+                .objects
+                .values()
+                .find_map(|v| v.get_rect().filter(|r| r.layer.as_deref() == Some(layer)))
+                .unwrap();
+            assert_relative_eq!(r.x0.0, x0, epsilon = EPSILON);
+            assert_relative_eq!(r.x1.0, x0 + w, epsilon = EPSILON);
+        }
+    }
+
+    #[test]
+    fn argon_match_unreachable() {
+        let o = parse_workspace_with_std(ARGON_MATCH_UNREACHABLE);
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 2);
+        assert!(matches!(
+            errors.errors[0].kind,
+            StaticErrorKind::UnreachableMatchArm
+        ));
+        assert!(matches!(
+            errors.errors[1].kind,
+            StaticErrorKind::DuplicateMatchArm
+        ));
+    }
+
+    #[test]
+    fn argon_construction_scope() {
+        let o = parse_workspace_with_std(ARGON_CONSTRUCTION_SCOPE);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let rects = cell
+            .objects
+            .values()
+            .filter_map(|v| v.get_rect())
+            .collect::<Vec<_>>();
+        assert_eq!(rects.len(), 4);
+        let emitted = rects.iter().filter(|r| !r.construction).collect::<Vec<_>>();
+        assert_eq!(emitted.len(), 2);
+        for r in emitted {
+            assert_relative_eq!(r.x0.0, 50., epsilon = EPSILON);
+            assert_relative_eq!(r.y0.0, 50., epsilon = EPSILON);
+            assert_relative_eq!(r.x1.0, 350., epsilon = EPSILON);
+            assert_relative_eq!(r.y1.0, 250., epsilon = EPSILON);
+        }
+    }
+
+    #[test]
+    fn argon_boolean_ops() {
+        let o = parse_workspace_with_std(ARGON_BOOLEAN_OPS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        // Number and total area of the emitted rects on `layer`.
+        let emitted = |layer: &str| {
+            let rects = cell
+                .objects
+                .values()
+                .filter_map(|v| v.get_rect())
+                .filter(|r| !r.construction && r.layer.as_deref() == Some(layer))
+                .collect::<Vec<_>>();
+            let area = rects
+                .iter()
+                .map(|r| (r.x1.0 - r.x0.0) * (r.y1.0 - r.y0.0))
+                .sum::<f64>();
+            (rects.len(), area)
+        };
+
+        let (n, area) = emitted("met1");
+        assert_eq!(n, 3);
+        assert_relative_eq!(area, 300. * 200. - 100. * 50., epsilon = EPSILON);
+        let (n, area) = emitted("met2");
+        assert_eq!(n, 2);
+        assert_relative_eq!(area, 100. * 50. + 50. * 50., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_import_gds() {
+        let o = parse_workspace_with_std(ARGON_BOOLEAN_OPS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_import_gds/layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let shapes = import_gds(&path, &map).expect("failed to import GDS");
+        let met1 = shapes
+            .iter()
+            .filter(|shape| shape.layer == "met1")
+            .collect::<Vec<_>>();
+        assert_eq!(met1.len(), 3);
+        assert_relative_eq!(
+            met1.iter()
+                .map(|shape| shape.bbox.width() * shape.bbox.height())
+                .sum::<f64>(),
+            300. * 200. - 100. * 50.,
+            epsilon = EPSILON
+        );
+        assert_eq!(
+            shapes.iter().filter(|shape| shape.layer == "met2").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn argon_import_gds_source() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_import_gds_source");
+        let path = dir.join("layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let src = import_gds_source(&path, &map).expect("failed to import GDS");
+        assert!(src.contains("cell bot() {"), "{src}");
+        assert!(src.contains(r#"rect("met1", x0=0., y0=0., x1=100., y1=100.);"#));
+        assert!(src.contains("inst(bot(), x=300., y=0., angle=90);"));
+
+        // The imported source compiles back to the same layout.
+        let lib_path = dir.join("lib.ar");
+        std::fs::write(&lib_path, &src).expect("failed to write source");
+        let o = parse_workspace_with_std(&lib_path);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let mismatches = cells.unwrap_valid().verify_gds(&lib, &map).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+    }
+
+    #[test]
+    fn argon_arbitrary_rotation() {
+        let o = parse_workspace_with_std(ARGON_ROTATION);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let data = cells.clone().unwrap_valid();
+        let angles = data.cells[&data.top]
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_instance())
+            .map(|inst| inst.angle)
+            .collect::<Vec<_>>();
+        assert_eq!(angles, [45., 90.]);
+
+        // Only the corners of the 45 degree instance leave the grid.
+        let off_grid = data.off_grid_rotations(1.);
+        assert_eq!(off_grid.len(), 1);
+        assert_eq!(off_grid[0].angle, 45.);
+        assert_eq!(off_grid[0].layer.as_deref(), Some("met1"));
+
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("build/argon_arbitrary_rotation/layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let mut ref_angles = lib
+            .structs
+            .iter()
+            .flat_map(|s| &s.elems)
+            .filter_map(|elem| match elem {
+                GdsElement::GdsStructRef(sref) => sref.strans.as_ref()?.angle,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        ref_angles.sort_by(f64::total_cmp);
+        assert_eq!(ref_angles, [45., 90.]);
+
+        let shapes = import_gds(&path, &map).expect("failed to import GDS");
+        let tilted = shapes
+            .iter()
+            .find(|shape| shape.bbox.x1 < 100.)
+            .expect("rotated shape not imported");
+        assert_relative_eq!(tilted.bbox.y1, 120. / 2f64.sqrt(), epsilon = 1.);
+    }
+
+    #[test]
+    fn argon_dbu_snapping() {
+        let o = parse_workspace_with_std(ARGON_DBU);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let data = cells.clone().unwrap_valid();
+        let top = &data.cells[&data.top];
+        let r = top.fields["r"].clone().into_elem().unwrap();
+        let rect = top.objects[&r].get_rect().unwrap();
+        // The solver's floats carry rounding artifacts, but the snapped
+        // geometry does not.
+        assert_ne!(rect.x1.0, 300.);
+        assert_ne!(rect.y1.0, 800.);
+        assert_eq!(
+            top.dbu_rect(r),
+            Some(DbuRect {
+                x0: 0,
+                y0: 0,
+                x1: 300,
+                y1: 800
+            })
+        );
+
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_dbu_snapping/layout.gds");
+        cells
+            .to_gds(map, GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let xy = lib
+            .structs
+            .iter()
+            .flat_map(|s| &s.elems)
+            .find_map(|elem| match elem {
+                GdsElement::GdsBoundary(boundary) => Some(&boundary.xy),
+                _ => None,
+            })
+            .expect("rect not exported");
+        assert!(xy.iter().all(|pt| [0, 300].contains(&pt.x)));
+        assert!(xy.iter().all(|pt| [0, 800].contains(&pt.y)));
+    }
+
+    #[test]
+    fn format_on_grid_rounds_to_grid() {
+        assert_eq!(format_on_grid(1.2999999999, 0.005), "1.3");
+        assert_eq!(format_on_grid(1.3020000001, 0.005), "1.3");
+        assert_eq!(format_on_grid(1299.9999999, 1.), "1300.");
+        assert_eq!(format_on_grid(1302.4, 5.), "1300.");
+        assert_eq!(format_on_grid(0.36, 0.1), "0.4");
+        assert_eq!(format_on_grid(-0.0001, 0.001), "0.");
+        assert_eq!(format_on_grid(-2.5000001, 0.5), "-2.5");
+    }
+
+    #[test]
+    fn argon_bbox() {
+        let o = parse_workspace_with_std(ARGON_BBOX);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 5);
+    }
+
+    #[test]
+    fn argon_extern_cell() {
+        let o = parse_workspace_with_std(ARGON_EXTERN_CELL);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let mut cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_extern_cell/layout.gds");
+        assert!(
+            cells
+                .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+                .is_err()
+        );
+
+        cells.set_extern_gds(&IndexMap::from_iter([(
+            "sram".to_string(),
+            PathBuf::from(EXAMPLES_DIR).join("extern_cell/sram.gds"),
+        )]));
+        cells
+            .to_gds(map, GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let cells = cells.unwrap_valid();
+        let top = &cells.cells[&cells.top];
+        assert!(!top.external);
+        let insts = top
+            .objects
+            .values()
+            .filter_map(|obj| obj.instance())
+            .collect::<Vec<_>>();
+        assert_eq!(insts.len(), 2);
+        assert!(cells.cells[&insts[0].cell].external);
+        let r = top.objects.values().find_map(|obj| obj.get_rect()).unwrap();
+        assert_relative_eq!(r.x1.0, 2500., epsilon = EPSILON);
+        assert_relative_eq!(r.y0.0, 850., epsilon = EPSILON);
+
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let names = lib
+            .structs
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names.iter().filter(|name| **name == "sram").count(), 1);
+        assert_eq!(names.iter().filter(|name| **name == "bitcell").count(), 1);
+        let sram = lib
+            .structs
+            .iter()
+            .find(|s| s.name.as_str() == "sram")
+            .unwrap();
+        assert_eq!(sram.elems.len(), 3);
+        let top = &cells.gds_cell_names(cells.top)[&cells.top];
+        let srefs = lib
+            .structs
+            .iter()
+            .find(|s| s.name == *top)
+            .unwrap()
+            .elems
+            .iter()
+            .filter_map(|elem| match elem {
+                GdsElement::GdsStructRef(sref) => Some((sref.name.as_str(), sref.xy.x)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(srefs, [("sram", 0), ("sram", 1300)]);
+    }
+
+    #[test]
+    fn argon_merge_shapes() {
+        let o = parse_workspace_with_std(ARGON_MERGE_SHAPES);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let mut cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        cells.set_merge_shapes(true);
+        let map = GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map");
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_merge_shapes/layout.gds");
+        cells
+            .to_gds(map, GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        assert_eq!(lib.structs.len(), 1);
+        let boundaries = |layer: i16| {
+            lib.structs[0]
+                .elems
+                .iter()
+                .filter_map(|elem| match elem {
+                    GdsElement::GdsBoundary(b) if b.layer == layer => {
+                        Some(b.xy.iter().map(|pt| (pt.x, pt.y)).collect::<Vec<_>>())
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // The L and the rect abutting it become a single hexagon.
+        let met1 = boundaries(68);
+        assert_eq!(met1.len(), 1);
+        assert_eq!(
+            met1[0],
+            [
+                (0, 0),
+                (300, 0),
+                (300, 100),
+                (100, 100),
+                (100, 300),
+                (0, 300)
+            ]
+        );
+        assert_eq!(boundaries(69).len(), 1);
+        // The ring is written as disjoint rects.
+        let met3 = boundaries(70);
+        assert_eq!(met3.len(), 4);
+        assert!(met3.iter().all(|xy| xy.len() == 4));
     }
 
     #[test]
-    fn argon_external_mods() {
-        let o = parse_workspace_with_std(ARGON_EXTERNAL_MODS);
-        assert!(o.static_errors().is_empty());
+    fn argon_bbox_fields() {
+        let o = parse_workspace_with_std(ARGON_BBOX_FIELDS);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
         let ast = o.ast();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["test"],
+                cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:?}");
+        println!("{cells:#?}");
+
         let cells = cells.unwrap_valid();
         let cell = &cells.cells[&cells.top];
-        assert_eq!(cell.objects.len(), 1);
-        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
-        assert_relative_eq!(r.x0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.x1.0, 10., epsilon = EPSILON);
-        assert_relative_eq!(r.y1.0, 20., epsilon = EPSILON);
+        let rect = |layer: &str| {
+            cell.objects
+                .values()
+                .filter_map(|obj| obj.get_rect())
+                .find(|r| r.layer.as_deref() == Some(layer))
+                .unwrap()
+                .to_float()
+                .bbox()
+        };
+        let xs = cell
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_instance())
+            .map(|inst| inst.x)
+            .collect::<Vec<_>>();
+        assert_eq!(xs.len(), 3);
+        assert_relative_eq!(xs[1], 120., epsilon = EPSILON);
+        let met2 = rect("met2");
+        assert_relative_eq!(met2.x1, 220., epsilon = EPSILON);
+        assert_relative_eq!(met2.y0, 60., epsilon = EPSILON);
+        assert_relative_eq!(met2.y1, 110., epsilon = EPSILON);
+        // The bbox of `mid` includes the offset of its instance of `bot`.
+        let met3 = rect("met3");
+        assert_relative_eq!(met3.x0, 10., epsilon = EPSILON);
+        assert_relative_eq!(met3.y0, 210., epsilon = EPSILON);
+        assert_relative_eq!(met3.x1, 110., epsilon = EPSILON);
+        assert_relative_eq!(met3.y1, 260., epsilon = EPSILON);
     }
 
     #[test]
-    fn argon_sky130_inverter() {
-        let o = parse_workspace_with_std(ARGON_SKY130_LIB);
-        assert!(o.static_errors().is_empty());
+    fn argon_fn_geometry() {
+        let o = parse_workspace_with_std(ARGON_FN_GEOMETRY);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
         let ast = o.ast();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["inv"],
-                args: vec![
-                    CellArg::Float(1_200.),
-                    CellArg::Float(2_000.),
-                    CellArg::Int(4),
-                ],
-                lyp_file: &PathBuf::from(SKY130_LYP),
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("cells: {cells:?}");
-
-        assert!(cells.is_valid());
+        println!("{cells:#?}");
 
-        let work_dir =
-            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_sky130_inverter");
-        cells
-            .to_gds(
-                GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map"),
-                GdsUnits::new(1e-3, 1e-9),
-                work_dir.join("layout.gds"),
-            )
-            .expect("Failed to write to GDS");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let rects = |layer: &str| {
+            cell.objects
+                .values()
+                .filter_map(|obj| obj.get_rect())
+                .filter(|r| r.layer.as_deref() == Some(layer))
+                .map(|r| r.to_float().bbox())
+                .collect::<Vec<_>>()
+        };
+        // Geometry and instances created by functions are emitted into the caller.
+        assert_eq!(rects("met1").len(), 2);
+        assert_eq!(rects("met2").len(), 2);
+        assert_eq!(
+            cell.objects
+                .values()
+                .filter_map(|obj| obj.get_instance())
+                .count(),
+            2
+        );
+        let met3 = rects("met3")[0];
+        assert_relative_eq!(met3.x0, -10., epsilon = EPSILON);
+        assert_relative_eq!(met3.y0, 20., epsilon = EPSILON);
+        assert_relative_eq!(met3.x1, 110., epsilon = EPSILON);
+        assert_relative_eq!(met3.y1, 40., epsilon = EPSILON);
+        let met4 = rects("met4")[0];
+        assert_relative_eq!(met4.x0, -5., epsilon = EPSILON);
+        assert_relative_eq!(met4.x1, 105., epsilon = EPSILON);
+        assert_relative_eq!(met4.y1, 5., epsilon = EPSILON);
     }
 
     #[test]
-    fn argon_enumerations() {
-        let o = parse_workspace_with_std(ARGON_ENUMERATIONS);
-        assert!(o.static_errors().is_empty());
+    fn argon_units() {
+        let o = parse_workspace_with_std(ARGON_UNITS);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
         let ast = o.ast();
         let cells = compile(
             &ast,
@@ -1042,24 +3243,41 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:?}");
-        let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        assert_eq!(cell.objects.len(), 1);
-        let r = cell.objects.iter().next().unwrap().1.as_ref().unwrap_rect();
-        assert_eq!(r.layer.as_deref(), Some("met2"));
-        assert_relative_eq!(r.x0.0, 100., epsilon = EPSILON);
-        assert_relative_eq!(r.y0.0, 0., epsilon = EPSILON);
-        assert_relative_eq!(r.x1.0, 300., epsilon = EPSILON);
-        assert_relative_eq!(r.y1.0, 400., epsilon = EPSILON);
+        println!("{cells:#?}");
+
+        let mut cells = cells.unwrap_valid();
+        let rect = |cells: &crate::compile::CompiledData, layer: &str| {
+            let cell = &cells.cells[&cells.top];
+            let (id, rect) = cell
+                .objects
+                .iter()
+                .find_map(|(id, obj)| {
+                    obj.get_rect()
+                        .filter(|r| r.layer.as_deref() == Some(layer))
+                        .map(|r| (*id, r.to_float().bbox()))
+                })
+                .unwrap();
+            (rect, cell.dbu_rect(id).unwrap())
+        };
+        let (met1, _) = rect(&cells, "met1");
+        assert_relative_eq!(met1.x1, 500., epsilon = EPSILON);
+        assert_relative_eq!(met1.y1, 250., epsilon = EPSILON);
+        let (met2, _) = rect(&cells, "met2");
+        assert_relative_eq!(met2.x0, 460., epsilon = EPSILON);
+        assert_relative_eq!(met2.x1, 610., epsilon = EPSILON);
+
+        // A finer database unit scales the snapped geometry.
+        cells.set_dbu(Some(0.5));
+        let (_, met1) = rect(&cells, "met1");
+        assert_eq!((met1.x1, met1.y1), (1000, 500));
     }
 
     #[test]
-    fn argon_bbox() {
-        let o = parse_workspace_with_std(ARGON_BBOX);
-        assert!(o.static_errors().is_empty());
+    fn argon_mixed_units() {
+        let o = parse_workspace_with_std(ARGON_MIXED_UNITS);
         let ast = o.ast();
         let cells = compile(
             &ast,
@@ -1067,12 +3285,17 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
-        let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        assert_eq!(cell.objects.len(), 5);
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 1);
+        assert!(matches!(
+            errors.errors[0].kind,
+            StaticErrorKind::MixedUnits { left: 1, right: 0 }
+        ));
     }
 
     #[test]
@@ -1086,6 +3309,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1108,6 +3332,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1134,6 +3359,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1159,6 +3385,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1184,6 +3411,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1209,6 +3437,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1234,6 +3463,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1259,53 +3489,299 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert!(cell.objects.len() >= 3);
+        let inst = cell
+            .objects
+            .iter()
+            .find_map(|(_, v)| v.get_instance())
+            .unwrap();
+        assert_relative_eq!(inst.x, 2000., epsilon = EPSILON);
+        assert_relative_eq!(inst.y, 3000., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_text() {
+        let o = parse_workspace_with_std(ARGON_TEXT);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let work_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_text");
+        cells
+            .to_gds(
+                GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map"),
+                GdsUnits::new(1e-3, 1e-9),
+                work_dir.join("layout.gds"),
+            )
+            .expect("Failed to write to GDS");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 2);
+        let t = cell.objects.iter().find_map(|(_, v)| v.get_text()).unwrap();
+        assert_eq!(t.layer, "met1.label");
+        assert_eq!(t.text, "mytext");
+        assert_relative_eq!(t.x, 0., epsilon = EPSILON);
+        assert_relative_eq!(t.y, 10., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_polygon() {
+        let o = parse_workspace_with_std(ARGON_POLYGON);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_polygon/layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let p = cell.objects.values().find_map(|v| v.get_polygon()).unwrap();
+        assert_eq!(p.layer, "met1");
+        assert_eq!(p.points, vec![(0., 100.), (100., 100.), (0., 300.)]);
+        let bbox = cells.cell_bbox(cells.top).unwrap();
+        assert_relative_eq!(bbox.y1, 300., epsilon = EPSILON);
+
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let mismatches = cells.verify_gds(&lib, &map).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+    }
+
+    #[test]
+    fn argon_oasis_export() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(BASIC_LYP).expect("failed to create GDS map");
+        let oas_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_oasis_export/layout.oas");
+        cells
+            .to_layout(
+                map,
+                GdsUnits::new(1e-3, 1e-9),
+                LayoutFormat::Oasis,
+                &oas_path,
+            )
+            .expect("Failed to write to OASIS");
+
+        let oas = std::fs::read(&oas_path).expect("failed to read OASIS");
+        assert!(oas.starts_with(b"%SEMI-OASIS\r\n"));
+        let contains = |needle: &[u8]| oas.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"\x0e\x03top"));
+        assert!(contains(b"\x0e\x03bot"));
+        // `bot`'s first rect is a 100 by 100 RECTANGLE record at the origin
+        // on `met1`, which is GDS layer 235/4.
+        assert!(contains(&[20, 0x7b, 0xeb, 0x01, 4, 100, 100, 0, 0]));
+    }
+
+    #[test]
+    fn compile_output_schema() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let json = cells.to_json().unwrap();
+        let read = CompileOutput::from_json(&json).unwrap();
+        assert_eq!(read.unwrap_valid().cells.len(), 2);
+        let bytes = cells.to_bincode().unwrap();
+        assert!(bytes.len() < json.len());
+        let read = CompileOutput::from_bytes(&bytes).unwrap();
+        assert_eq!(read.unwrap_valid().cells.len(), 2);
+        let read = CompileOutput::from_bytes(json.as_bytes()).unwrap();
+        assert_eq!(read.unwrap_valid().cells.len(), 2);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["schema_version"] = (SCHEMA_VERSION + 1).into();
+        assert!(matches!(
+            CompileOutput::from_json(&value.to_string()),
+            Err(SchemaError::VersionMismatch { found, expected: SCHEMA_VERSION })
+                if found == SCHEMA_VERSION + 1
+        ));
+        let unversioned = serde_json::to_string(&value["output"]).unwrap();
+        assert!(matches!(
+            CompileOutput::from_json(&unversioned),
+            Err(SchemaError::Unversioned)
+        ));
+    }
+
+    #[test]
+    fn argon_svg_export() {
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        let svg = cells.unwrap_valid().to_svg_string().unwrap();
+        assert!(
+            svg.starts_with(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 -200 300 200\">"
+            )
+        );
+        // Two rects from each instance of `bot`, plus `met3`.
+        assert_eq!(svg.matches("<rect ").count(), 5);
+        assert!(svg.contains("<rect x=\"0\" y=\"20\" width=\"300\" height=\"60\""));
+    }
+
+    #[test]
+    fn argon_pin() {
+        let o = parse_workspace_with_std(ARGON_PIN);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
+            },
+        );
+        let map = GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_pin/layout.gds");
+        cells
+            .to_gds(map.clone(), GdsUnits::new(1e-3, 1e-9), &path)
+            .expect("Failed to write to GDS");
+
+        let cells = cells.unwrap_valid();
+        let top = &cells.cells[&cells.top];
+        let pin = top.objects.values().find_map(|v| v.get_pin()).unwrap();
+        assert_eq!(pin.name, "A");
+        assert_eq!((pin.x0, pin.y0, pin.x1, pin.y1), (0., 0., 200., 100.));
+        assert_eq!(top.ports.len(), 1);
+        assert_eq!((top.ports[0].x, top.ports[0].y), (100., 50.));
+
+        let lib = GdsLibrary::load(&path).expect("failed to read GDS");
+        let mismatches = cells.verify_gds(&lib, &map).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+        let name = &cells.gds_cell_names(cells.top)[&cells.top];
+        let elems = &lib.structs.iter().find(|s| s.name == *name).unwrap().elems;
+        let label = elems
+            .iter()
+            .find_map(|elem| match elem {
+                GdsElement::GdsTextElem(text) => Some(text),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(label.string, "A");
+        assert_eq!((label.layer, label.texttype), (68, 5));
+        assert!(elems.iter().any(|elem| matches!(
+            elem,
+            GdsElement::GdsBoundary(b) if (b.layer, b.datatype) == (68, 16)
+        )));
+    }
+
+    #[test]
+    fn argon_lef_export() {
+        let o = parse_workspace_with_std(ARGON_PIN);
+        assert!(o.static_errors().is_empty(), "{:?}", o.static_errors());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
+            },
+        );
+        let lef = cells.unwrap_valid().to_lef_string().unwrap();
+        assert!(lef.contains("MACRO top\n"), "{lef}");
+        assert!(lef.contains("  SIZE 0.2 BY 0.1 ;"));
+        assert!(lef.contains("  PIN A\n"));
+        assert!(lef.contains("      LAYER met1 ;\n        RECT 0 0 0.2 0.1 ;"));
+        // The drawing rect under the pin is not an obstruction.
+        assert!(!lef.contains("OBS"));
+
+        let o = parse_workspace_with_std(ARGON_HIERARCHY);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
-        let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        assert!(cell.objects.len() >= 3);
-        let inst = cell
-            .objects
-            .iter()
-            .find_map(|(_, v)| v.get_instance())
-            .unwrap();
-        assert_relative_eq!(inst.x, 2000., epsilon = EPSILON);
-        assert_relative_eq!(inst.y, 3000., epsilon = EPSILON);
+        let lef = cells.unwrap_valid().to_lef_string().unwrap();
+        assert!(lef.contains("MACRO bot\n"), "{lef}");
+        // `right` is rotated by 90 degrees about (300, 0), so the cell spans x = 0 to 300.
+        assert!(lef.contains("MACRO top\n  CLASS BLOCK ;\n  ORIGIN 0 0 ;"));
+        assert!(lef.contains("    LAYER met3 ;\n      RECT 0 0.02 0.3 0.08 ;"));
+        assert!(lef.ends_with("END LIBRARY\n"));
     }
 
     #[test]
-    fn argon_text() {
-        let o = parse_workspace_with_std(ARGON_TEXT);
-        assert!(o.static_errors().is_empty());
+    fn argon_polygon_degenerate() {
+        let o = parse_workspace_with_std(ARGON_POLYGON);
         let ast = o.ast();
         let cells = compile(
             &ast,
             CompileInput {
-                cell: &["top"],
+                cell: &["degenerate"],
                 args: Vec::new(),
-                lyp_file: &PathBuf::from(SKY130_LYP),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
-        println!("{cells:#?}");
-
-        let work_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("build/argon_text");
-        cells
-            .to_gds(
-                GdsMap::from_lyp(SKY130_LYP).expect("failed to create GDS map"),
-                GdsUnits::new(1e-3, 1e-9),
-                work_dir.join("layout.gds"),
-            )
-            .expect("Failed to write to GDS");
-
-        let cells = cells.unwrap_valid();
-        let cell = &cells.cells[&cells.top];
-        assert_eq!(cell.objects.len(), 2);
-        let t = cell.objects.iter().find_map(|(_, v)| v.get_text()).unwrap();
-        assert_eq!(t.layer, "met1.label");
-        assert_eq!(t.text, "mytext");
-        assert_relative_eq!(t.x, 0., epsilon = EPSILON);
-        assert_relative_eq!(t.y, 10., epsilon = EPSILON);
+        let errors = cells.unwrap_exec_errors().errors;
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e.kind, ExecErrorKind::DegeneratePolygon(2))),
+            "{errors:?}"
+        );
     }
 
     #[test]
@@ -1319,6 +3795,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1346,6 +3823,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1373,6 +3851,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1400,6 +3879,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1424,6 +3904,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1448,6 +3929,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1472,6 +3954,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1496,6 +3979,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1536,6 +4020,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1563,6 +4048,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1588,6 +4074,352 @@ mod tests {
         }
     }
 
+    #[test]
+    fn argon_for_range() {
+        let o = parse_workspace_with_std(ARGON_FOR_RANGE);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: vec![CellArg::Int(3)],
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let assert_x0s = |layer: &str, expected: &[f64]| {
+            let mut x0s = cell
+                .objects
+                .iter()
+                .filter_map(|(_, v)| v.get_rect())
+                .filter(|r| r.layer.as_deref() == Some(layer))
+                .map(|r| r.x0.0)
+                .collect::<Vec<_>>();
+            x0s.sort_by(f64::total_cmp);
+            assert_eq!(x0s.len(), expected.len());
+            for (x0, expected) in x0s.iter().zip(expected) {
+                assert_relative_eq!(*x0, *expected, epsilon = EPSILON);
+            }
+        };
+        assert_x0s("met1", &[0., 200., 400.]);
+        assert_x0s("met3", &[200., 400., 600.]);
+    }
+
+    #[test]
+    fn argon_seq_literal() {
+        let o = parse_workspace_with_std(ARGON_SEQ_LITERAL);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let rects = cell
+            .objects
+            .iter()
+            .filter_map(|(_, v)| v.get_rect())
+            .collect::<Vec<_>>();
+        assert_eq!(rects.len(), 4);
+        for x0 in [0., 150., 300.] {
+            assert!(rects.iter().any(|r| r.layer.as_deref() == Some("met1")
+                && relative_eq!(r.x0.0, x0, epsilon = EPSILON)));
+        }
+        let met2 = rects
+            .iter()
+            .find(|r| r.layer.as_deref() == Some("met2"))
+            .unwrap();
+        assert_relative_eq!(met2.x1.0, 200., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_structs() {
+        let o = parse_workspace_with_std(ARGON_STRUCTS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        let met2 = cell
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_rect())
+            .find(|r| r.layer.as_deref() == Some("met2"))
+            .unwrap();
+        assert_relative_eq!(met2.x1.0, 240., epsilon = EPSILON);
+        assert_relative_eq!(met2.y1.0, 50., epsilon = EPSILON);
+
+        let inst = cell
+            .objects
+            .values()
+            .find_map(|obj| obj.get_instance())
+            .unwrap();
+        assert_eq!(inst.params["p"].to_string(), "Pad(w=200.0, h=50.0)");
+        let pad = cells.cells[&inst.cell]
+            .objects
+            .values()
+            .find_map(|obj| obj.get_rect())
+            .unwrap();
+        assert_relative_eq!(pad.x1.0, 200., epsilon = EPSILON);
+        assert_relative_eq!(pad.y1.0, 50., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn argon_structs_invalid() {
+        let o = parse_workspace_with_std(ARGON_STRUCTS_INVALID);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 4);
+        assert!(errors.errors.iter().any(
+            |e| matches!(&e.kind, StaticErrorKind::MissingFields(fields) if fields == &["h"])
+        ));
+        assert!(errors.errors.iter().any(|e| matches!(
+            e.kind,
+            StaticErrorKind::IncorrectTy {
+                expected: Ty::Float,
+                found: Ty::Int
+            }
+        )));
+        assert!(errors.errors.iter().any(|e| matches!(
+            e.kind,
+            StaticErrorKind::CallIncorrectPositionalArity {
+                expected: 2,
+                found: 3
+            }
+        )));
+        assert!(errors.errors.iter().any(
+            |e| matches!(&e.kind, StaticErrorKind::NoFieldOnTy { field, .. } if field == "d")
+        ));
+    }
+
+    #[test]
+    fn argon_builtin_args_invalid() {
+        let o = parse_workspace_with_std(ARGON_BUILTIN_ARGS_INVALID);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 2);
+        assert!(
+            errors
+                .errors
+                .iter()
+                .any(|e| matches!(e.kind, StaticErrorKind::InvalidKwArg))
+        );
+        assert!(errors.errors.iter().any(|e| matches!(
+            e.kind,
+            StaticErrorKind::CallIncorrectPositionalArity {
+                expected: 1,
+                found: 0
+            }
+        )));
+    }
+
+    #[test]
+    fn solver_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [solver]
+            backend = "svd"
+            round_step = 50.
+            fallback_priority = { y0i = 10 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.solver.backend, SolverBackend::Svd);
+        assert_eq!(config.solver.tolerance, SolverConfig::default().tolerance);
+        assert_eq!(config.solver.fallback_priority["y0i"], 10);
+        assert!(config.solver.validate().is_ok());
+        assert!(
+            SolverConfig {
+                round_step: 0.,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+
+        let o = parse_workspace_with_std(ARGON_FOR_LOOP_BASIC);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let compile_with = |solver: SolverConfig| {
+            compile(
+                &ast,
+                CompileInput {
+                    cell: &["top"],
+                    args: Vec::new(),
+                    lyp_file: &PathBuf::from(BASIC_LYP),
+                    solver,
+                },
+            )
+        };
+
+        // Every coordinate is a multiple of the round step.
+        let cells = compile_with(config.solver.clone()).unwrap_valid();
+        assert_eq!(cells.cells[&cells.top].objects.len(), 5);
+
+        let errors = compile_with(SolverConfig {
+            max_iterations: Some(0),
+            ..Default::default()
+        })
+        .unwrap_exec_errors()
+        .errors;
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e.kind, ExecErrorKind::SolverBudgetExceeded))
+        );
+    }
+
+    #[test]
+    fn argon_constants() {
+        let o = parse_workspace_with_std(ARGON_CONSTANTS);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let (consts, errors) = eval_consts(&ast[&vec![]]);
+        assert!(errors.is_empty());
+        assert_eq!(consts["N_FINGERS"].value, ConstValue::Int(4));
+        assert_eq!(consts["HEIGHT"].value, ConstValue::Float(710.));
+
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let cells = cells.unwrap_valid();
+        let cell = &cells.cells[&cells.top];
+        assert_eq!(cell.objects.len(), 4);
+        for i in 0..4 {
+            let x0 = 430. * i as f64;
+            let r = cell
+                .objects
+                .iter()
+                .find_map(|(_, v)| {
+                    v.get_rect()
+                        .filter(|r| relative_eq!(r.x0.0, x0, epsilon = EPSILON))
+                })
+                .unwrap();
+            assert_relative_eq!(r.x1.0, x0 + 150., epsilon = EPSILON);
+            assert_relative_eq!(r.y1.0, 710., epsilon = EPSILON);
+        }
+    }
+
+    #[test]
+    fn argon_constants_overflow() {
+        let o = parse_workspace_with_std(ARGON_CONSTANTS_OVERFLOW);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 4);
+        assert!(
+            errors
+                .errors
+                .iter()
+                .all(|e| matches!(e.kind, StaticErrorKind::IntegerOverflow))
+        );
+    }
+
+    #[test]
+    fn argon_constants_invalid() {
+        let o = parse_workspace_with_std(ARGON_CONSTANTS_INVALID);
+        assert!(o.static_errors().is_empty());
+        let ast = o.ast();
+        let cells = compile(
+            &ast,
+            CompileInput {
+                cell: &["top"],
+                args: Vec::new(),
+                lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
+            },
+        );
+        println!("{cells:#?}");
+
+        let errors = cells.unwrap_static_errors();
+        assert_eq!(errors.errors.len(), 3);
+        assert!(
+            errors
+                .errors
+                .iter()
+                .any(|e| matches!(e.kind, StaticErrorKind::DivisionByZero))
+        );
+        assert!(
+            errors
+                .errors
+                .iter()
+                .any(|e| matches!(e.kind, StaticErrorKind::NotConstant))
+        );
+        assert!(errors.errors.iter().any(|e| matches!(
+            e.kind,
+            StaticErrorKind::IncorrectTy {
+                expected: Ty::Int,
+                found: Ty::Float
+            }
+        )));
+    }
+
     /// Regression guard against O(n^2) `for` loops over `range`.
     ///
     /// Under the old `cons`-based `range`, building `range(20000)` cloned and
@@ -1608,6 +4440,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         let elapsed = start.elapsed();
@@ -1632,6 +4465,7 @@ mod tests {
                 cell: &["top"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1653,6 +4487,7 @@ mod tests {
                 cell: &["precedence"],
                 args: Vec::new(),
                 lyp_file: &PathBuf::from(BASIC_LYP),
+                solver: Default::default(),
             },
         );
         println!("{cells:#?}");
@@ -1685,6 +4520,7 @@ mod tests {
                 cell: &["diff_vco_top"],
                 args: vec![],
                 lyp_file: &PathBuf::from(SKY130_LYP),
+                solver: Default::default(),
             },
         );
         println!("cells: {cells:?}");
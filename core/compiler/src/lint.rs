@@ -0,0 +1,825 @@
+//! Lints for Argon workspaces, run by `argon lint` and the language server.
+//!
+//! Each [`LintRule`] defaults to [`LintLevel::Warn`] and can be reconfigured in
+//! the `[lint]` table of `Argon.toml`:
+//!
+//! ```toml
+//! [lint]
+//! magic-number = "allow"
+//! zero-area-rect = "deny"
+//! ```
+//!
+//! The `min-width` rule checks the minimum widths declared in the `[min_width]`
+//! table, keyed by layer, and the `off-grid` rule checks geometry against the
+//! manufacturing `grid`.
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use arcstr::Substr;
+use argon_geometry::Bbox;
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{ArgDecl, Decl, Expr, Scope, Span, Statement},
+    compile::{CellConstraint, CellId, CompiledCell, CompiledData, SolvedValue},
+    config::Config,
+    consts::{ConstValue, eval_consts},
+    layer::line_col,
+    parse::{ParseMetadata, WorkspaceParseAst},
+    rotation::on_grid,
+    solver::LinearExpr,
+};
+
+/// Literal values too common to be worth naming.
+const TRIVIAL_LITERALS: [f64; 2] = [0., 1.];
+
+/// A lint check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// A numeric literal in a cell or function equals the value of a constant.
+    MagicNumber,
+    /// A rect has zero width or height.
+    ZeroAreaRect,
+    /// A layer is drawn on, but only by geometry that is never exported.
+    UnexportedLayer,
+    /// A cell is never instantiated, listed in `Argon.toml`, or exported by
+    /// default.
+    UnusedCell,
+    /// Every shape of an instance is covered by a rect on the same layer in the
+    /// instantiating cell.
+    ShadowedInstance,
+    /// A rect is narrower than the minimum width of its layer.
+    MinWidth,
+    /// A `let` binding is never read. Bindings whose names start with `_` are
+    /// exempt.
+    UnusedVariable,
+    /// An enum variant is never referenced, listed in `Argon.toml`, or
+    /// enumerated with `variants()`.
+    UnusedVariant,
+    /// A coordinate of exported geometry, or the origin or pitch of an
+    /// instance, is not a multiple of the manufacturing grid.
+    OffGrid,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 9] = [
+        LintRule::MagicNumber,
+        LintRule::ZeroAreaRect,
+        LintRule::UnexportedLayer,
+        LintRule::UnusedCell,
+        LintRule::ShadowedInstance,
+        LintRule::MinWidth,
+        LintRule::UnusedVariable,
+        LintRule::UnusedVariant,
+        LintRule::OffGrid,
+    ];
+
+    /// The rule's name in `Argon.toml` and in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::MagicNumber => "magic-number",
+            LintRule::ZeroAreaRect => "zero-area-rect",
+            LintRule::UnexportedLayer => "unexported-layer",
+            LintRule::UnusedCell => "unused-cell",
+            LintRule::ShadowedInstance => "shadowed-instance",
+            LintRule::MinWidth => "min-width",
+            LintRule::UnusedVariable => "unused-variable",
+            LintRule::UnusedVariant => "unused-variant",
+            LintRule::OffGrid => "off-grid",
+        }
+    }
+}
+
+impl Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How a [`LintRule`] is reported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// The rule is not checked.
+    Allow,
+    #[default]
+    Warn,
+    /// Lints are reported as errors and fail `argon lint`.
+    Deny,
+}
+
+/// A violation of a [`LintRule`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lint {
+    pub rule: LintRule,
+    pub level: LintLevel,
+    pub message: String,
+    pub span: Span,
+    /// 1-indexed line of the start of `span`.
+    pub line: usize,
+    /// 1-indexed column of the start of `span`.
+    pub col: usize,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            LintLevel::Deny => "error",
+            _ => "warning",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {level}[{}]: {}",
+            self.span.path.display(),
+            self.line,
+            self.col,
+            self.rule,
+            self.message
+        )
+    }
+}
+
+/// Collects lints for a workspace.
+pub struct Linter<'a> {
+    config: &'a Config,
+    ast: &'a WorkspaceParseAst,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(config: &'a Config, ast: &'a WorkspaceParseAst) -> Self {
+        Self {
+            config,
+            ast,
+            lints: Vec::new(),
+        }
+    }
+
+    fn level(&self, rule: LintRule) -> LintLevel {
+        self.config.lint.get(&rule).copied().unwrap_or_default()
+    }
+
+    fn enabled(&self, rule: LintRule) -> bool {
+        self.level(rule) != LintLevel::Allow
+    }
+
+    fn push(&mut self, rule: LintRule, span: Span, message: String) {
+        let level = self.level(rule);
+        if level == LintLevel::Allow
+            || self
+                .lints
+                .iter()
+                .any(|lint| lint.rule == rule && lint.span == span)
+        {
+            return;
+        }
+        let Some(file) = self.ast.values().find(|file| file.path == span.path) else {
+            return;
+        };
+        let (line, col) = line_col(&file.text, span.span.start());
+        self.lints.push(Lint {
+            rule,
+            level,
+            message,
+            span,
+            line,
+            col,
+        });
+    }
+
+    /// Checks the Argon source of the workspace, skipping the standard library.
+    pub fn check_source(&mut self) {
+        let mut called = IndexSet::new();
+        let mut variants = IndexSet::new();
+        let mut decls = Vec::new();
+        let mut enums = Vec::new();
+        for (mod_path, file) in self.ast {
+            if mod_path.first().map(String::as_str) == Some("std") {
+                continue;
+            }
+            let (consts, _) = eval_consts(file);
+            let mut source = SourceLints {
+                consts: consts
+                    .into_iter()
+                    .filter_map(|(name, c)| match c.value {
+                        ConstValue::Int(v) => Some((name, v as f64)),
+                        ConstValue::Float(v) => Some((name, v)),
+                        _ => None,
+                    })
+                    .filter(|(_, v)| !TRIVIAL_LITERALS.contains(&v.abs()))
+                    .collect(),
+                literals: Vec::new(),
+                called: &mut called,
+                bindings: Vec::new(),
+                unused: Vec::new(),
+                variants: &mut variants,
+            };
+            for decl in &file.ast.decls {
+                match decl {
+                    Decl::Cell(cell) => {
+                        source.body(&cell.args, &cell.scope);
+                        for kwarg in cell.bbox.iter().flat_map(|bbox| &bbox.kwargs) {
+                            source.expr(&kwarg.value);
+                        }
+                        decls.push((&file.path, mod_path.is_empty(), cell));
+                    }
+                    Decl::Fn(f) => source.body(&f.args, &f.scope),
+                    Decl::Enum(e) => enums.push((&file.path, e)),
+                    Decl::Constant(c) => {
+                        // Only look for variants; a constant's own value is not
+                        // a magic number.
+                        let consts = std::mem::take(&mut source.consts);
+                        source.expr(&c.value);
+                        source.consts = consts;
+                    }
+                    _ => {}
+                }
+            }
+            let unused = std::mem::take(&mut source.unused);
+            for (span, name) in source.literals {
+                let literal = &file.text[span.start()..span.end()];
+                self.push(
+                    LintRule::MagicNumber,
+                    Span {
+                        path: file.path.clone(),
+                        span,
+                    },
+                    format!("literal `{literal}` has the same value as constant `{name}`"),
+                );
+            }
+            for (span, name) in unused {
+                self.push(
+                    LintRule::UnusedVariable,
+                    Span {
+                        path: file.path.clone(),
+                        span,
+                    },
+                    format!("variable `{name}` is never used"),
+                );
+            }
+        }
+
+        if self.enabled(LintRule::UnusedCell) {
+            let listed = self
+                .config
+                .cells
+                .iter()
+                .map(|cell| cell.split('(').next().unwrap_or_default().trim())
+                .chain(self.config.targets.iter().map(|t| t.cell_path()))
+                .map(|path| path.rsplit("::").next().unwrap_or(path).to_string())
+                .collect::<IndexSet<_>>();
+            // `argon gds --all` exports every root cell without parameters if
+            // no cells are listed.
+            let default_exports = self.config.cells.is_empty() && self.config.targets.is_empty();
+            for (path, root, cell) in decls {
+                let name = cell.name.name.as_str();
+                if called.contains(name)
+                    || listed.contains(name)
+                    || (default_exports && root && cell.args.is_empty())
+                {
+                    continue;
+                }
+                self.push(
+                    LintRule::UnusedCell,
+                    Span {
+                        path: path.clone(),
+                        span: cell.name.span,
+                    },
+                    format!("cell `{name}` is never instantiated or exported"),
+                );
+            }
+        }
+
+        if self.enabled(LintRule::UnusedVariant) {
+            for (path, e) in enums {
+                let name = e.name.name.as_str();
+                if variants.contains(&(name.to_string(), None)) {
+                    continue;
+                }
+                for variant in &e.variants {
+                    let ident = &variant.name;
+                    let variant = ident.name.as_str();
+                    let listed = self
+                        .config
+                        .cells
+                        .iter()
+                        .any(|cell| cell.contains(&format!("{name}::{variant}")));
+                    if listed || variants.contains(&(name.to_string(), Some(variant.to_string()))) {
+                        continue;
+                    }
+                    self.push(
+                        LintRule::UnusedVariant,
+                        Span {
+                            path: path.clone(),
+                            span: ident.span,
+                        },
+                        format!("variant `{name}::{variant}` is never used"),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Checks the geometry of a compiled cell and its descendants.
+    pub fn check_output(&mut self, data: &CompiledData) {
+        // Zero-area rects.
+        for cell in data.cells.values() {
+            for rect in cell.objects.values().filter_map(|obj| obj.get_rect()) {
+                let bbox = rect.to_float().bbox();
+                if !rect.construction
+                    && rect.layer.is_some()
+                    && bbox.area() == 0.
+                    && let Some(span) = &rect.span
+                {
+                    self.push(
+                        LintRule::ZeroAreaRect,
+                        span.clone(),
+                        format!("rect has zero area ({} x {})", bbox.width(), bbox.height()),
+                    );
+                }
+            }
+        }
+
+        // Layers drawn only by geometry that is not exported.
+        let mut exported_cells = IndexSet::from([data.top]);
+        let mut stack = vec![data.top];
+        while let Some(cell) = stack.pop() {
+            for inst in data.emitted(cell).filter_map(|obj| obj.instance()) {
+                if !inst.construction && exported_cells.insert(inst.cell) {
+                    stack.push(inst.cell);
+                }
+            }
+        }
+        let mut exported_layers = IndexSet::new();
+        let mut unexported = IndexMap::new();
+        for (id, cell) in &data.cells {
+            for rect in cell.objects.values().filter_map(|obj| obj.get_rect()) {
+                let Some(layer) = &rect.layer else {
+                    continue;
+                };
+                if !rect.construction && exported_cells.contains(id) {
+                    exported_layers.insert(layer.clone());
+                } else if let Some(span) = &rect.span {
+                    unexported
+                        .entry(layer.clone())
+                        .or_insert_with(|| span.clone());
+                }
+            }
+        }
+        for (layer, span) in unexported {
+            if !exported_layers.contains(&layer) {
+                self.push(
+                    LintRule::UnexportedLayer,
+                    span,
+                    format!("layer `{layer}` is drawn on, but never exported"),
+                );
+            }
+        }
+
+        self.check_min_width(data);
+        self.check_grid(data);
+
+        // Instances covered by geometry on the same layers.
+        if self.enabled(LintRule::ShadowedInstance) {
+            let mut layer_bboxes = HashMap::new();
+            for (id, cell) in &data.cells {
+                let rects = data
+                    .emitted(*id)
+                    .filter_map(|obj| obj.get_rect())
+                    .filter(|rect| !rect.construction)
+                    .filter_map(|rect| Some((rect.layer.as_ref()?, rect.to_float().bbox())))
+                    .collect::<Vec<_>>();
+                for obj in data.emitted(*id) {
+                    let Some(inst) = obj.instance().filter(|inst| !inst.construction) else {
+                        continue;
+                    };
+                    let layers = exported_layer_bboxes(data, inst.cell, &mut layer_bboxes);
+                    let shadowed = !layers.is_empty()
+                        && obj.instance_transforms().iter().all(|xform| {
+                            layers.iter().all(|(layer, bbox)| {
+                                let bbox = bbox.transform(xform);
+                                rects
+                                    .iter()
+                                    .any(|(l, rect)| *l == layer && rect.contains(&bbox))
+                            })
+                        });
+                    if shadowed {
+                        self.push(
+                            LintRule::ShadowedInstance,
+                            inst.span.clone(),
+                            format!(
+                                "instance of `{}` is covered by geometry on the same layers in `{}`",
+                                inst.cell_name, cell.name
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the width and height of each rect against the minimum width of
+    /// its layer, naming the constraints that fixed the offending dimension.
+    ///
+    /// Zero-area rects are left to [`LintRule::ZeroAreaRect`].
+    fn check_min_width(&mut self, data: &CompiledData) {
+        if !self.enabled(LintRule::MinWidth) || self.config.min_width.is_empty() {
+            return;
+        }
+        for (id, cell) in &data.cells {
+            let names = data.reachable_objs(*id, cell.root);
+            for rect in cell
+                .objects
+                .values()
+                .filter_map(|obj| obj.get_rect())
+                .filter(|rect| !rect.construction)
+            {
+                let (Some(layer), Some(span)) = (&rect.layer, &rect.span) else {
+                    continue;
+                };
+                let Some(min) = self.config.min_width.get(layer).copied() else {
+                    continue;
+                };
+                let name = match names.get(&rect.id) {
+                    Some(name) => format!("rect `{name}` on `{layer}`"),
+                    None => format!("`{layer}` rect"),
+                };
+                for (dim, size, lo, hi) in [
+                    ("wide", "width", &rect.x0, &rect.x1),
+                    ("tall", "height", &rect.y0, &rect.y1),
+                ] {
+                    let value = (hi.0 - lo.0).abs();
+                    if value == 0. || value >= min {
+                        continue;
+                    }
+                    let chain = constraint_chain(cell, &[&lo.1, &hi.1])
+                        .into_iter()
+                        .filter_map(|constraint| self.describe_constraint(constraint))
+                        .collect::<Vec<_>>();
+                    let mut message =
+                        format!("{name} is {value} {dim}, below the minimum width of {min}");
+                    if !chain.is_empty() {
+                        let shown = chain.len().min(MAX_CHAIN_LEN);
+                        message.push_str(&format!(
+                            "; its {size} is fixed by {}",
+                            chain[..shown].join(", ")
+                        ));
+                        if chain.len() > shown {
+                            message.push_str(&format!(" and {} more", chain.len() - shown));
+                        }
+                    }
+                    self.push(LintRule::MinWidth, span.clone(), message);
+                }
+            }
+        }
+    }
+
+    /// Checks that exported rects and polygons, and the origins and pitches of
+    /// instances, lie on the manufacturing grid, which defaults to the
+    /// database unit.
+    fn check_grid(&mut self, data: &CompiledData) {
+        if !self.enabled(LintRule::OffGrid) {
+            return;
+        }
+        let grid = self.config.grid.unwrap_or(data.dbu);
+        let off_grid = |coords: &[(&str, f64)]| {
+            coords
+                .iter()
+                .find(|(_, value)| !on_grid(*value, grid))
+                .map(|(name, value)| format!("{name} = {value}"))
+        };
+        for cell in data.cells.values() {
+            for obj in cell.objects.values() {
+                let (shape, span, coord) = match obj {
+                    SolvedValue::Rect(rect) if !rect.construction => {
+                        let (Some(layer), Some(span)) = (&rect.layer, &rect.span) else {
+                            continue;
+                        };
+                        (
+                            format!("rect on `{layer}`"),
+                            span,
+                            off_grid(&[
+                                ("x0", rect.x0.0),
+                                ("y0", rect.y0.0),
+                                ("x1", rect.x1.0),
+                                ("y1", rect.y1.0),
+                            ]),
+                        )
+                    }
+                    SolvedValue::Polygon(polygon) if !polygon.construction => {
+                        let Some(span) = &polygon.span else {
+                            continue;
+                        };
+                        (
+                            format!("polygon on `{}`", polygon.layer),
+                            span,
+                            polygon
+                                .points
+                                .iter()
+                                .find(|(x, y)| !on_grid(*x, grid) || !on_grid(*y, grid))
+                                .map(|(x, y)| format!("vertex ({x}, {y})")),
+                        )
+                    }
+                    SolvedValue::Instance(_) | SolvedValue::InstanceArray(_) => {
+                        let inst = obj.instance().unwrap();
+                        if inst.construction {
+                            continue;
+                        }
+                        let mut coords = vec![("x", inst.x), ("y", inst.y)];
+                        if let SolvedValue::InstanceArray(array) = obj {
+                            coords.extend([("dx", array.array.dx), ("dy", array.array.dy)]);
+                        }
+                        (
+                            format!("instance of `{}`", inst.cell_name),
+                            &inst.span,
+                            off_grid(&coords),
+                        )
+                    }
+                    _ => continue,
+                };
+                if let Some(coord) = coord {
+                    self.push(
+                        LintRule::OffGrid,
+                        span.clone(),
+                        format!("{shape} has {coord}, off the manufacturing grid of {grid}"),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Formats a constraint as its source and location, e.g. `` `w=100.` at 4:30 ``.
+    fn describe_constraint(&self, constraint: &CellConstraint) -> Option<String> {
+        let span = &constraint.span;
+        let file = self.ast.values().find(|file| file.path == span.path)?;
+        let source = file.text[span.span.start()..span.span.end()]
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim();
+        let (line, col) = line_col(&file.text, span.span.start());
+        let fallback = if constraint.fallback {
+            " (fallback)"
+        } else {
+            ""
+        };
+        Some(format!("`{source}` at {line}:{col}{fallback}"))
+    }
+
+    /// Returns the lints found so far, ordered by location.
+    pub fn finish(mut self) -> Vec<Lint> {
+        self.lints.sort_by(|a, b| {
+            (&a.span.path, a.span.span.start()).cmp(&(&b.span.path, b.span.span.start()))
+        });
+        self.lints
+    }
+}
+
+/// Maximum number of constraints named in a [`LintRule::MinWidth`] lint.
+const MAX_CHAIN_LEN: usize = 3;
+
+/// Returns the consistent constraints of `cell` that relate the variables of
+/// `exprs`, directly or through other variables, nearest first.
+fn constraint_chain<'a>(cell: &'a CompiledCell, exprs: &[&LinearExpr]) -> Vec<&'a CellConstraint> {
+    let mut vars = exprs
+        .iter()
+        .flat_map(|expr| expr.coeffs.iter().map(|(_, var)| *var))
+        .collect::<IndexSet<_>>();
+    let mut chain = IndexSet::new();
+    let mut i = 0;
+    while let Some(var) = vars.get_index(i).copied() {
+        for (id, constraint) in &cell.constraints {
+            if !cell.inconsistent_constraints.contains(id)
+                && constraint.vars.contains(&var)
+                && chain.insert(*id)
+            {
+                vars.extend(constraint.vars.iter().copied());
+            }
+        }
+        i += 1;
+    }
+    chain.iter().map(|id| &cell.constraints[id]).collect()
+}
+
+/// Returns the bounding box of the exported geometry of `cell` on each layer,
+/// caching the result in `cache`.
+fn exported_layer_bboxes(
+    data: &CompiledData,
+    cell: CellId,
+    cache: &mut HashMap<CellId, IndexMap<String, Bbox>>,
+) -> IndexMap<String, Bbox> {
+    if let Some(bboxes) = cache.get(&cell) {
+        return bboxes.clone();
+    }
+    let mut bboxes: IndexMap<String, Bbox> = IndexMap::new();
+    for obj in data.emitted(cell) {
+        if let Some(rect) = obj.get_rect()
+            && !rect.construction
+            && let Some(layer) = &rect.layer
+        {
+            let bbox = rect.to_float().bbox();
+            let entry = bboxes.entry(layer.clone()).or_insert(bbox);
+            *entry = Bbox::union(Some(*entry), Some(bbox)).unwrap();
+        } else if let Some(inst) = obj.instance()
+            && !inst.construction
+        {
+            let layers = exported_layer_bboxes(data, inst.cell, cache);
+            for xform in obj.instance_transforms() {
+                for (layer, bbox) in &layers {
+                    let bbox = bbox.transform(&xform);
+                    let entry = bboxes.entry(layer.clone()).or_insert(bbox);
+                    *entry = Bbox::union(Some(*entry), Some(bbox)).unwrap();
+                }
+            }
+        }
+    }
+    cache.insert(cell, bboxes.clone());
+    bboxes
+}
+
+/// A variable in scope while walking a cell or function body.
+struct Binding {
+    name: Substr,
+    /// Span of the name of a `let` binding; `None` for arguments and loop
+    /// variables, which are not linted.
+    span: Option<cfgrammar::Span>,
+    used: bool,
+}
+
+/// Collects the literals, calls, variables, and enum variants in the cells and
+/// functions of a file.
+struct SourceLints<'a> {
+    /// Numeric constants of the file and their values.
+    consts: Vec<(String, f64)>,
+    /// Literals that equal a constant, with the constant's name.
+    literals: Vec<(cfgrammar::Span, String)>,
+    /// Names of the functions and cells called anywhere in the workspace.
+    called: &'a mut IndexSet<String>,
+    /// Variables in scope, innermost scope last.
+    bindings: Vec<Vec<Binding>>,
+    /// `let` bindings that went out of scope without being used.
+    unused: Vec<(cfgrammar::Span, String)>,
+    /// Enum variants referenced anywhere in the workspace, keyed by enum and
+    /// variant name. A variant of `None` means all variants of the enum were
+    /// enumerated.
+    variants: &'a mut IndexSet<(String, Option<String>)>,
+}
+
+impl SourceLints<'_> {
+    /// Walks the body of a cell or function with the given arguments in scope.
+    fn body(
+        &mut self,
+        args: &[ArgDecl<Substr, ParseMetadata>],
+        scope: &Scope<Substr, ParseMetadata>,
+    ) {
+        self.bindings.push(
+            args.iter()
+                .map(|arg| Binding {
+                    name: arg.name.name.clone(),
+                    span: None,
+                    used: false,
+                })
+                .collect(),
+        );
+        self.scope(scope);
+        self.bindings.pop();
+    }
+
+    fn bind(&mut self, name: &Substr, span: Option<cfgrammar::Span>) {
+        let span = span.filter(|_| !name.starts_with('_'));
+        self.bindings.last_mut().unwrap().push(Binding {
+            name: name.clone(),
+            span,
+            used: false,
+        });
+    }
+
+    fn scope(&mut self, scope: &Scope<Substr, ParseMetadata>) {
+        self.bindings.push(Vec::new());
+        for stmt in &scope.stmts {
+            match stmt {
+                Statement::Expr { value, .. } => self.expr(value),
+                Statement::LetBinding(binding) => {
+                    self.expr(&binding.value);
+                    self.bind(&binding.name.name, Some(binding.name.span));
+                }
+                Statement::ForLoop(for_loop) => {
+                    self.expr(&for_loop.seq);
+                    self.bindings.push(Vec::new());
+                    self.bind(&for_loop.var.name, None);
+                    self.scope(&for_loop.body);
+                    self.bindings.pop();
+                }
+            }
+        }
+        if let Some(tail) = &scope.tail {
+            self.expr(tail);
+        }
+        for binding in self.bindings.pop().unwrap() {
+            if let (Some(span), false) = (binding.span, binding.used) {
+                self.unused.push((span, binding.name.to_string()));
+            }
+        }
+    }
+
+    /// Marks the innermost variable named `name` as used.
+    fn use_var(&mut self, name: &str) {
+        if let Some(binding) = self
+            .bindings
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name)
+        {
+            binding.used = true;
+        }
+    }
+
+    fn literal(&mut self, span: cfgrammar::Span, value: f64) {
+        if TRIVIAL_LITERALS.contains(&value.abs()) {
+            return;
+        }
+        if let Some((name, _)) = self.consts.iter().find(|(_, c)| c.abs() == value.abs()) {
+            self.literals.push((span, name.clone()));
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<Substr, ParseMetadata>) {
+        match expr {
+            Expr::If(e) => {
+                self.expr(&e.cond);
+                self.scope(&e.then);
+                self.scope(&e.else_);
+            }
+            Expr::Match(e) => {
+                self.expr(&e.scrutinee);
+                for arm in &e.arms {
+                    self.expr(&arm.expr);
+                }
+            }
+            Expr::Comparison(e) => {
+                self.expr(&e.left);
+                self.expr(&e.right);
+            }
+            Expr::BinOp(e) => {
+                self.expr(&e.left);
+                self.expr(&e.right);
+            }
+            Expr::UnaryOp(e) => self.expr(&e.operand),
+            Expr::Call(call) => {
+                if let Some(name) = call.func.path.last() {
+                    self.called.insert(name.name.to_string());
+                }
+                if let [.., e, f] = call.func.path.as_slice()
+                    && f.name == "variants"
+                {
+                    self.variants.insert((e.name.to_string(), None));
+                }
+                for arg in &call.args.posargs {
+                    self.expr(arg);
+                }
+                for kwarg in &call.args.kwargs {
+                    self.expr(&kwarg.value);
+                }
+            }
+            Expr::Emit(e) => self.expr(&e.value),
+            Expr::FieldAccess(e) => self.expr(&e.base),
+            Expr::IndexFieldAccess(e) => self.expr(&e.base),
+            Expr::Index(e) => {
+                self.expr(&e.base);
+                self.expr(&e.index);
+            }
+            Expr::Scope(scope) => self.scope(scope),
+            Expr::Cast(e) => self.expr(&e.value),
+            Expr::Tuple(e) => {
+                for item in &e.items {
+                    self.expr(item);
+                }
+            }
+            Expr::Range(e) => {
+                self.expr(&e.start);
+                self.expr(&e.end);
+            }
+            Expr::Seq(e) => {
+                for item in &e.items {
+                    self.expr(item);
+                }
+            }
+            Expr::FloatLiteral(lit) => self.literal(lit.span, lit.value),
+            Expr::IntLiteral(lit) => self.literal(lit.span, lit.value as f64),
+            Expr::IdentPath(path) => match path.path.as_slice() {
+                [var] => self.use_var(&var.name),
+                [.., e, v] => {
+                    self.variants
+                        .insert((e.name.to_string(), Some(v.name.to_string())));
+                }
+                [] => {}
+            },
+            Expr::Nil(_) | Expr::SeqNil(_) | Expr::StringLiteral(_) | Expr::BoolLiteral(_) => {}
+        }
+    }
+}
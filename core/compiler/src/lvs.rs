@@ -0,0 +1,319 @@
+//! Pre-LVS consistency checks against a reference SPICE netlist.
+//!
+//! Connectivity is extracted from the flattened rects of a cell using the
+//! layer connections in `Argon.toml`, and nets are named by the text labels
+//! placed in the top cell. Devices are the top cell's instances, identified
+//! by the name of the cell they instantiate.
+use std::fmt::{self, Display};
+
+use anyhow::{Result, anyhow, bail};
+use argon_geometry::Bbox;
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{
+    compile::{CompiledData, SolvedValue},
+    config::Connect,
+};
+
+/// A subcircuit in a SPICE netlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiceSubckt {
+    pub name: String,
+    pub ports: Vec<String>,
+    pub devices: Vec<SpiceDevice>,
+}
+
+/// A device instantiated in a [`SpiceSubckt`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiceDevice {
+    pub name: String,
+    /// Subcircuit or model name for `X` and `M` devices, or the device letter otherwise.
+    pub kind: String,
+}
+
+/// Parses the subcircuits in a SPICE netlist.
+///
+/// Names are lowercased since SPICE is case-insensitive. Only subcircuit
+/// definitions and the names and kinds of their devices are extracted.
+pub fn parse_spice(text: &str) -> Result<Vec<SpiceSubckt>> {
+    // Join continuation lines and drop comments.
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_once([';', '$']).map_or(line, |(line, _)| line);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('+') {
+            let prev = lines
+                .last_mut()
+                .ok_or_else(|| anyhow!("continuation line with nothing to continue"))?;
+            prev.push(' ');
+            prev.push_str(rest);
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+
+    let mut subckts = Vec::new();
+    let mut current: Option<SpiceSubckt> = None;
+    for line in lines {
+        let line = line.to_lowercase();
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens[0] {
+            ".subckt" => {
+                if current.is_some() {
+                    bail!("nested subcircuit definitions are not supported");
+                }
+                let name = tokens
+                    .get(1)
+                    .ok_or_else(|| anyhow!("subcircuit definition without a name"))?;
+                current = Some(SpiceSubckt {
+                    name: name.to_string(),
+                    ports: tokens[2..]
+                        .iter()
+                        .take_while(|token| !token.contains('='))
+                        .map(|token| token.to_string())
+                        .collect(),
+                    devices: Vec::new(),
+                });
+            }
+            ".ends" => {
+                subckts.push(
+                    current
+                        .take()
+                        .ok_or_else(|| anyhow!("`.ends` without a matching `.subckt`"))?,
+                );
+            }
+            name if name.starts_with('.') => {}
+            name => {
+                let Some(subckt) = current.as_mut() else {
+                    continue;
+                };
+                let args = tokens[1..]
+                    .iter()
+                    .take_while(|token| !token.contains('='))
+                    .collect::<Vec<_>>();
+                let kind = match name.as_bytes()[0] {
+                    b'x' => args.last().map(|kind| kind.to_string()),
+                    b'm' => args.get(4).map(|kind| kind.to_string()),
+                    letter => Some((letter as char).to_string()),
+                }
+                .ok_or_else(|| anyhow!("device `{name}` is missing its model"))?;
+                subckt.devices.push(SpiceDevice {
+                    name: name.to_string(),
+                    kind,
+                });
+            }
+        }
+    }
+    if let Some(subckt) = current {
+        bail!("subcircuit `{}` is missing `.ends`", subckt.name);
+    }
+    Ok(subckts)
+}
+
+/// Differences between a layout and its reference netlist.
+///
+/// Locations are in the coordinates of the checked cell.
+#[derive(Debug, Clone, Default)]
+pub struct LvsReport {
+    /// Device kinds that the layout has fewer of than the netlist, with the
+    /// number of missing devices.
+    pub missing_devices: Vec<(String, usize)>,
+    /// Layout instances in excess of the netlist's devices of the same kind.
+    pub extra_devices: Vec<ExtraDevice>,
+    /// Nets connecting more than one distinct label.
+    pub shorts: Vec<Short>,
+    /// Labels that appear on more than one net, or on no shape at all.
+    pub opens: Vec<Open>,
+    /// Netlist ports without a label in the layout.
+    pub missing_ports: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtraDevice {
+    pub kind: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Short {
+    pub labels: Vec<String>,
+    /// Bounding box of the shorted net.
+    pub bbox: Bbox,
+}
+
+#[derive(Debug, Clone)]
+pub struct Open {
+    pub label: String,
+    /// Positions of the label on each disconnected piece.
+    pub locations: Vec<(f64, f64)>,
+}
+
+impl LvsReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing_devices.is_empty()
+            && self.extra_devices.is_empty()
+            && self.shorts.is_empty()
+            && self.opens.is_empty()
+            && self.missing_ports.is_empty()
+    }
+}
+
+impl Display for LvsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return writeln!(f, "layout matches netlist");
+        }
+        for (kind, count) in &self.missing_devices {
+            writeln!(f, "missing {count} device(s) of kind `{kind}`")?;
+        }
+        for device in &self.extra_devices {
+            writeln!(
+                f,
+                "extra device of kind `{}` at ({}, {})",
+                device.kind, device.x, device.y
+            )?;
+        }
+        for short in &self.shorts {
+            writeln!(
+                f,
+                "short between {} within ({}, {}) to ({}, {})",
+                short
+                    .labels
+                    .iter()
+                    .map(|label| format!("`{label}`"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                short.bbox.x0,
+                short.bbox.y0,
+                short.bbox.x1,
+                short.bbox.y1
+            )?;
+        }
+        for open in &self.opens {
+            if open.locations.len() == 1 {
+                let (x, y) = open.locations[0];
+                writeln!(
+                    f,
+                    "label `{}` at ({x}, {y}) is not on any shape",
+                    open.label
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "open on `{}` between {}",
+                    open.label,
+                    open.locations
+                        .iter()
+                        .map(|(x, y)| format!("({x}, {y})"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )?;
+            }
+        }
+        for port in &self.missing_ports {
+            writeln!(f, "no label for port `{port}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl CompiledData {
+    /// Compares the top cell against `reference`, extracting nets with
+    /// [`CompiledData::connectivity`].
+    pub fn check_netlist(&self, reference: &SpiceSubckt, connect: &[Connect]) -> LvsReport {
+        let mut report = LvsReport::default();
+        let top = &self.cells[&self.top];
+
+        // Devices.
+        let mut devices: IndexMap<String, Vec<(f64, f64)>> = IndexMap::new();
+        for obj in self.emitted(self.top) {
+            if let Some(inst) = obj.instance()
+                && !inst.construction
+            {
+                let kind = self.cells[&inst.cell].name.to_lowercase();
+                devices
+                    .entry(kind)
+                    .or_default()
+                    .extend(obj.instance_transforms().iter().map(|xform| xform.offset));
+            }
+        }
+        let mut expected: IndexMap<&str, usize> = IndexMap::new();
+        for device in &reference.devices {
+            *expected.entry(device.kind.as_str()).or_default() += 1;
+        }
+        for (kind, count) in &expected {
+            let found = devices.get(*kind).map_or(0, Vec::len);
+            if found < *count {
+                report
+                    .missing_devices
+                    .push((kind.to_string(), count - found));
+            }
+        }
+        for (kind, locations) in &devices {
+            let count = expected.get(kind.as_str()).copied().unwrap_or_default();
+            for &(x, y) in locations.iter().skip(count) {
+                report.extra_devices.push(ExtraDevice {
+                    kind: kind.clone(),
+                    x,
+                    y,
+                });
+            }
+        }
+
+        // Connectivity.
+        let nets = self.connectivity(connect);
+        let mut label_nets: IndexMap<&str, IndexMap<usize, (f64, f64)>> = IndexMap::new();
+        for (i, net) in nets.nets.iter().enumerate() {
+            let names = net.names();
+            if names.len() > 1 {
+                report.shorts.push(Short {
+                    labels: names.iter().map(|label| label.to_string()).collect(),
+                    bbox: net.bbox,
+                });
+            }
+            for label in &net.labels {
+                label_nets
+                    .entry(&label.text)
+                    .or_default()
+                    .entry(i)
+                    .or_insert((label.x, label.y));
+            }
+        }
+        for label in &nets.unattached {
+            report.opens.push(Open {
+                label: label.text.clone(),
+                locations: vec![(label.x, label.y)],
+            });
+        }
+        for (label, nets) in &label_nets {
+            if nets.len() > 1 {
+                report.opens.push(Open {
+                    label: label.to_string(),
+                    locations: nets.values().copied().collect(),
+                });
+            }
+        }
+
+        // Ports.
+        let labels = top
+            .objects
+            .values()
+            .filter_map(|obj| match obj {
+                SolvedValue::Text(text) => Some(text.text.to_lowercase()),
+                _ => None,
+            })
+            .collect::<IndexSet<_>>();
+        report.missing_ports = reference
+            .ports
+            .iter()
+            .filter(|port| !labels.contains(*port))
+            .cloned()
+            .collect();
+
+        report
+    }
+}
@@ -0,0 +1,470 @@
+//! Net extraction from connected geometry.
+//!
+//! Nets are named by the text labels placed on them in the top cell, which
+//! serve as the cell's ports. The ports of the top cell's instances are
+//! attached to the nets they sit on, which gives the SPICE netlist written by
+//! [`CompiledData::to_spice_string`].
+use std::{
+    fmt::{self, Display, Write},
+    path::Path,
+};
+
+use anyhow::{Result, anyhow};
+use argon_geometry::Bbox;
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use uniquify::Names;
+
+use crate::{
+    compile::{CompileOutput, CompiledData, ExecErrorCompileOutput, ObjectId, SolvedValue},
+    config::Connect,
+};
+
+/// A conducting shape in the flattened top cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetShape {
+    pub layer: String,
+    pub bbox: Bbox,
+    /// IDs of the instances leading to the rect, followed by the rect's own ID.
+    pub path: Vec<ObjectId>,
+    /// Index into [`Nets::nets`].
+    pub net: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetLabel {
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A port of the top cell or of one of its instances.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetPin {
+    pub name: String,
+    /// ID of the top-level instance the port belongs to and its index within
+    /// an instance array, or `None` for a port of the top cell itself.
+    pub instance: Option<(ObjectId, Option<usize>)>,
+    /// Position of the port in the top cell's coordinates.
+    pub x: f64,
+    pub y: f64,
+    /// Index into [`Nets::nets`], if the port sits on a conducting shape.
+    pub net: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Net {
+    /// Text of the first label on the net, if any.
+    pub name: Option<String>,
+    pub labels: Vec<NetLabel>,
+    /// Indices into [`Nets::shapes`].
+    pub shapes: Vec<usize>,
+    /// Indices into [`Nets::pins`].
+    pub pins: Vec<usize>,
+    pub bbox: Bbox,
+}
+
+impl Net {
+    /// Distinct label texts on the net, in order of appearance.
+    pub fn names(&self) -> IndexSet<&str> {
+        self.labels
+            .iter()
+            .map(|label| label.text.as_str())
+            .collect()
+    }
+}
+
+/// Connectivity of the top cell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Nets {
+    pub shapes: Vec<NetShape>,
+    pub nets: Vec<Net>,
+    /// Labels that do not sit on a conducting shape.
+    pub unattached: Vec<NetLabel>,
+    /// Ports of the top cell and of its top-level instances.
+    pub pins: Vec<NetPin>,
+}
+
+/// A likely connectivity mistake found by [`Nets::warnings`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NetWarning {
+    /// A net without a label.
+    Floating { net: usize, bbox: Bbox },
+    /// A net with more than one distinct label.
+    Merged {
+        net: usize,
+        names: Vec<String>,
+        bbox: Bbox,
+    },
+    /// A label that does not sit on a conducting shape.
+    Unattached(NetLabel),
+}
+
+impl Display for NetWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetWarning::Floating { bbox, .. } => write!(
+                f,
+                "floating shapes within ({}, {}) to ({}, {})",
+                bbox.x0, bbox.y0, bbox.x1, bbox.y1
+            ),
+            NetWarning::Merged { names, bbox, .. } => write!(
+                f,
+                "nets {} are merged within ({}, {}) to ({}, {})",
+                names
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                bbox.x0,
+                bbox.y0,
+                bbox.x1,
+                bbox.y1
+            ),
+            NetWarning::Unattached(label) => write!(
+                f,
+                "label `{}` at ({}, {}) is not on any shape",
+                label.text, label.x, label.y
+            ),
+        }
+    }
+}
+
+impl Nets {
+    /// Returns the net containing the shape at `path`, if it is conducting.
+    pub fn net_of(&self, path: &[ObjectId]) -> Option<usize> {
+        self.shapes
+            .iter()
+            .find(|shape| shape.path == path)
+            .map(|shape| shape.net)
+    }
+
+    /// Returns the net of the first conducting shape under a label or port on
+    /// `layer` at `(x, y)`.
+    fn net_at(&self, layer: &str, x: f64, y: f64) -> Option<usize> {
+        self.shapes
+            .iter()
+            .find(|shape| {
+                labels_layer(layer, &shape.layer) && touches(&shape.bbox, &Bbox::point((x, y)))
+            })
+            .map(|shape| shape.net)
+    }
+
+    pub fn warnings(&self) -> Vec<NetWarning> {
+        let mut warnings = Vec::new();
+        for (i, net) in self.nets.iter().enumerate() {
+            let names = net.names();
+            if names.is_empty() {
+                warnings.push(NetWarning::Floating {
+                    net: i,
+                    bbox: net.bbox,
+                });
+            } else if names.len() > 1 {
+                warnings.push(NetWarning::Merged {
+                    net: i,
+                    names: names.into_iter().map(str::to_string).collect(),
+                    bbox: net.bbox,
+                });
+            }
+        }
+        warnings.extend(self.unattached.iter().cloned().map(NetWarning::Unattached));
+        warnings
+    }
+}
+
+/// Returns the index of the set containing `i`, compressing paths along the way.
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+fn touches(a: &Bbox, b: &Bbox) -> bool {
+    a.x0 <= b.x1 && b.x0 <= a.x1 && a.y0 <= b.y1 && b.y0 <= a.y1
+}
+
+fn overlaps(a: &Bbox, b: &Bbox) -> bool {
+    a.x0 < b.x1 && b.x0 < a.x1 && a.y0 < b.y1 && b.y0 < a.y1
+}
+
+/// Returns whether a label on `label` names shapes on the conducting layer `layer`,
+/// e.g. `met1.label` on `met1.drawing`.
+fn labels_layer(label: &str, layer: &str) -> bool {
+    let base = |name: &str| name.split_once('.').map_or(name, |(base, _)| base);
+    label == layer || base(label) == base(layer)
+}
+
+impl CompileOutput {
+    /// Runs [`CompiledData::extract_nets`] on the compiled cell, if any.
+    pub fn extract_nets(&mut self, connect: &[Connect]) {
+        if let CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        }) = self
+        {
+            output.extract_nets(connect);
+        }
+    }
+}
+
+impl CompiledData {
+    /// Stores the connectivity of the top cell in [`CompiledData::nets`].
+    ///
+    /// Does nothing if `connect` is empty.
+    pub fn extract_nets(&mut self, connect: &[Connect]) {
+        self.nets = (!connect.is_empty()).then(|| self.connectivity(connect));
+    }
+
+    /// Extracts the connectivity of the top cell.
+    ///
+    /// Only shapes on layers mentioned in `connect` conduct. Shapes on the
+    /// same layer are connected if they touch, and shapes on layers related by
+    /// `connect` are connected if they overlap.
+    pub fn connectivity(&self, connect: &[Connect]) -> Nets {
+        let mut pairs: IndexSet<(&str, &str)> = IndexSet::new();
+        for c in connect {
+            match &c.via {
+                Some(via) => {
+                    pairs.insert((c.top.as_str(), via.as_str()));
+                    pairs.insert((via.as_str(), c.bot.as_str()));
+                }
+                None => {
+                    pairs.insert((c.top.as_str(), c.bot.as_str()));
+                }
+            }
+        }
+        let connected = |a: &str, b: &str| pairs.contains(&(a, b)) || pairs.contains(&(b, a));
+        let flat = self
+            .iter_flat(self.top, None)
+            .filter_map(|rect| {
+                let layer = rect.layer?;
+                pairs
+                    .iter()
+                    .any(|(a, b)| *a == layer || *b == layer)
+                    .then(|| (layer, rect.rect.bbox(), rect.path))
+            })
+            .collect::<Vec<_>>();
+        let mut parents = (0..flat.len()).collect::<Vec<_>>();
+        // Quadratic in the number of shapes, which is fine for the cell sizes checked here.
+        for i in 0..flat.len() {
+            for j in (i + 1)..flat.len() {
+                let ((la, a, _), (lb, b, _)) = (&flat[i], &flat[j]);
+                let joined = if la == lb {
+                    touches(a, b)
+                } else {
+                    connected(la, lb) && overlaps(a, b)
+                };
+                if joined {
+                    let (ri, rj) = (find(&mut parents, i), find(&mut parents, j));
+                    parents[ri] = rj;
+                }
+            }
+        }
+
+        // Number nets in order of their first shape.
+        let mut net_ids: IndexMap<usize, usize> = IndexMap::new();
+        let mut nets: Vec<Net> = Vec::new();
+        let mut shapes = Vec::with_capacity(flat.len());
+        for (i, (layer, bbox, path)) in flat.into_iter().enumerate() {
+            let root = find(&mut parents, i);
+            let net = *net_ids.entry(root).or_insert_with(|| {
+                nets.push(Net {
+                    name: None,
+                    labels: Vec::new(),
+                    shapes: Vec::new(),
+                    pins: Vec::new(),
+                    bbox,
+                });
+                nets.len() - 1
+            });
+            nets[net].shapes.push(i);
+            nets[net].bbox = Bbox::union(Some(nets[net].bbox), Some(bbox)).unwrap();
+            shapes.push(NetShape {
+                layer: layer.to_string(),
+                bbox,
+                path,
+                net,
+            });
+        }
+
+        let mut nets = Nets {
+            shapes,
+            nets,
+            unattached: Vec::new(),
+            pins: Vec::new(),
+        };
+        for obj in self.cells[&self.top].objects.values() {
+            let SolvedValue::Text(text) = obj else {
+                continue;
+            };
+            let label = NetLabel {
+                text: text.text.clone(),
+                x: text.x,
+                y: text.y,
+            };
+            match nets.net_at(&text.layer, text.x, text.y) {
+                Some(net) => {
+                    let net = &mut nets.nets[net];
+                    net.name.get_or_insert_with(|| label.text.clone());
+                    net.labels.push(label);
+                }
+                None => nets.unattached.push(label),
+            }
+        }
+
+        let mut pins = self.cells[&self.top]
+            .ports
+            .iter()
+            .map(|port| (port, None, port.x, port.y))
+            .collect::<Vec<_>>();
+        for obj in self.emitted(self.top) {
+            let Some(inst) = obj.instance().filter(|inst| !inst.construction) else {
+                continue;
+            };
+            let array = obj.is_instance_array();
+            for (i, xform) in obj.instance_transforms().into_iter().enumerate() {
+                for port in &self.cells[&inst.cell].ports {
+                    let (x, y) = xform.apply((port.x, port.y));
+                    pins.push((port, Some((inst.id, array.then_some(i))), x, y));
+                }
+            }
+        }
+        for (port, instance, x, y) in pins {
+            let net = nets.net_at(&port.layer, x, y);
+            if let Some(net) = net {
+                nets.nets[net].pins.push(nets.pins.len());
+            }
+            nets.pins.push(NetPin {
+                name: port.name.clone(),
+                instance,
+                x,
+                y,
+                net,
+            });
+        }
+
+        nets
+    }
+
+    /// Renders the connectivity of the top cell as a SPICE subcircuit.
+    ///
+    /// The subcircuit's ports are the top cell's ports, and each top-level
+    /// instance becomes an `X` device whose terminals are the nets its ports
+    /// sit on, in order of the ports' first appearance. Subcircuits are named
+    /// as in GDS export. Nets are named by their first label, or `net<n>` if
+    /// unlabeled, and ports that do not sit on a conducting shape get a net of
+    /// their own.
+    pub fn to_spice_string(&self, connect: &[Connect]) -> Result<String> {
+        let nets = self.connectivity(connect);
+        let masters = self.gds_cell_names(self.top);
+        let object_names = self.object_names(self.top);
+
+        let mut net_names = Names::new();
+        let mut node =
+            |key: NodeKey, name: &str| net_names.assign_name(key, &spice_name(name)).to_string();
+        let net_nodes = nets
+            .nets
+            .iter()
+            .enumerate()
+            .map(|(i, net)| match &net.name {
+                Some(name) => node(NodeKey::Net(i), name),
+                None => node(NodeKey::Net(i), &format!("net{i}")),
+            })
+            .collect::<Vec<_>>();
+        // Terminals of the top cell (`None`) and of each instance, keyed by port name.
+        let mut terminals: IndexMap<_, IndexMap<&str, _>> = IndexMap::new();
+        for (i, pin) in nets.pins.iter().enumerate() {
+            terminals
+                .entry(pin.instance)
+                .or_default()
+                .entry(pin.name.as_str())
+                .or_insert_with(|| match pin.net {
+                    Some(net) => net_nodes[net].clone(),
+                    None => node(NodeKey::Pin(i), &pin.name),
+                });
+        }
+
+        let design = &masters[&self.top];
+        let mut out = String::new();
+        writeln!(out, "* Extracted from the layout of `{design}`.")?;
+        write!(out, ".subckt {design}")?;
+        for terminal in terminals
+            .get(&None)
+            .into_iter()
+            .flat_map(|ports| ports.values())
+        {
+            write!(out, " {terminal}")?;
+        }
+        writeln!(out)?;
+        let mut device_names = Names::new();
+        for obj in self.emitted(self.top) {
+            let Some(inst) = obj.instance().filter(|inst| !inst.construction) else {
+                continue;
+            };
+            let id = inst.id;
+            let name = spice_name(object_names.get(&id).map_or("inst", String::as_str));
+            let array = obj.is_instance_array();
+            for i in 0..obj.instance_transforms().len() {
+                let index = array.then_some(i);
+                let name = match index {
+                    Some(i) => device_names.assign_name((id, index), &format!("x{name}_{i}")),
+                    None => device_names.assign_name((id, index), &format!("x{name}")),
+                };
+                write!(out, "{name}")?;
+                if let Some(ports) = terminals.get(&Some((id, index))) {
+                    for terminal in ports.values() {
+                        write!(out, " {terminal}")?;
+                    }
+                }
+                writeln!(out, " {}", masters[&inst.cell])?;
+            }
+        }
+        writeln!(out, ".ends {design}")?;
+        Ok(out)
+    }
+}
+
+/// Key of a node in [`CompiledData::to_spice_string`]: a net, or a port that
+/// does not sit on a conducting shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NodeKey {
+    Net(usize),
+    Pin(usize),
+}
+
+/// Makes `name` a valid SPICE node or device name by replacing whitespace and
+/// reserved characters.
+fn spice_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl CompileOutput {
+    /// Writes the connectivity of the compiled cell to a SPICE netlist at `out_path`.
+    pub fn to_spice(&self, connect: &[Connect], out_path: impl AsRef<Path>) -> Result<()> {
+        let out_path = out_path.as_ref();
+        let (CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        })) = self
+        else {
+            return Err(anyhow!("no compiled cell to export"));
+        };
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(out_path, output.to_spice_string(connect)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+//! OASIS output of GDS libraries.
+//!
+//! Layouts are always built as a [`GdsLibrary`] first, so both formats share
+//! cell naming, layer mapping, and snapping. The writer emits records in
+//! non-strict mode without name tables: cells and placements refer to cells
+//! by name, and every record spells out its fields rather than relying on
+//! modal variables.
+use std::{io::Write, path::Path};
+
+use ::gds::{GdsElement, GdsLibrary, GdsPoint, GdsStrans};
+use anyhow::{Result, bail};
+
+const MAGIC: &[u8] = b"%SEMI-OASIS\r\n";
+/// Length of the END record, including its padding.
+const END_RECORD_LEN: usize = 256;
+
+const START: u64 = 1;
+const END: u64 = 2;
+const CELL_NAMED: u64 = 14;
+const PLACEMENT: u64 = 17;
+const PLACEMENT_ARBITRARY: u64 = 18;
+const TEXT: u64 = 19;
+const RECTANGLE: u64 = 20;
+const POLYGON: u64 = 21;
+
+/// Point list type whose entries are arbitrary displacements.
+const POINT_LIST_ANY_ANGLE: u64 = 4;
+
+/// Writes OASIS primitives to a byte buffer.
+#[derive(Default)]
+struct OasisWriter {
+    buf: Vec<u8>,
+}
+
+impl OasisWriter {
+    fn unsigned(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.buf.push(byte);
+                return;
+            }
+            self.buf.push(byte | 0x80);
+        }
+    }
+
+    /// Writes a signed integer, which stores the sign in the lowest bit.
+    fn signed(&mut self, value: i64) {
+        self.unsigned((value.unsigned_abs() << 1) | (value < 0) as u64);
+    }
+
+    fn real(&mut self, value: f64) {
+        if value.fract() == 0. && value.abs() < u32::MAX as f64 {
+            self.unsigned(if value < 0. { 1 } else { 0 });
+            self.unsigned(value.abs() as u64);
+        } else {
+            self.unsigned(7);
+            self.buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.unsigned(value.len() as u64);
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+
+    fn layer(&mut self, layer: i16) -> Result<()> {
+        if layer < 0 {
+            bail!("negative layer or datatype {layer} cannot be written to OASIS");
+        }
+        self.unsigned(layer as u64);
+        Ok(())
+    }
+
+    /// Writes a displacement in the general form of a g-delta.
+    fn g_delta(&mut self, dx: i64, dy: i64) {
+        self.unsigned((dx.unsigned_abs() << 2) | (((dx < 0) as u64) << 1) | 1);
+        self.signed(dy);
+    }
+
+    fn xy(&mut self, pt: &GdsPoint) {
+        self.signed(pt.x as i64);
+        self.signed(pt.y as i64);
+    }
+
+    /// Writes a placement of the cell `name` at `xy`.
+    fn placement(&mut self, name: &str, strans: &Option<GdsStrans>, xy: &GdsPoint) -> Result<()> {
+        let (reflected, angle) = match strans {
+            Some(strans) => {
+                if strans.mag.is_some_and(|mag| mag != 1.) {
+                    bail!("magnified reference to `{name}` is not supported");
+                }
+                (strans.reflected, strans.angle.unwrap_or(0.))
+            }
+            None => (false, 0.),
+        };
+        let flip = reflected as u8;
+        let quarter_turns = angle / 90.;
+        if quarter_turns.fract() == 0. {
+            self.unsigned(PLACEMENT);
+            // Explicit cell name, x, y, rotation, and flip.
+            let rotation = (quarter_turns as i64).rem_euclid(4) as u8;
+            self.buf.push(0b1011_0000 | (rotation << 1) | flip);
+            self.string(name);
+        } else {
+            self.unsigned(PLACEMENT_ARBITRARY);
+            // Explicit cell name, x, y, angle, and flip.
+            self.buf.push(0b1011_0010 | flip);
+            self.string(name);
+            self.real(angle);
+        }
+        self.xy(xy);
+        Ok(())
+    }
+}
+
+/// Returns the corners of `xy` if it is an axis-aligned rectangle, closed or not.
+pub(crate) fn rectangle(xy: &[GdsPoint]) -> Option<(GdsPoint, GdsPoint)> {
+    let xy = match xy {
+        [first, rest @ ..] if rest.last() == Some(first) => &xy[..xy.len() - 1],
+        _ => xy,
+    };
+    if xy.len() != 4 {
+        return None;
+    }
+    let manhattan = (0..4).all(|i| {
+        let (a, b) = (&xy[i], &xy[(i + 1) % 4]);
+        (a.x == b.x) != (a.y == b.y)
+    });
+    manhattan.then(|| {
+        let (x0, x1) = (xy[0].x.min(xy[2].x), xy[0].x.max(xy[2].x));
+        let (y0, y1) = (xy[0].y.min(xy[2].y), xy[0].y.max(xy[2].y));
+        (GdsPoint::new(x0, y0), GdsPoint::new(x1, y1))
+    })
+}
+
+/// Encodes `lib` as an OASIS file.
+pub fn to_oasis_bytes(lib: &GdsLibrary) -> Result<Vec<u8>> {
+    let mut w = OasisWriter::default();
+    w.buf.extend_from_slice(MAGIC);
+    w.unsigned(START);
+    w.string("1.0");
+    // Database units per micron.
+    w.real(1e-6 / lib.units.db_unit());
+    // Table offsets follow in this record; all six tables are absent.
+    w.unsigned(0);
+    for _ in 0..12 {
+        w.unsigned(0);
+    }
+
+    for s in &lib.structs {
+        w.unsigned(CELL_NAMED);
+        w.string(&s.name);
+        for elem in &s.elems {
+            match elem {
+                GdsElement::GdsBoundary(boundary) => {
+                    if let Some((p0, p1)) = rectangle(&boundary.xy) {
+                        w.unsigned(RECTANGLE);
+                        // Width, height, x, y, datatype, and layer.
+                        w.buf.push(0b0111_1011);
+                        w.layer(boundary.layer)?;
+                        w.layer(boundary.datatype)?;
+                        w.unsigned((p1.x as i64 - p0.x as i64) as u64);
+                        w.unsigned((p1.y as i64 - p0.y as i64) as u64);
+                        w.xy(&p0);
+                        continue;
+                    }
+                    let mut xy = boundary.xy.as_slice();
+                    if xy.len() > 1 && xy.first() == xy.last() {
+                        xy = &xy[..xy.len() - 1];
+                    }
+                    if xy.len() < 3 {
+                        bail!(
+                            "boundary with {} vertices in `{}` cannot be written to OASIS",
+                            xy.len(),
+                            s.name
+                        );
+                    }
+                    w.unsigned(POLYGON);
+                    // Point list, x, y, datatype, and layer.
+                    w.buf.push(0b0011_1011);
+                    w.layer(boundary.layer)?;
+                    w.layer(boundary.datatype)?;
+                    // The first vertex is the polygon's position and the
+                    // closing edge is implicit.
+                    w.unsigned(POINT_LIST_ANY_ANGLE);
+                    w.unsigned(xy.len() as u64 - 1);
+                    for pair in xy.windows(2) {
+                        w.g_delta(
+                            pair[1].x as i64 - pair[0].x as i64,
+                            pair[1].y as i64 - pair[0].y as i64,
+                        );
+                    }
+                    w.xy(&xy[0]);
+                }
+                GdsElement::GdsTextElem(text) => {
+                    w.unsigned(TEXT);
+                    // Explicit text string, x, y, texttype, and text layer.
+                    w.buf.push(0b0101_1011);
+                    w.string(&text.string);
+                    w.layer(text.layer)?;
+                    w.layer(text.texttype)?;
+                    w.xy(&text.xy);
+                }
+                GdsElement::GdsStructRef(sref) => {
+                    w.placement(&sref.name, &sref.strans, &sref.xy)?;
+                }
+                GdsElement::GdsArrayRef(aref) => {
+                    // Arrays are written as one placement per element.
+                    let [origin, col, row] = &aref.xy;
+                    let (cols, rows) = (aref.cols.max(1) as i64, aref.rows.max(1) as i64);
+                    let step = |end: i32, start: i32, n: i64| (end as i64 - start as i64) / n;
+                    let col = (step(col.x, origin.x, cols), step(col.y, origin.y, cols));
+                    let row = (step(row.x, origin.x, rows), step(row.y, origin.y, rows));
+                    for j in 0..rows {
+                        for i in 0..cols {
+                            let xy = GdsPoint::new(
+                                (origin.x as i64 + i * col.0 + j * row.0) as i32,
+                                (origin.y as i64 + i * col.1 + j * row.1) as i32,
+                            );
+                            w.placement(&aref.name, &aref.strans, &xy)?;
+                        }
+                    }
+                }
+                _ => bail!("unsupported element in `{}`", s.name),
+            }
+        }
+    }
+
+    w.unsigned(END);
+    // Pad the record to its fixed length, leaving room for the length of the
+    // padding string and the validation scheme.
+    let padding = END_RECORD_LEN - 4;
+    w.unsigned(padding as u64);
+    w.buf.extend(std::iter::repeat_n(0, padding));
+    // No validation.
+    w.unsigned(0);
+    Ok(w.buf)
+}
+
+/// Writes `lib` to an OASIS file at `path`.
+pub fn save_oasis(lib: &GdsLibrary, path: impl AsRef<Path>) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(&to_oasis_bytes(lib)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_encodings() {
+        let mut w = OasisWriter::default();
+        w.unsigned(127);
+        w.unsigned(128);
+        w.signed(-3);
+        w.signed(64);
+        assert_eq!(w.buf, [0x7f, 0x80, 0x01, 0x07, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn end_record_is_padded() {
+        let lib = GdsLibrary::new("TOP");
+        let bytes = to_oasis_bytes(&lib).unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        let end = &bytes[bytes.len() - END_RECORD_LEN..];
+        assert_eq!(end[0], END as u8);
+        assert_eq!(end[END_RECORD_LEN - 1], 0);
+    }
+}
@@ -0,0 +1,96 @@
+//! Overrides of the parameters of cell invocations.
+//!
+//! The language server keeps a set of parameter values per cell that replace
+//! the arguments of the open cell's invocation whenever it is compiled, so a
+//! cell can be checked at other parameter values without editing the
+//! invocation. The CLI similarly adds parameters passed with `--param` to the
+//! invocations it is given.
+use anyhow::bail;
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+use crate::{
+    invocation::Invocation,
+    parse::{WorkspaceParseAst, parse_cell},
+};
+
+/// Returns the name of the cell generator called by `cell`, e.g. `inv` for
+/// `inv(1200., 4)`.
+pub fn invocation_name(cell: &str) -> anyhow::Result<String> {
+    Ok(parse_cell(cell)?
+        .func
+        .path
+        .iter()
+        .map(|ident| ident.name)
+        .join("::"))
+}
+
+/// Returns `cell` with the arguments named in `overrides` replaced by the
+/// given values, e.g. `inv(1200.0, 6)` for `inv(1200., 4)` with `nf` set to `6`.
+///
+/// Parameter names are looked up in the declaration of the cell in `ast`. Each
+/// value must be a literal of the type of the parameter it overrides.
+pub fn override_invocation(
+    ast: &WorkspaceParseAst,
+    cell: &str,
+    overrides: &IndexMap<String, String>,
+) -> anyhow::Result<String> {
+    if overrides.is_empty() {
+        return Ok(cell.to_string());
+    }
+    let invocation = Invocation::resolve(ast, cell)?;
+    let name = invocation.name();
+    if let Some(param) = overrides
+        .keys()
+        .find(|param| !invocation.args.contains_key(*param))
+    {
+        bail!("cell `{name}` has no parameter `{param}`");
+    }
+    let args = invocation
+        .args
+        .iter()
+        .map(|(param, arg)| match overrides.get(param) {
+            Some(value) => format!("{param}={value}"),
+            None => format!("{param}={arg}"),
+        })
+        .join(", ");
+    Ok(Invocation::resolve(ast, &format!("{name}({args})"))?.to_string())
+}
+
+/// Returns `cell` with `params` passed as named arguments, e.g.
+/// `inv(1200.0, 4)` for `inv(1200.)` with `nf` set to `4`.
+///
+/// A cell name without an argument list, such as `inv`, is treated as
+/// `inv()`, so every argument may come from `params`. A parameter may not be
+/// passed both in `cell` and in `params`.
+pub fn invocation_with_params(
+    ast: &WorkspaceParseAst,
+    cell: &str,
+    params: &IndexMap<String, String>,
+) -> anyhow::Result<String> {
+    if params.is_empty() {
+        return Ok(cell.to_string());
+    }
+    let cell = cell.trim();
+    let cell = if cell.contains('(') {
+        cell.to_string()
+    } else {
+        format!("{cell}()")
+    };
+    let call = parse_cell(&cell)?;
+    let args = call
+        .args
+        .posargs
+        .iter()
+        .map(|arg| arg.span())
+        .chain(call.args.kwargs.iter().map(|kwarg| kwarg.span))
+        .map(|span| cell[span.start()..span.end()].to_string())
+        .chain(
+            params
+                .iter()
+                .map(|(param, value)| format!("{param}={value}")),
+        )
+        .join(", ");
+    let name = invocation_name(&cell)?;
+    Ok(Invocation::resolve(ast, &format!("{name}({args})"))?.to_string())
+}
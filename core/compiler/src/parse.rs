@@ -1,5 +1,6 @@
 use std::{
     fmt::Write,
+    io,
     path::{Path, PathBuf},
 };
 
@@ -8,9 +9,12 @@ use arcstr::{ArcStr, Substr};
 use indexmap::IndexMap;
 
 use crate::{
-    ast::{Ast, AstMetadata, CallExpr, Decl, ModPath, Span, WorkspaceAst, annotated::AnnotatedAst},
+    ast::{
+        Ast, AstMetadata, CallExpr, Decl, Expr, ModPath, Span, WorkspaceAst,
+        annotated::AnnotatedAst,
+    },
     compile::{StaticError, StaticErrorKind},
-    config::parse_config,
+    config::parse_config_str,
     parser::ParseError,
 };
 
@@ -45,10 +49,88 @@ impl AstMetadata for ParseMetadata {
     type Typ = ();
     type FnDecl = ();
     type CastExpr = ();
+    type RangeExpr = ();
+    type SeqExpr = ();
     type TupleExpr = ();
 }
 
+/// Source of the files read while parsing a workspace.
+///
+/// [`OsFiles`] reads from disk, and [`MemoryFiles`] serves sources held in
+/// memory, e.g. in a browser playground without a filesystem.
+pub trait FileProvider {
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+    fn is_file(&self, path: &Path) -> bool;
+}
+
+/// Reads files from disk.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFiles;
+
+#[cfg(feature = "fs")]
+impl FileProvider for OsFiles {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// Files held in memory, keyed by path.
+///
+/// The standard library is served at [`STD_LIB_PATH`] unless replaced.
+#[derive(Debug, Clone)]
+pub struct MemoryFiles {
+    files: IndexMap<PathBuf, String>,
+}
+
+impl MemoryFiles {
+    pub fn new() -> Self {
+        let mut files = IndexMap::new();
+        files.insert(PathBuf::from(STD_LIB_PATH), STD_LIB_SOURCE.to_string());
+        Self { files }
+    }
+
+    /// Adds or replaces the file at `path`.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) {
+        self.files.insert(path.into(), text.into());
+    }
+}
+
+impl Default for MemoryFiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileProvider for MemoryFiles {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no file at {path:?}")))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+}
+
+#[cfg(feature = "fs")]
 pub fn get_mod(root_lib: impl AsRef<Path>, path: &ModPath) -> Result<PathBuf, anyhow::Error> {
+    get_mod_in(root_lib, path, &OsFiles)
+}
+
+/// Returns the file declaring the module at `path` relative to `root_lib`,
+/// looking files up in `files`.
+pub fn get_mod_in(
+    root_lib: impl AsRef<Path>,
+    path: &ModPath,
+    files: &impl FileProvider,
+) -> Result<PathBuf, anyhow::Error> {
     let root_lib = root_lib.as_ref();
     let Some(last) = path.last() else {
         return Ok(PathBuf::from(root_lib));
@@ -62,13 +144,13 @@ pub fn get_mod(root_lib: impl AsRef<Path>, path: &ModPath) -> Result<PathBuf, an
     direct_path.push(format!("{last}.ar"));
     base_path.push(last);
     base_path.push("mod.ar");
-    if direct_path.is_file() && base_path.is_file() {
+    if files.is_file(&direct_path) && files.is_file(&base_path) {
         bail!("both mod paths exists for mod {last}");
     }
     if direct_path == root_lib {
         bail!("circular mods: {last}");
     }
-    if direct_path.is_file() {
+    if files.is_file(&direct_path) {
         Ok(direct_path)
     } else {
         Ok(base_path)
@@ -147,8 +229,7 @@ fn diagnostics_from_errors(errs: Vec<ParseError>) -> ParseDiagnostics {
 }
 
 fn parse_result_from_errors(
-    input: ArcStr,
-    path: PathBuf,
+    ast: AnnotatedParseAst,
     diagnostics: Vec<ParseDiagnostic>,
 ) -> (ParseResult, ParseDiagnostics) {
     if !diagnostics.is_empty() {
@@ -157,37 +238,55 @@ fn parse_result_from_errors(
             if let Err(write_err) = writeln!(&mut err, "{}", diagnostic.kind)
                 .with_context(|| "failed to write to string buffer")
             {
-                return (
-                    (make_backup_ast(input, path), Some(anyhow!("{write_err}"))),
-                    diagnostics,
-                );
+                return ((ast, Some(anyhow!("{write_err}"))), diagnostics);
             }
         }
         return (
-            (
-                make_backup_ast(input, path),
-                Some(anyhow!(err.trim_end().to_string())),
-            ),
+            (ast, Some(anyhow!(err.trim_end().to_string()))),
             diagnostics,
         );
     }
-    ((make_backup_ast(input, path), None), diagnostics)
+    ((ast, None), diagnostics)
 }
 
+/// Root file of the standard library, as of when the compiler was built.
+///
+/// Spans in the standard library refer to this path, which may not exist on
+/// machines other than the one the compiler was built on.
+pub const STD_LIB_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/std/lib.ar");
+
+/// Source of the standard library, as of when the compiler was built.
+pub const STD_LIB_SOURCE: &str = include_str!("std/lib.ar");
+
+#[cfg(feature = "fs")]
 pub fn parse_workspace_with_std(root_lib: impl AsRef<Path>) -> ParseOutput {
+    parse_workspace_with_std_in(root_lib, &OsFiles)
+}
+
+/// Parses the workspace rooted at `root_lib`, the modules listed in its
+/// `Argon.toml`, and the standard library, reading files from `files`.
+pub fn parse_workspace_with_std_in(
+    root_lib: impl AsRef<Path>,
+    files: &impl FileProvider,
+) -> ParseOutput {
     let root_lib = root_lib.as_ref();
     let mut ast = IndexMap::new();
     let mut err = IndexMap::new();
     let root_dir = root_lib.parent().unwrap();
-    if let Ok(config) = parse_config(root_dir.join("Argon.toml")) {
+    if let Ok(config) = files
+        .read_to_string(&root_dir.join("Argon.toml"))
+        .map_err(anyhow::Error::from)
+        .and_then(|text| parse_config_str(&text))
+    {
         for (name, mod_path) in config.mods {
-            let ParseOutput { asts, errs } = parse_workspace(
+            let ParseOutput { asts, errs } = parse_workspace_in(
                 if mod_path.is_relative() {
                     root_dir.join(mod_path)
                 } else {
                     mod_path
                 }
                 .join("lib.ar"),
+                files,
             );
             ast.extend(asts.into_iter().map(|(mut k, v)| {
                 k.insert(0, name.clone());
@@ -196,14 +295,13 @@ pub fn parse_workspace_with_std(root_lib: impl AsRef<Path>) -> ParseOutput {
             err.extend(errs);
         }
     }
-    let ParseOutput { asts, errs } = parse_workspace(root_lib);
+    let ParseOutput { asts, errs } = parse_workspace_in(root_lib, files);
     ast.extend(asts);
     err.extend(errs);
-    let std_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/std/lib.ar");
     let ParseOutput {
         asts: std_asts,
         errs: std_errs,
-    } = parse_workspace(std_path);
+    } = parse_workspace_in(STD_LIB_PATH, files);
     // TODO: fix std library overwriting user-defined std mods.
     ast.extend(std_asts.into_iter().map(|(mut k, v)| {
         k.insert(0, "std".to_string());
@@ -216,7 +314,13 @@ pub fn parse_workspace_with_std(root_lib: impl AsRef<Path>) -> ParseOutput {
     }
 }
 
+#[cfg(feature = "fs")]
 pub fn parse_workspace(root_lib: impl AsRef<Path>) -> ParseOutput {
+    parse_workspace_in(root_lib, &OsFiles)
+}
+
+/// Parses the workspace rooted at `root_lib`, reading files from `files`.
+pub fn parse_workspace_in(root_lib: impl AsRef<Path>, files: &impl FileProvider) -> ParseOutput {
     let root_lib = root_lib.as_ref();
 
     let mut stack = vec![vec![]];
@@ -224,9 +328,9 @@ pub fn parse_workspace(root_lib: impl AsRef<Path>) -> ParseOutput {
     let mut workspace_errs = IndexMap::new();
 
     while let Some(path) = stack.pop() {
-        match get_mod(root_lib, &path) {
+        match get_mod_in(root_lib, &path, files) {
             Ok(file_path) => {
-                let (ast, errs) = parse(&file_path);
+                let (ast, errs) = parse(&file_path, files);
                 let mut mod_spans = Vec::new();
                 for decl in &ast.0.ast.decls {
                     if let Decl::Mod(decl) = decl {
@@ -265,15 +369,15 @@ pub fn parse_workspace(root_lib: impl AsRef<Path>) -> ParseOutput {
     }
 }
 
-fn parse(path: impl Into<PathBuf>) -> (ParseResult, ParseDiagnostics) {
+fn parse(path: impl Into<PathBuf>, files: &impl FileProvider) -> (ParseResult, ParseDiagnostics) {
     let path = path.into();
-    match std::fs::read_to_string(&path) {
+    match files.read_to_string(&path) {
         Ok(input) => {
             let input = ArcStr::from(input);
-            match crate::parser::parse_ast(input.clone(), path.clone()) {
-                Ok(ast) => ((ast, None), Vec::new()),
-                Err(errs) => parse_result_from_errors(input, path, diagnostics_from_errors(errs)),
-            }
+            // Keep the partial AST on syntax errors so that downstream
+            // features keep working on the rest of the file.
+            let (ast, errs) = crate::parser::parse_ast_recovering(input, path);
+            parse_result_from_errors(ast, diagnostics_from_errors(errs))
         }
         Err(e) => (
             (make_backup_ast("".into(), path), Some(e.into())),
@@ -282,6 +386,15 @@ fn parse(path: impl Into<PathBuf>) -> (ParseResult, ParseDiagnostics) {
     }
 }
 
+/// Parses the source of the single file at `path`, keeping the partial AST on
+/// syntax errors.
+///
+/// Useful for looking at a file as it is being edited, e.g. to complete an
+/// identifier, where the file is usually not valid Argon.
+pub fn parse_file(text: impl Into<ArcStr>, path: impl Into<PathBuf>) -> AnnotatedParseAst {
+    crate::parser::parse_ast_recovering(text.into(), path.into()).0
+}
+
 /// Wrap a cell-body snippet (a single statement, written without its trailing
 /// `;`) into a complete program by placing it in a throwaway cell:
 /// `cell __dummy__() { <input>; }`. The result is intended for the whole-file
@@ -313,12 +426,45 @@ pub fn parse_cell(input: &str) -> Result<CallExpr<&str, ParseMetadata>, anyhow::
     }
 }
 
+/// Parse a single expression, such as a dimension value typed in the GUI.
+/// Fails unless the whole input is exactly one expression.
+pub fn parse_expr(input: &str) -> Result<Expr<&str, ParseMetadata>, anyhow::Error> {
+    match crate::parser::parse_expr(input) {
+        Ok(expr) => Ok(expr),
+        Err(errs) => {
+            let diagnostics = diagnostics_from_errors(errs);
+            let mut err = String::new();
+            for diagnostic in diagnostics {
+                writeln!(&mut err, "{}", diagnostic.kind)
+                    .with_context(|| "failed to write to string buffer")?;
+            }
+            bail!("{}", err.trim_end());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parse::parse_cell;
+    use crate::parse::{parse_cell, parse_expr};
 
     #[test]
     fn cell_invocation_parses() {
         parse_cell("test(1., 5)").expect("failed to parse cell");
     }
+
+    #[test]
+    fn dimension_values_parse() {
+        for value in [
+            "5.",
+            "w",
+            "2. * (w - 10.)",
+            "-pitch / 2.",
+            "rect0.x1 - rect0.x0",
+        ] {
+            parse_expr(value).unwrap_or_else(|e| panic!("should parse `{value}`: {e}"));
+        }
+        for value in ["", "5. 6.", "w +", "let x = 5.", "(w", "5.;"] {
+            assert!(parse_expr(value).is_err(), "should not parse `{value}`");
+        }
+    }
 }
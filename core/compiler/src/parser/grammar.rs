@@ -17,11 +17,11 @@ use cfgrammar::Span;
 
 use crate::ast::{
     ArgDecl, Args, Ast, BinOp, BinOpExpr, BoolLiteral, CallExpr, CastExpr, CellDecl,
-    ComparisonExpr, ComparisonOp, ConstantDecl, Decl, EmitExpr, EnumDecl, Expr, FieldAccessExpr,
-    FloatLiteral, FnDecl, ForLoop, Ident, IdentPath, IfExpr, IndexExpr, IndexFieldAccessExpr,
-    IntLiteral, KwArgValue, LetBinding, MatchArm, MatchExpr, ModDecl, NilLiteral, Scope,
-    SeqNilLiteral, Statement, StringLiteral, StructDecl, StructField, TupleExpr, TySpec,
-    TySpecKind, UnaryOp, UnaryOpExpr,
+    ComparisonExpr, ComparisonOp, ConstantDecl, Decl, EmitExpr, EnumDecl, EnumVariant, Expr,
+    FieldAccessExpr, FloatLiteral, FnDecl, ForLoop, Ident, IdentPath, IfExpr, IndexExpr,
+    IndexFieldAccessExpr, IntLiteral, KwArgValue, LengthUnit, LetBinding, MatchArm, MatchExpr,
+    ModDecl, NilLiteral, RangeExpr, Scope, SeqExpr, SeqNilLiteral, Statement, StringLiteral,
+    StructDecl, StructField, TupleExpr, TySpec, TySpecKind, UnaryOp, UnaryOpExpr,
 };
 use crate::parse::ParseMetadata;
 
@@ -342,11 +342,28 @@ impl<'a> Parser<'a> {
         Some(call)
     }
 
+    /// `expr EOF` as a standalone entry (used by `parse_expr`). Returns `None`
+    /// (with an error recorded) unless the whole input is one expression.
+    pub fn parse_expr_entry(&mut self) -> Option<Expr<&'a str, Md>> {
+        let expr = self.parse_expr(0);
+        if !self.at(TokenKind::Eof) {
+            self.error_at(
+                self.span(self.cur),
+                format!(
+                    "expected end of input after expression, found {}",
+                    self.cur.kind.describe()
+                ),
+            );
+            return None;
+        }
+        Some(expr)
+    }
+
     fn recover_to_decl(&mut self) {
         use TokenKind::*;
         while !self.at(Eof) {
             match self.cur.kind {
-                KwEnum | KwStruct | KwCell | KwFn | KwConst | KwMod => break,
+                KwEnum | KwStruct | KwCell | KwExtern | KwFn | KwConst | KwMod => break,
                 _ => {
                     self.bump();
                 }
@@ -364,6 +381,7 @@ impl<'a> Parser<'a> {
             KwEnum => Decl::Enum(self.parse_enum_decl()),
             KwStruct => Decl::Struct(self.parse_struct_decl()),
             KwCell => Decl::Cell(self.parse_cell_decl()),
+            KwExtern => Decl::Cell(self.parse_extern_cell_decl()),
             KwFn => Decl::Fn(self.parse_fn_decl()),
             KwConst => Decl::Constant(self.parse_const_decl()),
             KwMod => Decl::Mod(self.parse_mod_decl()),
@@ -371,20 +389,44 @@ impl<'a> Parser<'a> {
         })
     }
 
-    /// `enumDecl : ENUM ident LBRACE enumVariants RBRACE`
+    /// `enumDecl : ENUM ident (LPAREN structFields RPAREN)? LBRACE enumVariants RBRACE`
     fn parse_enum_decl(&mut self) -> EnumDecl<&'a str, Md> {
         self.expect(TokenKind::KwEnum);
         let name = self.ident();
+        let mut fields = Vec::new();
+        if self.eat(TokenKind::LParen) {
+            fields = self.separated_list(TokenKind::RParen, |p| p.parse_struct_field());
+            self.expect(TokenKind::RParen);
+        }
         self.expect(TokenKind::LBrace);
-        let variants = self.parse_ident_list();
+        let variants = self.separated_list(TokenKind::RBrace, |p| p.parse_enum_variant());
         self.expect(TokenKind::RBrace);
         EnumDecl {
             name,
+            fields,
             variants,
             metadata: (),
         }
     }
 
+    /// `enumVariant : ident (LPAREN (expr (COMMA expr)* COMMA?)? RPAREN)? (EQ expr)?`
+    fn parse_enum_variant(&mut self) -> EnumVariant<&'a str, Md> {
+        let lo = self.cur.start;
+        let name = self.ident();
+        let mut values = Vec::new();
+        if self.eat(TokenKind::LParen) {
+            values = self.separated_list(TokenKind::RParen, |p| p.parse_expr(0));
+            self.expect(TokenKind::RParen);
+        }
+        let discriminant = self.eat(TokenKind::Eq).then(|| self.parse_expr(0));
+        EnumVariant {
+            name,
+            values,
+            discriminant,
+            span: self.finish_span(lo),
+        }
+    }
+
     /// `structDecl : STRUCT ident LBRACE structFields RBRACE`
     fn parse_struct_decl(&mut self) -> StructDecl<&'a str, Md> {
         let lo = self.cur.start;
@@ -457,6 +499,37 @@ impl<'a> Parser<'a> {
             name,
             args,
             scope,
+            bbox: None,
+            span: self.finish_span(lo),
+            metadata: (),
+        }
+    }
+
+    /// `externCellDecl : EXTERN CELL ident LPAREN args RPAREN SEMI`
+    ///
+    /// The cell has no parameters and an empty body; its arguments give its
+    /// bounding box.
+    fn parse_extern_cell_decl(&mut self) -> CellDecl<&'a str, Md> {
+        let lo = self.cur.start;
+        self.expect(TokenKind::KwExtern);
+        self.expect(TokenKind::KwCell);
+        let name = self.ident();
+        self.expect(TokenKind::LParen);
+        let bbox = self.parse_args();
+        self.expect(TokenKind::RParen);
+        let semi = self.expect(TokenKind::Semi);
+        CellDecl {
+            name,
+            args: Vec::new(),
+            scope: Scope {
+                scope_annotation: None,
+                construction: false,
+                span: Span::new(semi.start as usize, semi.end as usize),
+                stmts: Vec::new(),
+                tail: None,
+                metadata: (),
+            },
+            bbox: Some(bbox),
             span: self.finish_span(lo),
             metadata: (),
         }
@@ -504,11 +577,6 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// `enumVariants : (ident (COMMA ident)* COMMA?)?`
-    fn parse_ident_list(&mut self) -> Vec<Ident<&'a str, Md>> {
-        self.separated_list(TokenKind::RBrace, |p| p.ident())
-    }
-
     /// `tySpec : ident | LBRACK tySpec RBRACK | LPAREN tySpecList RPAREN`
     fn parse_ty_spec(&mut self) -> TySpec<&'a str, Md> {
         let lo = self.cur.start;
@@ -558,7 +626,7 @@ impl<'a> Parser<'a> {
     // Scopes & statements
     // ------------------------------------------------------------------
 
-    /// `scope : scopeAnnotation? unannotatedScope`
+    /// `scope : (scopeAnnotation constructionModifier?)? unannotatedScope`
     fn parse_scope(&mut self) -> Scope<&'a str, Md> {
         let ann = if self.at(TokenKind::Annotation) {
             let t = self.bump();
@@ -566,19 +634,39 @@ impl<'a> Parser<'a> {
         } else {
             None
         };
-        self.parse_unannotated_scope(ann)
+        let construction = ann.is_some() && self.eat_construction_modifier();
+        self.parse_unannotated_scope(ann, construction)
+    }
+
+    /// Eats the contextual `construction` keyword if it directly precedes a
+    /// `{`, so that `construction` remains usable as an ordinary identifier.
+    fn eat_construction_modifier(&mut self) -> bool {
+        if self.at(TokenKind::Ident)
+            && self.nxt.kind == TokenKind::LBrace
+            && self.slice_tok(self.cur) == "construction"
+        {
+            self.bump();
+            true
+        } else {
+            false
+        }
     }
 
     /// `unannotatedScope : LBRACE statements (expr)? RBRACE`
     ///
     /// `Scope.span` covers only the braces (the annotation, if any, has its own
     /// span and is excluded), matching the ANTLR `AstBuilder`.
-    fn parse_unannotated_scope(&mut self, ann: Option<Ident<&'a str, Md>>) -> Scope<&'a str, Md> {
+    fn parse_unannotated_scope(
+        &mut self,
+        ann: Option<Ident<&'a str, Md>>,
+        construction: bool,
+    ) -> Scope<&'a str, Md> {
         if !self.enter_depth() {
             self.error_at(self.span(self.cur), "nesting too deep".to_string());
             let lo = self.cur.start;
             return Scope {
                 scope_annotation: ann,
+                construction,
                 span: Span::new(lo as usize, lo as usize),
                 stmts: Vec::new(),
                 tail: None,
@@ -652,6 +740,7 @@ impl<'a> Parser<'a> {
         self.exit_depth();
         Scope {
             scope_annotation: ann,
+            construction,
             span: self.finish_span(lo),
             stmts,
             tail,
@@ -675,13 +764,25 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// `forLoop : FOR ident IN expr scope`
+    /// `forLoop : FOR ident IN expr (DOTDOT expr)? scope`. `start..end`
+    /// iterates over the integers from `start` up to but excluding `end`.
     fn parse_for_loop(&mut self) -> ForLoop<&'a str, Md> {
         let lo = self.cur.start;
         self.expect(TokenKind::KwFor);
         let var = self.ident();
         self.expect(TokenKind::KwIn);
-        let seq = self.parse_expr(0);
+        let mut seq = self.parse_expr(0);
+        if self.at(TokenKind::DotDot) {
+            self.bump();
+            let start = seq;
+            let end = self.parse_expr(0);
+            seq = Expr::Range(Box::new(RangeExpr {
+                span: Span::new(start.span().start(), end.span().end()),
+                start,
+                end,
+                metadata: (),
+            }));
+        }
         let body = self.parse_scope();
         ForLoop {
             var,
@@ -745,7 +846,12 @@ impl<'a> Parser<'a> {
     /// `matchArm : identPath FAT_ARROW expr COMMA` (span includes the comma).
     fn parse_match_arm(&mut self) -> MatchArm<&'a str, Md> {
         let lo = self.cur.start;
-        let pattern = self.parse_ident_path();
+        let pattern = if self.at(TokenKind::Ident) && self.slice_tok(self.cur) == "_" {
+            self.bump();
+            None
+        } else {
+            Some(self.parse_ident_path())
+        };
         self.expect(TokenKind::FatArrow);
         let expr = self.parse_expr(0);
         self.expect(TokenKind::Comma);
@@ -950,13 +1056,13 @@ impl<'a> Parser<'a> {
     fn parse_primary(&mut self) -> Expr<&'a str, Md> {
         match self.cur.kind {
             TokenKind::LParen => self.parse_paren(),
-            TokenKind::LBrack => self.parse_seq_nil(),
+            TokenKind::LBrack => self.parse_seq(),
             TokenKind::KwIf => {
                 let lo = self.cur.start;
                 Expr::If(Box::new(self.parse_if(None, lo)))
             }
             TokenKind::KwMatch => Expr::Match(Box::new(self.parse_match())),
-            TokenKind::LBrace => Expr::Scope(Box::new(self.parse_unannotated_scope(None))),
+            TokenKind::LBrace => Expr::Scope(Box::new(self.parse_unannotated_scope(None, false))),
             TokenKind::Annotation => self.parse_annotated_primary(),
             TokenKind::Ident => {
                 let path = self.parse_ident_path();
@@ -990,14 +1096,20 @@ impl<'a> Parser<'a> {
     }
 
     /// An expression starting with `#name`: either an annotated `if`, an
-    /// annotated scope `{...}`, or an annotated call `path(...)`.
+    /// annotated scope `{...}` (optionally a `construction` scope), or an
+    /// annotated call `path(...)`.
     fn parse_annotated_primary(&mut self) -> Expr<&'a str, Md> {
         let ann_tok = self.bump();
         let lo = ann_tok.start;
         let ann = self.annotation_ident(ann_tok);
+        if self.eat_construction_modifier() {
+            return Expr::Scope(Box::new(self.parse_unannotated_scope(Some(ann), true)));
+        }
         match self.cur.kind {
             TokenKind::KwIf => Expr::If(Box::new(self.parse_if(Some(ann), lo))),
-            TokenKind::LBrace => Expr::Scope(Box::new(self.parse_unannotated_scope(Some(ann)))),
+            TokenKind::LBrace => {
+                Expr::Scope(Box::new(self.parse_unannotated_scope(Some(ann), false)))
+            }
             TokenKind::Ident => {
                 let path = self.parse_ident_path();
                 if self.at(TokenKind::LParen) {
@@ -1057,12 +1169,29 @@ impl<'a> Parser<'a> {
         })
     }
 
-    /// `seqNilLiteral : LBRACK RBRACK` (a non-empty `[...]` is not an expression).
-    fn parse_seq_nil(&mut self) -> Expr<&'a str, Md> {
+    /// `seqNilLiteral : LBRACK RBRACK` or
+    /// `seqExpr : LBRACK expr (COMMA expr)* COMMA? RBRACK`.
+    fn parse_seq(&mut self) -> Expr<&'a str, Md> {
         let lb = self.bump();
-        let rb = self.expect(TokenKind::RBrack);
-        Expr::SeqNil(SeqNilLiteral {
-            span: Span::new(lb.start as usize, rb.end as usize),
+        if self.at(TokenKind::RBrack) {
+            let rb = self.bump();
+            return Expr::SeqNil(SeqNilLiteral {
+                span: Span::new(lb.start as usize, rb.end as usize),
+            });
+        }
+        let mut items = vec![self.parse_expr(0)];
+        while self.at(TokenKind::Comma) {
+            self.bump();
+            if self.at(TokenKind::RBrack) {
+                break;
+            }
+            items.push(self.parse_expr(0));
+        }
+        self.expect(TokenKind::RBrack);
+        Expr::Seq(SeqExpr {
+            items,
+            span: self.finish_span(lb.start),
+            metadata: (),
         })
     }
 
@@ -1173,6 +1302,9 @@ impl<'a> Parser<'a> {
     /// `INTLIT` optionally followed by `. INTLIT?` to form a float. The value is
     /// parsed from the raw source slice (including any interior trivia) and
     /// defaults to 0 on failure, matching the ANTLR `AstBuilder`.
+    ///
+    /// A unit suffix directly after the number (`150nm`, `0.15um`) makes it a
+    /// length literal, which is always a float in layout units.
     fn parse_int_or_float(&mut self) -> Expr<&'a str, Md> {
         let i0 = self.bump();
         // `INTLIT .` forms a float (`1.`, `1.5`) — except when the `.` is
@@ -1181,23 +1313,42 @@ impl<'a> Parser<'a> {
         // an integer can be the base of `.field`/`.idx` like every other
         // primary. A `.` before another `INTLIT`, or before any non-identifier
         // token (e.g. `1.`), still assembles a float, matching prior behavior.
-        if self.at(TokenKind::Dot) && self.nxt.kind != TokenKind::Ident {
+        let (number, is_float) = if self.at(TokenKind::Dot) && self.nxt.kind != TokenKind::Ident {
             let dot = self.bump();
             let end = if self.at(TokenKind::IntLit) {
                 self.bump().end
             } else {
                 dot.end
             };
-            let span = Span::new(i0.start as usize, end as usize);
+            (Span::new(i0.start as usize, end as usize), true)
+        } else {
+            (self.span(i0), false)
+        };
+        let unit = if self.at(TokenKind::Ident) && self.cur.start == self.prev_end {
+            LengthUnit::from_suffix(self.slice_tok(self.cur))
+        } else {
+            None
+        };
+        if let Some(unit) = unit {
+            self.bump();
+            // Shifting the exponent rather than multiplying keeps `0.13um` from
+            // becoming `130.00000000000003`.
+            let value = format!("{}e{}", self.slice_span(number), unit.exponent());
             Expr::FloatLiteral(FloatLiteral {
-                span,
-                value: self.slice_span(span).parse::<f64>().unwrap_or_default(),
+                span: self.finish_span(i0.start),
+                value: value.parse::<f64>().unwrap_or_default(),
+                unit: Some(unit),
+            })
+        } else if is_float {
+            Expr::FloatLiteral(FloatLiteral {
+                span: number,
+                value: self.slice_span(number).parse::<f64>().unwrap_or_default(),
+                unit: None,
             })
         } else {
-            let span = self.span(i0);
             Expr::IntLiteral(IntLiteral {
-                span,
-                value: self.slice_tok(i0).parse::<i64>().unwrap_or_default(),
+                span: number,
+                value: self.slice_span(number).parse::<i64>().unwrap_or_default(),
             })
         }
     }
@@ -2,7 +2,7 @@
 //!
 //! Replaces the ANTLR-generated parser: a streaming byte lexer ([`lexer`]) feeds
 //! a single-pass recursive-descent + Pratt parser ([`grammar`]) that builds the
-//! AST directly, borrowing all identifier/string text from the source. The two
+//! AST directly, borrowing all identifier/string text from the source. The
 //! public entry points match the contract the rest of the compiler expects.
 
 mod grammar;
@@ -14,8 +14,8 @@ use std::path::PathBuf;
 use arcstr::ArcStr;
 use cfgrammar::Span;
 
-use crate::ast::CallExpr;
 use crate::ast::annotated::AnnotatedAst;
+use crate::ast::{CallExpr, Expr};
 use crate::parse::{AnnotatedParseAst, ParseMetadata};
 
 /// A syntax error with the byte span (into the original input) it occurred at.
@@ -33,16 +33,35 @@ pub struct ParseError {
 /// by span, so spans must be byte-exact (they index the original, untrimmed
 /// input). On any syntax error, returns every collected diagnostic.
 pub fn parse_ast(input: ArcStr, path: PathBuf) -> Result<AnnotatedParseAst, Vec<ParseError>> {
+    let (ast, errors) = parse_ast_recovering(input, path);
+    if errors.is_empty() {
+        Ok(ast)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parse a whole source file, returning a best-effort AST alongside every
+/// collected diagnostic.
+///
+/// The parser recovers from syntax errors by skipping to the next declaration,
+/// substituting nil literals for missing expressions, and keeping statements
+/// that lack their terminator, so declarations around a syntax error survive.
+/// The AST is exact when the error list is empty.
+pub fn parse_ast_recovering(input: ArcStr, path: PathBuf) -> (AnnotatedParseAst, Vec<ParseError>) {
     let input_for_ast = input.clone();
     let normalized = input.trim_start_matches(char::is_whitespace);
     let offset_base = input.len() - normalized.len();
 
     let mut parser = grammar::Parser::new(normalized, offset_base);
     let ast = parser.parse_root();
-    if !parser.errors.is_empty() {
-        return Err(parser.finish_errors(offset_base, input.len()));
-    }
-    Ok(AnnotatedAst::new(input_for_ast, &ast, path))
+    let ast = AnnotatedAst::new(input_for_ast, &ast, path);
+    let errors = if parser.errors.is_empty() {
+        Vec::new()
+    } else {
+        parser.finish_errors(offset_base, input.len())
+    };
+    (ast, errors)
 }
 
 /// Parse a single cell invocation (a `callExpr`) from raw input, as used by the
@@ -63,6 +82,23 @@ pub fn parse_cell(input: &str) -> Result<CallExpr<&str, ParseMetadata>, Vec<Pars
     }
 }
 
+/// Parse a single expression, such as the value of a dimension, from raw input.
+/// Returns the borrowed-`&str` AST directly (no annotation pass).
+pub fn parse_expr(input: &str) -> Result<Expr<&str, ParseMetadata>, Vec<ParseError>> {
+    let normalized = input.trim_start_matches(char::is_whitespace);
+    let offset_base = input.len() - normalized.len();
+
+    let mut parser = grammar::Parser::new(normalized, offset_base);
+    let expr = parser.parse_expr_entry();
+    if !parser.errors.is_empty() {
+        return Err(parser.finish_errors(offset_base, input.len()));
+    }
+    match expr {
+        Some(expr) => Ok(expr),
+        None => Err(parser.finish_errors(offset_base, input.len())),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::{Path, PathBuf};
@@ -110,6 +146,10 @@ mod tests {
             "#scope0 { eq(a, b); }",
             "let r = rect(\"met1\", x0=0., y0=0., x1=400.)!;",
             "for i in range(3) { eq(i, i); }",
+            "for i in 0..n { eq(i, i); }",
+            "let x = [a];",
+            "let x = [a, b, c];",
+            "let x = [a, b,][1].x0;",
             "match k { A => 1, B => 2, }",
         ];
         for body in valid {
@@ -126,12 +166,32 @@ mod tests {
             "let x = ;",            // missing expression
             "let x = (a, b;",       // unterminated tuple
             "let x = match k {};",  // empty match: `matchArms` requires >= 1 arm
+            "let x = [a b];",       // sequence items are separated by commas
+            "let x = [,];",         // a comma needs a preceding item
         ];
         for body in invalid {
             assert!(!snippet_ok(body), "should be rejected: `{body}`");
         }
     }
 
+    #[test]
+    fn recovers_partial_ast() {
+        let src = "cell a() { let x = ; }\n@@\ncell b() { let y = 1. }\nfn c() -> Float { 2. }\n";
+        let (ast, errs) = super::parse_ast_recovering(ArcStr::from(src), PathBuf::from("test.ar"));
+        assert!(errs.len() >= 2, "{errs:?}");
+        let names = ast
+            .ast
+            .decls
+            .iter()
+            .filter_map(|decl| match decl {
+                crate::ast::Decl::Cell(cell) => Some(cell.name.name.to_string()),
+                crate::ast::Decl::Fn(f) => Some(f.name.name.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
     #[test]
     fn leading_comment_is_allowed() {
         // The lexer skips `//` comments as trivia everywhere, so a comment
@@ -140,6 +200,104 @@ mod tests {
         assert!(parse("  \n// c1\n// c2\nfn f() -> Float { 1. }\n").is_ok());
     }
 
+    #[test]
+    fn enum_fields_and_discriminants() {
+        use crate::ast::Decl;
+
+        let src = r#"
+enum Layer(gds: Int, name: String) {
+  Met1(68, "met1") = 1,
+  Met2(69, "met2"),
+}
+"#;
+        let ast = parse(src).unwrap();
+        let Decl::Enum(e) = &ast.ast.decls[0] else {
+            panic!("expected an enum decl, got {:?}", ast.ast.decls[0]);
+        };
+        assert_eq!(e.fields.len(), 2);
+        assert_eq!(e.variants.len(), 2);
+        assert_eq!(e.variants[0].values.len(), 2);
+        assert!(e.variants[0].discriminant.is_some());
+        assert!(e.variants[1].discriminant.is_none());
+        assert!(parse("enum E { A, B = 2, }\n").is_ok());
+        assert!(parse("enum E(x: Int) { A(1) B(2) }\n").is_err());
+    }
+
+    #[test]
+    fn construction_scopes() {
+        use crate::ast::{Decl, Expr, Statement};
+
+        let src = "cell c() {\n  let g = #guide construction { rect(\"met1\") };\n  let construction = 1;\n  #s { construction }\n}\n";
+        let ast = parse(src).unwrap();
+        let Decl::Cell(cell) = &ast.ast.decls[0] else {
+            panic!("expected a cell decl, got {:?}", ast.ast.decls[0]);
+        };
+        let Statement::LetBinding(g) = &cell.scope.stmts[0] else {
+            panic!("expected a let binding, got {:?}", cell.scope.stmts[0]);
+        };
+        let Expr::Scope(scope) = &g.value else {
+            panic!("expected a scope, got {:?}", g.value);
+        };
+        assert!(scope.construction);
+        assert!(scope.tail.is_some());
+        let Some(Expr::Scope(scope)) = &cell.scope.tail else {
+            panic!("expected a scope tail, got {:?}", cell.scope.tail);
+        };
+        assert!(!scope.construction);
+        assert!(matches!(scope.tail, Some(Expr::IdentPath(_))));
+    }
+
+    #[test]
+    fn extern_cells() {
+        use crate::ast::Decl;
+
+        let src = "extern cell sram(x0=0., y0=0., x1=1200., y1=800.);\ncell top() {}\n";
+        let ast = parse(src).unwrap();
+        let Decl::Cell(cell) = &ast.ast.decls[0] else {
+            panic!("expected a cell decl, got {:?}", ast.ast.decls[0]);
+        };
+        assert_eq!(cell.name.name, "sram");
+        assert!(cell.args.is_empty());
+        assert!(cell.scope.stmts.is_empty());
+        let bbox = cell.bbox.as_ref().expect("extern cell has a bbox");
+        assert_eq!(bbox.kwargs.len(), 4);
+        assert!(matches!(&ast.ast.decls[1], Decl::Cell(cell) if cell.bbox.is_none()));
+        // Extern cells have no body.
+        assert!(parse("extern cell sram(x1=1200., y1=800.) {}\n").is_err());
+    }
+
+    #[test]
+    fn length_literals() {
+        use crate::ast::{Decl, Expr, LengthUnit, Statement};
+
+        let src = "cell c() {\n  let a = 150nm;\n  let b = 0.13um;\n  let c = 2 um;\n}\n";
+        let mut parser = super::grammar::Parser::new(src, 0);
+        let ast = parser.parse_root();
+        let Decl::Cell(cell) = &ast.decls[0] else {
+            panic!("expected a cell decl, got {:?}", ast.decls[0]);
+        };
+        let literal = |i: usize| {
+            let Statement::LetBinding(binding) = &cell.scope.stmts[i] else {
+                panic!("expected a let binding, got {:?}", cell.scope.stmts[i]);
+            };
+            match &binding.value {
+                Expr::FloatLiteral(f) => *f,
+                value => panic!("expected a FloatLiteral, got {value:?}"),
+            }
+        };
+
+        let a = literal(0);
+        assert_eq!(a.value, 150.);
+        assert_eq!(a.unit, Some(LengthUnit::Nm));
+        assert_eq!(&src[a.span.start()..a.span.end()], "150nm");
+        let b = literal(1);
+        assert_eq!(b.value, 130.);
+        assert_eq!(b.unit, Some(LengthUnit::Um));
+        assert_eq!(&src[b.span.start()..b.span.end()], "0.13um");
+        // A unit must directly follow its number.
+        assert!(!parser.errors.is_empty());
+    }
+
     #[test]
     fn literal_values_and_spans() {
         use crate::ast::{Decl, Expr, Statement};
@@ -15,6 +15,7 @@ pub enum TokenKind {
     KwMatch,
     KwConst,
     KwCell,
+    KwExtern,
     KwMod,
     KwIf,
     KwFn,
@@ -42,6 +43,7 @@ pub enum TokenKind {
     Geq,      // >=
     Leq,      // <=
     Arrow,    // ->
+    DotDot,   // ..
 
     // Single-character operators / punctuation.
     Lt,      // <
@@ -82,6 +84,7 @@ impl TokenKind {
             KwMatch => "'match'",
             KwConst => "'const'",
             KwCell => "'cell'",
+            KwExtern => "'extern'",
             KwMod => "'mod'",
             KwIf => "'if'",
             KwFn => "'fn'",
@@ -103,6 +106,7 @@ impl TokenKind {
             Geq => "'>='",
             Leq => "'<='",
             Arrow => "'->'",
+            DotDot => "'..'",
             Lt => "'<'",
             Gt => "'>'",
             Eq => "'='",
@@ -181,13 +185,11 @@ pub fn keyword_or_ident(s: &[u8]) -> TokenKind {
             b"false" => KwFalse,
             _ => Ident,
         },
-        6 => {
-            if s == b"struct" {
-                KwStruct
-            } else {
-                Ident
-            }
-        }
+        6 => match s {
+            b"struct" => KwStruct,
+            b"extern" => KwExtern,
+            _ => Ident,
+        },
         _ => Ident,
     }
 }
@@ -0,0 +1,90 @@
+//! Checks for geometry moved off the layout grid by non-Manhattan rotations.
+//!
+//! Rects are axis-aligned in the cell that draws them, so an instance rotated
+//! by an angle that is not a multiple of 90 degrees turns them into polygons
+//! whose corners generally do not land on the manufacturing grid.
+use std::fmt::{self, Display};
+
+use argon_geometry::Flatten;
+use serde::{Deserialize, Serialize};
+
+use crate::compile::{CompiledData, ObjectId};
+
+/// Tolerance, in grid steps, within which a coordinate is considered on-grid.
+const GRID_EPSILON: f64 = 1e-6;
+
+/// A rect whose corners are off-grid in the flattened top cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OffGridRotation {
+    pub layer: Option<String>,
+    /// IDs of the instances leading to the rect, followed by the rect's own ID.
+    pub path: Vec<ObjectId>,
+    /// Rotation of the rect relative to the top cell, in degrees.
+    pub angle: f64,
+    /// The first off-grid corner, in the coordinates of the top cell.
+    pub corner: (f64, f64),
+}
+
+impl Display for OffGridRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rect rotated by {} degrees has off-grid corner ({}, {})",
+            self.layer.as_deref().unwrap_or("unlayered"),
+            self.angle,
+            self.corner.0,
+            self.corner.1
+        )
+    }
+}
+
+/// Returns whether `coord` is a multiple of `grid`.
+pub(crate) fn on_grid(coord: f64, grid: f64) -> bool {
+    let steps = coord / grid;
+    (steps - steps.round()).abs() < GRID_EPSILON
+}
+
+impl CompiledData {
+    /// Returns the non-construction rects of the top cell that are rotated by
+    /// a non-Manhattan angle and have a corner off a grid of `grid` layout
+    /// units.
+    pub fn off_grid_rotations(&self, grid: f64) -> Vec<OffGridRotation> {
+        Flatten::new((self.top, Vec::new()), None, |(cell, path), _, _| {
+            self.emitted(*cell)
+                .filter(|obj| obj.instance().is_some_and(|inst| !inst.construction))
+                .flat_map(|obj| {
+                    let inst = obj.instance().unwrap();
+                    let mut path = path.clone();
+                    path.push(inst.id);
+                    obj.instance_transforms()
+                        .into_iter()
+                        .map(move |xform| ((inst.cell, path.clone()), xform))
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|(_, transform, _)| !transform.is_manhattan())
+        .flat_map(|((cell, path), transform, _)| {
+            self.emitted(cell)
+                .filter_map(|obj| obj.get_rect())
+                .filter(|rect| !rect.construction)
+                .filter_map(move |rect| {
+                    let corner = rect
+                        .to_float()
+                        .bbox()
+                        .corners()
+                        .map(|pt| transform.apply(pt))
+                        .into_iter()
+                        .find(|(x, y)| !on_grid(*x, grid) || !on_grid(*y, grid))?;
+                    let mut path = path.clone();
+                    path.push(rect.id);
+                    Some(OffGridRotation {
+                        layer: rect.layer.clone(),
+                        path,
+                        angle: transform.angle,
+                        corner,
+                    })
+                })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,125 @@
+//! Versioned serialization of [`CompileOutput`] between Argon binaries.
+//!
+//! The compiler, language server, and GUI may come from different builds, so
+//! serialized output is tagged with [`SCHEMA_VERSION`], e.g.
+//!
+//! ```json
+//! { "schema_version": 1, "output": { "Valid": { ... } } }
+//! ```
+//!
+//! and readers refuse output of any other version with a [`SchemaError`]
+//! rather than failing somewhere inside the output.
+//!
+//! Large cells are much smaller and faster to read as bincode, which is
+//! prefixed by [`BINCODE_MAGIC`] and the schema version as a little-endian
+//! `u32`. JSON is kept for debugging.
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::compile::CompileOutput;
+
+/// Version of the serialized form of [`CompileOutput`].
+///
+/// Bump this whenever a change to [`CompileOutput`] or the types it contains
+/// would keep an older build from reading newer output or vice versa.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Leading bytes of output serialized by [`CompileOutput::to_bincode`].
+pub const BINCODE_MAGIC: &[u8; 4] = b"ARGN";
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error(
+        "compile output has schema version {found}, but this build of Argon reads version \
+         {expected}; make sure the compiler, language server, and GUI are from the same release"
+    )]
+    VersionMismatch { found: u32, expected: u32 },
+    #[error(
+        "compile output has no schema version, so it was likely written by an older build of \
+         Argon; make sure the compiler, language server, and GUI are from the same release"
+    )]
+    Unversioned,
+    #[error("invalid compile output: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid compile output: {0}")]
+    Bincode(#[from] bincode::Error),
+}
+
+/// Returns an error unless `found` is the [`SCHEMA_VERSION`] of this build.
+pub fn check_schema_version(found: u32) -> Result<(), SchemaError> {
+    if found == SCHEMA_VERSION {
+        Ok(())
+    } else {
+        Err(SchemaError::VersionMismatch {
+            found,
+            expected: SCHEMA_VERSION,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct VersionedRef<'a> {
+    schema_version: u32,
+    output: &'a CompileOutput,
+}
+
+/// The version of serialized output, read before the rest of it.
+#[derive(Deserialize)]
+struct Header {
+    schema_version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    output: CompileOutput,
+}
+
+impl CompileOutput {
+    /// Serializes the output as pretty-printed JSON tagged with [`SCHEMA_VERSION`].
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&VersionedRef {
+            schema_version: SCHEMA_VERSION,
+            output: self,
+        })
+    }
+
+    /// Deserializes output written by [`CompileOutput::to_json`], checking its
+    /// schema version first.
+    pub fn from_json(json: &str) -> Result<Self, SchemaError> {
+        let header: Header = serde_json::from_str(json)?;
+        check_schema_version(header.schema_version.ok_or(SchemaError::Unversioned)?)?;
+        let versioned: Versioned = serde_json::from_str(json)?;
+        Ok(versioned.output)
+    }
+
+    /// Serializes the output as bincode, prefixed by [`BINCODE_MAGIC`] and
+    /// [`SCHEMA_VERSION`].
+    pub fn to_bincode(&self) -> Result<Vec<u8>, SchemaError> {
+        let mut bytes = BINCODE_MAGIC.to_vec();
+        bytes.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes output written by [`CompileOutput::to_bincode`], checking
+    /// its schema version first.
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, SchemaError> {
+        let (version, payload) = bytes
+            .strip_prefix(BINCODE_MAGIC)
+            .and_then(|rest| rest.split_first_chunk::<4>())
+            .ok_or(SchemaError::Unversioned)?;
+        check_schema_version(u32::from_le_bytes(*version))?;
+        Ok(bincode::deserialize(payload)?)
+    }
+
+    /// Deserializes output written by either [`CompileOutput::to_json`] or
+    /// [`CompileOutput::to_bincode`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SchemaError> {
+        if bytes.starts_with(BINCODE_MAGIC) {
+            Self::from_bincode(bytes)
+        } else {
+            let json = std::str::from_utf8(bytes).map_err(|_| SchemaError::Unversioned)?;
+            Self::from_json(json)
+        }
+    }
+}
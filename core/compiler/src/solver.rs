@@ -2,18 +2,21 @@ use approx::{relative_eq, relative_ne};
 use indexmap::{IndexMap, IndexSet};
 use itertools::{Either, Itertools, multiunzip};
 use nalgebra::{CsMatrix, DMatrix, DVector};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+use crate::config::{SolverBackend, SolverConfig};
+
 const EPSILON: f64 = 1e-8;
-const ROUND_STEP: f64 = 0.1;
-const INV_ROUND_STEP: f64 = 1. / ROUND_STEP;
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Ord, PartialOrd)]
 pub struct Var(u64);
 
 #[derive(Clone, Default)]
 pub struct Solver {
+    config: SolverConfig,
     next_var: u64,
     next_constraint: ConstraintId,
     constraints: IndexMap<ConstraintId, LinearExpr>,
@@ -32,10 +35,12 @@ pub struct Solver {
     // cleared at the start of each elimination pass, so they hold no state between solves.
     elim_worklist: VecDeque<ConstraintId>,
     substitutions: Vec<(Var, LinearExpr)>,
-}
-
-fn round(x: f64) -> f64 {
-    (x * INV_ROUND_STEP).round() * ROUND_STEP
+    // Variables of constraints added or changed since the last `solve()`. Components
+    // without any of them were already solved as far as possible, so `solve()` skips
+    // their SVD.
+    dirty_vars: IndexSet<Var>,
+    #[cfg(test)]
+    components_solved: usize,
 }
 
 impl Solver {
@@ -43,6 +48,19 @@ impl Solver {
         Default::default()
     }
 
+    /// Creates a solver with the given backend, tolerance, and rounding step.
+    pub fn with_config(config: SolverConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Rounds `x` to the nearest multiple of the rounding step.
+    fn round(&self, x: f64) -> f64 {
+        (x * (1. / self.config.round_step)).round() * self.config.round_step
+    }
+
     pub fn new_var(&mut self) -> Var {
         let var = Var(self.next_var);
         self.unsolved_vars.insert(var);
@@ -103,6 +121,7 @@ impl Solver {
         self.next_constraint += 1;
         for (_, var) in &expr.coeffs {
             self.var_to_constraints.entry(*var).or_default().insert(id);
+            self.dirty_vars.insert(*var);
         }
         self.constraints.insert(id, expr);
         // Use explicit stack in heap-allocated vector to avoid stack overflow.
@@ -121,7 +140,7 @@ impl Solver {
         {
             constraint.simplify(&self.solved_vars);
             if constraint.coeffs.is_empty()
-                && !relative_eq!(constraint.constant, 0., epsilon = EPSILON)
+                && !relative_eq!(constraint.constant, 0., epsilon = self.config.tolerance)
             {
                 self.inconsistent_constraints.insert(id);
                 self.constraints.swap_remove(&id);
@@ -135,12 +154,12 @@ impl Solver {
             let (coeff, var) = constraint.coeffs[0];
             let val = -constraint.constant / coeff;
             if let Some(old_val) = self.solved_vars.get(&var) {
-                if relative_ne!(*old_val, val, epsilon = EPSILON) {
+                if relative_ne!(*old_val, val, epsilon = self.config.tolerance) {
                     self.inconsistent_constraints.insert(id);
                 }
             } else {
-                let rounded_val = round(val);
-                if relative_ne!(val, rounded_val, epsilon = EPSILON) {
+                let rounded_val = self.round(val);
+                if relative_ne!(val, rounded_val, epsilon = self.config.tolerance) {
                     self.invalid_rounding.insert(var);
                 }
                 self.solve_var(var, rounded_val);
@@ -154,14 +173,36 @@ impl Solver {
                 .copied()
                 .collect_vec()
             {
+                self.mark_dirty(constraint);
                 self.back_substitute_stack.push(constraint);
             }
         }
     }
 
+    /// Adds `constraints` and solves for as many variables as possible, returning
+    /// the IDs of the new constraints.
+    ///
+    /// Values solved so far are kept, and only the parts of the system the new
+    /// constraints touch are solved again, so adding a single rect or dimension to
+    /// a large cell does not refactorize the rest of it.
+    pub fn add_constraints_and_resolve(
+        &mut self,
+        constraints: impl IntoIterator<Item = LinearExpr>,
+    ) -> Vec<ConstraintId> {
+        let ids = constraints
+            .into_iter()
+            .map(|expr| self.constrain_eq0(expr))
+            .collect();
+        self.solve();
+        ids
+    }
+
     /// Solves for as many variables as possible and substitutes their values into existing constraints.
     /// Deletes constraints that no longer contain unsolved variables.
     ///
+    /// Components of the system whose constraints have not changed since the last
+    /// call are not solved again.
+    ///
     /// Constraints should be simplified before this function is invoked.
     pub fn solve(&mut self) {
         if self.unsolved_vars.is_empty() || self.constraints.is_empty() {
@@ -176,15 +217,45 @@ impl Solver {
         // For systems whose constraints are all <= 2 variables (e.g. the coupled ring in
         // `bench_constraints`) this resolves everything in O(n) and the SVD never runs;
         // for a genuinely dense block it is a no-op and behaviour is identical to before.
-        self.eliminate_definitional();
+        if self.config.backend == SolverBackend::Eliminate {
+            self.eliminate_definitional();
+        }
 
-        for component in self.constraint_components() {
-            self.solve_component(&component.vars, &component.constraints);
+        // Components share no variables, so they are solved in parallel. Their
+        // solutions are applied in order so that the result is deterministic.
+        let dirty_vars = std::mem::take(&mut self.dirty_vars);
+        let components = self
+            .constraint_components()
+            .into_iter()
+            .filter(|component| component.vars.iter().any(|var| dirty_vars.contains(var)))
+            .collect_vec();
+        #[cfg(test)]
+        {
+            self.components_solved += components.len();
+        }
+        #[cfg(feature = "parallel")]
+        let components = components.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let components = components.iter();
+        let solutions = components
+            .map(|component| self.solve_component(&component.vars, &component.constraints))
+            .collect::<Vec<_>>();
+        for (var, val) in solutions.into_iter().flatten() {
+            let rounded_val = self.round(val);
+            if relative_ne!(val, rounded_val, epsilon = self.config.tolerance) {
+                self.invalid_rounding.insert(var);
+            }
+            self.solve_var(var, rounded_val);
         }
         for (id, constraint) in self.constraints.iter_mut() {
+            let len = constraint.coeffs.len();
             constraint.simplify(&self.solved_vars);
+            if constraint.coeffs.len() != len {
+                self.dirty_vars
+                    .extend(constraint.coeffs.iter().map(|(_, var)| *var));
+            }
             if constraint.coeffs.is_empty()
-                && approx::relative_ne!(constraint.constant, 0., epsilon = EPSILON)
+                && approx::relative_ne!(constraint.constant, 0., epsilon = self.config.tolerance)
             {
                 self.inconsistent_constraints.insert(*id);
             }
@@ -216,7 +287,7 @@ impl Solver {
             let constant = constraint.constant;
             match len {
                 0 => {
-                    if relative_ne!(constant, 0., epsilon = EPSILON) {
+                    if relative_ne!(constant, 0., epsilon = self.config.tolerance) {
                         self.inconsistent_constraints.insert(id);
                     }
                     self.remove_constraint(id);
@@ -228,6 +299,14 @@ impl Solver {
         }
     }
 
+    /// Marks the variables of constraint `id` as needing to be solved again.
+    fn mark_dirty(&mut self, id: ConstraintId) {
+        if let Some(constraint) = self.constraints.get(&id) {
+            self.dirty_vars
+                .extend(constraint.coeffs.iter().map(|(_, var)| *var));
+        }
+    }
+
     /// Removes a constraint from the system and unlinks it from `var_to_constraints`.
     fn remove_constraint(&mut self, id: ConstraintId) {
         if let Some(constraint) = self.constraints.swap_remove(&id) {
@@ -249,6 +328,9 @@ impl Solver {
             .flatten()
             .copied()
             .collect();
+        for &id in &neighbors {
+            self.mark_dirty(id);
+        }
         self.elim_worklist.extend(neighbors);
     }
 
@@ -275,7 +357,7 @@ impl Solver {
         } else {
             ((c1, v1), (c0, v0))
         };
-        if a.abs() <= EPSILON || v == w {
+        if a.abs() <= self.config.tolerance || v == w {
             return;
         }
         // From `a*v + cw*w + constant = 0`: v = (-cw/a) * w + (-constant/a).
@@ -296,6 +378,8 @@ impl Solver {
                 continue;
             };
             substitute_var(constraint, v, &v_expr);
+            self.dirty_vars
+                .extend(constraint.coeffs.iter().map(|(_, var)| *var));
             self.var_to_constraints.entry(w).or_default().insert(nid);
             self.elim_worklist.push_back(nid);
         }
@@ -344,8 +428,8 @@ impl Solver {
         if self.solved_vars.contains_key(&var) {
             return;
         }
-        let rounded = round(val);
-        if relative_ne!(val, rounded, epsilon = EPSILON) {
+        let rounded = self.round(val);
+        if relative_ne!(val, rounded, epsilon = self.config.tolerance) {
             self.invalid_rounding.insert(var);
         }
         self.solve_var(var, rounded);
@@ -355,12 +439,16 @@ impl Solver {
         if self.unsolved_vars.is_empty() || self.constraints.is_empty() {
             return Vec::new();
         }
-        self.constraint_components()
-            .into_iter()
-            .flat_map(|component| {
-                self.rowspace_component_vecs(&component.vars, &component.constraints)
-            })
-            .collect()
+        let components = self.constraint_components();
+        #[cfg(feature = "parallel")]
+        let vecs = components.par_iter().flat_map_iter(|component| {
+            self.rowspace_component_vecs(&component.vars, &component.constraints)
+        });
+        #[cfg(not(feature = "parallel"))]
+        let vecs = components.iter().flat_map(|component| {
+            self.rowspace_component_vecs(&component.vars, &component.constraints)
+        });
+        vecs.collect()
     }
 
     pub fn value_of(&self, var: Var) -> Option<f64> {
@@ -372,19 +460,27 @@ impl Solver {
     }
 
     pub fn eval_expr(&self, expr: &LinearExpr) -> Option<f64> {
-        Some(round(
-            expr.coeffs
-                .iter()
-                .map(|(coeff, var)| self.value_of(*var).map(|val| val * coeff))
-                .fold_options(0., |a, b| a + b)?
-                + expr.constant,
-        ))
+        Some(
+            self.round(
+                expr.coeffs
+                    .iter()
+                    .map(|(coeff, var)| self.value_of(*var).map(|val| val * coeff))
+                    .fold_options(0., |a, b| a + b)?
+                    + expr.constant,
+            ),
+        )
     }
 
-    fn solve_component(&mut self, vars: &IndexSet<Var>, constraints: &[ConstraintId]) {
+    /// Returns the (unrounded) values of the variables of a component that its
+    /// constraints determine.
+    fn solve_component(
+        &self,
+        vars: &IndexSet<Var>,
+        constraints: &[ConstraintId],
+    ) -> Vec<(Var, f64)> {
         let n_vars = vars.len();
         if n_vars == 0 || constraints.is_empty() {
-            return;
+            return Vec::new();
         }
         let var_indices: IndexMap<Var, usize> =
             IndexMap::from_iter(vars.iter().enumerate().map(|(i, var)| (*var, i)));
@@ -408,28 +504,25 @@ impl Solver {
         );
         let svd = a.svd(true, true);
         let vt = svd.v_t.as_ref().expect("No V^T matrix");
-        let r = svd.rank(EPSILON);
+        let r = svd.rank(self.config.tolerance);
         if r == 0 {
-            return;
-        }
-        let sol = svd.solve(&b, EPSILON).unwrap();
-
-        for (i, var) in vars.iter().enumerate() {
-            let recons = (0..r)
-                .map(|row| {
-                    let coeff = vt[(row, i)];
-                    coeff * coeff
-                })
-                .sum::<f64>();
-            if relative_eq!(recons, 1., epsilon = EPSILON) {
-                let val = sol[(i, 0)];
-                let rounded_val = round(val);
-                if relative_ne!(val, rounded_val, epsilon = EPSILON) {
-                    self.invalid_rounding.insert(*var);
-                }
-                self.solve_var(*var, rounded_val);
-            }
+            return Vec::new();
         }
+        let sol = svd.solve(&b, self.config.tolerance).unwrap();
+
+        vars.iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                let recons = (0..r)
+                    .map(|row| {
+                        let coeff = vt[(row, *i)];
+                        coeff * coeff
+                    })
+                    .sum::<f64>();
+                relative_eq!(recons, 1., epsilon = self.config.tolerance)
+            })
+            .map(|(i, var)| (*var, sol[(i, 0)]))
+            .collect()
     }
 
     fn rowspace_component_vecs(
@@ -459,7 +552,7 @@ impl Solver {
         ));
         let svd = a.svd(false, true);
         let vt = svd.v_t.as_ref().expect("No V^T matrix");
-        let r = svd.rank(EPSILON);
+        let r = svd.rank(self.config.tolerance);
 
         (0..r)
             .map(|i| {
@@ -467,7 +560,7 @@ impl Solver {
                     .enumerate()
                     .filter_map(|(j, v)| {
                         let coeff = vt[(i, j)];
-                        if relative_ne!(coeff, 0., epsilon = EPSILON) {
+                        if relative_ne!(coeff, 0., epsilon = self.config.tolerance) {
                             Some((coeff, *v))
                         } else {
                             None
@@ -521,6 +614,125 @@ impl Solver {
     }
 }
 
+/// Returns true if `constraints`, applied in order to a fresh solver with
+/// `config`, are consistent.
+fn is_consistent<'a>(
+    config: &SolverConfig,
+    constraints: impl IntoIterator<Item = &'a LinearExpr>,
+) -> bool {
+    let mut solver = Solver::with_config(config.clone());
+    let mut vars = IndexMap::new();
+    for expr in constraints {
+        let coeffs = expr
+            .coeffs
+            .iter()
+            .map(|(coeff, var)| {
+                (
+                    *coeff,
+                    *vars.entry(*var).or_insert_with(|| solver.new_var()),
+                )
+            })
+            .collect();
+        solver.constrain_eq0(LinearExpr {
+            coeffs,
+            constant: expr.constant,
+        });
+    }
+    solver.solve();
+    solver.inconsistent_constraints.is_empty()
+}
+
+/// Largest component of constraints searched for an irreducible infeasible
+/// subset, since the search solves the component once per constraint.
+pub const MAX_INFEASIBLE_COMPONENT_SIZE: usize = 256;
+
+/// Returns the constraints applied up to and including `conflict` that share
+/// variables with it, directly or through other constraints, in the order they
+/// were applied.
+pub fn conflict_component(
+    constraints: &IndexMap<ConstraintId, LinearExpr>,
+    conflict: ConstraintId,
+) -> IndexSet<ConstraintId> {
+    let Some(expr) = constraints.get(&conflict) else {
+        return IndexSet::from([conflict]);
+    };
+    // Constraint IDs are allocated in the order constraints are applied.
+    let mut var_to_constraints: IndexMap<Var, Vec<ConstraintId>> = IndexMap::new();
+    for (id, expr) in constraints.iter().filter(|(id, _)| **id <= conflict) {
+        for (_, var) in &expr.coeffs {
+            var_to_constraints.entry(*var).or_default().push(*id);
+        }
+    }
+    let mut component = IndexSet::from([conflict]);
+    let mut visited_vars = IndexSet::new();
+    let mut queue = expr
+        .coeffs
+        .iter()
+        .map(|(_, var)| *var)
+        .collect::<VecDeque<_>>();
+    while let Some(var) = queue.pop_front() {
+        if !visited_vars.insert(var) {
+            continue;
+        }
+        for id in var_to_constraints.get(&var).into_iter().flatten() {
+            if component.insert(*id) {
+                queue.extend(constraints[id].coeffs.iter().map(|(_, var)| *var));
+            }
+        }
+    }
+    component.sort();
+    component
+}
+
+/// Returns an irreducible infeasible subset of `component`, the
+/// [`conflict_component`] of `conflict`, that explains `conflict`: constraints
+/// that cannot all hold, but any proper subset of which can.
+///
+/// `conflict` is kept in the subset unless the others conflict without it. The
+/// subset is found with a deletion filter, which solves the candidate
+/// constraints once per constraint. Returns `None` without searching if the
+/// component has more than [`MAX_INFEASIBLE_COMPONENT_SIZE`] constraints, or
+/// part way through if `exhausted` returns true before a solve.
+pub fn irreducible_infeasible_subset(
+    config: &SolverConfig,
+    constraints: &IndexMap<ConstraintId, LinearExpr>,
+    mut component: IndexSet<ConstraintId>,
+    conflict: ConstraintId,
+    mut exhausted: impl FnMut() -> bool,
+) -> Option<Vec<ConstraintId>> {
+    if !constraints.contains_key(&conflict) {
+        return Some(vec![conflict]);
+    }
+    if component.len() > MAX_INFEASIBLE_COMPONENT_SIZE {
+        return None;
+    }
+    let mut consistent = |set: &IndexSet<ConstraintId>| {
+        if exhausted() {
+            return None;
+        }
+        Some(is_consistent(config, set.iter().map(|id| &constraints[id])))
+    };
+    if consistent(&component)? {
+        // The conflict depends on constraints applied later, e.g. when a
+        // variable was rounded before `conflict` was checked.
+        return Some(vec![conflict]);
+    }
+    let mut without_conflict = component.clone();
+    without_conflict.shift_remove(&conflict);
+    let keep = consistent(&without_conflict)?.then_some(conflict);
+    for id in component.clone() {
+        if Some(id) == keep {
+            continue;
+        }
+        let mut candidate = component.clone();
+        candidate.shift_remove(&id);
+        if !consistent(&candidate)? {
+            component = candidate;
+        }
+    }
+    Some(component.into_iter().collect())
+}
+
 /// Replaces variable `v` in `expr` with `v_expr` (an expression equal to `v`),
 /// coalescing any resulting duplicate terms. Used by the elimination pre-pass.
 fn substitute_var(expr: &mut LinearExpr, v: Var, v_expr: &LinearExpr) {
@@ -801,6 +1013,69 @@ mod tests {
         assert!(!s.inconsistent_constraints().is_empty());
     }
 
+    /// Only the constraints that actually conflict are reported, not the
+    /// consistent constraints connected to them or unrelated ones.
+    #[test]
+    fn irreducible_infeasible_chain() {
+        let mut constraints = IndexMap::new();
+        let (a, b, d, e, f) = (Var(0), Var(1), Var(2), Var(3), Var(4));
+        constraints.insert(0, c(vec![(1., a)], 0.)); // a = 0
+        constraints.insert(1, c(vec![(1., f)], -1.)); // f = 1
+        constraints.insert(2, c(vec![(1., b), (-1., a)], -10.)); // b - a = 10
+        constraints.insert(3, c(vec![(1., e), (-1., a)], -5.)); // e - a = 5
+        constraints.insert(4, c(vec![(1., d), (-1., b)], -10.)); // c - b = 10
+        constraints.insert(5, c(vec![(1., d)], -25.)); // c = 25
+        constraints.insert(6, c(vec![(1., e)], -7.)); // e = 7
+        let config = SolverConfig::default();
+        let subset = |conflict| {
+            let component = conflict_component(&constraints, conflict);
+            irreducible_infeasible_subset(&config, &constraints, component, conflict, || false)
+        };
+        assert_eq!(subset(5), Some(vec![0, 2, 4, 5]));
+        // `a = 0` is dropped since the rest conflict without it.
+        assert_eq!(subset(6), Some(vec![2, 3, 4, 5, 6]));
+        // The search stops once its budget is spent.
+        let mut solves = 0;
+        let component = conflict_component(&constraints, 6);
+        assert_eq!(
+            irreducible_infeasible_subset(&config, &constraints, component, 6, || {
+                solves += 1;
+                solves > 2
+            }),
+            None
+        );
+    }
+
+    /// Adding constraints re-solves only the components they touch.
+    #[test]
+    fn add_constraints_and_resolve_reuses_solution() {
+        let mut s = Solver::new();
+        let [a, b, d, e, f, g, h] = std::array::from_fn(|_| s.new_var());
+        s.constrain_eq0(c(vec![(1., a), (1., b), (1., d)], -3.)); // a + b + d = 3
+        s.constrain_eq0(c(vec![(1., e), (1., f), (1., g), (1., h)], -4.)); // e + f + g + h = 4
+        s.solve();
+        assert_eq!(s.components_solved, 2);
+        // Re-solving an unchanged system does no work.
+        s.solve();
+        assert_eq!(s.components_solved, 2);
+
+        s.add_constraints_and_resolve([c(vec![(1., e), (-1., f)], 0.)]); // e - f = 0
+        assert_eq!(s.components_solved, 3);
+        assert!(s.value_of(e).is_none());
+
+        let ids = s.add_constraints_and_resolve([
+            c(vec![(1., f)], -1.), // f = 1
+            c(vec![(1., h)], -1.), // h = 1
+        ]);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(s.components_solved, 3);
+        for var in [e, f, g, h] {
+            assert_relative_eq!(s.value_of(var).unwrap(), 1., epsilon = EPSILON);
+        }
+        assert!(s.value_of(a).is_none());
+        assert!(s.inconsistent_constraints().is_empty());
+    }
+
     /// A duplicate constraint inside a coupled core is dropped as redundant (not flagged
     /// inconsistent), and the remaining system still solves.
     #[test]
@@ -851,6 +1126,44 @@ mod tests {
         assert_relative_eq!(s.value_of(d).unwrap(), 8., epsilon = EPSILON);
     }
 
+    /// The SVD backend skips the elimination pre-pass and solves the ring directly.
+    #[test]
+    fn svd_backend_solves_cycle() {
+        let mut s = Solver::with_config(SolverConfig {
+            backend: SolverBackend::Svd,
+            ..Default::default()
+        });
+        let a = s.new_var();
+        let b = s.new_var();
+        let d = s.new_var();
+        s.constrain_eq0(c(vec![(1., a), (-1., b)], -5.)); // a - b = 5
+        s.constrain_eq0(c(vec![(1., b), (-1., d)], -5.)); // b - c = 5
+        s.constrain_eq0(c(vec![(1., a), (1., d)], -100.)); // a + c = 100
+        s.solve();
+        assert_relative_eq!(s.value_of(a).unwrap(), 55., epsilon = EPSILON);
+        assert_relative_eq!(s.value_of(b).unwrap(), 50., epsilon = EPSILON);
+        assert_relative_eq!(s.value_of(d).unwrap(), 45., epsilon = EPSILON);
+        assert!(s.inconsistent_constraints().is_empty());
+    }
+
+    /// Solved values are rounded to the configured step, and values off that grid
+    /// are flagged in `invalid_rounding`.
+    #[test]
+    fn configured_round_step() {
+        let mut s = Solver::with_config(SolverConfig {
+            round_step: 5.,
+            ..Default::default()
+        });
+        let a = s.new_var();
+        let b = s.new_var();
+        s.constrain_eq0(c(vec![(1., a)], -10.)); // a = 10
+        s.constrain_eq0(c(vec![(1., b)], -12.)); // b = 12
+        assert_relative_eq!(s.value_of(a).unwrap(), 10., epsilon = EPSILON);
+        assert_relative_eq!(s.value_of(b).unwrap(), 10., epsilon = EPSILON);
+        assert!(!s.invalid_rounding().contains(&a));
+        assert!(s.invalid_rounding().contains(&b));
+    }
+
     /// A fully-coupled 3x3 block has no size-<=2 pivot: the pre-pass is a no-op and the
     /// dense SVD path solves it, exactly as before.
     #[test]
@@ -868,4 +1181,29 @@ mod tests {
         assert_relative_eq!(s.value_of(d).unwrap(), 3., epsilon = EPSILON);
         assert!(s.inconsistent_constraints().is_empty());
     }
+
+    /// Independent dense blocks are solved as separate components.
+    #[test]
+    fn independent_blocks_solved_separately() {
+        let mut s = Solver::new();
+        let blocks = (0..4)
+            .map(|k| {
+                let [a, b, d] = std::array::from_fn(|_| s.new_var());
+                let k = k as f64;
+                s.constrain_eq0(c(vec![(1., a), (1., b), (1., d)], -(6. + 3. * k)));
+                s.constrain_eq0(c(vec![(1., a), (2., b), (3., d)], -(14. + 6. * k)));
+                s.constrain_eq0(c(vec![(1., a), (3., b), (6., d)], -(25. + 10. * k)));
+                [a, b, d]
+            })
+            .collect_vec();
+        s.solve();
+        assert_eq!(s.components_solved, 4);
+        for (k, [a, b, d]) in blocks.into_iter().enumerate() {
+            let k = k as f64;
+            assert_relative_eq!(s.value_of(a).unwrap(), 1. + k, epsilon = EPSILON);
+            assert_relative_eq!(s.value_of(b).unwrap(), 2. + k, epsilon = EPSILON);
+            assert_relative_eq!(s.value_of(d).unwrap(), 3. + k, epsilon = EPSILON);
+        }
+        assert!(s.inconsistent_constraints().is_empty());
+    }
 }
@@ -0,0 +1,138 @@
+//! SVG rendering of a cell's flattened geometry.
+use std::{fmt::Write, path::Path};
+
+use anyhow::{Result, anyhow};
+use rgb::Rgb;
+use tracing::trace;
+
+use crate::compile::{CompileOutput, CompiledData, ExecErrorCompileOutput};
+
+/// Color of layers missing from the layer properties file.
+const UNKNOWN_LAYER_COLOR: Rgb<u8> = Rgb::new(0x80, 0x80, 0x80);
+/// Opacity of shape fills, so that overlapping layers remain visible.
+const FILL_OPACITY: f64 = 0.5;
+
+fn svg_color(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Escapes the characters of `text` that are special in XML.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl CompileOutput {
+    /// Renders the top cell to an SVG file.
+    pub fn to_svg(&self, out_path: impl AsRef<Path>) -> Result<()> {
+        let out_path = out_path.as_ref();
+        trace!("Exporting to svg at {out_path:?}");
+        let (CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            errors: _,
+            output: Some(output),
+        })) = self
+        else {
+            return Err(anyhow!("no compiled cell to export"));
+        };
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(out_path, output.to_svg_string()?)?;
+        Ok(())
+    }
+}
+
+impl CompiledData {
+    /// Renders the flattened rects, polygons, and top-level text labels of the
+    /// top cell as SVG, in layout units.
+    ///
+    /// Layers are colored and stacked as in the layer properties file, with
+    /// layers missing from it drawn on top in gray. The y-axis points up, as in
+    /// the GUI.
+    pub fn to_svg_string(&self) -> Result<String> {
+        let layers = &self.layers.layers;
+        let style = |layer: &str| {
+            let (z, fill, border) = match layers.iter().position(|l| l.name == layer) {
+                Some(z) => (z, layers[z].fill_color, layers[z].border_color),
+                None => (layers.len(), UNKNOWN_LAYER_COLOR, UNKNOWN_LAYER_COLOR),
+            };
+            (
+                z,
+                format!(
+                    "fill=\"{}\" fill-opacity=\"{FILL_OPACITY}\" stroke=\"{}\" \
+                     vector-effect=\"non-scaling-stroke\"",
+                    svg_color(fill),
+                    svg_color(border)
+                ),
+            )
+        };
+
+        let mut shapes = Vec::new();
+        for rect in self.iter_flat(self.top, None) {
+            let Some(layer) = rect.layer else {
+                continue;
+            };
+            let (z, style) = style(layer);
+            let bbox = rect.rect.bbox();
+            shapes.push((
+                z,
+                format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {style}/>",
+                    bbox.x0,
+                    bbox.y0,
+                    bbox.width(),
+                    bbox.height()
+                ),
+            ));
+        }
+        for (layer, points) in self.iter_flat_polygons(self.top, None) {
+            let (z, style) = style(layer);
+            let points = points
+                .iter()
+                .map(|(x, y)| format!("{x},{y}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            shapes.push((z, format!("<polygon points=\"{points}\" {style}/>")));
+        }
+        shapes.sort_by_key(|(z, _)| *z);
+
+        let bbox = self
+            .cell_bbox(self.top)
+            .map(|rect| rect.bbox())
+            .ok_or_else(|| anyhow!("top cell has no geometry to render"))?;
+        let mut out = String::new();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+            bbox.x0,
+            -bbox.y1,
+            bbox.width(),
+            bbox.height()
+        )?;
+        // Strokes do not scale with the drawing, since layout units may be
+        // tiny or huge relative to a screen pixel.
+        writeln!(out, "<g transform=\"scale(1,-1)\" stroke-width=\"1\">")?;
+        for (_, shape) in &shapes {
+            writeln!(out, "{shape}")?;
+        }
+        writeln!(out, "</g>")?;
+        for text in self.cells[&self.top]
+            .objects
+            .values()
+            .filter_map(|obj| obj.get_text())
+        {
+            writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                text.x,
+                -text.y,
+                xml_escape(&text.text)
+            )?;
+        }
+        writeln!(out, "</svg>")?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,159 @@
+//! Parameter sweeps of cell invocations.
+//!
+//! A sweep compiles a cell once for each of a range of values of one of its
+//! parameters, so that the GUI can step through the results and check that
+//! the geometry scales sensibly across the whole range.
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail};
+use arcstr::ArcStr;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{Expr, WorkspaceAst},
+    compile::{self, CellArg, CompileOutput, VarIdTyMetadata},
+    config::SolverConfig,
+    parse::WorkspaceParseAst,
+};
+
+/// The result of compiling one step of a sweep.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepFrame {
+    /// Value of the swept parameter.
+    pub value: f64,
+    /// Cell invocation compiled for this step, e.g. `inv(1200., 2000., 4)`.
+    pub invocation: String,
+    pub output: CompileOutput,
+}
+
+impl SweepFrame {
+    pub fn is_valid(&self) -> bool {
+        matches!(self.output, CompileOutput::Valid(_))
+    }
+}
+
+/// Returns `steps` evenly spaced values from `from` to `to`, inclusive.
+pub fn sweep_values(from: f64, to: f64, steps: usize) -> Vec<f64> {
+    match steps {
+        0 => Vec::new(),
+        1 => vec![from],
+        _ => (0..steps)
+            .map(|i| from + (to - from) * i as f64 / (steps - 1) as f64)
+            .collect(),
+    }
+}
+
+/// Returns the invocations of `cell` with its `param`th argument replaced by
+/// each of `values`.
+///
+/// Integer parameters are rounded to the nearest integer, so neighboring
+/// values may yield the same invocation.
+pub fn sweep_invocations(cell: &str, param: usize, values: &[f64]) -> anyhow::Result<Vec<String>> {
+    let cell_ast = crate::parse::parse_cell(cell)?;
+    let name = cell_ast.func.path.iter().map(|ident| ident.name).join("::");
+    let args = cell_ast
+        .args
+        .posargs
+        .iter()
+        .map(|arg| match arg {
+            Expr::FloatLiteral(float_literal) => Ok(CellArg::Float(float_literal.value)),
+            Expr::IntLiteral(int_literal) => Ok(CellArg::Int(int_literal.value)),
+            _ => Err(anyhow!("cell arguments must be int or float literals")),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if param >= args.len() {
+        bail!(
+            "cannot sweep parameter {param} of `{cell}`, which takes {} argument(s)",
+            args.len()
+        );
+    }
+    values
+        .iter()
+        .map(|&value| {
+            if value < 0. {
+                bail!("cannot sweep to negative value {value}");
+            }
+            let mut args = args.clone();
+            args[param] = match args[param] {
+                CellArg::Int(_) => CellArg::Int(value.round() as i64),
+                _ => CellArg::Float(value),
+            };
+            Ok(format!("{name}({})", args.iter().join(", ")))
+        })
+        .collect()
+}
+
+/// Compile outputs of swept invocations, reused until the workspace sources
+/// or solver options change.
+///
+/// Sweeping back and forth over a range, or over an integer parameter whose
+/// steps round to the same value, then only compiles each invocation once.
+#[derive(Debug, Default)]
+pub struct SweepCache {
+    /// Sources of the workspace the cached outputs were compiled from.
+    sources: Vec<ArcStr>,
+    /// Solver options the cached outputs were compiled with.
+    solver: SolverConfig,
+    outputs: HashMap<String, CompileOutput>,
+}
+
+impl SweepCache {
+    /// Clears the cache if the sources in `ast` differ from those the cached
+    /// outputs were compiled from.
+    pub fn invalidate(&mut self, ast: &WorkspaceParseAst) {
+        let sources = ast.values().map(|ast| ast.text.clone()).collect_vec();
+        if sources != self.sources {
+            self.sources = sources;
+            self.outputs.clear();
+        }
+    }
+
+    /// Compiles each of `invocations` against the statically compiled `ast`,
+    /// reusing cached outputs compiled with the same `solver` options.
+    /// `postprocess` runs on each newly compiled output before it is cached.
+    pub fn compile(
+        &mut self,
+        ast: &WorkspaceAst<VarIdTyMetadata>,
+        invocations: &[String],
+        values: &[f64],
+        lyp_file: &Path,
+        solver: &SolverConfig,
+        mut postprocess: impl FnMut(&mut CompileOutput),
+    ) -> anyhow::Result<Vec<SweepFrame>> {
+        if *solver != self.solver {
+            self.solver = solver.clone();
+            self.outputs.clear();
+        }
+        invocations
+            .iter()
+            .zip(values)
+            .map(|(invocation, &value)| {
+                let output = match self.outputs.get(invocation) {
+                    Some(output) => output.clone(),
+                    None => {
+                        let mut output =
+                            compile::dynamic_compile_invocation(ast, invocation, lyp_file, solver)?;
+                        postprocess(&mut output);
+                        self.outputs.insert(invocation.clone(), output.clone());
+                        output
+                    }
+                };
+                Ok(SweepFrame {
+                    value,
+                    invocation: invocation.clone(),
+                    output,
+                })
+            })
+            .collect()
+    }
+
+    /// Number of distinct invocations compiled since the cache was last cleared.
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+}
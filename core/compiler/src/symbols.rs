@@ -0,0 +1,438 @@
+//! Definitions of the names in a workspace and the references to them.
+//!
+//! Names are resolved as the compiler resolves them: a single name refers to the
+//! innermost argument, `let` binding, or loop variable in scope, or else to a
+//! declaration of the current module. Paths are relative to the current module
+//! unless they start with `crate` or `std`, e.g. `crate::utils::width` or
+//! `Layer::Met1`. Builtins and types such as `Float` have no definition in the
+//! workspace and are skipped, as are names that do not resolve.
+use std::path::{Path, PathBuf};
+
+use arcstr::Substr;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{
+        ArgDecl, Decl, Expr, Ident, IdentPath, ModPath, Scope, Span, Statement, TySpec, TySpecKind,
+    },
+    parse::{ParseMetadata, WorkspaceParseAst},
+};
+
+/// What a [`Symbol`] names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymbolKind {
+    Cell,
+    Fn,
+    Enum,
+    Variant,
+    Struct,
+    Constant,
+    Mod,
+    Arg,
+    Let,
+    LoopVar,
+}
+
+/// Index of a [`Symbol`] in [`SymbolTable::symbols`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SymbolId(pub usize);
+
+/// A named declaration, argument, or binding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Span of the name where it is defined, or the start of the file of a
+    /// module.
+    pub span: Span,
+}
+
+/// A use of a [`Symbol`] by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolRef {
+    pub symbol: SymbolId,
+    /// Span of the name, or of its segment of a path.
+    pub span: Span,
+}
+
+/// The symbols defined in a workspace and every reference to them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+    pub refs: Vec<SymbolRef>,
+}
+
+impl SymbolTable {
+    /// Resolves the names in every module of `ast`, including the standard
+    /// library.
+    pub fn from_workspace(ast: &WorkspaceParseAst) -> Self {
+        let mut builder = Builder {
+            ast,
+            table: SymbolTable::default(),
+            decls: IndexMap::new(),
+            variants: IndexMap::new(),
+            mod_path: Vec::new(),
+            path: PathBuf::new(),
+            frames: Vec::new(),
+        };
+        for (mod_path, file) in ast {
+            builder.declare(mod_path, &file.path, &file.ast.decls);
+        }
+        for (mod_path, file) in ast {
+            builder.mod_path = mod_path.clone();
+            builder.path = file.path.clone();
+            for decl in &file.ast.decls {
+                builder.decl(decl);
+            }
+        }
+        builder.table
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    /// Returns the symbol defined or referenced at `offset` in the file at
+    /// `path`.
+    pub fn find(&self, path: &Path, offset: usize) -> Option<SymbolId> {
+        let contains = |span: &Span| {
+            span.path == path
+                && span.span.start() < span.span.end()
+                && span.span.start() <= offset
+                && offset <= span.span.end()
+        };
+        self.refs
+            .iter()
+            .find(|r| contains(&r.span))
+            .map(|r| r.symbol)
+            .or_else(|| {
+                self.symbols
+                    .iter()
+                    .position(|symbol| contains(&symbol.span))
+                    .map(SymbolId)
+            })
+    }
+
+    /// The references to the symbol `id`, excluding its definition.
+    pub fn references(&self, id: SymbolId) -> impl Iterator<Item = &SymbolRef> {
+        self.refs.iter().filter(move |r| r.symbol == id)
+    }
+}
+
+struct Builder<'a> {
+    ast: &'a WorkspaceParseAst,
+    table: SymbolTable,
+    /// The declarations of each module by name, including its submodules.
+    decls: IndexMap<ModPath, IndexMap<String, SymbolId>>,
+    /// The variants of each enum by name.
+    variants: IndexMap<SymbolId, IndexMap<String, SymbolId>>,
+    mod_path: ModPath,
+    path: PathBuf,
+    /// Names bound by the enclosing scopes, innermost last.
+    frames: Vec<Vec<(String, SymbolId)>>,
+}
+
+impl Builder<'_> {
+    fn span(&self, span: cfgrammar::Span) -> Span {
+        Span {
+            path: self.path.clone(),
+            span,
+        }
+    }
+
+    fn define(&mut self, name: &str, kind: SymbolKind, span: Span) -> SymbolId {
+        let id = SymbolId(self.table.symbols.len());
+        self.table.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            span,
+        });
+        id
+    }
+
+    fn refer(&mut self, id: SymbolId, span: cfgrammar::Span) {
+        let span = self.span(span);
+        self.table.refs.push(SymbolRef { symbol: id, span });
+    }
+
+    /// Defines the declarations of the module at `mod_path`.
+    fn declare(&mut self, mod_path: &ModPath, path: &Path, decls: &[Decl<Substr, ParseMetadata>]) {
+        self.path = path.to_path_buf();
+        let mut names = IndexMap::new();
+        for decl in decls {
+            let (name, kind) = match decl {
+                Decl::Cell(cell) => (&cell.name, SymbolKind::Cell),
+                Decl::Fn(f) => (&f.name, SymbolKind::Fn),
+                Decl::Enum(e) => (&e.name, SymbolKind::Enum),
+                Decl::Struct(s) => (&s.name, SymbolKind::Struct),
+                Decl::Constant(c) => (&c.name, SymbolKind::Constant),
+                Decl::Mod(m) => (&m.ident, SymbolKind::Mod),
+            };
+            let id = if let Decl::Mod(m) = decl {
+                // Modules are defined by their files, which the declaration
+                // refers to.
+                let mut child = mod_path.clone();
+                child.push(m.ident.name.to_string());
+                let span = match self.ast.get(&child).filter(|file| !file.text.is_empty()) {
+                    Some(file) => Span {
+                        path: file.path.clone(),
+                        span: cfgrammar::Span::new(0, 0),
+                    },
+                    None => self.span(m.ident.span),
+                };
+                let id = self.define(&name.name, kind, span);
+                self.refer(id, m.ident.span);
+                id
+            } else {
+                self.define(&name.name, kind, self.span(name.span))
+            };
+            if let Decl::Enum(e) = decl {
+                let variants = e
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let span = self.span(variant.name.span);
+                        let variant_id = self.define(&variant.name.name, SymbolKind::Variant, span);
+                        (variant.name.name.to_string(), variant_id)
+                    })
+                    .collect();
+                self.variants.insert(id, variants);
+            }
+            names.entry(name.name.to_string()).or_insert(id);
+        }
+        self.decls.insert(mod_path.clone(), names);
+    }
+
+    fn decl(&mut self, decl: &Decl<Substr, ParseMetadata>) {
+        match decl {
+            Decl::Cell(cell) => {
+                self.frames.push(Vec::new());
+                self.args(&cell.args);
+                if let Some(bbox) = &cell.bbox {
+                    for kwarg in &bbox.kwargs {
+                        self.expr(&kwarg.value);
+                    }
+                }
+                self.scope(&cell.scope);
+                self.frames.pop();
+            }
+            Decl::Fn(f) => {
+                self.frames.push(Vec::new());
+                self.args(&f.args);
+                if let Some(ty) = &f.return_ty {
+                    self.ty(ty);
+                }
+                self.scope(&f.scope);
+                self.frames.pop();
+            }
+            Decl::Enum(e) => {
+                for field in &e.fields {
+                    self.ty_name(&field.ty);
+                }
+                for variant in &e.variants {
+                    for value in variant.values.iter().chain(&variant.discriminant) {
+                        self.expr(value);
+                    }
+                }
+            }
+            Decl::Struct(s) => {
+                for field in &s.fields {
+                    self.ty_name(&field.ty);
+                }
+            }
+            Decl::Constant(c) => {
+                self.ty_name(&c.ty);
+                self.expr(&c.value);
+            }
+            Decl::Mod(_) => {}
+        }
+    }
+
+    fn args(&mut self, args: &[ArgDecl<Substr, ParseMetadata>]) {
+        for arg in args {
+            self.ty(&arg.ty);
+            self.bind(&arg.name, SymbolKind::Arg);
+        }
+    }
+
+    /// Defines `name` in the innermost scope.
+    fn bind(&mut self, name: &Ident<Substr, ParseMetadata>, kind: SymbolKind) {
+        let id = self.define(&name.name, kind, self.span(name.span));
+        if let Some(frame) = self.frames.last_mut() {
+            frame.push((name.name.to_string(), id));
+        }
+    }
+
+    fn ty(&mut self, ty: &TySpec<Substr, ParseMetadata>) {
+        match &ty.kind {
+            TySpecKind::Ident(ident) => self.ty_name(ident),
+            TySpecKind::Seq(elem) => self.ty(elem),
+            TySpecKind::Tuple(tys) => {
+                for ty in tys {
+                    self.ty(ty);
+                }
+            }
+        }
+    }
+
+    fn ty_name(&mut self, ident: &Ident<Substr, ParseMetadata>) {
+        if let Some(&id) = self
+            .decls
+            .get(&self.mod_path)
+            .and_then(|names| names.get(ident.name.as_str()))
+        {
+            self.refer(id, ident.span);
+        }
+    }
+
+    fn scope(&mut self, scope: &Scope<Substr, ParseMetadata>) {
+        self.frames.push(Vec::new());
+        for stmt in &scope.stmts {
+            match stmt {
+                Statement::Expr { value, .. } => self.expr(value),
+                Statement::LetBinding(binding) => {
+                    self.expr(&binding.value);
+                    self.bind(&binding.name, SymbolKind::Let);
+                }
+                Statement::ForLoop(for_loop) => {
+                    self.expr(&for_loop.seq);
+                    self.frames.push(Vec::new());
+                    self.bind(&for_loop.var, SymbolKind::LoopVar);
+                    self.scope(&for_loop.body);
+                    self.frames.pop();
+                }
+            }
+        }
+        if let Some(tail) = &scope.tail {
+            self.expr(tail);
+        }
+        self.frames.pop();
+    }
+
+    /// Resolves the segments of `path` that name symbols.
+    fn path(&mut self, path: &IdentPath<Substr, ParseMetadata>) {
+        if let [ident] = path.path.as_slice() {
+            let local = self
+                .frames
+                .iter()
+                .rev()
+                .flat_map(|frame| frame.iter().rev())
+                .find(|(name, _)| *name == ident.name.as_str())
+                .map(|(_, id)| *id);
+            let id = local.or_else(|| {
+                self.decls
+                    .get(&self.mod_path)
+                    .and_then(|names| names.get(ident.name.as_str()).copied())
+            });
+            if let Some(id) = id {
+                self.refer(id, ident.span);
+            }
+            return;
+        }
+        let (mut mod_path, segments) = match path.path[0].name.as_str() {
+            "std" => (vec!["std".to_string()], &path.path[1..]),
+            "crate" => (Vec::new(), &path.path[1..]),
+            _ => (self.mod_path.clone(), path.path.as_slice()),
+        };
+        let Some((last, prefix)) = segments.split_last() else {
+            return;
+        };
+        let mut enum_id = None;
+        for (i, ident) in prefix.iter().enumerate() {
+            let Some(&id) = self
+                .decls
+                .get(&mod_path)
+                .and_then(|names| names.get(ident.name.as_str()))
+            else {
+                return;
+            };
+            self.refer(id, ident.span);
+            match self.table.symbol(id).kind {
+                SymbolKind::Mod => mod_path.push(ident.name.to_string()),
+                SymbolKind::Enum if i + 1 == prefix.len() => enum_id = Some(id),
+                _ => return,
+            }
+        }
+        let id = match enum_id {
+            Some(enum_id) => self.variants[&enum_id].get(last.name.as_str()),
+            None => self
+                .decls
+                .get(&mod_path)
+                .and_then(|names| names.get(last.name.as_str())),
+        };
+        if let Some(&id) = id {
+            self.refer(id, last.span);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<Substr, ParseMetadata>) {
+        match expr {
+            Expr::If(e) => {
+                self.expr(&e.cond);
+                self.scope(&e.then);
+                self.scope(&e.else_);
+            }
+            Expr::Match(e) => {
+                self.expr(&e.scrutinee);
+                for arm in &e.arms {
+                    if let Some(pattern) = &arm.pattern {
+                        self.path(pattern);
+                    }
+                    self.expr(&arm.expr);
+                }
+            }
+            Expr::Comparison(e) => {
+                self.expr(&e.left);
+                self.expr(&e.right);
+            }
+            Expr::BinOp(e) => {
+                self.expr(&e.left);
+                self.expr(&e.right);
+            }
+            Expr::UnaryOp(e) => self.expr(&e.operand),
+            Expr::Call(call) => {
+                self.path(&call.func);
+                for arg in &call.args.posargs {
+                    self.expr(arg);
+                }
+                for kwarg in &call.args.kwargs {
+                    self.expr(&kwarg.value);
+                }
+            }
+            Expr::Emit(e) => self.expr(&e.value),
+            Expr::FieldAccess(e) => self.expr(&e.base),
+            Expr::IndexFieldAccess(e) => self.expr(&e.base),
+            Expr::Index(e) => {
+                self.expr(&e.base);
+                self.expr(&e.index);
+            }
+            Expr::IdentPath(path) => self.path(path),
+            Expr::Scope(scope) => self.scope(scope),
+            Expr::Cast(e) => {
+                self.expr(&e.value);
+                self.ty(&e.ty);
+            }
+            Expr::Tuple(e) => {
+                for item in &e.items {
+                    self.expr(item);
+                }
+            }
+            Expr::Range(e) => {
+                self.expr(&e.start);
+                self.expr(&e.end);
+            }
+            Expr::Seq(e) => {
+                for item in &e.items {
+                    self.expr(item);
+                }
+            }
+            Expr::Nil(_)
+            | Expr::SeqNil(_)
+            | Expr::FloatLiteral(_)
+            | Expr::IntLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::BoolLiteral(_) => {}
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Axis-aligned bounding boxes.
+use serde::{Deserialize, Serialize};
+
+use crate::Transform;
+
+/// An axis-aligned box with `x0 <= x1` and `y0 <= y1`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bbox {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Bbox {
+    pub fn new(x0: f64, y0: f64, x1: f64, y1: f64) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+
+    /// The smallest box containing both corners, in any order.
+    pub fn from_corners(p0: (f64, f64), p1: (f64, f64)) -> Self {
+        Self {
+            x0: p0.0.min(p1.0),
+            y0: p0.1.min(p1.1),
+            x1: p0.0.max(p1.0),
+            y1: p0.1.max(p1.1),
+        }
+    }
+
+    pub fn point(pt: (f64, f64)) -> Self {
+        Self::from_corners(pt, pt)
+    }
+
+    pub fn width(&self) -> f64 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> f64 {
+        self.y1 - self.y0
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width() * self.height()
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        2. * (self.width() + self.height())
+    }
+
+    /// Returns the overlap of two boxes, or `None` if they do not overlap with
+    /// positive area.
+    pub fn intersection(&self, other: &Bbox) -> Option<Bbox> {
+        let bbox = Bbox {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+        };
+        (bbox.x0 < bbox.x1 && bbox.y0 < bbox.y1).then_some(bbox)
+    }
+
+    /// Whether `other` lies entirely within this box, including its edges.
+    pub fn contains(&self, other: &Bbox) -> bool {
+        self.x0 <= other.x0 && self.y0 <= other.y0 && other.x1 <= self.x1 && other.y1 <= self.y1
+    }
+
+    /// Returns the union of two optional boxes, where `None` is empty.
+    pub fn union(b1: Option<Bbox>, b2: Option<Bbox>) -> Option<Bbox> {
+        match (b1, b2) {
+            (Some(b1), Some(b2)) => Some(Bbox {
+                x0: b1.x0.min(b2.x0),
+                y0: b1.y0.min(b2.y0),
+                x1: b1.x1.max(b2.x1),
+                y1: b1.y1.max(b2.y1),
+            }),
+            (Some(b), None) | (None, Some(b)) => Some(b),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the corners of the box, counterclockwise from `(x0, y0)`.
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        [
+            (self.x0, self.y0),
+            (self.x1, self.y0),
+            (self.x1, self.y1),
+            (self.x0, self.y1),
+        ]
+    }
+
+    /// Maps this box through `t`, returning the bounding box of the result.
+    ///
+    /// The result is exact for Manhattan transforms.
+    pub fn transform(&self, t: &Transform) -> Bbox {
+        if t.is_manhattan() {
+            return Bbox::from_corners(t.apply((self.x0, self.y0)), t.apply((self.x1, self.y1)));
+        }
+        self.corners()
+            .map(|pt| Bbox::point(t.apply(pt)))
+            .into_iter()
+            .reduce(|a, b| Bbox::union(Some(a), Some(b)).unwrap())
+            .unwrap()
+    }
+}
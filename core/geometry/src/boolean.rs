@@ -0,0 +1,57 @@
+//! Boolean operations on sets of boxes.
+//!
+//! Results are returned as disjoint boxes covering the resulting region. The
+//! decomposition is into maximal horizontal strips, with strips of identical
+//! horizontal extent merged vertically.
+use crate::Bbox;
+
+/// Returns the region covered by any of `boxes` as disjoint boxes.
+pub fn merge(boxes: &[Bbox]) -> Vec<Bbox> {
+    subtract(boxes, &[])
+}
+
+/// Returns the region covered by `boxes` but not by `cut` as disjoint boxes.
+pub fn subtract(boxes: &[Bbox], cut: &[Bbox]) -> Vec<Bbox> {
+    let coords = |f: fn(&Bbox) -> [f64; 2]| {
+        let mut coords = boxes.iter().chain(cut).flat_map(f).collect::<Vec<_>>();
+        coords.sort_by(f64::total_cmp);
+        coords.dedup();
+        coords
+    };
+    let xs = coords(|b| [b.x0, b.x1]);
+    let ys = coords(|b| [b.y0, b.y1]);
+    let contains = |b: &Bbox, x: f64, y: f64| b.x0 < x && x < b.x1 && b.y0 < y && y < b.y1;
+
+    let mut out: Vec<Bbox> = Vec::new();
+    // Indices into `out` of the boxes ending at the bottom of the current strip.
+    let mut open: Vec<usize> = Vec::new();
+    for y in ys.windows(2) {
+        let ym = (y[0] + y[1]) / 2.;
+        let mut runs: Vec<(f64, f64)> = Vec::new();
+        for x in xs.windows(2) {
+            let xm = (x[0] + x[1]) / 2.;
+            let covered = boxes.iter().any(|b| contains(b, xm, ym))
+                && !cut.iter().any(|b| contains(b, xm, ym));
+            if !covered {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if run.1 == x[0] => run.1 = x[1],
+                _ => runs.push((x[0], x[1])),
+            }
+        }
+
+        let mut next_open = Vec::with_capacity(runs.len());
+        for (x0, x1) in runs {
+            if let Some(&i) = open.iter().find(|&&i| out[i].x0 == x0 && out[i].x1 == x1) {
+                out[i].y1 = y[1];
+                next_open.push(i);
+            } else {
+                out.push(Bbox::new(x0, y[0], x1, y[1]));
+                next_open.push(out.len() - 1);
+            }
+        }
+        open = next_open;
+    }
+    out
+}
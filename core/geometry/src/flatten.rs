@@ -0,0 +1,48 @@
+//! Breadth-first traversal of instance hierarchies.
+use std::collections::VecDeque;
+
+use crate::Transform;
+
+/// Iterator over the nodes of a hierarchy in breadth-first order.
+///
+/// Each node is yielded with its world-space transform and depth. The
+/// `expand` callback returns a node's children along with their transforms
+/// relative to that node; children deeper than `max_depth` are not visited.
+pub struct Flatten<N, F> {
+    queue: VecDeque<(N, Transform, usize)>,
+    max_depth: Option<usize>,
+    expand: F,
+}
+
+impl<N, F, I> Flatten<N, F>
+where
+    F: FnMut(&N, &Transform, usize) -> I,
+    I: IntoIterator<Item = (N, Transform)>,
+{
+    pub fn new(root: N, max_depth: Option<usize>, expand: F) -> Self {
+        Self {
+            queue: VecDeque::from([(root, Transform::identity(), 0)]),
+            max_depth,
+            expand,
+        }
+    }
+}
+
+impl<N, F, I> Iterator for Flatten<N, F>
+where
+    F: FnMut(&N, &Transform, usize) -> I,
+    I: IntoIterator<Item = (N, Transform)>,
+{
+    type Item = (N, Transform, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, transform, depth) = self.queue.pop_front()?;
+        if self.max_depth.is_none_or(|max_depth| depth < max_depth) {
+            for (child, local) in (self.expand)(&node, &transform, depth) {
+                self.queue
+                    .push_back((child, transform.compose(&local), depth + 1));
+            }
+        }
+        Some((node, transform, depth))
+    }
+}
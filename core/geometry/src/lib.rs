@@ -0,0 +1,145 @@
+//! Geometry shared by the Argon compiler, GDS exporter, and GUI.
+//!
+//! Coordinates are `f64` in layout units. Transforms are an optional
+//! vertical reflection and a counterclockwise rotation followed by a
+//! translation, matching the semantics of Argon instances. Rotations are
+//! usually, but not necessarily, multiples of 90 degrees.
+pub mod bbox;
+pub mod boolean;
+pub mod flatten;
+pub mod transform;
+
+pub use bbox::Bbox;
+pub use flatten::Flatten;
+pub use geometry::transform::{Rotation, TransformationMatrix};
+pub use transform::{Transform, ifmatvec, imat, manhattan_rotation, normalize_angle, tmat};
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::{Bbox, Flatten, Rotation, Transform, boolean};
+
+    const EPSILON: f64 = 1e-10;
+
+    #[test]
+    fn transform_applies_rotation_before_translation() {
+        let t = Transform::new(Rotation::R90, false, 10., 20.);
+        let (x, y) = t.apply((1., 0.));
+        assert_relative_eq!(x, 10., epsilon = EPSILON);
+        assert_relative_eq!(y, 21., epsilon = EPSILON);
+    }
+
+    #[test]
+    fn transform_compose_matches_sequential_application() {
+        let parent = Transform::new(Rotation::R90, true, 5., -3.);
+        let child = Transform::new(Rotation::R180, false, 2., 7.);
+        let composed = parent.compose(&child);
+        for pt in [(0., 0.), (1., 2.), (-4., 3.)] {
+            let expected = parent.apply(child.apply(pt));
+            let actual = composed.apply(pt);
+            assert_relative_eq!(actual.0, expected.0, epsilon = EPSILON);
+            assert_relative_eq!(actual.1, expected.1, epsilon = EPSILON);
+        }
+        let (rot, reflect) = composed.orientation();
+        let rebuilt = Transform::rotate(rot, reflect, composed.offset.0, composed.offset.1);
+        assert_eq!(rebuilt, composed);
+        assert_eq!(composed.rotation(), Some(Rotation::R270));
+    }
+
+    #[test]
+    fn transform_supports_arbitrary_angles() {
+        let parent = Transform::rotate(30., true, 5., -3.);
+        let child = Transform::rotate(45., false, 2., 7.);
+        let composed = parent.compose(&child);
+        assert!(!composed.is_manhattan());
+        assert_relative_eq!(composed.angle, 345., epsilon = EPSILON);
+        for pt in [(0., 0.), (1., 2.), (-4., 3.)] {
+            let expected = parent.apply(child.apply(pt));
+            let actual = composed.apply(pt);
+            assert_relative_eq!(actual.0, expected.0, epsilon = EPSILON);
+            assert_relative_eq!(actual.1, expected.1, epsilon = EPSILON);
+        }
+
+        let (x, y) = Transform::rotate(45., false, 0., 0.).apply((1., 0.));
+        assert_relative_eq!(x, 0.5f64.sqrt(), epsilon = EPSILON);
+        assert_relative_eq!(y, 0.5f64.sqrt(), epsilon = EPSILON);
+        assert_eq!(
+            Transform::rotate(-90., false, 0., 0.).rotation(),
+            Some(Rotation::R270)
+        );
+        let bbox = Bbox::new(0., 0., 10., 10.).transform(&Transform::rotate(45., false, 0., 0.));
+        assert_relative_eq!(bbox.x0, -(50f64.sqrt()), epsilon = EPSILON);
+        assert_relative_eq!(bbox.y0, 0., epsilon = EPSILON);
+        assert_relative_eq!(bbox.x1, 50f64.sqrt(), epsilon = EPSILON);
+        assert_relative_eq!(bbox.y1, 200f64.sqrt(), epsilon = EPSILON);
+    }
+
+    #[test]
+    fn bbox_transform_normalizes_corners() {
+        let bbox = Bbox::new(0., 0., 100., 50.);
+        let t = Transform::new(Rotation::R90, false, 0., 0.);
+        let out = bbox.transform(&t);
+        assert_eq!(out, Bbox::new(-50., 0., 0., 100.));
+        assert_eq!(
+            Bbox::union(Some(bbox), Some(Bbox::new(-10., 10., 20., 70.))),
+            Some(Bbox::new(-10., 0., 100., 70.))
+        );
+    }
+
+    #[test]
+    fn bbox_measurements() {
+        let a = Bbox::new(0., 0., 100., 50.);
+        let b = Bbox::new(60., 20., 160., 120.);
+        assert_eq!(a.area(), 5000.);
+        assert_eq!(a.perimeter(), 300.);
+        assert_eq!(a.intersection(&b), Some(Bbox::new(60., 20., 100., 50.)));
+        assert_eq!(a.intersection(&b).map(|i| i.area()), Some(1200.));
+        // Boxes that only share an edge do not overlap.
+        assert_eq!(a.intersection(&Bbox::new(100., 0., 200., 50.)), None);
+        assert!(a.contains(&Bbox::new(0., 10., 40., 50.)));
+        assert!(!a.contains(&b));
+    }
+
+    #[test]
+    fn boolean_ops_produce_disjoint_boxes() {
+        let area = |boxes: &[Bbox]| boxes.iter().map(|b| b.width() * b.height()).sum::<f64>();
+
+        // Two overlapping boxes forming an L.
+        let merged = boolean::merge(&[Bbox::new(0., 0., 100., 50.), Bbox::new(0., 0., 50., 100.)]);
+        assert_eq!(
+            merged,
+            vec![Bbox::new(0., 0., 100., 50.), Bbox::new(0., 50., 50., 100.)]
+        );
+
+        // A notch cut into the top edge of a well.
+        let notched = boolean::subtract(
+            &[Bbox::new(0., 0., 300., 200.)],
+            &[Bbox::new(100., 150., 200., 250.)],
+        );
+        assert_eq!(notched.len(), 3);
+        assert_relative_eq!(area(&notched), 300. * 200. - 100. * 50., epsilon = EPSILON);
+
+        assert!(
+            boolean::subtract(
+                &[Bbox::new(0., 0., 10., 10.)],
+                &[Bbox::new(-5., -5., 15., 15.)]
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn flatten_composes_transforms_and_respects_max_depth() {
+        // Node `n` has a single child `n + 1` offset by 10 in x.
+        let nodes = Flatten::new(0u32, Some(2), |n, _, _| {
+            [(*n + 1, Transform::translate(10., 0.))]
+        })
+        .map(|(n, t, depth)| (n, t.offset, depth))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            vec![(0, (0., 0.), 0), (1, (10., 0.), 1), (2, (20., 0.), 2)]
+        );
+    }
+}
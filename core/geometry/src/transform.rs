@@ -0,0 +1,163 @@
+//! Rotations, reflections, and translations.
+use geometry::transform::{Rotation, TransformationMatrix};
+use serde::{Deserialize, Serialize};
+
+/// Tolerance used to recognize Manhattan angles.
+const ANGLE_EPSILON: f64 = 1e-9;
+
+/// Multiplies `pt` by the integer matrix `mat`.
+pub fn ifmatvec(mat: TransformationMatrix, pt: (f64, f64)) -> (f64, f64) {
+    (
+        mat[0][0] as f64 * pt.0 + mat[0][1] as f64 * pt.1,
+        mat[1][0] as f64 * pt.0 + mat[1][1] as f64 * pt.1,
+    )
+}
+
+/// Returns the matrix that reflects vertically (if `refv` is set) and then rotates by `rot`.
+pub fn tmat(rot: Rotation, refv: bool) -> TransformationMatrix {
+    let mut mat = TransformationMatrix::identity();
+    if refv {
+        mat = mat.reflect_vert()
+    }
+    mat = mat.rotate(rot);
+    mat
+}
+
+/// Inverse of [`tmat`].
+///
+/// # Panics
+///
+/// Panics if `mat` is not a Manhattan rotation/reflection matrix.
+pub fn imat(mat: TransformationMatrix) -> (Rotation, bool) {
+    let refv = mat[1][0] == mat[0][1] && mat[0][0] == -mat[1][1];
+    let rot = match (mat[0][0], mat[1][0]) {
+        (1, 0) => Rotation::R0,
+        (0, 1) => Rotation::R90,
+        (-1, 0) => Rotation::R180,
+        (0, -1) => Rotation::R270,
+        _ => panic!("invalid rotation matrix"),
+    };
+    (rot, refv)
+}
+
+/// Normalizes an angle in degrees to `[0, 360)`.
+pub fn normalize_angle(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.);
+    if (360. - angle).abs() < ANGLE_EPSILON {
+        0.
+    } else {
+        angle
+    }
+}
+
+/// Returns the Manhattan rotation by `angle` degrees, if there is one.
+pub fn manhattan_rotation(angle: f64) -> Option<Rotation> {
+    let angle = normalize_angle(angle);
+    [Rotation::R0, Rotation::R90, Rotation::R180, Rotation::R270]
+        .into_iter()
+        .find(|rot| (rot.degrees() - angle).abs() < ANGLE_EPSILON)
+}
+
+/// Returns the cosine and sine of `angle` degrees, exactly for Manhattan angles.
+fn cos_sin(angle: f64) -> (f64, f64) {
+    match manhattan_rotation(angle) {
+        Some(Rotation::R0) => (1., 0.),
+        Some(Rotation::R90) => (0., 1.),
+        Some(Rotation::R180) => (-1., 0.),
+        Some(Rotation::R270) => (0., -1.),
+        None => {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            (cos, sin)
+        }
+    }
+}
+
+/// A vertical reflection followed by a counterclockwise rotation and a
+/// translation.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    /// Counterclockwise rotation in degrees, in `[0, 360)`.
+    pub angle: f64,
+    /// Whether to reflect about the x-axis before rotating.
+    pub reflect: bool,
+    pub offset: (f64, f64),
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    /// The transform of an instance placed at `(x, y)` with the given orientation.
+    pub fn new(rot: Rotation, reflect: bool, x: f64, y: f64) -> Self {
+        Self::rotate(rot.degrees(), reflect, x, y)
+    }
+
+    /// Like [`Transform::new`], but rotating by an arbitrary angle in degrees.
+    pub fn rotate(angle: f64, reflect: bool, x: f64, y: f64) -> Self {
+        Self {
+            angle: normalize_angle(angle),
+            reflect,
+            offset: (x, y),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::translate(0., 0.)
+    }
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self {
+            angle: 0.,
+            reflect: false,
+            offset: (x, y),
+        }
+    }
+
+    /// Returns the rotation in degrees and vertical reflection of this transform.
+    pub fn orientation(&self) -> (f64, bool) {
+        (self.angle, self.reflect)
+    }
+
+    /// Returns the Manhattan rotation of this transform, or `None` if it rotates
+    /// by an angle that is not a multiple of 90 degrees.
+    pub fn rotation(&self) -> Option<Rotation> {
+        manhattan_rotation(self.angle)
+    }
+
+    /// Whether the transform maps axis-aligned boxes to axis-aligned boxes.
+    pub fn is_manhattan(&self) -> bool {
+        self.rotation().is_some()
+    }
+
+    /// Maps a point from local coordinates to parent coordinates.
+    pub fn apply(&self, pt: (f64, f64)) -> (f64, f64) {
+        let (x, y) = self.apply_vec(pt);
+        (x + self.offset.0, y + self.offset.1)
+    }
+
+    /// Maps a direction vector, ignoring the translation.
+    pub fn apply_vec(&self, v: (f64, f64)) -> (f64, f64) {
+        let (x, y) = if self.reflect { (v.0, -v.1) } else { v };
+        let (cos, sin) = cos_sin(self.angle);
+        (cos * x - sin * y, sin * x + cos * y)
+    }
+
+    /// Returns the transform equivalent to applying `child` and then `self`.
+    pub fn compose(&self, child: &Transform) -> Transform {
+        // Reflecting before rotating by `a` is the same as rotating by `-a`
+        // and then reflecting.
+        let child_angle = if self.reflect {
+            -child.angle
+        } else {
+            child.angle
+        };
+        Transform {
+            angle: normalize_angle(self.angle + child_angle),
+            reflect: self.reflect != child.reflect,
+            offset: self.apply(child.offset),
+        }
+    }
+}
@@ -8,16 +8,33 @@ actions!(
         DrawRect,
         SelectMode,
         DrawDim,
+        QueryMode,
         Edit,
         Fit,
         Zero,
         One,
         All,
         EditDim,
+        RectDims,
         Undo,
         Redo,
         Command,
         Cancel,
+        NudgeLeft,
+        NudgeRight,
+        NudgeUp,
+        NudgeDown,
+        NudgeLeftCoarse,
+        NudgeRightCoarse,
+        NudgeUpCoarse,
+        NudgeDownCoarse,
+        ToggleGrid,
+        ZoomIn,
+        ZoomOut,
+        ZoomReset,
+        ZoomBack,
+        NextDrcMarker,
+        PrevDrcMarker,
         Backspace,
         Delete,
         Left,
@@ -0,0 +1,252 @@
+//! KLayout report database (`.lyrdb`) import for displaying sign-off DRC results.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use argon_geometry::{Bbox, Flatten, Transform};
+use compiler::compile::{CellId, CompiledData};
+use itertools::Itertools;
+
+/// Layout units per micron, matching the database units used for GDS export.
+pub const LAYOUT_UNITS_PER_MICRON: f64 = 1e3;
+
+#[derive(Clone, Debug, Default)]
+pub struct MarkerDb {
+    pub description: String,
+    pub top_cell: String,
+    pub markers: Vec<Marker>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Marker {
+    pub category: String,
+    /// Name of the cell whose coordinate system the shapes are given in.
+    pub cell: String,
+    /// Bounding boxes of each shape in the marker, in microns.
+    pub shapes: Vec<Bbox>,
+}
+
+/// A marker mapped into the coordinate system of the open cell.
+#[derive(Clone, Debug)]
+pub struct MappedMarker {
+    /// Index into [`MarkerDb::markers`].
+    pub marker: usize,
+    /// One entry per shape per placement of the marker's cell, in layout units.
+    pub shapes: Vec<Bbox>,
+}
+
+impl MappedMarker {
+    pub fn bbox(&self) -> Option<Bbox> {
+        self.shapes
+            .iter()
+            .copied()
+            .reduce(|a, b| Bbox::union(Some(a), Some(b)).unwrap())
+    }
+}
+
+pub struct DrcState {
+    pub path: PathBuf,
+    pub db: MarkerDb,
+    pub mapped: Vec<MappedMarker>,
+    pub selected: Option<usize>,
+}
+
+impl DrcState {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read DRC results at {path:?}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            db: parse_lyrdb(&text)?,
+            mapped: Vec::new(),
+            selected: None,
+        })
+    }
+
+    /// Recomputes [`DrcState::mapped`] for a new compile output.
+    pub fn remap(&mut self, output: &CompiledData) {
+        self.mapped = map_markers(&self.db, output);
+        if self
+            .selected
+            .is_some_and(|selected| selected >= self.mapped.len())
+        {
+            self.selected = None;
+        }
+    }
+
+    /// Selects the marker `delta` entries away from the current selection, wrapping around.
+    pub fn step(&mut self, delta: isize) -> Option<&MappedMarker> {
+        if self.mapped.is_empty() {
+            return None;
+        }
+        let len = self.mapped.len() as isize;
+        let next = match self.selected {
+            Some(selected) => (selected as isize + delta).rem_euclid(len),
+            None if delta < 0 => len - 1,
+            None => 0,
+        } as usize;
+        self.selected = Some(next);
+        self.mapped.get(next)
+    }
+}
+
+/// Parses a KLayout report database.
+pub fn parse_lyrdb(text: &str) -> Result<MarkerDb> {
+    let doc = roxmltree::Document::parse(text)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("report-database") {
+        return Err(anyhow!(
+            "expected a <report-database> element, found <{}>",
+            root.tag_name().name()
+        ));
+    }
+    let child_text = |node: roxmltree::Node, name: &str| {
+        node.children()
+            .find(|child| child.has_tag_name(name))
+            .and_then(|child| child.text())
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let markers = root
+        .children()
+        .filter(|node| node.has_tag_name("items"))
+        .flat_map(|items| items.children().filter(|node| node.has_tag_name("item")))
+        .map(|item| Marker {
+            category: child_text(item, "category").replace('\'', ""),
+            cell: child_text(item, "cell"),
+            shapes: item
+                .descendants()
+                .filter(|node| node.has_tag_name("value"))
+                .filter_map(|value| parse_value(value.text()?))
+                .collect(),
+        })
+        .filter(|marker| !marker.shapes.is_empty())
+        .collect();
+    Ok(MarkerDb {
+        description: child_text(root, "description"),
+        top_cell: child_text(root, "top-cell"),
+        markers,
+    })
+}
+
+/// Returns the bounding box of a geometric marker value such as
+/// `polygon: (0,0;0,1;1,1)` or `edge-pair: (0,0;1,0)|(0,1;1,1)`.
+///
+/// Non-geometric values (e.g. `text: ...` or `float: ...`) are ignored.
+fn parse_value(value: &str) -> Option<Bbox> {
+    let (kind, coords) = value.split_once(':')?;
+    if !matches!(
+        kind.trim(),
+        "box" | "polygon" | "path" | "edge" | "edge-pair" | "point"
+    ) {
+        return None;
+    }
+    coords
+        .split(|c| matches!(c, '(' | ')' | ';' | '|' | '/'))
+        .filter_map(|pt| {
+            let (x, y) = pt.split_once(',')?;
+            Some(Bbox::point((
+                x.trim().parse().ok()?,
+                y.trim().parse().ok()?,
+            )))
+        })
+        .reduce(|a, b| Bbox::union(Some(a), Some(b)).unwrap())
+}
+
+/// Maps markers into the coordinate system of `output`'s top cell.
+///
+/// Markers are placed at every instance of their cell. Markers in cells the
+/// layout does not contain, such as a wrapper top cell, are used as is.
+pub fn map_markers(db: &MarkerDb, output: &CompiledData) -> Vec<MappedMarker> {
+    let names = output.gds_cell_names(output.top);
+    let placements = Flatten::new(output.top, None, |cell: &CellId, _, _| {
+        output.cells[cell]
+            .objects
+            .values()
+            .filter(|obj| obj.instance().is_some_and(|inst| !inst.construction))
+            .flat_map(|obj| {
+                let cell = obj.instance().unwrap().cell;
+                obj.instance_transforms()
+                    .into_iter()
+                    .map(move |xform| (cell, xform))
+            })
+            .collect_vec()
+    })
+    .map(|(cell, xform, _)| (names[&cell].as_str(), xform))
+    .into_group_map();
+    db.markers
+        .iter()
+        .enumerate()
+        .map(|(i, marker)| {
+            let identity = [Transform::identity()];
+            let xforms = placements
+                .get(marker.cell.as_str())
+                .map_or(identity.as_slice(), Vec::as_slice);
+            MappedMarker {
+                marker: i,
+                shapes: xforms
+                    .iter()
+                    .flat_map(|xform| {
+                        marker.shapes.iter().map(|shape| {
+                            Bbox::new(
+                                shape.x0 * LAYOUT_UNITS_PER_MICRON,
+                                shape.y0 * LAYOUT_UNITS_PER_MICRON,
+                                shape.x1 * LAYOUT_UNITS_PER_MICRON,
+                                shape.y1 * LAYOUT_UNITS_PER_MICRON,
+                            )
+                            .transform(xform)
+                        })
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LYRDB: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<report-database>
+ <description>sky130 DRC</description>
+ <top-cell>inv</top-cell>
+ <categories>
+  <category><name>m1.1</name><description>met1 width</description></category>
+ </categories>
+ <items>
+  <item>
+   <category>'m1.1'</category>
+   <cell>inv</cell>
+   <values>
+    <value>polygon: (0,0;0,0.1;0.2,0.1;0.2,0)</value>
+    <value>edge-pair: (1,1;2,1)|(1,1.5;2,1.5)</value>
+    <value>text: 'ignored'</value>
+   </values>
+  </item>
+  <item>
+   <category>m2.1</category>
+   <cell>inv</cell>
+   <values><value>float: 1.5</value></values>
+  </item>
+ </items>
+</report-database>
+"#;
+
+    #[test]
+    fn parse_lyrdb_markers() {
+        let db = parse_lyrdb(LYRDB).unwrap();
+        assert_eq!(db.description, "sky130 DRC");
+        assert_eq!(db.top_cell, "inv");
+        // Markers without geometry are dropped.
+        assert_eq!(db.markers.len(), 1);
+        let marker = &db.markers[0];
+        assert_eq!(marker.category, "m1.1");
+        assert_eq!(marker.cell, "inv");
+        assert_eq!(
+            marker.shapes,
+            vec![Bbox::new(0., 0., 0.2, 0.1), Bbox::new(1., 1., 2., 1.5)]
+        );
+    }
+}
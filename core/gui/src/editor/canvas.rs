@@ -1,22 +1,25 @@
 use std::{
-    collections::VecDeque,
     fmt::Debug,
     ops::{Add, Sub},
+    time::{Duration, Instant},
 };
 
+use argon_geometry::{Bbox, Transform};
 use compiler::{
     ast::Span,
-    compile::{self, ObjectId, SolvedValue, ifmatvec},
+    compile::{self, FlatScope, ObjectId, SolvedValue},
+    dbu::format_on_grid,
     solver::{LinearExpr, Var},
 };
 use enumify::enumify;
-use geometry::{dir::Dir, transform::TransformationMatrix};
+use geometry::dir::Dir;
 use gpui::{
-    BorderStyle, Bounds, Context, Corners, DefiniteLength, Edges, Element, Entity, FocusHandle,
-    Focusable, Half, InteractiveElement, IntoElement, Length, MouseButton, MouseDownEvent,
-    MouseMoveEvent, MouseUpEvent, PaintQuad, ParentElement, Pixels, Point, Render, Rgba,
-    ScrollWheelEvent, SharedString, Size, Style, Styled, Subscription, Window, div, pattern_slash,
-    px, rgb, size, solid_background,
+    App, BorderStyle, Bounds, ContentMask, Context, Corners, DefiniteLength, Edges, Element,
+    Entity, FocusHandle, Focusable, Half, InteractiveElement, IntoElement, Length, MouseButton,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad, ParentElement, Pixels, Point, Render,
+    Rgba, ScrollWheelEvent, SharedString, Size, StatefulInteractiveElement, Style, Styled,
+    Subscription, TextRun, Window, anchored, deferred, div, pattern_slash, px, rgb, size,
+    solid_background,
 };
 use indexmap::IndexSet;
 use itertools::Itertools;
@@ -25,7 +28,12 @@ use tower_lsp_server::ls_types::MessageType;
 
 use crate::{
     actions::*,
-    editor::{self, CompileOutputState, EditorState, LayerState, ScopeAddress},
+    drc::LAYOUT_UNITS_PER_MICRON,
+    editor::{
+        self, CompileOutputState, EditorState, LayerState, ScopeAddress, ScopePath,
+        toolbars::DraggedCell,
+    },
+    query::{self, QueryShape},
     sse::SparseVec,
 };
 
@@ -45,6 +53,32 @@ const HANDLE_HIT: Pixels = px(22.);
 /// Fill / border colors of the SSE drag handles.
 const HANDLE_FILL: u32 = 0x3b9dff;
 const HANDLE_BORDER: u32 = 0xffffff;
+/// Idle time after the last nudge keypress before the accumulated offset is
+/// written back to the source, so a burst of presses becomes a single edit.
+const NUDGE_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Number of grid steps moved by a shifted nudge.
+const NUDGE_COARSE_STEPS: f64 = 10.;
+/// Grid lines closer together than this on screen are not drawn.
+const MIN_GRID_SPACING: f32 = 8.;
+/// Length of the x-axis arm of an instance origin marker; the y-axis arm is
+/// half as long, so reflections are visible.
+const INSTANCE_MARKER_LEN: Pixels = px(16.);
+/// Side length of the square drawn at each port of an outlined instance.
+const PORT_MARKER_SIZE: Pixels = px(8.);
+/// Dimensions shorter than this on screen are not drawn unless selected.
+const MIN_DIM_LEN: Pixels = px(3.);
+/// Font size of dimension labels on dimensions with room to spare.
+const DIM_FONT_SIZE: Pixels = px(14.);
+/// Smallest font size dimension labels shrink to before they are hidden.
+const MIN_DIM_FONT_SIZE: Pixels = px(9.);
+/// Length of dimension arrowheads; they are half as wide.
+const DIM_ARROW_LEN: Pixels = px(8.);
+/// Gap between a dimension line and its label.
+const DIM_LABEL_GAP: Pixels = px(3.);
+/// Duration of animated camera transitions.
+const CAMERA_ANIMATION_DURATION: Duration = Duration::from_millis(200);
+/// Factor by which a single zoom in/out step changes the scale.
+const ZOOM_STEP: f32 = 1.5;
 
 /// A draggable solution-space-exploration handle, drawn at the midpoint of an
 /// unconstrained (dashed) rectangle edge. Clicking within `bounds` begins an
@@ -141,14 +175,19 @@ impl From<editor::Rect<(f64, Var)>> for Rect {
 }
 
 impl Rect {
-    pub fn transform(&self, mat: TransformationMatrix, ofs: (f64, f64)) -> Self {
-        let p0p = ifmatvec(mat, (self.x0 as f64, self.y0 as f64));
-        let p1p = ifmatvec(mat, (self.x1 as f64, self.y1 as f64));
+    pub fn transform(&self, t: &Transform) -> Self {
+        let bbox = Bbox::new(
+            self.x0 as f64,
+            self.y0 as f64,
+            self.x1 as f64,
+            self.y1 as f64,
+        )
+        .transform(t);
         Self {
-            x0: (p0p.0.min(p1p.0) + ofs.0) as f32,
-            y0: (p0p.1.min(p1p.1) + ofs.1) as f32,
-            x1: (p0p.0.max(p1p.0) + ofs.0) as f32,
-            y1: (p0p.1.max(p1p.1) + ofs.1) as f32,
+            x0: bbox.x0 as f32,
+            y0: bbox.y0 as f32,
+            x1: bbox.x1 as f32,
+            y1: bbox.y1 as f32,
             id: self.id.clone(),
             object_path: self.object_path.clone(),
             border_widths: self.border_widths,
@@ -158,6 +197,10 @@ impl Rect {
     }
 }
 
+fn minmax<T>(a: (f32, T), b: (f32, T)) -> ((f32, T), (f32, T)) {
+    if a.0 <= b.0 { (a, b) } else { (b, a) }
+}
+
 pub fn intersect(a: &Bounds<Pixels>, b: &Bounds<Pixels>) -> Option<Bounds<Pixels>> {
     let origin = a.origin.max(&b.origin);
     let br = a.bottom_right().min(&b.bottom_right());
@@ -173,7 +216,223 @@ pub struct CanvasElement {
 
 #[derive(Debug, Default, Clone)]
 pub(crate) struct DrawRectToolState {
-    p0: Option<Point<f32>>,
+    pub(crate) p0: Option<SnappedPoint>,
+}
+
+/// A point in layout coordinates, together with the edges (e.g. `rect0.x1`)
+/// that its x and y coordinates were snapped to, if any.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct SnappedPoint {
+    pub(crate) pt: Point<f32>,
+    pub(crate) x_ref: Option<String>,
+    pub(crate) y_ref: Option<String>,
+}
+
+impl From<Point<f32>> for SnappedPoint {
+    fn from(pt: Point<f32>) -> Self {
+        Self {
+            pt,
+            x_ref: None,
+            y_ref: None,
+        }
+    }
+}
+
+/// Parses exact rect dimensions typed while drawing a rect, e.g. `2.5x0.6`.
+///
+/// Returns `None` unless both the width and height are positive and finite.
+pub(crate) fn parse_rect_dims(input: &str) -> Option<(f64, f64)> {
+    let (w, h) = input.trim().split_once(['x', 'X'])?;
+    let w = w.trim().parse::<f64>().ok()?;
+    let h = h.trim().parse::<f64>().ok()?;
+    (w.is_finite() && h.is_finite() && w > 0. && h > 0.).then_some((w, h))
+}
+
+/// Default screen distance between the steps of an exploded view.
+pub(crate) const DEFAULT_EXPLODE_OFFSET: f32 = 12.;
+
+/// What the steps of an exploded view are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExplodeMode {
+    /// Each layer is one step above the layer beneath it.
+    Layer,
+    /// Each level of the hierarchy is one step above its parent.
+    Scope,
+}
+
+/// Debug view that draws shapes offset up and to the right in steps, so that
+/// shapes buried in dense stacks become visible.
+///
+/// Only the drawing is affected: the compile output is left untouched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Explode {
+    pub mode: ExplodeMode,
+    /// Screen distance between successive steps, in pixels.
+    pub offset: f32,
+}
+
+impl Explode {
+    /// Parses the arguments of `:explode <layer|scope|off> [offset]`.
+    ///
+    /// Returns `None` if the exploded view should be turned off.
+    pub(crate) fn parse(args: &str) -> anyhow::Result<Option<Self>> {
+        let usage = || anyhow::anyhow!("usage: :explode <layer|scope|off> [offset]");
+        let mut args = args.split_whitespace();
+        let mode = match args.next() {
+            Some("off") if args.next().is_none() => return Ok(None),
+            Some("layer") => ExplodeMode::Layer,
+            Some("scope") => ExplodeMode::Scope,
+            _ => return Err(usage()),
+        };
+        let offset = match (args.next(), args.next()) {
+            (None, _) => DEFAULT_EXPLODE_OFFSET,
+            (Some(offset), None) => offset
+                .parse::<f32>()
+                .ok()
+                .filter(|offset| offset.is_finite() && *offset >= 0.)
+                .ok_or_else(usage)?,
+            _ => return Err(usage()),
+        };
+        Ok(Some(Self { mode, offset }))
+    }
+
+    /// Moves `rect`, drawn on the layer at `z` in a scope `depth` levels below
+    /// the top cell, to its place in the exploded view at `scale` pixels per
+    /// layout unit.
+    fn apply(&self, rect: Rect, z: usize, depth: usize, scale: f32) -> Rect {
+        let step = match self.mode {
+            ExplodeMode::Layer => z,
+            ExplodeMode::Scope => depth,
+        };
+        let d = step as f32 * self.offset / scale;
+        Rect {
+            x0: rect.x0 + d,
+            x1: rect.x1 + d,
+            y0: rect.y0 + d,
+            y1: rect.y1 + d,
+            ..rect
+        }
+    }
+}
+
+/// Asks the language server to insert a rect spanning `p0` to `p1` into the
+/// selected scope, recording any failure as a fatal editor error.
+///
+/// Coordinates that snapped to an existing edge are emitted as references to
+/// that edge. If `size` is provided, the rect is emitted with hard `w`/`h`
+/// constraints instead of a fallback upper-right corner.
+pub(crate) fn commit_rect(
+    state: &Entity<EditorState>,
+    cx: &mut App,
+    p0: SnappedPoint,
+    p1: SnappedPoint,
+    size: Option<(f64, f64)>,
+) {
+    let ((x0, x0_ref), (x1, x1_ref)) = minmax((p0.pt.x, p0.x_ref), (p1.pt.x, p1.x_ref));
+    let ((y0, y0_ref), (y1, y1_ref)) = minmax((p0.pt.y, p0.y_ref), (p1.pt.y, p1.y_ref));
+    state.update(cx, |state, cx| {
+        let error = state.solved_cell.update(cx, {
+            |cell, cx| {
+                if let Some(cell) = cell.as_mut() {
+                    // TODO update in memory representation of code
+                    // TODO add solver to gui
+                    let scope_address = &cell.state[&cell.selected_scope].address;
+                    let reachable_objs = cell
+                        .output
+                        .reachable_objs(scope_address.cell, scope_address.scope);
+                    let names: IndexSet<_> = reachable_objs.values().collect();
+                    let scope = cell
+                        .output
+                        .cells
+                        .get_mut(&scope_address.cell)
+                        .unwrap()
+                        .scopes
+                        .get_mut(&scope_address.scope)
+                        .unwrap();
+                    let rect_name = (0..)
+                        .map(|i| format!("rect{i}"))
+                        .find(|name| !names.contains(name))
+                        .unwrap();
+
+                    match state.lang_server_client.draw_rect(
+                        scope.span.clone(),
+                        rect_name,
+                        compile::BasicRect {
+                            layer: state
+                                .layers
+                                .read(cx)
+                                .selected_layer
+                                .clone()
+                                .map(|s| s.to_string()),
+                            x0: (x0 as f64, x0_ref),
+                            y0: (y0 as f64, y0_ref),
+                            x1: (x1 as f64, x1_ref),
+                            y1: (y1 as f64, y1_ref),
+                            construction: false,
+                        },
+                        size,
+                    ) {
+                        Ok(None) => Some("inconsistent editor and GUI state".into()),
+                        Ok(Some(_)) => None,
+                        Err(e) => Some(format!("{e}").into()),
+                    }
+                } else {
+                    Some("no cell to edit".into())
+                }
+            }
+        });
+        if state.fatal_error.is_none() {
+            state.fatal_error = error;
+        }
+    });
+}
+
+/// Asks the language server to instantiate `cell` in the selected scope with
+/// its origin at `(x, y)`, recording any failure as a fatal editor error.
+pub(crate) fn commit_instance(
+    state: &Entity<EditorState>,
+    cx: &mut App,
+    cell: &str,
+    x: f64,
+    y: f64,
+) {
+    state.update(cx, |state, cx| {
+        let error = state.solved_cell.update(cx, {
+            |solved_cell, _cx| {
+                if let Some(solved_cell) = solved_cell.as_ref() {
+                    let scope_address = &solved_cell.state[&solved_cell.selected_scope].address;
+                    let reachable_objs = solved_cell
+                        .output
+                        .reachable_objs(scope_address.cell, scope_address.scope);
+                    let names: IndexSet<_> = reachable_objs.values().collect();
+                    let scope =
+                        &solved_cell.output.cells[&scope_address.cell].scopes[&scope_address.scope];
+                    let base = cell.rsplit("::").next().unwrap_or(cell);
+                    let inst_name = (0..)
+                        .map(|i| format!("{base}{i}"))
+                        .find(|name| !names.contains(name))
+                        .unwrap();
+
+                    match state.lang_server_client.place_instance(
+                        scope.span.clone(),
+                        inst_name,
+                        cell.to_string(),
+                        x,
+                        y,
+                    ) {
+                        Ok(None) => Some("inconsistent editor and GUI state".into()),
+                        Ok(Some(_)) => None,
+                        Err(e) => Some(format!("{e}").into()),
+                    }
+                } else {
+                    Some("no cell to edit".into())
+                }
+            }
+        });
+        if state.fatal_error.is_none() {
+            state.fatal_error = error;
+        }
+    });
 }
 
 #[derive(Debug, Clone)]
@@ -206,11 +465,203 @@ pub struct GlobalObjectId {
     idx: usize,
 }
 
+/// Keyboard nudges of the object created by the call at `span` that have not
+/// yet been written back to the source.
+#[derive(Debug, Clone)]
+struct PendingNudge {
+    span: Span,
+    dx: f64,
+    dy: f64,
+}
+
+/// Annotations drawn inside the outline box of an instance whose contents are
+/// hidden by the hierarchy depth limit.
+struct InstanceLabel {
+    bbox: Rect,
+    name: Option<String>,
+    cell: String,
+    /// Layout-space position of the instance origin.
+    origin: Point<f32>,
+    /// Layout-space directions of the instance's local x and y axes.
+    x_dir: (f64, f64),
+    y_dir: (f64, f64),
+    ports: Vec<InstancePort>,
+}
+
+/// A port of an outlined instance, drawn as a square so that connection points
+/// remain visible when the instance's contents are hidden.
+struct InstancePort {
+    name: String,
+    /// Layout-space position of the port.
+    pos: Point<f32>,
+    /// Color of the port's layer, or `None` if the layer is not in the layer
+    /// properties, in which case the square is drawn hollow.
+    color: Option<Rgba>,
+}
+
+/// A view of the layout: `scale` pixels per layout unit, with the layout origin
+/// drawn at `offset` from the top-left corner of the canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Camera {
+    scale: f32,
+    offset: Point<Pixels>,
+}
+
+impl Camera {
+    /// Returns the layout point drawn at canvas-relative position `center`.
+    fn layout_at(&self, center: Point<Pixels>) -> Point<f32> {
+        Point::new(
+            f32::from(center.x - self.offset.x) / self.scale,
+            f32::from(self.offset.y - center.y) / self.scale,
+        )
+    }
+
+    /// Returns the camera with the given `scale` that draws layout point `pt`
+    /// at canvas-relative position `center`.
+    fn centered(scale: f32, pt: Point<f32>, center: Point<Pixels>) -> Self {
+        Camera {
+            scale,
+            offset: Point::new(center.x - px(scale * pt.x), center.y + px(scale * pt.y)),
+        }
+    }
+
+    /// Returns the camera a fraction `t` of the way from `self` to `other`.
+    ///
+    /// The scale is interpolated geometrically and the layout point at `center`
+    /// linearly, so zooming feels uniform and the view does not swing sideways.
+    fn lerp(&self, other: &Camera, t: f32, center: Point<Pixels>) -> Self {
+        let a = self.layout_at(center);
+        let b = other.layout_at(center);
+        Camera::centered(
+            self.scale * (other.scale / self.scale).powf(t),
+            Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t),
+            center,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CameraAnimation {
+    from: Camera,
+    to: Camera,
+    start: Instant,
+}
+
+/// Cubic ease-out: fast at first, settling gently onto the target.
+fn ease_out(t: f32) -> f32 {
+    1. - (1. - t).powi(3)
+}
+
+/// An object under the cursor, as found by [`LayoutCanvas::hit_test`].
+#[derive(Clone, Copy)]
+enum CanvasHit<'a> {
+    Rect(&'a Rect, &'a LayerState),
+    /// The bounding box drawn in place of a scope or instance whose contents
+    /// are hidden.
+    Scope(&'a Rect),
+    Dim(&'a Span),
+}
+
+impl<'a> CanvasHit<'a> {
+    fn span(&self) -> &'a Span {
+        match self {
+            CanvasHit::Rect(r, _) | CanvasHit::Scope(r) => r.id.as_ref().unwrap(),
+            CanvasHit::Dim(span) => span,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ContextMenuItem {
+    Select,
+    EditConstraints,
+    GoToSource,
+    /// Explains the rect's coordinates by the constraints that fixed them.
+    WhyIsThisHere,
+    HideLayer,
+    DescendIntoInstance,
+    Delete,
+    Paste,
+    ZoomFit,
+    ToggleGrid,
+}
+
+impl ContextMenuItem {
+    fn label(self) -> &'static str {
+        match self {
+            ContextMenuItem::Select => "Select",
+            ContextMenuItem::EditConstraints => "Edit Constraints",
+            ContextMenuItem::GoToSource => "Go to Source",
+            ContextMenuItem::WhyIsThisHere => "Why Is This Here?",
+            ContextMenuItem::HideLayer => "Hide Layer",
+            ContextMenuItem::DescendIntoInstance => "Descend into Instance",
+            ContextMenuItem::Delete => "Delete",
+            ContextMenuItem::Paste => "Paste",
+            ContextMenuItem::ZoomFit => "Zoom to Fit",
+            ContextMenuItem::ToggleGrid => "Toggle Grid",
+        }
+    }
+}
+
+/// The object a context menu was opened on.
+#[derive(Clone, Debug)]
+struct ContextMenuTarget {
+    span: Span,
+    /// Layer of the object, if it is a rect.
+    layer: Option<SharedString>,
+    /// Scope path of the instantiated cell, if the object is an instance.
+    instance: Option<ScopePath>,
+    is_dim: bool,
+}
+
+#[derive(Clone, Debug)]
+struct ContextMenu {
+    position: Point<Pixels>,
+    /// `None` if the menu was opened on empty canvas.
+    target: Option<ContextMenuTarget>,
+    /// Whether the open cell is read-only, which hides items that edit it.
+    read_only: bool,
+}
+
+impl ContextMenu {
+    fn items(&self) -> Vec<ContextMenuItem> {
+        let Some(target) = &self.target else {
+            return vec![
+                ContextMenuItem::Paste,
+                ContextMenuItem::ZoomFit,
+                ContextMenuItem::ToggleGrid,
+            ];
+        };
+        let mut items = vec![ContextMenuItem::Select];
+        if target.is_dim && !self.read_only {
+            items.push(ContextMenuItem::EditConstraints);
+        }
+        items.push(ContextMenuItem::GoToSource);
+        if target.layer.is_some() {
+            items.push(ContextMenuItem::WhyIsThisHere);
+            items.push(ContextMenuItem::HideLayer);
+        }
+        if target.instance.is_some() {
+            items.push(ContextMenuItem::DescendIntoInstance);
+        }
+        if !self.read_only {
+            items.push(ContextMenuItem::Delete);
+        }
+        items
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub(crate) struct SelectToolState {
     pub(crate) selected_obj: Option<Span>,
 }
 
+/// Shapes picked for area and perimeter measurement.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct QueryToolState {
+    pub(crate) shapes: Vec<QueryShape>,
+}
+
 #[enumify]
 #[derive(Debug, Clone)]
 pub(crate) enum ToolState {
@@ -218,6 +669,7 @@ pub(crate) enum ToolState {
     DrawDim(DrawDimToolState),
     EditDim(EditDimToolState),
     Select(SelectToolState),
+    Query(QueryToolState),
 }
 
 impl Default for ToolState {
@@ -242,6 +694,11 @@ pub struct LayoutCanvas {
     // Drag handles for unconstrained edges, recomputed each paint. SSE drags are
     // only started by clicking one of these.
     sse_handles: Vec<SseHandle>,
+    // nudge state
+    pending_nudge: Option<PendingNudge>,
+    // Incremented on every nudge so that only the last debounce timer flushes.
+    nudge_generation: usize,
+    context_menu: Option<ContextMenu>,
     // drag state
     is_dragging: bool,
     offset_start: Point<Pixels>,
@@ -250,6 +707,9 @@ pub struct LayoutCanvas {
     mouse_position: Point<Pixels>,
     // zoom state
     scale: f32,
+    camera_animation: Option<CameraAnimation>,
+    // Camera before the last animated transition, restored by `ZoomBack`.
+    previous_camera: Option<Camera>,
     screen_bounds: Bounds<Pixels>,
     #[allow(unused)]
     subscriptions: Vec<Subscription>,
@@ -303,6 +763,91 @@ fn get_rect_bounds(
     )
 }
 
+/// Thickness of the lines of a dimension `len` long on screen, thinning short
+/// dimensions so that they do not turn into blobs.
+fn dim_line_width(len: Pixels) -> Pixels {
+    (len / 40.).clamp(px(1.), DEFAULT_BORDER_WIDTH)
+}
+
+/// Whether the arrowheads of a dimension `len` long on screen fit between its
+/// extension lines.
+fn dim_arrows_inside(len: Pixels) -> bool {
+    len >= DIM_ARROW_LEN * 3.
+}
+
+/// Font size of the label of a dimension `len` long on screen, or `None` if
+/// the dimension is too short to label. Focused dimensions are always labeled.
+fn dim_font_size(len: Pixels, focused: bool) -> Option<Pixels> {
+    let font_size = (len / 4.).min(DIM_FONT_SIZE);
+    if focused {
+        Some(font_size.max(MIN_DIM_FONT_SIZE))
+    } else {
+        (font_size >= MIN_DIM_FONT_SIZE).then_some(font_size)
+    }
+}
+
+/// Arrowhead with its tip at `tip`, pointing along the unit vector `dir`.
+fn dim_arrowhead(tip: Point<Pixels>, dir: Point<f32>) -> gpui::Path<Pixels> {
+    let base = tip - Point::new(DIM_ARROW_LEN * dir.x, DIM_ARROW_LEN * dir.y);
+    let side = Point::new(DIM_ARROW_LEN / 4. * -dir.y, DIM_ARROW_LEN / 4. * dir.x);
+    let mut path = gpui::Path::new(tip);
+    path.line_to(base + side);
+    path.line_to(base - side);
+    path
+}
+
+/// Places of a label of size `label` for the dimension line from `start` to
+/// `end`, in order of preference: beside the middle of the line on either
+/// side, then past either end (and past the arrowheads, if they are
+/// `outside` the extension lines).
+fn dim_label_candidates(
+    start: Point<Pixels>,
+    end: Point<Pixels>,
+    horiz: bool,
+    outside: bool,
+    label: Size<Pixels>,
+) -> [Bounds<Pixels>; 4] {
+    let mid = Point::new((start.x + end.x) / 2., (start.y + end.y) / 2.);
+    let past = DIM_LABEL_GAP + if outside { DIM_ARROW_LEN } else { px(0.) };
+    let origins = if horiz {
+        let (min, max) = (start.x.min(end.x), start.x.max(end.x));
+        let y = mid.y - label.height / 2.;
+        [
+            Point::new(
+                mid.x - label.width / 2.,
+                mid.y - DIM_LABEL_GAP - label.height,
+            ),
+            Point::new(mid.x - label.width / 2., mid.y + DIM_LABEL_GAP),
+            Point::new(max + past, y),
+            Point::new(min - past - label.width, y),
+        ]
+    } else {
+        let (min, max) = (start.y.min(end.y), start.y.max(end.y));
+        let x = mid.x - label.width / 2.;
+        [
+            Point::new(mid.x + DIM_LABEL_GAP, mid.y - label.height / 2.),
+            Point::new(
+                mid.x - DIM_LABEL_GAP - label.width,
+                mid.y - label.height / 2.,
+            ),
+            Point::new(x, min - past - label.height),
+            Point::new(x, max + past),
+        ]
+    };
+    origins.map(|origin| Bounds::new(origin, label))
+}
+
+/// Returns the first of `candidates` that does not overlap a `placed` label.
+fn place_dim_label(
+    candidates: &[Bounds<Pixels>],
+    placed: &[Bounds<Pixels>],
+) -> Option<Bounds<Pixels>> {
+    candidates
+        .iter()
+        .find(|candidate| !placed.iter().any(|label| label.intersects(candidate)))
+        .copied()
+}
+
 fn get_paint_quad(
     bounds: Bounds<Pixels>,
     fill: ShapeFill,
@@ -386,6 +931,9 @@ impl Element for CanvasElement {
                 inner.pending_init = false;
                 inner.fit_to_screen(cx);
             }
+            if inner.step_camera_animation() {
+                window.request_animation_frame();
+            }
         });
         let inner = self.inner.read(cx);
         let solved_cell = &inner.state.read(cx).solved_cell.read(cx);
@@ -397,10 +945,23 @@ impl Element for CanvasElement {
 
         // TODO: Clean up code.
         let mut rects = Vec::new();
+        // Polygons, including rects in instances rotated by non-Manhattan angles.
+        let mut polygons = Vec::new();
         let mut dims = Vec::new();
         let mut scope_rects = Vec::new();
+        // Outlines of construction geometry and the border colors of their layers.
+        let mut construction_rects = Vec::new();
         let mut select_rects = Vec::new();
+        let mut net_shapes = Vec::new();
+        let mut instance_labels = Vec::new();
+        // Names of pins and the layout-space centers they are drawn at.
+        let mut pin_labels = Vec::new();
         let layout_mouse_position = inner.px_to_layout(inner.mouse_position);
+        let snapped_mouse_position = if tool.is_draw_rect() {
+            inner.snap(cx, inner.mouse_position)
+        } else {
+            SnappedPoint::from(layout_mouse_position)
+        };
         if let Some(solved_cell) = solved_cell {
             let top = &solved_cell.output.cells[&solved_cell.output.top];
             if inner.is_sse_dragging {
@@ -424,48 +985,63 @@ impl Element for CanvasElement {
                     crate::sse::drag_delta(&u, &rowspace_vecs, &top.unsolved_vars, delta as f64);
             }
             let scope_address = &solved_cell.state[&solved_cell.selected_scope].address;
-            let mut queue = VecDeque::from_iter([(
-                ScopeAddress {
-                    cell: scope_address.cell,
-                    scope: if *hide_external_geometry {
-                        scope_address.scope
-                    } else {
-                        solved_cell.output.cells[&scope_address.cell].root
-                    },
-                },
-                TransformationMatrix::identity(),
-                (0., 0.),
-                0,
-                true,
-                vec![],
-            )]);
             dims.extend(
                 solved_cell.output.cells[&scope_address.cell]
                     .objects
                     .values()
                     .filter_map(|obj| obj.get_dimension().cloned()),
             );
-            while let Some((
-                curr_address @ ScopeAddress { scope, cell },
-                mat,
-                ofs,
+            let start = if *hide_external_geometry {
+                scope_address.scope
+            } else {
+                solved_cell.output.cells[&scope_address.cell].root
+            };
+            let state_of = |cell, scope| {
+                &solved_cell.state[&solved_cell.scope_paths[&ScopeAddress { cell, scope }]]
+            };
+            // Hidden and collapsed scopes are drawn as their bounding boxes, and
+            // nothing inside them is shown.
+            let collapsed = |cell, scope, depth| {
+                depth >= state.hierarchy_depth || !state_of(cell, scope).visible
+            };
+            let flat = solved_cell.output.iter_flat_scopes(
+                scope_address.cell,
+                start,
+                true,
+                true,
+                |parent, depth, cell, scope| {
+                    // Unloaded scopes have no descendant states to look up.
+                    if !state_of(parent.cell, parent.scope).loaded {
+                        return None;
+                    }
+                    let show = parent.data && !collapsed(parent.cell, parent.scope, depth);
+                    let instance = scope == solved_cell.output.cells[&cell].root;
+                    Some(show && !(instance && collapsed(cell, scope, depth + 1)))
+                },
+            );
+            for (
+                FlatScope {
+                    cell,
+                    scope,
+                    path,
+                    data: mut show,
+                },
+                xform,
                 depth,
-                mut show,
-                path,
-            )) = queue.pop_front()
+            ) in flat
             {
                 let cell_info = &solved_cell.output.cells[&cell];
                 let scope_info = &cell_info.scopes[&scope];
-                let scope_state = &solved_cell.state[&solved_cell.scope_paths[&curr_address]];
-                if depth >= state.hierarchy_depth || !scope_state.visible {
+                let scope_state = state_of(cell, scope);
+                let mut obj_names = None;
+                if collapsed(cell, scope, depth) {
                     if let Some(bbox) = &scope_state.bbox {
-                        let p0p = ifmatvec(mat, (bbox.x0, bbox.y0));
-                        let p1p = ifmatvec(mat, (bbox.x1, bbox.y1));
+                        let bbox = bbox.bbox().transform(&xform);
                         let rect = Rect {
-                            x0: (p0p.0.min(p1p.0) + ofs.0) as f32,
-                            y0: (p0p.1.min(p1p.1) + ofs.1) as f32,
-                            x1: (p0p.0.max(p1p.0) + ofs.0) as f32,
-                            y1: (p0p.1.max(p1p.1) + ofs.1) as f32,
+                            x0: bbox.x0 as f32,
+                            y0: bbox.y0 as f32,
+                            x1: bbox.x1 as f32,
+                            y1: bbox.y1 as f32,
                             id: Some(scope_info.span.clone()),
                             object_path: Vec::new(),
                             border_widths: Edges::all(DEFAULT_BORDER_WIDTH),
@@ -486,20 +1062,38 @@ impl Element for CanvasElement {
                     }
                     show = false;
                 }
+                // Hidden scopes that were never shown have no descendant states.
+                if !scope_state.loaded {
+                    continue;
+                }
                 for (obj, _) in &scope_info.emit {
                     let mut object_path = path.clone();
                     object_path.push(*obj);
                     let value = &cell_info.objects[obj];
                     match value {
                         SolvedValue::Rect(rect) => {
-                            let p0p = ifmatvec(mat, (rect.x0.0, rect.y0.0));
-                            let p1p = ifmatvec(mat, (rect.x1.0, rect.y1.0));
+                            let local_bbox = solved_cell
+                                .output
+                                .snapped_bbox(cell_info, *obj)
+                                .expect("rect was not snapped");
+                            let bbox = local_bbox.transform(&xform);
                             let layer = rect
                                 .layer
                                 .as_ref()
                                 .and_then(|layer| layers.layers.get(layer.as_str()));
                             if let Some(layer) = layer
                                 && !rect.construction
+                                && !xform.is_manhattan()
+                            {
+                                if show && layer.visible {
+                                    let corners = local_bbox
+                                        .corners()
+                                        .map(|pt| xform.apply(pt))
+                                        .map(|(x, y)| Point::new(x as f32, y as f32));
+                                    polygons.push((corners.to_vec(), layer.clone()));
+                                }
+                            } else if let Some(layer) = layer
+                                && !rect.construction
                             {
                                 let (sse_dx0, sse_dx1, sse_dy0, sse_dy1) = if let Some(ref sse_dv) =
                                     sse_dv
@@ -519,10 +1113,10 @@ impl Element for CanvasElement {
                                 };
                                 let rect =
                                     Rect {
-                                        x0: (p0p.0.min(p1p.0) + ofs.0 + sse_dx0) as f32,
-                                        y0: (p0p.1.min(p1p.1) + ofs.1 + sse_dy0) as f32,
-                                        x1: (p0p.0.max(p1p.0) + ofs.0 + sse_dx1) as f32,
-                                        y1: (p0p.1.max(p1p.1) + ofs.1 + sse_dy1) as f32,
+                                        x0: (bbox.x0 + sse_dx0) as f32,
+                                        y0: (bbox.y0 + sse_dy0) as f32,
+                                        x1: (bbox.x1 + sse_dx1) as f32,
+                                        y1: (bbox.y1 + sse_dy1) as f32,
                                         id: rect.span.clone(),
                                         object_path,
                                         border_widths: Edges::all(DEFAULT_BORDER_WIDTH),
@@ -565,6 +1159,12 @@ impl Element for CanvasElement {
                                             top: rect.y1.1.clone(),
                                         }),
                                     };
+                                let rect = match &state.explode {
+                                    Some(explode) => {
+                                        explode.apply(rect, layer.z, depth, inner.scale)
+                                    }
+                                    None => rect,
+                                };
                                 if let ToolState::Select(SelectToolState { selected_obj }) = &tool
                                     && rect.id.is_some()
                                     && &rect.id == selected_obj
@@ -577,37 +1177,90 @@ impl Element for CanvasElement {
                                 if show && layer.visible {
                                     rects.push((rect, layer.clone()));
                                 }
+                            } else if rect.construction
+                                && state.show_construction
+                                && show
+                                && xform.is_manhattan()
+                                && layer.is_none_or(|layer| layer.visible)
+                            {
+                                let outline = Rect {
+                                    x0: bbox.x0 as f32,
+                                    y0: bbox.y0 as f32,
+                                    x1: bbox.x1 as f32,
+                                    y1: bbox.y1 as f32,
+                                    id: rect.span.clone(),
+                                    object_path,
+                                    border_widths: Edges::all(DEFAULT_BORDER_WIDTH),
+                                    border_styles: Edges::all(BorderStyle::Dashed),
+                                    cvars: None,
+                                };
+                                let outline = match (&state.explode, layer) {
+                                    (Some(explode), Some(layer)) => {
+                                        explode.apply(outline, layer.z, depth, inner.scale)
+                                    }
+                                    _ => outline,
+                                };
+                                construction_rects
+                                    .push((outline, layer.map(|layer| layer.border_color)));
                             }
                         }
-                        SolvedValue::Instance(inst) => {
-                            if inst.construction {
-                                continue;
-                            }
-                            let mut inst_mat = TransformationMatrix::identity();
-                            if inst.reflect {
-                                inst_mat = inst_mat.reflect_vert()
-                            }
-                            inst_mat = inst_mat.rotate(inst.angle);
-                            let inst_ofs = ifmatvec(mat, (inst.x, inst.y));
-
+                        SolvedValue::Instance(_) | SolvedValue::InstanceArray(_) => {
+                            let inst = value.instance().unwrap();
                             let inst_address = ScopeAddress {
                                 scope: solved_cell.output.cells[&inst.cell].root,
                                 cell: inst.cell,
                             };
-                            let new_mat = mat * inst_mat;
-                            let new_ofs = (inst_ofs.0 + ofs.0, inst_ofs.1 + ofs.1);
-                            let scope_state =
-                                &solved_cell.state[&solved_cell.scope_paths[&inst_address]];
-                            let mut show = show;
-                            if depth + 1 >= state.hierarchy_depth || !scope_state.visible {
-                                if let Some(bbox) = &scope_state.bbox {
-                                    let p0p = ifmatvec(new_mat, (bbox.x0, bbox.y0));
-                                    let p1p = ifmatvec(new_mat, (bbox.x1, bbox.y1));
+                            // Construction instances are only outlined.
+                            if inst.construction {
+                                if !state.show_construction || !show {
+                                    continue;
+                                }
+                                let scope_state =
+                                    &solved_cell.state[&solved_cell.scope_paths[&inst_address]];
+                                let Some(bbox) = &scope_state.bbox else {
+                                    continue;
+                                };
+                                for snapped in solved_cell
+                                    .output
+                                    .snapped_transforms(cell_info, *obj)
+                                    .expect("instance was not snapped")
+                                {
+                                    let bbox = bbox.bbox().transform(&xform.compose(&snapped));
+                                    construction_rects.push((
+                                        Rect {
+                                            x0: bbox.x0 as f32,
+                                            y0: bbox.y0 as f32,
+                                            x1: bbox.x1 as f32,
+                                            y1: bbox.y1 as f32,
+                                            id: Some(inst.span.clone()),
+                                            object_path: object_path.clone(),
+                                            border_widths: Edges::all(DEFAULT_BORDER_WIDTH),
+                                            border_styles: Edges::all(BorderStyle::Dashed),
+                                            cvars: None,
+                                        },
+                                        None,
+                                    ));
+                                }
+                                continue;
+                            }
+
+                            // Each element of an array is drawn like a separate instance.
+                            for snapped in solved_cell
+                                .output
+                                .snapped_transforms(cell_info, *obj)
+                                .expect("instance was not snapped")
+                            {
+                                let inst_xform = xform.compose(&snapped);
+                                if collapsed(inst.cell, inst_address.scope, depth + 1)
+                                    && let Some(bbox) =
+                                        &state_of(inst.cell, inst_address.scope).bbox
+                                {
+                                    let bbox = bbox.bbox().transform(&inst_xform);
                                     let rect = Rect {
-                                        x0: (p0p.0.min(p1p.0) + new_ofs.0) as f32,
-                                        y0: (p0p.1.min(p1p.1) + new_ofs.1) as f32,
-                                        x1: (p0p.0.max(p1p.0) + new_ofs.0) as f32,
-                                        y1: (p0p.1.max(p1p.1) + new_ofs.1) as f32,
+                                        x0: bbox.x0 as f32,
+                                        y0: bbox.y0 as f32,
+                                        x1: bbox.x1 as f32,
+                                        y1: bbox.y1 as f32,
                                         id: Some(inst.span.clone()),
                                         object_path: object_path.clone(),
                                         border_widths: Edges::all(DEFAULT_BORDER_WIDTH),
@@ -625,34 +1278,142 @@ impl Element for CanvasElement {
                                         });
                                     }
                                     if show {
+                                        let inst_cell = &solved_cell.output.cells[&inst.cell];
+                                        instance_labels.push(InstanceLabel {
+                                            bbox: rect.clone(),
+                                            name: obj_names
+                                                .get_or_insert_with(|| {
+                                                    solved_cell.output.reachable_objs(cell, scope)
+                                                })
+                                                .get(obj)
+                                                .cloned(),
+                                            cell: inst_cell.scopes[&inst_cell.root].name.clone(),
+                                            origin: Point::new(
+                                                inst_xform.offset.0 as f32,
+                                                inst_xform.offset.1 as f32,
+                                            ),
+                                            x_dir: inst_xform.apply_vec((1., 0.)),
+                                            y_dir: inst_xform.apply_vec((0., 1.)),
+                                            ports: inst_cell
+                                                .ports
+                                                .iter()
+                                                .filter_map(|port| {
+                                                    let color = match layers
+                                                        .layers
+                                                        .get(port.layer.as_str())
+                                                    {
+                                                        Some(layer) if !layer.visible => {
+                                                            return None;
+                                                        }
+                                                        Some(layer) => Some(layer.color),
+                                                        None => None,
+                                                    };
+                                                    let (x, y) = inst_xform.apply((port.x, port.y));
+                                                    Some(InstancePort {
+                                                        name: port.name.clone(),
+                                                        pos: Point::new(x as f32, y as f32),
+                                                        color,
+                                                    })
+                                                })
+                                                .collect(),
+                                        });
                                         scope_rects.push(rect);
                                     }
                                 }
-                                show = false;
                             }
-                            queue.push_back((
-                                inst_address,
-                                new_mat,
-                                new_ofs,
-                                depth + 1,
-                                show,
-                                object_path,
+                        }
+                        SolvedValue::Polygon(polygon) => {
+                            if let Some(layer) = layers.layers.get(polygon.layer.as_str())
+                                && !polygon.construction
+                                && show
+                                && layer.visible
+                            {
+                                let dbu = solved_cell.output.dbu;
+                                let points = cell_info
+                                    .dbu_polygon(*obj)
+                                    .expect("polygon was not snapped")
+                                    .iter()
+                                    .map(|(x, y)| xform.apply((*x as f64 * dbu, *y as f64 * dbu)))
+                                    .map(|(x, y)| Point::new(x as f32, y as f32))
+                                    .collect_vec();
+                                polygons.push((points, layer.clone()));
+                            }
+                        }
+                        SolvedValue::Pin(pin) => {
+                            if !show {
+                                continue;
+                            }
+                            let bbox = solved_cell
+                                .output
+                                .snapped_bbox(cell_info, *obj)
+                                .expect("pin was not snapped")
+                                .transform(&xform);
+                            let layer = layers.layers.get(
+                                pin.pin_layer(|layer| layers.layers.contains_key(layer))
+                                    .as_str(),
+                            );
+                            if let Some(layer) = layer
+                                && layer.visible
+                            {
+                                rects.push((
+                                    Rect {
+                                        x0: bbox.x0 as f32,
+                                        y0: bbox.y0 as f32,
+                                        x1: bbox.x1 as f32,
+                                        y1: bbox.y1 as f32,
+                                        id: None,
+                                        object_path,
+                                        border_widths: Edges::all(DEFAULT_BORDER_WIDTH),
+                                        border_styles: Edges::all(BorderStyle::Solid),
+                                        cvars: None,
+                                    },
+                                    layer.clone(),
+                                ));
+                            }
+                            pin_labels.push((
+                                pin.name.clone(),
+                                Point::new(
+                                    ((bbox.x0 + bbox.x1) / 2.) as f32,
+                                    ((bbox.y0 + bbox.y1) / 2.) as f32,
+                                ),
                             ));
                         }
                         SolvedValue::Dimension(_) => {}
                         SolvedValue::Text(_) => {}
                     }
                 }
-                for child in &scope_info.children {
-                    let scope_address = ScopeAddress {
-                        scope: *child,
-                        cell,
-                    };
-                    queue.push_back((scope_address, mat, ofs, depth + 1, show, path.clone()));
+            }
+
+            // Highlight the net containing the selected shape.
+            if scope_address.cell == solved_cell.output.top
+                && let Some(nets) = &solved_cell.output.nets
+                && let Some(net) = select_rects
+                    .iter()
+                    .find_map(|rect| nets.net_of(&rect.object_path))
+            {
+                net_shapes.extend(nets.nets[net].shapes.iter().map(|&i| nets.shapes[i].bbox));
+            }
+
+            // Preview nudges that have not been written back to the source yet.
+            if let Some(nudge) = &inner.pending_nudge {
+                let (dx, dy) = (nudge.dx as f32, nudge.dy as f32);
+                for rect in rects
+                    .iter_mut()
+                    .map(|(rect, _)| rect)
+                    .chain(scope_rects.iter_mut())
+                    .chain(select_rects.iter_mut())
+                    .filter(|rect| rect.id.as_ref() == Some(&nudge.span))
+                {
+                    rect.x0 += dx;
+                    rect.x1 += dx;
+                    rect.y0 += dy;
+                    rect.y1 += dy;
                 }
             }
 
-            if let ToolState::DrawRect(DrawRectToolState { p0: Some(p0) }) = tool {
+            if let ToolState::DrawRect(DrawRectToolState { p0: Some(p0) }) = &tool {
+                let p0 = p0.pt;
+                let layout_mouse_position = snapped_mouse_position.pt;
                 rects.push((
                     Rect {
                         object_path: Vec::new(),
@@ -674,16 +1435,94 @@ impl Element for CanvasElement {
             .into_iter()
             .sorted_by_key(|(_, layer)| layer.z)
             .collect_vec();
+        polygons.sort_by_key(|(_, layer)| layer.z);
         let scale = inner.scale;
         let offset = inner.offset;
         let mut dim_hitboxes = Vec::new();
         let mut sse_handles: Vec<SseHandle> = Vec::new();
         let theme = inner.state.read(cx).theme();
+        let read_only = inner.state.read(cx).read_only;
+        let grid_lines = if state.show_grid {
+            inner.grid_lines(state.grid(cx))
+        } else {
+            Vec::new()
+        };
+        let mut drc_markers = state
+            .drc
+            .read(cx)
+            .as_ref()
+            .map(|drc| {
+                drc.mapped
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, marker)| {
+                        let selected = drc.selected == Some(i);
+                        marker.shapes.iter().map(move |shape| (*shape, selected))
+                    })
+                    .collect_vec()
+            })
+            .unwrap_or_default();
+        // Violations of the workspace's rule deck are drawn like imported markers.
+        if let Some(solved_cell) = solved_cell {
+            drc_markers.extend(
+                solved_cell
+                    .output
+                    .drc_violations
+                    .iter()
+                    .map(|violation| (violation.bbox, false)),
+            );
+        }
+        // Queried shapes are outlined, and the overlap of a pair is filled.
+        let (query_shapes, query_overlap) = match &tool {
+            ToolState::Query(QueryToolState { shapes }) => (
+                shapes.iter().map(|shape| shape.bbox).collect_vec(),
+                match shapes.as_slice() {
+                    [a, b] => a.bbox.intersection(&b.bbox),
+                    _ => None,
+                },
+            ),
+            _ => (Vec::new(), None),
+        };
+        // Underlay shapes take the color of the matching layer and are hidden
+        // along with it.
+        let underlay_shapes = state
+            .underlay
+            .read(cx)
+            .as_ref()
+            .filter(|underlay| underlay.visible)
+            .map(|underlay| {
+                underlay
+                    .shapes
+                    .iter()
+                    .filter_map(|shape| {
+                        let color = match layers.layers.get(shape.layer.as_str()) {
+                            Some(layer) if !layer.visible => return None,
+                            Some(layer) => layer.color,
+                            None => theme.text,
+                        };
+                        Some((
+                            shape.bbox,
+                            Rgba {
+                                a: underlay.opacity,
+                                ..color
+                            },
+                        ))
+                    })
+                    .collect_vec()
+            })
+            .unwrap_or_default();
         inner
             .bg_style
             .clone()
             .paint(bounds, window, cx, |window, cx| {
                 window.paint_layer(bounds, |window| {
+                    for line in &grid_lines {
+                        window.paint_quad(get_paint_path(
+                            line.select_bounds(px(0.)),
+                            theme.divider,
+                            px(1.),
+                        ));
+                    }
                     // Draw origin lines.
                     let origin_coords = self.inner.read(cx).layout_to_px(Point::new(0., 0.));
                     let y_axis = Edge {
@@ -708,6 +1547,27 @@ impl Element for CanvasElement {
                         theme.axes,
                         DEFAULT_BORDER_WIDTH,
                     ));
+                    for (shape, color) in &underlay_shapes {
+                        let underlay_bounds = Bounds::new(
+                            Point::new(
+                                scale * px(shape.x0 as f32),
+                                scale * px(-shape.y1 as f32),
+                            ) + offset
+                                + bounds.origin,
+                            Size::new(
+                                scale * px(shape.width() as f32),
+                                scale * px(shape.height() as f32),
+                            ),
+                        );
+                        window.paint_quad(get_paint_quad(
+                            underlay_bounds,
+                            ShapeFill::Solid,
+                            *color,
+                            Rgba { a: 0., ..*color },
+                            Edges::all(px(0.)),
+                            Edges::all(BorderStyle::Solid),
+                        ));
+                    }
                     for (r, l) in &rects {
                         window.paint_quad(get_paint_quad(
                             get_rect_bounds(r, bounds, scale, offset),
@@ -718,6 +1578,23 @@ impl Element for CanvasElement {
                             r.border_styles,
                         ));
                     }
+                    for (corners, l) in &polygons {
+                        let corners = corners
+                            .iter()
+                            .map(|corner| self.inner.read(cx).layout_to_px(*corner))
+                            .collect_vec();
+                        let mut path = gpui::Path::new(corners[0]);
+                        for corner in &corners[1..] {
+                            path.line_to(*corner);
+                        }
+                        window.paint_path(
+                            path,
+                            match l.fill {
+                                ShapeFill::Solid => solid_background(l.color),
+                                ShapeFill::Stippling => pattern_slash(l.color.into(), 1., 9.),
+                            },
+                        );
+                    }
                     for r in &scope_rects {
                         window.paint_quad(get_paint_quad(
                             get_rect_bounds(r, bounds, scale, offset),
@@ -728,55 +1605,241 @@ impl Element for CanvasElement {
                             r.border_styles,
                         ));
                     }
-                    for r in &select_rects {
+                    for (r, color) in &construction_rects {
+                        let color = color.unwrap_or(theme.subtext);
                         window.paint_quad(get_paint_quad(
                             get_rect_bounds(r, bounds, scale, offset),
                             ShapeFill::Solid,
-                            Rgba { a: 0., ..rgb(0xffff00) },
-                            rgb(0xffff00),
+                            Rgba { a: 0., ..color },
+                            color,
                             r.border_widths,
                             r.border_styles,
                         ));
                     }
-                    // Draw a draggable handle at the midpoint of every
-                    // unconstrained (dashed) edge, so the user can see exactly
-                    // where to grab to explore the solution space. The set of
-                    // handles is recorded for hit-testing in `on_left_mouse_down`.
-                    // Only top-level rects expose `cvars`, and an edge is
-                    // draggable iff it was rendered dashed.
-                    if matches!(tool, ToolState::Select(_)) {
-                        for (r, _) in &rects {
-                            let Some(cvars) = &r.cvars else { continue };
-                            let pb = get_rect_bounds(r, bounds, scale, offset);
-                            let center = pb.center();
-                            let draw_half = HANDLE_SIZE.half();
-                            let hit_half = HANDLE_HIT.half();
-                            let edges = [
-                                (r.border_styles.left, Point::new(pb.left(), center.y), &cvars.left, Point::new(1f32, 0.)),
-                                (r.border_styles.right, Point::new(pb.right(), center.y), &cvars.right, Point::new(1f32, 0.)),
-                                (r.border_styles.top, Point::new(center.x, pb.top()), &cvars.top, Point::new(0., 1f32)),
-                                (r.border_styles.bottom, Point::new(center.x, pb.bottom()), &cvars.bottom, Point::new(0., 1f32)),
-                            ];
-                            for (style, mid, expr, normal) in edges {
-                                if style != BorderStyle::Dashed {
-                                    continue;
-                                }
-                                // Draw a small visible handle, but record a
-                                // larger clickable area so it is easy to grab.
-                                window.paint_quad(get_paint_quad(
-                                    Bounds::new(
-                                        Point::new(mid.x - draw_half, mid.y - draw_half),
-                                        Size::new(HANDLE_SIZE, HANDLE_SIZE),
-                                    ),
-                                    ShapeFill::Solid,
-                                    rgb(HANDLE_FILL),
-                                    rgb(HANDLE_BORDER),
-                                    Edges::all(px(1.5)),
-                                    Edges::all(BorderStyle::Solid),
-                                ));
-                                sse_handles.push(SseHandle {
-                                    bounds: Bounds::new(
-                                        Point::new(mid.x - hit_half, mid.y - hit_half),
+                    for (shape, selected) in &drc_markers {
+                        let marker_bounds = Bounds::new(
+                            Point::new(
+                                scale * px(shape.x0 as f32),
+                                scale * px(-shape.y1 as f32),
+                            ) + offset
+                                + bounds.origin,
+                            Size::new(
+                                scale * px(shape.width() as f32),
+                                scale * px(shape.height() as f32),
+                            ),
+                        );
+                        window.paint_quad(get_paint_quad(
+                            marker_bounds,
+                            ShapeFill::Solid,
+                            Rgba {
+                                a: if *selected { 0.4 } else { 0.15 },
+                                ..theme.error
+                            },
+                            theme.error,
+                            Edges::all(if *selected {
+                                2. * DEFAULT_BORDER_WIDTH
+                            } else {
+                                DEFAULT_BORDER_WIDTH
+                            }),
+                            Edges::all(BorderStyle::Solid),
+                        ));
+                    }
+                    for shape in &net_shapes {
+                        let net_bounds = Bounds::new(
+                            Point::new(
+                                scale * px(shape.x0 as f32),
+                                scale * px(-shape.y1 as f32),
+                            ) + offset
+                                + bounds.origin,
+                            Size::new(
+                                scale * px(shape.width() as f32),
+                                scale * px(shape.height() as f32),
+                            ),
+                        );
+                        window.paint_quad(get_paint_quad(
+                            net_bounds,
+                            ShapeFill::Solid,
+                            Rgba {
+                                a: 0.3,
+                                ..rgb(0xffff00)
+                            },
+                            rgb(0xffff00),
+                            Edges::all(DEFAULT_BORDER_WIDTH),
+                            Edges::all(BorderStyle::Solid),
+                        ));
+                    }
+                    for (name, pos) in &pin_labels {
+                        let center = self.inner.read(cx).layout_to_px(*pos);
+                        let text = SharedString::from(name.clone());
+                        let runs = &[TextRun {
+                            color: theme.text.into(),
+                            ..window.text_style().to_run(text.len())
+                        }];
+                        let line = window.text_system().shape_line(text, px(12.), runs, None);
+                        let line_height = px(16.);
+                        line.paint(
+                            center - Point::new(line.width.half(), line_height.half()),
+                            line_height,
+                            window,
+                            cx,
+                        )
+                        .unwrap();
+                    }
+                    // Mark the origin, orientation, and ports of each outlined
+                    // instance, and label it with its name, cell, and port names
+                    // when there is room.
+                    for label in &instance_labels {
+                        let origin = self.inner.read(cx).layout_to_px(label.origin);
+                        for (dir, len) in [
+                            (label.x_dir, INSTANCE_MARKER_LEN),
+                            (label.y_dir, INSTANCE_MARKER_LEN.half()),
+                        ] {
+                            let end = origin
+                                + Point::new(len * dir.0 as f32, -len * dir.1 as f32);
+                            window.paint_quad(get_paint_path(
+                                Bounds::from_corners(origin.min(&end), origin.max(&end)),
+                                theme.text,
+                                DEFAULT_BORDER_WIDTH,
+                            ));
+                        }
+                        for port in &label.ports {
+                            let center = self.inner.read(cx).layout_to_px(port.pos);
+                            let half = PORT_MARKER_SIZE.half();
+                            window.paint_quad(get_paint_quad(
+                                Bounds::new(
+                                    Point::new(center.x - half, center.y - half),
+                                    Size::new(PORT_MARKER_SIZE, PORT_MARKER_SIZE),
+                                ),
+                                ShapeFill::Solid,
+                                port.color.unwrap_or(Rgba { a: 0., ..theme.text }),
+                                theme.text,
+                                Edges::all(px(1.)),
+                                Edges::all(BorderStyle::Solid),
+                            ));
+                        }
+                        let bbox = get_rect_bounds(&label.bbox, bounds, scale, offset);
+                        let line_height = px(16.);
+                        if bbox.size.height < line_height * 2. + px(8.) {
+                            continue;
+                        }
+                        window.with_content_mask(Some(ContentMask { bounds: bbox }), |window| {
+                            for port in &label.ports {
+                                let center = self.inner.read(cx).layout_to_px(port.pos);
+                                let text = SharedString::from(port.name.clone());
+                                let runs = &[TextRun {
+                                    color: theme.text.into(),
+                                    ..window.text_style().to_run(text.len())
+                                }];
+                                window
+                                    .text_system()
+                                    .shape_line(text, px(12.), runs, None)
+                                    .paint(
+                                        center + Point::new(PORT_MARKER_SIZE, -line_height.half()),
+                                        line_height,
+                                        window,
+                                        cx,
+                                    )
+                                    .unwrap();
+                            }
+                            let mut text_origin = bbox.origin + Point::new(px(4.), px(4.));
+                            for (text, color) in [
+                                (label.name.as_deref(), theme.text),
+                                (Some(label.cell.as_str()), theme.subtext),
+                            ] {
+                                let Some(text) = text else {
+                                    continue;
+                                };
+                                let text = SharedString::from(text.to_string());
+                                let runs = &[TextRun {
+                                    color: color.into(),
+                                    ..window.text_style().to_run(text.len())
+                                }];
+                                window
+                                    .text_system()
+                                    .shape_line(text, px(14.), runs, None)
+                                    .paint(text_origin, line_height, window, cx)
+                                    .unwrap();
+                                text_origin.y += line_height;
+                            }
+                        });
+                    }
+                    for (shape, fill) in query_shapes
+                        .iter()
+                        .map(|shape| (shape, 0.))
+                        .chain(query_overlap.iter().map(|shape| (shape, 0.5)))
+                    {
+                        let query_bounds = Bounds::new(
+                            Point::new(
+                                scale * px(shape.x0 as f32),
+                                scale * px(-shape.y1 as f32),
+                            ) + offset
+                                + bounds.origin,
+                            Size::new(
+                                scale * px(shape.width() as f32),
+                                scale * px(shape.height() as f32),
+                            ),
+                        );
+                        window.paint_quad(get_paint_quad(
+                            query_bounds,
+                            ShapeFill::Solid,
+                            Rgba {
+                                a: fill,
+                                ..rgb(0xffff00)
+                            },
+                            rgb(0xffff00),
+                            Edges::all(SELECT_WIDTH),
+                            Edges::all(BorderStyle::Solid),
+                        ));
+                    }
+                    for r in &select_rects {
+                        window.paint_quad(get_paint_quad(
+                            get_rect_bounds(r, bounds, scale, offset),
+                            ShapeFill::Solid,
+                            Rgba { a: 0., ..rgb(0xffff00) },
+                            rgb(0xffff00),
+                            r.border_widths,
+                            r.border_styles,
+                        ));
+                    }
+                    // Draw a draggable handle at the midpoint of every
+                    // unconstrained (dashed) edge, so the user can see exactly
+                    // where to grab to explore the solution space. The set of
+                    // handles is recorded for hit-testing in `on_left_mouse_down`.
+                    // Only top-level rects expose `cvars`, and an edge is
+                    // draggable iff it was rendered dashed.
+                    if matches!(tool, ToolState::Select(_)) && !read_only {
+                        for (r, _) in &rects {
+                            let Some(cvars) = &r.cvars else { continue };
+                            let pb = get_rect_bounds(r, bounds, scale, offset);
+                            let center = pb.center();
+                            let draw_half = HANDLE_SIZE.half();
+                            let hit_half = HANDLE_HIT.half();
+                            let edges = [
+                                (r.border_styles.left, Point::new(pb.left(), center.y), &cvars.left, Point::new(1f32, 0.)),
+                                (r.border_styles.right, Point::new(pb.right(), center.y), &cvars.right, Point::new(1f32, 0.)),
+                                (r.border_styles.top, Point::new(center.x, pb.top()), &cvars.top, Point::new(0., 1f32)),
+                                (r.border_styles.bottom, Point::new(center.x, pb.bottom()), &cvars.bottom, Point::new(0., 1f32)),
+                            ];
+                            for (style, mid, expr, normal) in edges {
+                                if style != BorderStyle::Dashed {
+                                    continue;
+                                }
+                                // Draw a small visible handle, but record a
+                                // larger clickable area so it is easy to grab.
+                                window.paint_quad(get_paint_quad(
+                                    Bounds::new(
+                                        Point::new(mid.x - draw_half, mid.y - draw_half),
+                                        Size::new(HANDLE_SIZE, HANDLE_SIZE),
+                                    ),
+                                    ShapeFill::Solid,
+                                    rgb(HANDLE_FILL),
+                                    rgb(HANDLE_BORDER),
+                                    Edges::all(px(1.5)),
+                                    Edges::all(BorderStyle::Solid),
+                                ));
+                                sse_handles.push(SseHandle {
+                                    bounds: Bounds::new(
+                                        Point::new(mid.x - hit_half, mid.y - hit_half),
                                         Size::new(HANDLE_HIT, HANDLE_HIT),
                                     ),
                                     expr: expr.clone(),
@@ -786,6 +1849,9 @@ impl Element for CanvasElement {
                         }
                     }
 
+                    // Screen bounds of the dimension labels drawn so far, which
+                    // later labels are placed to avoid.
+                    let mut dim_labels: Vec<Bounds<Pixels>> = Vec::new();
                     let mut draw_dim =
                         |p: f32,
                          n: f32,
@@ -795,7 +1861,8 @@ impl Element for CanvasElement {
                          horiz: bool,
                          value: String,
                          color: Rgba,
-                         span: Option<&Span>| {
+                         span: Option<&Span>,
+                         focused: bool| {
                             let (x0, y0, x1, y1) = if horiz {
                                 (
                                     p,
@@ -884,41 +1951,93 @@ impl Element for CanvasElement {
                                 border_styles: Edges::all(BorderStyle::Solid),
                                 cvars: None,
                             };
+                            // Dimensions too short to read on screen are
+                            // skipped unless they are being worked on.
+                            let inner = self.inner.read(cx);
+                            let (start, end) = (
+                                inner.layout_to_px(Point::new(x0, y0)),
+                                inner.layout_to_px(Point::new(x1, y1)),
+                            );
+                            let len = if horiz {
+                                (end.x - start.x).abs()
+                            } else {
+                                (end.y - start.y).abs()
+                            };
+                            if len < MIN_DIM_LEN && !focused {
+                                return;
+                            }
+                            let thickness = dim_line_width(len);
                             for r in &[start_line, stop_line, dim_line] {
                                 window.paint_quad(get_paint_path(
                                     get_rect_bounds(r, bounds, scale, offset),
                                     color,
-                                    DEFAULT_BORDER_WIDTH,
+                                    thickness,
                                 ));
                             }
 
-                            let run_len = value.len();
-                            let font_size = px(14.);
-                            let runs = &[window.text_style().to_run(run_len)];
-                            let origin = self
-                                .inner
-                                .read(cx)
-                                .layout_to_px(Point::new((x0 + x1) / 2., (y0 + y1) / 2.));
+                            // Arrowheads point out to the extension lines, or in
+                            // from outside them if the line is too short to hold
+                            // both.
+                            let dir = if len > px(0.) {
+                                Point::new((end.x - start.x) / len, (end.y - start.y) / len)
+                            } else if horiz {
+                                Point::new(1., 0.)
+                            } else {
+                                Point::new(0., 1.)
+                            };
+                            let inside = dim_arrows_inside(len);
+                            let sign = if inside { 1. } else { -1. };
+                            for (tip, dir) in [
+                                (start, Point::new(-sign * dir.x, -sign * dir.y)),
+                                (end, Point::new(sign * dir.x, sign * dir.y)),
+                            ] {
+                                window.paint_path(
+                                    dim_arrowhead(tip, dir),
+                                    solid_background(color),
+                                );
+                            }
+
+                            let Some(font_size) = dim_font_size(len, focused) else {
+                                return;
+                            };
+                            let runs = &[window.text_style().to_run(value.len())];
                             let text = SharedString::from(value);
                             let layout =
                                 window
                                     .text_system()
                                     .layout_line(&text, font_size, runs, None);
+                            let candidates = dim_label_candidates(
+                                start,
+                                end,
+                                horiz,
+                                !inside,
+                                size(layout.width, font_size),
+                            );
+                            let Some(label) = place_dim_label(&candidates, &dim_labels)
+                                .or(focused.then_some(candidates[0]))
+                            else {
+                                return;
+                            };
+                            dim_labels.push(label);
                             if let Some(span) = span {
-                                dim_hitboxes.push((
-                                    span.clone(),
-                                    vec![Bounds::new(origin, size(layout.width, font_size))],
-                                    text.clone(),
-                                ));
+                                dim_hitboxes.push((span.clone(), vec![label], text.clone()));
                             }
                             window
                                 .text_system()
                                 .shape_line(text, font_size, runs, None)
-                                .paint(origin, px(16.), window, cx)
+                                .paint(label.origin, font_size, window, cx)
                                 .unwrap();
                         };
 
                     for dim in dims {
+                        let selected = matches!(
+                            &tool,
+                            ToolState::Select(SelectToolState {
+                                selected_obj: Some(selected),
+                            })
+                            | ToolState::EditDim(EditDimToolState { dim: selected, .. })
+                                if Some(selected) == dim.span.as_ref()
+                        );
                         draw_dim(
                             dim.p as f32,
                             dim.n as f32,
@@ -927,18 +2046,9 @@ impl Element for CanvasElement {
                             dim.nstop as f32,
                             dim.horiz,
                             format!("{:.3}", dim.value), // TODO: show actual expression
-                            match &tool {
-                                ToolState::Select(SelectToolState {
-                                    selected_obj: Some(selected),
-                                })
-                                | ToolState::EditDim(EditDimToolState { dim: selected, .. })
-                                    if Some(selected) == dim.span.as_ref() =>
-                                {
-                                    rgb(0xffff00)
-                                }
-                                _ => theme.text,
-                            },
+                            if selected { rgb(0xffff00) } else { theme.text },
                             dim.span.as_ref(),
+                            selected,
                         );
                     }
 
@@ -960,6 +2070,7 @@ impl Element for CanvasElement {
                                     format!("{:.3}", (edge.stop - edge.start).abs()),
                                     rgb(0xff0000),
                                     None,
+                                    true,
                                 );
                             }
                         } else if edges.len() == 2 {
@@ -1001,7 +2112,18 @@ impl Element for CanvasElement {
                                     }
                                     _ => unreachable!(),
                                 };
-                            draw_dim(p, n, coord, pstop, nstop, horiz, value, rgb(0xff0000), None);
+                            draw_dim(
+                                p,
+                                n,
+                                coord,
+                                pstop,
+                                nstop,
+                                horiz,
+                                value,
+                                rgb(0xff0000),
+                                None,
+                                true,
+                            );
                         }
                         // highlight selected edges
                         for edge in edges {
@@ -1038,6 +2160,23 @@ impl Element for CanvasElement {
                             window.paint_quad(get_paint_path(bounds, rgb(0xffff00), DEFAULT_BORDER_WIDTH));
                         }
                     }
+                    // Show the size of the rect being drawn next to the cursor.
+                    if let ToolState::DrawRect(DrawRectToolState { p0: Some(p0) }) = &tool {
+                        let p1 = snapped_mouse_position.pt;
+                        let text = SharedString::from(format!(
+                            "{:.3} x {:.3}",
+                            (p1.x - p0.pt.x).abs(),
+                            (p1.y - p0.pt.y).abs(),
+                        ));
+                        let font_size = px(14.);
+                        let runs = &[window.text_style().to_run(text.len())];
+                        let origin = self.inner.read(cx).mouse_position + Point::new(px(12.), px(12.));
+                        window
+                            .text_system()
+                            .shape_line(text, font_size, runs, None)
+                            .paint(origin, px(16.), window, cx)
+                            .unwrap();
+                    }
                     let inner = self.inner.read(cx);
                     // highlight hover edges
                     // TODO: reduce repeat code from on_left_mouse_down
@@ -1294,14 +2433,17 @@ impl Render for LayoutCanvas {
         div()
             .flex()
             .flex_1()
+            .key_context("LayoutCanvas")
             .track_focus(&self.focus_handle(cx))
             .size_full()
             .on_mouse_down(MouseButton::Left, cx.listener(Self::on_left_mouse_down))
             .on_mouse_down(MouseButton::Middle, cx.listener(Self::on_middle_mouse_down))
+            .on_mouse_down(MouseButton::Right, cx.listener(Self::on_right_mouse_down))
             .on_mouse_move(cx.listener(Self::on_mouse_move))
             .on_action(cx.listener(Self::draw_rect))
             .on_action(cx.listener(Self::select_mode))
             .on_action(cx.listener(Self::draw_dim))
+            .on_action(cx.listener(Self::query_mode))
             .on_action(cx.listener(Self::edit_action))
             .on_action(cx.listener(Self::fit_to_screen_action))
             .on_action(cx.listener(Self::zero_hierarchy))
@@ -1311,14 +2453,35 @@ impl Render for LayoutCanvas {
             .on_action(cx.listener(Self::cancel))
             .on_action(cx.listener(Self::dark_mode))
             .on_action(cx.listener(Self::light_mode))
+            .on_action(cx.listener(Self::nudge_left))
+            .on_action(cx.listener(Self::nudge_right))
+            .on_action(cx.listener(Self::nudge_up))
+            .on_action(cx.listener(Self::nudge_down))
+            .on_action(cx.listener(Self::nudge_left_coarse))
+            .on_action(cx.listener(Self::nudge_right_coarse))
+            .on_action(cx.listener(Self::nudge_up_coarse))
+            .on_action(cx.listener(Self::nudge_down_coarse))
+            .on_action(cx.listener(Self::toggle_grid))
+            .on_action(cx.listener(Self::zoom_in))
+            .on_action(cx.listener(Self::zoom_out))
+            .on_action(cx.listener(Self::zoom_reset))
+            .on_action(cx.listener(Self::zoom_back))
+            .on_action(cx.listener(Self::next_drc_marker))
+            .on_action(cx.listener(Self::prev_drc_marker))
             .on_mouse_up(MouseButton::Middle, cx.listener(Self::on_middle_mouse_up))
             .on_mouse_up_out(MouseButton::Middle, cx.listener(Self::on_middle_mouse_up))
             .on_mouse_up(MouseButton::Left, cx.listener(Self::on_left_mouse_up))
             .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_left_mouse_up))
             .on_scroll_wheel(cx.listener(Self::on_scroll_wheel))
+            .on_drop(cx.listener(Self::on_drop_cell))
             .child(CanvasElement {
                 inner: cx.entity().clone(),
             })
+            .children(
+                self.context_menu
+                    .clone()
+                    .map(|menu| self.render_context_menu(menu, cx)),
+            )
     }
 }
 
@@ -1352,10 +2515,15 @@ impl LayoutCanvas {
             sse_delta: Point::default(),
             sse_normal: Point::new(0., 0.),
             sse_handles: Vec::new(),
+            pending_nudge: None,
+            nudge_generation: 0,
+            context_menu: None,
             drag_start: Point::default(),
             offset_start: Point::default(),
             mouse_position: Point::default(),
             scale: 1.0,
+            camera_animation: None,
+            previous_camera: None,
             screen_bounds: Bounds::default(),
             subscriptions: vec![cx.observe(state, |_, _, cx| cx.notify())],
             state: state.clone(),
@@ -1367,6 +2535,76 @@ impl LayoutCanvas {
     }
 
     pub(crate) fn fit_to_screen(&mut self, cx: &mut Context<Self>) {
+        self.camera_animation = None;
+        self.set_camera(self.fit_camera(cx));
+        cx.notify();
+    }
+
+    /// Returns the camera that fits `bbox` to the canvas with a small margin.
+    fn camera_for_bbox(&self, bbox: Bbox) -> Camera {
+        let scalex = self.screen_bounds.size.width / bbox.width() as f32;
+        let scaley = self.screen_bounds.size.height / bbox.height() as f32;
+        let scale = 0.9 * f32::from(scalex.min(scaley));
+        Camera {
+            scale,
+            offset: Point::new(
+                px((-(bbox.x0 + bbox.x1) as f32 * scale
+                    + f32::from(self.screen_bounds.size.width))
+                    / 2.),
+                px(((bbox.y1 + bbox.y0) as f32 * scale
+                    + f32::from(self.screen_bounds.size.height))
+                    / 2.),
+            ),
+        }
+    }
+
+    /// Animates the camera to show `bbox` along with some surrounding context.
+    pub(crate) fn zoom_to_bbox(&mut self, bbox: Bbox, cx: &mut Context<Self>) {
+        let margin = bbox.width().max(bbox.height()).max(LAYOUT_UNITS_PER_MICRON);
+        self.animate_camera(
+            self.camera_for_bbox(Bbox::new(
+                bbox.x0 - margin,
+                bbox.y0 - margin,
+                bbox.x1 + margin,
+                bbox.y1 + margin,
+            )),
+            cx,
+        );
+    }
+
+    /// Selects the DRC marker `delta` entries away from the current one and zooms to it.
+    fn step_drc_marker(&mut self, delta: isize, cx: &mut Context<Self>) {
+        let drc = self.state.read(cx).drc.clone();
+        let bbox = drc.update(cx, |drc, cx| {
+            let bbox = drc.as_mut()?.step(delta)?.bbox();
+            cx.notify();
+            bbox
+        });
+        if let Some(bbox) = bbox {
+            self.zoom_to_bbox(bbox, cx);
+        }
+    }
+
+    pub(crate) fn next_drc_marker(
+        &mut self,
+        _: &NextDrcMarker,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.step_drc_marker(1, cx);
+    }
+
+    pub(crate) fn prev_drc_marker(
+        &mut self,
+        _: &PrevDrcMarker,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.step_drc_marker(-1, cx);
+    }
+
+    /// Returns the camera that fits the selected scope to the canvas.
+    fn fit_camera(&self, cx: &App) -> Camera {
         if let Some(cell) = self.state.read(cx).solved_cell.read(cx)
             && let Some(bbox) = &cell.state[&cell.selected_scope].bbox.as_ref().or_else(|| {
                 let scope_address = &cell.state[&cell.selected_scope].address;
@@ -1378,29 +2616,94 @@ impl LayoutCanvas {
                     .as_ref()
             })
         {
-            let scalex = self.screen_bounds.size.width / (bbox.x1 - bbox.x0) as f32;
-            let scaley = self.screen_bounds.size.height / (bbox.y1 - bbox.y0) as f32;
-            self.scale = 0.9 * f32::from(scalex.min(scaley));
-            self.offset = Point::new(
-                px((-(bbox.x0 + bbox.x1) as f32 * self.scale
-                    + f32::from(self.screen_bounds.size.width))
-                    / 2.),
-                px(((bbox.y1 + bbox.y0) as f32 * self.scale
-                    + f32::from(self.screen_bounds.size.height))
-                    / 2.),
-            );
+            self.camera_for_bbox(bbox.bbox())
         } else {
-            self.offset = Point::new(px(0.), self.screen_bounds.size.height);
+            Camera {
+                scale: self.scale,
+                offset: Point::new(px(0.), self.screen_bounds.size.height),
+            }
+        }
+    }
+
+    fn camera(&self) -> Camera {
+        Camera {
+            scale: self.scale,
+            offset: self.offset,
         }
+    }
+
+    fn set_camera(&mut self, camera: Camera) {
+        self.scale = camera.scale;
+        self.offset = camera.offset;
+    }
+
+    /// Canvas-relative position of the center of the canvas.
+    fn screen_center(&self) -> Point<Pixels> {
+        Point::new(
+            self.screen_bounds.size.width.half(),
+            self.screen_bounds.size.height.half(),
+        )
+    }
+
+    /// The camera the view is at or currently animating towards.
+    fn target_camera(&self) -> Camera {
+        self.camera_animation
+            .map(|animation| animation.to)
+            .unwrap_or_else(|| self.camera())
+    }
+
+    /// Starts an animated transition from the current view to `camera`,
+    /// remembering the view being left for [`ZoomBack`].
+    fn animate_camera(&mut self, camera: Camera, cx: &mut Context<Self>) {
+        self.previous_camera = Some(self.target_camera());
+        self.camera_animation = Some(CameraAnimation {
+            from: self.camera(),
+            to: camera,
+            start: Instant::now(),
+        });
         cx.notify();
     }
 
+    /// Advances the camera animation, if any, to the current time. Returns
+    /// `true` if the animation needs another frame.
+    fn step_camera_animation(&mut self) -> bool {
+        let Some(animation) = self.camera_animation else {
+            return false;
+        };
+        let t = animation.start.elapsed().as_secs_f32() / CAMERA_ANIMATION_DURATION.as_secs_f32();
+        if t >= 1. {
+            self.set_camera(animation.to);
+            self.camera_animation = None;
+            false
+        } else {
+            self.set_camera(
+                animation
+                    .from
+                    .lerp(&animation.to, ease_out(t), self.screen_center()),
+            );
+            true
+        }
+    }
+
+    /// Animates to `scale`, keeping the layout point at the center of the
+    /// canvas fixed.
+    fn zoom_to(&mut self, scale: f32, cx: &mut Context<Self>) {
+        let center = self.screen_center();
+        let target = self.target_camera();
+        let camera = Camera::centered(scale.clamp(0.01, 100.), target.layout_at(center), center);
+        self.animate_camera(camera, cx);
+    }
+
     pub(crate) fn on_left_mouse_down(
         &mut self,
         event: &MouseDownEvent,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.context_menu.take().is_some() {
+            cx.notify();
+            return;
+        }
         let origin_coords = self.layout_to_px(Point::new(0., 0.));
         let y_axis = Edge {
             dir: Dir::Vert,
@@ -1415,6 +2718,9 @@ impl LayoutCanvas {
             stop: self.screen_bounds.origin.x + self.screen_bounds.size.width,
         };
         let layout_mouse_position = self.px_to_layout(event.position);
+        let snapped = self.snap(cx, event.position);
+        let mut start_rect = false;
+        let mut finish_rect = false;
         let edit_dim = self.state.read(cx).tool.clone().update(cx, |tool, cx| {
             let mut edit_dim = false;
             match tool {
@@ -1425,73 +2731,12 @@ impl LayoutCanvas {
                         && let Some(layer_info) = layers.layers.get(layer)
                     {
                         if layer_info.visible {
-                            if let Some(p0) = rect_tool.p0 {
-                                rect_tool.p0 = None;
-                                let p1 = layout_mouse_position;
-                                let p0p = Point::new(f32::min(p0.x, p1.x), f32::min(p0.y, p1.y));
-                                let p1p = Point::new(f32::max(p0.x, p1.x), f32::max(p0.y, p1.y));
-                                self.state.update(cx, |state, cx| {
-                                    let error = state.solved_cell.update(cx, {
-                                        |cell, cx| {
-                                            if let Some(cell) = cell.as_mut() {
-                                                // TODO update in memory representation of code
-                                                // TODO add solver to gui
-                                                let scope_address =
-                                                    &cell.state[&cell.selected_scope].address;
-                                                let reachable_objs = cell.output.reachable_objs(
-                                                    scope_address.cell,
-                                                    scope_address.scope,
-                                                );
-                                                let names: IndexSet<_> =
-                                                    reachable_objs.values().collect();
-                                                let scope = cell
-                                                    .output
-                                                    .cells
-                                                    .get_mut(&scope_address.cell)
-                                                    .unwrap()
-                                                    .scopes
-                                                    .get_mut(&scope_address.scope)
-                                                    .unwrap();
-                                                let rect_name = (0..)
-                                                    .map(|i| format!("rect{i}"))
-                                                    .find(|name| !names.contains(name))
-                                                    .unwrap();
-
-                                                match state.lang_server_client.draw_rect(
-                                                    scope.span.clone(),
-                                                    rect_name,
-                                                    compile::BasicRect {
-                                                        layer: state
-                                                            .layers
-                                                            .read(cx)
-                                                            .selected_layer
-                                                            .clone()
-                                                            .map(|s| s.to_string()),
-                                                        x0: p0p.x as f64,
-                                                        y0: p0p.y as f64,
-                                                        x1: p1p.x as f64,
-                                                        y1: p1p.y as f64,
-                                                        construction: false,
-                                                    },
-                                                ) {
-                                                    Ok(None) => Some(
-                                                        "inconsistent editor and GUI state".into(),
-                                                    ),
-                                                    Ok(Some(_)) => None,
-                                                    Err(e) => Some(format!("{e}").into()),
-                                                }
-                                            } else {
-                                                Some("no cell to edit".into())
-                                            }
-                                        }
-                                    });
-                                    if state.fatal_error.is_none() {
-                                        state.fatal_error = error;
-                                    }
-                                });
+                            if let Some(p0) = rect_tool.p0.take() {
+                                commit_rect(&self.state, cx, p0, snapped, None);
+                                finish_rect = true;
                             } else {
-                                let p0 = self.px_to_layout(event.position);
-                                rect_tool.p0 = Some(p0);
+                                rect_tool.p0 = Some(snapped);
+                                start_rect = true;
                             }
                         } else {
                             let res = state.lang_server_client.show_message(
@@ -1707,7 +2952,8 @@ impl LayoutCanvas {
                                 Dir::Vert => ("y0", "y1", layout_mouse_position.x, "false"),
                             };
 
-                            let value = format!("{:?}", edge.2.stop - edge.2.start);
+                            let grid = cell.output.grid;
+                            let value = format_on_grid(edge.2.stop - edge.2.start, grid);
                             let res = state.lang_server_client.draw_dimension(
                                 cell.output.cells[&selected_scope_addr.cell].scopes
                                     [&selected_scope_addr.scope]
@@ -1716,11 +2962,21 @@ impl LayoutCanvas {
                                 DimensionParams {
                                     p: format!("{}.{}", edge.0, right),
                                     n: format!("{}.{}", edge.0, left),
-                                    value: value.clone(),
+                                    value,
                                     coord: if coord > edge.2.coord {
-                                        format!("{}.{} + {}", edge.0, edge.1, coord - edge.2.coord)
+                                        format!(
+                                            "{}.{} + {}",
+                                            edge.0,
+                                            edge.1,
+                                            format_on_grid(coord - edge.2.coord, grid)
+                                        )
                                     } else {
-                                        format!("{}.{} - {}", edge.0, edge.1, edge.2.coord - coord)
+                                        format!(
+                                            "{}.{} - {}",
+                                            edge.0,
+                                            edge.1,
+                                            format_on_grid(edge.2.coord - coord, grid)
+                                        )
                                     },
                                     pstop: format!("{}.{}", edge.0, edge.1),
                                     nstop: format!("{}.{}", edge.0, edge.1),
@@ -1737,7 +2993,7 @@ impl LayoutCanvas {
                                 });
                             }
 
-                            res.unwrap_or_default().map(|span| (span, value))
+                            res.unwrap_or_default()
                         } else if dim_tool.edges.len() == 2 {
                             match (&dim_tool.edges[0], &dim_tool.edges[1]) {
                                 (DimEdge::Edge(edge0), DimEdge::Edge(edge1)) => {
@@ -1753,15 +3009,22 @@ impl LayoutCanvas {
                                         }
                                     };
 
+                                    let grid = cell.output.grid;
                                     let intended_coord =
                                         (right.2.start + right.2.stop + left.2.start + left.2.stop)
                                             / 4.;
                                     let coord_offset = if coord > intended_coord {
-                                        format!("+ {}", coord - intended_coord)
+                                        format!(
+                                            "+ {}",
+                                            format_on_grid(coord - intended_coord, grid)
+                                        )
                                     } else {
-                                        format!("- {}", intended_coord - coord)
+                                        format!(
+                                            "- {}",
+                                            format_on_grid(intended_coord - coord, grid)
+                                        )
                                     };
-                                    let value = format!("{:?}", right.2.coord - left.2.coord);
+                                    let value = format_on_grid(right.2.coord - left.2.coord, grid);
                                     let res = state.lang_server_client.draw_dimension(
                                         cell.output.cells[&selected_scope_addr.cell].scopes
                                             [&selected_scope_addr.scope]
@@ -1770,7 +3033,7 @@ impl LayoutCanvas {
                                         DimensionParams {
                                             p: format!("{}.{}", right.0, right.1,),
                                             n: format!("{}.{}", left.0, left.1),
-                                            value: value.clone(),
+                                            value,
                                             coord: format!(
                                                 "({}.{} + {}.{} + {}.{} + {}.{})/4. {coord_offset}",
                                                 right.0,
@@ -1805,7 +3068,7 @@ impl LayoutCanvas {
                                         });
                                     }
 
-                                    res.unwrap_or_default().map(|span| (span, value))
+                                    res.unwrap_or_default()
                                 }
                                 (DimEdge::X0 | DimEdge::Y0, DimEdge::Edge(edge))
                                 | (DimEdge::Edge(edge), DimEdge::X0 | DimEdge::Y0) => {
@@ -1816,11 +3079,18 @@ impl LayoutCanvas {
                                         }
                                     };
 
+                                    let grid = cell.output.grid;
                                     let intended_coord = (edge.2.start + edge.2.stop) / 2.;
                                     let coord_offset = if coord > intended_coord {
-                                        format!("+ {}", coord - intended_coord)
+                                        format!(
+                                            "+ {}",
+                                            format_on_grid(coord - intended_coord, grid)
+                                        )
                                     } else {
-                                        format!("- {}", intended_coord - coord)
+                                        format!(
+                                            "- {}",
+                                            format_on_grid(intended_coord - coord, grid)
+                                        )
                                     };
 
                                     let pnstop = format!(
@@ -1832,7 +3102,7 @@ impl LayoutCanvas {
                                         (
                                             "0.".to_string(),
                                             format!("{}.{}", edge.0, edge.1),
-                                            format!("{:?}", -edge.2.coord),
+                                            format_on_grid(-edge.2.coord, grid),
                                             coord.clone(),
                                             pnstop,
                                         )
@@ -1840,7 +3110,7 @@ impl LayoutCanvas {
                                         (
                                             format!("{}.{}", edge.0, edge.1),
                                             "0.".to_string(),
-                                            format!("{:?}", edge.2.coord),
+                                            format_on_grid(edge.2.coord, grid),
                                             pnstop,
                                             coord.clone(),
                                         )
@@ -1853,7 +3123,7 @@ impl LayoutCanvas {
                                         DimensionParams {
                                             p,
                                             n,
-                                            value: value.clone(),
+                                            value,
                                             coord,
                                             pstop,
                                             nstop,
@@ -1872,7 +3142,7 @@ impl LayoutCanvas {
                                         });
                                     }
 
-                                    res.unwrap_or_default().map(|span| (span, value))
+                                    res.unwrap_or_default()
                                 }
                                 _ => unreachable!(),
                             }
@@ -1907,35 +3177,7 @@ impl LayoutCanvas {
                         self.sse_normal = handle.normal;
                         cx.notify();
                     } else {
-                        let rects = self
-                            .rects
-                            .iter()
-                            .rev()
-                            .sorted_by_key(|(_, layer)| usize::MAX - layer.z)
-                            .map(|(r, _)| r);
-                        let scale = self.scale;
-                        let offset = self.offset;
-                        let mut selected_obj = None;
-                        for (span, bounds) in rects
-                            .chain(self.scope_rects.iter())
-                            .filter_map(|r| {
-                                let rect_bounds = Bounds::new(
-                                    Point::new(scale * px(r.x0), scale * px(-r.y1))
-                                        + offset
-                                        + self.screen_bounds.origin,
-                                    Size::new(scale * px(r.x1 - r.x0), scale * px(r.y1 - r.y0)),
-                                );
-                                Some((r.id.as_ref()?, rect_bounds))
-                            })
-                            .chain(self.dim_hitboxes.iter().flat_map(|(span, hitboxes, _)| {
-                                hitboxes.iter().map(|hitbox| (span, *hitbox)).collect_vec()
-                            }))
-                        {
-                            if bounds.contains(&event.position) {
-                                selected_obj = Some(span);
-                                break;
-                            }
-                        }
+                        let selected_obj = self.hit_test(event.position).map(|hit| hit.span());
                         if let Some(span) = selected_obj {
                             select_tool.selected_obj = Some(span.clone());
                             if let Err(e) = self
@@ -1956,6 +3198,46 @@ impl LayoutCanvas {
                         cx.notify();
                     }
                 }
+                ToolState::Query(query_tool) => {
+                    // Shift-click adds a shape to (or removes it from) the query;
+                    // a plain click replaces it.
+                    let picked = match self.hit_test(event.position) {
+                        Some(CanvasHit::Rect(rect, layer)) => self
+                            .state
+                            .read(cx)
+                            .solved_cell
+                            .read(cx)
+                            .as_ref()
+                            .and_then(|cell| {
+                                query::object_bbox(
+                                    &cell.output,
+                                    cell.state[&cell.selected_scope].address.cell,
+                                    &rect.object_path,
+                                )
+                            })
+                            .map(|bbox| QueryShape {
+                                object_path: rect.object_path.clone(),
+                                layer: layer.name.to_string(),
+                                bbox,
+                            }),
+                        _ => None,
+                    };
+                    if !event.modifiers.shift {
+                        query_tool.shapes.clear();
+                    }
+                    if let Some(shape) = picked {
+                        if let Some(i) = query_tool
+                            .shapes
+                            .iter()
+                            .position(|s| s.object_path == shape.object_path)
+                        {
+                            query_tool.shapes.remove(i);
+                        } else {
+                            query_tool.shapes.push(shape);
+                        }
+                    }
+                    cx.notify();
+                }
                 _ => {}
             }
             edit_dim
@@ -1965,9 +3247,70 @@ impl LayoutCanvas {
             self.text_input_focus_handle
                 .dispatch_action(&EditDim, window, cx);
             window.prevent_default();
+        } else if start_rect {
+            // Route keystrokes to the command prompt so exact dimensions can be
+            // typed (e.g. `2.5x0.6<enter>`) while the rect is being dragged out.
+            window.focus(&self.text_input_focus_handle);
+            self.text_input_focus_handle
+                .dispatch_action(&RectDims, window, cx);
+            window.prevent_default();
+        } else if finish_rect {
+            self.text_input_focus_handle
+                .dispatch_action(&Cancel, window, cx);
         }
     }
 
+    /// Snaps a screen position to nearby edges of named rects in the selected
+    /// scope. Each axis snaps independently to the closest edge within
+    /// [`SELECT_WIDTH`] that the position lies alongside.
+    fn snap(&self, cx: &App, position: Point<Pixels>) -> SnappedPoint {
+        let mut snapped = SnappedPoint::from(self.px_to_layout(position));
+        let Some(cell) = self.state.read(cx).solved_cell.read(cx) else {
+            return snapped;
+        };
+        let scope = cell.state[&cell.selected_scope].address;
+        let (mut best_x, mut best_y) = (SELECT_WIDTH, SELECT_WIDTH);
+        for (r, _) in &self.rects {
+            if r.id.is_none() || r.object_path.is_empty() {
+                continue;
+            }
+            let b = get_rect_bounds(r, self.screen_bounds, self.scale, self.offset);
+            let alongside_x =
+                position.x >= b.left() - SELECT_WIDTH && position.x <= b.right() + SELECT_WIDTH;
+            let alongside_y =
+                position.y >= b.top() - SELECT_WIDTH && position.y <= b.bottom() + SELECT_WIDTH;
+            let edge_ref = |edge: &str| match find_obj_path(&r.object_path, cell, scope) {
+                (true, path) => Some(format!("{}.{edge}", path.join("."))),
+                _ => None,
+            };
+            if alongside_y {
+                for (edge, coord_px, coord) in [("x0", b.left(), r.x0), ("x1", b.right(), r.x1)] {
+                    let dist = (position.x - coord_px).abs();
+                    if dist < best_x
+                        && let Some(edge_ref) = edge_ref(edge)
+                    {
+                        best_x = dist;
+                        snapped.pt.x = coord;
+                        snapped.x_ref = Some(edge_ref);
+                    }
+                }
+            }
+            if alongside_x {
+                for (edge, coord_px, coord) in [("y0", b.bottom(), r.y0), ("y1", b.top(), r.y1)] {
+                    let dist = (position.y - coord_px).abs();
+                    if dist < best_y
+                        && let Some(edge_ref) = edge_ref(edge)
+                    {
+                        best_y = dist;
+                        snapped.pt.y = coord;
+                        snapped.y_ref = Some(edge_ref);
+                    }
+                }
+            }
+        }
+        snapped
+    }
+
     fn layout_to_px(&self, pt: Point<f32>) -> Point<Pixels> {
         Point::new(self.scale * px(pt.x), self.scale * px(-pt.y))
             + self.offset
@@ -1980,6 +3323,9 @@ impl LayoutCanvas {
     }
 
     pub(crate) fn draw_rect(&mut self, _: &DrawRect, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.state.read(cx).read_only {
+            return;
+        }
         self.state.read(cx).tool.clone().update(cx, |tool, cx| {
             if !tool.is_draw_rect() {
                 *tool = ToolState::DrawRect(DrawRectToolState::default());
@@ -2003,6 +3349,9 @@ impl LayoutCanvas {
     }
 
     pub(crate) fn draw_dim(&mut self, _: &DrawDim, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.state.read(cx).read_only {
+            return;
+        }
         self.state.read(cx).tool.clone().update(cx, |tool, cx| {
             if !tool.is_draw_dim() {
                 *tool = ToolState::DrawDim(DrawDimToolState::default());
@@ -2011,19 +3360,57 @@ impl LayoutCanvas {
         });
     }
 
+    pub(crate) fn query_mode(
+        &mut self,
+        _: &QueryMode,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.state.read(cx).tool.clone().update(cx, |tool, cx| {
+            if !tool.is_query() {
+                *tool = ToolState::Query(QueryToolState::default());
+                cx.notify();
+            }
+        });
+    }
+
     pub(crate) fn fit_to_screen_action(
         &mut self,
         _: &Fit,
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        self.fit_to_screen(cx);
+        self.animate_camera(self.fit_camera(cx), cx);
+    }
+
+    pub(crate) fn zoom_in(&mut self, _: &ZoomIn, _window: &mut Window, cx: &mut Context<Self>) {
+        self.zoom_to(self.target_camera().scale * ZOOM_STEP, cx);
+    }
+
+    pub(crate) fn zoom_out(&mut self, _: &ZoomOut, _window: &mut Window, cx: &mut Context<Self>) {
+        self.zoom_to(self.target_camera().scale / ZOOM_STEP, cx);
+    }
+
+    pub(crate) fn zoom_reset(
+        &mut self,
+        _: &ZoomReset,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.zoom_to(1., cx);
+    }
+
+    pub(crate) fn zoom_back(&mut self, _: &ZoomBack, _window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(camera) = self.previous_camera {
+            self.animate_camera(camera, cx);
+        }
     }
 
     pub(crate) fn edit_action(&mut self, _: &Edit, window: &mut Window, cx: &mut Context<Self>) {
-        if let ToolState::Select(SelectToolState {
-            selected_obj: Some(obj),
-        }) = self.state.read(cx).tool.clone().read(cx)
+        if !self.state.read(cx).read_only
+            && let ToolState::Select(SelectToolState {
+                selected_obj: Some(obj),
+            }) = self.state.read(cx).tool.clone().read(cx)
             && let Some((_, _, value)) = self.dim_hitboxes.iter().find(|(span, _, _)| span == obj)
         {
             let obj = obj.clone();
@@ -2080,6 +3467,10 @@ impl LayoutCanvas {
     }
 
     pub(crate) fn cancel(&mut self, _: &Cancel, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.context_menu.take().is_some() {
+            cx.notify();
+            return;
+        }
         self.state.read(cx).tool.clone().update(cx, |tool, cx| {
             match tool {
                 ToolState::DrawRect(DrawRectToolState { p0: p0 @ Some(_) }) => {
@@ -2091,6 +3482,9 @@ impl LayoutCanvas {
                 ToolState::Select(SelectToolState { selected_obj }) => {
                     *selected_obj = None;
                 }
+                ToolState::Query(QueryToolState { shapes }) if !shapes.is_empty() => {
+                    shapes.clear();
+                }
                 _ => {
                     *tool = ToolState::default();
                 }
@@ -2118,12 +3512,434 @@ impl LayoutCanvas {
         });
     }
 
+    /// Moves the selected object by `steps` grid steps in each direction.
+    ///
+    /// Rapid presses accumulate into a single pending nudge, which is written
+    /// back to the source once no further presses arrive within
+    /// [`NUDGE_DEBOUNCE`].
+    fn nudge(&mut self, steps: (f64, f64), cx: &mut Context<Self>) {
+        if self.state.read(cx).read_only {
+            return;
+        }
+        let ToolState::Select(SelectToolState {
+            selected_obj: Some(span),
+        }) = self.state.read(cx).tool.read(cx)
+        else {
+            return;
+        };
+        let span = span.clone();
+        if self
+            .pending_nudge
+            .as_ref()
+            .is_some_and(|nudge| nudge.span != span)
+        {
+            self.flush_nudge(cx);
+        }
+        let grid = self.state.read(cx).grid(cx);
+        let nudge = self.pending_nudge.get_or_insert(PendingNudge {
+            span,
+            dx: 0.,
+            dy: 0.,
+        });
+        nudge.dx += steps.0 * grid;
+        nudge.dy += steps.1 * grid;
+
+        self.nudge_generation += 1;
+        let generation = self.nudge_generation;
+        cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(NUDGE_DEBOUNCE).await;
+            let _ = this.update(cx, |this, cx| {
+                if this.nudge_generation == generation {
+                    this.flush_nudge(cx);
+                }
+            });
+        })
+        .detach();
+        cx.notify();
+    }
+
+    /// Writes the pending nudge, if any, back to the source and keeps the moved
+    /// object selected under its rewritten span.
+    fn flush_nudge(&mut self, cx: &mut Context<Self>) {
+        let Some(nudge) = self.pending_nudge.take() else {
+            return;
+        };
+        match self.state.read(cx).lang_server_client.translate(
+            nudge.span.clone(),
+            nudge.dx,
+            nudge.dy,
+        ) {
+            Ok(Some(span)) => {
+                self.state.read(cx).tool.clone().update(cx, |tool, cx| {
+                    if let ToolState::Select(SelectToolState { selected_obj }) = tool
+                        && selected_obj.as_ref() == Some(&nudge.span)
+                    {
+                        *selected_obj = Some(span);
+                        cx.notify();
+                    }
+                });
+            }
+            Ok(None) => {}
+            Err(e) => {
+                self.state.update(cx, |state, cx| {
+                    state.fatal_error = Some(format!("Failed to persist nudge: {e}").into());
+                    cx.notify();
+                });
+            }
+        }
+        cx.notify();
+    }
+
+    pub(crate) fn nudge_left(
+        &mut self,
+        _: &NudgeLeft,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((-1., 0.), cx);
+    }
+
+    pub(crate) fn nudge_right(
+        &mut self,
+        _: &NudgeRight,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((1., 0.), cx);
+    }
+
+    pub(crate) fn nudge_up(&mut self, _: &NudgeUp, _window: &mut Window, cx: &mut Context<Self>) {
+        self.nudge((0., 1.), cx);
+    }
+
+    pub(crate) fn nudge_down(
+        &mut self,
+        _: &NudgeDown,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((0., -1.), cx);
+    }
+
+    pub(crate) fn nudge_left_coarse(
+        &mut self,
+        _: &NudgeLeftCoarse,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((-NUDGE_COARSE_STEPS, 0.), cx);
+    }
+
+    pub(crate) fn nudge_right_coarse(
+        &mut self,
+        _: &NudgeRightCoarse,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((NUDGE_COARSE_STEPS, 0.), cx);
+    }
+
+    pub(crate) fn nudge_up_coarse(
+        &mut self,
+        _: &NudgeUpCoarse,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((0., NUDGE_COARSE_STEPS), cx);
+    }
+
+    pub(crate) fn nudge_down_coarse(
+        &mut self,
+        _: &NudgeDownCoarse,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.nudge((0., -NUDGE_COARSE_STEPS), cx);
+    }
+
+    pub(crate) fn toggle_grid(
+        &mut self,
+        _: &ToggleGrid,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.state.update(cx, |state, cx| {
+            state.show_grid = !state.show_grid;
+            cx.notify();
+        });
+    }
+
+    /// Returns the topmost selectable object at `position`.
+    fn hit_test(&self, position: Point<Pixels>) -> Option<CanvasHit<'_>> {
+        let contains = |r: &Rect| {
+            r.id.is_some()
+                && get_rect_bounds(r, self.screen_bounds, self.scale, self.offset)
+                    .contains(&position)
+        };
+        self.rects
+            .iter()
+            .rev()
+            .sorted_by_key(|(_, layer)| usize::MAX - layer.z)
+            .filter(|(r, _)| contains(r))
+            .map(|(r, layer)| CanvasHit::Rect(r, layer))
+            .chain(
+                self.scope_rects
+                    .iter()
+                    .filter(|r| contains(r))
+                    .map(CanvasHit::Scope),
+            )
+            .chain(
+                self.dim_hitboxes
+                    .iter()
+                    .filter(|(_, hitboxes, _)| {
+                        hitboxes.iter().any(|hitbox| hitbox.contains(&position))
+                    })
+                    .map(|(span, _, _)| CanvasHit::Dim(span)),
+            )
+            .next()
+    }
+
+    /// Returns the scope path of the cell instantiated by the instance at
+    /// `object_path`, relative to the selected scope's cell.
+    fn instance_scope_path(&self, object_path: &[ObjectId], cx: &App) -> Option<ScopePath> {
+        let cell = self.state.read(cx).solved_cell.read(cx).as_ref()?;
+        let mut cell_id = cell.state[&cell.selected_scope].address.cell;
+        for obj in object_path {
+            cell_id = cell.output.cells[&cell_id].objects[obj].instance()?.cell;
+        }
+        cell.scope_paths
+            .get(&ScopeAddress {
+                cell: cell_id,
+                scope: cell.output.cells[&cell_id].root,
+            })
+            .cloned()
+    }
+
+    pub(crate) fn on_right_mouse_down(
+        &mut self,
+        event: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let target = self.hit_test(event.position).map(|hit| ContextMenuTarget {
+            span: hit.span().clone(),
+            layer: match hit {
+                CanvasHit::Rect(_, layer) => Some(layer.name.clone()),
+                _ => None,
+            },
+            instance: match hit {
+                CanvasHit::Scope(r) if !r.object_path.is_empty() => {
+                    self.instance_scope_path(&r.object_path, cx)
+                }
+                _ => None,
+            },
+            is_dim: matches!(hit, CanvasHit::Dim(_)),
+        });
+        self.context_menu = Some(ContextMenu {
+            position: event.position,
+            target,
+            read_only: self.state.read(cx).read_only,
+        });
+        cx.notify();
+    }
+
+    fn render_context_menu(&self, menu: ContextMenu, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = self.state.read(cx).theme();
+        deferred(
+            anchored().position(menu.position).child(
+                div()
+                    .occlude()
+                    .flex()
+                    .flex_col()
+                    .py_1()
+                    .min_w(px(160.))
+                    .bg(theme.sidebar)
+                    .border_1()
+                    .border_color(theme.divider)
+                    .rounded_sm()
+                    .shadow_md()
+                    .text_sm()
+                    .text_color(theme.text)
+                    .children(menu.items().into_iter().map(|item| {
+                        let target = menu.target.clone();
+                        div()
+                            .id(item.label())
+                            .px_3()
+                            .py_0p5()
+                            .hover(|style| style.bg(theme.selection))
+                            .child(item.label())
+                            .on_click(cx.listener(move |this, _, window, cx| {
+                                this.context_menu = None;
+                                this.apply_context_menu_item(item, target.clone(), window, cx);
+                                cx.notify();
+                            }))
+                    })),
+            ),
+        )
+        .with_priority(1)
+    }
+
+    fn apply_context_menu_item(
+        &mut self,
+        item: ContextMenuItem,
+        target: Option<ContextMenuTarget>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let result = match (item, target) {
+            (ContextMenuItem::Select | ContextMenuItem::EditConstraints, Some(target)) => {
+                self.state.read(cx).tool.clone().update(cx, |tool, cx| {
+                    *tool = ToolState::Select(SelectToolState {
+                        selected_obj: Some(target.span.clone()),
+                    });
+                    cx.notify();
+                });
+                let result = self
+                    .state
+                    .read(cx)
+                    .lang_server_client
+                    .select_rect(target.span);
+                if item == ContextMenuItem::EditConstraints {
+                    self.edit_action(&Edit, window, cx);
+                }
+                result
+            }
+            (ContextMenuItem::GoToSource, Some(target)) => {
+                self.state.read(cx).lang_server_client.reveal(target.span)
+            }
+            (ContextMenuItem::WhyIsThisHere, Some(target)) => {
+                let provenance = self
+                    .state
+                    .read(cx)
+                    .lang_server_client
+                    .provenance(target.span);
+                self.state.update(cx, |state, cx| {
+                    match provenance {
+                        Ok(provenance) => state.provenance = Some(provenance),
+                        Err(e) => {
+                            let _ = state
+                                .lang_server_client
+                                .show_message(MessageType::WARNING, format!("{e:#}"));
+                        }
+                    }
+                    cx.notify();
+                });
+                Ok(())
+            }
+            (
+                ContextMenuItem::HideLayer,
+                Some(ContextMenuTarget {
+                    layer: Some(layer), ..
+                }),
+            ) => {
+                self.state.read(cx).layers.clone().update(cx, |layers, cx| {
+                    if let Some(layer) = layers.layers.get_mut(&layer) {
+                        layer.visible = false;
+                    }
+                    cx.notify();
+                });
+                Ok(())
+            }
+            (
+                ContextMenuItem::DescendIntoInstance,
+                Some(ContextMenuTarget {
+                    instance: Some(scope_path),
+                    ..
+                }),
+            ) => {
+                self.state
+                    .read(cx)
+                    .solved_cell
+                    .clone()
+                    .update(cx, |cell, cx| {
+                        if let Some(cell) = cell.as_mut() {
+                            cell.selected_scope = scope_path;
+                            cx.notify();
+                        }
+                    });
+                self.state.read(cx).tool.clone().update(cx, |tool, cx| {
+                    *tool = ToolState::default();
+                    cx.notify();
+                });
+                self.animate_camera(self.fit_camera(cx), cx);
+                Ok(())
+            }
+            (ContextMenuItem::Delete, Some(target)) => {
+                self.state.read(cx).tool.clone().update(cx, |tool, cx| {
+                    if let ToolState::Select(SelectToolState { selected_obj }) = tool
+                        && selected_obj.as_ref() == Some(&target.span)
+                    {
+                        *selected_obj = None;
+                        cx.notify();
+                    }
+                });
+                self.state
+                    .read(cx)
+                    .lang_server_client
+                    .delete_object(target.span)
+            }
+            (ContextMenuItem::Paste, _) => {
+                // The command prompt is the only paste target on the canvas.
+                window.focus(&self.text_input_focus_handle);
+                self.text_input_focus_handle
+                    .dispatch_action(&Paste, window, cx);
+                Ok(())
+            }
+            (ContextMenuItem::ZoomFit, _) => {
+                self.animate_camera(self.fit_camera(cx), cx);
+                Ok(())
+            }
+            (ContextMenuItem::ToggleGrid, _) => {
+                self.toggle_grid(&ToggleGrid, window, cx);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            self.state.update(cx, |state, cx| {
+                state.fatal_error = Some(format!("Editing disabled due to error {e}").into());
+                cx.notify();
+            });
+        }
+    }
+
+    /// Screen-space lines of the layout grid with spacing `grid`, or none if
+    /// they would be too dense to be useful at the current zoom.
+    fn grid_lines(&self, grid: f64) -> Vec<Edge<Pixels>> {
+        let grid = grid as f32;
+        if grid * self.scale < MIN_GRID_SPACING {
+            return Vec::new();
+        }
+        let bounds = self.screen_bounds;
+        let p0 = self.px_to_layout(bounds.origin);
+        let p1 = self.px_to_layout(bounds.bottom_right());
+        let mut lines = Vec::new();
+        for i in (p0.x.min(p1.x) / grid).ceil() as i64..=(p0.x.max(p1.x) / grid).floor() as i64 {
+            lines.push(Edge {
+                dir: Dir::Vert,
+                coord: self.layout_to_px(Point::new(i as f32 * grid, 0.)).x,
+                start: bounds.origin.y,
+                stop: bounds.origin.y + bounds.size.height,
+            });
+        }
+        for i in (p0.y.min(p1.y) / grid).ceil() as i64..=(p0.y.max(p1.y) / grid).floor() as i64 {
+            lines.push(Edge {
+                dir: Dir::Horiz,
+                coord: self.layout_to_px(Point::new(0., i as f32 * grid)).y,
+                start: bounds.origin.x,
+                stop: bounds.origin.x + bounds.size.width,
+            });
+        }
+        lines
+    }
+
     pub(crate) fn on_middle_mouse_down(
         &mut self,
         event: &MouseDownEvent,
         _window: &mut Window,
         _cx: &mut Context<Self>,
     ) {
+        self.camera_animation = None;
         self.is_dragging = true;
         self.drag_start = event.position;
         self.offset_start = self.offset;
@@ -2192,6 +4008,16 @@ impl LayoutCanvas {
             .collect()
     }
 
+    fn on_drop_cell(&mut self, cell: &DraggedCell, window: &mut Window, cx: &mut Context<Self>) {
+        if self.state.read(cx).read_only {
+            return;
+        }
+        let pt = self.px_to_layout(window.mouse_position());
+        let grid = self.state.read(cx).grid(cx);
+        let snap = |v: f32| (v as f64 / grid).round() * grid;
+        commit_instance(&self.state, cx, &cell.name, snap(pt.x), snap(pt.y));
+    }
+
     pub(crate) fn on_left_mouse_up(
         &mut self,
         _event: &MouseUpEvent,
@@ -2227,6 +4053,7 @@ impl LayoutCanvas {
             // Do not allow zooming during a drag.
             return;
         }
+        self.camera_animation = None;
         let new_scale = {
             let delta = event.delta.pixel_delta(px(20.));
             let ns = self.scale + f32::from(delta.y) / 400.;
@@ -2264,7 +4091,7 @@ pub(crate) fn find_obj_path(
             .output
             .reachable_objs(current_scope.cell, current_scope.scope);
         if let Some(name) = reachable_objs.swap_remove(obj)
-            && let Some(inst) = cell.output.cells[&current_scope.cell].objects[obj].get_instance()
+            && let Some(inst) = cell.output.cells[&current_scope.cell].objects[obj].instance()
         {
             string_path.push(name);
             current_scope = ScopeAddress {
@@ -2289,3 +4116,199 @@ pub(crate) fn find_obj_path(
     }
     (reachable, string_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use compiler::ast::Span;
+
+    use approx::assert_relative_eq;
+    use gpui::{Point, px};
+
+    use super::{
+        Camera, ContextMenu, ContextMenuItem, ContextMenuTarget, DEFAULT_EXPLODE_OFFSET, Explode,
+        ExplodeMode, MIN_DIM_FONT_SIZE, Rect, dim_arrows_inside, dim_font_size,
+        dim_label_candidates, parse_rect_dims, place_dim_label,
+    };
+
+    #[test]
+    fn parses_typed_rect_dims() {
+        assert_eq!(parse_rect_dims("2.5x0.6"), Some((2.5, 0.6)));
+        assert_eq!(parse_rect_dims(" 3 X 4 "), Some((3., 4.)));
+        assert_eq!(parse_rect_dims("2.5"), None);
+        assert_eq!(parse_rect_dims("0x1"), None);
+        assert_eq!(parse_rect_dims("-1x1"), None);
+        assert_eq!(parse_rect_dims("axb"), None);
+    }
+
+    #[test]
+    fn dimension_annotations_scale_with_length() {
+        assert_eq!(dim_font_size(px(100.), false), Some(px(14.)));
+        assert_eq!(dim_font_size(px(40.), false), Some(px(10.)));
+        assert_eq!(dim_font_size(px(20.), false), None);
+        assert_eq!(dim_font_size(px(20.), true), Some(MIN_DIM_FONT_SIZE));
+        assert!(dim_arrows_inside(px(40.)));
+        assert!(!dim_arrows_inside(px(10.)));
+    }
+
+    #[test]
+    fn dimension_labels_avoid_overlaps() {
+        let label = gpui::size(px(30.), px(14.));
+        let candidates = dim_label_candidates(
+            Point::new(px(0.), px(100.)),
+            Point::new(px(100.), px(100.)),
+            true,
+            false,
+            label,
+        );
+        // Centered above the line first, then below it.
+        assert_eq!(candidates[0].origin, Point::new(px(35.), px(83.)));
+        assert_eq!(candidates[1].origin, Point::new(px(35.), px(103.)));
+        assert_eq!(place_dim_label(&candidates, &[]), Some(candidates[0]));
+        assert_eq!(
+            place_dim_label(&candidates, &[candidates[0]]),
+            Some(candidates[1])
+        );
+        assert_eq!(place_dim_label(&candidates, &candidates), None);
+    }
+
+    #[test]
+    fn parses_explode_args() {
+        assert_eq!(
+            Explode::parse("layer").unwrap(),
+            Some(Explode {
+                mode: ExplodeMode::Layer,
+                offset: DEFAULT_EXPLODE_OFFSET
+            })
+        );
+        assert_eq!(
+            Explode::parse(" scope 20 ").unwrap(),
+            Some(Explode {
+                mode: ExplodeMode::Scope,
+                offset: 20.
+            })
+        );
+        assert_eq!(Explode::parse("off").unwrap(), None);
+        assert!(Explode::parse("off 3").is_err());
+        assert!(Explode::parse("layer -1").is_err());
+        assert!(Explode::parse("layer 1 2").is_err());
+        assert!(Explode::parse("cell").is_err());
+    }
+
+    #[test]
+    fn explode_offsets_rects_by_step() {
+        let rect = Rect {
+            x0: 0.,
+            x1: 1.,
+            y0: 0.,
+            y1: 2.,
+            id: None,
+            object_path: Vec::new(),
+            border_widths: Default::default(),
+            border_styles: Default::default(),
+            cvars: None,
+        };
+        let layer = Explode {
+            mode: ExplodeMode::Layer,
+            offset: 10.,
+        };
+        let shifted = layer.apply(rect.clone(), 3, 1, 5.);
+        assert_relative_eq!(shifted.x0, 6.);
+        assert_relative_eq!(shifted.y1, 8.);
+        let scope = Explode {
+            mode: ExplodeMode::Scope,
+            ..layer
+        };
+        assert_eq!(scope.apply(rect.clone(), 3, 0, 5.), rect);
+    }
+
+    #[test]
+    fn context_menu_items_depend_on_target() {
+        let target = ContextMenuTarget {
+            span: Span {
+                path: PathBuf::from("lib.ar"),
+                span: cfgrammar::Span::new(0, 1),
+            },
+            layer: Some("met1".into()),
+            instance: None,
+            is_dim: false,
+        };
+        let menu = |target| ContextMenu {
+            position: Default::default(),
+            target,
+            read_only: false,
+        };
+        assert_eq!(
+            menu(None).items(),
+            [
+                ContextMenuItem::Paste,
+                ContextMenuItem::ZoomFit,
+                ContextMenuItem::ToggleGrid
+            ]
+        );
+        assert_eq!(
+            menu(Some(target.clone())).items(),
+            [
+                ContextMenuItem::Select,
+                ContextMenuItem::GoToSource,
+                ContextMenuItem::WhyIsThisHere,
+                ContextMenuItem::HideLayer,
+                ContextMenuItem::Delete
+            ]
+        );
+        assert_eq!(
+            menu(Some(ContextMenuTarget {
+                layer: None,
+                is_dim: true,
+                ..target
+            }))
+            .items(),
+            [
+                ContextMenuItem::Select,
+                ContextMenuItem::EditConstraints,
+                ContextMenuItem::GoToSource,
+                ContextMenuItem::Delete
+            ]
+        );
+    }
+
+    #[test]
+    fn read_only_context_menu_hides_edits() {
+        let menu = ContextMenu {
+            position: Default::default(),
+            target: Some(ContextMenuTarget {
+                span: Span {
+                    path: PathBuf::from("lib.ar"),
+                    span: cfgrammar::Span::new(0, 1),
+                },
+                layer: None,
+                instance: None,
+                is_dim: true,
+            }),
+            read_only: true,
+        };
+        assert_eq!(
+            menu.items(),
+            [ContextMenuItem::Select, ContextMenuItem::GoToSource]
+        );
+    }
+
+    #[test]
+    fn camera_lerp_zooms_geometrically_about_center() {
+        let center = Point::new(px(400.), px(300.));
+        let a = Camera::centered(1., Point::new(0., 0.), center);
+        let b = Camera::centered(4., Point::new(100., -50.), center);
+        for (t, camera) in [(0., a), (1., b)] {
+            let lerped = a.lerp(&b, t, center);
+            assert_relative_eq!(lerped.scale, camera.scale);
+            assert_relative_eq!(f32::from(lerped.offset.x), f32::from(camera.offset.x));
+            assert_relative_eq!(f32::from(lerped.offset.y), f32::from(camera.offset.y));
+        }
+        let mid = a.lerp(&b, 0.5, center);
+        assert_relative_eq!(mid.scale, 2.);
+        let pt = mid.layout_at(center);
+        assert_relative_eq!(pt.x, 50.);
+        assert_relative_eq!(pt.y, -25.);
+    }
+}
@@ -1,5 +1,6 @@
-use std::ops::Range;
+use std::{ops::Range, path::Path};
 
+use compiler::{ast::Span, parse::parse_expr};
 use gpui::{
     App, Bounds, ClipboardItem, Context, CursorStyle, ElementId, ElementInputHandler, Entity,
     EntityInputHandler, FocusHandle, Focusable, GlobalElementId, LayoutId, MouseButton,
@@ -13,7 +14,10 @@ use crate::{
     actions::*,
     editor::{
         EditorState,
-        canvas::{DrawDimToolState, EditDimToolState, LayoutCanvas, ToolState},
+        canvas::{
+            DrawDimToolState, DrawRectToolState, EditDimToolState, Explode, LayoutCanvas,
+            SelectToolState, SnappedPoint, ToolState, commit_rect, parse_rect_dims,
+        },
     },
 };
 
@@ -32,6 +36,8 @@ pub(crate) struct TextInput {
     pub(crate) cancel_handler: fn(&mut TextInput, &Cancel, &mut Window, &mut Context<TextInput>),
     pub(crate) state: Entity<EditorState>,
     pub(crate) px: f32,
+    /// Error shown in a popover above the input until its content changes.
+    pub(crate) error: Option<SharedString>,
 }
 
 impl TextInput {
@@ -56,6 +62,7 @@ impl TextInput {
             enter_handler: Self::command_prompt_enter,
             cancel_handler: Self::command_prompt_cancel,
             px: 8.,
+            error: None,
         }
     }
     pub(crate) fn new_filter(
@@ -80,6 +87,7 @@ impl TextInput {
             enter_handler: Self::filter_enter,
             cancel_handler: Self::filter_cancel,
             px: 4.,
+            error: None,
         }
     }
     fn left(&mut self, _: &Left, _: &mut Window, cx: &mut Context<Self>) {
@@ -243,16 +251,46 @@ impl TextInput {
 
     fn command_prompt_cancel(&mut self, _: &Cancel, window: &mut Window, cx: &mut Context<Self>) {
         window.focus(&self.canvas_focus_handle);
-        self.state.read(cx).tool.clone().update(cx, |tool, _cx| {
-            if let ToolState::EditDim(_) = tool {
-                *tool = ToolState::default();
-            }
-        });
+        self.error = None;
+        let draw_rect = self
+            .state
+            .read(cx)
+            .tool
+            .clone()
+            .update(cx, |tool, _cx| match tool {
+                ToolState::EditDim(_) => {
+                    *tool = ToolState::default();
+                    false
+                }
+                ToolState::DrawRect(DrawRectToolState { p0 }) => {
+                    *p0 = None;
+                    true
+                }
+                _ => false,
+            });
+        if draw_rect {
+            self.reset();
+        }
     }
 
     fn command_prompt_enter(&mut self, _: &Enter, window: &mut Window, cx: &mut Context<Self>) {
         let reset = self.state.read(cx).tool.clone().update(cx, |tool, cx| {
-            if let ToolState::EditDim(EditDimToolState { dim, dim_mode, .. }) = tool {
+            if let ToolState::DrawRect(DrawRectToolState { p0: p0 @ Some(_) }) = tool {
+                // Typed `<width>x<height>` finishes the rect anchored at the first
+                // corner, extending towards positive x and y.
+                let Some((w, h)) = parse_rect_dims(&self.content) else {
+                    return false;
+                };
+                let p0 = p0.take().unwrap();
+                let p1 = SnappedPoint::from(Point::new(p0.pt.x + w as f32, p0.pt.y + h as f32));
+                commit_rect(&self.state, cx, p0, p1, Some((w, h)));
+                true
+            } else if let ToolState::EditDim(EditDimToolState { dim, dim_mode, .. }) = tool {
+                // Keep invalid values out of the source and let the user fix them in place.
+                if let Err(e) = parse_expr(&self.content) {
+                    self.error = Some(format!("invalid dimension value: {e}").into());
+                    return false;
+                }
                 let error = match self
                     .state
                     .read(cx)
@@ -278,7 +316,6 @@ impl TextInput {
                 }
             } else {
                 if let Some((command, rest)) = self.content.split_once(" ") {
-                    #[allow(clippy::single_match)]
                     match command.trim_start_matches(":") {
                         "openCell" => {
                             let res = self
@@ -293,6 +330,115 @@ impl TextInput {
                             }
                             return res.is_ok();
                         }
+                        "drc" => {
+                            return self.state.update(cx, |state, cx| {
+                                let res = state.load_drc(cx, Path::new(rest.trim()));
+                                if let Err(e) = &res {
+                                    state.fatal_error = Some(format!("{e:#}").into());
+                                }
+                                res.is_ok()
+                            });
+                        }
+                        "palette" => {
+                            return self
+                                .state
+                                .update(cx, |state, cx| match rest.trim().parse() {
+                                    Ok(palette) => {
+                                        state.set_palette(cx, palette);
+                                        true
+                                    }
+                                    Err(e) => {
+                                        state.fatal_error = Some(e.into());
+                                        false
+                                    }
+                                });
+                        }
+                        "underlay" => {
+                            return self.state.update(cx, |state, cx| {
+                                let res = state.load_underlay(cx, Path::new(rest.trim()));
+                                if let Err(e) = &res {
+                                    state.fatal_error = Some(format!("{e:#}").into());
+                                }
+                                res.is_ok()
+                            });
+                        }
+                        "sweep" => {
+                            return self.state.update(cx, |state, cx| {
+                                let res =
+                                    parse_sweep_args(rest).and_then(|(name, from, to, steps)| {
+                                        state.start_sweep(cx, name, from, to, steps)
+                                    });
+                                if let Err(e) = &res {
+                                    state.fatal_error = Some(format!("{e:#}").into());
+                                }
+                                res.is_ok()
+                            });
+                        }
+                        "override" => {
+                            let Some((name, value)) = rest.trim().split_once(char::is_whitespace)
+                            else {
+                                self.state.update(cx, |state, _cx| {
+                                    state.fatal_error =
+                                        Some("usage: :override <name> <value|default>".into());
+                                });
+                                return false;
+                            };
+                            let res = self
+                                .state
+                                .read(cx)
+                                .lang_server_client
+                                .set_param_override(name.to_string(), value.trim().to_string());
+                            if let Err(e) = &res {
+                                self.state.update(cx, |state, _cx| {
+                                    state.fatal_error = Some(format!("{e}").into());
+                                });
+                            }
+                            return res.is_ok();
+                        }
+                        "explode" => {
+                            return self
+                                .state
+                                .update(cx, |state, cx| match Explode::parse(rest) {
+                                    Ok(explode) => {
+                                        state.explode = explode;
+                                        cx.notify();
+                                        true
+                                    }
+                                    Err(e) => {
+                                        state.fatal_error = Some(format!("{e}").into());
+                                        false
+                                    }
+                                });
+                        }
+                        "param" => {
+                            if self.state.read(cx).read_only {
+                                self.state.update(cx, |state, _cx| {
+                                    state.fatal_error = Some("the open cell is read-only".into());
+                                });
+                                return false;
+                            }
+                            let ToolState::Select(SelectToolState {
+                                selected_obj: Some(span),
+                            }) = tool
+                            else {
+                                self.state.update(cx, |state, _cx| {
+                                    state.fatal_error = Some("no instance selected".into());
+                                });
+                                return false;
+                            };
+                            return match set_instance_param(&self.state, cx, span, rest) {
+                                Ok(new_span) => {
+                                    *span = new_span;
+                                    true
+                                }
+                                Err(e) => {
+                                    self.state.update(cx, |state, _cx| {
+                                        state.fatal_error = Some(e);
+                                    });
+                                    false
+                                }
+                            };
+                        }
                         _ => {} // TODO: support other commands, reduce redundancy with rpc.rs
                     }
                 }
@@ -303,6 +449,7 @@ impl TextInput {
             self.reset();
             window.focus(&self.canvas_focus_handle);
         }
+        cx.notify();
     }
 
     fn offset_from_utf16(&self, offset: usize) -> usize {
@@ -366,6 +513,7 @@ impl TextInput {
         self.last_layout = None;
         self.last_bounds = None;
         self.is_selecting = false;
+        self.error = None;
     }
 }
 
@@ -426,6 +574,7 @@ impl EntityInputHandler for TextInput {
                 .into();
         self.selected_range = range.start + new_text.len()..range.start + new_text.len();
         self.marked_range.take();
+        self.error = None;
         cx.notify();
     }
 
@@ -691,6 +840,10 @@ impl Render for TextInput {
                     input.selected_range = 0..input.content.len();
                 }
             }))
+            .on_action(cx.listener(move |input, _: &RectDims, _window, cx| {
+                input.reset();
+                cx.notify();
+            }))
             .on_action(cx.listener(Self::backspace))
             .on_action(cx.listener(Self::delete))
             .on_action(cx.listener(Self::left))
@@ -712,6 +865,22 @@ impl Render for TextInput {
             .on_mouse_move(cx.listener(Self::on_mouse_move))
             .bg(theme.input_bg)
             .text_color(theme.text)
+            .relative()
+            .children(self.error.clone().map(|error| {
+                div()
+                    .id("input_error_popover")
+                    .absolute()
+                    .bottom(relative(1.))
+                    .left(px(self.px))
+                    .mb_1()
+                    .p_2()
+                    .bg(theme.bg)
+                    .border_1()
+                    .border_color(theme.error)
+                    .rounded_sm()
+                    .text_color(theme.error)
+                    .child(error)
+            }))
             .child(
                 div()
                     .w_full()
@@ -730,3 +899,57 @@ impl Focusable for TextInput {
         self.focus_handle.clone()
     }
 }
+
+/// Parses the arguments of `:sweep <name> <from> <to> <steps>`.
+fn parse_sweep_args(args: &str) -> anyhow::Result<(&str, f64, f64, usize)> {
+    let usage = || anyhow::anyhow!("usage: :sweep <name> <from> <to> <steps>");
+    let [name, from, to, steps] = args
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| usage())?;
+    let (Ok(from), Ok(to), Ok(steps)) = (from.parse(), to.parse(), steps.parse()) else {
+        return Err(usage());
+    };
+    Ok((name, from, to, steps))
+}
+
+/// Handles `:param <name> <value>`, which sets a parameter of the instance at
+/// `span` in the source and recompiles.
+///
+/// Returns the span of the instance after the edit.
+fn set_instance_param(
+    state: &Entity<EditorState>,
+    cx: &mut App,
+    span: &Span,
+    args: &str,
+) -> Result<Span, SharedString> {
+    let Some((name, value)) = args.trim().split_once(char::is_whitespace) else {
+        return Err("usage: :param <name> <value>".into());
+    };
+    let state = state.read(cx);
+    let index = state
+        .solved_cell
+        .read(cx)
+        .as_ref()
+        .and_then(|cell| {
+            cell.output
+                .cells
+                .values()
+                .flat_map(|cell| cell.objects.values())
+                .filter_map(|obj| obj.instance())
+                .find(|inst| inst.span == *span)
+        })
+        .ok_or("no instance selected")?
+        .params
+        .get_index_of(name)
+        .ok_or_else(|| format!("instance has no parameter `{name}`"))?;
+    match state
+        .lang_server_client
+        .set_instance_param(span.clone(), index, value.trim().to_string())
+    {
+        Ok(Some(span)) => Ok(span),
+        Ok(None) => Err("instance parameters can only be edited on direct cell calls".into()),
+        Err(e) => Err(format!("{e}").into()),
+    }
+}
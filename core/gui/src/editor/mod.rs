@@ -1,27 +1,41 @@
 use std::{
-    hash::{DefaultHasher, Hash, Hasher},
+    collections::HashMap,
     net::SocketAddr,
+    path::{Path, PathBuf},
 };
 
-use canvas::{LayoutCanvas, ShapeFill};
-use compiler::compile::{
-    CellId, CompileOutput, CompiledData, ExecErrorCompileOutput, ExecErrorKind, Rect, ScopeId,
-    SolvedValue, bbox_dim_union, bbox_text_union, bbox_union, ifmatvec,
+use canvas::{Explode, LayoutCanvas, ShapeFill};
+use compiler::{
+    compile::{
+        CellId, CompileOutput, CompiledData, ExecError, ExecErrorCompileOutput, ExecErrorKind,
+        Rect, ScopeId, StableId,
+    },
+    dbu::DEFAULT_DBU,
+    graph::Provenance,
 };
 use futures::StreamExt;
-use geometry::transform::TransformationMatrix;
 use gpui::*;
 use indexmap::{IndexMap, IndexSet};
 use lang_server::rpc::LangServerAction;
 use rgb::Rgb;
-use toolbars::{HierarchySideBar, LayerSideBar, TitleBar, ToolBar};
+use toolbars::{
+    CellLibrary, DrcSideBar, ErrorSideBar, HierarchySideBar, LayerSideBar, ProvenanceSideBar,
+    SweepBar, TitleBar, ToolBar,
+};
 use tower_lsp_server::ls_types::MessageType;
 
 use crate::{
     actions::{Redo, Undo},
-    editor::{canvas::ToolState, input::TextInput},
+    drc::DrcState,
+    editor::{
+        canvas::{QueryToolState, SelectToolState, ToolState},
+        input::TextInput,
+    },
+    query::QueryReport,
     rpc::SyncLangServerClient,
-    theme::{DARK_THEME, LIGHT_THEME, Theme},
+    sweep::{SWEEP_FRAME_INTERVAL, Sweep},
+    theme::{DARK_THEME, LIGHT_THEME, Palette, Theme},
+    underlay::Underlay,
 };
 
 pub mod canvas;
@@ -46,6 +60,13 @@ pub struct ScopeState {
     pub visible: bool,
     pub bbox: Option<Rect<f64>>,
     pub parent: Option<ScopeAddress>,
+    /// Stable IDs of the scopes along [`ScopePath`], used to carry state
+    /// across recompiles.
+    pub stable_path: Vec<StableId>,
+    /// Whether the states of the scope's instances and child scopes have been
+    /// built. The descendants of hidden scopes are only built once the scope
+    /// is shown, expanded, or selected.
+    pub loaded: bool,
 }
 
 pub type ScopePath = Vec<String>;
@@ -62,6 +83,148 @@ pub struct CompileOutputState {
     pub selected_scope: ScopePath,
     pub state: IndexMap<ScopePath, ScopeState>,
     pub scope_paths: IndexMap<ScopeAddress, ScopePath>,
+    /// Visibility of scopes in the previous compile output, keyed by stable
+    /// path, applied to scopes as they are loaded.
+    old_visibility: HashMap<Vec<StableId>, bool>,
+}
+
+impl CompileOutputState {
+    fn loader(&mut self) -> ScopeLoader<'_> {
+        ScopeLoader {
+            output: &self.output,
+            state: &mut self.state,
+            scope_paths: &mut self.scope_paths,
+            old_visibility: &self.old_visibility,
+        }
+    }
+
+    /// Builds the states of the descendants of the scope at `path`, if they
+    /// have not been built yet.
+    pub fn load_scope(&mut self, path: &ScopePath) {
+        if self.state.get(path).is_some_and(|scope| !scope.loaded) {
+            self.loader().load(path);
+        }
+    }
+
+    /// Loads every visible scope along with its visible descendants.
+    pub fn load_visible(&mut self) {
+        loop {
+            let pending = self
+                .state
+                .iter()
+                .filter(|(_, scope)| scope.visible && !scope.loaded)
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                break;
+            }
+            for path in pending {
+                self.load_scope(&path);
+            }
+        }
+    }
+
+    /// Loads the ancestors of the scope with the given stable path.
+    fn load_stable_path(&mut self, stable_path: &[StableId]) {
+        while let Some(path) = self
+            .state
+            .iter()
+            .find(|(_, scope)| !scope.loaded && stable_path.starts_with(&scope.stable_path))
+            .map(|(path, _)| path.clone())
+        {
+            self.load_scope(&path);
+        }
+    }
+
+    /// Makes every scope visible, loading any that were hidden.
+    pub fn show_all(&mut self) {
+        for scope in self.state.values_mut() {
+            scope.visible = true;
+        }
+        self.old_visibility.clear();
+        self.load_visible();
+    }
+}
+
+/// Builds [`ScopeState`]s for a compile output.
+struct ScopeLoader<'a> {
+    output: &'a CompiledData,
+    state: &'a mut IndexMap<ScopePath, ScopeState>,
+    scope_paths: &'a mut IndexMap<ScopeAddress, ScopePath>,
+    old_visibility: &'a HashMap<Vec<StableId>, bool>,
+}
+
+impl ScopeLoader<'_> {
+    /// Builds the state of `scope` and, if it is visible, of its descendants.
+    fn process_scope(&mut self, scope: ScopeAddress, parent: Option<&ScopePath>) {
+        let scope_info = &self.output.cells[&scope.cell].scopes[&scope.scope];
+        let (mut scope_path, mut stable_path) = if let Some(parent) = parent {
+            (parent.clone(), self.state[parent].stable_path.clone())
+        } else {
+            (vec![], vec![])
+        };
+        scope_path.push(scope_info.name.clone());
+        stable_path.push(scope_info.stable_id);
+        self.scope_paths.insert(scope, scope_path.clone());
+        // Instances of the same cell in a scope share a path, so each subtree
+        // only needs to be built once.
+        if self
+            .state
+            .get(&scope_path)
+            .is_some_and(|state| state.address == scope)
+        {
+            return;
+        }
+
+        let visible = self
+            .old_visibility
+            .get(&stable_path)
+            .copied()
+            .unwrap_or(true);
+        let parent = parent.map(|parent| self.state[parent].address);
+        self.state.insert(
+            scope_path.clone(),
+            ScopeState {
+                name: scope_info.name.clone(),
+                address: scope,
+                visible,
+                bbox: scope_info.bbox.clone(),
+                parent,
+                stable_path,
+                loaded: false,
+            },
+        );
+        if visible {
+            self.load(&scope_path);
+        }
+    }
+
+    /// Builds the states of the instances and child scopes of the scope at
+    /// `path`.
+    fn load(&mut self, path: &ScopePath) {
+        let state = self.state.get_mut(path).expect("scope not found");
+        state.loaded = true;
+        let scope = state.address;
+        let output = self.output;
+        let cell = &output.cells[&scope.cell];
+        let scope_info = &cell.scopes[&scope.scope];
+        for (obj, _) in &scope_info.emit {
+            if let Some(inst) = cell.objects[obj].instance() {
+                let inst_address = ScopeAddress {
+                    scope: output.cells[&inst.cell].root,
+                    cell: inst.cell,
+                };
+                self.process_scope(inst_address, Some(path));
+            }
+        }
+        for child in &scope_info.children {
+            let scope_address = ScopeAddress {
+                scope: *child,
+                cell: scope.cell,
+            };
+            self.process_scope(scope_address, Some(path));
+        }
+    }
 }
 
 pub struct Layers {
@@ -72,10 +235,40 @@ pub struct Layers {
 pub struct EditorState {
     pub hierarchy_depth: usize,
     pub dark_mode: bool,
+    /// Palette for layers without colors in the layer properties file.
+    pub palette: Palette,
     pub fatal_error: Option<SharedString>,
+    /// GUI edit held back by the language server because it conflicts with
+    /// changes made in the editor, if any.
+    pub edit_conflict: Option<SharedString>,
+    /// Execution errors of the displayed compile output.
+    pub errors: Vec<ExecError>,
+    /// Number of source lines commented out by relaxing constraints from the
+    /// error console.
+    pub relaxed_constraints: usize,
+    /// Provenance of the coordinates of the rect picked with "Why Is This
+    /// Here?", shown in a sidebar until it is closed or the cell recompiles.
+    pub provenance: Option<Vec<Provenance>>,
     pub solved_cell: Entity<Option<CompileOutputState>>,
     pub hide_external_geometry: bool,
+    /// Whether the open cell is defined in a read-only dependency or the
+    /// standard library, in which case editing tools are disabled.
+    pub read_only: bool,
+    /// Exploded view the canvas is drawn in, if any.
+    pub explode: Option<Explode>,
+    /// Names of the parameters of the open cell that are overridden for the
+    /// session.
+    pub param_overrides: Vec<String>,
+    pub show_grid: bool,
+    /// Whether construction geometry, which is never exported, is drawn.
+    pub show_construction: bool,
     pub layers: Entity<Layers>,
+    /// Imported sign-off DRC results, if any.
+    pub drc: Entity<Option<DrcState>>,
+    /// Reference layout drawn beneath the open cell, if any.
+    pub underlay: Entity<Option<Underlay>>,
+    /// Parameter sweep being stepped through, if any.
+    pub sweep: Entity<Option<Sweep>>,
     pub lang_server_client: SyncLangServerClient,
     pub subscriptions: Vec<Subscription>,
     pub(crate) tool: Entity<ToolState>,
@@ -88,6 +281,11 @@ pub struct Editor {
     pub tool_bar: Entity<ToolBar>,
     pub hierarchy_sidebar: Entity<HierarchySideBar>,
     pub layer_sidebar: Entity<LayerSideBar>,
+    pub cell_library: Entity<CellLibrary>,
+    pub drc_sidebar: Entity<DrcSideBar>,
+    pub error_sidebar: Entity<ErrorSideBar>,
+    pub provenance_sidebar: Entity<ProvenanceSideBar>,
+    pub sweep_bar: Entity<SweepBar>,
     pub canvas: Entity<LayoutCanvas>,
     pub(crate) text_input: Entity<TextInput>,
 }
@@ -96,13 +294,6 @@ fn rgb_to_rgba(color: Rgb<u8>) -> Rgba {
     rgb(((color.r as u32) << 16) | ((color.g as u32) << 8) | color.b as u32)
 }
 
-#[derive(Default)]
-struct ProcessScopeState {
-    layers: IndexMap<SharedString, LayerState>,
-    state: IndexMap<ScopePath, ScopeState>,
-    scope_paths: IndexMap<ScopeAddress, ScopePath>,
-}
-
 impl EditorState {
     fn theme(&self) -> &'static Theme {
         if self.dark_mode {
@@ -111,124 +302,245 @@ impl EditorState {
             &LIGHT_THEME
         }
     }
-    fn process_scope(
+    /// Adds the layers drawn on by `output` that are missing from the layer
+    /// properties file, and marks all drawn layers as used.
+    fn add_used_layers(
         &self,
-        cx: &App,
-        solved_cell: &CompiledData,
-        scope: ScopeAddress,
-        state: &mut ProcessScopeState,
-        parent: Option<ScopeAddress>,
+        output: &CompiledData,
+        layers: &mut IndexMap<SharedString, LayerState>,
     ) {
-        let scope_info = &solved_cell.cells[&scope.cell].scopes[&scope.scope];
-        let mut scope_path = if let Some(parent) = &parent {
-            state.scope_paths[parent].clone()
-        } else {
-            vec![]
-        };
-        scope_path.push(scope_info.name.clone());
-        state.scope_paths.insert(scope, scope_path.clone());
-        let mut bbox = None;
-        for (obj, _) in &scope_info.emit {
-            let value = &solved_cell.cells[&scope.cell].objects[obj];
-            match value {
-                SolvedValue::Rect(rect) => {
-                    bbox = bbox_union(bbox, Some(rect.to_float()));
-                    if let Some(layer) = &rect.layer {
-                        let layer = SharedString::from(layer);
-                        if let Some(layer_info) = state.layers.get_mut(&layer) {
-                            layer_info.used = true;
-                        } else {
-                            let mut s = DefaultHasher::new();
-                            layer.hash(&mut s);
-                            let hash = s.finish() as usize;
-                            let color =
-                                rgb([0xff0000, 0x0ff000, 0x00ff00, 0x000ff0, 0x0000ff][hash % 5]);
-                            state.layers.insert(
-                                layer.clone(),
-                                LayerState {
-                                    name: layer,
-                                    color,
-                                    fill: ShapeFill::Stippling,
-                                    border_color: color,
-                                    visible: true,
-                                    used: true,
-                                    z: state.layers.len(),
-                                },
-                            );
-                        }
-                    }
-                }
-                SolvedValue::Instance(inst) => {
-                    let inst_address = ScopeAddress {
-                        scope: solved_cell.cells[&inst.cell].root,
-                        cell: inst.cell,
+        for cell in output.cells.values() {
+            for scope in cell.scopes.values() {
+                for (obj, _) in &scope.emit {
+                    let Some(layer) = cell.objects[obj]
+                        .get_rect()
+                        .and_then(|rect| rect.layer.as_ref())
+                    else {
+                        continue;
                     };
-                    self.process_scope(cx, solved_cell, inst_address, state, Some(scope));
-                    bbox = bbox_union(
-                        bbox,
-                        state.state[&state.scope_paths[&inst_address]]
-                            .bbox
-                            .as_ref()
-                            .map(|rect| {
-                                let mut inst_mat = TransformationMatrix::identity();
-                                if inst.reflect {
-                                    inst_mat = inst_mat.reflect_vert()
-                                }
-                                inst_mat = inst_mat.rotate(inst.angle);
-                                let p0p = ifmatvec(inst_mat, (rect.x0, rect.y0));
-                                let p1p = ifmatvec(inst_mat, (rect.x1, rect.y1));
-                                Rect {
-                                    layer: None,
-                                    x0: p0p.0.min(p1p.0) + inst.x,
-                                    y0: p0p.1.min(p1p.1) + inst.y,
-                                    x1: p0p.0.max(p1p.0) + inst.x,
-                                    y1: p0p.1.max(p1p.1) + inst.y,
-                                    id: inst.id,
-                                    construction: true,
-                                    span: rect.span.clone(),
-                                }
-                            }),
-                    );
-                }
-                SolvedValue::Dimension(dim) => {
-                    bbox = bbox_dim_union(bbox, dim);
-                }
-                SolvedValue::Text(t) => {
-                    bbox = bbox_text_union(bbox, t);
+                    let layer = SharedString::from(layer);
+                    if let Some(layer_info) = layers.get_mut(&layer) {
+                        layer_info.used = true;
+                    } else {
+                        let color = self.palette.layer_color(&layer);
+                        let z = layers.len();
+                        layers.insert(
+                            layer.clone(),
+                            LayerState {
+                                name: layer,
+                                color,
+                                fill: ShapeFill::Stippling,
+                                border_color: color,
+                                visible: true,
+                                used: true,
+                                z,
+                            },
+                        );
+                    }
                 }
             }
         }
+    }
+    /// Manufacturing grid of the open cell, which keyboard nudges move by and
+    /// dropped cells snap to.
+    pub fn grid(&self, cx: &App) -> f64 {
+        self.solved_cell
+            .read(cx)
+            .as_ref()
+            .map_or(DEFAULT_DBU, |cell| cell.output.grid)
+    }
 
-        for child in &scope_info.children {
-            let scope_address = ScopeAddress {
-                scope: *child,
-                cell: scope.cell,
-            };
-            self.process_scope(cx, solved_cell, scope_address, state, Some(scope));
-            bbox = bbox_union(
-                bbox,
-                state.state[&state.scope_paths[&scope_address]].bbox.clone(),
-            );
-        }
+    /// Switches the fallback layer palette, recoloring layers that are not
+    /// defined in the layer properties file.
+    pub fn set_palette(&mut self, cx: &mut App, palette: Palette) {
+        self.palette = palette;
+        let lyp_layers = self
+            .solved_cell
+            .read(cx)
+            .as_ref()
+            .map(|cell| {
+                cell.output
+                    .layers
+                    .layers
+                    .iter()
+                    .map(|layer| layer.name.clone())
+                    .collect::<IndexSet<_>>()
+            })
+            .unwrap_or_default();
+        self.layers.update(cx, |layers, cx| {
+            for layer in layers.layers.values_mut() {
+                if !lyp_layers.contains(layer.name.as_ref()) {
+                    layer.color = palette.layer_color(&layer.name);
+                    layer.border_color = layer.color;
+                }
+            }
+            cx.notify();
+        });
+    }
 
-        let visible = self
+    /// Loads a KLayout report database and maps its markers onto the open cell.
+    pub fn load_drc(&mut self, cx: &mut App, path: &Path) -> anyhow::Result<()> {
+        let drc = DrcState::load(path)?;
+        self.drc.update(cx, |old_drc, cx| {
+            *old_drc = Some(drc);
+            cx.notify();
+        });
+        self.remap_drc(cx);
+        Ok(())
+    }
+
+    /// Imports a GDS library as a read-only underlay beneath the open cell.
+    pub fn load_underlay(&mut self, cx: &mut App, path: &Path) -> anyhow::Result<()> {
+        let shapes = self.lang_server_client.load_underlay(path.to_path_buf())?;
+        self.underlay.update(cx, |underlay, cx| {
+            *underlay = Some(Underlay::new(path.to_path_buf(), shapes));
+            cx.notify();
+        });
+        Ok(())
+    }
+
+    /// Compiles the open cell for `steps` values of its parameter `name` from
+    /// `from` to `to` in the background, then displays the first step.
+    pub fn start_sweep(
+        &mut self,
+        cx: &mut Context<Self>,
+        name: &str,
+        from: f64,
+        to: f64,
+        steps: usize,
+    ) -> anyhow::Result<()> {
+        let base = if let Some(sweep) = self.sweep.read(cx) {
+            sweep.base.clone()
+        } else if let Some(cell) = self.solved_cell.read(cx) {
+            if self.errors.is_empty() {
+                CompileOutput::Valid(cell.output.clone())
+            } else {
+                CompileOutput::ExecErrors(ExecErrorCompileOutput {
+                    errors: self.errors.clone(),
+                    output: Some(cell.output.clone()),
+                })
+            }
+        } else {
+            anyhow::bail!("no cell is open");
+        };
+        let param = self
             .solved_cell
             .read(cx)
             .as_ref()
-            .and_then(|cell| Some(cell.state.get(&scope_path)?.visible))
-            .unwrap_or(true);
-        state.state.insert(
-            scope_path,
-            ScopeState {
-                name: scope_info.name.clone(),
-                address: scope,
-                visible,
-                bbox,
-                parent,
-            },
-        );
+            .and_then(|cell| {
+                cell.output.cells[&cell.output.top]
+                    .params
+                    .get_index_of(name)
+            })
+            .ok_or_else(|| anyhow::anyhow!("open cell has no parameter `{name}`"))?;
+        let name = name.to_string();
+        let frames = self.lang_server_client.sweep_cell(param, from, to, steps);
+        cx.spawn(async move |this, cx| {
+            let frames = frames.await;
+            let _ = this.update(cx, |state, cx| {
+                match frames {
+                    Ok(frames) if frames.is_empty() => {
+                        state.fatal_error = Some("sweep has no steps".into());
+                    }
+                    Ok(frames) => {
+                        state.sweep.update(cx, |sweep, cx| {
+                            *sweep = Some(Sweep::new(name, param, frames, base));
+                            cx.notify();
+                        });
+                        state.show_sweep_frame(cx, 0);
+                    }
+                    Err(e) => state.fatal_error = Some(format!("{e:#}").into()),
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+        Ok(())
+    }
+
+    /// Displays the `index`th frame of the open sweep.
+    pub fn show_sweep_frame(&mut self, cx: &mut App, index: usize) {
+        let Some(output) = self.sweep.update(cx, |sweep, cx| {
+            let sweep = sweep.as_mut()?;
+            let output = sweep.frames.get(index)?.output.clone();
+            sweep.index = index;
+            cx.notify();
+            Some(output)
+        }) else {
+            return;
+        };
+        self.update(cx, output);
+    }
+
+    /// Starts or stops stepping through the frames of the open sweep.
+    pub fn toggle_sweep_playback(&mut self, cx: &mut Context<Self>) {
+        let Some(playback) = self.sweep.update(cx, |sweep, cx| {
+            let sweep = sweep.as_mut()?;
+            sweep.playing = !sweep.playing;
+            sweep.playback += 1;
+            cx.notify();
+            sweep.playing.then_some(sweep.playback)
+        }) else {
+            return;
+        };
+        cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(SWEEP_FRAME_INTERVAL).await;
+                let playing = this.update(cx, |state, cx| {
+                    let Some(next) = state
+                        .sweep
+                        .read(cx)
+                        .as_ref()
+                        .filter(|sweep| sweep.playing && sweep.playback == playback)
+                        .map(|sweep| sweep.next_index())
+                    else {
+                        return false;
+                    };
+                    state.show_sweep_frame(cx, next);
+                    true
+                });
+                if !matches!(playing, Ok(true)) {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Closes the open sweep and restores the output displayed before it.
+    pub fn close_sweep(&mut self, cx: &mut App) {
+        let Some(sweep) = self.sweep.update(cx, |sweep, cx| {
+            cx.notify();
+            sweep.take()
+        }) else {
+            return;
+        };
+        self.update(cx, sweep.base);
+    }
+
+    fn remap_drc(&self, cx: &mut App) {
+        let solved_cell = self.solved_cell.clone();
+        self.drc.update(cx, |drc, cx| {
+            if let Some(drc) = drc
+                && let Some(cell) = solved_cell.read(cx)
+            {
+                drc.remap(&cell.output);
+                cx.notify();
+            }
+        });
     }
-    pub fn update(&mut self, cx: &mut App, output: CompileOutput) {
+
+    /// Replaces the displayed compile output.
+    ///
+    /// Returns the new paths of scopes that existed in the previous output,
+    /// keyed by their old paths.
+    pub fn update(
+        &mut self,
+        cx: &mut App,
+        output: CompileOutput,
+    ) -> IndexMap<ScopePath, ScopePath> {
+        self.errors.clear();
+        self.provenance = None;
         let solved_cell = match output {
             CompileOutput::Valid(d) => d,
             CompileOutput::ExecErrors(ExecErrorCompileOutput {
@@ -243,13 +555,14 @@ impl EditorState {
                         .lang_server_client
                         .show_message(MessageType::ERROR, "Open cell is invalid");
                     self.fatal_error = Some(SharedString::from("open cell is invalid"));
-                    return;
+                    return IndexMap::new();
                 }
+                self.errors = errors;
                 d
             }
             _ => {
                 self.fatal_error = Some(SharedString::from("static compile errors encountered"));
-                return;
+                return IndexMap::new();
             }
         };
         let root_scope = ScopeAddress {
@@ -259,7 +572,19 @@ impl EditorState {
         let root_scope_name = &solved_cell.cells[&root_scope.cell].scopes[&root_scope.scope]
             .name
             .clone();
-        let mut state = ProcessScopeState::default();
+        let old_cell = self.solved_cell.read(cx).as_ref();
+        let old_visibility = old_cell
+            .map(|cell| {
+                let mut visibility = cell.old_visibility.clone();
+                visibility.extend(
+                    cell.state
+                        .values()
+                        .map(|scope| (scope.stable_path.clone(), scope.visible)),
+                );
+                visibility
+            })
+            .unwrap_or_default();
+        let mut layers = IndexMap::new();
         let old_layers = self.layers.read(cx);
         for layer in &solved_cell.layers.layers {
             let name = SharedString::from(layer.name.clone());
@@ -268,7 +593,8 @@ impl EditorState {
                 .get(&name)
                 .map(|layer| layer.visible)
                 .unwrap_or(true);
-            state.layers.insert(
+            let z = layers.len();
+            layers.insert(
                 name.clone(),
                 LayerState {
                     name,
@@ -277,16 +603,64 @@ impl EditorState {
                     border_color: rgb_to_rgba(layer.border_color),
                     visible,
                     used: false,
-                    z: state.layers.len(),
+                    z,
                 },
             );
         }
-        self.process_scope(cx, &solved_cell, root_scope, &mut state, None);
-        let ProcessScopeState {
-            layers,
-            state,
-            scope_paths,
-        } = state;
+        self.add_used_layers(&solved_cell, &mut layers);
+        let mut new_cell = CompileOutputState {
+            output: solved_cell,
+            selected_scope: vec![root_scope_name.clone()],
+            state: IndexMap::new(),
+            scope_paths: IndexMap::new(),
+            old_visibility,
+        };
+        new_cell.loader().process_scope(root_scope, None);
+        // Keep the selected scope across recompiles even if it is hidden.
+        if let Some(old_cell) = old_cell
+            && let Some(selected) = old_cell.state.get(&old_cell.selected_scope)
+        {
+            new_cell.load_stable_path(&selected.stable_path);
+        }
+        let new_paths: HashMap<_, _> = new_cell
+            .state
+            .iter()
+            .map(|(path, scope)| (&scope.stable_path, path))
+            .collect();
+        let renamed: IndexMap<ScopePath, ScopePath> = old_cell
+            .map(|cell| {
+                cell.state
+                    .iter()
+                    .filter_map(|(path, scope)| {
+                        Some((path.clone(), (*new_paths.get(&scope.stable_path)?).clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(selected_scope) =
+            old_cell.and_then(|cell| renamed.get(&cell.selected_scope).cloned())
+        {
+            new_cell.selected_scope = selected_scope;
+        }
+        let selected_span =
+            if let (Some(old_cell), ToolState::Select(select)) = (old_cell, self.tool.read(cx)) {
+                select
+                    .selected_obj
+                    .as_ref()
+                    .and_then(|span| old_cell.output.stable_id(span))
+                    .and_then(|id| new_cell.output.span_of(id))
+                    .cloned()
+            } else {
+                None
+            };
+        if let Some(span) = selected_span {
+            self.tool.update(cx, |tool, cx| {
+                *tool = ToolState::Select(SelectToolState {
+                    selected_obj: Some(span),
+                });
+                cx.notify();
+            });
+        }
         self.layers.update(cx, |old_layers, cx| {
             old_layers.layers = layers;
             if old_layers
@@ -300,27 +674,22 @@ impl EditorState {
             cx.notify();
         });
         self.solved_cell.update(cx, |old_cell, cx| {
-            *old_cell = Some(CompileOutputState {
-                output: solved_cell,
-                selected_scope: old_cell
-                    .as_ref()
-                    .and_then(|cell| {
-                        state
-                            .contains_key(&cell.selected_scope)
-                            .then(|| cell.selected_scope.clone())
-                    })
-                    .unwrap_or_else(|| vec![root_scope_name.clone()]),
-                state,
-                scope_paths,
-            });
+            *old_cell = Some(new_cell);
             cx.notify();
         });
+        self.remap_drc(cx);
         self.fatal_error = None;
+        renamed
     }
 }
 
 impl Editor {
-    pub fn new(cx: &mut Context<Self>, window: &mut Window, lang_server_addr: SocketAddr) -> Self {
+    pub fn new(
+        cx: &mut Context<Self>,
+        window: &mut Window,
+        lang_server_addr: SocketAddr,
+        drc_path: Option<PathBuf>,
+    ) -> Self {
         let (lang_server_client, mut rx) =
             SyncLangServerClient::new(cx.to_async(), lang_server_addr);
         let solved_cell = cx.new(|_cx| None);
@@ -329,19 +698,38 @@ impl Editor {
             layers: IndexMap::new(),
             selected_layer: None,
         });
+        let drc = cx.new(|_cx| None);
+        let underlay = cx.new(|_cx| None);
+        let sweep = cx.new(|_cx| None);
         let state = cx.new(|cx| {
             let subscriptions = vec![
                 cx.observe(&solved_cell, |_, _, cx| cx.notify()),
                 cx.observe(&layers, |_, _, cx| cx.notify()),
+                cx.observe(&drc, |_, _, cx| cx.notify()),
+                cx.observe(&underlay, |_, _, cx| cx.notify()),
+                cx.observe(&sweep, |_, _, cx| cx.notify()),
             ];
             EditorState {
                 hierarchy_depth: usize::MAX,
                 dark_mode: true,
+                palette: Palette::default(),
                 fatal_error: None,
+                edit_conflict: None,
+                errors: Vec::new(),
+                relaxed_constraints: 0,
+                provenance: None,
                 solved_cell,
                 hide_external_geometry: false,
+                read_only: false,
+                explode: None,
+                param_overrides: Vec::new(),
+                show_grid: false,
+                show_construction: true,
                 tool,
                 layers,
+                drc,
+                underlay,
+                sweep,
                 subscriptions,
                 lang_server_client: lang_server_client.clone(),
             }
@@ -363,6 +751,18 @@ impl Editor {
             .new(|cx| TextInput::new_command_prompt(cx, text_input_focus_handle, &state, &canvas));
         let hierarchy_sidebar = cx.new(|cx| HierarchySideBar::new(cx, &state, &canvas));
         let layer_sidebar = cx.new(|cx| LayerSideBar::new(cx, &state, &canvas));
+        let cell_library = cx.new(|cx| CellLibrary::new(cx, &state, &canvas));
+        let drc_sidebar = cx.new(|cx| DrcSideBar::new(cx, &state, &canvas));
+        let error_sidebar = cx.new(|cx| ErrorSideBar::new(cx, &state));
+        let provenance_sidebar = cx.new(|cx| ProvenanceSideBar::new(cx, &state));
+        let sweep_bar = cx.new(|cx| SweepBar::new(cx, &state));
+        if let Some(path) = drc_path {
+            state.update(cx, |state, cx| {
+                if let Err(e) = state.load_drc(cx, &path) {
+                    state.fatal_error = Some(format!("{e:#}").into());
+                }
+            });
+        }
 
         let editor = Self {
             state,
@@ -370,6 +770,11 @@ impl Editor {
             tool_bar,
             hierarchy_sidebar,
             layer_sidebar,
+            cell_library,
+            drc_sidebar,
+            error_sidebar,
+            provenance_sidebar,
+            sweep_bar,
             canvas,
             text_input,
         };
@@ -388,25 +793,37 @@ impl Editor {
         editor
     }
 
-    pub fn open_cell(&self, cx: &mut App, output: CompileOutput, update: bool) {
-        self.state.update(cx, |state, cx| {
-            state.update(cx, output);
+    pub fn open_cell(&self, cx: &mut App, output: CompileOutput, update: bool, read_only: bool) {
+        let renamed = self.state.update(cx, |state, cx| {
+            state.read_only = read_only;
+            if read_only {
+                // Only selection, measurement, and navigation remain available.
+                state.tool.update(cx, |tool, cx| {
+                    if !matches!(tool, ToolState::Select(_) | ToolState::Query(_)) {
+                        *tool = ToolState::default();
+                        cx.notify();
+                    }
+                });
+            }
+            // The frames of an open sweep were compiled from the previous sources.
+            state.sweep.update(cx, |sweep, cx| {
+                *sweep = None;
+                cx.notify();
+            });
+            let renamed = state.update(cx, output);
             cx.notify();
+            renamed
         });
+        self.cell_library
+            .update(cx, |library, cx| library.refresh(cx));
         if update {
-            let state = self.state.clone();
             self.hierarchy_sidebar.update(cx, move |sidebar, cx| {
-                let scope_paths: IndexSet<_> = state
-                    .read(cx)
-                    .solved_cell
-                    .read(cx)
-                    .as_ref()
-                    .map(|cell| cell.state.keys().cloned().collect())
-                    .unwrap_or_default();
                 sidebar.state.update(cx, |state, _cx| {
-                    state
+                    state.expanded_scopes = state
                         .expanded_scopes
-                        .retain(|path| scope_paths.contains(path));
+                        .iter()
+                        .filter_map(|path| renamed.get(path).cloned())
+                        .collect::<IndexSet<_>>();
                 });
                 cx.notify();
             });
@@ -469,6 +886,13 @@ impl Editor {
 impl Render for Editor {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = self.theme(cx);
+        let query = match self.state.read(cx).tool.read(cx) {
+            ToolState::Query(QueryToolState { shapes }) => Some((
+                shapes.clone(),
+                QueryReport::new(&shapes.iter().map(|shape| shape.bbox).collect::<Vec<_>>()),
+            )),
+            _ => None,
+        };
         div()
             .id("top")
             .track_focus(&self.canvas.focus_handle(cx))
@@ -535,10 +959,151 @@ impl Render for Editor {
                             );
                         }
 
+                        if let Some(action) = &self.state.read(cx).edit_conflict {
+                            let button = |id: &'static str, label: &'static str, reapply: bool| {
+                                div()
+                                    .id(id)
+                                    .px_1()
+                                    .border_1()
+                                    .border_color(theme.divider)
+                                    .rounded_sm()
+                                    .child(label)
+                                    .on_click({
+                                        let state = self.state.clone();
+                                        move |_event, _window, cx| {
+                                            state.update(cx, |state, cx| {
+                                                state.edit_conflict = None;
+                                                if let Err(e) = state
+                                                    .lang_server_client
+                                                    .resolve_edit_conflict(reapply)
+                                                {
+                                                    state.fatal_error = Some(format!("{e}").into());
+                                                }
+                                                cx.notify();
+                                            })
+                                        }
+                                    })
+                            };
+                            d = d.child(
+                                div()
+                                    .id("conflict_modal")
+                                    .bg(theme.bg)
+                                    .border_1()
+                                    .border_color(theme.divider)
+                                    .rounded_sm()
+                                    .absolute()
+                                    .p_2()
+                                    .flex()
+                                    .flex_col()
+                                    .gap_1()
+                                    .child(format!(
+                                        "Edit `{action}` conflicts with changes made in the editor."
+                                    ))
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .text_color(theme.subtext)
+                                            .child("Reapply it on top of the editor's changes, or discard it."),
+                                    )
+                                    .child(
+                                        div()
+                                            .flex()
+                                            .flex_row()
+                                            .gap_1()
+                                            .child(button("conflict_reapply_btn", "Reapply", true))
+                                            .child(button("conflict_discard_btn", "Discard", false)),
+                                    )
+                                    .whitespace_normal()
+                                    .bottom_2()
+                                    .left_2()
+                                    .right_2(),
+                            );
+                        }
+
+                        if let Some((shapes, report)) = query.as_ref().filter(|(shapes, _)| !shapes.is_empty()) {
+                            d = d.child(
+                                div()
+                                    .id("query_popup")
+                                    .bg(theme.bg)
+                                    .border_1()
+                                    .border_color(theme.divider)
+                                    .rounded_sm()
+                                    .absolute()
+                                    .p_2()
+                                    .flex()
+                                    .flex_col()
+                                    .children(shapes.iter().zip(&report.shapes).map(
+                                        |(shape, (area, perimeter))| {
+                                            div()
+                                                .flex()
+                                                .flex_row()
+                                                .gap_2()
+                                                .child(div().text_color(theme.subtext).child(shape.layer.clone()))
+                                                .child(format!("area {area:.3}"))
+                                                .child(format!("perimeter {perimeter:.3}"))
+                                        },
+                                    ))
+                                    .children((shapes.len() > 1).then(|| {
+                                        format!("total area {:.3}", report.union_area)
+                                    }))
+                                    .children(
+                                        report
+                                            .overlap_area
+                                            .map(|overlap| format!("overlap area {overlap:.3}")),
+                                    )
+                                    .top_2()
+                                    .right_2(),
+                            );
+                        }
+
                         d
                     })
-                    .child(self.layer_sidebar.clone()),
+                    .child(self.layer_sidebar.clone())
+                    .child(self.cell_library.clone())
+                    .children(
+                        self.state
+                            .read(cx)
+                            .drc
+                            .read(cx)
+                            .is_some()
+                            .then(|| self.drc_sidebar.clone()),
+                    )
+                    .children({
+                        let state = self.state.read(cx);
+                        (!state.errors.is_empty() || state.relaxed_constraints > 0)
+                            .then(|| self.error_sidebar.clone())
+                    })
+                    .children(
+                        self.state
+                            .read(cx)
+                            .provenance
+                            .is_some()
+                            .then(|| self.provenance_sidebar.clone()),
+                    ),
+            )
+            .children(
+                self.state
+                    .read(cx)
+                    .sweep
+                    .read(cx)
+                    .is_some()
+                    .then(|| self.sweep_bar.clone()),
             )
+            .children(query.map(|(shapes, report)| {
+                div()
+                    .id("status_bar")
+                    .px_2()
+                    .border_t_1()
+                    .border_color(theme.divider)
+                    .text_xs()
+                    .text_color(theme.subtext)
+                    .child(if shapes.is_empty() {
+                        "Query: click a shape to measure it, or shift-click to measure several."
+                            .to_string()
+                    } else {
+                        format!("Query: {}", report.summary())
+                    })
+            }))
             .child(self.text_input.clone())
     }
 }
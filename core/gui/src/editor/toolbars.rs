@@ -1,19 +1,24 @@
 use std::sync::Arc;
 
-use compiler::compile::SolvedValue;
+use compiler::{ast::Span, compile::ExecErrorKind};
 use gpui::prelude::*;
 use gpui::*;
 use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
-use lang_server::rpc::LangServerAction;
+use lang_server::{
+    library::{CellInfo, Thumbnail},
+    rpc::LangServerAction,
+};
+use tower_lsp_server::ls_types::MessageType;
 
 use crate::{
-    actions::{DrawDim, DrawRect, SelectMode},
+    actions::{DrawDim, DrawRect, NextDrcMarker, PrevDrcMarker, SelectMode},
     editor::{
         CompileOutputState, Layers, ScopeAddress, ScopePath,
         canvas::{EditDimToolState, LayoutCanvas, ToolState},
         input::TextInput,
     },
+    theme::Theme,
 };
 
 use super::EditorState;
@@ -47,6 +52,11 @@ impl Render for TitleBar {
     }
 }
 
+/// Toolbar buttons that edit the source, disabled while a read-only cell is open.
+const EDIT_BUTTONS: [&str; 4] = ["btn_undo", "btn_redo", "btn_rect", "btn_dim"];
+/// Opacity of disabled toolbar buttons.
+const DISABLED_OPACITY: f32 = 0.4;
+
 pub struct ToolBar {
     state: Entity<EditorState>,
 }
@@ -73,13 +83,13 @@ impl Render for ToolBar {
             .flex()
             .flex_row()
             .children({
-                type HighlightFn = Box<dyn Fn(&ToolState) -> bool>;
+                type HighlightFn = Box<dyn Fn(&EditorState, &ToolState) -> bool>;
                 type OnClickFn = Arc<dyn Fn(Entity<EditorState>, &mut App)>;
                 let tools: [Option<(&'static str, &'static str, HighlightFn, OnClickFn)>; _] = [
                     Some((
                         "btn_undo",
                         "icons/arrow-rotate-left-solid-full.svg",
-                        Box::new(|_| false),
+                        Box::new(|_, _| false),
                         Arc::new(|state, cx| {
                             if let Err(e) = state
                                 .read(cx)
@@ -95,7 +105,7 @@ impl Render for ToolBar {
                     Some((
                         "btn_redo",
                         "icons/arrow-rotate-right-solid-full.svg",
-                        Box::new(|_| false),
+                        Box::new(|_, _| false),
                         Arc::new(|state, cx| {
                             if let Err(e) = state
                                 .read(cx)
@@ -112,7 +122,7 @@ impl Render for ToolBar {
                     Some((
                         "btn_select",
                         "icons/arrow-pointer-solid-full.svg",
-                        Box::new(|tool| {
+                        Box::new(|_, tool| {
                             matches!(
                                 tool,
                                 ToolState::Select(_)
@@ -131,7 +141,7 @@ impl Render for ToolBar {
                     Some((
                         "btn_rect",
                         "icons/rect.svg",
-                        Box::new(|tool| matches!(tool, ToolState::DrawRect(_))),
+                        Box::new(|_, tool| matches!(tool, ToolState::DrawRect(_))),
                         Arc::new(|_state, cx| {
                             cx.defer(move |cx| {
                                 cx.dispatch_action(&DrawRect);
@@ -141,7 +151,7 @@ impl Render for ToolBar {
                     Some((
                         "btn_dim",
                         "icons/arrows-left-right-to-line-solid-full.svg",
-                        Box::new(|tool| {
+                        Box::new(|_, tool| {
                             matches!(
                                 tool,
                                 ToolState::DrawDim(_)
@@ -154,13 +164,27 @@ impl Render for ToolBar {
                             });
                         }),
                     )),
+                    None,
+                    Some((
+                        "btn_construction",
+                        "icons/rect-dashed.svg",
+                        Box::new(|state, _| state.show_construction),
+                        Arc::new(|state, cx| {
+                            state.update(cx, |state, cx| {
+                                state.show_construction = !state.show_construction;
+                                cx.notify();
+                            });
+                        }),
+                    )),
                 ];
                 let wh = 20.;
+                let read_only = self.state.read(cx).read_only;
                 tools
                     .iter()
                     .map(|path| {
                         if let Some((id, path, highlighted, on_click)) = path {
                             let on_click = on_click.clone();
+                            let disabled = read_only && EDIT_BUTTONS.contains(id);
                             div()
                                 .w(px(wh + 8.))
                                 .h(px(wh + 8.))
@@ -170,16 +194,24 @@ impl Render for ToolBar {
                                 .child(div().flex_1())
                                 .child(svg().path(*path).w(px(wh)).h_auto().text_color(theme.text))
                                 .child(div().flex_1())
-                                .bg(if highlighted(self.state.read(cx).tool.read(cx)) {
-                                    theme.selection
-                                } else {
-                                    rgba(0)
-                                })
+                                .bg(
+                                    if highlighted(
+                                        self.state.read(cx),
+                                        self.state.read(cx).tool.read(cx),
+                                    ) {
+                                        theme.selection
+                                    } else {
+                                        rgba(0)
+                                    },
+                                )
+                                .opacity(if disabled { DISABLED_OPACITY } else { 1. })
                                 .id(*id)
                                 .on_click({
                                     let state = self.state.clone();
                                     move |_, _, cx| {
-                                        on_click(state.clone(), cx);
+                                        if !disabled {
+                                            on_click(state.clone(), cx);
+                                        }
                                     }
                                 })
                         } else {
@@ -200,6 +232,14 @@ impl Render for ToolBar {
                     .collect_vec()
             })
             .child(div().flex_1())
+            .children(self.state.read(cx).read_only.then(|| {
+                div()
+                    .flex()
+                    .flex_col()
+                    .justify_center()
+                    .text_color(theme.subtext)
+                    .child("Read-only")
+            }))
     }
 }
 
@@ -224,11 +264,13 @@ impl LayerSideBar {
         canvas: &Entity<LayoutCanvas>,
     ) -> Self {
         let layers = editor_state.read(cx).layers.clone();
+        let underlay = editor_state.read(cx).underlay.clone();
         let name_filter =
             cx.new(|cx| TextInput::new_filter(cx, cx.focus_handle(), editor_state, canvas));
         let state = cx.new(|_cx| LayerSideBarState::default());
         let subscriptions = vec![
             cx.observe(&layers, |_, _, cx| cx.notify()),
+            cx.observe(&underlay, |_, _, cx| cx.notify()),
             cx.observe(&name_filter, |_, _, cx| cx.notify()),
         ];
         Self {
@@ -250,6 +292,65 @@ impl Render for LayerSideBar {
         let layers = self.layers.read(cx);
         let theme = self.editor_state.read(cx).theme();
         let icon_wh = 16.;
+        let underlay_entity = self.editor_state.read(cx).underlay.clone();
+        let underlay_row = underlay_entity.read(cx).as_ref().map(|underlay| {
+            div()
+                .flex()
+                .w_full()
+                .border_b_1()
+                .border_color(theme.divider)
+                .child(div().flex_1().overflow_hidden().child(format!(
+                    "underlay: {}",
+                    underlay.path.file_name().map_or_else(
+                        || underlay.path.display().to_string(),
+                        |name| { name.to_string_lossy().into_owned() }
+                    )
+                )))
+                .child(
+                    div()
+                        .id("underlay_opacity_btn")
+                        .px_1()
+                        .child(format!("{:.0}%", underlay.opacity * 100.))
+                        .on_click({
+                            let underlay = underlay_entity.clone();
+                            move |_event, _window, cx| {
+                                underlay.update(cx, |underlay, cx| {
+                                    if let Some(underlay) = underlay {
+                                        underlay.cycle_opacity();
+                                    }
+                                    cx.notify();
+                                })
+                            }
+                        }),
+                )
+                .child(
+                    icon_div()
+                        .child(
+                            svg()
+                                .path(if underlay.visible {
+                                    "icons/eye-solid-full.svg"
+                                } else {
+                                    "icons/eye-slash-solid-full.svg"
+                                })
+                                .w(px(icon_wh))
+                                .h_auto()
+                                .text_color(theme.text),
+                        )
+                        .child(div().flex_1())
+                        .id("underlay_control")
+                        .on_click({
+                            let underlay = underlay_entity.clone();
+                            move |_event, _window, cx| {
+                                underlay.update(cx, |underlay, cx| {
+                                    if let Some(underlay) = underlay {
+                                        underlay.visible = !underlay.visible;
+                                    }
+                                    cx.notify();
+                                })
+                            }
+                        }),
+                )
+        });
         let icon_div = || {
             div()
                 .w(px(icon_wh + 8.))
@@ -350,6 +451,7 @@ impl Render for LayerSideBar {
                     ),
             )
             .child(self.name_filter.clone())
+            .children(underlay_row)
             .child(
                 div()
                     .flex()
@@ -479,6 +581,7 @@ impl HierarchySideBar {
         scopes: &mut Vec<Div>,
         scope: ScopeAddress,
         count: usize,
+        params: Option<String>,
         depth: usize,
     ) {
         let icon_wh = 16.;
@@ -493,6 +596,7 @@ impl HierarchySideBar {
         };
         let solved_cell_clone_1 = self.editor_state.read(cx).solved_cell.clone();
         let solved_cell_clone_2 = self.editor_state.read(cx).solved_cell.clone();
+        let solved_cell_clone_3 = self.editor_state.read(cx).solved_cell.clone();
         let tool_clone = self.tool.clone();
         let scope_state = &solved_cell.state[&solved_cell.scope_paths[&scope]];
         let scope_path = solved_cell.scope_paths[&scope].clone();
@@ -534,6 +638,12 @@ impl HierarchySideBar {
                             .on_click({
                                 let scope_path = scope_path.clone();
                                 move |_event, _window, cx| {
+                                    solved_cell_clone_3.update(cx, |state, cx| {
+                                        if let Some(state) = state.as_mut() {
+                                            state.load_scope(&scope_path);
+                                            cx.notify();
+                                        }
+                                    });
                                     self_entity.read(cx).state.clone().update(cx, |state, cx| {
                                         if !state.expanded_scopes.insert(scope_path.clone()) {
                                             state.expanded_scopes.swap_remove(&scope_path);
@@ -557,11 +667,15 @@ impl HierarchySideBar {
                                     "".to_string()
                                 }
                             ))
+                            .children(params.map(|params| {
+                                div().text_xs().text_color(theme.subtext).child(params)
+                            }))
                             .on_click({
                                 let scope_path = scope_path.clone();
                                 move |_event, _window, cx| {
                                     solved_cell_clone_1.update(cx, |state, cx| {
                                         if let Some(state) = state.as_mut() {
+                                            state.load_scope(&scope_path);
                                             state.selected_scope = scope_path.clone();
                                             cx.notify();
                                         }
@@ -593,8 +707,11 @@ impl HierarchySideBar {
                                 move |_event, _window, cx| {
                                     solved_cell_clone_2.update(cx, |state, cx| {
                                         if let Some(state) = state.as_mut() {
-                                            state.state.get_mut(&scope_path).unwrap().visible =
-                                                !state.state[&scope_path].visible;
+                                            let scope = state.state.get_mut(&scope_path).unwrap();
+                                            scope.visible = !scope.visible;
+                                            if scope.visible {
+                                                state.load_scope(&scope_path);
+                                            }
                                             cx.notify();
                                         }
                                     })
@@ -607,20 +724,29 @@ impl HierarchySideBar {
         let mut cells = IndexMap::new();
         for (obj, _) in scope_info.emit.iter() {
             let elt = &solved_cell.output.cells[&scope.cell].objects[obj];
-            if let SolvedValue::Instance(inst) = elt {
-                *cells.entry(inst.cell).or_insert(0) += 1;
+            if let Some(inst) = elt.instance() {
+                cells.entry(inst.cell).or_insert((0, &inst.params)).0 += 1;
             }
         }
 
-        if expanded {
-            for (cell, count) in cells {
+        // Unloaded scopes have no states for their descendants yet; they are
+        // loaded when expanded.
+        if expanded && scope_state.loaded {
+            for (cell, (count, params)) in cells {
                 let scope = solved_cell.output.cells[&cell].root;
+                let params = (!params.is_empty()).then(|| {
+                    params
+                        .iter()
+                        .map(|(name, value)| format!("{name} = {value}"))
+                        .join(", ")
+                });
                 self.render_scopes_helper(
                     cx,
                     solved_cell,
                     scopes,
                     ScopeAddress { scope, cell },
                     count,
+                    params,
                     depth + 1,
                 );
             }
@@ -634,6 +760,7 @@ impl HierarchySideBar {
                         cell: scope.cell,
                     },
                     1,
+                    None,
                     depth + 1,
                 );
             }
@@ -643,7 +770,21 @@ impl HierarchySideBar {
     fn render_scopes(&mut self, cx: &mut gpui::Context<Self>) -> impl gpui::IntoElement {
         let mut scopes = Vec::new();
         if let Some(state) = self.editor_state.read(cx).solved_cell.read(cx).clone() {
-            let scope = state.output.cells[&state.output.top].root;
+            let top = &state.output.cells[&state.output.top];
+            let scope = top.root;
+            let overrides = &self.editor_state.read(cx).param_overrides;
+            let params = (!top.params.is_empty()).then(|| {
+                top.params
+                    .iter()
+                    .map(|(name, value)| {
+                        if overrides.contains(name) {
+                            format!("{name} = {value} (override)")
+                        } else {
+                            format!("{name} = {value}")
+                        }
+                    })
+                    .join(", ")
+            });
             self.render_scopes_helper(
                 cx,
                 &state,
@@ -653,6 +794,7 @@ impl HierarchySideBar {
                     cell: state.output.top,
                 },
                 1,
+                params,
                 0,
             );
         }
@@ -717,9 +859,7 @@ impl Render for HierarchySideBar {
                                 move |_event, _window, cx| {
                                     solved_cell.update(cx, |cell, cx| {
                                         if let Some(cell) = cell {
-                                            for state in cell.state.values_mut() {
-                                                state.visible = true;
-                                            }
+                                            cell.show_all();
                                         }
                                         cx.notify();
                                     })
@@ -831,3 +971,803 @@ impl Render for HierarchySideBar {
             .child(self.render_scopes(cx))
     }
 }
+
+/// Payload for dragging a cell from the library onto the canvas.
+#[derive(Clone)]
+pub struct DraggedCell {
+    pub name: String,
+    theme: &'static Theme,
+}
+
+impl Render for DraggedCell {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        _cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        div()
+            .px_2()
+            .py_1()
+            .border_1()
+            .border_color(self.theme.divider)
+            .rounded_sm()
+            .bg(self.theme.bg)
+            .text_sm()
+            .text_color(self.theme.text)
+            .child(self.name.clone())
+    }
+}
+
+pub struct CellLibrary {
+    editor_state: Entity<EditorState>,
+    name_filter: Entity<TextInput>,
+    cells: Vec<CellInfo>,
+    #[allow(dead_code)]
+    subscriptions: Vec<Subscription>,
+}
+
+impl CellLibrary {
+    pub fn new(
+        cx: &mut Context<Self>,
+        editor_state: &Entity<EditorState>,
+        canvas: &Entity<LayoutCanvas>,
+    ) -> Self {
+        let name_filter =
+            cx.new(|cx| TextInput::new_filter(cx, cx.focus_handle(), editor_state, canvas));
+        let subscriptions = vec![cx.observe(&name_filter, |_, _, cx| cx.notify())];
+        Self {
+            editor_state: editor_state.clone(),
+            name_filter,
+            cells: Vec::new(),
+            subscriptions,
+        }
+    }
+
+    /// Re-fetches the cell list from the language server in the background.
+    pub fn refresh(&mut self, cx: &mut Context<Self>) {
+        let cells = self.editor_state.read(cx).lang_server_client.list_cells();
+        cx.spawn(async move |this, cx| {
+            let cells = cells.await;
+            this.update(cx, |this, cx| match cells {
+                Ok(cells) => {
+                    this.cells = cells;
+                    cx.notify();
+                }
+                Err(e) => {
+                    this.editor_state.update(cx, |state, cx| {
+                        state.fatal_error = Some(format!("{e}").into());
+                        cx.notify();
+                    });
+                }
+            })
+        })
+        .detach();
+    }
+}
+
+fn render_thumbnail(thumbnail: Option<&Thumbnail>, theme: &'static Theme) -> impl IntoElement {
+    let wh = 48.;
+    let thumbnail = thumbnail.cloned();
+    div()
+        .w(px(wh))
+        .h(px(wh))
+        .flex_none()
+        .border_1()
+        .border_color(theme.divider)
+        .bg(theme.bg)
+        .child(
+            canvas(
+                |_, _, _| (),
+                move |bounds, _, window, _| {
+                    let Some(thumbnail) = &thumbnail else {
+                        return;
+                    };
+                    let bbox = thumbnail.bbox;
+                    let scale = (f32::from(bounds.size.width) / bbox.width().max(1e-9) as f32)
+                        .min(f32::from(bounds.size.height) / bbox.height().max(1e-9) as f32);
+                    // Center the cell and flip the y-axis so that layout +y points up.
+                    let ofs_x = (f32::from(bounds.size.width) - bbox.width() as f32 * scale) / 2.;
+                    let ofs_y = (f32::from(bounds.size.height) - bbox.height() as f32 * scale) / 2.;
+                    for rect in &thumbnail.rects {
+                        let x0 = (rect.bbox.x0 - bbox.x0) as f32 * scale + ofs_x;
+                        let y0 = (bbox.y1 - rect.bbox.y1) as f32 * scale + ofs_y;
+                        let rect_bounds = Bounds::new(
+                            bounds.origin + point(px(x0), px(y0)),
+                            size(
+                                px(rect.bbox.width() as f32 * scale),
+                                px(rect.bbox.height() as f32 * scale),
+                            ),
+                        );
+                        let mut color = rgb(rect.color);
+                        color.a = 0.5;
+                        window.paint_quad(fill(rect_bounds, color));
+                    }
+                },
+            )
+            .size_full(),
+        )
+}
+
+impl Render for CellLibrary {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        let theme = self.editor_state.read(cx).theme();
+        let filter = self.name_filter.read(cx).content.to_lowercase();
+        div()
+            .flex()
+            .flex_col()
+            .h_full()
+            .w(px(200.))
+            .p_1()
+            .border_l_1()
+            .border_t_1()
+            .border_color(theme.divider)
+            .bg(theme.sidebar)
+            .min_h_0()
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_center()
+                    .child("Cells")
+                    .child(div().flex_1()),
+            )
+            .child(self.name_filter.clone())
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w_full()
+                    .items_start()
+                    .id("cells_scroll_vert")
+                    .overflow_y_scroll()
+                    .children(
+                        self.cells
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, cell)| cell.name.to_lowercase().contains(&filter))
+                            // List build targets first.
+                            .sorted_by_key(|(_, cell)| cell.targets.is_empty())
+                            .map(|(i, cell)| {
+                                div()
+                                    .id(SharedString::from(format!("cell_library_{i}")))
+                                    .flex()
+                                    .flex_row()
+                                    .w_full()
+                                    .gap_1()
+                                    .py_0p5()
+                                    .rounded_sm()
+                                    .bg(if cell.targets.is_empty() {
+                                        theme.sidebar
+                                    } else {
+                                        theme.selection
+                                    })
+                                    .child(render_thumbnail(cell.thumbnail.as_ref(), theme))
+                                    .child(
+                                        div()
+                                            .flex()
+                                            .flex_col()
+                                            .flex_1()
+                                            .overflow_hidden()
+                                            .child(div().flex_1())
+                                            .child(cell.name.clone())
+                                            .child(div().text_xs().text_color(theme.subtext).child(
+                                                match cell.num_params {
+                                                    0 => "no parameters".to_string(),
+                                                    1 => "1 parameter".to_string(),
+                                                    n => format!("{n} parameters"),
+                                                },
+                                            ))
+                                            .children(cell.targets.first().map(|target| {
+                                                div()
+                                                    .text_xs()
+                                                    .text_color(theme.subtext)
+                                                    .child(format!("target: {target}"))
+                                            }))
+                                            .child(div().flex_1()),
+                                    )
+                                    .on_drag(
+                                        DraggedCell {
+                                            name: cell.name.clone(),
+                                            theme,
+                                        },
+                                        |cell, _offset, _window, cx| cx.new(|_cx| cell.clone()),
+                                    )
+                                    .on_click({
+                                        let editor_state = self.editor_state.clone();
+                                        // Build targets open with their default parameters.
+                                        let name =
+                                            cell.targets.first().unwrap_or(&cell.name).clone();
+                                        move |event, _window, cx| {
+                                            if event.click_count() != 2 {
+                                                return;
+                                            }
+                                            editor_state.update(cx, |state, cx| {
+                                                if let Err(e) =
+                                                    state.lang_server_client.open_cell(name.clone())
+                                                {
+                                                    state.fatal_error = Some(format!("{e}").into());
+                                                    cx.notify();
+                                                }
+                                            })
+                                        }
+                                    })
+                            })
+                            .collect_vec(),
+                    ),
+            )
+    }
+}
+
+pub struct DrcSideBar {
+    editor_state: Entity<EditorState>,
+    canvas: Entity<LayoutCanvas>,
+    #[allow(dead_code)]
+    subscriptions: Vec<Subscription>,
+}
+
+impl DrcSideBar {
+    pub fn new(
+        cx: &mut Context<Self>,
+        editor_state: &Entity<EditorState>,
+        canvas: &Entity<LayoutCanvas>,
+    ) -> Self {
+        let drc = editor_state.read(cx).drc.clone();
+        let subscriptions = vec![cx.observe(&drc, |_, _, cx| cx.notify())];
+        Self {
+            editor_state: editor_state.clone(),
+            canvas: canvas.clone(),
+            subscriptions,
+        }
+    }
+}
+
+impl Render for DrcSideBar {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        let theme = self.editor_state.read(cx).theme();
+        let drc_entity = self.editor_state.read(cx).drc.clone();
+        let icon_wh = 16.;
+        let icon_div = || {
+            div()
+                .w(px(icon_wh + 8.))
+                .h(px(icon_wh + 8.))
+                .flex()
+                .flex_col()
+                .items_center()
+                .child(div().flex_1())
+        };
+        let Some(drc) = drc_entity.read(cx) else {
+            return div();
+        };
+        div()
+            .flex()
+            .flex_col()
+            .h_full()
+            .w(px(200.))
+            .p_1()
+            .border_l_1()
+            .border_t_1()
+            .border_color(theme.divider)
+            .bg(theme.sidebar)
+            .min_h_0()
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_center()
+                    .child(format!("DRC ({})", drc.mapped.len()))
+                    .child(div().flex_1())
+                    .child(
+                        icon_div()
+                            .child(
+                                svg()
+                                    .path("icons/angles-up-solid-full.svg")
+                                    .w(px(icon_wh))
+                                    .h_auto()
+                                    .text_color(theme.text),
+                            )
+                            .child(div().flex_1())
+                            .id("prev_drc_marker_btn")
+                            .on_click(|_event, window, cx| {
+                                window.dispatch_action(Box::new(PrevDrcMarker), cx)
+                            }),
+                    )
+                    .child(
+                        icon_div()
+                            .child(
+                                svg()
+                                    .path("icons/angles-down-solid-full.svg")
+                                    .w(px(icon_wh))
+                                    .h_auto()
+                                    .text_color(theme.text),
+                            )
+                            .child(div().flex_1())
+                            .id("next_drc_marker_btn")
+                            .on_click(|_event, window, cx| {
+                                window.dispatch_action(Box::new(NextDrcMarker), cx)
+                            }),
+                    ),
+            )
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(theme.subtext)
+                    .overflow_hidden()
+                    .child(if drc.db.description.is_empty() {
+                        drc.path.display().to_string()
+                    } else {
+                        drc.db.description.clone()
+                    }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w_full()
+                    .items_start()
+                    .id("drc_scroll_vert")
+                    .overflow_y_scroll()
+                    .children(
+                        drc.mapped
+                            .iter()
+                            .enumerate()
+                            .map(|(i, mapped)| {
+                                let marker = &drc.db.markers[mapped.marker];
+                                div()
+                                    .id(SharedString::from(format!("drc_marker_{i}")))
+                                    .flex()
+                                    .flex_col()
+                                    .w_full()
+                                    .overflow_hidden()
+                                    .bg(if drc.selected == Some(i) {
+                                        theme.selection
+                                    } else {
+                                        theme.sidebar
+                                    })
+                                    .child(marker.category.clone())
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .text_color(theme.subtext)
+                                            .child(marker.cell.clone()),
+                                    )
+                                    .on_click({
+                                        let drc_entity = drc_entity.clone();
+                                        let canvas = self.canvas.clone();
+                                        let bbox = mapped.bbox();
+                                        move |_event, _window, cx| {
+                                            drc_entity.update(cx, |drc, cx| {
+                                                if let Some(drc) = drc {
+                                                    drc.selected = Some(i);
+                                                }
+                                                cx.notify();
+                                            });
+                                            if let Some(bbox) = bbox {
+                                                canvas.update(cx, |canvas, cx| {
+                                                    canvas.zoom_to_bbox(bbox, cx)
+                                                });
+                                            }
+                                        }
+                                    })
+                            })
+                            .collect_vec(),
+                    ),
+            )
+    }
+}
+
+/// Lists the execution errors of the open cell, offering to temporarily relax
+/// inconsistent constraints so that their geometric consequences can be seen.
+pub struct ErrorSideBar {
+    editor_state: Entity<EditorState>,
+    #[allow(dead_code)]
+    subscriptions: Vec<Subscription>,
+}
+
+impl ErrorSideBar {
+    pub fn new(cx: &mut Context<Self>, editor_state: &Entity<EditorState>) -> Self {
+        let subscriptions = vec![cx.observe(editor_state, |_, _, cx| cx.notify())];
+        Self {
+            editor_state: editor_state.clone(),
+            subscriptions,
+        }
+    }
+}
+
+/// Asks the language server to comment out the constraint at `span`.
+fn relax_constraint(editor_state: &Entity<EditorState>, span: &Span, cx: &mut App) {
+    editor_state.update(cx, |state, cx| {
+        match state.lang_server_client.relax_constraint(span.clone()) {
+            Ok(true) => state.relaxed_constraints += 1,
+            Ok(false) => {
+                let _ = state.lang_server_client.show_message(
+                    MessageType::WARNING,
+                    "Only `eq` and `dimension` statements on their own lines can be relaxed.",
+                );
+            }
+            Err(e) => state.fatal_error = Some(format!("{e}").into()),
+        }
+        cx.notify();
+    });
+}
+
+impl Render for ErrorSideBar {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        let state = self.editor_state.read(cx);
+        let theme = state.theme();
+        let button = |id: SharedString, label: &'static str| {
+            div()
+                .id(id)
+                .px_1()
+                .border_1()
+                .border_color(theme.divider)
+                .rounded_sm()
+                .text_xs()
+                .child(label)
+        };
+        div()
+            .flex()
+            .flex_col()
+            .h_full()
+            .w(px(200.))
+            .p_1()
+            .border_l_1()
+            .border_t_1()
+            .border_color(theme.divider)
+            .bg(theme.sidebar)
+            .min_h_0()
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_center()
+                    .child(format!("Errors ({})", state.errors.len()))
+                    .child(div().flex_1())
+                    .children((state.relaxed_constraints > 0).then(|| {
+                        button("restore_constraints_btn".into(), "Restore").on_click({
+                            let editor_state = self.editor_state.clone();
+                            move |_event, _window, cx| {
+                                editor_state.update(cx, |state, cx| {
+                                    match state.lang_server_client.restore_constraints() {
+                                        Ok(_) => state.relaxed_constraints = 0,
+                                        Err(e) => state.fatal_error = Some(format!("{e}").into()),
+                                    }
+                                    cx.notify();
+                                })
+                            }
+                        })
+                    })),
+            )
+            .children((state.relaxed_constraints > 0).then(|| {
+                div()
+                    .text_xs()
+                    .text_color(theme.subtext)
+                    .whitespace_normal()
+                    .child(match state.relaxed_constraints {
+                        1 => "1 constraint relaxed".to_string(),
+                        n => format!("{n} constraints relaxed"),
+                    })
+            }))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w_full()
+                    .items_start()
+                    .id("errors_scroll_vert")
+                    .overflow_y_scroll()
+                    .children(
+                        state
+                            .errors
+                            .iter()
+                            .enumerate()
+                            .map(|(i, error)| {
+                                let location = error.span.as_ref().map(|span| {
+                                    span.path
+                                        .file_name()
+                                        .map(|name| name.to_string_lossy().to_string())
+                                        .unwrap_or_default()
+                                });
+                                let relax_span = match (&error.kind, &error.span) {
+                                    (ExecErrorKind::InconsistentConstraint(..), Some(span)) => {
+                                        Some(span.clone())
+                                    }
+                                    _ => None,
+                                };
+                                div()
+                                    .id(SharedString::from(format!("exec_error_{i}")))
+                                    .flex()
+                                    .flex_col()
+                                    .w_full()
+                                    .overflow_hidden()
+                                    .child(
+                                        div()
+                                            .flex()
+                                            .flex_row()
+                                            .child(
+                                                div()
+                                                    .flex_1()
+                                                    .overflow_hidden()
+                                                    .text_color(theme.error)
+                                                    .child(format!("{}", error.kind)),
+                                            )
+                                            .children(relax_span.map(|span| {
+                                                button(
+                                                    SharedString::from(format!(
+                                                        "relax_constraint_btn_{i}"
+                                                    )),
+                                                    "Relax",
+                                                )
+                                                .on_click({
+                                                    let editor_state = self.editor_state.clone();
+                                                    move |_event, _window, cx| {
+                                                        cx.stop_propagation();
+                                                        relax_constraint(&editor_state, &span, cx);
+                                                    }
+                                                })
+                                            })),
+                                    )
+                                    .children(location.map(|location| {
+                                        div().text_xs().text_color(theme.subtext).child(location)
+                                    }))
+                                    .on_click({
+                                        let editor_state = self.editor_state.clone();
+                                        let span = error.span.clone();
+                                        move |_event, _window, cx| {
+                                            if let Some(span) = span.clone() {
+                                                let state = editor_state.read(cx);
+                                                let _ = state.lang_server_client.reveal(span);
+                                            }
+                                        }
+                                    })
+                            })
+                            .collect_vec(),
+                    ),
+            )
+    }
+}
+
+/// Lists the constraints that fixed the coordinates of the rect picked with
+/// "Why Is This Here?". Click a constraint to reveal it in the code editor.
+pub struct ProvenanceSideBar {
+    editor_state: Entity<EditorState>,
+    #[allow(dead_code)]
+    subscriptions: Vec<Subscription>,
+}
+
+impl ProvenanceSideBar {
+    pub fn new(cx: &mut Context<Self>, editor_state: &Entity<EditorState>) -> Self {
+        let subscriptions = vec![cx.observe(editor_state, |_, _, cx| cx.notify())];
+        Self {
+            editor_state: editor_state.clone(),
+            subscriptions,
+        }
+    }
+}
+
+impl Render for ProvenanceSideBar {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        let state = self.editor_state.read(cx);
+        let theme = state.theme();
+        let provenance = state.provenance.clone().unwrap_or_default();
+        div()
+            .flex()
+            .flex_col()
+            .h_full()
+            .w(px(240.))
+            .p_1()
+            .border_l_1()
+            .border_t_1()
+            .border_color(theme.divider)
+            .bg(theme.sidebar)
+            .min_h_0()
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .child("Why Is This Here?")
+                    .child(div().flex_1())
+                    .child(
+                        div()
+                            .id("close_provenance_btn")
+                            .px_1()
+                            .border_1()
+                            .border_color(theme.divider)
+                            .rounded_sm()
+                            .text_xs()
+                            .child("Close")
+                            .on_click({
+                                let editor_state = self.editor_state.clone();
+                                move |_event, _window, cx| {
+                                    editor_state.update(cx, |state, cx| {
+                                        state.provenance = None;
+                                        cx.notify();
+                                    })
+                                }
+                            }),
+                    ),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w_full()
+                    .gap_1()
+                    .id("provenance_scroll_vert")
+                    .overflow_y_scroll()
+                    .children(provenance.into_iter().enumerate().map(|(i, provenance)| {
+                        div()
+                            .flex()
+                            .flex_col()
+                            .w_full()
+                            .child(format!("{} = {}", provenance.coord, provenance.value))
+                            .children(provenance.steps.is_empty().then(|| {
+                                div()
+                                    .text_xs()
+                                    .text_color(theme.subtext)
+                                    .child("unconstrained")
+                            }))
+                            .children(provenance.steps.into_iter().enumerate().map(|(j, step)| {
+                                div()
+                                    .id(SharedString::from(format!("provenance_step_{i}_{j}")))
+                                    .flex()
+                                    .flex_col()
+                                    .w_full()
+                                    .pl_2()
+                                    .overflow_hidden()
+                                    .hover(|style| style.bg(theme.selection))
+                                    .child(format!("= {}", step.expr))
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .text_color(theme.subtext)
+                                            .whitespace_normal()
+                                            .child(format!("{} at {}", step.reason, step.location)),
+                                    )
+                                    .on_click({
+                                        let editor_state = self.editor_state.clone();
+                                        move |_event, _window, cx| {
+                                            let state = editor_state.read(cx);
+                                            let _ =
+                                                state.lang_server_client.reveal(step.span.clone());
+                                        }
+                                    })
+                            }))
+                    })),
+            )
+    }
+}
+
+/// Slider for stepping through the frames of a parameter sweep.
+pub struct SweepBar {
+    editor_state: Entity<EditorState>,
+    #[allow(dead_code)]
+    subscriptions: Vec<Subscription>,
+}
+
+impl SweepBar {
+    pub fn new(cx: &mut Context<Self>, editor_state: &Entity<EditorState>) -> Self {
+        let sweep = editor_state.read(cx).sweep.clone();
+        let subscriptions = vec![
+            cx.observe(editor_state, |_, _, cx| cx.notify()),
+            cx.observe(&sweep, |_, _, cx| cx.notify()),
+        ];
+        Self {
+            editor_state: editor_state.clone(),
+            subscriptions,
+        }
+    }
+}
+
+impl Render for SweepBar {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        let state = self.editor_state.read(cx);
+        let theme = state.theme();
+        let Some(sweep) = state.sweep.read(cx) else {
+            return div();
+        };
+        let button = |id: &'static str, label: &'static str| {
+            div()
+                .id(id)
+                .px_1()
+                .border_1()
+                .border_color(theme.divider)
+                .rounded_sm()
+                .text_xs()
+                .child(label)
+        };
+        let invalid = sweep
+            .frames
+            .iter()
+            .filter(|frame| !frame.is_valid())
+            .count();
+        div()
+            .flex()
+            .flex_row()
+            .items_center()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .border_t_1()
+            .border_color(theme.divider)
+            .bg(theme.sidebar)
+            .text_xs()
+            .child(
+                button(
+                    "sweep_play_btn",
+                    if sweep.playing { "Pause" } else { "Play" },
+                )
+                .on_click({
+                    let editor_state = self.editor_state.clone();
+                    move |_event, _window, cx| {
+                        editor_state.update(cx, |state, cx| state.toggle_sweep_playback(cx));
+                    }
+                }),
+            )
+            .child(
+                div()
+                    .flex_none()
+                    .child(sweep.frame().map_or_else(String::new, |frame| {
+                        format!("{} = {}", sweep.name, frame.value)
+                    })),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .flex_1()
+                    .h(px(12.))
+                    .gap_px()
+                    .children(sweep.frames.iter().enumerate().map(|(i, frame)| {
+                        div()
+                            .id(SharedString::from(format!("sweep_frame_{i}")))
+                            .flex_1()
+                            .h_full()
+                            .bg(if i == sweep.index {
+                                theme.text
+                            } else if !frame.is_valid() {
+                                theme.error
+                            } else {
+                                theme.divider
+                            })
+                            .on_click({
+                                let editor_state = self.editor_state.clone();
+                                move |_event, _window, cx| {
+                                    editor_state
+                                        .update(cx, |state, cx| state.show_sweep_frame(cx, i));
+                                }
+                            })
+                    })),
+            )
+            .children((invalid > 0).then(|| {
+                div()
+                    .text_color(theme.error)
+                    .child(format!("{invalid} invalid step(s)"))
+            }))
+            .child(button("sweep_close_btn", "Close").on_click({
+                let editor_state = self.editor_state.clone();
+                move |_event, _window, cx| {
+                    editor_state.update(cx, |state, cx| state.close_sweep(cx));
+                }
+            }))
+    }
+}
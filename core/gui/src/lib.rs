@@ -1,11 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{borrow::Cow, net::SocketAddr};
 
 use clap::Parser;
 use editor::Editor;
 use gpui::*;
 use lang_server::config::default_argon_home;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 use crate::actions::*;
@@ -13,15 +13,84 @@ use crate::assets::{ZED_PLEX_MONO, ZED_PLEX_SANS};
 
 pub mod actions;
 pub mod assets;
+pub mod drc;
 pub mod editor;
+pub mod query;
 pub mod rpc;
+pub mod snapshot;
 pub mod sse;
+pub mod sweep;
 pub mod theme;
+pub mod underlay;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    lang_server_addr: SocketAddr,
+    /// Address of the language server to connect to, or with `--headless`, a
+    /// JSON-serialized compile output to render.
+    target: String,
+    /// KLayout report database (`.lyrdb`) of DRC results to display.
+    #[arg(long)]
+    drc: Option<PathBuf>,
+    /// Renders `target` off-screen and exits instead of opening a window.
+    #[arg(long, requires = "snapshot")]
+    headless: bool,
+    /// PNG file written by `--headless`.
+    #[arg(long, requires = "headless")]
+    snapshot: Option<PathBuf>,
+    /// Index of the display to open the window on. Defaults to the primary
+    /// display.
+    #[arg(long)]
+    display: Option<usize>,
+    /// Position of the window's top left corner relative to its display, in
+    /// pixels, e.g. `100,50`.
+    #[arg(long, value_parser = parse_position)]
+    position: Option<(f32, f32)>,
+}
+
+/// Size of the window when opened at an explicit `--position`.
+const WINDOW_SIZE: (f32, f32) = (1280., 800.);
+
+fn parse_position(s: &str) -> std::result::Result<(f32, f32), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `<x>,<y>`, found `{s}`"))?;
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<f32>()
+            .map_err(|e| format!("invalid coordinate `{v}`: {e}"))
+    };
+    Ok((parse(x)?, parse(y)?))
+}
+
+/// Returns the display and bounds to open the window with, given the
+/// `--display` and `--position` arguments.
+///
+/// Falls back to the primary display if `display` does not exist.
+fn window_placement(
+    cx: &App,
+    display: Option<usize>,
+    position: Option<(f32, f32)>,
+) -> (Option<DisplayId>, Option<WindowBounds>) {
+    let display = match display {
+        Some(index) => cx.displays().get(index).cloned().or_else(|| {
+            warn!("no display with index {index}; using the primary display");
+            cx.primary_display()
+        }),
+        None if position.is_some() => cx.primary_display(),
+        None => None,
+    };
+    let bounds = position.map(|(x, y)| {
+        let origin = display
+            .as_ref()
+            .map(|display| display.bounds().origin)
+            .unwrap_or_default();
+        WindowBounds::Windowed(Bounds::new(
+            origin + point(px(x), px(y)),
+            size(px(WINDOW_SIZE.0), px(WINDOW_SIZE.1)),
+        ))
+    });
+    (display.map(|display| display.id()), bounds)
 }
 
 struct Assets {
@@ -54,6 +123,28 @@ impl AssetSource for Assets {
 pub fn main() {
     let args = Args::parse();
 
+    if args.headless {
+        let snapshot = args
+            .snapshot
+            .as_deref()
+            .expect("--headless requires --snapshot");
+        if let Err(e) = snapshot::render_file(Path::new(&args.target), snapshot) {
+            eprintln!("error: {e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let lang_server_addr: SocketAddr = match args.target.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!(
+                "error: invalid language server address `{}`: {e}",
+                args.target
+            );
+            std::process::exit(2);
+        }
+    };
+
     // TODO: Allow configuration via ARGON_HOME environment variable.
     if let Some(log_dir) = default_argon_home() {
         tracing_subscriber::fmt()
@@ -81,7 +172,9 @@ pub fn main() {
                 KeyBinding::new("r", DrawRect, None),
                 KeyBinding::new("s", SelectMode, None),
                 KeyBinding::new("d", DrawDim, None),
+                KeyBinding::new("m", QueryMode, None),
                 KeyBinding::new("f", Fit, None),
+                KeyBinding::new("g", ToggleGrid, None),
                 KeyBinding::new("q", Edit, None),
                 KeyBinding::new("u", Undo, None),
                 KeyBinding::new("ctrl-r", Redo, None),
@@ -104,6 +197,20 @@ pub fn main() {
                 KeyBinding::new("end", End, None),
                 KeyBinding::new("enter", Enter, None),
                 KeyBinding::new("ctrl-cmd-space", ShowCharacterPalette, None),
+                KeyBinding::new("left", NudgeLeft, Some("LayoutCanvas")),
+                KeyBinding::new("right", NudgeRight, Some("LayoutCanvas")),
+                KeyBinding::new("up", NudgeUp, Some("LayoutCanvas")),
+                KeyBinding::new("down", NudgeDown, Some("LayoutCanvas")),
+                KeyBinding::new("shift-left", NudgeLeftCoarse, Some("LayoutCanvas")),
+                KeyBinding::new("shift-right", NudgeRightCoarse, Some("LayoutCanvas")),
+                KeyBinding::new("shift-up", NudgeUpCoarse, Some("LayoutCanvas")),
+                KeyBinding::new("shift-down", NudgeDownCoarse, Some("LayoutCanvas")),
+                KeyBinding::new("=", ZoomIn, Some("LayoutCanvas")),
+                KeyBinding::new("-", ZoomOut, Some("LayoutCanvas")),
+                KeyBinding::new("cmd-0", ZoomReset, Some("LayoutCanvas")),
+                KeyBinding::new("b", ZoomBack, Some("LayoutCanvas")),
+                KeyBinding::new("]", NextDrcMarker, Some("LayoutCanvas")),
+                KeyBinding::new("[", PrevDrcMarker, Some("LayoutCanvas")),
             ]);
             // Register the `quit` function so it can be referenced by the `MenuItem::action` in the menu bar
             cx.on_action(quit);
@@ -125,6 +232,7 @@ pub fn main() {
                     items: vec![
                         MenuItem::action("Rect", DrawRect),
                         MenuItem::action("Dim", DrawDim),
+                        MenuItem::action("Query", QueryMode),
                         MenuItem::action("Edit", Edit),
                         MenuItem::action("Command Prompt", Command),
                     ],
@@ -136,14 +244,24 @@ pub fn main() {
                         MenuItem::action("Box Only", Zero),
                         MenuItem::action("Top Level Only", One),
                         MenuItem::action("Fit to Screen", Fit),
+                        MenuItem::action("Zoom In", ZoomIn),
+                        MenuItem::action("Zoom Out", ZoomOut),
+                        MenuItem::action("Actual Size", ZoomReset),
+                        MenuItem::action("Zoom Back", ZoomBack),
+                        MenuItem::action("Next DRC Marker", NextDrcMarker),
+                        MenuItem::action("Previous DRC Marker", PrevDrcMarker),
+                        MenuItem::action("Toggle Grid", ToggleGrid),
                         MenuItem::action("Dark Mode", DarkMode),
                         MenuItem::action("Light Mode", LightMode),
                     ],
                 },
             ]);
 
+            let (display_id, window_bounds) = window_placement(cx, args.display, args.position);
             cx.open_window(
                 WindowOptions {
+                    display_id,
+                    window_bounds,
                     titlebar: Some(TitlebarOptions {
                         title: None,
                         appears_transparent: true,
@@ -154,7 +272,7 @@ pub fn main() {
                 },
                 |window, cx| {
                     window.replace_root(cx, |window, cx| {
-                        Editor::new(cx, window, args.lang_server_addr)
+                        Editor::new(cx, window, lang_server_addr, args.drc.clone())
                     })
                 },
             )
@@ -0,0 +1,93 @@
+//! Area and perimeter measurements of shapes picked with the query tool.
+use argon_geometry::{Bbox, Transform, boolean};
+use compiler::compile::{CellId, CompiledData, ObjectId, SolvedValue};
+
+/// A shape picked with the query tool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryShape {
+    /// Path to the rect, relative to the cell it was picked in.
+    pub object_path: Vec<ObjectId>,
+    pub layer: String,
+    /// Bounds of the rect in the coordinates of the cell it was picked in.
+    pub bbox: Bbox,
+}
+
+/// Measurements of a set of queried shapes, in layout units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryReport {
+    /// Area and perimeter of each shape.
+    pub shapes: Vec<(f64, f64)>,
+    /// Area covered by any of the shapes, counting overlaps once.
+    pub union_area: f64,
+    /// Area of the overlap between the shapes, if exactly two are queried.
+    pub overlap_area: Option<f64>,
+}
+
+impl QueryReport {
+    pub fn new(shapes: &[Bbox]) -> Self {
+        Self {
+            shapes: shapes
+                .iter()
+                .map(|bbox| (bbox.area(), bbox.perimeter()))
+                .collect(),
+            union_area: boolean::merge(shapes).iter().map(Bbox::area).sum(),
+            overlap_area: match shapes {
+                [a, b] => Some(a.intersection(b).map_or(0., |overlap| overlap.area())),
+                _ => None,
+            },
+        }
+    }
+
+    /// One-line summary for the status bar.
+    pub fn summary(&self) -> String {
+        match (self.shapes.as_slice(), self.overlap_area) {
+            ([(area, perimeter)], _) => format!("area {area:.3}, perimeter {perimeter:.3}"),
+            (shapes, Some(overlap)) => format!(
+                "{} shapes: total area {:.3}, overlap area {overlap:.3}",
+                shapes.len(),
+                self.union_area
+            ),
+            (shapes, None) => format!("{} shapes: total area {:.3}", shapes.len(), self.union_area),
+        }
+    }
+}
+
+/// Returns the bounds of the rect at `object_path` in the coordinates of `cell`.
+pub fn object_bbox(output: &CompiledData, cell: CellId, object_path: &[ObjectId]) -> Option<Bbox> {
+    let (obj, instances) = object_path.split_last()?;
+    let mut cell = cell;
+    let mut xform = Transform::identity();
+    for id in instances {
+        let cell_info = output.cells.get(&cell)?;
+        xform = xform.compose(&output.snapped_transform(cell_info, *id)?);
+        cell = cell_info.objects.get(id)?.instance()?.cell;
+    }
+    let cell_info = output.cells.get(&cell)?;
+    match cell_info.objects.get(obj)? {
+        SolvedValue::Rect(_) => Some(output.snapped_bbox(cell_info, *obj)?.transform(&xform)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_report_measures_overlap() {
+        let a = Bbox::new(0., 0., 100., 50.);
+        let b = Bbox::new(60., 20., 160., 120.);
+
+        let report = QueryReport::new(&[a]);
+        assert_eq!(report.shapes, [(5000., 300.)]);
+        assert_eq!(report.overlap_area, None);
+        assert_eq!(report.summary(), "area 5000.000, perimeter 300.000");
+
+        let report = QueryReport::new(&[a, b]);
+        assert_eq!(report.overlap_area, Some(1200.));
+        assert_eq!(report.union_area, 5000. + 10000. - 1200.);
+
+        let report = QueryReport::new(&[a, Bbox::new(200., 0., 300., 10.)]);
+        assert_eq!(report.overlap_area, Some(0.));
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     fmt::Display,
     net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
     time::Duration,
 };
 
@@ -9,17 +10,23 @@ use async_compat::CompatExt;
 use compiler::{
     ast::Span,
     compile::{BasicRect, CompileOutput},
+    gds::ImportedShape,
+    graph::Provenance,
+    schema::SCHEMA_VERSION,
+    sweep::SweepFrame,
 };
 use futures::{
     channel::mpsc::{self, Receiver, Sender},
     prelude::*,
 };
 use gpui::AsyncApp;
-use lang_server::rpc::{DimensionParams, Gui, LangServerAction, LangServerClient, ValueEdit};
+use lang_server::{
+    library::CellInfo,
+    rpc::{DimensionParams, Gui, LangServerAction, LangServerClient, ValueEdit, rpc_codec},
+};
 use tarpc::{
     context,
     server::{Channel, incoming::Incoming},
-    tokio_serde::formats::Json,
 };
 use tower_lsp_server::ls_types::MessageType;
 use tracing::error;
@@ -40,7 +47,7 @@ impl SyncLangServerClient {
         let client = app.background_executor().block(
             async move {
                 let mut transport =
-                    tarpc::serde_transport::tcp::connect(lang_server_addr, Json::default);
+                    tarpc::serde_transport::tcp::connect(lang_server_addr, rpc_codec);
                 transport.config_mut().max_frame_length(usize::MAX);
 
                 LangServerClient::new(tarpc::client::Config::default(), transport.await.unwrap())
@@ -68,12 +75,12 @@ impl SyncLangServerClient {
                     .and_then(|p| p.parse::<u16>().ok())
                     .unwrap_or(12346);
                 if let Ok(listener) =
-                    tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, port), Json::default)
+                    tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, port), rpc_codec)
                         .await
                 {
                     listener
                 } else {
-                    tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, 0), Json::default)
+                    tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, 0), rpc_codec)
                         .await
                         .unwrap()
                 }
@@ -117,9 +124,14 @@ impl SyncLangServerClient {
             .background_executor()
             .block_with_timeout(
                 LANG_SERVER_CLIENT_TIMEOUT,
-                async move { client_clone.register(context::current(), server_addr).await }
-                    .compat()
-                    .map_err(|e| format!("{}", e)),
+                async move {
+                    client_clone
+                        .register(context::current(), server_addr, SCHEMA_VERSION)
+                        .await
+                }
+                .compat()
+                .map_err(|e| format!("{}", e))
+                .and_then(|registered| async { registered }),
             )
             .map_err(|_| format!("timeout after {LANG_SERVER_CLIENT_TIMEOUT:?}"))
         {
@@ -146,11 +158,27 @@ impl SyncLangServerClient {
         Ok(())
     }
 
+    pub fn reveal(&self, span: Span) -> Result<()> {
+        let client_clone = self.client.clone();
+        self.app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move { client_clone.reveal(context::current(), span).await }.compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??;
+
+        Ok(())
+    }
+
     pub fn draw_rect(
         &self,
         scope_span: Span,
         var_name: String,
-        rect: BasicRect<f64>,
+        rect: BasicRect<(f64, Option<String>)>,
+        size: Option<(f64, f64)>,
     ) -> Result<Option<Span>> {
         let client_clone = self.client.clone();
         Ok(self
@@ -160,7 +188,7 @@ impl SyncLangServerClient {
                 LANG_SERVER_CLIENT_TIMEOUT,
                 async move {
                     client_clone
-                        .draw_rect(context::current(), scope_span, var_name, rect)
+                        .draw_rect(context::current(), scope_span, var_name, rect, size)
                         .await
                 }
                 .compat(),
@@ -174,7 +202,7 @@ impl SyncLangServerClient {
         &self,
         scope_span: Span,
         params: DimensionParams,
-    ) -> Result<Option<Span>> {
+    ) -> Result<Option<(Span, String)>> {
         let client_clone = self.client.clone();
         Ok(self
             .app
@@ -227,6 +255,116 @@ impl SyncLangServerClient {
         Ok(())
     }
 
+    pub fn translate(&self, span: Span, dx: f64, dy: f64) -> Result<Option<Span>> {
+        let client_clone = self.client.clone();
+        Ok(self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move {
+                    client_clone
+                        .translate(context::current(), span, dx, dy)
+                        .await
+                }
+                .compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??)
+    }
+
+    pub fn set_instance_param(
+        &self,
+        span: Span,
+        index: usize,
+        value: String,
+    ) -> Result<Option<Span>> {
+        let client_clone = self.client.clone();
+        Ok(self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move {
+                    client_clone
+                        .set_instance_param(context::current(), span, index, value)
+                        .await
+                }
+                .compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??)
+    }
+
+    pub fn delete_object(&self, span: Span) -> Result<()> {
+        let client_clone = self.client.clone();
+        self.app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move { client_clone.delete_object(context::current(), span).await }.compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??;
+
+        Ok(())
+    }
+
+    pub fn relax_constraint(&self, span: Span) -> Result<bool> {
+        let client_clone = self.client.clone();
+        Ok(self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move {
+                    client_clone
+                        .relax_constraint(context::current(), span)
+                        .await
+                }
+                .compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??)
+    }
+
+    pub fn restore_constraints(&self) -> Result<usize> {
+        let client_clone = self.client.clone();
+        Ok(self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move { client_clone.restore_constraints(context::current()).await }.compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??)
+    }
+
+    pub fn resolve_edit_conflict(&self, reapply: bool) -> Result<bool> {
+        let client_clone = self.client.clone();
+        Ok(self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move {
+                    client_clone
+                        .resolve_edit_conflict(context::current(), reapply)
+                        .await
+                }
+                .compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??)
+    }
+
     pub fn add_eq_constraint(&self, scope_span: Span, lhs: String, rhs: String) -> Result<()> {
         let client_clone = self.client.clone();
         self.app
@@ -247,6 +385,62 @@ impl SyncLangServerClient {
         Ok(())
     }
 
+    pub fn place_instance(
+        &self,
+        scope_span: Span,
+        var_name: String,
+        cell: String,
+        x: f64,
+        y: f64,
+    ) -> Result<Option<Span>> {
+        let client_clone = self.client.clone();
+        Ok(self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move {
+                    client_clone
+                        .place_instance(context::current(), scope_span, var_name, cell, x, y)
+                        .await
+                }
+                .compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??)
+    }
+
+    /// Lists the cells in the workspace.
+    ///
+    /// Unlike the other requests, this does not block: rendering thumbnails
+    /// may require compiling every cell in the workspace.
+    pub fn list_cells(&self) -> impl Future<Output = Result<Vec<CellInfo>>> + use<> {
+        let client_clone = self.client.clone();
+        async move { Ok(client_clone.list_cells(context::current()).await?) }.compat()
+    }
+
+    /// Compiles the open cell across a sweep of its `param`th argument.
+    ///
+    /// Like [`Self::list_cells`], this does not block, since the sweep
+    /// compiles the cell once per step.
+    pub fn sweep_cell(
+        &self,
+        param: usize,
+        from: f64,
+        to: f64,
+        steps: usize,
+    ) -> impl Future<Output = Result<Vec<SweepFrame>>> + use<> {
+        let client_clone = self.client.clone();
+        async move {
+            client_clone
+                .sweep_cell(context::current(), param, from, to, steps)
+                .await?
+                .map_err(|e| anyhow!(e))
+        }
+        .compat()
+    }
+
     pub fn open_cell(&self, cell: String) -> Result<()> {
         let client_clone = self.client.clone();
         self.app
@@ -262,6 +456,60 @@ impl SyncLangServerClient {
         Ok(())
     }
 
+    /// Overrides parameter `param` of the open cell with `value` for the rest
+    /// of the session, or clears the override if `value` is `default`.
+    pub fn set_param_override(&self, param: String, value: String) -> Result<()> {
+        let client_clone = self.client.clone();
+        self.app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move {
+                    client_clone
+                        .set_param_override(context::current(), param, value)
+                        .await
+                }
+                .compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??
+            .map_err(|e| anyhow!(e))
+    }
+
+    /// Explains the coordinates of the rect created at `span`, see
+    /// [`CompiledData::provenance`](compiler::compile::CompiledData::provenance).
+    pub fn provenance(&self, span: Span) -> Result<Vec<Provenance>> {
+        let client_clone = self.client.clone();
+        self.app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move { client_clone.provenance(context::current(), span).await }.compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??
+            .map_err(|e| anyhow!(e))
+    }
+
+    pub fn load_underlay(&self, path: PathBuf) -> Result<Vec<ImportedShape>> {
+        let client_clone = self.client.clone();
+        let shapes = self
+            .app
+            .background_executor()
+            .block_with_timeout(
+                LANG_SERVER_CLIENT_TIMEOUT,
+                async move { client_clone.load_underlay(context::current(), path).await }.compat(),
+            )
+            .map_err(|_| {
+                anyhow!("timeout reaching language server after {LANG_SERVER_CLIENT_TIMEOUT:?}")
+            })??
+            .map_err(|e| anyhow!(e))?;
+
+        Ok(shapes)
+    }
+
     pub fn show_message<M: Display>(&self, typ: MessageType, message: M) -> Result<()> {
         let client_clone = self.client.clone();
         self.app
@@ -311,11 +559,17 @@ pub struct GuiServer {
 }
 
 impl Gui for GuiServer {
-    async fn open_cell(mut self, _: context::Context, cell: CompileOutput, update: bool) {
+    async fn open_cell(
+        mut self,
+        _: context::Context,
+        cell: CompileOutput,
+        update: bool,
+        read_only: bool,
+    ) {
         self.to_exec
             .send(Box::new(move |editor, cx| {
                 let _ = cx.update(|cx| {
-                    editor.open_cell(cx, cell, update);
+                    editor.open_cell(cx, cell, update, read_only);
                 });
             }))
             .await
@@ -355,12 +609,60 @@ impl Gui for GuiServer {
                     .await
                     .unwrap();
             }
+            "palette" => {
+                self.to_exec
+                    .send(Box::new(move |editor, cx| {
+                        if let Ok(palette) = value.parse() {
+                            editor
+                                .state
+                                .update(cx, |state, cx| {
+                                    state.set_palette(cx, palette);
+                                    cx.notify();
+                                })
+                                .unwrap();
+                        }
+                    }))
+                    .await
+                    .unwrap();
+            }
+            "paramOverrides" => {
+                self.to_exec
+                    .send(Box::new(move |editor, cx| {
+                        editor
+                            .state
+                            .update(cx, |state, cx| {
+                                state.param_overrides = value
+                                    .split(',')
+                                    .filter(|name| !name.is_empty())
+                                    .map(String::from)
+                                    .collect();
+                                cx.notify();
+                            })
+                            .unwrap();
+                    }))
+                    .await
+                    .unwrap();
+            }
             _ => {
                 // TODO: handle errors.
             }
         }
     }
 
+    async fn edit_conflict(mut self, _: context::Context, action: String) {
+        self.to_exec
+            .send(Box::new(move |editor, cx| {
+                editor
+                    .state
+                    .update(cx, |state, cx| {
+                        state.edit_conflict = Some(action.into());
+                        cx.notify();
+                    })
+                    .unwrap();
+            }))
+            .await
+            .unwrap();
+    }
     async fn activate(mut self, _context: ::tarpc::context::Context) -> () {
         self.to_exec
             .send(Box::new(|_, cx| {
@@ -0,0 +1,220 @@
+//! Headless rendering of compile outputs to PNG images.
+//!
+//! Snapshots draw the layers of a cell the way the canvas does when the cell is
+//! first opened, but at a fixed size and pixel density, in the dark theme, and
+//! without opening a window. Rendering the same output always produces the same
+//! image, so snapshots can be diffed against reference images in CI.
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::{Context, anyhow};
+use argon_geometry::Bbox;
+use compiler::compile::{CompileOutput, CompiledData, ExecErrorCompileOutput};
+use gpui::Rgba;
+use rgb::Rgb;
+
+use crate::theme::{DARK_THEME, Palette, Theme};
+
+pub const SNAPSHOT_WIDTH: u32 = 800;
+pub const SNAPSHOT_HEIGHT: u32 = 600;
+/// Width of rect borders, in pixels.
+const BORDER_WIDTH: i64 = 2;
+/// Distance between the diagonal lines of stippled fills, in pixels.
+const STIPPLE_SPACING: i64 = 9;
+
+/// A color as `[r, g, b, a]`.
+pub type Pixel = [u8; 4];
+
+/// A rect to draw, in layout units.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotRect {
+    pub bbox: Bbox,
+    pub fill: Pixel,
+    pub border: Pixel,
+}
+
+/// An RGBA image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Pixel>,
+}
+
+fn rgba_to_pixel(color: Rgba) -> Pixel {
+    [color.r, color.g, color.b, color.a].map(|c| (c.clamp(0., 1.) * 255.).round() as u8)
+}
+
+fn rgb_to_pixel(color: Rgb<u8>) -> Pixel {
+    [color.r, color.g, color.b, 255]
+}
+
+impl Snapshot {
+    fn new(width: u32, height: u32, bg: Pixel) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![bg; (width * height) as usize],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Pixel {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Colors the pixels in `[x0, x1) x [y0, y1)` for which `mask` returns true.
+    fn fill(
+        &mut self,
+        (x0, y0, x1, y1): (i64, i64, i64, i64),
+        color: Pixel,
+        mask: impl Fn(i64, i64) -> bool,
+    ) {
+        let (w, h) = (self.width as i64, self.height as i64);
+        for y in y0.clamp(0, h)..y1.clamp(0, h) {
+            for x in x0.clamp(0, w)..x1.clamp(0, w) {
+                if mask(x, y) {
+                    self.pixels[(y * w + x) as usize] = color;
+                }
+            }
+        }
+    }
+
+    /// Draws `rects` in order, fitting `bbox` to the image the way the canvas
+    /// fits a newly opened cell to the screen.
+    pub fn render_rects(
+        width: u32,
+        height: u32,
+        bbox: Bbox,
+        rects: &[SnapshotRect],
+        theme: &Theme,
+    ) -> Self {
+        let mut snapshot = Self::new(width, height, rgba_to_pixel(theme.bg));
+        let scale = 0.9 * (width as f64 / bbox.width()).min(height as f64 / bbox.height());
+        let offset_x = (-(bbox.x0 + bbox.x1) * scale + width as f64) / 2.;
+        let offset_y = ((bbox.y0 + bbox.y1) * scale + height as f64) / 2.;
+        for rect in rects {
+            let x0 = (rect.bbox.x0 * scale + offset_x).round() as i64;
+            let x1 = (rect.bbox.x1 * scale + offset_x).round() as i64;
+            let y0 = (offset_y - rect.bbox.y1 * scale).round() as i64;
+            let y1 = (offset_y - rect.bbox.y0 * scale).round() as i64;
+            snapshot.fill((x0, y0, x1, y1), rect.fill, |x, y| {
+                (x + y) % STIPPLE_SPACING == 0
+            });
+            let (lo, hi) = (BORDER_WIDTH / 2, BORDER_WIDTH - BORDER_WIDTH / 2);
+            for edge in [
+                (x0 - lo, y0 - lo, x1 + hi, y0 + hi),
+                (x0 - lo, y1 - lo, x1 + hi, y1 + hi),
+                (x0 - lo, y0 - lo, x0 + hi, y1 + hi),
+                (x1 - lo, y0 - lo, x1 + hi, y1 + hi),
+            ] {
+                snapshot.fill(edge, rect.border, |_, _| true);
+            }
+        }
+        snapshot
+    }
+
+    /// Draws the top cell of `output` with all of its layers visible.
+    pub fn render(output: &CompiledData, width: u32, height: u32) -> Self {
+        let palette = Palette::default();
+        let layers = &output.layers.layers;
+        let mut rects = output
+            .iter_flat(output.top, None)
+            .filter_map(|rect| {
+                let name = rect.layer?;
+                let (z, fill, border) = match layers.iter().position(|l| l.name == name) {
+                    Some(z) => (
+                        z,
+                        rgb_to_pixel(layers[z].fill_color),
+                        rgb_to_pixel(layers[z].border_color),
+                    ),
+                    None => {
+                        let color = rgba_to_pixel(palette.layer_color(name));
+                        (layers.len(), color, color)
+                    }
+                };
+                Some((
+                    z,
+                    SnapshotRect {
+                        bbox: rect.rect.bbox(),
+                        fill,
+                        border,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        // Layers missing from the layer properties file are drawn on top, like
+        // the canvas does.
+        rects.sort_by_key(|(z, _)| *z);
+        let rects = rects.into_iter().map(|(_, rect)| rect).collect::<Vec<_>>();
+        let bbox = output
+            .cell_bbox(output.top)
+            .map(|bbox| bbox.bbox())
+            .unwrap_or_else(|| Bbox::new(0., 0., width as f64, height as f64));
+        Self::render_rects(width, height, bbox, &rects, &DARK_THEME)
+    }
+
+    pub fn write_png(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_flattened())?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Renders the [`CompileOutput`] serialized as JSON or bincode by `argon compile`
+/// in `input` to the PNG `output`.
+pub fn render_file(input: &Path, output: &Path) -> anyhow::Result<()> {
+    let bytes =
+        std::fs::read(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let compile_output = CompileOutput::from_bytes(&bytes)
+        .with_context(|| format!("failed to parse {}", input.display()))?;
+    let data = match compile_output {
+        CompileOutput::Valid(data)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            output: Some(data), ..
+        }) => data,
+        _ => return Err(anyhow!("{} has no geometry to render", input.display())),
+    };
+    Snapshot::render(&data, SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT).write_png(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_rects_is_deterministic() {
+        let rect = SnapshotRect {
+            bbox: Bbox::new(0., 0., 100., 100.),
+            fill: [255, 0, 0, 255],
+            border: [0, 255, 0, 255],
+        };
+        let render = || {
+            Snapshot::render_rects(
+                200,
+                100,
+                Bbox::new(0., 0., 100., 100.),
+                &[rect],
+                &DARK_THEME,
+            )
+        };
+        let snapshot = render();
+        assert_eq!(snapshot, render());
+
+        // The rect is scaled to 90% of the image height and centered.
+        let bg = rgba_to_pixel(DARK_THEME.bg);
+        assert_eq!(snapshot.pixel(0, 0), bg);
+        assert_eq!(snapshot.pixel(199, 99), bg);
+        assert_eq!(snapshot.pixel(55, 50), rect.border);
+        assert_eq!(snapshot.pixel(145, 50), rect.border);
+        assert_eq!(snapshot.pixel(100, 5), rect.border);
+        // Fills are stippled with diagonal lines.
+        assert_eq!(snapshot.pixel(100, 8), rect.fill);
+        assert_eq!(snapshot.pixel(101, 8), bg);
+    }
+}
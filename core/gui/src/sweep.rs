@@ -0,0 +1,47 @@
+//! Animation of the open cell across a sweep of one of its parameters.
+use std::time::Duration;
+
+use compiler::{compile::CompileOutput, sweep::SweepFrame};
+
+/// Time each frame is displayed while a sweep plays.
+pub const SWEEP_FRAME_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct Sweep {
+    /// Name of the swept cell parameter.
+    pub name: String,
+    /// Index of the swept cell parameter.
+    pub param: usize,
+    pub frames: Vec<SweepFrame>,
+    /// Index of the displayed frame.
+    pub index: usize,
+    pub playing: bool,
+    /// Incremented whenever playback is toggled, so that a stopped playback
+    /// loop does not advance frames after playback restarts.
+    pub playback: u64,
+    /// Output displayed before the sweep was opened, restored when it is closed.
+    pub base: CompileOutput,
+}
+
+impl Sweep {
+    pub fn new(name: String, param: usize, frames: Vec<SweepFrame>, base: CompileOutput) -> Self {
+        Self {
+            name,
+            param,
+            frames,
+            index: 0,
+            playing: false,
+            playback: 0,
+            base,
+        }
+    }
+
+    pub fn frame(&self) -> Option<&SweepFrame> {
+        self.frames.get(self.index)
+    }
+
+    /// Index of the frame after the displayed one, wrapping around to the
+    /// first frame.
+    pub fn next_index(&self) -> usize {
+        (self.index + 1) % self.frames.len().max(1)
+    }
+}
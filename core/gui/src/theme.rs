@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use gpui::{Rgba, rgb, rgba};
 use lazy_static::lazy_static;
 
@@ -40,3 +42,87 @@ lazy_static! {
         subtext: rgb(0x999999),
     };
 }
+
+/// Color-blind-safe palettes used for layers without colors in the layer
+/// properties file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+    /// Okabe and Ito's palette for color vision deficiency, with grey in
+    /// place of black.
+    #[default]
+    OkabeIto,
+    /// Paul Tol's bright qualitative palette.
+    TolBright,
+    /// Paul Tol's vibrant qualitative palette.
+    TolVibrant,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::OkabeIto, Palette::TolBright, Palette::TolVibrant];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::OkabeIto => "okabe-ito",
+            Palette::TolBright => "tol-bright",
+            Palette::TolVibrant => "tol-vibrant",
+        }
+    }
+
+    fn colors(&self) -> &'static [u32] {
+        match self {
+            Palette::OkabeIto => &[
+                0xe69f00, 0x56b4e9, 0x009e73, 0xf0e442, 0x0072b2, 0xd55e00, 0xcc79a7, 0x999999,
+            ],
+            Palette::TolBright => &[
+                0x4477aa, 0xee6677, 0x228833, 0xccbb44, 0x66ccee, 0xaa3377, 0xbbbbbb,
+            ],
+            Palette::TolVibrant => &[
+                0x0077bb, 0x33bbee, 0x009988, 0xee7733, 0xcc3311, 0xee3377, 0xbbbbbb,
+            ],
+        }
+    }
+
+    /// Color of the layer named `layer`.
+    ///
+    /// Uses an FNV-1a hash of the name so that a layer keeps its color across
+    /// runs and cells.
+    pub fn layer_color(&self, layer: &str) -> Rgba {
+        let hash = layer.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        let colors = self.colors();
+        rgb(colors[(hash % colors.len() as u64) as usize])
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Palette::ALL
+            .into_iter()
+            .find(|palette| palette.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown palette `{s}`; expected one of {}",
+                    Palette::ALL.map(|palette| palette.name()).join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_layer_colors_are_stable() {
+        for palette in Palette::ALL {
+            assert_eq!(palette.name().parse::<Palette>(), Ok(palette));
+        }
+        assert!("rainbow".parse::<Palette>().is_err());
+        // The FNV-1a hash of a name is fixed, so its color does not depend on the build.
+        assert_eq!(Palette::OkabeIto.layer_color("met1"), rgb(0xe69f00));
+        assert_eq!(Palette::TolBright.layer_color("met1"), rgb(0x228833));
+    }
+}
@@ -0,0 +1,36 @@
+//! Read-only reference layout displayed beneath the open cell.
+use std::path::PathBuf;
+
+use compiler::gds::ImportedShape;
+
+/// Opacities cycled through by the underlay dimming control.
+pub const UNDERLAY_OPACITIES: [f32; 4] = [0.4, 0.25, 0.1, 0.6];
+
+pub struct Underlay {
+    pub path: PathBuf,
+    /// Flattened shapes of the imported top cell, in layout units.
+    pub shapes: Vec<ImportedShape>,
+    pub visible: bool,
+    /// Fill opacity of underlay shapes.
+    pub opacity: f32,
+}
+
+impl Underlay {
+    pub fn new(path: PathBuf, shapes: Vec<ImportedShape>) -> Self {
+        Self {
+            path,
+            shapes,
+            visible: true,
+            opacity: UNDERLAY_OPACITIES[0],
+        }
+    }
+
+    /// Switches to the next opacity in [`UNDERLAY_OPACITIES`].
+    pub fn cycle_opacity(&mut self) {
+        let i = UNDERLAY_OPACITIES
+            .iter()
+            .position(|&o| o == self.opacity)
+            .map_or(0, |i| (i + 1) % UNDERLAY_OPACITIES.len());
+        self.opacity = UNDERLAY_OPACITIES[i];
+    }
+}
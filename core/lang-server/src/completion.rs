@@ -0,0 +1,413 @@
+//! Completions of the identifier being typed in a document.
+//!
+//! The document is parsed as it stands, recovering from syntax errors, to find
+//! the arguments and `let` bindings in scope at the cursor and the declarations
+//! of its module. Other modules are completed from the workspace as of the last
+//! save. Layer names are completed in string literals, and the keyword
+//! arguments of builtins such as `rect` within their argument lists.
+use std::path::PathBuf;
+
+use arcstr::Substr;
+use compiler::{
+    ast::{ArgDecl, Decl, Expr, Ident, ModPath, Scope, Statement},
+    compile::{BUILTINS, builtin_kwargs},
+    invocation::ty_name,
+    layer::LayerProperties,
+    parse::{self, ParseMetadata},
+};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use tower_lsp_server::ls_types::{CompletionItem, CompletionItemKind, Position, Uri};
+
+use crate::StateMut;
+
+fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn item(label: &str, kind: CompletionItemKind, detail: Option<String>) -> CompletionItem {
+    CompletionItem {
+        label: label.to_string(),
+        kind: Some(kind),
+        detail,
+        ..Default::default()
+    }
+}
+
+impl StateMut {
+    /// Completions of the identifier ending at `pos` in the document at `uri`.
+    pub(crate) fn completions(&self, uri: &Uri, pos: Position) -> Option<Vec<CompletionItem>> {
+        let path = PathBuf::from(uri.to_file_path()?);
+        let doc = self.editor_files.get(uri)?;
+        let text = doc.contents();
+        let offset = doc.pos_to_offset(pos)?;
+        let start = text[..offset].rfind(|c| !is_ident(c)).map_or(0, |i| i + 1);
+        if text[start..offset].starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        let before = &text[..start];
+        match line_context(&before[before.rfind('\n').map_or(0, |i| i + 1)..]) {
+            LineContext::Comment => return None,
+            LineContext::String => return Some(self.layer_completions()),
+            LineContext::Code => {}
+        }
+
+        let file = parse::parse_file(text, &path);
+        let mod_path = self
+            .ast
+            .iter()
+            .find(|(_, ast)| ast.path == path)
+            .map(|(mod_path, _)| mod_path.clone())
+            .unwrap_or_default();
+        let decls = |target: &ModPath| {
+            if *target == mod_path {
+                Some(file.ast.decls.as_slice())
+            } else {
+                self.ast.get(target).map(|ast| ast.ast.decls.as_slice())
+            }
+        };
+
+        if let Some(prefix) = before.strip_suffix("::") {
+            let segments = path_segments(prefix);
+            let target = resolve_mod(&mod_path, &segments);
+            if let Some(decls) = decls(&target) {
+                let mut items = decls.iter().map(decl_item).collect_vec();
+                if target == ["std"] {
+                    items.extend(builtin_items());
+                }
+                return Some(items);
+            }
+            let (name, prefix) = segments.split_last()?;
+            let enum_decl = decls(&resolve_mod(&mod_path, prefix))
+                .into_iter()
+                .chain(self.ast.values().map(|ast| ast.ast.decls.as_slice()))
+                .flatten()
+                .find_map(|decl| match decl {
+                    Decl::Enum(e) if e.name.name == *name => Some(e),
+                    _ => None,
+                })?;
+            let mut items = enum_decl
+                .variants
+                .iter()
+                .map(|variant| item(&variant.name.name, CompletionItemKind::ENUM_MEMBER, None))
+                .collect_vec();
+            items.push(item(
+                "variants",
+                CompletionItemKind::FUNCTION,
+                Some(format!("fn variants() -> [{name}]")),
+            ));
+            return Some(items);
+        }
+        if before.ends_with('.') {
+            return Some(Vec::new());
+        }
+
+        let mut items = IndexMap::new();
+        for completion in builtin_items()
+            .chain([item("std", CompletionItemKind::MODULE, None)])
+            .chain(file.ast.decls.iter().map(decl_item))
+        {
+            items.insert(completion.label.clone(), completion);
+        }
+        let mut locals = Locals {
+            offset,
+            items: Vec::new(),
+        };
+        locals.decls(&file.ast.decls);
+        for completion in locals.items {
+            items.insert(completion.label.clone(), completion);
+        }
+        let mut items = items.into_values().collect_vec();
+        items.splice(0..0, kwarg_items(before));
+        Some(items)
+    }
+
+    /// The layers of the layer properties file configured in `Argon.toml`.
+    fn layer_completions(&self) -> Vec<CompletionItem> {
+        let Some(root_dir) = &self.root_dir else {
+            return Vec::new();
+        };
+        let Some(layers) = std::fs::read_to_string(self.lyp_path(root_dir))
+            .ok()
+            .and_then(|text| LayerProperties::from_lyp_str(&text).ok())
+        else {
+            return Vec::new();
+        };
+        layers
+            .layers
+            .iter()
+            .map(|layer| &layer.name)
+            .unique()
+            .map(|name| item(name, CompletionItemKind::VALUE, Some("layer".to_string())))
+            .collect()
+    }
+}
+
+/// What the end of a line of source is within.
+enum LineContext {
+    Code,
+    String,
+    Comment,
+}
+
+fn line_context(line: &str) -> LineContext {
+    let mut in_string = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek() == Some(&'/') => return LineContext::Comment,
+            _ => {}
+        }
+    }
+    if in_string {
+        LineContext::String
+    } else {
+        LineContext::Code
+    }
+}
+
+/// The segments of the path ending `text`, e.g. `["crate", "Layer"]` for
+/// `let l = crate::Layer`.
+fn path_segments(mut text: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    loop {
+        let start = text.rfind(|c| !is_ident(c)).map_or(0, |i| i + 1);
+        if start == text.len() {
+            break;
+        }
+        segments.push(&text[start..]);
+        match text[..start].strip_suffix("::") {
+            Some(rest) => text = rest,
+            None => break,
+        }
+    }
+    segments.reverse();
+    segments
+}
+
+/// The module named by `segments` relative to the module at `mod_path`.
+fn resolve_mod(mod_path: &ModPath, segments: &[&str]) -> ModPath {
+    match segments.split_first() {
+        Some((&"std", rest)) => ["std"].iter().chain(rest).map(|s| s.to_string()).collect(),
+        Some((&"crate", rest)) => rest.iter().map(|s| s.to_string()).collect(),
+        _ => mod_path
+            .iter()
+            .cloned()
+            .chain(segments.iter().map(|s| s.to_string()))
+            .collect(),
+    }
+}
+
+fn builtin_items() -> impl Iterator<Item = CompletionItem> {
+    BUILTINS.iter().map(|name| {
+        item(
+            name,
+            CompletionItemKind::FUNCTION,
+            Some("builtin".to_string()),
+        )
+    })
+}
+
+fn decl_item(decl: &Decl<Substr, ParseMetadata>) -> CompletionItem {
+    let args = |args: &[ArgDecl<Substr, ParseMetadata>]| {
+        args.iter()
+            .map(|arg| format!("{}: {}", arg.name.name, ty_name(&arg.ty)))
+            .join(", ")
+    };
+    match decl {
+        Decl::Cell(cell) => item(
+            &cell.name.name,
+            CompletionItemKind::CLASS,
+            Some(format!("cell {}({})", cell.name.name, args(&cell.args))),
+        ),
+        Decl::Fn(f) => item(
+            &f.name.name,
+            CompletionItemKind::FUNCTION,
+            Some(format!(
+                "fn {}({}){}",
+                f.name.name,
+                args(&f.args),
+                f.return_ty
+                    .as_ref()
+                    .map(|ty| format!(" -> {}", ty_name(ty)))
+                    .unwrap_or_default()
+            )),
+        ),
+        Decl::Enum(e) => item(&e.name.name, CompletionItemKind::ENUM, None),
+        Decl::Struct(s) => item(&s.name.name, CompletionItemKind::STRUCT, None),
+        Decl::Constant(c) => item(
+            &c.name.name,
+            CompletionItemKind::CONSTANT,
+            Some(format!("const {}: {}", c.name.name, c.ty.name)),
+        ),
+        Decl::Mod(m) => item(&m.ident.name, CompletionItemKind::MODULE, None),
+    }
+}
+
+/// Keyword arguments of the builtin whose argument list `before` ends in, if
+/// an argument starts there, omitting those already given.
+fn kwarg_items(before: &str) -> Vec<CompletionItem> {
+    if !before.trim_end().ends_with(['(', ',']) {
+        return Vec::new();
+    }
+    let mut depth = 0;
+    let Some(open) = before.rfind(|c: char| {
+        match c {
+            ')' | ']' | '}' => depth += 1,
+            '(' | '[' | '{' if depth > 0 => depth -= 1,
+            '(' | '[' | '{' | ';' => return true,
+            _ => {}
+        }
+        false
+    }) else {
+        return Vec::new();
+    };
+    let callee = before[..open].trim_end();
+    let func = &callee[callee.rfind(|c| !is_ident(c)).map_or(0, |i| i + 1)..];
+    if !before[open..].starts_with('(') || !BUILTINS.contains(&func) {
+        return Vec::new();
+    }
+    let given = before[open + 1..]
+        .split(',')
+        .filter_map(|arg| Some(arg.split_once('=')?.0.trim()))
+        .collect_vec();
+    builtin_kwargs(func)
+        .iter()
+        .filter(|kwarg| !given.contains(kwarg))
+        .enumerate()
+        .map(|(i, kwarg)| CompletionItem {
+            label: format!("{kwarg}="),
+            kind: Some(CompletionItemKind::PROPERTY),
+            detail: Some(format!("{func} keyword argument")),
+            filter_text: Some(kwarg.to_string()),
+            sort_text: Some(format!("0{i:02}")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Collects the arguments and `let` bindings in scope at `offset`.
+struct Locals {
+    offset: usize,
+    items: Vec<CompletionItem>,
+}
+
+impl Locals {
+    fn contains(&self, span: cfgrammar::Span) -> bool {
+        span.start() <= self.offset && self.offset <= span.end()
+    }
+
+    fn push(&mut self, name: &Ident<Substr, ParseMetadata>, detail: Option<String>) {
+        self.items
+            .push(item(&name.name, CompletionItemKind::VARIABLE, detail));
+    }
+
+    fn decls(&mut self, decls: &[Decl<Substr, ParseMetadata>]) {
+        for decl in decls {
+            let (args, scope) = match decl {
+                Decl::Cell(cell) if self.contains(cell.span) => (&cell.args, &cell.scope),
+                Decl::Fn(f) if self.contains(f.span) => (&f.args, &f.scope),
+                _ => continue,
+            };
+            for arg in args {
+                self.push(&arg.name, Some(ty_name(&arg.ty)));
+            }
+            self.scope(scope);
+        }
+    }
+
+    fn scope(&mut self, scope: &Scope<Substr, ParseMetadata>) {
+        if !self.contains(scope.span) {
+            return;
+        }
+        for stmt in &scope.stmts {
+            match stmt {
+                Statement::LetBinding(binding) if binding.span.end() <= self.offset => {
+                    self.push(&binding.name, None)
+                }
+                Statement::LetBinding(binding) => self.expr(&binding.value),
+                Statement::ForLoop(for_loop) => {
+                    self.expr(&for_loop.seq);
+                    if self.contains(for_loop.body.span) {
+                        self.push(&for_loop.var, None);
+                        self.scope(&for_loop.body);
+                    }
+                }
+                Statement::Expr { value, .. } => self.expr(value),
+            }
+        }
+        if let Some(tail) = &scope.tail {
+            self.expr(tail);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<Substr, ParseMetadata>) {
+        if !self.contains(expr.span()) {
+            return;
+        }
+        match expr {
+            Expr::If(e) => {
+                self.expr(&e.cond);
+                self.scope(&e.then);
+                self.scope(&e.else_);
+            }
+            Expr::Match(e) => {
+                self.expr(&e.scrutinee);
+                for arm in &e.arms {
+                    self.expr(&arm.expr);
+                }
+            }
+            Expr::Comparison(e) => {
+                self.expr(&e.left);
+                self.expr(&e.right);
+            }
+            Expr::BinOp(e) => {
+                self.expr(&e.left);
+                self.expr(&e.right);
+            }
+            Expr::UnaryOp(e) => self.expr(&e.operand),
+            Expr::Call(call) => {
+                for arg in &call.args.posargs {
+                    self.expr(arg);
+                }
+                for kwarg in &call.args.kwargs {
+                    self.expr(&kwarg.value);
+                }
+            }
+            Expr::Emit(e) => self.expr(&e.value),
+            Expr::FieldAccess(e) => self.expr(&e.base),
+            Expr::IndexFieldAccess(e) => self.expr(&e.base),
+            Expr::Index(e) => {
+                self.expr(&e.base);
+                self.expr(&e.index);
+            }
+            Expr::Scope(scope) => self.scope(scope),
+            Expr::Cast(e) => self.expr(&e.value),
+            Expr::Tuple(e) => {
+                for item in &e.items {
+                    self.expr(item);
+                }
+            }
+            Expr::Range(e) => {
+                self.expr(&e.start);
+                self.expr(&e.end);
+            }
+            Expr::Seq(e) => {
+                for item in &e.items {
+                    self.expr(item);
+                }
+            }
+            Expr::IdentPath(_)
+            | Expr::Nil(_)
+            | Expr::SeqNil(_)
+            | Expr::FloatLiteral(_)
+            | Expr::IntLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::BoolLiteral(_) => {}
+        }
+    }
+}
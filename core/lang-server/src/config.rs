@@ -1,6 +1,99 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use compiler::config::GuiConfig;
+use itertools::Itertools;
+use serde::Deserialize;
 
 // TODO: Allow configuration via ARGON_HOME environment variable.
 pub fn default_argon_home() -> Option<PathBuf> {
     Some(homedir::my_home().ok()??.join(".local/state/argon"))
 }
+
+/// Returns the paths searched for the GUI binary, in order.
+///
+/// The `path` under `[gui]` in `Argon.toml` is used on its own if set.
+/// Otherwise, the GUI is looked for next to the language server binary, then
+/// on `PATH`, then in the release build of the Argon repository.
+pub fn gui_binary_candidates(config: &GuiConfig, root_dir: Option<&Path>) -> Vec<PathBuf> {
+    if let Some(path) = &config.path {
+        return vec![match root_dir {
+            Some(root_dir) if path.is_relative() => root_dir.join(path),
+            _ => path.clone(),
+        }];
+    }
+    let name = format!("gui{}", std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(&name)))
+        .into_iter()
+        .chain(
+            std::env::var_os("PATH")
+                .map(|paths| std::env::split_paths(&paths).collect_vec())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|dir| dir.join(&name)),
+        )
+        .chain([
+            PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/release")).join(&name),
+        ])
+        .collect()
+}
+
+/// Returns the first of [`gui_binary_candidates`] that exists, or an error
+/// listing the paths searched.
+pub fn find_gui_binary(config: &GuiConfig, root_dir: Option<&Path>) -> Result<PathBuf, String> {
+    let candidates = gui_binary_candidates(config, root_dir);
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "could not find the GUI binary; searched {}. Set `path` under `[gui]` in Argon.toml to its location",
+                candidates
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .join(", ")
+            )
+        })
+}
+
+/// Settings of the language server, sent by the editor as initialization
+/// options and in `workspace/didChangeConfiguration`, optionally nested under
+/// `argon`, e.g.
+///
+/// ```json
+/// { "argon": { "inlayHints": { "enable": false } } }
+/// ```
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub inlay_hints: InlayHintSettings,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct InlayHintSettings {
+    /// Whether to annotate rects, dimensions, and `eq` constraints with their
+    /// solved values.
+    pub enable: bool,
+}
+
+impl Default for InlayHintSettings {
+    fn default() -> Self {
+        Self { enable: true }
+    }
+}
+
+impl Settings {
+    /// Reads settings from `value`, or returns `None` if they are invalid.
+    pub fn from_value(value: serde_json::Value) -> Option<Self> {
+        let value = match value {
+            serde_json::Value::Object(mut settings) if settings.contains_key("argon") => {
+                settings.remove("argon")?
+            }
+            value => value,
+        };
+        serde_json::from_value(value).ok()
+    }
+}
@@ -0,0 +1,75 @@
+//! Definitions of and references to the names in the workspace.
+//!
+//! Names are resolved in the workspace as of the last save, so requests in a
+//! document with unsaved changes find nothing.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use compiler::{
+    ast::Span,
+    symbols::{SymbolId, SymbolTable},
+};
+use tower_lsp_server::ls_types::{Location, Position, Uri};
+
+use crate::{StateMut, source::SourceFile};
+
+impl StateMut {
+    /// The symbols of the workspace and the symbol defined or referenced at
+    /// `pos` in the document at `uri`.
+    fn symbol_at(&self, uri: &Uri, pos: Position) -> Option<(SymbolTable, SymbolId)> {
+        let path = PathBuf::from(uri.to_file_path()?);
+        let doc = self.editor_files.get(uri)?;
+        let ast = self.ast.values().find(|ast| ast.path == path)?;
+        if doc.contents() != ast.text {
+            return None;
+        }
+        let symbols = SymbolTable::from_workspace(&self.ast);
+        let id = symbols.find(&path, doc.pos_to_offset(pos)?)?;
+        Some((symbols, id))
+    }
+
+    /// The definition of the name at `pos` in the document at `uri`.
+    pub(crate) fn definition(&self, uri: &Uri, pos: Position) -> Option<Location> {
+        let (symbols, id) = self.symbol_at(uri, pos)?;
+        self.locations([&symbols.symbol(id).span]).pop()
+    }
+
+    /// The references to the name at `pos` in the document at `uri`, and its
+    /// definition if `include_declaration` is set.
+    pub(crate) fn references(
+        &self,
+        uri: &Uri,
+        pos: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let (symbols, id) = self.symbol_at(uri, pos)?;
+        let definition = include_declaration.then(|| &symbols.symbol(id).span);
+        Some(
+            self.locations(
+                definition
+                    .into_iter()
+                    .chain(symbols.references(id).map(|r| &r.span)),
+            ),
+        )
+    }
+
+    /// Converts `spans` of the workspace as of the last save into locations.
+    fn locations<'a>(&self, spans: impl IntoIterator<Item = &'a Span>) -> Vec<Location> {
+        let mut files: HashMap<&Path, Option<SourceFile>> = HashMap::new();
+        spans
+            .into_iter()
+            .filter_map(|span| {
+                let file = files
+                    .entry(span.path.as_path())
+                    .or_insert_with(|| self.source_file(&span.path))
+                    .as_ref()?;
+                Some(Location {
+                    uri: file.uri.clone(),
+                    range: file.doc.span_to_range(span.span),
+                })
+            })
+            .collect()
+    }
+}
@@ -37,6 +37,10 @@ impl Document {
         pos2position(self.contents.offset_to_pos(offset).unwrap())
     }
 
+    pub(crate) fn pos_to_offset(&self, pos: Position) -> Option<usize> {
+        self.contents.pos_to_offset(&position2pos(pos))
+    }
+
     pub(crate) fn span_to_range(&self, span: Span) -> Range {
         Range::new(
             self.offset_to_pos(span.start()),
@@ -71,7 +75,6 @@ impl Document {
         self.contents.text()
     }
 
-    #[allow(dead_code)]
     pub(crate) fn version(&self) -> i32 {
         self.version
     }
@@ -2,8 +2,9 @@ use arcstr::Substr;
 use compiler::{
     ast::{
         ArgDecl, Args, AstMetadata, AstTransformer, BinOpExpr, CallExpr, CellDecl, ComparisonExpr,
-        ConstantDecl, Decl, EnumDecl, Expr, FieldAccessExpr, FnDecl, Ident, IdentPath, IfExpr,
-        IndexFieldAccessExpr, IntLiteral, Scope, TySpec, UnaryOpExpr, annotated::AnnotatedAst,
+        ConstantDecl, Decl, EnumDecl, EnumVariant, Expr, FieldAccessExpr, FnDecl, Ident, IdentPath,
+        IfExpr, IndexFieldAccessExpr, IntLiteral, Scope, StructDecl, StructField, TySpec,
+        UnaryOpExpr, annotated::AnnotatedAst,
     },
     compile::BUILTINS,
     parse::ParseMetadata,
@@ -42,8 +43,7 @@ impl<'a> ScopeAnnotationPass<'a> {
                 Decl::Mod(c) => {
                     self.transform_mod_decl(c);
                 }
-                Decl::Enum(_) => {}
-                _ => todo!(),
+                Decl::Enum(_) | Decl::Struct(_) | Decl::Constant(_) => {}
             }
         }
 
@@ -103,10 +103,27 @@ impl<'a> AstTransformer for ScopeAnnotationPass<'a> {
         &mut self,
         _input: &EnumDecl<Substr, Self::InputMetadata>,
         _name: &Ident<Substr, Self::OutputMetadata>,
-        _variants: &[Ident<Substr, Self::OutputMetadata>],
+        _fields: &[StructField<Substr, Self::OutputMetadata>],
+        _variants: &[EnumVariant<Substr, Self::OutputMetadata>],
     ) -> <Self::OutputMetadata as AstMetadata>::EnumDecl {
     }
 
+    fn dispatch_struct_decl(
+        &mut self,
+        _input: &StructDecl<Substr, Self::InputMetadata>,
+        _name: &Ident<Substr, Self::OutputMetadata>,
+        _fields: &[StructField<Substr, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::StructDecl {
+    }
+
+    fn dispatch_struct_field(
+        &mut self,
+        _input: &StructField<Substr, Self::InputMetadata>,
+        _name: &Ident<Substr, Self::OutputMetadata>,
+        _ty: &Ident<Substr, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::StructField {
+    }
+
     fn dispatch_cell_decl(
         &mut self,
         _input: &CellDecl<Substr, Self::InputMetadata>,
@@ -315,6 +332,21 @@ impl<'a> AstTransformer for ScopeAnnotationPass<'a> {
     ) -> <Self::OutputMetadata as AstMetadata>::TupleExpr {
     }
 
+    fn dispatch_seq_expr(
+        &mut self,
+        _input: &compiler::ast::SeqExpr<Self::InputS, Self::InputMetadata>,
+        _items: &[Expr<Self::OutputS, Self::OutputMetadata>],
+    ) -> <Self::OutputMetadata as AstMetadata>::SeqExpr {
+    }
+
+    fn dispatch_range_expr(
+        &mut self,
+        _input: &compiler::ast::RangeExpr<Self::InputS, Self::InputMetadata>,
+        _start: &Expr<Self::OutputS, Self::OutputMetadata>,
+        _end: &Expr<Self::OutputS, Self::OutputMetadata>,
+    ) -> <Self::OutputMetadata as AstMetadata>::RangeExpr {
+    }
+
     fn dispatch_emit_expr(
         &mut self,
         _input: &compiler::ast::EmitExpr<Substr, Self::InputMetadata>,
@@ -403,6 +435,8 @@ impl<'a> AstTransformer for ScopeAnnotationPass<'a> {
             }
             Expr::Cast(cast) => Expr::Cast(Box::new(self.transform_cast(cast))),
             Expr::Tuple(tuple) => Expr::Tuple(self.transform_tuple_expr(tuple)),
+            Expr::Range(range) => Expr::Range(Box::new(self.transform_range_expr(range))),
+            Expr::Seq(seq) => Expr::Seq(self.transform_seq_expr(seq)),
         }
     }
 
@@ -0,0 +1,326 @@
+//! Journal of GUI-originated source edits.
+//!
+//! Every edit the language server applies on behalf of the GUI is appended to
+//! `journal.jsonl` in the Argon home directory, so that a file corrupted by the
+//! editor and GUI getting out of sync can be repaired with `argon journal`.
+//!
+//! Edits are also written ahead to `pending.jsonl` before they are sent to the
+//! editor and removed once the editor has saved them, so that edits cut short
+//! by a crash can be restored by [`recover`] when the server restarts. The
+//! record is shared by the servers of every workspace, so it is only read and
+//! written while holding a lock on `pending.jsonl.lock`.
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use arcstr::ArcStr;
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::ls_types::{TextEdit, Uri};
+use tracing::warn;
+
+use crate::{config::default_argon_home, document::Document};
+
+/// A replacement of `old_text` at byte offset `start` with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEdit {
+    pub start: usize,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+/// The edits made to a single file by one GUI action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub path: PathBuf,
+    /// Name of the GUI request that produced the edit, e.g. `draw_dimension`.
+    pub action: String,
+    /// Non-overlapping edits sorted by `start`, with offsets into the file
+    /// contents before the entry was applied.
+    pub edits: Vec<JournalEdit>,
+}
+
+impl JournalEntry {
+    /// Applies the entry to `text`.
+    ///
+    /// Returns `None` if `text` does not contain the expected old text at each
+    /// edit, i.e. if the file has changed since the entry was recorded.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let mut text = text.to_string();
+        for edit in self.edits.iter().rev() {
+            let end = edit.start + edit.old_text.len();
+            if text.get(edit.start..end)? != edit.old_text {
+                return None;
+            }
+            text.replace_range(edit.start..end, &edit.new_text);
+        }
+        Some(text)
+    }
+
+    /// Returns the entry that reverts this one.
+    pub fn inverse(&self) -> Self {
+        let mut shift = 0isize;
+        let edits = self
+            .edits
+            .iter()
+            .map(|edit| {
+                let start = (edit.start as isize + shift) as usize;
+                shift += edit.new_text.len() as isize - edit.old_text.len() as isize;
+                JournalEdit {
+                    start,
+                    old_text: edit.new_text.clone(),
+                    new_text: edit.old_text.clone(),
+                }
+            })
+            .collect();
+        Self {
+            timestamp: self.timestamp,
+            path: self.path.clone(),
+            action: self.action.clone(),
+            edits,
+        }
+    }
+}
+
+/// Path of the edit journal, if the Argon home directory is known.
+pub fn journal_path() -> Option<PathBuf> {
+    Some(default_argon_home()?.join("journal.jsonl"))
+}
+
+/// Path of the write-ahead record of edits that have been sent to the editor
+/// but not yet saved, if the Argon home directory is known.
+pub fn pending_path() -> Option<PathBuf> {
+    Some(default_argon_home()?.join("pending.jsonl"))
+}
+
+/// Reads every entry in the journal at `path`, oldest first.
+pub fn read_journal(path: impl AsRef<Path>) -> io::Result<Vec<JournalEntry>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    BufReader::new(file)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// Appends `entries` to the journal at `path`.
+pub fn append_journal(path: impl AsRef<Path>, entries: &[JournalEntry]) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    Ok(())
+}
+
+/// Replaces the contents of the journal at `path` with `entries`.
+fn write_journal(path: &Path, entries: &[JournalEntry]) -> io::Result<()> {
+    if entries.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp, "")?;
+    append_journal(&tmp, entries)?;
+    std::fs::rename(tmp, path)
+}
+
+/// Runs `f` while holding an exclusive lock on the pending edit record at
+/// `path`.
+///
+/// The lock is taken on a separate file because [`write_journal`] replaces the
+/// record itself.
+fn with_lock<T>(path: &Path, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let lock = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(path.with_extension("jsonl.lock"))?;
+    lock.lock()?;
+    f()
+}
+
+/// Converts `changes` into journal entries, where `base` holds the contents of
+/// each file that the changes were computed against.
+fn journal_entries(
+    action: &str,
+    changes: &HashMap<Uri, Vec<TextEdit>>,
+    base: &HashMap<Uri, ArcStr>,
+) -> io::Result<Vec<JournalEntry>> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut entries = Vec::new();
+    for (uri, edits) in changes {
+        let (Some(path), Some(text)) = (uri.to_file_path(), base.get(uri)) else {
+            continue;
+        };
+        let doc = Document::new(text.clone(), 0);
+        let mut edits = edits
+            .iter()
+            .map(|edit| {
+                let offset = |pos| {
+                    doc.pos_to_offset(pos).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("edit position {pos:?} is outside of {path:?}"),
+                        )
+                    })
+                };
+                let start = offset(edit.range.start)?;
+                let end = offset(edit.range.end)?;
+                Ok(JournalEdit {
+                    start,
+                    old_text: text[start..end].to_string(),
+                    new_text: edit.new_text.clone(),
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        edits.sort_by_key(|edit| edit.start);
+        entries.push(JournalEntry {
+            timestamp,
+            path: PathBuf::from(path),
+            action: action.to_string(),
+            edits,
+        });
+    }
+    Ok(entries)
+}
+
+/// Records `changes`, made on behalf of GUI request `action` to files with
+/// contents `base`, in the edit journal.
+///
+/// Failures are logged rather than returned so that journaling never blocks an
+/// edit.
+pub(crate) fn record(
+    action: &str,
+    changes: &HashMap<Uri, Vec<TextEdit>>,
+    base: &HashMap<Uri, ArcStr>,
+) {
+    let Some(path) = journal_path() else {
+        return;
+    };
+    if let Err(e) =
+        journal_entries(action, changes, base).and_then(|entries| append_journal(&path, &entries))
+    {
+        warn!("failed to record `{action}` in edit journal: {e}");
+    }
+}
+
+/// Writes `changes`, about to be made on behalf of GUI request `action` to
+/// files with contents `base`, ahead to the pending edit record.
+///
+/// Returns the recorded entries, which must be passed to [`finish`] once the
+/// edit has been saved or abandoned. Like [`record`], failures are only logged.
+pub(crate) fn begin(
+    action: &str,
+    changes: &HashMap<Uri, Vec<TextEdit>>,
+    base: &HashMap<Uri, ArcStr>,
+) -> Vec<JournalEntry> {
+    let Some(path) = pending_path() else {
+        return Vec::new();
+    };
+    match journal_entries(action, changes, base).and_then(|entries| {
+        with_lock(&path, || append_journal(&path, &entries))?;
+        Ok(entries)
+    }) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("failed to record pending `{action}` edit: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Removes `entries`, returned by [`begin`], from the pending edit record.
+pub(crate) fn finish(entries: &[JournalEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+    let Some(path) = pending_path() else {
+        return;
+    };
+    if let Err(e) = with_lock(&path, || {
+        let pending = read_journal(&path)?
+            .into_iter()
+            .filter(|entry| !entries.contains(entry))
+            .collect::<Vec<_>>();
+        write_journal(&path, &pending)
+    }) {
+        warn!("failed to clear pending edits: {e}");
+    }
+}
+
+/// Outcome of [`recover`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    /// Files to which interrupted edits were restored.
+    pub restored: Vec<PathBuf>,
+    /// Files whose contents match neither side of an interrupted edit, which
+    /// were left untouched.
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// Restores the edits in the pending edit record at `pending` that were cut
+/// short before being saved to files within `root_dir`.
+///
+/// An edit whose new text is already on disk was saved before the crash and is
+/// simply dropped from the record, as are edits that no longer apply. Edits to
+/// files outside of `root_dir` are kept for the server of their workspace.
+pub fn recover(pending: impl AsRef<Path>, root_dir: &Path) -> io::Result<Recovery> {
+    let pending = pending.as_ref();
+    with_lock(pending, || recover_locked(pending, root_dir))
+}
+
+/// [`recover`], with the lock on the pending edit record held.
+fn recover_locked(pending: &Path, root_dir: &Path) -> io::Result<Recovery> {
+    let mut recovery = Recovery::default();
+    let mut kept = Vec::new();
+    for entry in read_journal(pending)? {
+        if !entry.path.starts_with(root_dir) {
+            kept.push(entry);
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(&entry.path) else {
+            recovery.conflicts.push(entry.path);
+            continue;
+        };
+        if entry.inverse().apply(&text).is_some() {
+            continue;
+        }
+        match entry.apply(&text) {
+            Some(text) => {
+                std::fs::write(&entry.path, text)?;
+                if !recovery.restored.contains(&entry.path) {
+                    recovery.restored.push(entry.path.clone());
+                }
+            }
+            None => {
+                warn!(
+                    "pending `{}` edit no longer applies to {:?}",
+                    entry.action, entry.path
+                );
+                recovery.conflicts.push(entry.path.clone());
+            }
+        }
+    }
+    write_journal(pending, &kept)?;
+    Ok(recovery)
+}
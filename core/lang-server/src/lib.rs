@@ -1,161 +1,471 @@
+mod completion;
 pub mod config;
+mod definition;
 pub mod document;
 pub mod import;
+pub mod journal;
+pub mod library;
+mod live;
+pub mod overrides;
 pub mod rpc;
+pub mod session;
+mod solved;
+pub mod source;
 
 use std::{
     cmp::Reverse,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     net::{Ipv4Addr, SocketAddr},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Stdio,
-    sync::Arc,
+    sync::{Arc, atomic::AtomicUsize},
 };
 
 use compiler::{
-    ast::{Expr, Span},
+    ast::Span,
     compile::{
-        self, CellArg, CompileInput, CompileOutput, ExecErrorCompileOutput,
-        StaticErrorCompileOutput,
+        self, CompileOutput, ExecErrorCompileOutput, ExecErrorKind, StaticErrorCompileOutput,
     },
     config::{Config, parse_config},
+    consts::{Constant, eval_consts},
+    layer::{LayerRef, LayerUsage},
+    lint::{Lint, LintLevel, Linter},
     parse::{self, WorkspaceParseAst},
+    sweep::SweepCache,
 };
 use futures::prelude::*;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use rpc::{GuiClient, LangServer};
+use rpc::{GuiClient, LangServer, PendingEdit, rpc_codec};
 use serde::{Deserialize, Serialize};
 use tarpc::{
     context,
-    server::{Channel, incoming::Incoming},
-    tokio_serde::formats::Json,
+    server::{Channel, incoming::Incoming, request_hook::RequestHook},
 };
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::{Child, Command},
     sync::Mutex,
 };
-use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::jsonrpc::{self, Result};
 use tower_lsp_server::ls_types::{request::Request, *};
-use tower_lsp_server::{Client, LanguageServer, LspService, Server};
+use tower_lsp_server::{Client, ClientSocket, LanguageServer, LspService, Server};
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
 use crate::{
-    config::default_argon_home,
+    config::{Settings, default_argon_home, find_gui_binary},
     document::{Document, DocumentChange},
     import::ScopeAnnotationPass,
+    journal,
+    overrides::{PARAM_KEY_PREFIX, PARAM_OVERRIDES_KEY, ParamOverrides},
+    session::Tap,
 };
 
+/// Command listing the layers referenced in the workspace, optionally
+/// restricted to the layer given as its only argument.
+const LAYER_USAGE_COMMAND: &str = "argon.layerUsage";
+
 // TODO: finer-grained synchronization?
 // TODO: Verify synchronization between GUI and editor files when appropriate.
 #[derive(Debug, Default)]
 pub struct StateMut {
     gui: Option<Child>,
     root_dir: Option<PathBuf>,
+    /// The last valid `Argon.toml` of the workspace.
     config: Option<Config>,
+    /// Error in `Argon.toml` last shown to the user, if it is invalid.
+    config_error: Option<String>,
     ast: WorkspaceParseAst,
     prev_diagnostics: IndexMap<Uri, Vec<Diagnostic>>,
     compile_output: Option<CompileOutput>,
     cell: Option<String>,
     gui_client: Option<GuiClient>,
     editor_files: IndexMap<Uri, Document>,
+    /// GUI edit awaiting [`LangServer::resolve_edit_conflict`], if any.
+    pending_edit: Option<PendingEdit>,
+    /// Outputs of [`LangServer::sweep_cell`] reused across sweeps.
+    sweep_cache: SweepCache,
+    /// Parameter overrides of the cells of the workspace.
+    overrides: ParamOverrides,
+    /// Settings sent by the editor.
+    settings: Settings,
+}
+
+/// Converts the errors and DRC violations in `output` into LSP diagnostics for
+/// the files they occur in, which need not be open in the editor.
+///
+/// Errors without a span are reported at the start of `root_dir/lib.ar`.
+fn diagnostics(
+    ast: &WorkspaceParseAst,
+    root_dir: &Path,
+    output: &CompileOutput,
+) -> IndexMap<Uri, Vec<Diagnostic>> {
+    let mut diagnostics = IndexMap::new();
+    let errs = match output {
+        CompileOutput::FatalParseErrors => {
+            vec![(
+                Span {
+                    path: root_dir.join("lib.ar"),
+                    span: cfgrammar::Span::new(0, 0),
+                },
+                "fatal parse errors encountered, unable to compile".to_string(),
+                None,
+                DiagnosticSeverity::ERROR,
+                Vec::new(),
+            )]
+        }
+        CompileOutput::StaticErrors(StaticErrorCompileOutput { errors }) => errors
+            .iter()
+            .map(|e| {
+                (
+                    e.span.clone(),
+                    format!("{}", e.kind),
+                    Some(e.kind.code()),
+                    DiagnosticSeverity::ERROR,
+                    Vec::new(),
+                )
+            })
+            .collect(),
+        CompileOutput::ExecErrors(ExecErrorCompileOutput { errors, .. }) => errors
+            .iter()
+            .map(|e| {
+                (
+                    e.span.clone().unwrap_or_else(|| Span {
+                        path: root_dir.join("lib.ar"),
+                        span: cfgrammar::Span::new(0, 0),
+                    }),
+                    format!("{}", e.kind),
+                    Some(e.kind.code()),
+                    DiagnosticSeverity::ERROR,
+                    related(&e.kind),
+                )
+            })
+            .collect(),
+        CompileOutput::Valid(_) => vec![],
+    };
+    let violations = match output {
+        CompileOutput::Valid(data)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            output: Some(data), ..
+        }) => data.drc_violations.as_slice(),
+        _ => &[],
+    };
+    let violations = violations.iter().filter_map(|violation| {
+        Some((
+            violation.span.clone()?,
+            format!("{violation}"),
+            Some(violation.kind.name()),
+            DiagnosticSeverity::WARNING,
+            Vec::new(),
+        ))
+    });
+    let range = |span: &Span| {
+        let doc = Document::new(source::source_text(ast, &span.path)?, 0);
+        Some(Range {
+            start: doc.offset_to_pos(span.span.start()),
+            end: doc.offset_to_pos(span.span.end()),
+        })
+    };
+    for (span, message, code, severity, related) in errs.into_iter().chain(violations) {
+        let Some(range) = range(&span) else {
+            continue;
+        };
+        let related = related
+            .into_iter()
+            .filter_map(|(span, message)| {
+                Some(DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: Uri::from_file_path(&span.path)?,
+                        range: range(&span)?,
+                    },
+                    message,
+                })
+            })
+            .collect::<Vec<_>>();
+        diagnostics
+            .entry(Uri::from_file_path(&span.path).unwrap())
+            .or_insert_with(Vec::new)
+            .push(Diagnostic {
+                range,
+                severity: Some(severity),
+                code: code.map(|code| NumberOrString::String(code.to_string())),
+                message,
+                related_information: (!related.is_empty()).then_some(related),
+                ..Default::default()
+            });
+    }
+    diagnostics
+}
+
+/// Locations related to an error, such as the constraints that an inconsistent
+/// constraint conflicts with.
+fn related(kind: &ExecErrorKind) -> Vec<(Span, String)> {
+    match kind {
+        ExecErrorKind::InconsistentConstraint(_, conflicts) => conflicts
+            .iter()
+            .map(|c| {
+                (
+                    c.span.clone(),
+                    format!("conflicting constraint `{}`", c.source),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Converts the errors and DRC violations in `output`, along with the lints of
+/// `ast` and the geometry of `output`, into LSP diagnostics.
+///
+/// Lints are skipped if `output` has parse or static errors.
+fn workspace_diagnostics(
+    ast: &WorkspaceParseAst,
+    root_dir: &Path,
+    config: &Config,
+    output: Option<&CompileOutput>,
+) -> IndexMap<Uri, Vec<Diagnostic>> {
+    let mut diagnostics = if let Some(o) = output {
+        diagnostics(ast, root_dir, o)
+    } else {
+        IndexMap::new()
+    };
+    if matches!(
+        output,
+        Some(CompileOutput::FatalParseErrors | CompileOutput::StaticErrors(_))
+    ) {
+        return diagnostics;
+    }
+    for lint in lints(ast, config, output) {
+        let Some(ast) = ast.values().find(|ast| ast.path == lint.span.path) else {
+            continue;
+        };
+        let doc = Document::new(&ast.text, 0);
+        diagnostics
+            .entry(Uri::from_file_path(&lint.span.path).unwrap())
+            .or_default()
+            .push(Diagnostic {
+                range: Range {
+                    start: doc.offset_to_pos(lint.span.span.start()),
+                    end: doc.offset_to_pos(lint.span.span.end()),
+                },
+                severity: Some(match lint.level {
+                    LintLevel::Deny => DiagnosticSeverity::ERROR,
+                    _ => DiagnosticSeverity::WARNING,
+                }),
+                code: Some(NumberOrString::String(lint.rule.to_string())),
+                source: Some("argon lint".to_string()),
+                message: lint.message,
+                ..Default::default()
+            });
+    }
+    diagnostics
+}
+
+/// Lints `ast` and the geometry of `output`, if any.
+fn lints(ast: &WorkspaceParseAst, config: &Config, output: Option<&CompileOutput>) -> Vec<Lint> {
+    let mut linter = Linter::new(config, ast);
+    linter.check_source();
+    if let Some(
+        CompileOutput::Valid(data)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            output: Some(data), ..
+        }),
+    ) = output
+    {
+        linter.check_output(data);
+    }
+    linter.finish()
+}
+
+/// Returns the byte range of the identifier in `text` containing `offset`, if any.
+fn ident_range(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let start = text[..offset].rfind(|c| !is_ident(c)).map_or(0, |i| i + 1);
+    let end = text[offset..]
+        .find(|c| !is_ident(c))
+        .map_or(text.len(), |i| offset + i);
+    (start < end).then_some((start, end))
 }
 
 impl StateMut {
     fn diagnostics(&self) -> IndexMap<Uri, Vec<Diagnostic>> {
-        let mut diagnostics = IndexMap::new();
-        if let Some(o) = &self.compile_output {
-            let errs = match o {
-                CompileOutput::FatalParseErrors => {
-                    vec![(
-                        Span {
-                            path: self.root_dir.as_ref().unwrap().join("lib.ar"),
-                            span: cfgrammar::Span::new(0, 0),
-                        },
-                        "fatal parse errors encountered, unable to compile".to_string(),
-                    )]
-                }
-                CompileOutput::StaticErrors(StaticErrorCompileOutput { errors }) => errors
-                    .iter()
-                    .map(|e| (e.span.clone(), format!("{}", e.kind)))
-                    .collect(),
-                CompileOutput::ExecErrors(ExecErrorCompileOutput { errors, .. }) => errors
-                    .iter()
-                    .map(|e| {
-                        (
-                            e.span.clone().unwrap_or_else(|| Span {
-                                path: self.root_dir.as_ref().unwrap().join("lib.ar"),
-                                span: cfgrammar::Span::new(0, 0),
-                            }),
-                            format!("{}", e.kind),
-                        )
-                    })
-                    .collect(),
-                CompileOutput::Valid(_) => vec![],
-            };
-            for (span, message) in errs {
-                let url = Uri::from_file_path(&span.path).unwrap();
-                if let Some(ast) = self.ast.values().find(|ast| ast.path == span.path) {
-                    let doc = Document::new(&ast.text, 0);
-                    diagnostics
-                        .entry(url)
-                        .or_insert_with(Vec::new)
-                        .push(Diagnostic {
-                            range: Range {
-                                start: doc.offset_to_pos(span.span.start()),
-                                end: doc.offset_to_pos(span.span.end()),
-                            },
-                            severity: Some(DiagnosticSeverity::ERROR),
-                            message,
-                            ..Default::default()
-                        });
-                }
+        workspace_diagnostics(
+            &self.ast,
+            self.root_dir.as_ref().unwrap(),
+            &self.config.clone().unwrap_or_default(),
+            self.compile_output.as_ref(),
+        )
+    }
+
+    /// Publishes `diagnostics`, clearing those of files that had diagnostics
+    /// when they were last published.
+    async fn publish_diagnostics(
+        &mut self,
+        client: &Client,
+        mut diagnostics: IndexMap<Uri, Vec<Diagnostic>>,
+    ) {
+        let prev = std::mem::replace(&mut self.prev_diagnostics, diagnostics.clone());
+        for (uri, _) in prev {
+            diagnostics.entry(uri).or_default();
+        }
+        for (uri, diags) in diagnostics {
+            // TODO: potentially add version number
+            client.publish_diagnostics(uri, diags, None).await;
+        }
+    }
+
+    /// The layer properties file configured in `Argon.toml`, relative to `root_dir`.
+    fn lyp_path(&self, root_dir: &Path) -> PathBuf {
+        self.config.clone().unwrap_or_default().lyp_path(root_dir)
+    }
+
+    /// Collects the layer references in the workspace as of the last compile.
+    fn layer_usage(&self) -> Result<LayerUsage> {
+        let root_dir = self
+            .root_dir
+            .as_ref()
+            .ok_or_else(jsonrpc::Error::invalid_request)?;
+        LayerUsage::from_workspace(
+            root_dir,
+            &self.config.clone().unwrap_or_default(),
+            &self.ast,
+        )
+        .map_err(|e| jsonrpc::Error::invalid_params(format!("{e}")))
+    }
+
+    /// Returns the layer reference at `pos` in the document at `uri`.
+    ///
+    /// Returns `None` if the document has changed since the last compile,
+    /// since references are located in the compiled source.
+    fn layer_ref_at<'a>(
+        &self,
+        usage: &'a LayerUsage,
+        uri: &Uri,
+        pos: Position,
+    ) -> Option<&'a LayerRef> {
+        let path = PathBuf::from(uri.to_file_path()?);
+        let doc = self.editor_files.get(uri)?;
+        let ast = self.ast.values().find(|ast| ast.path == path)?;
+        if doc.contents() != ast.text {
+            return None;
+        }
+        usage.find(&path, doc.pos_to_offset(pos)?)
+    }
+
+    /// Returns the name and value of the constant named by the identifier at
+    /// `pos` in the document at `uri`.
+    fn const_at(&self, uri: &Uri, pos: Position) -> Option<(String, Constant)> {
+        let path = PathBuf::from(uri.to_file_path()?);
+        let doc = self.editor_files.get(uri)?;
+        let text = doc.contents();
+        let (start, end) = ident_range(text, doc.pos_to_offset(pos)?)?;
+        let name = &text[start..end];
+        let ast = self.ast.values().find(|ast| ast.path == path)?;
+        let (mut consts, _) = eval_consts(ast);
+        let constant = consts.swap_remove(name)?;
+        Some((name.to_string(), constant))
+    }
+
+    /// Builds an edit renaming the layer `from` to `to` everywhere it is referenced.
+    fn layer_rename_edit(&self, usage: &LayerUsage, from: &str, to: &str) -> Result<WorkspaceEdit> {
+        usage
+            .check_rename(from, to)
+            .map_err(|e| jsonrpc::Error::invalid_params(format!("{e}")))?;
+        let mut docs: HashMap<&Path, Document> = HashMap::new();
+        let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+        for r in usage.refs.iter().filter(|r| r.layer == from) {
+            let path = r.span.path.as_path();
+            if !docs.contains_key(path) {
+                let text = match self.ast.values().find(|ast| ast.path == path) {
+                    Some(ast) => ast.text.to_string(),
+                    None => std::fs::read_to_string(path)
+                        .map_err(|e| jsonrpc::Error::invalid_params(format!("{e}")))?,
+                };
+                docs.insert(path, Document::new(text, 0));
             }
+            changes
+                .entry(Uri::from_file_path(path).unwrap())
+                .or_default()
+                .push(TextEdit {
+                    range: docs[path].span_to_range(r.span.span),
+                    new_text: to.to_string(),
+                });
         }
-        diagnostics
+        Ok(WorkspaceEdit {
+            changes: Some(changes),
+            document_changes: None,
+            change_annotations: None,
+        })
     }
 
     async fn compile(&mut self, client: &Client, update: bool) {
         if let Some(root_dir) = &self.root_dir {
-            self.config = parse_config(root_dir.join("Argon.toml")).ok();
-            let lyp = self
-                .config
-                .as_ref()
-                .and_then(|config| {
-                    let lyp = config.lyp.as_ref()?;
-                    Some(if lyp.is_relative() {
-                        root_dir.join(lyp)
-                    } else {
-                        lyp.clone()
-                    })
-                })
-                .unwrap_or_else(|| {
-                    PathBuf::from(concat!(
-                        env!("CARGO_MANIFEST_DIR"),
-                        "/../../pdks/sky130/sky130.lyp"
-                    ))
-                });
+            let config_path = root_dir.join("Argon.toml");
+            if !config_path.exists() {
+                self.config = None;
+                self.config_error = None;
+            } else {
+                match parse_config(&config_path) {
+                    Ok(config) => {
+                        self.config = Some(config);
+                        self.config_error = None;
+                    }
+                    // The last valid configuration stays in use, and each new
+                    // error is shown once rather than on every compile.
+                    Err(e) => {
+                        let message = format!("Invalid Argon.toml: {e:#}");
+                        if self.config_error.as_ref() != Some(&message) {
+                            client
+                                .show_message(
+                                    MessageType::ERROR,
+                                    format!("{message}. Using the last valid configuration."),
+                                )
+                                .await;
+                        }
+                        self.config_error = Some(message);
+                    }
+                }
+            }
+            let lyp = self.lyp_path(root_dir);
             let parse_output = parse::parse_workspace_with_std(root_dir.join("lib.ar"));
             let parse_errs = parse_output.static_errors();
+            // ASTs of files with syntax errors are partial, so scopes in them
+            // are annotated once they parse again.
+            let broken: HashSet<PathBuf> = parse_output
+                .errs
+                .iter()
+                .filter(|(_, (errs, _))| !errs.is_empty())
+                .map(|(path, _)| path.clone())
+                .collect();
             let ast = parse_output.ast();
             self.ast = ast;
             let static_output = compile::static_compile(&self.ast);
             // If GUI is connected, must annotate scopes.
             if self.gui_client.is_some() {
                 let mut to_save = Vec::new();
+                let mut pending = Vec::new();
                 for (_, ast) in &self.ast {
+                    if broken.contains(&ast.path) {
+                        continue;
+                    }
                     let scope_annotation = ScopeAnnotationPass::new(ast);
                     let mut text_edits = scope_annotation.execute();
                     text_edits.sort_by_key(|edit| Reverse(edit.range.start));
                     if !text_edits.is_empty() {
+                        let changes = HashMap::from_iter([(
+                            Uri::from_file_path(&ast.path).unwrap(),
+                            text_edits,
+                        )]);
+                        let base = HashMap::from_iter([(
+                            Uri::from_file_path(&ast.path).unwrap(),
+                            ast.text.clone(),
+                        )]);
+                        pending.extend(journal::begin("annotate_scopes", &changes, &base));
+                        journal::record("annotate_scopes", &changes, &base);
                         client
                             .apply_edit(WorkspaceEdit {
-                                changes: Some(HashMap::from_iter([(
-                                    Uri::from_file_path(&ast.path).unwrap(),
-                                    text_edits,
-                                )])),
+                                changes: Some(changes),
                                 document_changes: None,
                                 change_annotations: None,
                             })
@@ -169,6 +479,7 @@ impl StateMut {
                 for path in to_save {
                     client.send_request::<ForceSave>(path).await.unwrap();
                 }
+                journal::finish(&pending);
                 if should_return {
                     return;
                 }
@@ -179,35 +490,88 @@ impl StateMut {
                     static_output.errors.extend(parse_errs);
                     Some(CompileOutput::StaticErrors(static_output))
                 } else if let Some(cell) = &self.cell {
-                    match parse::parse_cell(cell) {
-                        Ok(cell_ast) => {
-                            let cell_path = cell_ast
-                                .func
-                                .path
-                                .iter()
-                                .map(|ident| ident.name)
-                                .collect_vec();
-                            Some(compile::dynamic_compile(
-                                &ast,
-                                CompileInput {
-                                    cell: &cell_path,
-                                    args: cell_ast
-                                        .args
-                                        .posargs
-                                        .iter()
-                                        .map(|arg| match arg {
-                                            Expr::FloatLiteral(float_literal) => {
-                                                CellArg::Float(float_literal.value)
-                                            }
-                                            Expr::IntLiteral(int_literal) => {
-                                                CellArg::Int(int_literal.value)
-                                            }
-                                            _ => panic!("must be int or float literal for now"),
-                                        })
-                                        .collect(),
-                                    lyp_file: &lyp,
-                                },
-                            ))
+                    let cell = match self.overridden_cell(cell) {
+                        Ok(cell) => cell,
+                        Err(e) => {
+                            client
+                                .show_message(
+                                    MessageType::WARNING,
+                                    format!("Parameter overrides not applied: {e}"),
+                                )
+                                .await;
+                            cell.clone()
+                        }
+                    };
+                    let solver = self
+                        .config
+                        .as_ref()
+                        .map(|config| config.solver.clone())
+                        .unwrap_or_default();
+                    match compile::dynamic_compile_invocation(&ast, &cell, &lyp, &solver) {
+                        Ok(mut output) => {
+                            if let Some(config) = &self.config {
+                                output.set_dbu(config.dbu);
+                                output.check_density(&config.density);
+                                output.extract_nets(&config.connect);
+                                output.set_grid(config.grid);
+                                match config.drc_rules(root_dir) {
+                                    Ok(rules) => output.check_drc(&rules),
+                                    Err(e) => {
+                                        client
+                                            .show_message(
+                                                MessageType::WARNING,
+                                                format!("DRC rules not checked: {e:#}"),
+                                            )
+                                            .await;
+                                    }
+                                }
+                            }
+                            if let CompileOutput::Valid(data) = &output {
+                                if !data.density_violations.is_empty() {
+                                    client
+                                        .show_message(
+                                            MessageType::WARNING,
+                                            format!(
+                                                "{} density violation(s), first: {}",
+                                                data.density_violations.len(),
+                                                data.density_violations[0]
+                                            ),
+                                        )
+                                        .await;
+                                }
+                                let warnings = data
+                                    .nets
+                                    .as_ref()
+                                    .map(|nets| nets.warnings())
+                                    .unwrap_or_default();
+                                if !warnings.is_empty() {
+                                    client
+                                        .show_message(
+                                            MessageType::WARNING,
+                                            format!(
+                                                "{} connectivity warning(s), first: {}",
+                                                warnings.len(),
+                                                warnings[0]
+                                            ),
+                                        )
+                                        .await;
+                                }
+                                // Checked against the database unit of exported layouts.
+                                let off_grid = data.off_grid_rotations(data.dbu);
+                                if !off_grid.is_empty() {
+                                    client
+                                        .show_message(
+                                            MessageType::WARNING,
+                                            format!(
+                                                "{} rotated shape(s) off-grid, first: {}",
+                                                off_grid.len(),
+                                                off_grid[0]
+                                            ),
+                                        )
+                                        .await;
+                                }
+                            }
+                            Some(output)
                         }
                         Err(e) => {
                             client
@@ -226,26 +590,50 @@ impl StateMut {
                 Some(CompileOutput::FatalParseErrors)
             };
             self.compile_output = o;
-            let mut tmp = self.diagnostics();
-            let mut diagnostics = tmp.clone();
-            std::mem::swap(&mut self.prev_diagnostics, &mut tmp);
-            for (path, _) in tmp {
-                diagnostics.entry(path).or_default();
-            }
-            for (uri, diags) in diagnostics {
-                // TODO: potentially add version number
-                client.publish_diagnostics(uri, diags, None).await;
+            self.publish_diagnostics(client, self.diagnostics()).await;
+            if self.settings.inlay_hints.enable {
+                let _ = client.inlay_hint_refresh().await;
             }
+            let read_only = self
+                .compile_output
+                .as_ref()
+                .is_some_and(|o| self.is_output_read_only(o));
+            let overridden_params = self.overridden_params().join(",");
             if let Some(o) = &self.compile_output
                 && let Some(gui_client) = self.gui_client.as_mut()
-                && let Err(e) = gui_client
-                    .open_cell(context::current(), o.clone(), update)
-                    .await
             {
-                client
-                    .show_message(MessageType::ERROR, format!("{e}"))
-                    .await;
-                self.gui_client = None;
+                session::record_with(session::Channel::GuiOut, || rpc::GuiRequest::Set {
+                    key: PARAM_OVERRIDES_KEY.to_string(),
+                    value: overridden_params.clone(),
+                });
+                if let Err(e) = gui_client
+                    .set(
+                        context::current(),
+                        PARAM_OVERRIDES_KEY.to_string(),
+                        overridden_params,
+                    )
+                    .await
+                {
+                    client
+                        .show_message(MessageType::ERROR, format!("{e}"))
+                        .await;
+                    self.gui_client = None;
+                    return;
+                }
+                session::record_with(session::Channel::GuiOut, || rpc::GuiRequest::OpenCell {
+                    cell: o.clone(),
+                    update,
+                    read_only,
+                });
+                if let Err(e) = gui_client
+                    .open_cell(context::current(), o.clone(), update, read_only)
+                    .await
+                {
+                    client
+                        .show_message(MessageType::ERROR, format!("{e}"))
+                        .await;
+                    self.gui_client = None;
+                }
             }
         }
     }
@@ -256,6 +644,9 @@ pub struct State {
     server_addr: SocketAddr,
     editor_client: Client,
     state_mut: Arc<Mutex<StateMut>>,
+    /// Incremented on every edit and save so that only the latest
+    /// [`live::check`] publishes diagnostics.
+    edit_generation: Arc<AtomicUsize>,
 }
 
 impl State {
@@ -264,6 +655,7 @@ impl State {
             server_addr,
             editor_client,
             state_mut: Default::default(),
+            edit_generation: Default::default(),
         }
     }
 }
@@ -307,9 +699,16 @@ impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
         #[allow(deprecated)]
         {
-            self.state.state_mut.lock().await.root_dir = params
+            let mut state_mut = self.state.state_mut.lock().await;
+            state_mut.root_dir = params
                 .root_uri
                 .map(|root| PathBuf::from(root.to_file_path().unwrap()));
+            if let Some(root_dir) = &state_mut.root_dir {
+                state_mut.overrides = ParamOverrides::load(root_dir);
+            }
+            if let Some(settings) = params.initialization_options.and_then(Settings::from_value) {
+                state_mut.settings = settings;
+            }
         }
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
@@ -321,6 +720,23 @@ impl LanguageServer for Backend {
                         ..Default::default()
                     },
                 )),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(vec![":".to_string(), "\"".to_string()]),
+                    ..Default::default()
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![LAYER_USAGE_COMMAND.to_string()],
+                    ..Default::default()
+                }),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
                 ..Default::default()
             },
             ..Default::default()
@@ -332,13 +748,26 @@ impl LanguageServer for Backend {
             .editor_client
             .log_message(MessageType::INFO, "server initialized!")
             .await;
+        self.recover_pending_edits().await;
         self.compile().await;
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let mut state_mut = self.state.state_mut.lock().await;
-        let doc = Document::new(params.text_document.text, params.text_document.version);
-        state_mut.editor_files.insert(params.text_document.uri, doc);
+        let compiled = {
+            let mut state_mut = self.state.state_mut.lock().await;
+            let path = params.text_document.uri.to_file_path().map(PathBuf::from);
+            // Diagnostics of files in the workspace are published by every
+            // compile, so only recompile if the file changed since the last.
+            let compiled = state_mut.ast.values().any(|ast| {
+                Some(&ast.path) == path.as_ref() && *ast.text == *params.text_document.text
+            });
+            let doc = Document::new(params.text_document.text, params.text_document.version);
+            state_mut.editor_files.insert(params.text_document.uri, doc);
+            compiled
+        };
+        if !compiled {
+            self.compile().await;
+        }
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -356,15 +785,29 @@ impl LanguageServer for Backend {
                     .collect(),
                 params.text_document.version,
             );
+            live::schedule(&self.state);
         } else {
             // optional: log error, or handle missing document
         }
     }
 
     async fn did_save(&self, _: DidSaveTextDocumentParams) {
+        live::cancel(&self.state);
         self.compile().await;
     }
 
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let Some(settings) = Settings::from_value(params.settings) else {
+            return;
+        };
+        let mut state_mut = self.state.state_mut.lock().await;
+        if state_mut.settings != settings {
+            state_mut.settings = settings;
+            drop(state_mut);
+            let _ = self.state.editor_client.inlay_hint_refresh().await;
+        }
+    }
+
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let mut state_mut = self.state.state_mut.lock().await;
         state_mut
@@ -372,6 +815,154 @@ impl LanguageServer for Backend {
             .swap_remove(&params.text_document.uri);
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let mut actions = vec![CodeActionOrCommand::Command(
+            tower_lsp_server::ls_types::Command {
+                title: "List layer usage".to_string(),
+                command: LAYER_USAGE_COMMAND.to_string(),
+                arguments: None,
+            },
+        )];
+        if let Ok(usage) = state_mut.layer_usage()
+            && let Some(r) =
+                state_mut.layer_ref_at(&usage, &params.text_document.uri, params.range.start)
+        {
+            actions.push(CodeActionOrCommand::Command(
+                tower_lsp_server::ls_types::Command {
+                    title: format!("Show usages of layer `{}`", r.layer),
+                    command: LAYER_USAGE_COMMAND.to_string(),
+                    arguments: Some(vec![r.layer.clone().into()]),
+                },
+            ));
+        }
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<LSPAny>> {
+        if params.command != LAYER_USAGE_COMMAND {
+            return Err(jsonrpc::Error::method_not_found());
+        }
+        let usage = self.state.state_mut.lock().await.layer_usage()?;
+        let report = match params.arguments.first().and_then(|layer| layer.as_str()) {
+            Some(layer) => LayerUsage {
+                refs: usage
+                    .refs
+                    .into_iter()
+                    .filter(|r| r.layer == layer)
+                    .collect(),
+            },
+            None => usage,
+        }
+        .to_string();
+        self.state
+            .editor_client
+            .show_message(MessageType::INFO, report)
+            .await;
+        Ok(None)
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let Ok(usage) = state_mut.layer_usage() else {
+            return Ok(None);
+        };
+        let Some(r) = state_mut.layer_ref_at(&usage, &params.text_document.uri, params.position)
+        else {
+            return Ok(None);
+        };
+        let doc = &state_mut.editor_files[&params.text_document.uri];
+        Ok(Some(PrepareRenameResponse::RangeWithPlaceholder {
+            range: doc.span_to_range(r.span.span),
+            placeholder: r.layer.clone(),
+        }))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let position = params.text_document_position;
+        let usage = state_mut.layer_usage()?;
+        let Some(r) =
+            state_mut.layer_ref_at(&usage, &position.text_document.uri, position.position)
+        else {
+            return Ok(None);
+        };
+        state_mut
+            .layer_rename_edit(&usage, &r.layer, &params.new_name)
+            .map(Some)
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let position = params.text_document_position_params;
+        let Some((name, constant)) =
+            state_mut.const_at(&position.text_document.uri, position.position)
+        else {
+            return Ok(state_mut
+                .solved_at(&position.text_document.uri, position.position)
+                .map(|value| Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value,
+                    }),
+                    range: None,
+                }));
+        };
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!(
+                    "```argon\nconst {name}: {:?} = {};\n```",
+                    constant.value.ty(),
+                    constant.value
+                ),
+            }),
+            range: None,
+        }))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let position = params.text_document_position;
+        Ok(state_mut
+            .completions(&position.text_document.uri, position.position)
+            .map(CompletionResponse::Array))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let position = params.text_document_position_params;
+        Ok(state_mut
+            .definition(&position.text_document.uri, position.position)
+            .map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let state_mut = self.state.state_mut.lock().await;
+        let position = params.text_document_position;
+        Ok(state_mut.references(
+            &position.text_document.uri,
+            position.position,
+            params.context.include_declaration,
+        ))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let state_mut = self.state.state_mut.lock().await;
+        if !state_mut.settings.inlay_hints.enable {
+            return Ok(None);
+        }
+        Ok(Some(
+            state_mut.inlay_hints(&params.text_document.uri, params.range),
+        ))
+    }
+
     async fn shutdown(&self) -> Result<()> {
         if let Some(gui) = self.state.state_mut.lock().await.gui.as_mut() {
             let _ = gui.kill().await;
@@ -390,6 +981,25 @@ struct SetParams {
     kv: String,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileCellParams {
+    cell: String,
+    /// Omits the compiled output from the result, returning only diagnostics.
+    #[serde(default)]
+    summary: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileCellResult {
+    /// Whether the cell compiled without errors.
+    valid: bool,
+    diagnostics: IndexMap<Uri, Vec<Diagnostic>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<CompileOutput>,
+}
+
 impl Backend {
     async fn start_gui(&self) -> Result<()> {
         let mut state_mut = self.state.state_mut.lock().await;
@@ -398,6 +1008,7 @@ impl Backend {
                 .editor_client
                 .show_message(MessageType::LOG, "Attempting to contact existing GUI...")
                 .await;
+            session::record_with(session::Channel::GuiOut, || rpc::GuiRequest::Activate {});
             if gui_client.activate(context::current()).await.is_ok() {
                 self.state
                     .editor_client
@@ -414,22 +1025,47 @@ impl Backend {
             let _ = gui.kill().await;
         }
 
+        let config = state_mut
+            .root_dir
+            .as_ref()
+            .and_then(|root_dir| parse_config(root_dir.join("Argon.toml")).ok())
+            .or_else(|| state_mut.config.clone())
+            .unwrap_or_default();
+        let gui_binary = match find_gui_binary(&config.gui, state_mut.root_dir.as_deref()) {
+            Ok(gui_binary) => gui_binary,
+            Err(e) => {
+                error!("{e}");
+                self.state
+                    .editor_client
+                    .show_message(MessageType::ERROR, format!("Failed to start the GUI: {e}"))
+                    .await;
+                return Ok(());
+            }
+        };
+
         self.state
             .editor_client
-            .show_message(MessageType::LOG, "Starting the GUI...")
+            .show_message(
+                MessageType::LOG,
+                format!("Starting the GUI from `{}`...", gui_binary.display()),
+            )
             .await;
         let state = self.state.clone();
 
+        let mut command = Command::new(&gui_binary);
+        command.arg(format!("{}", state.server_addr));
+        if let Some(display) = config.gui.display {
+            command.arg("--display").arg(display.to_string());
+        }
+        if let Some([x, y]) = config.gui.position {
+            command.arg("--position").arg(format!("{x},{y}"));
+        }
         tokio::spawn(async move {
-            match Command::new(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/../../target/release/gui"
-            ))
-            .arg(format!("{}", state.server_addr))
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
+            match command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
             {
                 Ok(mut child) => {
                     if let Some(stdout) = child.stdout.take() {
@@ -458,7 +1094,19 @@ impl Backend {
                     }
                     state.state_mut.lock().await.gui = Some(child);
                 }
-                Err(_) => todo!(),
+                Err(e) => {
+                    error!("failed to spawn GUI `{}`: {e}", gui_binary.display());
+                    state
+                        .editor_client
+                        .show_message(
+                            MessageType::ERROR,
+                            format!(
+                                "Failed to start the GUI from `{}`: {e}",
+                                gui_binary.display()
+                            ),
+                        )
+                        .await;
+                }
             }
         });
 
@@ -472,6 +1120,58 @@ impl Backend {
         state_mut.compile(&self.state.editor_client, false).await;
     }
 
+    /// Restores GUI edits that a crash kept from being saved to the workspace,
+    /// and tells the user which files were restored.
+    async fn recover_pending_edits(&self) {
+        let Some(root_dir) = self.state.state_mut.lock().await.root_dir.clone() else {
+            return;
+        };
+        let Some(pending) = journal::pending_path() else {
+            return;
+        };
+        let client = &self.state.editor_client;
+        match journal::recover(&pending, &root_dir) {
+            Ok(recovery) => {
+                let names = |paths: &[PathBuf]| {
+                    paths
+                        .iter()
+                        .map(|path| path.strip_prefix(&root_dir).unwrap_or(path).display())
+                        .join(", ")
+                };
+                if !recovery.restored.is_empty() {
+                    client
+                        .show_message(
+                            MessageType::INFO,
+                            format!(
+                                "Restored unsaved GUI edits to {}.",
+                                names(&recovery.restored)
+                            ),
+                        )
+                        .await;
+                }
+                if !recovery.conflicts.is_empty() {
+                    client
+                        .show_message(
+                            MessageType::WARNING,
+                            format!(
+                                "Could not restore unsaved GUI edits to {}, which changed since.",
+                                names(&recovery.conflicts)
+                            ),
+                        )
+                        .await;
+                }
+            }
+            Err(e) => {
+                client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!("Failed to recover unsaved GUI edits: {e}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
     /// Compiles the current workspace and the open cell if it exists.
     async fn compile(&self) {
         let mut state_mut = self.state.state_mut.lock().await;
@@ -491,6 +1191,55 @@ impl Backend {
         Ok(())
     }
 
+    /// Compiles a cell without opening it, for use by external tools.
+    ///
+    /// The open cell, diagnostics, and GUI are left untouched.
+    async fn compile_cell_request(&self, params: CompileCellParams) -> Result<CompileCellResult> {
+        let (ast, root_dir, lyp, config) = {
+            let state_mut = self.state.state_mut.lock().await;
+            let Some(root_dir) = state_mut.root_dir.clone() else {
+                return Err(jsonrpc::Error::invalid_request());
+            };
+            let lyp = state_mut.lyp_path(&root_dir);
+            let config = state_mut.config.clone().unwrap_or_default();
+            (state_mut.ast.clone(), root_dir, lyp, config)
+        };
+        tokio::task::spawn_blocking(move || {
+            let mut output = match compile::static_compile(&ast) {
+                Some((static_ast, static_output)) if static_output.errors.is_empty() => {
+                    compile::dynamic_compile_invocation(
+                        &static_ast,
+                        &params.cell,
+                        &lyp,
+                        &config.solver,
+                    )
+                    .map_err(|e| jsonrpc::Error::invalid_params(format!("{e}")))?
+                }
+                Some((_, static_output)) => CompileOutput::StaticErrors(static_output),
+                None => CompileOutput::FatalParseErrors,
+            };
+            output.set_dbu(config.dbu);
+            output.check_density(&config.density);
+            output.extract_nets(&config.connect);
+            output.set_grid(config.grid);
+            let rules = config
+                .drc_rules(&root_dir)
+                .map_err(|e| jsonrpc::Error::invalid_params(format!("{e:#}")))?;
+            output.check_drc(&rules);
+            Ok(CompileCellResult {
+                valid: matches!(output, CompileOutput::Valid(_)),
+                diagnostics: diagnostics(&ast, &root_dir, &output),
+                output: (!params.summary).then_some(output),
+            })
+        })
+        .await
+        .map_err(|e| jsonrpc::Error {
+            code: jsonrpc::ErrorCode::InternalError,
+            message: format!("{e}").into(),
+            data: None,
+        })?
+    }
+
     async fn set(&self, params: SetParams) -> Result<()> {
         let state = self.state.clone();
         // TODO: Error handling.
@@ -498,14 +1247,30 @@ impl Backend {
         let (k, v) = (k.to_string(), v.to_string());
         tokio::spawn(async move {
             let mut state_mut = state.state_mut.lock().await;
-            if let Some(client) = state_mut.gui_client.as_mut()
-                && let Err(e) = client.set(context::current(), k, v).await
-            {
-                state
-                    .editor_client
-                    .show_message(MessageType::ERROR, format!("{e}"))
-                    .await;
-                state_mut.gui_client = None;
+            if let Some(param) = k.strip_prefix(PARAM_KEY_PREFIX) {
+                match state_mut.set_param_override(param, &v) {
+                    Ok(()) => state_mut.compile(&state.editor_client, true).await,
+                    Err(e) => {
+                        state
+                            .editor_client
+                            .show_message(MessageType::ERROR, e)
+                            .await;
+                    }
+                }
+                return;
+            }
+            if let Some(client) = state_mut.gui_client.as_mut() {
+                session::record_with(session::Channel::GuiOut, || rpc::GuiRequest::Set {
+                    key: k.clone(),
+                    value: v.clone(),
+                });
+                if let Err(e) = client.set(context::current(), k, v).await {
+                    state
+                        .editor_client
+                        .show_message(MessageType::ERROR, format!("{e}"))
+                        .await;
+                    state_mut.gui_client = None;
+                }
             }
         });
         Ok(())
@@ -516,6 +1281,23 @@ async fn spawn(fut: impl Future<Output = ()> + Send + 'static) {
     tokio::spawn(fut);
 }
 
+/// Builds the LSP service, with [`State`] shared between the editor and the
+/// GUI server at `server_addr`.
+pub(crate) fn build_service(server_addr: SocketAddr) -> (LspService<Backend>, ClientSocket, State) {
+    let mut ext_state = None;
+    let (service, socket) = LspService::build(|client| {
+        let state = State::new(server_addr, client);
+        ext_state = Some(state.clone());
+        Backend { state }
+    })
+    .custom_method("custom/startGui", Backend::start_gui)
+    .custom_method("custom/openCell", Backend::open_cell)
+    .custom_method("custom/set", Backend::set)
+    .custom_method("custom/compileCell", Backend::compile_cell_request)
+    .finish();
+    (service, socket, ext_state.unwrap())
+}
+
 pub async fn main() {
     // Start server for communication with GUI.
     let port = std::env::var("ARGON_LANG_SERVER_DEFAULT_PORT")
@@ -523,11 +1305,11 @@ pub async fn main() {
         .and_then(|p| p.parse::<u16>().ok())
         .unwrap_or(12345);
     let mut listener = if let Ok(listener) =
-        tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, port), Json::default).await
+        tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, port), rpc_codec).await
     {
         listener
     } else {
-        tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, 0), Json::default)
+        tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, 0), rpc_codec)
             .await
             .unwrap()
     };
@@ -537,17 +1319,7 @@ pub async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let mut ext_state = None;
-    let (service, socket) = LspService::build(|client| {
-        let state = State::new(server_addr, client);
-        ext_state = Some(state.clone());
-        Backend { state }
-    })
-    .custom_method("custom/startGui", Backend::start_gui)
-    .custom_method("custom/openCell", Backend::open_cell)
-    .custom_method("custom/set", Backend::set)
-    .finish();
-    let state = ext_state.unwrap();
+    let (service, socket, state) = build_service(server_addr);
     listener.config_mut().max_frame_length(usize::MAX);
     let state_clone = state.clone();
     tokio::spawn(async move {
@@ -559,7 +1331,16 @@ pub async fn main() {
             .max_channels_per_key(1, |t| t.transport().peer_addr().unwrap().ip())
             // serve is generated by the service attribute. It takes as input any type implementing
             // the generated World trait.
-            .map(|channel| channel.execute(state_clone.clone().serve()).for_each(spawn))
+            .map(|channel| {
+                channel
+                    .execute(
+                        state_clone
+                            .clone()
+                            .serve()
+                            .before(session::record_gui_request),
+                    )
+                    .for_each(spawn)
+            })
             // Max 10 channels.
             .buffer_unordered(10)
             .for_each(|_| async {})
@@ -583,6 +1364,18 @@ pub async fn main() {
             .init();
     }
 
+    if let Ok(path) = std::env::var(session::RECORD_ENV) {
+        if let Err(e) = session::start_recording(path.as_ref()) {
+            error!("failed to record session to {path}: {e}");
+        }
+    }
+
     // Start actual LSP server.
-    Server::new(stdin, stdout, socket).serve(service).await;
+    Server::new(
+        Tap::new(stdin, session::Channel::LspIn),
+        Tap::new(stdout, session::Channel::LspOut),
+        socket,
+    )
+    .serve(service)
+    .await;
 }
@@ -0,0 +1,176 @@
+//! Cell listings for the GUI's cell library browser.
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+use argon_geometry::Bbox;
+use compiler::{
+    ast::{Decl, Span, WorkspaceAst},
+    compile::{self, CompileInput, CompileOutput, ExecErrorCompileOutput, VarIdTyMetadata},
+    config::{SolverConfig, Target},
+    parse::WorkspaceParseAst,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Maximum number of rects drawn in a thumbnail; the rest are dropped.
+pub const MAX_THUMBNAIL_RECTS: usize = 2048;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CellInfo {
+    /// The path used to open or instantiate the cell (e.g. `inv` or `stdcells::inv`).
+    pub name: String,
+    pub span: Span,
+    pub num_params: usize,
+    /// Invocations of the cell declared as build targets in `Argon.toml`.
+    pub targets: Vec<String>,
+    /// Only available for cells without parameters that compile successfully.
+    pub thumbnail: Option<Thumbnail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub bbox: Bbox,
+    pub rects: Vec<ThumbnailRect>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailRect {
+    pub bbox: Bbox,
+    /// Fill color of the rect's layer as `0xRRGGBB`.
+    pub color: u32,
+}
+
+/// Lists the cells declared in `ast`, rendering thumbnails with the layers in `lyp`
+/// and noting which cells are invoked by `targets`.
+///
+/// Thumbnails are cached as JSON in `cache_dir`, keyed by the workspace source
+/// and solver options, so that only cells in edited workspaces are recompiled.
+pub fn list_cells(
+    ast: &WorkspaceParseAst,
+    lyp: &Path,
+    targets: &[Target],
+    solver: &SolverConfig,
+    cache_dir: Option<&Path>,
+) -> Vec<CellInfo> {
+    let static_ast = compile::static_compile(ast)
+        .and_then(|(ast, output)| output.errors.is_empty().then_some(ast));
+    let mut workspace_hash = DefaultHasher::new();
+    for (mod_path, ast) in ast {
+        mod_path.hash(&mut workspace_hash);
+        ast.text.hash(&mut workspace_hash);
+    }
+    lyp.hash(&mut workspace_hash);
+    format!("{solver:?}").hash(&mut workspace_hash);
+
+    let mut cells = Vec::new();
+    for (mod_path, mod_ast) in ast {
+        for decl in &mod_ast.ast.decls {
+            let Decl::Cell(cell) = decl else {
+                continue;
+            };
+            let path = mod_path
+                .iter()
+                .map(String::as_str)
+                .chain([cell.name.name.as_str()])
+                .collect::<Vec<_>>();
+            let name = path.join("::");
+            let thumbnail = if cell.args.is_empty()
+                && let Some(static_ast) = &static_ast
+            {
+                let mut s = workspace_hash.clone();
+                name.hash(&mut s);
+                let cache_file = cache_dir.map(|dir| dir.join(format!("{:016x}.json", s.finish())));
+                cached_thumbnail(cache_file.as_deref(), || {
+                    render_thumbnail(static_ast, &path, lyp, solver)
+                })
+            } else {
+                None
+            };
+            let cell_targets = targets
+                .iter()
+                .filter(|target| target.cell_path() == name)
+                .map(|target| target.cell.clone())
+                .collect();
+            cells.push(CellInfo {
+                name,
+                span: Span {
+                    path: mod_ast.path.clone(),
+                    span: cell.span,
+                },
+                num_params: cell.args.len(),
+                targets: cell_targets,
+                thumbnail,
+            });
+        }
+    }
+    cells
+}
+
+fn cached_thumbnail(
+    cache_file: Option<&Path>,
+    render: impl FnOnce() -> Option<Thumbnail>,
+) -> Option<Thumbnail> {
+    if let Some(cached) = cache_file
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+    {
+        return cached;
+    }
+    let thumbnail = render();
+    if let Some(file) = cache_file {
+        let res = file
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                std::fs::write(file, serde_json::to_string(&thumbnail).unwrap_or_default())
+            });
+        if let Err(e) = res {
+            warn!("failed to cache thumbnail at {file:?}: {e}");
+        }
+    }
+    thumbnail
+}
+
+fn render_thumbnail(
+    ast: &WorkspaceAst<VarIdTyMetadata>,
+    cell: &[&str],
+    lyp: &Path,
+    solver: &SolverConfig,
+) -> Option<Thumbnail> {
+    let output = match compile::dynamic_compile(
+        ast,
+        CompileInput {
+            cell,
+            args: Vec::new(),
+            lyp_file: lyp,
+            solver: solver.clone(),
+        },
+    ) {
+        CompileOutput::Valid(output)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            output: Some(output),
+            ..
+        }) => output,
+        _ => return None,
+    };
+    let bbox = output.cell_bbox(output.top)?.bbox();
+    let rects = output
+        .iter_flat(output.top, None)
+        .filter_map(|rect| {
+            let layer = output
+                .layers
+                .layers
+                .iter()
+                .find(|layer| Some(layer.name.as_str()) == rect.layer)?;
+            let color = layer.fill_color;
+            Some(ThumbnailRect {
+                bbox: rect.rect.bbox(),
+                color: ((color.r as u32) << 16) | ((color.g as u32) << 8) | color.b as u32,
+            })
+        })
+        .take(MAX_THUMBNAIL_RECTS)
+        .collect();
+    Some(Thumbnail { bbox, rects })
+}
@@ -0,0 +1,156 @@
+//! Diagnostics of unsaved editor buffers.
+//!
+//! Edits are checked once typing pauses for [`DEBOUNCE`], by compiling the
+//! workspace with open buffers in place of the files on disk. Checks only
+//! publish diagnostics: the compiled output, the GUI, and the ASTs used by
+//! other requests are left as of the last save.
+//!
+//! Every edit and save increments [`State::edit_generation`], which cancels
+//! checks scheduled by earlier edits. The solver cannot be interrupted, so a
+//! stale check that has already started runs to completion, but its
+//! diagnostics are dropped.
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use compiler::{
+    compile::{self, CompileOutput},
+    config::Config,
+    parse::{self, FileProvider, OsFiles},
+};
+use indexmap::IndexMap;
+use tower_lsp_server::ls_types::{Diagnostic, Uri};
+
+use crate::{State, StateMut, workspace_diagnostics};
+
+/// Time without edits after which the workspace is checked.
+pub(crate) const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Files of the workspace, with the contents of open editor buffers in place
+/// of the files on disk.
+struct EditorFiles {
+    buffers: HashMap<PathBuf, String>,
+}
+
+impl FileProvider for EditorFiles {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        match self.buffers.get(path) {
+            Some(text) => Ok(text.clone()),
+            None => OsFiles.read_to_string(path),
+        }
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.buffers.contains_key(path) || OsFiles.is_file(path)
+    }
+}
+
+/// The state needed to check the workspace, copied so that the check runs
+/// without holding the lock on [`StateMut`].
+struct Snapshot {
+    root_dir: PathBuf,
+    lyp: PathBuf,
+    config: Config,
+    cell: Option<String>,
+    files: EditorFiles,
+}
+
+impl StateMut {
+    fn snapshot(&self) -> Option<Snapshot> {
+        let root_dir = self.root_dir.clone()?;
+        let buffers = self
+            .editor_files
+            .iter()
+            .filter_map(|(uri, doc)| {
+                Some((
+                    PathBuf::from(uri.to_file_path()?),
+                    doc.contents().to_string(),
+                ))
+            })
+            .collect();
+        Some(Snapshot {
+            lyp: self.lyp_path(&root_dir),
+            config: self.config.clone().unwrap_or_default(),
+            cell: self
+                .cell
+                .as_ref()
+                .map(|cell| self.overridden_cell(cell).unwrap_or_else(|_| cell.clone())),
+            files: EditorFiles { buffers },
+            root_dir,
+        })
+    }
+}
+
+impl Snapshot {
+    /// Compiles the workspace and the open cell, if any, returning their
+    /// diagnostics.
+    fn check(self) -> IndexMap<Uri, Vec<Diagnostic>> {
+        let parse_output =
+            parse::parse_workspace_with_std_in(self.root_dir.join("lib.ar"), &self.files);
+        let parse_errs = parse_output.static_errors();
+        let ast = parse_output.ast();
+        let output = match compile::static_compile(&ast) {
+            Some((_, mut static_output))
+                if !static_output.errors.is_empty() || !parse_errs.is_empty() =>
+            {
+                static_output.errors.extend(parse_errs);
+                Some(CompileOutput::StaticErrors(static_output))
+            }
+            Some((static_ast, _)) => self.cell.as_ref().and_then(|cell| {
+                let mut output = compile::dynamic_compile_invocation(
+                    &static_ast,
+                    cell,
+                    &self.lyp,
+                    &self.config.solver,
+                )
+                .ok()?;
+                output.set_dbu(self.config.dbu);
+                output.check_density(&self.config.density);
+                output.extract_nets(&self.config.connect);
+                output.set_grid(self.config.grid);
+                if let Ok(rules) = self.config.drc_rules(&self.root_dir) {
+                    output.check_drc(&rules);
+                }
+                Some(output)
+            }),
+            None => Some(CompileOutput::FatalParseErrors),
+        };
+        workspace_diagnostics(&ast, &self.root_dir, &self.config, output.as_ref())
+    }
+}
+
+/// Checks the workspace once there have been no edits for [`DEBOUNCE`],
+/// cancelling checks scheduled by earlier edits.
+pub(crate) fn schedule(state: &State) {
+    let generation = state.edit_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let edit_generation = state.edit_generation.clone();
+    let current = move || edit_generation.load(Ordering::SeqCst) == generation;
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(DEBOUNCE).await;
+        if !current() {
+            return;
+        }
+        let Some(snapshot) = state.state_mut.lock().await.snapshot() else {
+            return;
+        };
+        let Ok(diagnostics) = tokio::task::spawn_blocking(move || snapshot.check()).await else {
+            return;
+        };
+        let mut state_mut = state.state_mut.lock().await;
+        if current() {
+            state_mut
+                .publish_diagnostics(&state.editor_client, diagnostics)
+                .await;
+        }
+    });
+}
+
+/// Cancels scheduled checks, e.g. before a save recompiles the workspace.
+pub(crate) fn cancel(state: &State) {
+    state.edit_generation.fetch_add(1, Ordering::SeqCst);
+}
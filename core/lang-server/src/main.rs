@@ -1,4 +1,21 @@
+use std::path::Path;
+
 #[tokio::main]
 async fn main() {
+    // `lang-server --replay <session> [out]` replays a recorded session instead
+    // of serving an editor over stdio.
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let [flag, session, out @ ..] = args.as_slice()
+        && flag == "--replay"
+        && out.len() <= 1
+    {
+        if let Err(e) =
+            lang_server::session::replay(Path::new(session), out.first().map(Path::new)).await
+        {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
     lang_server::main().await;
 }
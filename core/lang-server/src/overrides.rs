@@ -0,0 +1,142 @@
+//! Session-wide overrides of the parameters of the open cell.
+//!
+//! Overrides are set with `custom/set` keys of the form `param.<name>` or the
+//! GUI's `:override` command, and replace the arguments of the open cell's
+//! invocation on every compile. They are stored per workspace and cell
+//! generator in `overrides.json` in the Argon home directory, so they persist
+//! across sessions.
+use std::path::{Path, PathBuf};
+
+use compiler::overrides::{invocation_name, override_invocation};
+use indexmap::IndexMap;
+use tracing::warn;
+
+use crate::{StateMut, config::default_argon_home};
+
+/// Prefix of the `custom/set` keys that set parameter overrides.
+pub(crate) const PARAM_KEY_PREFIX: &str = "param.";
+
+/// [`Gui::set`](crate::rpc::Gui::set) key listing the overridden parameters of
+/// the open cell, separated by commas.
+pub(crate) const PARAM_OVERRIDES_KEY: &str = "paramOverrides";
+
+/// Value that clears a parameter override.
+pub(crate) const CLEAR_VALUE: &str = "default";
+
+/// Parameter values of each workspace, keyed by workspace root, cell
+/// generator, and parameter name.
+type OverridesFile = IndexMap<PathBuf, IndexMap<String, IndexMap<String, String>>>;
+
+/// Path of the file overrides are persisted to, if the Argon home directory is known.
+fn overrides_path() -> Option<PathBuf> {
+    Some(default_argon_home()?.join("overrides.json"))
+}
+
+fn read_overrides(path: &Path) -> OverridesFile {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Parameter overrides of the cells of a workspace.
+#[derive(Debug, Default)]
+pub(crate) struct ParamOverrides {
+    root_dir: PathBuf,
+    cells: IndexMap<String, IndexMap<String, String>>,
+}
+
+impl ParamOverrides {
+    /// Loads the overrides persisted for the workspace at `root_dir`.
+    pub(crate) fn load(root_dir: &Path) -> Self {
+        let cells = overrides_path()
+            .map(|path| read_overrides(&path))
+            .and_then(|mut file| file.swap_remove(root_dir))
+            .unwrap_or_default();
+        Self {
+            root_dir: root_dir.to_path_buf(),
+            cells,
+        }
+    }
+
+    /// Overrides of the parameters of the cell generator `cell`, by name.
+    pub(crate) fn get(&self, cell: &str) -> IndexMap<String, String> {
+        self.cells.get(cell).cloned().unwrap_or_default()
+    }
+
+    /// Sets parameter `param` of the cell generator `cell` to `value`, or
+    /// clears its override if `value` is `None`, and persists the change.
+    pub(crate) fn set(&mut self, cell: &str, param: &str, value: Option<String>) {
+        let params = self.cells.entry(cell.to_string()).or_default();
+        match value {
+            Some(value) => {
+                params.insert(param.to_string(), value);
+            }
+            None => {
+                params.shift_remove(param);
+            }
+        }
+        if params.is_empty() {
+            self.cells.shift_remove(cell);
+        }
+        if let Err(e) = self.save() {
+            warn!("failed to persist parameter overrides: {e}");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = overrides_path() else {
+            return Ok(());
+        };
+        let mut file = read_overrides(&path);
+        if self.cells.is_empty() {
+            file.shift_remove(&self.root_dir);
+        } else {
+            file.insert(self.root_dir.clone(), self.cells.clone());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&file)?)
+    }
+}
+
+impl StateMut {
+    /// Returns `cell` with the overrides of its cell generator's parameters
+    /// applied.
+    pub(crate) fn overridden_cell(&self, cell: &str) -> Result<String, String> {
+        let overrides = self
+            .overrides
+            .get(&invocation_name(cell).map_err(|e| format!("{e:#}"))?);
+        override_invocation(&self.ast, cell, &overrides).map_err(|e| format!("{e:#}"))
+    }
+
+    /// Names of the overridden parameters of the open cell.
+    pub(crate) fn overridden_params(&self) -> Vec<String> {
+        self.cell
+            .as_deref()
+            .and_then(|cell| invocation_name(cell).ok())
+            .map(|name| self.overrides.get(&name).into_keys().collect())
+            .unwrap_or_default()
+    }
+
+    /// Overrides parameter `param` of the open cell with `value`, or clears
+    /// the override if `value` is [`CLEAR_VALUE`].
+    ///
+    /// The override is checked against the open cell's invocation before it
+    /// is stored.
+    pub(crate) fn set_param_override(&mut self, param: &str, value: &str) -> Result<(), String> {
+        let Some(cell) = &self.cell else {
+            return Err("no cell is open".to_string());
+        };
+        let name = invocation_name(cell).map_err(|e| format!("{e:#}"))?;
+        let mut overrides = self.overrides.get(&name);
+        let value = (value != CLEAR_VALUE).then(|| value.to_string());
+        if let Some(value) = &value {
+            overrides.insert(param.to_string(), value.clone());
+            override_invocation(&self.ast, cell, &overrides).map_err(|e| format!("{e:#}"))?;
+        }
+        self.overrides.set(&name, param, value);
+        Ok(())
+    }
+}
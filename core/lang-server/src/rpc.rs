@@ -1,18 +1,126 @@
-use std::{collections::HashMap, net::SocketAddr};
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
 
+use arcstr::{ArcStr, Substr};
 use compiler::{
-    ast::Span,
-    compile::{BasicRect, CompileOutput},
+    ast::{AstMetadata, Expr, Scope, Span, Statement, UnaryOp, annotated::AnnotatedAst},
+    compile::{BasicRect, CompileOutput, ExecErrorCompileOutput},
+    config::Config,
+    consts::{ConstValue, eval_consts},
+    gds::{GdsMap, ImportedShape},
+    graph::Provenance,
+    parse::WorkspaceParseAst,
+    schema::check_schema_version,
+    sweep::{SweepCache, SweepFrame, sweep_invocations, sweep_values},
 };
 
+use bytes::{Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
-use tarpc::tokio_serde::formats::Json;
+use tarpc::tokio_serde::{
+    Deserializer, Serializer,
+    formats::{Bincode, Json},
+};
 use tower_lsp_server::ls_types::{
-    Diagnostic, DiagnosticSeverity, MessageType, Position, Range, ShowDocumentParams, TextEdit,
-    Uri, WorkspaceEdit,
+    Diagnostic, DiagnosticSeverity, DocumentChanges, MessageType, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, Range, ShowDocumentParams, TextDocumentEdit,
+    TextEdit, Uri, WorkspaceEdit,
+};
+
+use crate::{
+    ForceSave, Redo, State, Undo,
+    config::default_argon_home,
+    document::Document,
+    journal,
+    library::{self, CellInfo},
 };
 
-use crate::{ForceSave, Redo, State, Undo, document::Document};
+/// Environment variable choosing the [`RpcFormat`] between the language server
+/// and the GUI.
+pub const RPC_FORMAT_ENV: &str = "ARGON_RPC_FORMAT";
+
+/// Wire format of the RPCs between the language server and the GUI.
+///
+/// Compile output of large cells runs to megabytes of JSON, so bincode is the
+/// default; setting [`RPC_FORMAT_ENV`] to `json` keeps messages readable for
+/// debugging. The GUI inherits the variable from the language server that
+/// starts it, so both ends agree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RpcFormat {
+    #[default]
+    Bincode,
+    Json,
+}
+
+impl RpcFormat {
+    pub fn from_env() -> Self {
+        match std::env::var(RPC_FORMAT_ENV).as_deref() {
+            Ok("json") => RpcFormat::Json,
+            _ => RpcFormat::Bincode,
+        }
+    }
+}
+
+/// A tarpc codec in either [`RpcFormat`].
+pub enum RpcCodec<Item, SinkItem> {
+    Bincode(Bincode<Item, SinkItem>),
+    Json(Json<Item, SinkItem>),
+}
+
+/// Returns a codec in the [`RpcFormat`] chosen by the environment, for
+/// connecting or listening with `tarpc::serde_transport::tcp`.
+pub fn rpc_codec<Item, SinkItem>() -> RpcCodec<Item, SinkItem> {
+    match RpcFormat::from_env() {
+        RpcFormat::Bincode => RpcCodec::Bincode(Bincode::default()),
+        RpcFormat::Json => RpcCodec::Json(Json::default()),
+    }
+}
+
+impl<Item: Unpin, SinkItem: Serialize + Unpin> Serializer<SinkItem> for RpcCodec<Item, SinkItem> {
+    type Error = io::Error;
+
+    fn serialize(self: Pin<&mut Self>, item: &SinkItem) -> Result<Bytes, Self::Error> {
+        match self.get_mut() {
+            RpcCodec::Bincode(codec) => Pin::new(codec).serialize(item).map_err(io::Error::other),
+            RpcCodec::Json(codec) => Pin::new(codec).serialize(item).map_err(io::Error::other),
+        }
+    }
+}
+
+impl<Item: for<'de> Deserialize<'de> + Unpin, SinkItem: Unpin> Deserializer<Item>
+    for RpcCodec<Item, SinkItem>
+{
+    type Error = io::Error;
+
+    fn deserialize(self: Pin<&mut Self>, src: &BytesMut) -> Result<Item, Self::Error> {
+        match self.get_mut() {
+            RpcCodec::Bincode(codec) => Pin::new(codec).deserialize(src).map_err(io::Error::other),
+            RpcCodec::Json(codec) => Pin::new(codec).deserialize(src).map_err(io::Error::other),
+        }
+    }
+}
+
+/// Prefix of lines commented out by [`LangServer::relax_constraint`].
+pub const RELAXED_MARKER: &str = "// relaxed: ";
+
+/// Number of times a GUI edit is re-sent after the editor rejects it even
+/// though the buffer contents still match, e.g. because a save bumped the
+/// document version in between.
+const EDIT_RETRIES: usize = 2;
+
+/// A GUI edit that could not be applied because an editor buffer no longer
+/// matches the text the edit was computed against.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingEdit {
+    action: String,
+    changes: HashMap<Uri, Vec<TextEdit>>,
+    /// Contents of each edited file that `changes` was computed against.
+    base: HashMap<Uri, ArcStr>,
+}
 
 /// A single source rewrite: replace the text at `span` with `value`. Used to
 /// persist solution-space-exploration drags by updating initial-condition
@@ -40,15 +148,133 @@ pub enum LangServerAction {
     Redo,
 }
 
+/// Compiles `cell` with its `param`th argument set to each of `values`.
+fn compile_sweep(
+    ast: &WorkspaceParseAst,
+    cell: &str,
+    param: usize,
+    values: &[f64],
+    lyp: &Path,
+    config: &Config,
+    cache: &mut SweepCache,
+) -> Result<Vec<SweepFrame>, String> {
+    let invocations = sweep_invocations(cell, param, values).map_err(|e| format!("{e:#}"))?;
+    let Some((static_ast, static_output)) = compiler::compile::static_compile(ast) else {
+        return Err("fatal parse errors encountered, unable to compile".to_string());
+    };
+    if !static_output.errors.is_empty() {
+        return Err(format!(
+            "{} static error(s) encountered, unable to compile",
+            static_output.errors.len()
+        ));
+    }
+    cache
+        .compile(
+            &static_ast,
+            &invocations,
+            values,
+            lyp,
+            &config.solver,
+            |output| {
+                output.set_dbu(config.dbu);
+                output.check_density(&config.density);
+                output.extract_nets(&config.connect);
+                output.set_grid(config.grid);
+            },
+        )
+        .map_err(|e| format!("{e:#}"))
+}
+
 #[tarpc::service]
 pub trait LangServer {
-    async fn register(addr: SocketAddr);
+    /// Connects to the GUI serving at `addr`.
+    ///
+    /// Fails if the GUI reads a different [`compiler::schema::SCHEMA_VERSION`]
+    /// of compile output.
+    async fn register(addr: SocketAddr, schema_version: u32) -> Result<(), String>;
     async fn select_rect(span: Span);
-    async fn draw_rect(scope_span: Span, var_name: String, rect: BasicRect<f64>) -> Option<Span>;
-    async fn draw_dimension(scope_span: Span, params: DimensionParams) -> Option<Span>;
+    /// Opens the source file containing `span` in the editor with `span` selected.
+    async fn reveal(span: Span);
+    /// Inserts a rect into the scope at `scope_span`.
+    ///
+    /// Each coordinate carries its numeric value and, if it was snapped to an
+    /// existing edge, an expression referencing that edge (e.g. `rect0.x1`),
+    /// which is emitted as a hard constraint in place of the value. If `size`
+    /// is provided, the rect's width and height are emitted as hard
+    /// constraints instead of its upper-right corner.
+    async fn draw_rect(
+        scope_span: Span,
+        var_name: String,
+        rect: BasicRect<(f64, Option<String>)>,
+        size: Option<(f64, f64)>,
+    ) -> Option<Span>;
+    /// Inserts a dimension into the scope at `scope_span`.
+    ///
+    /// If a dimension between the same edges already exists in the scope, or
+    /// a constant in the same module has the dimension's value, the value is
+    /// replaced by that dimension's value expression or the constant's name.
+    ///
+    /// Returns the span of the inserted dimension and its value expression.
+    async fn draw_dimension(scope_span: Span, params: DimensionParams) -> Option<(Span, String)>;
     async fn edit_dimension(span: Span, value: String) -> Option<Span>;
     async fn update_values(edits: Vec<ValueEdit>);
+    /// Moves the rect or instance created by the call at `span` by `(dx, dy)`,
+    /// rewriting all of its position arguments in a single edit.
+    ///
+    /// Returns the span of the call after the edit is applied.
+    async fn translate(span: Span, dx: f64, dy: f64) -> Option<Span>;
+    /// Replaces the `index`th argument of the cell generator call instantiated
+    /// by the `inst(...)` call at `span` with `value`.
+    ///
+    /// Returns the span of the `inst(...)` call after the edit is applied, or
+    /// `None` if the instantiated cell is not a direct call.
+    async fn set_instance_param(span: Span, index: usize, value: String) -> Option<Span>;
+    /// Deletes the statement that creates the object at `span`.
+    async fn delete_object(span: Span);
+    /// Comments out the `eq` or `dimension` statement containing `span` so that
+    /// the cell recompiles without it.
+    ///
+    /// Returns `false` if `span` does not lie in such a statement on its own lines.
+    async fn relax_constraint(span: Span) -> bool;
+    /// Uncomments every statement commented out by `relax_constraint`.
+    ///
+    /// Returns the number of lines restored.
+    async fn restore_constraints() -> usize;
     async fn add_eq_constraint(scope_span: Span, lhs: String, rhs: String);
+    /// Inserts an instance of `cell` with its origin at `(x, y)` into the
+    /// scope at `scope_span`.
+    async fn place_instance(
+        scope_span: Span,
+        var_name: String,
+        cell: String,
+        x: f64,
+        y: f64,
+    ) -> Option<Span>;
+    /// Lists the cells in the workspace along with their thumbnails.
+    async fn list_cells() -> Vec<CellInfo>;
+    /// Resolves the edit held back by the last [`Gui::edit_conflict`], either
+    /// by reapplying it on top of the current editor buffers or by discarding it.
+    ///
+    /// Returns whether the edit was applied.
+    async fn resolve_edit_conflict(reapply: bool) -> bool;
+    /// Imports the flattened top cell of the GDS at `path` for display as a
+    /// read-only underlay. Relative paths are resolved against the workspace root.
+    async fn load_underlay(path: PathBuf) -> Result<Vec<ImportedShape>, String>;
+    /// Compiles the open cell once for each of `steps` evenly spaced values of
+    /// its `param`th argument, from `from` to `to`, without opening the results.
+    async fn sweep_cell(
+        param: usize,
+        from: f64,
+        to: f64,
+        steps: usize,
+    ) -> Result<Vec<SweepFrame>, String>;
+    /// Overrides parameter `param` of the open cell with `value` for this and
+    /// later sessions, or clears the override if `value` is `default`, and
+    /// recompiles.
+    async fn set_param_override(param: String, value: String) -> Result<(), String>;
+    /// Explains the `x0`, `y0`, `x1`, and `y1` of the rect created at `span`
+    /// in the last compile output by the constraints that fixed them.
+    async fn provenance(span: Span) -> Result<Vec<Provenance>, String>;
     async fn open_cell(cell: String);
     async fn show_message(typ: MessageType, message: String);
     async fn dispatch_action(action: LangServerAction);
@@ -56,15 +282,32 @@ pub trait LangServer {
 
 #[tarpc::service]
 pub trait Gui {
-    async fn open_cell(cell: CompileOutput, update: bool);
+    /// Displays `cell`. If `read_only` is set, the cell is defined in a
+    /// read-only dependency or the standard library and cannot be edited.
+    async fn open_cell(cell: CompileOutput, update: bool, read_only: bool);
     async fn set(key: String, value: String);
     async fn activate();
+    /// Notifies the GUI that edit `action` conflicts with changes made in the
+    /// editor and is waiting on [`LangServer::resolve_edit_conflict`].
+    async fn edit_conflict(action: String);
 }
 
 impl LangServer for State {
-    async fn register(self, _: tarpc::context::Context, addr: SocketAddr) -> () {
+    async fn register(
+        self,
+        _: tarpc::context::Context,
+        addr: SocketAddr,
+        schema_version: u32,
+    ) -> Result<(), String> {
+        if let Err(e) = check_schema_version(schema_version) {
+            let message = format!("failed to connect to the GUI: {e}");
+            self.editor_client
+                .show_message(MessageType::ERROR, &message)
+                .await;
+            return Err(message);
+        }
         let gui_client = {
-            let mut transport = tarpc::serde_transport::tcp::connect(addr, Json::default);
+            let mut transport = tarpc::serde_transport::tcp::connect(addr, rpc_codec);
             transport.config_mut().max_frame_length(usize::MAX);
 
             GuiClient::new(tarpc::client::Config::default(), transport.await.unwrap()).spawn()
@@ -72,27 +315,43 @@ impl LangServer for State {
         let mut state_mut = self.state_mut.lock().await;
         state_mut.gui_client = Some(gui_client);
         state_mut.compile(&self.editor_client, false).await;
+        Ok(())
     }
 
     async fn select_rect(self, _: tarpc::context::Context, span: Span) {
         // TODO: check that vim file is in sync with GUI file.
         let state_mut = self.state_mut.lock().await;
-        if let Some(ast) = state_mut.ast.values().find(|ast| ast.path == span.path) {
-            let doc = Document::new(&ast.text, 0);
-            let url = Uri::from_file_path(&span.path).unwrap();
-            let diagnostics = vec![Diagnostic {
-                range: Range {
-                    start: doc.offset_to_pos(span.span.start()),
-                    end: doc.offset_to_pos(span.span.end()),
-                },
-                severity: Some(DiagnosticSeverity::INFORMATION),
-                message: "selected rect".to_string(),
-                ..Default::default()
-            }];
-            self.editor_client
-                .publish_diagnostics(url, diagnostics, None)
+        let Some(file) = state_mut.source_file(&span.path) else {
+            return;
+        };
+        let is_open = state_mut.is_open(&file.uri);
+        drop(state_mut);
+        let diagnostics = vec![Diagnostic {
+            range: file.doc.span_to_range(span.span),
+            severity: Some(DiagnosticSeverity::INFORMATION),
+            message: "selected rect".to_string(),
+            ..Default::default()
+        }];
+        // Diagnostics in files that are not open are only listed in the
+        // workspace diagnostics, so open the file without taking focus.
+        if !is_open {
+            self.show_source(file.uri.clone(), None, false, file.read_only)
                 .await;
         }
+        self.editor_client
+            .publish_diagnostics(file.uri, diagnostics, None)
+            .await;
+    }
+
+    async fn reveal(self, _: tarpc::context::Context, span: Span) {
+        let state_mut = self.state_mut.lock().await;
+        let Some(file) = state_mut.source_file(&span.path) else {
+            return;
+        };
+        drop(state_mut);
+        let selection = file.doc.span_to_range(span.span);
+        self.show_source(file.uri, Some(selection), true, file.read_only)
+            .await;
     }
 
     async fn draw_rect(
@@ -100,154 +359,42 @@ impl LangServer for State {
         _: tarpc::context::Context,
         scope_span: Span,
         var_name: String,
-        rect: BasicRect<f64>,
+        rect: BasicRect<(f64, Option<String>)>,
+        size: Option<(f64, f64)>,
     ) -> Option<Span> {
-        let state_mut = self.state_mut.lock().await;
-
-        if state_mut.ast.values().any(|ast| {
-            state_mut
-                .editor_files
-                .get(&Uri::from_file_path(&ast.path).unwrap())
-                .map(|file| file.contents() != ast.text)
-                .unwrap_or_default()
-        }) {
-            self.editor_client
-                .show_message(
-                    MessageType::ERROR,
-                    "Editor buffer state is inconsistent with GUI state.",
-                )
-                .await;
-            return None;
-        }
-
-        let url = Uri::from_file_path(&scope_span.path).unwrap();
-
-        if let Some(ast) = state_mut
-            .ast
-            .values()
-            .find(|ast| ast.path == scope_span.path)
-            && let Some(scope) = ast.span2scope.get(&scope_span)
-        {
-            let doc = Document::new(&ast.text, 0);
-            let format_rect = |rect: &BasicRect<f64>| {
-                format!(
-                    "rect({}x0i = {}, y0i = {}, x1i = {}, y1i = {})",
-                    rect.layer
-                        .as_ref()
-                        .map(|layer| format!("\"{layer}\", "))
-                        .unwrap_or_default(),
-                    rect.x0,
-                    rect.y0,
-                    rect.x1,
-                    rect.y1,
-                )
-            };
-            let (edit, span) = if let Some(tail) = &scope.tail {
-                let start = doc.offset_to_pos(tail.span().start());
-                let prefix = format!("let {var_name} = ");
-                let rect_str = format_rect(&rect);
-                (
-                    TextEdit {
-                        range: Range::new(start, start),
-                        new_text: format!(
-                            "{prefix}{rect_str}!;\n{}",
-                            // TODO: handle different types of indentation, or enforce that gui
-                            // reformats file before editing.
-                            std::iter::repeat_n(' ', start.character as usize).collect::<String>()
-                        ),
-                    },
-                    Span {
-                        path: scope_span.path.clone(),
-                        span: cfgrammar::Span::new(
-                            tail.span().start() + prefix.len(),
-                            tail.span().start() + prefix.len() + rect_str.len(),
-                        ),
-                    },
-                )
+        let format_coord = |name: &str, (value, expr): &(f64, Option<String>)| {
+            if let Some(expr) = expr {
+                format!("{name} = {expr}")
             } else {
-                let start = doc.offset_to_pos(scope.span.start());
-                let stop = doc.offset_to_pos(scope.span.end());
-                let line = doc.substr(Position::new(stop.line, 0)..stop);
-                let trimmed = line.trim_start();
-                let whitespace = &line[..line.len() - trimmed.len()];
-                let insert_loc = doc.offset_to_pos(scope.span.end() - 1);
-                let prefix = format!(
-                    "{}let {var_name} = ",
-                    if start.line != stop.line {
-                        "    "
-                    } else {
-                        "\n"
-                    }
-                );
-                let rect_str = format_rect(&rect);
-                (
-                    TextEdit {
-                        range: Range::new(insert_loc, insert_loc),
-                        new_text: format!("{prefix}{rect_str}!;\n{whitespace}",),
-                    },
-                    Span {
-                        path: scope_span.path.clone(),
-                        span: cfgrammar::Span::new(
-                            scope.span.end() - 1 + prefix.len(),
-                            scope.span.end() - 1 + prefix.len() + rect_str.len(),
-                        ),
-                    },
-                )
-            };
-
-            self.editor_client
-                .show_document(ShowDocumentParams {
-                    uri: url.clone(),
-                    external: None,
-                    take_focus: None,
-                    selection: None,
-                })
-                .await
-                .unwrap();
-
-            self.editor_client
-                .apply_edit(WorkspaceEdit {
-                    changes: Some(HashMap::from_iter([(url, vec![edit])])),
-                    document_changes: None,
-                    change_annotations: None,
-                })
-                .await
-                .unwrap();
-
-            self.editor_client
-                .send_request::<ForceSave>(scope_span.path.clone())
-                .await
-                .unwrap();
-            Some(span)
+                format!("{name}i = {value}")
+            }
+        };
+        let layer = rect
+            .layer
+            .as_ref()
+            .map(|layer| format!("\"{layer}\", "))
+            .unwrap_or_default();
+        let x0 = format_coord("x0", &rect.x0);
+        let y0 = format_coord("y0", &rect.y0);
+        let rect_str = if let Some((w, h)) = size {
+            format!("rect({layer}{x0}, {y0}, w = {w:?}, h = {h:?})")
         } else {
-            None
-        }
+            let x1 = format_coord("x1", &rect.x1);
+            let y1 = format_coord("y1", &rect.y1);
+            format!("rect({layer}{x0}, {y0}, {x1}, {y1})")
+        };
+        self.insert_binding("draw_rect", &scope_span, &var_name, &rect_str)
+            .await
     }
 
     async fn draw_dimension(
         self,
         _: tarpc::context::Context,
         scope_span: Span,
-        params: DimensionParams,
-    ) -> Option<Span> {
+        mut params: DimensionParams,
+    ) -> Option<(Span, String)> {
         let state_mut = self.state_mut.lock().await;
 
-        if state_mut.ast.values().any(|ast| {
-            state_mut
-                .editor_files
-                .get(&Uri::from_file_path(&ast.path).unwrap())
-                .map(|file| file.contents() != ast.text)
-                .unwrap_or_default()
-        }) {
-            self.editor_client
-                .show_message(
-                    MessageType::ERROR,
-                    "Editor buffer state is inconsistent with GUI state.",
-                )
-                .await;
-            return None;
-        }
-
         let url = Uri::from_file_path(&scope_span.path).unwrap();
 
         if let Some(ast) = state_mut
@@ -256,6 +403,9 @@ impl LangServer for State {
             .find(|ast| ast.path == scope_span.path)
             && let Some(scope) = ast.span2scope.get(&scope_span)
         {
+            if let Some(value) = symbolic_dimension_value(ast, scope, &params) {
+                params.value = value;
+            }
             let doc = Document::new(&ast.text, 0);
             let format_dimension = |params: &DimensionParams| {
                 format!(
@@ -329,20 +479,14 @@ impl LangServer for State {
                 .await
                 .unwrap();
 
-            self.editor_client
-                .apply_edit(WorkspaceEdit {
-                    changes: Some(HashMap::from_iter([(url, vec![edit])])),
-                    document_changes: None,
-                    change_annotations: None,
-                })
+            drop(state_mut);
+            if !self
+                .apply_edits("draw_dimension", HashMap::from_iter([(url, vec![edit])]))
                 .await
-                .unwrap();
-
-            self.editor_client
-                .send_request::<ForceSave>(scope_span.path.clone())
-                .await
-                .unwrap();
-            Some(span)
+            {
+                return None;
+            }
+            Some((span, params.value))
         } else {
             None
         }
@@ -356,22 +500,6 @@ impl LangServer for State {
     ) -> Option<Span> {
         let state_mut = self.state_mut.lock().await;
 
-        if state_mut.ast.values().any(|ast| {
-            state_mut
-                .editor_files
-                .get(&Uri::from_file_path(&ast.path).unwrap())
-                .map(|file| file.contents() != ast.text)
-                .unwrap_or_default()
-        }) {
-            self.editor_client
-                .show_message(
-                    MessageType::ERROR,
-                    "Editor buffer state is inconsistent with GUI state.",
-                )
-                .await;
-            return None;
-        }
-
         let url = Uri::from_file_path(&span.path).unwrap();
 
         if let Some(ast) = state_mut.ast.values().find(|ast| ast.path == span.path)
@@ -380,7 +508,11 @@ impl LangServer for State {
             let doc = Document::new(&ast.text, 0);
             let start = doc.offset_to_pos(c.args.posargs[2].span().start());
             let stop = doc.offset_to_pos(c.args.posargs[2].span().end());
-            let value_len = value.len();
+            let value_start = c.args.posargs[2].span().start();
+            let new_span = Span {
+                path: span.path.clone(),
+                span: cfgrammar::Span::new(value_start, value_start + value.len()),
+            };
             let edit = TextEdit {
                 range: Range::new(start, stop),
                 new_text: value,
@@ -396,27 +528,15 @@ impl LangServer for State {
                 .await
                 .unwrap();
 
-            self.editor_client
-                .apply_edit(WorkspaceEdit {
-                    changes: Some(HashMap::from_iter([(url, vec![edit])])),
-                    document_changes: None,
-                    change_annotations: None,
-                })
+            drop(state_mut);
+            if !self
+                .apply_edits("edit_dimension", HashMap::from_iter([(url, vec![edit])]))
                 .await
-                .unwrap();
-
-            self.editor_client
-                .send_request::<ForceSave>(span.path.clone())
-                .await
-                .unwrap();
+            {
+                return None;
+            }
 
-            Some(Span {
-                path: span.path.clone(),
-                span: cfgrammar::Span::new(
-                    c.args.posargs[2].span().start(),
-                    c.args.posargs[2].span().start() + value_len,
-                ),
-            })
+            Some(new_span)
         } else {
             None
         }
@@ -426,73 +546,243 @@ impl LangServer for State {
     /// then saves (triggering recompilation). Used to persist SSE drags so the
     /// dragged layout survives recompilation instead of snapping back.
     async fn update_values(self, _: tarpc::context::Context, edits: Vec<ValueEdit>) -> () {
-        if edits.is_empty() {
-            return;
+        self.apply_value_edits("update_values", edits).await;
+    }
+
+    async fn translate(
+        self,
+        _: tarpc::context::Context,
+        span: Span,
+        dx: f64,
+        dy: f64,
+    ) -> Option<Span> {
+        let (edits, new_end) = {
+            let state_mut = self.state_mut.lock().await;
+            let ast = state_mut.ast.values().find(|ast| ast.path == span.path)?;
+            let call = ast.span2call.get(&span)?;
+            let mut new_end = span.span.end();
+            let edits = call
+                .args
+                .kwargs
+                .iter()
+                .filter_map(|kwarg| {
+                    let delta = match kwarg.name.name.as_str() {
+                        "x" | "xi" | "x0" | "x0i" | "x1" | "x1i" => dx,
+                        "y" | "yi" | "y0" | "y0i" | "y1" | "y1i" => dy,
+                        _ => return None,
+                    };
+                    if delta == 0. {
+                        return None;
+                    }
+                    let value_span = kwarg.value.span();
+                    let value = translate_expr(
+                        &kwarg.value,
+                        &ast.text[value_span.start()..value_span.end()],
+                        delta,
+                    );
+                    new_end = new_end + value.len() - (value_span.end() - value_span.start());
+                    Some(ValueEdit {
+                        span: Span {
+                            path: span.path.clone(),
+                            span: value_span,
+                        },
+                        value,
+                    })
+                })
+                .collect::<Vec<_>>();
+            (edits, new_end)
+        };
+
+        if !self.apply_value_edits("translate", edits).await {
+            return None;
         }
-        let state_mut = self.state_mut.lock().await;
+        Some(Span {
+            path: span.path.clone(),
+            span: cfgrammar::Span::new(span.span.start(), new_end),
+        })
+    }
 
-        if state_mut.ast.values().any(|ast| {
-            state_mut
-                .editor_files
-                .get(&Uri::from_file_path(&ast.path).unwrap())
-                .map(|file| file.contents() != ast.text)
-                .unwrap_or_default()
-        }) {
-            self.editor_client
-                .show_message(
-                    MessageType::ERROR,
-                    "Editor buffer state is inconsistent with GUI state.",
-                )
-                .await;
-            return;
+    async fn set_instance_param(
+        self,
+        _: tarpc::context::Context,
+        span: Span,
+        index: usize,
+        value: String,
+    ) -> Option<Span> {
+        let (edit, new_end) = {
+            let state_mut = self.state_mut.lock().await;
+            let ast = state_mut.ast.values().find(|ast| ast.path == span.path)?;
+            let call = ast.span2call.get(&span)?;
+            let Some(Expr::Call(cell)) = call.args.posargs.first() else {
+                return None;
+            };
+            let arg_span = cell.args.posargs.get(index)?.span();
+            let new_end = span.span.end() + value.len() - (arg_span.end() - arg_span.start());
+            (
+                ValueEdit {
+                    span: Span {
+                        path: span.path.clone(),
+                        span: arg_span,
+                    },
+                    value,
+                },
+                new_end,
+            )
+        };
+
+        if !self
+            .apply_value_edits("set_instance_param", vec![edit])
+            .await
+        {
+            return None;
         }
+        Some(Span {
+            path: span.path.clone(),
+            span: cfgrammar::Span::new(span.span.start(), new_end),
+        })
+    }
 
-        // Build one WorkspaceEdit grouping all rewrites per file. Edits within a
-        // file are sorted by descending start offset so they can be applied
-        // back-to-front without invalidating each other's offsets.
-        let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
-        let mut offsets: HashMap<Uri, Vec<usize>> = HashMap::new();
-        let mut paths: Vec<std::path::PathBuf> = Vec::new();
-        for ValueEdit { span, value } in edits {
-            let url = Uri::from_file_path(&span.path).unwrap();
-            if let Some(ast) = state_mut.ast.values().find(|ast| ast.path == span.path) {
-                let doc = Document::new(&ast.text, 0);
-                let start = doc.offset_to_pos(span.span.start());
-                let stop = doc.offset_to_pos(span.span.end());
-                changes.entry(url.clone()).or_default().push(TextEdit {
-                    range: Range::new(start, stop),
-                    new_text: value,
-                });
-                offsets.entry(url).or_default().push(span.span.start());
-                if !paths.contains(&span.path) {
-                    paths.push(span.path.clone());
-                }
+    async fn delete_object(self, _: tarpc::context::Context, span: Span) {
+        let edit = {
+            let state_mut = self.state_mut.lock().await;
+            let Some(ast) = state_mut.ast.values().find(|ast| ast.path == span.path) else {
+                return;
+            };
+            // Delete the innermost statement enclosing the object, along with its
+            // trailing semicolon and, if it sits on its own line, the whole line.
+            let Some(stmt) = ast
+                .span2scope
+                .values()
+                .flat_map(|scope| &scope.stmts)
+                .filter_map(|stmt| match stmt {
+                    Statement::LetBinding(binding) => Some(binding.span),
+                    Statement::Expr { value, .. } => Some(value.span()),
+                    Statement::ForLoop(_) => None,
+                })
+                .filter(|stmt| stmt.start() <= span.span.start() && span.span.end() <= stmt.end())
+                .min_by_key(|stmt| stmt.end() - stmt.start())
+            else {
+                return;
+            };
+            let text = ast.text.as_bytes();
+            let mut start = stmt.start();
+            while start > 0 && matches!(text[start - 1], b' ' | b'\t') {
+                start -= 1;
             }
-        }
-        if changes.is_empty() {
-            return;
-        }
-        for (url, edits) in changes.iter_mut() {
-            let starts = &offsets[url];
-            let mut idx: Vec<usize> = (0..edits.len()).collect();
-            idx.sort_by(|&a, &b| starts[b].cmp(&starts[a]));
-            *edits = idx.into_iter().map(|i| edits[i].clone()).collect();
-        }
+            let mut end = stmt.end();
+            if text.get(end) == Some(&b';') {
+                end += 1;
+            }
+            while matches!(text.get(end), Some(b' ' | b'\t')) {
+                end += 1;
+            }
+            if (start == 0 || text[start - 1] == b'\n') && text.get(end) == Some(&b'\n') {
+                end += 1;
+            } else {
+                start = stmt.start();
+            }
+            ValueEdit {
+                span: Span {
+                    path: span.path.clone(),
+                    span: cfgrammar::Span::new(start, end),
+                },
+                value: String::new(),
+            }
+        };
+        self.apply_value_edits("delete_object", vec![edit]).await;
+    }
 
-        self.editor_client
-            .apply_edit(WorkspaceEdit {
-                changes: Some(changes),
-                document_changes: None,
-                change_annotations: None,
-            })
-            .await
-            .unwrap();
+    async fn relax_constraint(self, _: tarpc::context::Context, span: Span) -> bool {
+        let edits = {
+            let state_mut = self.state_mut.lock().await;
+            let Some(ast) = state_mut.ast.values().find(|ast| ast.path == span.path) else {
+                return false;
+            };
+            let Some(stmt) = ast
+                .span2scope
+                .values()
+                .flat_map(|scope| &scope.stmts)
+                .filter_map(|stmt| match stmt {
+                    Statement::Expr {
+                        value: Expr::Call(call),
+                        ..
+                    } if call.func.path.len() == 1
+                        && matches!(call.func.path[0].name.as_str(), "eq" | "dimension") =>
+                    {
+                        Some(call.span)
+                    }
+                    _ => None,
+                })
+                .filter(|stmt| stmt.start() <= span.span.start() && span.span.end() <= stmt.end())
+                .min_by_key(|stmt| stmt.end() - stmt.start())
+            else {
+                return false;
+            };
+            // Only comment out statements that do not share a line with other code.
+            let text = ast.text.as_bytes();
+            let line_start = ast.text[..stmt.start()].rfind('\n').map_or(0, |i| i + 1);
+            let mut end = stmt.end();
+            if text.get(end) == Some(&b';') {
+                end += 1;
+            }
+            let line_end = ast.text[end..].find('\n').map_or(text.len(), |i| end + i);
+            if !ast.text[line_start..stmt.start()].trim().is_empty()
+                || !ast.text[end..line_end].trim().is_empty()
+            {
+                return false;
+            }
+            let indent = stmt.start() - line_start;
+            std::iter::once(line_start)
+                .chain(
+                    ast.text[line_start..line_end]
+                        .match_indices('\n')
+                        .map(|(i, _)| line_start + i + 1),
+                )
+                .map(|start| {
+                    // Keep the marker aligned with the statement's indentation.
+                    let start = start
+                        + ast.text[start..]
+                            .bytes()
+                            .take(indent)
+                            .take_while(|c| matches!(c, b' ' | b'\t'))
+                            .count();
+                    ValueEdit {
+                        span: Span {
+                            path: span.path.clone(),
+                            span: cfgrammar::Span::new(start, start),
+                        },
+                        value: RELAXED_MARKER.to_string(),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        self.apply_value_edits("relax_constraint", edits).await
+    }
 
-        for path in paths {
-            self.editor_client
-                .send_request::<ForceSave>(path)
-                .await
-                .unwrap();
+    async fn restore_constraints(self, _: tarpc::context::Context) -> usize {
+        let edits = {
+            let state_mut = self.state_mut.lock().await;
+            state_mut
+                .ast
+                .values()
+                .flat_map(|ast| {
+                    ast.text
+                        .match_indices(RELAXED_MARKER)
+                        .map(|(i, _)| ValueEdit {
+                            span: Span {
+                                path: ast.path.clone(),
+                                span: cfgrammar::Span::new(i, i + RELAXED_MARKER.len()),
+                            },
+                            value: String::new(),
+                        })
+                })
+                .collect::<Vec<_>>()
+        };
+        let count = edits.len();
+        if self.apply_value_edits("restore_constraints", edits).await {
+            count
+        } else {
+            0
         }
     }
 
@@ -505,22 +795,6 @@ impl LangServer for State {
     ) {
         let state_mut = self.state_mut.lock().await;
 
-        if state_mut.ast.values().any(|ast| {
-            state_mut
-                .editor_files
-                .get(&Uri::from_file_path(&ast.path).unwrap())
-                .map(|file| file.contents() != ast.text)
-                .unwrap_or_default()
-        }) {
-            self.editor_client
-                .show_message(
-                    MessageType::ERROR,
-                    "Editor buffer state is inconsistent with GUI state.",
-                )
-                .await;
-            return;
-        }
-
         let url = Uri::from_file_path(&scope_span.path).unwrap();
 
         if let Some(ast) = state_mut
@@ -580,20 +854,192 @@ impl LangServer for State {
                 .await
                 .unwrap();
 
-            self.editor_client
-                .apply_edit(WorkspaceEdit {
-                    changes: Some(HashMap::from_iter([(url, vec![edit])])),
-                    document_changes: None,
-                    change_annotations: None,
-                })
-                .await
-                .unwrap();
+            drop(state_mut);
+            self.apply_edits("add_eq_constraint", HashMap::from_iter([(url, vec![edit])]))
+                .await;
+        }
+    }
 
-            self.editor_client
-                .send_request::<ForceSave>(scope_span.path.clone())
-                .await
-                .unwrap();
+    async fn place_instance(
+        self,
+        _: tarpc::context::Context,
+        scope_span: Span,
+        var_name: String,
+        cell: String,
+        x: f64,
+        y: f64,
+    ) -> Option<Span> {
+        let value = format!(
+            "inst({cell}(), xi = {}, yi = {})",
+            format_float(x),
+            format_float(y)
+        );
+        self.insert_binding("place_instance", &scope_span, &var_name, &value)
+            .await
+    }
+
+    async fn list_cells(self, _: tarpc::context::Context) -> Vec<CellInfo> {
+        let (ast, lyp, config) = {
+            let state_mut = self.state_mut.lock().await;
+            let Some(root_dir) = &state_mut.root_dir else {
+                return Vec::new();
+            };
+            (
+                state_mut.ast.clone(),
+                state_mut.lyp_path(root_dir),
+                state_mut.config.clone().unwrap_or_default(),
+            )
+        };
+        tokio::task::spawn_blocking(move || {
+            let cache_dir = default_argon_home().map(|home| home.join("thumbnails"));
+            library::list_cells(
+                &ast,
+                &lyp,
+                &config.targets,
+                &config.solver,
+                cache_dir.as_deref(),
+            )
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn resolve_edit_conflict(self, _: tarpc::context::Context, reapply: bool) -> bool {
+        let Some(pending) = self.state_mut.lock().await.pending_edit.take() else {
+            return false;
+        };
+        if !reapply {
+            return false;
         }
+        let rebased = {
+            let state_mut = self.state_mut.lock().await;
+            pending
+                .changes
+                .iter()
+                .map(|(uri, edits)| {
+                    let base = pending.base.get(uri)?;
+                    let current = state_mut
+                        .editor_files
+                        .get(uri)
+                        .map_or_else(|| base.clone(), |doc| ArcStr::from(doc.contents()));
+                    let edits = rebase_edits(base, &current, edits)?;
+                    Some(((uri.clone(), edits), (uri.clone(), current)))
+                })
+                .collect::<Option<(HashMap<_, _>, HashMap<_, _>)>>()
+        };
+        let Some((changes, base)) = rebased else {
+            self.editor_client
+                .show_message(
+                    MessageType::ERROR,
+                    format!(
+                        "Unable to reapply `{}`: the edited text was changed in the editor.",
+                        pending.action
+                    ),
+                )
+                .await;
+            return false;
+        };
+        self.apply_edits_against(&pending.action, changes, base)
+            .await
+    }
+
+    async fn load_underlay(
+        self,
+        _: tarpc::context::Context,
+        path: PathBuf,
+    ) -> Result<Vec<ImportedShape>, String> {
+        let (path, lyp) = {
+            let state_mut = self.state_mut.lock().await;
+            let Some(root_dir) = &state_mut.root_dir else {
+                return Err("no workspace is open".to_string());
+            };
+            (root_dir.join(path), state_mut.lyp_path(root_dir))
+        };
+        tokio::task::spawn_blocking(move || {
+            let map = GdsMap::from_lyp(&lyp)?;
+            compiler::gds::import_gds(&path, &map)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("{e:#}"))
+    }
+
+    async fn sweep_cell(
+        self,
+        _: tarpc::context::Context,
+        param: usize,
+        from: f64,
+        to: f64,
+        steps: usize,
+    ) -> Result<Vec<SweepFrame>, String> {
+        let (ast, cell, lyp, config, mut cache) = {
+            let mut state_mut = self.state_mut.lock().await;
+            let Some(root_dir) = state_mut.root_dir.clone() else {
+                return Err("no workspace is open".to_string());
+            };
+            let Some(cell) = state_mut.cell.clone() else {
+                return Err("no cell is open".to_string());
+            };
+            let cell = state_mut.overridden_cell(&cell)?;
+            let lyp = state_mut.lyp_path(&root_dir);
+            let config = state_mut.config.clone().unwrap_or_default();
+            let ast = state_mut.ast.clone();
+            // Take the cache so that other requests are not blocked while the
+            // sweep compiles.
+            let mut cache = std::mem::take(&mut state_mut.sweep_cache);
+            cache.invalidate(&ast);
+            (ast, cell, lyp, config, cache)
+        };
+        let (cache, frames) = tokio::task::spawn_blocking(move || {
+            let values = sweep_values(from, to, steps);
+            let frames = compile_sweep(&ast, &cell, param, &values, &lyp, &config, &mut cache);
+            (cache, frames)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        self.state_mut.lock().await.sweep_cache = cache;
+        frames
+    }
+
+    async fn set_param_override(
+        self,
+        _: tarpc::context::Context,
+        param: String,
+        value: String,
+    ) -> Result<(), String> {
+        self.state_mut
+            .lock()
+            .await
+            .set_param_override(&param, &value)?;
+        tokio::spawn(async move {
+            let mut state_mut = self.state_mut.lock().await;
+            state_mut.compile(&self.editor_client, true).await;
+        });
+        Ok(())
+    }
+
+    async fn provenance(
+        self,
+        _: tarpc::context::Context,
+        span: Span,
+    ) -> Result<Vec<Provenance>, String> {
+        let state_mut = self.state_mut.lock().await;
+        let Some(
+            CompileOutput::Valid(data)
+            | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+                output: Some(data), ..
+            }),
+        ) = &state_mut.compile_output
+        else {
+            return Err("the open cell did not compile".to_string());
+        };
+        ["x0", "y0", "x1", "y1"]
+            .into_iter()
+            .map(|coord| {
+                data.provenance(&state_mut.ast, &span, coord)
+                    .map_err(|e| format!("{e:#}"))
+            })
+            .collect()
     }
 
     async fn open_cell(self, _: tarpc::context::Context, cell: String) {
@@ -622,3 +1068,447 @@ impl LangServer for State {
         }
     }
 }
+
+impl State {
+    /// Opens `uri` in the editor with `selection` selected, noting that GUI
+    /// edits to the file are rejected if it is read-only.
+    async fn show_source(
+        &self,
+        uri: Uri,
+        selection: Option<Range>,
+        take_focus: bool,
+        read_only: bool,
+    ) {
+        if let Err(e) = self
+            .editor_client
+            .show_document(ShowDocumentParams {
+                uri: uri.clone(),
+                external: None,
+                take_focus: Some(take_focus),
+                selection,
+            })
+            .await
+        {
+            self.editor_client
+                .show_message(MessageType::ERROR, format!("{e}"))
+                .await;
+            return;
+        }
+        if read_only && let Some(path) = uri.to_file_path() {
+            self.editor_client
+                .show_message(
+                    MessageType::INFO,
+                    format!(
+                        "{} is outside the workspace and is read-only in the GUI.",
+                        path.display()
+                    ),
+                )
+                .await;
+        }
+    }
+
+    /// Appends `let {var_name} = {value}!;` to the scope at `scope_span`.
+    ///
+    /// Returns the span of `value` after the edit is applied.
+    async fn insert_binding(
+        &self,
+        action: &str,
+        scope_span: &Span,
+        var_name: &str,
+        value: &str,
+    ) -> Option<Span> {
+        let state_mut = self.state_mut.lock().await;
+
+        let url = Uri::from_file_path(&scope_span.path).unwrap();
+
+        let ast = state_mut
+            .ast
+            .values()
+            .find(|ast| ast.path == scope_span.path)?;
+        let scope = ast.span2scope.get(scope_span)?;
+        let doc = Document::new(&ast.text, 0);
+        let (edit, span) = if let Some(tail) = &scope.tail {
+            let start = doc.offset_to_pos(tail.span().start());
+            let prefix = format!("let {var_name} = ");
+            (
+                TextEdit {
+                    range: Range::new(start, start),
+                    new_text: format!(
+                        "{prefix}{value}!;\n{}",
+                        // TODO: handle different types of indentation, or enforce that gui
+                        // reformats file before editing.
+                        std::iter::repeat_n(' ', start.character as usize).collect::<String>()
+                    ),
+                },
+                Span {
+                    path: scope_span.path.clone(),
+                    span: cfgrammar::Span::new(
+                        tail.span().start() + prefix.len(),
+                        tail.span().start() + prefix.len() + value.len(),
+                    ),
+                },
+            )
+        } else {
+            let start = doc.offset_to_pos(scope.span.start());
+            let stop = doc.offset_to_pos(scope.span.end());
+            let line = doc.substr(Position::new(stop.line, 0)..stop);
+            let trimmed = line.trim_start();
+            let whitespace = &line[..line.len() - trimmed.len()];
+            let insert_loc = doc.offset_to_pos(scope.span.end() - 1);
+            let prefix = format!(
+                "{}let {var_name} = ",
+                if start.line != stop.line {
+                    "    "
+                } else {
+                    "\n"
+                }
+            );
+            (
+                TextEdit {
+                    range: Range::new(insert_loc, insert_loc),
+                    new_text: format!("{prefix}{value}!;\n{whitespace}",),
+                },
+                Span {
+                    path: scope_span.path.clone(),
+                    span: cfgrammar::Span::new(
+                        scope.span.end() - 1 + prefix.len(),
+                        scope.span.end() - 1 + prefix.len() + value.len(),
+                    ),
+                },
+            )
+        };
+
+        self.editor_client
+            .show_document(ShowDocumentParams {
+                uri: url.clone(),
+                external: None,
+                take_focus: None,
+                selection: None,
+            })
+            .await
+            .unwrap();
+
+        drop(state_mut);
+        self.apply_edits(action, HashMap::from_iter([(url, vec![edit])]))
+            .await
+            .then_some(span)
+    }
+
+    /// Applies `edits` as a single workspace edit and saves the affected files.
+    ///
+    /// Returns `false` if nothing was applied, either because there were no
+    /// edits or because the editor buffers have diverged from the GUI state.
+    async fn apply_value_edits(&self, action: &str, edits: Vec<ValueEdit>) -> bool {
+        if edits.is_empty() {
+            return false;
+        }
+        let state_mut = self.state_mut.lock().await;
+
+        // Build one WorkspaceEdit grouping all rewrites per file. Edits within a
+        // file are sorted by descending start offset so they can be applied
+        // back-to-front without invalidating each other's offsets.
+        let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+        let mut offsets: HashMap<Uri, Vec<usize>> = HashMap::new();
+        for ValueEdit { span, value } in edits {
+            let url = Uri::from_file_path(&span.path).unwrap();
+            if let Some(ast) = state_mut.ast.values().find(|ast| ast.path == span.path) {
+                let doc = Document::new(&ast.text, 0);
+                let start = doc.offset_to_pos(span.span.start());
+                let stop = doc.offset_to_pos(span.span.end());
+                changes.entry(url.clone()).or_default().push(TextEdit {
+                    range: Range::new(start, stop),
+                    new_text: value,
+                });
+                offsets.entry(url).or_default().push(span.span.start());
+            }
+        }
+        if changes.is_empty() {
+            return false;
+        }
+        for (url, edits) in changes.iter_mut() {
+            let starts = &offsets[url];
+            let mut idx: Vec<usize> = (0..edits.len()).collect();
+            idx.sort_by(|&a, &b| starts[b].cmp(&starts[a]));
+            *edits = idx.into_iter().map(|i| edits[i].clone()).collect();
+        }
+
+        drop(state_mut);
+        self.apply_edits(action, changes).await
+    }
+
+    /// Applies `changes`, computed against the last compiled text of each file,
+    /// on behalf of GUI request `action`.
+    ///
+    /// See [`State::apply_edits_against`].
+    async fn apply_edits(&self, action: &str, changes: HashMap<Uri, Vec<TextEdit>>) -> bool {
+        let base = {
+            let state_mut = self.state_mut.lock().await;
+            if let Some(path) = changes
+                .keys()
+                .filter_map(|uri| uri.to_file_path())
+                .find(|path| state_mut.is_read_only(path))
+            {
+                let message = format!(
+                    "GUI edit `{action}` was not applied: {} is read-only.",
+                    path.display()
+                );
+                drop(state_mut);
+                self.editor_client
+                    .show_message(MessageType::WARNING, message)
+                    .await;
+                return false;
+            }
+            changes
+                .keys()
+                .filter_map(|uri| {
+                    let path = uri.to_file_path()?;
+                    let ast = state_mut.ast.values().find(|ast| ast.path == *path)?;
+                    Some((uri.clone(), ast.text.clone()))
+                })
+                .collect()
+        };
+        self.apply_edits_against(action, changes, base).await
+    }
+
+    /// Applies `changes`, computed against the file contents in `base`, as a
+    /// versioned workspace edit, then journals the edit and saves the affected
+    /// files.
+    ///
+    /// If an editor buffer no longer matches `base`, or the editor keeps
+    /// rejecting the edit, the edit is held back and the GUI is asked whether to
+    /// reapply or discard it. Returns whether the edit was applied.
+    async fn apply_edits_against(
+        &self,
+        action: &str,
+        changes: HashMap<Uri, Vec<TextEdit>>,
+        base: HashMap<Uri, ArcStr>,
+    ) -> bool {
+        let pending = journal::begin(action, &changes, &base);
+        for _ in 0..=EDIT_RETRIES {
+            let document_changes = {
+                let state_mut = self.state_mut.lock().await;
+                changes
+                    .iter()
+                    .map(|(uri, edits)| {
+                        let doc = state_mut.editor_files.get(uri);
+                        if let Some(doc) = doc
+                            && base
+                                .get(uri)
+                                .is_none_or(|base| doc.contents() != base.as_str())
+                        {
+                            return None;
+                        }
+                        Some(TextDocumentEdit {
+                            text_document: OptionalVersionedTextDocumentIdentifier {
+                                uri: uri.clone(),
+                                version: doc.map(|doc| doc.version()),
+                            },
+                            edits: edits.iter().cloned().map(OneOf::Left).collect(),
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()
+            };
+            let Some(document_changes) = document_changes else {
+                break;
+            };
+
+            let applied = self
+                .editor_client
+                .apply_edit(WorkspaceEdit {
+                    changes: None,
+                    document_changes: Some(DocumentChanges::Edits(document_changes)),
+                    change_annotations: None,
+                })
+                .await
+                .is_ok_and(|response| response.applied);
+            if applied {
+                journal::record(action, &changes, &base);
+                for uri in changes.keys() {
+                    if let Some(path) = uri.to_file_path() {
+                        self.editor_client
+                            .send_request::<ForceSave>(path.into_owned())
+                            .await
+                            .unwrap();
+                    }
+                }
+                journal::finish(&pending);
+                return true;
+            }
+        }
+        journal::finish(&pending);
+
+        let gui_client = {
+            let mut state_mut = self.state_mut.lock().await;
+            state_mut.pending_edit = Some(PendingEdit {
+                action: action.to_string(),
+                changes,
+                base,
+            });
+            state_mut.gui_client.clone()
+        };
+        self.editor_client
+            .show_message(
+                MessageType::WARNING,
+                format!("GUI edit `{action}` conflicts with changes made in the editor."),
+            )
+            .await;
+        if let Some(gui_client) = gui_client {
+            let action = action.to_string();
+            crate::session::record_with(crate::session::Channel::GuiOut, || {
+                GuiRequest::EditConflict {
+                    action: action.clone(),
+                }
+            });
+            tokio::spawn(async move {
+                let _ = gui_client
+                    .edit_conflict(tarpc::context::current(), action)
+                    .await;
+            });
+        }
+        false
+    }
+}
+
+/// Returns an expression for the value of the dimension `params` that reuses
+/// an existing relation between its edges: the value of a dimension between
+/// the same edges in `scope`, or the name of a float constant in `ast` equal to
+/// the value.
+fn symbolic_dimension_value<T: AstMetadata>(
+    ast: &AnnotatedAst<T>,
+    scope: &Scope<Substr, T>,
+    params: &DimensionParams,
+) -> Option<String> {
+    let text = |expr: &Expr<Substr, T>| {
+        let span = expr.span();
+        ast.text[span.start()..span.end()]
+            .split_whitespace()
+            .collect::<String>()
+    };
+    let (p, n) = (
+        params.p.split_whitespace().collect::<String>(),
+        params.n.split_whitespace().collect::<String>(),
+    );
+    let existing = scope.stmts.iter().find_map(|stmt| match stmt {
+        Statement::Expr {
+            value: Expr::Call(call),
+            ..
+        } if call.func.path.len() == 1
+            && call.func.path[0].name.as_str() == "dimension"
+            && call.args.posargs.len() > 2
+            && text(&call.args.posargs[0]) == p
+            && text(&call.args.posargs[1]) == n =>
+        {
+            let span = call.args.posargs[2].span();
+            Some(ast.text[span.start()..span.end()].to_string())
+        }
+        _ => None,
+    });
+    if existing.is_some() {
+        return existing;
+    }
+    let value = params.value.trim().parse::<f64>().ok()?;
+    let (consts, _) = eval_consts(ast);
+    consts
+        .into_iter()
+        .find_map(|(name, constant)| match constant.value {
+            ConstValue::Float(x) if (x - value).abs() <= 1e-9 * value.abs().max(1.) => Some(name),
+            _ => None,
+        })
+}
+
+/// Moves `edits`, given as ranges in `base`, onto `current`.
+///
+/// Edits are shifted past the region where `current` differs from `base`.
+/// Returns `None` if any edit overlaps that region.
+fn rebase_edits(base: &str, current: &str, edits: &[TextEdit]) -> Option<Vec<TextEdit>> {
+    let mut prefix = base
+        .bytes()
+        .zip(current.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !base.is_char_boundary(prefix) || !current.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = base[prefix..]
+        .bytes()
+        .rev()
+        .zip(current[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !base.is_char_boundary(base.len() - suffix)
+        || !current.is_char_boundary(current.len() - suffix)
+    {
+        suffix -= 1;
+    }
+    let changed_end = base.len() - suffix;
+
+    let base_doc = Document::new(base, 0);
+    let current_doc = Document::new(current, 0);
+    edits
+        .iter()
+        .map(|edit| {
+            let start = base_doc.pos_to_offset(edit.range.start)?;
+            let end = base_doc.pos_to_offset(edit.range.end)?;
+            let (start, end) = if end <= prefix {
+                (start, end)
+            } else if start >= changed_end {
+                (
+                    start + current.len() - base.len(),
+                    end + current.len() - base.len(),
+                )
+            } else {
+                return None;
+            };
+            Some(TextEdit {
+                range: Range::new(
+                    current_doc.offset_to_pos(start),
+                    current_doc.offset_to_pos(end),
+                ),
+                new_text: edit.new_text.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the source text of `expr`, spelled `text` in the source, offset by
+/// `delta`.
+///
+/// Numeric literals are folded into a new literal; any other expression has
+/// the offset appended (e.g. `rect0.x1` becomes `rect0.x1 + 20.`).
+fn translate_expr<T: AstMetadata>(expr: &Expr<Substr, T>, text: &str, delta: f64) -> String {
+    let literal = match expr {
+        Expr::FloatLiteral(lit) => Some(lit.value),
+        Expr::IntLiteral(lit) => Some(lit.value as f64),
+        Expr::UnaryOp(op) if op.op == UnaryOp::Neg => match &op.operand {
+            Expr::FloatLiteral(lit) => Some(-lit.value),
+            Expr::IntLiteral(lit) => Some(-lit.value as f64),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(value) = literal {
+        return format_float(value + delta);
+    }
+    let text = match expr {
+        Expr::IdentPath(_)
+        | Expr::FieldAccess(_)
+        | Expr::Call(_)
+        | Expr::Index(_)
+        | Expr::BinOp(_) => text.to_string(),
+        _ => format!("({text})"),
+    };
+    if delta < 0. {
+        format!("{text} - {}", format_float(-delta))
+    } else {
+        format!("{text} + {}", format_float(delta))
+    }
+}
+
+/// Formats `value` as a float literal, dropping the floating-point noise that
+/// accumulates from repeated offsets (e.g. `0.30000000000000004` becomes `0.3`).
+fn format_float(value: f64) -> String {
+    // `+ 0.` collapses a possible `-0.` to `0.`.
+    let value = (value * 1e6).round() / 1e6 + 0.;
+    let s = format!("{value}");
+    if s.contains('.') { s } else { format!("{s}.") }
+}
@@ -0,0 +1,413 @@
+//! Recording and replay of language server sessions.
+//!
+//! When [`RECORD_ENV`] is set, the server appends every LSP message it reads or
+//! writes and every RPC exchanged with the GUI to a JSON lines file. Replaying
+//! the file with `lang-server --replay` feeds the recorded editor and GUI
+//! messages to a fresh server in their original order and records the new
+//! session the same way, so that the two recordings can be diffed.
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::{Ipv4Addr, SocketAddr},
+    path::Path,
+    pin::Pin,
+    sync::{Mutex, OnceLock},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use compiler::compile::CompileOutput;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tarpc::{ServerError, context, server::Channel as _};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    sync::mpsc,
+};
+use tower_lsp_server::Server;
+use tracing::warn;
+
+use crate::rpc::{Gui, LangServer, LangServerRequest, rpc_codec};
+
+/// Environment variable holding the file to record the session to.
+pub const RECORD_ENV: &str = "ARGON_RECORD_SESSION";
+
+/// Time to wait for the server to send a message a replayed message depends on.
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channel {
+    /// LSP messages from the editor.
+    LspIn,
+    /// LSP messages to the editor.
+    LspOut,
+    /// Requests from the GUI.
+    GuiIn,
+    /// Requests to the GUI.
+    GuiOut,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// Milliseconds since recording started.
+    pub elapsed_ms: u64,
+    pub channel: Channel,
+    pub message: Value,
+}
+
+struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+static RECORDER: OnceLock<Mutex<Recorder>> = OnceLock::new();
+
+/// Records the rest of the session to `path`.
+pub fn start_recording(path: &Path) -> io::Result<()> {
+    let recorder = Recorder {
+        file: BufWriter::new(File::create(path)?),
+        start: Instant::now(),
+    };
+    RECORDER
+        .set(Mutex::new(recorder))
+        .map_err(|_| io::Error::other("session is already being recorded"))
+}
+
+pub fn is_recording() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// Appends `message` to the recording, if any.
+pub fn record(channel: Channel, message: &impl Serialize) {
+    let Some(recorder) = RECORDER.get() else {
+        return;
+    };
+    let mut recorder = recorder.lock().unwrap_or_else(|e| e.into_inner());
+    let recorder = &mut *recorder;
+    let event = SessionEvent {
+        elapsed_ms: recorder.start.elapsed().as_millis() as u64,
+        channel,
+        message: serde_json::to_value(message).unwrap_or(Value::Null),
+    };
+    let res = serde_json::to_writer(&mut recorder.file, &event)
+        .map_err(io::Error::from)
+        .and_then(|_| recorder.file.write_all(b"\n"))
+        .and_then(|_| recorder.file.flush());
+    if let Err(e) = res {
+        warn!("failed to record session event: {e}");
+    }
+}
+
+/// Like [`record`], but only builds the message if the session is being recorded.
+pub fn record_with<T: Serialize>(channel: Channel, message: impl FnOnce() -> T) {
+    if is_recording() {
+        record(channel, &message());
+    }
+}
+
+/// Request hook recording requests from the GUI.
+pub fn record_gui_request(
+    _: &mut context::Context,
+    request: &LangServerRequest,
+) -> future::Ready<Result<(), ServerError>> {
+    record(Channel::GuiIn, request);
+    future::ready(Ok(()))
+}
+
+/// Reads the events of a recorded session.
+pub fn read_session(path: &Path) -> io::Result<Vec<SessionEvent>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// Splits a stream of `Content-Length`-framed LSP messages into messages.
+#[derive(Debug, Default)]
+struct FrameDecoder {
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    /// Appends `bytes` to the stream and returns the messages it completes.
+    fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        self.buf.extend_from_slice(bytes);
+        let mut messages = Vec::new();
+        while let Some(header_len) = self.buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let start = header_len + 4;
+            let len = String::from_utf8_lossy(&self.buf[..header_len])
+                .lines()
+                .find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                });
+            let Some(len) = len else {
+                // Skip malformed headers rather than stalling the stream.
+                self.buf.drain(..start);
+                continue;
+            };
+            if self.buf.len() < start + len {
+                break;
+            }
+            let body = &self.buf[start..start + len];
+            messages.push(
+                serde_json::from_slice(body)
+                    .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned())),
+            );
+            self.buf.drain(..start + len);
+        }
+        messages
+    }
+}
+
+/// Frames `message` for the LSP base protocol.
+fn encode_frame(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{body}", body.len()).into_bytes()
+}
+
+/// A reader or writer that records the LSP messages passing through it.
+pub struct Tap<T> {
+    inner: T,
+    channel: Channel,
+    decoder: FrameDecoder,
+}
+
+impl<T> Tap<T> {
+    pub fn new(inner: T, channel: Channel) -> Self {
+        Self {
+            inner,
+            channel,
+            decoder: FrameDecoder::default(),
+        }
+    }
+
+    fn record_bytes(&mut self, bytes: &[u8]) {
+        if is_recording() {
+            for message in self.decoder.push(bytes) {
+                record(self.channel, &message);
+            }
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Tap<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = res {
+            self.record_bytes(&buf.filled()[filled..]);
+        }
+        res
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Tap<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            self.record_bytes(&buf[..n]);
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Stands in for the GUI during replay. Requests to the GUI are recorded by
+/// the server before they are sent, so the stub only needs to accept them.
+#[derive(Clone)]
+struct StubGui;
+
+impl Gui for StubGui {
+    async fn open_cell(
+        self,
+        _: context::Context,
+        _cell: CompileOutput,
+        _update: bool,
+        _read_only: bool,
+    ) {
+    }
+    async fn set(self, _: context::Context, _key: String, _value: String) {}
+    async fn activate(self, _: context::Context) {}
+    async fn edit_conflict(self, _: context::Context, _action: String) {}
+}
+
+/// Serves [`StubGui`] on a free local port.
+async fn spawn_stub_gui() -> io::Result<SocketAddr> {
+    let mut listener =
+        tarpc::serde_transport::tcp::listen((Ipv4Addr::LOCALHOST, 0), rpc_codec).await?;
+    listener.config_mut().max_frame_length(usize::MAX);
+    let addr = listener.local_addr();
+    tokio::spawn(async move {
+        listener
+            .filter_map(|r| future::ready(r.ok()))
+            .map(tarpc::server::BaseChannel::with_defaults)
+            .for_each(|channel| async move {
+                tokio::spawn(
+                    channel
+                        .execute(StubGui.serve())
+                        .for_each(|response| async move {
+                            tokio::spawn(response);
+                        }),
+                );
+            })
+            .await;
+    });
+    Ok(addr)
+}
+
+/// Messages the replayed server has sent to the editor, indexed by what
+/// replayed messages wait on.
+struct ServerMessages {
+    rx: mpsc::UnboundedReceiver<Value>,
+    /// IDs of responses to editor requests.
+    responses: HashSet<Value>,
+    /// IDs of requests to the editor.
+    requests: HashSet<Value>,
+}
+
+impl ServerMessages {
+    /// Waits until the server has sent a response (or, if `request` is set, a
+    /// request) with ID `id`.
+    async fn wait_for(&mut self, id: &Value, request: bool) -> Result<(), String> {
+        let deadline = tokio::time::Instant::now() + REPLAY_TIMEOUT;
+        loop {
+            let seen = if request {
+                &self.requests
+            } else {
+                &self.responses
+            };
+            if seen.contains(id) {
+                return Ok(());
+            }
+            let message = tokio::time::timeout_at(deadline, self.rx.recv())
+                .await
+                .map_err(|_| {
+                    format!(
+                        "replay diverged: server sent no {} with ID {id}",
+                        if request { "request" } else { "response" }
+                    )
+                })?
+                .ok_or_else(|| "server exited before the session ended".to_string())?;
+            if let Some(id) = message.get("id") {
+                if message.get("method").is_some() {
+                    self.requests.insert(id.clone());
+                } else {
+                    self.responses.insert(id.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Replays the session recorded in `session` against a fresh server, recording
+/// the new session to `out` if provided.
+///
+/// Editor messages are sent in their recorded order. Each editor request waits
+/// for its response, and each response to a server request waits for the
+/// request, so that the replay does not depend on the timing of the original
+/// session. The server compiles the workspace on disk, so it should contain
+/// the sources the session started with.
+pub async fn replay(session: &Path, out: Option<&Path>) -> Result<(), String> {
+    let events = read_session(session).map_err(|e| format!("{}: {e}", session.display()))?;
+    if let Some(out) = out {
+        start_recording(out).map_err(|e| format!("{}: {e}", out.display()))?;
+    }
+    let gui_addr = spawn_stub_gui().await.map_err(|e| e.to_string())?;
+
+    let (service, socket, state) = crate::build_service(gui_addr);
+    let (client_io, server_io) = tokio::io::duplex(1 << 20);
+    let (server_read, server_write) = tokio::io::split(server_io);
+    tokio::spawn(
+        Server::new(
+            Tap::new(server_read, Channel::LspIn),
+            Tap::new(server_write, Channel::LspOut),
+            socket,
+        )
+        .serve(service),
+    );
+
+    let (mut client_read, mut client_write) = tokio::io::split(client_io);
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        use tokio::io::AsyncReadExt;
+        let mut decoder = FrameDecoder::default();
+        let mut buf = vec![0; 1 << 16];
+        while let Ok(n) = client_read.read(&mut buf).await
+            && n > 0
+        {
+            for message in decoder.push(&buf[..n]) {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    let mut server = ServerMessages {
+        rx,
+        responses: HashSet::new(),
+        requests: HashSet::new(),
+    };
+
+    for event in events {
+        match event.channel {
+            Channel::LspIn => {
+                let message = event.message;
+                // Replays run without a real GUI.
+                if message.get("method").and_then(Value::as_str) == Some("custom/startGui") {
+                    continue;
+                }
+                let id = message.get("id").cloned();
+                let is_request = message.get("method").is_some();
+                if let Some(id) = &id
+                    && !is_request
+                {
+                    server.wait_for(id, true).await?;
+                }
+                client_write
+                    .write_all(&encode_frame(&message))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if let Some(id) = &id
+                    && is_request
+                {
+                    server.wait_for(id, false).await?;
+                }
+            }
+            Channel::GuiIn => {
+                let mut request: LangServerRequest = serde_json::from_value(event.message)
+                    .map_err(|e| format!("invalid GUI request: {e}"))?;
+                if let LangServerRequest::Register { addr, .. } = &mut request {
+                    *addr = gui_addr;
+                }
+                record(Channel::GuiIn, &request);
+                tarpc::server::Serve::serve(state.clone().serve(), context::current(), request)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Channel::LspOut | Channel::GuiOut => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+//! Solved values of the open cell at positions in its source.
+//!
+//! Values come from the last compile, so they are only shown while the
+//! document matches the text it was compiled from. Cells may be compiled
+//! several times with different parameters, in which case the values of the
+//! top cell, or else of the first compiled cell, are shown. Sources that create
+//! several objects in one cell, such as the body of a loop, have no single
+//! value and are skipped.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use compiler::{
+    ast::Span,
+    compile::{
+        Arrayed, CellId, CompileOutput, CompiledCell, CompiledData, Dimension,
+        ExecErrorCompileOutput, Rect, SolvedValue,
+    },
+    graph::{CoordNames, SolvedCoord},
+    solver::{ConstraintId, LinearExpr},
+};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use tower_lsp_server::ls_types::{InlayHint, InlayHintLabel, Position, Range, Uri};
+
+use crate::{StateMut, document::Document, ident_range};
+
+/// A rect, dimension, or constraint whose solved value is shown in the editor.
+pub(crate) enum Solved<'a> {
+    Rect(CellId, &'a Rect<(f64, LinearExpr)>),
+    Dimension(&'a Dimension<f64>),
+    Constraint(CellId, ConstraintId),
+}
+
+impl StateMut {
+    /// The compiled data of the open cell as of the last compile, if any.
+    pub(crate) fn compiled_data(&self) -> Option<&CompiledData> {
+        match self.compile_output.as_ref()? {
+            CompileOutput::Valid(data)
+            | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+                output: Some(data), ..
+            }) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// The compiled data of the open cell, the path of the document at `uri`,
+    /// and the document, if it has not changed since the last compile.
+    fn compiled_doc(&self, uri: &Uri) -> Option<(&CompiledData, PathBuf, &Document)> {
+        let data = self.compiled_data()?;
+        let path = PathBuf::from(uri.to_file_path()?);
+        let doc = self.editor_files.get(uri)?;
+        let ast = self.ast.values().find(|ast| ast.path == path)?;
+        (doc.contents() == ast.text).then_some((data, path, doc))
+    }
+
+    /// Describes, in Markdown, the solved coordinates of the rect named at
+    /// `pos` in the document at `uri`, or the solved value of the innermost
+    /// rect, dimension, or constraint created there.
+    ///
+    /// Only the named coordinate is described for e.g. `r.x0`.
+    pub(crate) fn solved_at(&self, uri: &Uri, pos: Position) -> Option<String> {
+        let (data, path, doc) = self.compiled_doc(uri)?;
+        let text = doc.contents();
+        let offset = doc.pos_to_offset(pos)?;
+        if let Some((start, end)) = ident_range(text, offset) {
+            let field = text[..start]
+                .strip_suffix('.')
+                .and_then(|before| ident_range(before, before.len()))
+                .filter(|_| ["x0", "y0", "x1", "y1"].contains(&&text[start..end]));
+            let (name, coord) = match field {
+                Some((base_start, base_end)) => {
+                    (&text[base_start..base_end], Some(&text[start..end]))
+                }
+                None => (&text[start..end], None),
+            };
+            if let Some((cell, rect)) = rect_named(data, &path, offset, name) {
+                let (name, coords) = data.coord_names(cell).rect(rect);
+                let lines = coords
+                    .iter()
+                    .filter(|(c, _)| coord.is_none_or(|coord| coord == *c))
+                    .map(|(c, value)| format!("{name}.{c} = {value}"));
+                return Some(code_block(lines));
+            }
+        }
+        let (_, solved) = solved_in(data, &path)
+            .into_iter()
+            .filter(|(span, _)| span.span.start() <= offset && offset <= span.span.end())
+            .min_by_key(|(span, _)| span.span.end() - span.span.start())?;
+        let lines = match solved {
+            Solved::Rect(cell, rect) => {
+                let (name, coords) = data.coord_names(cell).rect(rect);
+                coords
+                    .iter()
+                    .map(|(c, value)| format!("{name}.{c} = {value}"))
+                    .collect_vec()
+            }
+            Solved::Dimension(dim) => {
+                let dir = if dim.horiz { "horizontal" } else { "vertical" };
+                vec![format!("{dir} dimension = {}", dim.value)]
+            }
+            Solved::Constraint(cell, id) => data
+                .coord_names(cell)
+                .constraint(id)
+                .iter()
+                .map(|(coord, value)| format!("{coord} = {value}"))
+                .collect_vec(),
+        };
+        (!lines.is_empty()).then(|| code_block(lines))
+    }
+
+    /// Inlay hints of the solved coordinates of the rects, the values of the
+    /// dimensions, and the coordinates related by the `eq` constraints in
+    /// `range` of the document at `uri`.
+    pub(crate) fn inlay_hints(&self, uri: &Uri, range: Range) -> Vec<InlayHint> {
+        let Some((data, path, doc)) = self.compiled_doc(uri) else {
+            return Vec::new();
+        };
+        let text = doc.contents();
+        let start = doc.pos_to_offset(range.start).unwrap_or(0);
+        let end = doc.pos_to_offset(range.end).unwrap_or(text.len());
+        let mut names: HashMap<CellId, CoordNames<'_>> = HashMap::new();
+        let mut hints = Vec::new();
+        for (span, solved) in solved_in(data, &path) {
+            if span.span.end() < start || span.span.start() > end {
+                continue;
+            }
+            let label = match solved {
+                Solved::Rect(cell, rect) => {
+                    let (_, coords) = names
+                        .entry(cell)
+                        .or_insert_with(|| data.coord_names(cell))
+                        .rect(rect);
+                    coords
+                        .iter()
+                        .map(|(coord, value)| format!("{coord}={}", hint_value(value)))
+                        .join(" ")
+                }
+                Solved::Dimension(dim) => format!("= {}", dim.value),
+                Solved::Constraint(cell, id) => {
+                    if !text[span.span.start()..].starts_with("eq(") {
+                        continue;
+                    }
+                    let coords = names
+                        .entry(cell)
+                        .or_insert_with(|| data.coord_names(cell))
+                        .constraint(id);
+                    if coords.is_empty() {
+                        continue;
+                    }
+                    coords
+                        .iter()
+                        .map(|(coord, value)| format!("{coord}={}", hint_value(value)))
+                        .join(" ")
+                }
+            };
+            hints.push(InlayHint {
+                position: doc.offset_to_pos(span.span.end()),
+                label: InlayHintLabel::String(label),
+                kind: None,
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            });
+        }
+        hints
+    }
+}
+
+fn code_block(lines: impl IntoIterator<Item = String>) -> String {
+    format!("```argon\n{}\n```", lines.into_iter().join("\n"))
+}
+
+/// Formats `value` compactly, with `?` for unsolved coordinates.
+fn hint_value(value: &SolvedCoord) -> String {
+    match value {
+        SolvedCoord::Solved(value) => value.to_string(),
+        SolvedCoord::Unsolved(_) => "?".to_string(),
+    }
+}
+
+/// Compiled cells, starting with the top cell.
+fn cells(data: &CompiledData) -> impl Iterator<Item = (CellId, &CompiledCell)> {
+    std::iter::once(data.top)
+        .chain(data.cells.keys().copied().filter(|id| *id != data.top))
+        .map(|id| (id, &data.cells[&id]))
+}
+
+/// The rects, dimensions, and non-fallback constraints created in the file at
+/// `path`, along with their spans.
+pub(crate) fn solved_in<'a>(data: &'a CompiledData, path: &Path) -> Vec<(&'a Span, Solved<'a>)> {
+    let mut seen = HashSet::new();
+    let mut solved = Vec::new();
+    for (id, cell) in cells(data) {
+        let objects = cell.objects.values().filter_map(|obj| match obj {
+            SolvedValue::Rect(rect) => Some((rect.span.as_ref()?, Solved::Rect(id, rect))),
+            SolvedValue::Dimension(dim) => Some((dim.span.as_ref()?, Solved::Dimension(dim))),
+            _ => None,
+        });
+        let constraints = cell
+            .constraints
+            .iter()
+            .filter(|(_, constraint)| !constraint.fallback)
+            .map(|(cid, constraint)| (&constraint.span, Solved::Constraint(id, *cid)));
+        let mut by_span: IndexMap<_, Vec<_>> = IndexMap::new();
+        for (span, value) in objects.chain(constraints) {
+            if span.path == path {
+                let is_constraint = matches!(value, Solved::Constraint(..));
+                by_span
+                    .entry((span.span, is_constraint))
+                    .or_default()
+                    .push((span, value));
+            }
+        }
+        for (key, mut values) in by_span {
+            if seen.insert(key) && values.len() == 1 {
+                solved.extend(values.pop());
+            }
+        }
+    }
+    solved
+}
+
+/// Returns the rect bound to `name` in the innermost scope containing
+/// `offset` in the file at `path`, or in its enclosing scopes.
+fn rect_named<'a>(
+    data: &'a CompiledData,
+    path: &Path,
+    offset: usize,
+    name: &str,
+) -> Option<(CellId, &'a Rect<(f64, LinearExpr)>)> {
+    cells(data).find_map(|(id, cell)| {
+        let mut scope = cell
+            .scopes
+            .values()
+            .filter(|scope| {
+                scope.span.path == path
+                    && scope.span.span.start() <= offset
+                    && offset <= scope.span.span.end()
+            })
+            .min_by_key(|scope| scope.span.span.end() - scope.span.span.start())?;
+        loop {
+            let binding = scope.bindings.values().rev().find(|(n, _)| n == name);
+            if let Some((_, Arrayed::Elem(obj))) = binding {
+                return cell.objects.get(obj)?.get_rect().map(|rect| (id, rect));
+            }
+            scope = &cell.scopes[&scope.static_parent?.0];
+        }
+    })
+}
@@ -0,0 +1,74 @@
+//! Source files referenced by spans.
+//!
+//! Spans from the compiler and the GUI can point into any file of the
+//! workspace, its dependencies, or the standard library, whether or not the
+//! file is open in the editor. Files outside the workspace root, including the
+//! standard library, are read-only: they can be shown in the editor, but GUI
+//! edits to them are rejected.
+use std::path::Path;
+
+use arcstr::ArcStr;
+use compiler::{
+    compile::{CompileOutput, ExecErrorCompileOutput},
+    parse::{STD_LIB_PATH, WorkspaceParseAst},
+};
+use tower_lsp_server::ls_types::Uri;
+
+use crate::{StateMut, document::Document};
+
+/// A file containing a span, along with the text the span indexes into.
+pub(crate) struct SourceFile {
+    pub(crate) uri: Uri,
+    pub(crate) doc: Document,
+    pub(crate) read_only: bool,
+}
+
+/// Returns the text spans into the file at `path` index into: the text `ast`
+/// was parsed from, or the file on disk if it is not part of `ast`.
+pub(crate) fn source_text(ast: &WorkspaceParseAst, path: &Path) -> Option<ArcStr> {
+    match ast.values().find(|ast| ast.path == path) {
+        Some(ast) => Some(ast.text.clone()),
+        None => std::fs::read_to_string(path).ok().map(ArcStr::from),
+    }
+}
+
+impl StateMut {
+    /// Resolves the file at `path`, using the text described by [`source_text`]
+    /// rather than the editor buffer so that spans from the last compile stay
+    /// valid.
+    pub(crate) fn source_file(&self, path: &Path) -> Option<SourceFile> {
+        Some(SourceFile {
+            uri: Uri::from_file_path(path)?,
+            doc: Document::new(source_text(&self.ast, path)?, 0),
+            read_only: self.is_read_only(path),
+        })
+    }
+
+    /// Whether `path` lies outside the workspace root or in the standard library.
+    pub(crate) fn is_read_only(&self, path: &Path) -> bool {
+        let std_dir = Path::new(STD_LIB_PATH).parent().unwrap();
+        path.starts_with(std_dir)
+            || self
+                .root_dir
+                .as_ref()
+                .is_some_and(|root_dir| !path.starts_with(root_dir))
+    }
+
+    /// Whether the top cell of `output` is defined in a read-only file.
+    pub(crate) fn is_output_read_only(&self, output: &CompileOutput) -> bool {
+        let (CompileOutput::Valid(data)
+        | CompileOutput::ExecErrors(ExecErrorCompileOutput {
+            output: Some(data), ..
+        })) = output
+        else {
+            return false;
+        };
+        let top = &data.cells[&data.top];
+        self.is_read_only(&top.scopes[&top.root].span.path)
+    }
+
+    /// Whether the editor has the file at `uri` open.
+    pub(crate) fn is_open(&self, uri: &Uri) -> bool {
+        self.editor_files.contains_key(uri)
+    }
+}
@@ -41,6 +41,12 @@ local Config = {
     --- Defaults to `nil`, which means argon will not use a local development repo as source.
     ---@type nil | string
     argon_repo_path = nil,
+    inlay_hints = {
+        --- Whether to annotate rects, dimensions, and `eq` constraints with their solved values.
+        --- Defaults to `true`. Neovim only shows inlay hints after `vim.lsp.inlay_hint.enable()`.
+        ---@type boolean
+        enable = true,
+    },
     log = {
         --- Log level following [`RUST_LOG`](https://docs.rs/tracing-subscriber/latest/tracing_subscriber/fmt/index.html#filtering-events-with-environment-variables) syntax.
         --- Defaults to `nil`.
@@ -78,6 +78,14 @@ M.start = function(bufnr)
         name = 'argon',
         cmd = { config.argon_repo_path ..'/target/release/lang-server' },
         cmd_env = cmd_env,
+        init_options = {
+            inlayHints = { enable = config.inlay_hints.enable },
+        },
+        settings = {
+            argon = {
+                inlayHints = { enable = config.inlay_hints.enable },
+            },
+        },
         handlers = {
             ['custom/forceSave'] = function(err, result, ctx)
                 local bufnr = vim.fn.bufnr(result)
@@ -79,6 +79,9 @@ export function activate(context: ExtensionContext) {
         name: path.basename(workspaceRoot),
         index: 0,
     },
+        initializationOptions: { inlayHints: config.argon.inlayHints },
+        // Sends `argon.*` settings to the server when they change.
+        synchronize: { configurationSection: 'argon' },
     };
 
     // Create the language client and start the client.